@@ -15,6 +15,13 @@ fn main() {
         .write_bindings(GlobalGenerator, &mut file)
         .unwrap();
 
+    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+    wayland_scanner::generate_code(
+        "../extra/wayland/fractional-scale-v1.xml",
+        &Path::new(&dest).join("fractional_scale_v1.rs"),
+        wayland_scanner::Side::Client,
+    );
+
     #[cfg(windows)]
     embed_resource::compile("../extra/windows/windows.rc");
 }
@@ -4,21 +4,178 @@ precision mediump float;
 layout(location = 0) out vec4 color;
 uniform sampler2D u_glyph_ref;
 uniform sampler2D u_atlas;
-uniform sampler2D u_color_fg;
+uniform sampler2D u_color_fg; // .a = highlight class
 uniform sampler2D u_color_bg;
+uniform sampler2D u_color_index; // .xy = fg palette index, .zw = bg palette index
+uniform sampler2D u_palette; // row 0 = palette faded from, row 1 = palette faded to
+uniform float u_palette_blend;
 uniform vec4 u_screen_dim; // .xy = padding, .zw = resolution
+uniform vec2 u_screen_rows; // .x = texture row of the first line, .y = lines
+uniform vec3 u_minimap; // .x = left edge, .y = width in pixels, .z = history lines above the screen
+uniform bool u_minimap_pass;
 uniform vec2 u_cell_dim;
 uniform vec4 u_atlas_dim; // .xy = offset, .zw = cell_size
-uniform vec4 u_cursor;
+uniform vec4 u_cursor; // .xy = cell, .z = width in cells, .w = style
 uniform vec3 u_cursor_color;
+uniform float u_cursor_thickness; // line width of beam, underline and hollow block in pixels
+uniform vec4 u_terminal_cursor; // like u_cursor, terminal cursor shown below the vi mode cursor
+uniform vec3 u_terminal_cursor_color;
 uniform bool u_main_pass;
+uniform vec4 u_underline; // .xy = top and thickness, .zw = top of both double underlines
+uniform vec4 u_strikeout; // .xy = top and thickness, .zw = top and thickness of overline
+uniform vec2 u_undercurl; // .x = center, .y = amplitude
+uniform sampler2D u_background_image;
+uniform vec4 u_background_image_params; // .xy = image size (zero if none), .z = opacity, .w = mode
+uniform sampler2D u_underlay; // premultiplied images below the text, in framebuffer pixels
+uniform bool u_has_underlay;
+uniform float u_time; // seconds into the current blink cycle
+uniform float u_blink_interval; // seconds per blink phase, zero when text doesn't blink
+uniform vec4 u_highlight_fg[2]; // per highlight class: .rgb = color, .w = source
+uniform vec4 u_highlight_bg[2];
+
+#define GLYPH_REF_FLAG_COLORED 2
+#define GLYPH_REF_FLAG_STRIKEOUT 32
+#define GLYPH_REF_FLAG_CELL_BACKGROUND 64
+#define GLYPH_REF_FLAG_OVERLINE 128
+
+#define GLYPH_REF_ATTR_BLINK 1
+#define GLYPH_REF_ATTR_HIDDEN 2
+
+#define HIGHLIGHT_NONE 0
+#define HIGHLIGHT_SELECTION 1
+#define HIGHLIGHT_SEARCH_MATCH 2
+
+#define HIGHLIGHT_CELL_FOREGROUND 0
+#define HIGHLIGHT_CELL_BACKGROUND 1
+
+#define NAMED_COLOR_FOREGROUND 256
+#define NAMED_COLOR_BACKGROUND 257
+
+#define BACKGROUND_IMAGE_STRETCH 0
+#define BACKGROUND_IMAGE_TILE 1
+#define BACKGROUND_IMAGE_CENTER 2
+
+#define CURSOR_BLOCK 1
+#define CURSOR_BEAM 2
+#define CURSOR_UNDERLINE 3
+#define CURSOR_HOLLOW_BLOCK 4
+
+#define UNDERLINE 1
+#define DOUBLE_UNDERLINE 2
+#define UNDERCURL 3
+#define DOTTED_UNDERLINE 4
+#define DASHED_UNDERLINE 5
+
+// Palette entry `entry`, cross-faded between the old and the new palette.
+vec3 paletteEntry(int entry) {
+	vec3 from = texelFetch(u_palette, ivec2(entry, 0), 0).rgb;
+	vec3 to = texelFetch(u_palette, ivec2(entry, 1), 0).rgb;
+	return mix(from, to, u_palette_blend);
+}
+
+// Resolve indexed colors through the palettes, so palette changes can be cross-faded.
+vec3 paletteColor(vec2 index, vec3 rgb) {
+	index = floor(index * 255. + .5);
+
+	// Zero high byte marks colors which are not from the palette.
+	if (index.y < .5) {
+		return rgb;
+	}
+
+	return paletteEntry(int(index.x + (index.y - 1.) * 256.));
+}
+
+// Highlight color taken from the cell's foreground, background or a fixed color.
+vec3 highlightColor(vec4 highlight, vec3 fg, vec3 bg) {
+	int source = int(highlight.w + .5);
+	if (source == HIGHLIGHT_CELL_FOREGROUND) {
+		return fg;
+	} else if (source == HIGHLIGHT_CELL_BACKGROUND) {
+		return bg;
+	}
+	return highlight.rgb;
+}
+
+// Foreground and background of the cell at `tuv`, with its highlight class applied.
+//
+// This mirrors `RenderableCell::resolve_highlight`, which is used for glyphs drawn as quads.
+//
+// Returns true if the highlight replaced the cell's background.
+bool cellColors(vec2 tuv, float attrs, out vec3 fg, out vec4 bg) {
+	vec4 fg_tex = texture(u_color_fg, tuv);
+	vec4 index = texture(u_color_index, tuv);
+	fg = paletteColor(index.xy, fg_tex.rgb);
+	bg = texture(u_color_bg, tuv);
+	bg.rgb = paletteColor(index.zw, bg.rgb);
+
+	int highlight = int(fg_tex.a * 255. + .5);
+	if (highlight == HIGHLIGHT_NONE) {
+		return false;
+	}
+
+	vec4 fg_highlight = u_highlight_fg[highlight - 1];
+	vec4 bg_highlight = u_highlight_bg[highlight - 1];
+	vec3 highlight_fg = highlightColor(fg_highlight, fg, bg.rgb);
+	vec3 highlight_bg = highlightColor(bg_highlight, fg, bg.rgb);
+
+	bool hidden = (int(attrs + .5) & GLYPH_REF_ATTR_HIDDEN) != 0;
+	if (highlight == HIGHLIGHT_SELECTION && !hidden && distance(highlight_fg, highlight_bg) < .001) {
+		// Reveal inversed text when fg/bg is the same.
+		fg = paletteEntry(NAMED_COLOR_BACKGROUND);
+		bg = vec4(paletteEntry(NAMED_COLOR_FOREGROUND), 1.);
+		return true;
+	}
+
+	fg = highlight_fg;
+	if (int(bg_highlight.w + .5) == HIGHLIGHT_CELL_BACKGROUND) {
+		return false;
+	}
+
+	bg = vec4(highlight_bg, 1.);
+	return true;
+}
+
+// Background image pixel at `uv`, with alpha already scaled by the configured opacity.
+vec4 backgroundImage(vec2 uv, vec2 screen_cells) {
+	vec2 image_size = u_background_image_params.xy;
+	vec2 grid_size = screen_cells * u_cell_dim;
+	int mode = int(u_background_image_params.w + .5);
+
+	vec2 image_uv;
+	if (mode == BACKGROUND_IMAGE_TILE) {
+		image_uv = uv / image_size;
+	} else if (mode == BACKGROUND_IMAGE_CENTER) {
+		image_uv = (uv - (grid_size - image_size) * .5) / image_size;
+		if (any(lessThan(image_uv, vec2(0.))) || any(greaterThanEqual(image_uv, vec2(1.)))) {
+			return vec4(0.);
+		}
+	} else {
+		image_uv = uv / grid_size;
+	}
+
+	vec4 image = texture(u_background_image, image_uv);
+	image.a *= u_background_image_params.z;
+	return image;
+}
+
+// Whether a cell with the attribute byte `attrs` is in the hidden phase of blinking.
+bool blinkHidden(float attrs) {
+	return (int(attrs + .5) & GLYPH_REF_ATTR_BLINK) != 0
+		&& u_blink_interval > 0.
+		&& u_time >= u_blink_interval;
+}
+
+vec4 blendGlyphPixel(vec4 glyph_ref, vec2 cell_pix, vec3 fg, vec4 dst) {
+	if (blinkHidden(glyph_ref.w)) {
+		return dst;
+	}
 
-vec4 blendGlyphPixel(vec3 glyph_ref, vec2 cell_pix, vec3 fg, vec4 dst) {
 	vec2 atlas_pix = glyph_ref.xy * u_atlas_dim.zw + u_atlas_dim.xy + cell_pix;
 	vec4 glyph = texture(u_atlas, atlas_pix / vec2(textureSize(u_atlas, 0)));
 	vec3 mask;
 
-	if (glyph_ref.z > 2.) {
+	int flags = int(glyph_ref.z + .5);
+	if ((flags & GLYPH_REF_FLAG_COLORED) != 0) {
 		// Colored glyph (e.g. emoji)
 		mask = vec3(glyph.a);
 		glyph.rgb /= glyph.a;
@@ -32,10 +189,138 @@ vec4 blendGlyphPixel(vec3 glyph_ref, vec2 cell_pix, vec3 fg, vec4 dst) {
 	return vec4(mix(dst.rgb, fg, mask.rgb), color.a + glyph.a);
 }
 
-void doGlyph(vec2 offset, vec2 cell, vec2 cell_pix, vec2 screen_cells, inout vec4 color) {
-	vec2 tuv = (cell + offset + .5) / screen_cells;
-	vec3 glyph_ref = texture(u_glyph_ref, tuv).rgb * 255.;
-	vec3 fg = texture(u_color_fg, tuv).rgb;
+// Coverage of a horizontal line of `thickness` pixels starting at `top`.
+float lineCoverage(float y, float top, float thickness) {
+	return float(y >= top && y < top + thickness);
+}
+
+// Coverage of underlines, strikeout and overline encoded in the glyph ref flags.
+float decorationCoverage(int flags, vec2 cell_pix) {
+	float thickness = u_underline.y;
+	float coverage = 0.;
+
+	int underline = (flags >> 2) & 7;
+	if (underline == UNDERLINE) {
+		coverage = lineCoverage(cell_pix.y, u_underline.x, thickness);
+	} else if (underline == DOUBLE_UNDERLINE) {
+		coverage = max(
+			lineCoverage(cell_pix.y, u_underline.z, thickness),
+			lineCoverage(cell_pix.y, u_underline.w, thickness));
+	} else if (underline == UNDERCURL) {
+		// One period per cell, so curls connect across cells.
+		float frequency = 6.2831853 / u_cell_dim.x;
+		float phase = cell_pix.x * frequency;
+		float wave = u_undercurl.x - u_undercurl.y * sin(phase);
+		float slope = u_undercurl.y * frequency * cos(phase);
+		float dist = abs(cell_pix.y - wave) / sqrt(1. + slope * slope);
+		coverage = clamp(thickness * .5 + .5 - dist, 0., 1.);
+	} else if (underline == DOTTED_UNDERLINE) {
+		float dot_size = max(thickness, 1.);
+		coverage = lineCoverage(cell_pix.y, u_underline.x, thickness)
+			* step(mod(gl_FragCoord.x, dot_size * 2.), dot_size);
+	} else if (underline == DASHED_UNDERLINE) {
+		// Two dashes per cell.
+		float period = u_cell_dim.x * .5;
+		coverage = lineCoverage(cell_pix.y, u_underline.x, thickness)
+			* step(mod(cell_pix.x, period), period * .6);
+	}
+
+	if ((flags & GLYPH_REF_FLAG_STRIKEOUT) != 0) {
+		coverage = max(coverage, lineCoverage(cell_pix.y, u_strikeout.x, u_strikeout.y));
+	}
+
+	if ((flags & GLYPH_REF_FLAG_OVERLINE) != 0) {
+		coverage = max(coverage, lineCoverage(cell_pix.y, u_strikeout.z, u_strikeout.w));
+	}
+
+	return coverage;
+}
+
+// Coverage of a pixel `distance` pixels away from an edge by a line of `thickness` along that
+// edge, partially covering the last pixel for fractional thickness.
+float edgeCoverage(float distance, float thickness) {
+	return clamp(thickness - distance, 0., 1.);
+}
+
+// Coverage of the pixel at `pix` within a cursor of `size` pixels.
+float cursorCoverage(vec2 pix, vec2 size, float cursor_style) {
+	int style = int(cursor_style + .5);
+	float thickness = u_cursor_thickness;
+	vec2 far = size - 1. - pix;
+
+	if (style == CURSOR_BLOCK) {
+		return 1.;
+	} else if (style == CURSOR_BEAM) {
+		return edgeCoverage(pix.x, thickness);
+	} else if (style == CURSOR_UNDERLINE) {
+		return edgeCoverage(far.y, thickness);
+	} else if (style == CURSOR_HOLLOW_BLOCK) {
+		return max(
+			max(edgeCoverage(pix.x, thickness), edgeCoverage(far.x, thickness)),
+			max(edgeCoverage(pix.y, thickness), edgeCoverage(far.y, thickness)));
+	}
+
+	return 0.;
+}
+
+// Blend the cursor described like `u_cursor` over `color` at `uv`.
+vec4 drawCursor(vec2 uv, vec4 cursor, vec3 cursor_color, vec4 color) {
+	vec2 cursor_pix = uv - cursor.xy * u_cell_dim;
+	vec2 cursor_size = u_cell_dim * vec2(cursor.z, 1.);
+	if (any(lessThan(cursor_pix, vec2(0.))) || any(greaterThanEqual(cursor_pix, cursor_size))) {
+		return color;
+	}
+
+	float coverage = cursorCoverage(floor(cursor_pix), cursor_size, cursor.w);
+	return vec4(mix(color.rgb, cursor_color, coverage), color.a + coverage);
+}
+
+// Texture coordinates of a cell in the screen textures, which are ring buffers of rows.
+vec2 screenUV(vec2 cell) {
+	vec2 size = vec2(textureSize(u_glyph_ref, 0));
+	cell.y = mod(cell.y + u_screen_rows.x, size.y);
+	return (cell + .5) / size;
+}
+
+// Overview of the screen and the history lines above it, with every row of the screen textures
+// scaled to the minimap.
+//
+// The screen is shown at the bottom, with the stored history above it.
+vec4 minimap(vec2 uv) {
+	vec2 size = vec2(textureSize(u_glyph_ref, 0));
+	float lines = u_screen_rows.y;
+	vec2 scale = vec2(u_minimap.y, lines * u_cell_dim.y) / size;
+	vec2 cell = floor(vec2(uv.x - u_minimap.x, uv.y) / scale);
+	cell.y -= size.y - lines;
+	if (cell.y < -u_minimap.z || cell.y >= lines) {
+		discard;
+	}
+
+	vec2 tuv = screenUV(cell);
+	vec4 glyph = texture(u_glyph_ref, tuv) * 255.;
+	vec3 fg;
+	vec4 bg;
+	cellColors(tuv, glyph.w, fg, bg);
+
+	// Text is drawn as blocks, search matches are drawn in their solid highlight color.
+	int highlight = int(texture(u_color_fg, tuv).a * 255. + .5);
+	bool text = any(notEqual(glyph.xy, vec2(0.))) && highlight != HIGHLIGHT_SEARCH_MATCH;
+	vec3 color = text ? mix(bg.rgb, fg, .5) : bg.rgb;
+
+	// Dim the history, so the part visible on screen stands out.
+	if (cell.y < 0.) {
+		color *= .6;
+	}
+
+	return vec4(color, text ? 1. : bg.a);
+}
+
+void doGlyph(vec2 offset, vec2 cell, vec2 cell_pix, inout vec4 color) {
+	vec2 tuv = screenUV(cell + offset);
+	vec4 glyph_ref = texture(u_glyph_ref, tuv) * 255.;
+	vec3 fg;
+	vec4 bg;
+	cellColors(tuv, glyph_ref.w, fg, bg);
 	color = blendGlyphPixel(glyph_ref, cell_pix - u_cell_dim * offset, fg, color);
 }
 
@@ -44,8 +329,17 @@ void main() {
 	uv.y = u_screen_dim.w - uv.y;
 	uv.xy -= u_screen_dim.xy;
 
+	// The minimap is only drawn by its own pass.
+	bool inside_minimap = u_minimap.y > 0. && uv.x >= u_minimap.x && uv.y >= 0.;
+	if (inside_minimap != u_minimap_pass) {
+		discard;
+	} else if (u_minimap_pass) {
+		color = minimap(uv);
+		return;
+	}
+
 	vec2 cell = floor(uv / u_cell_dim);
-	vec2 screen_cells = vec2(textureSize(u_glyph_ref, 0));
+	vec2 screen_cells = vec2(textureSize(u_glyph_ref, 0).x, u_screen_rows.y);
 
 	if (any(lessThan(uv.xy, vec2(0.)))
 		|| any(greaterThanEqual(cell, screen_cells)))
@@ -55,15 +349,28 @@ void main() {
 		return;
 	}
 
-	vec2 tuv = (cell + .5) / screen_cells;
+	vec2 tuv = screenUV(cell);
 	vec2 cell_pix = mod(uv, u_cell_dim);
-	vec3 glyph = texture(u_glyph_ref, tuv).rgb * 255.;
+	vec4 glyph = texture(u_glyph_ref, tuv) * 255.;
+
+	vec3 fg;
+	vec4 bg;
+	bool highlight_background = cellColors(tuv, glyph.w, fg, bg);
 
 	if (u_main_pass) {
-		color = texture(u_color_bg, tuv);
-		if (cell == u_cursor.xy) {
-			color = blendGlyphPixel(vec3(u_cursor.zw, 1.), cell_pix, u_cursor_color, color);
+		color = bg;
+		bool cell_background = highlight_background
+			|| (int(glyph.z + .5) & GLYPH_REF_FLAG_CELL_BACKGROUND) != 0;
+		if (u_background_image_params.x > 0. && !cell_background) {
+			vec4 image = backgroundImage(uv, screen_cells);
+			color = vec4(mix(color.rgb, image.rgb, image.a), mix(color.a, 1., image.a));
 		}
+		if (u_has_underlay) {
+			vec4 under = texelFetch(u_underlay, ivec2(gl_FragCoord.xy), 0);
+			color = vec4(under.rgb + color.rgb * (1. - under.a), under.a + color.a * (1. - under.a));
+		}
+		color = drawCursor(uv, u_terminal_cursor, u_terminal_cursor_color, color);
+		color = drawCursor(uv, u_cursor, u_cursor_color, color);
 		//color = vec4(vec3(mod(gl_FragCoord.x + gl_FragCoord.y, 2.)), 1.); return;
 	} else {
 		color = vec4(0.);
@@ -78,7 +385,6 @@ void main() {
 	/* } */
 
 	// This cell glyph
-	vec3 fg = texture(u_color_fg, tuv).rgb;
 	color = blendGlyphPixel(glyph, cell_pix, fg, color);
 
 	// Neighbour cells overlappery
@@ -95,15 +401,21 @@ void main() {
 	// +, -
 
 	if (cell_pix.y > (u_cell_dim.y - u_atlas_dim.y) && cell.y < (screen_cells.y-1.)) {
-		doGlyph(vec2(0., 1.), cell, cell_pix, screen_cells, color);
+		doGlyph(vec2(0., 1.), cell, cell_pix, color);
 		//color.g = 1.;
 	}
 
 	if (cell.x > 0. && cell_pix.x < (u_atlas_dim.z - u_atlas_dim.x - u_cell_dim.x)) {
-		doGlyph(vec2(-1., 0.), cell, cell_pix, screen_cells, color);
+		doGlyph(vec2(-1., 0.), cell, cell_pix, color);
 		//color.r = 1.;
 	}
 
+	// Underlines, strikeout and overline are drawn on top of all glyphs in this pass
+	if (u_main_pass) {
+		float decoration = blinkHidden(glyph.w) ? 0. : decorationCoverage(int(glyph.z + .5), cell_pix);
+		color = vec4(mix(color.rgb, fg, decoration), color.a + decoration);
+	}
+
 	//color = vec4(fg.rgb, 1.);
 	//color = vec4(bg.rgb, 1.);
 	//color = vec4(mask.rgb, 1.);
@@ -0,0 +1,223 @@
+//! Detection of the desktop's light/dark color scheme and reduced motion preferences.
+
+#[cfg(not(any(target_os = "macos", windows)))]
+use std::io::{BufRead, BufReader};
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::process::{Command, Stdio};
+
+use glutin::window::Theme;
+use log::debug;
+#[cfg(windows)]
+use winapi::um::winbase::CREATE_NO_WINDOW;
+
+#[cfg(not(any(target_os = "macos", windows)))]
+use alacritty_terminal::thread;
+
+#[cfg(not(any(target_os = "macos", windows)))]
+use crate::event::Event;
+use crate::event::EventProxy;
+
+/// Color scheme preferred by the desktop environment.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Appearance {
+    Light,
+    Dark,
+}
+
+impl From<Theme> for Appearance {
+    fn from(theme: Theme) -> Self {
+        match theme {
+            Theme::Light => Appearance::Light,
+            Theme::Dark => Appearance::Dark,
+        }
+    }
+}
+
+/// Query the current desktop appearance.
+///
+/// Returns `None` when the platform does not expose a preference.
+#[cfg(not(any(target_os = "macos", windows)))]
+pub fn current() -> Option<Appearance> {
+    match read_portal_setting("org.freedesktop.appearance", "color-scheme")?.as_str() {
+        "1" => Some(Appearance::Dark),
+        "2" => Some(Appearance::Light),
+        value => {
+            debug!("No color scheme preference in desktop portal: {}", value);
+            None
+        },
+    }
+}
+
+/// Query whether the desktop asks applications to avoid animations.
+///
+/// Returns `None` when the platform does not expose a preference.
+#[cfg(not(any(target_os = "macos", windows)))]
+pub fn reduced_motion() -> Option<bool> {
+    match read_portal_setting("org.gnome.desktop.interface", "enable-animations")?.as_str() {
+        "true" => Some(false),
+        "false" => Some(true),
+        value => {
+            debug!("No animation preference in desktop portal: {}", value);
+            None
+        },
+    }
+}
+
+/// Read a setting from the desktop portal.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn read_portal_setting(namespace: &str, key: &str) -> Option<String> {
+    let namespace = format!("string:{}", namespace);
+    let key = format!("string:{}", key);
+    let output = Command::new("dbus-send")
+        .args(&[
+            "--session",
+            "--print-reply=literal",
+            "--reply-timeout=1000",
+            "--dest=org.freedesktop.portal.Desktop",
+            "/org/freedesktop/portal/desktop",
+            "org.freedesktop.portal.Settings.Read",
+            namespace.as_str(),
+            key.as_str(),
+        ])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    // The reply is a nested variant like `variant variant uint32 1`.
+    let reply = String::from_utf8_lossy(&output.stdout);
+    reply.split_whitespace().last().map(String::from)
+}
+
+#[cfg(target_os = "macos")]
+pub fn current() -> Option<Appearance> {
+    let output = Command::new("defaults")
+        .args(&["read", "-g", "AppleInterfaceStyle"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    // The key is only present while dark mode is active.
+    if String::from_utf8_lossy(&output.stdout).trim() == "Dark" {
+        Some(Appearance::Dark)
+    } else {
+        Some(Appearance::Light)
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn reduced_motion() -> Option<bool> {
+    let output = Command::new("defaults")
+        .args(&["read", "com.apple.universalaccess", "reduceMotion"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "1" => Some(true),
+        "0" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(windows)]
+pub fn current() -> Option<Appearance> {
+    let output = Command::new("reg")
+        .args(&[
+            "query",
+            r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+            "/v",
+            "AppsUseLightTheme",
+        ])
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+
+    let reply = String::from_utf8_lossy(&output.stdout);
+    match reply.split_whitespace().last()? {
+        "0x0" => Some(Appearance::Dark),
+        "0x1" => Some(Appearance::Light),
+        value => {
+            debug!("Unexpected AppsUseLightTheme value: {}", value);
+            None
+        },
+    }
+}
+
+#[cfg(windows)]
+pub fn reduced_motion() -> Option<bool> {
+    let output = Command::new("reg")
+        .args(&["query", r"HKCU\Control Panel\Desktop\WindowMetrics", "/v", "MinAnimate"])
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+
+    // Window animations are turned off through the "Show animations in Windows" setting.
+    let reply = String::from_utf8_lossy(&output.stdout);
+    match reply.split_whitespace().last()? {
+        "0" => Some(true),
+        "1" => Some(false),
+        value => {
+            debug!("Unexpected MinAnimate value: {}", value);
+            None
+        },
+    }
+}
+
+/// Watch the desktop portal for color scheme and animation preference changes.
+///
+/// On Windows color scheme changes are reported through the window's `ThemeChanged` event and
+/// otherwise the preferences are queried again whenever the window gains focus, so this is a
+/// no-op there.
+#[cfg(not(any(target_os = "macos", windows)))]
+pub fn watch(event_proxy: EventProxy) {
+    let monitor = Command::new("dbus-monitor")
+        .args(&[
+            "--session",
+            "type='signal',interface='org.freedesktop.portal.Settings',member='SettingChanged'",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let mut monitor = match monitor {
+        Ok(monitor) => monitor,
+        Err(err) => {
+            debug!("Unable to watch desktop color scheme: {}", err);
+            return;
+        },
+    };
+
+    let stdout = match monitor.stdout.take() {
+        Some(stdout) => stdout,
+        None => return,
+    };
+
+    thread::spawn_named("appearance watcher", move || {
+        for line in BufReader::new(stdout).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+
+            // Signal arguments are printed one per line, so just ask the portal again.
+            if line.contains("\"color-scheme\"") {
+                if let Some(appearance) = current() {
+                    event_proxy.send_event(Event::AppearanceChanged(appearance));
+                }
+            } else if line.contains("\"enable-animations\"") {
+                if let Some(reduced_motion) = reduced_motion() {
+                    event_proxy.send_event(Event::ReducedMotionChanged(reduced_motion));
+                }
+            }
+        }
+
+        let _ = monitor.kill();
+    });
+}
+
+#[cfg(any(target_os = "macos", windows))]
+pub fn watch(_event_proxy: EventProxy) {}
@@ -0,0 +1,271 @@
+//! Visual reordering of bidirectional text.
+//!
+//! The terminal stores text in logical order, so lines containing Arabic or Hebrew are resolved
+//! with the Unicode bidirectional algorithm (UAX #9) before their cells are drawn. Every line is
+//! its own left-to-right paragraph, like the rest of the grid.
+
+use unicode_bidi::{BidiInfo, Level};
+
+use alacritty_terminal::index::Column;
+use alacritty_terminal::term::cell::Flags;
+use alacritty_terminal::term::{RenderableCell, RenderableCellContent};
+
+/// Characters before this are neither right-to-left nor change the direction of other text.
+const FIRST_RTL_CHAR: char = '\u{590}';
+
+/// Number of presentation forms for the Arabic letters U+0621 to U+063A and U+0641 to U+064A.
+///
+/// The forms are stored in this order in the Arabic Presentation Forms-B block, starting at
+/// U+FE80. Letters with four forms join on both sides, letters with two only to the preceding
+/// letter.
+const ARABIC_FORMS: [u32; 36] = [
+    1, 2, 2, 2, 2, 4, 2, 4, 2, 4, 4, 4, 4, 4, 2, 2, 2, 2, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4,
+    4, 2, 2, 4,
+];
+
+/// Arabic tatweel, which connects to letters on both sides.
+const TATWEEL: char = '\u{640}';
+
+/// Character spanning one or two columns.
+struct Unit {
+    column: usize,
+    width: usize,
+    byte: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Joining {
+    None,
+    Right,
+    Dual,
+}
+
+/// Move the cells of a line from logical to visual order.
+///
+/// Arabic letters are replaced by their contextual presentation forms when `arabic_shaping` is
+/// enabled. Returns the visual column of every logical column, if the line contains any
+/// right-to-left text.
+pub fn reorder_line(
+    cells: &mut [RenderableCell],
+    columns: usize,
+    arabic_shaping: bool,
+) -> Option<Vec<Column>> {
+    // Character of every column, blank columns are spaces.
+    let mut chars = vec![' '; columns];
+    let mut wide = vec![false; columns];
+    for cell in cells.iter().filter(|cell| cell.column.0 < columns) {
+        if let RenderableCellContent::Chars(cell_chars) = cell.inner {
+            if !cell.flags.intersects(Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER) {
+                chars[cell.column.0] = cell_chars[0];
+                wide[cell.column.0] = cell.flags.contains(Flags::WIDE_CHAR);
+            }
+        }
+    }
+
+    if chars.iter().all(|&c| c < FIRST_RTL_CHAR) {
+        return None;
+    }
+
+    // Text with one character per unit, wide characters skip their spacer.
+    let mut text = String::with_capacity(columns);
+    let mut units = Vec::with_capacity(columns);
+    let mut column = 0;
+    while column < columns {
+        let width = if wide[column] { 2.min(columns - column) } else { 1 };
+        units.push(Unit { column, width, byte: text.len() });
+        text.push(chars[column]);
+        column += width;
+    }
+
+    let info = BidiInfo::new(&text, Some(Level::ltr()));
+    if !info.has_rtl() {
+        return None;
+    }
+
+    let unit_index = |byte: usize| match units.binary_search_by_key(&byte, |unit| unit.byte) {
+        Ok(index) | Err(index) => index,
+    };
+
+    let mut map = vec![Column(0); columns];
+    let mut rtl = vec![false; columns];
+    let mut visual = 0;
+    for paragraph in &info.paragraphs {
+        let (levels, runs) = info.visual_runs(paragraph, paragraph.range.clone());
+        for run in runs {
+            let is_rtl = levels[run.start].is_rtl();
+            let run_units = &units[unit_index(run.start)..unit_index(run.end)];
+
+            let mut place = |unit: &Unit| {
+                for offset in 0..unit.width {
+                    map[unit.column + offset] = Column(visual + offset);
+                }
+                rtl[unit.column] = is_rtl;
+                visual += unit.width;
+            };
+
+            if is_rtl {
+                run_units.iter().rev().for_each(&mut place);
+            } else {
+                run_units.iter().for_each(&mut place);
+            }
+        }
+    }
+
+    for cell in cells.iter_mut().filter(|cell| cell.column.0 < columns) {
+        let column = cell.column.0;
+        if let RenderableCellContent::Chars(cell_chars) = &mut cell.inner {
+            if arabic_shaping {
+                let previous = column.checked_sub(1).map_or(' ', |column| chars[column]);
+                let next = chars.get(column + 1).copied().unwrap_or(' ');
+                cell_chars[0] = shape_arabic(previous, cell_chars[0], next);
+            }
+
+            // Brackets in right-to-left text point the other way.
+            if rtl[column] {
+                cell_chars[0] = mirror(cell_chars[0]);
+            }
+        }
+
+        cell.column = map[column];
+    }
+
+    // Sorting is stable, so cursors still follow the cell they are drawn on.
+    cells.sort_by_key(|cell| cell.column);
+
+    Some(map)
+}
+
+/// Presentation forms of an Arabic letter, as the first form and the number of forms.
+fn arabic_forms(c: char) -> Option<(u32, u32)> {
+    let index = match c {
+        '\u{621}'..='\u{63a}' => c as usize - 0x621,
+        '\u{641}'..='\u{64a}' => c as usize - 0x641 + 26,
+        _ => return None,
+    };
+
+    let first = 0xfe80 + ARABIC_FORMS[..index].iter().sum::<u32>();
+    Some((first, ARABIC_FORMS[index]))
+}
+
+fn joining(c: char) -> Joining {
+    match arabic_forms(c) {
+        Some((_, 4)) => Joining::Dual,
+        Some((_, 2)) => Joining::Right,
+        _ if c == TATWEEL => Joining::Dual,
+        _ => Joining::None,
+    }
+}
+
+/// Contextual form of `c`, between the logically `previous` and `next` characters.
+fn shape_arabic(previous: char, c: char, next: char) -> char {
+    let (first, _) = match arabic_forms(c) {
+        Some(forms) => forms,
+        None => return c,
+    };
+
+    let joining_c = joining(c);
+    let joins_previous = joining_c != Joining::None && joining(previous) == Joining::Dual;
+    let joins_next = joining_c == Joining::Dual && joining(next) != Joining::None;
+
+    // Forms are isolated, final, initial and medial.
+    let form = match (joins_previous, joins_next) {
+        (false, false) => 0,
+        (true, false) => 1,
+        (false, true) => 2,
+        (true, true) => 3,
+    };
+
+    std::char::from_u32(first + form).unwrap_or(c)
+}
+
+fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alacritty_terminal::index::Line;
+    use alacritty_terminal::term::cell::MAX_ZEROWIDTH_CHARS;
+    use alacritty_terminal::term::CellHighlight;
+
+    use crate::frame::FrameLine;
+
+    fn cells(text: &str) -> Vec<RenderableCell> {
+        text.chars()
+            .enumerate()
+            .filter(|&(_, c)| c != ' ')
+            .map(|(column, c)| {
+                let mut chars = [' '; MAX_ZEROWIDTH_CHARS + 1];
+                chars[0] = c;
+
+                RenderableCell {
+                    inner: RenderableCellContent::Chars(chars),
+                    line: Line(0),
+                    column: Column(column),
+                    fg: Default::default(),
+                    bg: Default::default(),
+                    fg_index: None,
+                    bg_index: None,
+                    bg_alpha: 0.,
+                    flags: Flags::empty(),
+                    highlight: CellHighlight::None,
+                    hyperlink: 0,
+                }
+            })
+            .collect()
+    }
+
+    fn text(cells: &[RenderableCell], columns: usize) -> String {
+        FrameLine { line: Line(0), cells }.text(columns)
+    }
+
+    #[test]
+    fn left_to_right_is_untouched() {
+        let mut line = cells("echo hello");
+        assert_eq!(reorder_line(&mut line, 20, true), None);
+        assert_eq!(text(&line, 20), "echo hello");
+    }
+
+    #[test]
+    fn hebrew_is_reversed() {
+        let mut line = cells("ls שלום.txt");
+        let map = reorder_line(&mut line, 12, true).unwrap();
+
+        assert_eq!(text(&line, 12), "ls םולש.txt");
+        assert_eq!(map[3], Column(6));
+        assert_eq!(map[6], Column(3));
+    }
+
+    #[test]
+    fn brackets_are_mirrored() {
+        let mut line = cells("א(ב)ג");
+        reorder_line(&mut line, 5, true);
+        assert_eq!(text(&line, 5), "ג(ב)א");
+    }
+
+    #[test]
+    fn arabic_is_shaped() {
+        // Beh, alef and beh again, where alef does not join the following letter.
+        let mut line = cells("\u{628}\u{627}\u{628}");
+        reorder_line(&mut line, 3, true);
+        assert_eq!(text(&line, 3), "\u{fe8f}\u{fe8e}\u{fe91}");
+
+        let mut line = cells("\u{628}\u{627}\u{628}");
+        reorder_line(&mut line, 3, false);
+        assert_eq!(text(&line, 3), "\u{628}\u{627}\u{628}");
+    }
+}
@@ -2,6 +2,8 @@ use std::cmp::max;
 use std::path::PathBuf;
 
 use clap::{crate_authors, crate_description, crate_name, crate_version, App, Arg};
+#[cfg(unix)]
+use clap::{AppSettings, SubCommand};
 use log::{self, error, LevelFilter};
 use serde_yaml::Value;
 
@@ -12,6 +14,8 @@ use crate::config::serde_utils;
 use crate::config::ui_config::Delta;
 use crate::config::window::{Dimensions, DEFAULT_NAME};
 use crate::config::Config;
+#[cfg(unix)]
+use crate::ipc::SocketMessage;
 
 #[cfg(not(any(target_os = "macos", windows)))]
 const CONFIG_PATH: &str = "$XDG_CONFIG_HOME/alacritty/alacritty.yml";
@@ -37,7 +41,20 @@ pub struct Options {
     pub working_directory: Option<PathBuf>,
     pub config_path: Option<PathBuf>,
     pub persistent_logging: bool,
+    pub diagnostic_bundle: bool,
+    pub replay_frames: Option<PathBuf>,
+    pub replay_output: Option<PathBuf>,
     pub config_options: Value,
+    #[cfg(unix)]
+    pub message: Option<MessageOptions>,
+}
+
+/// Message for a running instance, sent with `alacritty msg`.
+#[cfg(unix)]
+pub struct MessageOptions {
+    /// Socket of the instance, instead of the one Alacritty was started in.
+    pub socket: Option<PathBuf>,
+    pub message: SocketMessage,
 }
 
 impl Default for Options {
@@ -58,7 +75,12 @@ impl Default for Options {
             working_directory: None,
             config_path: None,
             persistent_logging: false,
+            diagnostic_bundle: false,
+            replay_frames: None,
+            replay_output: None,
             config_options: Value::Null,
+            #[cfg(unix)]
+            message: None,
         }
     }
 }
@@ -74,7 +96,7 @@ impl Options {
 
         let mut options = Options::default();
 
-        let matches = App::new(crate_name!())
+        let app = App::new(crate_name!())
             .version(version.as_str())
             .author(crate_authors!("\n"))
             .about(crate_description!())
@@ -100,6 +122,24 @@ impl Options {
                     .long("persistent-logging")
                     .help("Keep the log file after quitting Alacritty"),
             )
+            .arg(
+                Arg::with_name("diagnostic-bundle")
+                    .long("diagnostic-bundle")
+                    .help("Write a diagnostic bundle for bug reports after the first frame"),
+            )
+            .arg(
+                Arg::with_name("replay-frames")
+                    .long("replay-frames")
+                    .value_name("file")
+                    .help("Draw frames recorded with `debug.record_frames`"),
+            )
+            .arg(
+                Arg::with_name("replay-output")
+                    .long("replay-output")
+                    .value_name("directory")
+                    .requires("replay-frames")
+                    .help("Write every replayed frame into a PNG file and quit after the last"),
+            )
             .arg(
                 Arg::with_name("dimensions")
                     .long("dimensions")
@@ -183,8 +223,61 @@ impl Options {
                     .multiple(true)
                     .takes_value(true)
                     .help("Override configuration file options [example: cursor.style=Beam]"),
-            )
-            .get_matches();
+            );
+
+        #[cfg(unix)]
+        let app = app.subcommand(
+            SubCommand::with_name("msg")
+                .about("Send a message to a running Alacritty instance")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .arg(
+                    Arg::with_name("socket")
+                        .long("socket")
+                        .short("s")
+                        .takes_value(true)
+                        .help("Path of the instance's socket [default: $ALACRITTY_SOCKET]"),
+                )
+                .subcommand(
+                    SubCommand::with_name("config")
+                        .about("Override configuration options of the running instance")
+                        .arg(Arg::with_name("options").required(true).multiple(true).help(
+                            "YAML or JSON fragments, or options like in --option [example: \
+                             font.size=14]",
+                        )),
+                )
+                .subcommand(
+                    SubCommand::with_name("opacity")
+                        .about("Change the background opacity of the running instance")
+                        .arg(
+                            Arg::with_name("opacity")
+                                .required(true)
+                                .validator(|value| parse_opacity(&value).map(|_| ()))
+                                .help("Opacity from 0.0 to 1.0, or `reset` for the configured one"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("stats")
+                        .about("Print statistics of the last frame drawn by the running instance"),
+                )
+                .subcommand(
+                    SubCommand::with_name("mark")
+                        .about("Mark cells of the running instance, like `OSC 1339 ; mark`")
+                        .arg(
+                            Arg::with_name("clear")
+                                .long("clear")
+                                .conflicts_with("parameters")
+                                .help("Remove all marks of the active screen buffer"),
+                        )
+                        .arg(
+                            Arg::with_name("parameters")
+                                .multiple(true)
+                                .required_unless("clear")
+                                .help("Mark parameters [example: line=2 columns=3-5 tint=#ff0000]"),
+                        ),
+                ),
+        );
+
+        let matches = app.get_matches();
 
         if matches.is_present("ref-test") {
             options.ref_test = true;
@@ -204,6 +297,18 @@ impl Options {
             options.persistent_logging = true;
         }
 
+        if matches.is_present("diagnostic-bundle") {
+            options.diagnostic_bundle = true;
+        }
+
+        if let Some(path) = matches.value_of("replay-frames") {
+            options.replay_frames = Some(PathBuf::from(path));
+        }
+
+        if let Some(path) = matches.value_of("replay-output") {
+            options.replay_output = Some(PathBuf::from(path));
+        }
+
         if let Some(mut dimensions) = matches.values_of("dimensions") {
             let columns = dimensions.next().map(|columns| columns.parse().map(Column));
             let lines = dimensions.next().map(|lines| lines.parse().map(Line));
@@ -273,6 +378,38 @@ impl Options {
             }
         }
 
+        #[cfg(unix)]
+        if let Some(msg) = matches.subcommand_matches("msg") {
+            let socket = msg.value_of("socket").map(PathBuf::from);
+            if let Some(config) = msg.subcommand_matches("config") {
+                let mut config_options = Value::Null;
+                for fragment in config.values_of("options").into_iter().flatten() {
+                    match fragment_as_value(fragment) {
+                        Ok(value) => config_options = serde_utils::merge(config_options, value),
+                        Err(_) => eprintln!("Invalid config fragment: {:?}", fragment),
+                    }
+                }
+
+                let message = SocketMessage::Config(config_options);
+                options.message = Some(MessageOptions { socket, message });
+            } else if let Some(opacity) = msg.subcommand_matches("opacity") {
+                let opacity = parse_opacity(opacity.value_of("opacity").unwrap()).unwrap();
+                let message = SocketMessage::Opacity(opacity);
+                options.message = Some(MessageOptions { socket, message });
+            } else if msg.subcommand_matches("stats").is_some() {
+                let message = SocketMessage::Stats;
+                options.message = Some(MessageOptions { socket, message });
+            } else if let Some(mark) = msg.subcommand_matches("mark") {
+                let message = if mark.is_present("clear") {
+                    SocketMessage::ClearMarks
+                } else {
+                    let params = mark.values_of("parameters").into_iter().flatten();
+                    SocketMessage::Mark(params.map(String::from).collect())
+                };
+                options.message = Some(MessageOptions { socket, message });
+            }
+        }
+
         options
     }
 
@@ -323,6 +460,9 @@ impl Options {
         config.ui_config.debug.log_level = max(config.ui_config.debug.log_level, self.log_level);
         config.ui_config.debug.ref_test |= self.ref_test;
         config.ui_config.debug.persistent_logging |= self.persistent_logging;
+        config.ui_config.debug.diagnostic_bundle |= self.diagnostic_bundle;
+        config.ui_config.debug.replay_frames = self.replay_frames.clone();
+        config.ui_config.debug.replay_output = self.replay_output.clone();
 
         if config.ui_config.debug.print_events {
             config.ui_config.debug.log_level =
@@ -362,6 +502,28 @@ fn option_as_value(option: &str) -> Result<Value, serde_yaml::Error> {
     serde_yaml::from_str(&yaml_text)
 }
 
+/// Parse the background opacity of `alacritty msg opacity`, `None` to reset it.
+#[cfg(unix)]
+fn parse_opacity(value: &str) -> Result<Option<f32>, String> {
+    if value == "reset" {
+        return Ok(None);
+    }
+
+    match value.parse::<f32>() {
+        Ok(opacity) if (0. ..=1.).contains(&opacity) => Ok(Some(opacity)),
+        _ => Err(String::from("opacity must be between 0.0 and 1.0, or reset")),
+    }
+}
+
+/// Parse a configuration fragment in YAML or JSON syntax, or in the format of `--option`.
+#[cfg(unix)]
+fn fragment_as_value(fragment: &str) -> Result<Value, serde_yaml::Error> {
+    match serde_yaml::from_str(fragment) {
+        Ok(value @ Value::Mapping(_)) => Ok(value),
+        _ => option_as_value(fragment),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -435,4 +597,28 @@ mod tests {
 
         assert_eq!(value, Value::Mapping(expected));
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn fragment_as_value_formats() {
+        let mut font = Mapping::new();
+        font.insert(Value::String(String::from("size")), Value::Number(14.into()));
+        let mut expected = Mapping::new();
+        expected.insert(Value::String(String::from("font")), Value::Mapping(font));
+        let expected = Value::Mapping(expected);
+
+        assert_eq!(fragment_as_value("font.size=14").unwrap(), expected);
+        assert_eq!(fragment_as_value("{ font: { size: 14 } }").unwrap(), expected);
+        assert_eq!(fragment_as_value(r#"{"font": {"size": 14}}"#).unwrap(), expected);
+        assert!(fragment_as_value("}").is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn parse_opacity_values() {
+        assert_eq!(parse_opacity("0.5"), Ok(Some(0.5)));
+        assert_eq!(parse_opacity("reset"), Ok(None));
+        assert!(parse_opacity("1.5").is_err());
+        assert!(parse_opacity("opaque").is_err());
+    }
 }
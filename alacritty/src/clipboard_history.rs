@@ -0,0 +1,165 @@
+//! Recently copied selections, which can be pasted again from a chooser overlay.
+
+use std::collections::VecDeque;
+
+use unicode_width::UnicodeWidthChar;
+
+/// Number of entries which can be picked with the number keys.
+pub const NUMBERED_ENTRIES: usize = 9;
+
+/// Copied selections, most recent first.
+#[derive(Debug, Default)]
+pub struct ClipboardHistory {
+    entries: VecDeque<String>,
+
+    /// Highlighted entry while the chooser is open.
+    chooser: Option<usize>,
+}
+
+impl ClipboardHistory {
+    /// Remember copied text, keeping at most `capacity` entries.
+    ///
+    /// Copying text which is already in the history moves it to the front.
+    pub fn push(&mut self, text: String, capacity: usize) {
+        if let Some(index) = self.entries.iter().position(|entry| *entry == text) {
+            self.entries.remove(index);
+        }
+
+        self.entries.push_front(text);
+        self.entries.truncate(capacity);
+
+        // Entries move around, so a highlight would point at the wrong text.
+        self.chooser = self.chooser.map(|_| 0);
+    }
+
+    /// Open the chooser, unless there is nothing to choose from.
+    pub fn open(&mut self) {
+        if !self.entries.is_empty() {
+            self.chooser = Some(0);
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.chooser = None;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.chooser.is_some()
+    }
+
+    /// Move the highlight of the chooser by a number of entries.
+    pub fn select(&mut self, delta: isize) {
+        let last = self.entries.len().saturating_sub(1) as isize;
+        if let Some(selected) = &mut self.chooser {
+            *selected = (*selected as isize + delta).max(0).min(last) as usize;
+        }
+    }
+
+    /// Close the chooser, returning the highlighted entry.
+    pub fn choose(&mut self) -> Option<String> {
+        let index = self.chooser?;
+        self.pick(index)
+    }
+
+    /// Close the chooser, returning the entry at `index`.
+    ///
+    /// The entry becomes the most recent one, like it has been copied again.
+    pub fn pick(&mut self, index: usize) -> Option<String> {
+        self.chooser = None;
+
+        let text = self.entries.remove(index)?;
+        self.entries.push_front(text.clone());
+        Some(text)
+    }
+
+    /// Highlighted entry of the open chooser.
+    pub fn selected(&self) -> Option<usize> {
+        self.chooser
+    }
+
+    /// Single line previews of the entries, at most `columns` wide.
+    pub fn previews(&self, columns: usize) -> Vec<String> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let number =
+                    if i < NUMBERED_ENTRIES { format!("{} ", i + 1) } else { String::from("  ") };
+                preview(&number, entry, columns)
+            })
+            .collect()
+    }
+}
+
+/// First line of `text` following `prefix`, truncated with an ellipsis to fit `columns`.
+fn preview(prefix: &str, text: &str, columns: usize) -> String {
+    let multiline = text.trim_end().contains('\n');
+    let mut line = text.trim().lines().next().unwrap_or("").replace('\t', " ");
+    if multiline {
+        line.push_str(" …");
+    }
+
+    let mut preview = String::from(prefix);
+    let mut width = prefix.len();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let c_width = c.width().unwrap_or(0);
+        let remaining = if chars.peek().is_some() { 1 } else { 0 };
+        if width + c_width + remaining > columns {
+            preview.push('…');
+            break;
+        }
+
+        preview.push(c);
+        width += c_width;
+    }
+
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_entries_first() {
+        let mut history = ClipboardHistory::default();
+        history.push(String::from("a"), 2);
+        history.push(String::from("b"), 2);
+        history.push(String::from("a"), 2);
+        history.push(String::from("c"), 2);
+
+        assert_eq!(history.entries, vec![String::from("c"), String::from("a")]);
+    }
+
+    #[test]
+    fn choose_entry() {
+        let mut history = ClipboardHistory::default();
+        history.open();
+        assert!(!history.is_active());
+
+        history.push(String::from("first"), 10);
+        history.push(String::from("second"), 10);
+        history.push(String::from("third"), 10);
+
+        history.open();
+        history.select(5);
+        assert_eq!(history.selected(), Some(2));
+        history.select(-1);
+        assert_eq!(history.choose(), Some(String::from("second")));
+        assert!(!history.is_active());
+
+        assert_eq!(history.pick(2), Some(String::from("first")));
+        assert_eq!(history.pick(3), None);
+    }
+
+    #[test]
+    fn previews() {
+        let mut history = ClipboardHistory::default();
+        history.push(String::from("echo hello\necho world\n"), 10);
+        history.push(String::from("a long line of text"), 10);
+
+        assert_eq!(history.previews(12), vec!["1 a long li…", "2 echo hell…"]);
+        assert_eq!(history.previews(40), vec!["1 a long line of text", "2 echo hello …"]);
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use alacritty_terminal::config::failure_default;
+
+use crate::config::ui_config::DefaultTrueBool;
+
+/// Switch for all animated transitions.
+#[serde(default)]
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Animations {
+    /// Animate cursor motion, color and font size changes, the visual bell and idle dimming.
+    #[serde(deserialize_with = "failure_default")]
+    enabled: DefaultTrueBool,
+
+    /// Disable animations while the desktop prefers reduced motion.
+    #[serde(deserialize_with = "failure_default")]
+    follow_system: DefaultTrueBool,
+
+    /// Reduced motion preference of the desktop.
+    #[serde(skip)]
+    system_reduced_motion: bool,
+}
+
+impl Animations {
+    /// Whether transitions should be animated.
+    pub fn enabled(&self) -> bool {
+        self.enabled.0 && !(self.follow_system.0 && self.system_reduced_motion)
+    }
+
+    /// Duration of an animation, zero while animations are disabled.
+    pub fn duration(&self, duration: Duration) -> Duration {
+        if self.enabled() {
+            duration
+        } else {
+            Duration::default()
+        }
+    }
+
+    /// Whether the desktop preference should be queried.
+    pub fn follow_system(&self) -> bool {
+        self.follow_system.0
+    }
+
+    pub fn system_reduced_motion(&self) -> bool {
+        self.system_reduced_motion
+    }
+
+    pub fn set_system_reduced_motion(&mut self, reduced_motion: bool) {
+        self.system_reduced_motion = reduced_motion;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reduced_motion() {
+        let mut animations = Animations::default();
+        assert_eq!(animations.duration(Duration::from_millis(100)), Duration::from_millis(100));
+
+        animations.set_system_reduced_motion(true);
+        assert!(!animations.enabled());
+        assert_eq!(animations.duration(Duration::from_millis(100)), Duration::default());
+
+        let mut animations: Animations = serde_yaml::from_str("follow_system: false").unwrap();
+        animations.set_system_reduced_motion(true);
+        assert!(animations.enabled());
+
+        let animations: Animations = serde_yaml::from_str("enabled: false").unwrap();
+        assert!(!animations.enabled());
+    }
+}
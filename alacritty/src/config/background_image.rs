@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use alacritty_terminal::config::{failure_default, Percentage};
+
+/// Image drawn behind the terminal grid.
+#[serde(default)]
+#[derive(Default, Deserialize, Clone, Debug, PartialEq)]
+pub struct BackgroundImage {
+    /// Path to a PNG or JPEG file.
+    #[serde(deserialize_with = "failure_default")]
+    pub path: PathBuf,
+
+    /// Opacity of the image on top of the background color.
+    #[serde(deserialize_with = "failure_default")]
+    opacity: Percentage,
+
+    /// Placement of the image inside the window.
+    #[serde(deserialize_with = "failure_default")]
+    pub mode: BackgroundImageMode,
+}
+
+impl BackgroundImage {
+    #[inline]
+    pub fn opacity(&self) -> f32 {
+        self.opacity.as_f32()
+    }
+}
+
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BackgroundImageMode {
+    /// Scale the image to cover the entire grid.
+    Stretch,
+    /// Repeat the image at its original size.
+    Tile,
+    /// Center the image at its original size.
+    Center,
+}
+
+impl Default for BackgroundImageMode {
+    fn default() -> Self {
+        BackgroundImageMode::Stretch
+    }
+}
@@ -88,6 +88,10 @@ pub enum Action {
     #[serde(skip)]
     Command(Program),
 
+    /// Switch to a color scheme of `theme.schemes`.
+    #[serde(skip)]
+    SetColorScheme(String),
+
     /// Move vi mode cursor.
     #[serde(skip)]
     ViMotion(ViMotion),
@@ -182,6 +186,49 @@ pub enum Action {
     /// Start a backward buffer search.
     SearchBackward,
 
+    /// Toggle the glyph atlas debug overlay.
+    ToggleAtlasOverlay,
+
+    /// Write all glyph atlases into PNG files.
+    #[serde(alias = "DumpAtlas")]
+    DumpAtlases,
+
+    /// Write a zip file with information for reporting rendering bugs.
+    DiagnosticBundle,
+
+    /// Toggle the frame time display of `debug.render_timer`.
+    ToggleRendererHud,
+
+    /// Compile the post-processing and idle shaders again from their files.
+    ReloadShaders,
+
+    /// Switch to the next background opacity step.
+    CycleBackgroundOpacity,
+
+    /// Switch to the next color scheme of `theme.schemes`.
+    ToggleColorScheme,
+
+    /// Toggle the sheet of all cached glyphs.
+    ToggleGlyphInspector,
+
+    /// Toggle the overlay showing recently pressed keys.
+    ToggleKeyOverlay,
+
+    /// Select a screen region to export as text, ANSI text or PNG.
+    ToggleRegionCapture,
+
+    /// Label URLs and paths on screen, opening the one whose label is typed.
+    OpenHint,
+
+    /// Label URLs and paths on screen, copying the one whose label is typed.
+    CopyHint,
+
+    /// Draw rings around the cursor, shrinking towards it.
+    LocateCursor,
+
+    /// Choose a recently copied selection to paste.
+    PasteFromHistory,
+
     /// No action.
     None,
 }
@@ -715,7 +762,8 @@ impl<'a> Deserialize<'a> for RawBinding {
     where
         D: Deserializer<'a>,
     {
-        const FIELDS: &[&str] = &["key", "mods", "mode", "action", "chars", "mouse", "command"];
+        const FIELDS: &[&str] =
+            &["key", "mods", "mode", "action", "chars", "mouse", "command", "color_scheme"];
 
         enum Field {
             Key,
@@ -725,6 +773,7 @@ impl<'a> Deserialize<'a> for RawBinding {
             Chars,
             Mouse,
             Command,
+            ColorScheme,
         }
 
         impl<'a> Deserialize<'a> for Field {
@@ -753,6 +802,7 @@ impl<'a> Deserialize<'a> for RawBinding {
                             "chars" => Ok(Field::Chars),
                             "mouse" => Ok(Field::Mouse),
                             "command" => Ok(Field::Command),
+                            "color_scheme" => Ok(Field::ColorScheme),
                             _ => Err(E::unknown_field(value, FIELDS)),
                         }
                     }
@@ -782,6 +832,7 @@ impl<'a> Deserialize<'a> for RawBinding {
                 let mut not_mode: Option<TermMode> = None;
                 let mut mouse: Option<MouseButton> = None;
                 let mut command: Option<Program> = None;
+                let mut color_scheme: Option<String> = None;
 
                 use de::Error;
 
@@ -880,6 +931,13 @@ impl<'a> Deserialize<'a> for RawBinding {
 
                             command = Some(map.next_value::<Program>()?);
                         },
+                        Field::ColorScheme => {
+                            if color_scheme.is_some() {
+                                return Err(<V::Error as Error>::duplicate_field("color_scheme"));
+                            }
+
+                            color_scheme = Some(map.next_value()?);
+                        },
                     }
                 }
 
@@ -887,9 +945,9 @@ impl<'a> Deserialize<'a> for RawBinding {
                 let not_mode = not_mode.unwrap_or_else(TermMode::empty);
                 let mods = mods.unwrap_or_else(ModifiersState::default);
 
-                let action = match (action, chars, command) {
-                    (Some(action @ Action::ViMotion(_)), None, None)
-                    | (Some(action @ Action::ViAction(_)), None, None) => {
+                let action = match (action, chars, command, color_scheme) {
+                    (Some(action @ Action::ViMotion(_)), None, None, None)
+                    | (Some(action @ Action::ViAction(_)), None, None, None) => {
                         if !mode.intersects(TermMode::VI) || not_mode.intersects(TermMode::VI) {
                             return Err(V::Error::custom(format!(
                                 "action `{}` is only available in vi mode, try adding `mode: Vi`",
@@ -898,13 +956,16 @@ impl<'a> Deserialize<'a> for RawBinding {
                         }
                         action
                     },
-                    (Some(action), None, None) => action,
-                    (None, Some(chars), None) => Action::Esc(chars),
-                    (None, None, Some(cmd)) => Action::Command(cmd),
+                    (Some(action), None, None, None) => action,
+                    (None, Some(chars), None, None) => Action::Esc(chars),
+                    (None, None, Some(cmd), None) => Action::Command(cmd),
+                    (None, None, None, Some(scheme)) => Action::SetColorScheme(scheme),
                     _ => {
-                        return Err(V::Error::custom(
-                            "must specify exactly one of chars, action or command",
-                        ))
+                        let fields = "chars, action, command or color_scheme";
+                        return Err(V::Error::custom(format!(
+                            "must specify exactly one of {}",
+                            fields
+                        )));
                     },
                 };
 
@@ -955,6 +1016,12 @@ impl ModsWrapper {
     }
 }
 
+impl From<ModifiersState> for ModsWrapper {
+    fn from(mods: ModifiersState) -> Self {
+        ModsWrapper(mods)
+    }
+}
+
 impl<'a> de::Deserialize<'a> for ModsWrapper {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -995,10 +1062,11 @@ impl<'a> de::Deserialize<'a> for ModsWrapper {
 
 #[cfg(test)]
 mod tests {
-    use glutin::event::ModifiersState;
+    use glutin::event::{ModifiersState, MouseButton};
 
     use alacritty_terminal::term::TermMode;
 
+    use crate::config::bindings::{KeyBinding, MouseBinding};
     use crate::config::{Action, Binding};
 
     type MockBinding = Binding<usize>;
@@ -1015,6 +1083,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn mouse_binding_renderer_action() {
+        let yaml = "{ mouse: Middle, mods: Control, action: ReloadShaders }";
+        let binding: MouseBinding = serde_yaml::from_str(yaml).unwrap();
+
+        assert_eq!(binding.trigger, MouseButton::Middle);
+        assert_eq!(binding.mods, ModifiersState::CTRL);
+        assert_eq!(binding.action, Action::ReloadShaders);
+
+        let yaml = "{ key: A, action: DumpAtlas }";
+        let binding: KeyBinding = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(binding.action, Action::DumpAtlases);
+    }
+
+    #[test]
+    fn color_scheme_binding() {
+        let yaml = "{ key: F5, color_scheme: solarized }";
+        let binding: KeyBinding = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(binding.action, Action::SetColorScheme("solarized".into()));
+
+        let yaml = "{ key: F5, color_scheme: solarized, action: ToggleColorScheme }";
+        assert!(serde_yaml::from_str::<KeyBinding>(yaml).is_err());
+    }
+
     #[test]
     fn binding_matches_itself() {
         let binding = MockBinding::default();
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use alacritty_terminal::config::failure_default;
+use alacritty_terminal::term::color::Rgb;
+
+/// Highlighting of the cursor position, for finding it on large screens.
+#[serde(default)]
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CursorLocatorConfig {
+    /// Time the rings around the cursor are shown for, in milliseconds.
+    #[serde(deserialize_with = "failure_default")]
+    duration: u64,
+
+    /// Color of the rings and outline, the primary foreground color by default.
+    #[serde(deserialize_with = "failure_default")]
+    pub color: Option<Rgb>,
+
+    /// Always outline the row and column of the cursor.
+    #[serde(deserialize_with = "failure_default")]
+    pub outline: bool,
+}
+
+impl Default for CursorLocatorConfig {
+    fn default() -> Self {
+        Self { duration: 600, color: None, outline: false }
+    }
+}
+
+impl CursorLocatorConfig {
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration)
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use log::{error, LevelFilter};
 use serde::{Deserialize, Deserializer};
 
@@ -5,7 +7,7 @@ use alacritty_terminal::config::{failure_default, LOG_TARGET_CONFIG};
 
 /// Debugging options.
 #[serde(default)]
-#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Debug {
     #[serde(default = "default_log_level", deserialize_with = "deserialize_log_level")]
     pub log_level: LevelFilter,
@@ -21,6 +23,34 @@ pub struct Debug {
     #[serde(deserialize_with = "failure_default")]
     pub render_timer: bool,
 
+    /// Should show the glyph atlas textures.
+    #[serde(deserialize_with = "failure_default")]
+    pub atlas_overlay: bool,
+
+    /// Include the glyph atlas textures in diagnostic bundles.
+    #[serde(deserialize_with = "failure_default")]
+    pub bundle_atlases: bool,
+
+    /// Local port serving metrics in the Prometheus text format.
+    #[serde(deserialize_with = "failure_default")]
+    pub metrics_port: Option<u16>,
+
+    /// File receiving a snapshot of every drawn frame.
+    #[serde(deserialize_with = "failure_default")]
+    pub record_frames: Option<PathBuf>,
+
+    /// Recorded frames which are drawn instead of the terminal.
+    #[serde(skip)]
+    pub replay_frames: Option<PathBuf>,
+
+    /// Directory receiving a PNG file for every replayed frame.
+    #[serde(skip)]
+    pub replay_output: Option<PathBuf>,
+
+    /// Write a diagnostic bundle after the first frame.
+    #[serde(skip)]
+    pub diagnostic_bundle: bool,
+
     /// Record ref test.
     #[serde(skip)]
     pub ref_test: bool,
@@ -33,6 +63,13 @@ impl Default for Debug {
             print_events: Default::default(),
             persistent_logging: Default::default(),
             render_timer: Default::default(),
+            atlas_overlay: Default::default(),
+            bundle_atlases: Default::default(),
+            metrics_port: Default::default(),
+            record_frames: Default::default(),
+            replay_frames: Default::default(),
+            replay_output: Default::default(),
+            diagnostic_bundle: Default::default(),
             ref_test: Default::default(),
         }
     }
@@ -7,9 +7,7 @@ use serde::{Deserialize, Deserializer};
 
 use alacritty_terminal::config::{failure_default, LOG_TARGET_CONFIG};
 
-#[cfg(target_os = "macos")]
-use crate::config::ui_config::DefaultTrueBool;
-use crate::config::ui_config::Delta;
+use crate::config::ui_config::{DefaultTrueBool, Delta};
 
 /// Font config.
 ///
@@ -18,7 +16,7 @@ use crate::config::ui_config::Delta;
 /// each value independently. Alternatively, maybe erroring when the user
 /// doesn't provide complete config is Ok.
 #[serde(default)]
-#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct Font {
     /// Normal font face.
     #[serde(deserialize_with = "failure_default")]
@@ -36,6 +34,14 @@ pub struct Font {
     #[serde(deserialize_with = "failure_default")]
     bold_italic: SecondaryFontDescription,
 
+    /// Fonts searched in order for glyphs which are missing from the other faces.
+    #[serde(deserialize_with = "failure_default")]
+    fallback: Vec<FontDescription>,
+
+    /// Fitting of fallback glyphs which are larger than the cell.
+    #[serde(deserialize_with = "failure_default")]
+    pub fallback_sizing: FallbackSizing,
+
     /// Font size in points.
     #[serde(deserialize_with = "DeserializeSize::deserialize")]
     pub size: Size,
@@ -48,6 +54,30 @@ pub struct Font {
     #[serde(deserialize_with = "failure_default")]
     pub glyph_offset: Delta<i8>,
 
+    /// Multiplier for the advance of the font, which determines the cell width.
+    #[serde(deserialize_with = "failure_default")]
+    letter_spacing: Factor,
+
+    /// Multiplier for the line height of the font, which determines the cell height.
+    #[serde(deserialize_with = "failure_default")]
+    line_height: Factor,
+
+    /// Draw box drawing and block element characters without the font.
+    #[serde(deserialize_with = "failure_default")]
+    builtin_box_drawing: DefaultTrueBool,
+
+    /// Emulate bold and italic styles which have no face in the configured family.
+    #[serde(deserialize_with = "failure_default")]
+    builtin_synthesis: bool,
+
+    /// Maximum number of cached glyphs, zero for no limit.
+    #[serde(deserialize_with = "failure_default")]
+    pub glyph_cache_size: usize,
+
+    /// Scale factor for the thickness of underlines reported by the font.
+    #[serde(deserialize_with = "failure_default")]
+    underline_thickness: Factor,
+
     #[cfg(target_os = "macos")]
     #[serde(deserialize_with = "failure_default")]
     use_thin_strokes: DefaultTrueBool,
@@ -61,8 +91,16 @@ impl Default for Font {
             bold: Default::default(),
             italic: Default::default(),
             bold_italic: Default::default(),
+            fallback: Default::default(),
+            fallback_sizing: Default::default(),
             glyph_offset: Default::default(),
             offset: Default::default(),
+            letter_spacing: Default::default(),
+            line_height: Default::default(),
+            builtin_box_drawing: Default::default(),
+            builtin_synthesis: Default::default(),
+            glyph_cache_size: 4096,
+            underline_thickness: Default::default(),
             #[cfg(target_os = "macos")]
             use_thin_strokes: Default::default(),
         }
@@ -95,6 +133,36 @@ impl Font {
         self.bold_italic.desc(&self.normal)
     }
 
+    /// Get the fallback font descriptions, in order of priority.
+    pub fn fallback(&self) -> &[FontDescription] {
+        &self.fallback
+    }
+
+    /// Whether box drawing characters should be drawn without the font.
+    pub fn builtin_box_drawing(&self) -> bool {
+        self.builtin_box_drawing.0
+    }
+
+    /// Whether missing bold and italic faces should be synthesized from other faces.
+    pub fn builtin_synthesis(&self) -> bool {
+        self.builtin_synthesis
+    }
+
+    /// Scale factor for the cell width.
+    pub fn letter_spacing(&self) -> f32 {
+        self.letter_spacing.0.max(0.)
+    }
+
+    /// Scale factor for the cell height.
+    pub fn line_height(&self) -> f32 {
+        self.line_height.0.max(0.)
+    }
+
+    /// Scale factor for the thickness of underlines.
+    pub fn underline_thickness(&self) -> f32 {
+        self.underline_thickness.0.max(0.)
+    }
+
     #[cfg(target_os = "macos")]
     pub fn use_thin_strokes(&self) -> bool {
         self.use_thin_strokes.0
@@ -110,6 +178,30 @@ fn default_font_size() -> Size {
     Size::new(11.)
 }
 
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq)]
+struct Factor(f32);
+
+impl Default for Factor {
+    fn default() -> Self {
+        Factor(1.)
+    }
+}
+
+/// Fitting of glyphs from fallback fonts whose metrics differ from the normal font.
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FallbackSizing {
+    /// Grow the glyph atlas cells, so the glyphs are drawn at their original size.
+    Expand,
+    /// Scale glyphs down to fit the cell.
+    Scale,
+}
+
+impl Default for FallbackSizing {
+    fn default() -> Self {
+        FallbackSizing::Expand
+    }
+}
+
 /// Description of the normal font.
 #[serde(default)]
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
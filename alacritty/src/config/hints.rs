@@ -0,0 +1,68 @@
+use log::error;
+use serde::{Deserialize, Deserializer};
+
+use alacritty_terminal::config::{failure_default, LOG_TARGET_CONFIG};
+
+use crate::url::DEFAULT_PATH_ESCAPE_CHARS;
+
+/// Label characters used by default, starting with the home row.
+const DEFAULT_ALPHABET: &str = "jfkdls;ahgurieowpq";
+
+/// Keyboard selection of URLs and paths.
+#[serde(default)]
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct HintsConfig {
+    /// Characters used for the labels, in order of preference.
+    #[serde(deserialize_with = "deserialize_alphabet")]
+    alphabet: String,
+
+    /// Characters which end the paths matched by hints, in addition to whitespace.
+    #[serde(deserialize_with = "failure_default")]
+    escape_chars: HintEscapeChars,
+}
+
+impl Default for HintsConfig {
+    fn default() -> Self {
+        Self { alphabet: DEFAULT_ALPHABET.into(), escape_chars: Default::default() }
+    }
+}
+
+impl HintsConfig {
+    pub fn alphabet(&self) -> &str {
+        &self.alphabet
+    }
+
+    pub fn escape_chars(&self) -> &str {
+        &self.escape_chars.0
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+struct HintEscapeChars(String);
+
+impl Default for HintEscapeChars {
+    fn default() -> Self {
+        HintEscapeChars(String::from(DEFAULT_PATH_ESCAPE_CHARS))
+    }
+}
+
+fn deserialize_alphabet<'a, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'a>,
+{
+    let alphabet = failure_default::<D, String>(deserializer)?;
+
+    let mut chars: Vec<char> = alphabet.chars().collect();
+    chars.sort_unstable();
+    chars.dedup();
+    if chars.len() >= 2 {
+        return Ok(alphabet);
+    }
+
+    error!(
+        target: LOG_TARGET_CONFIG,
+        "Problem with config: hint alphabet needs two different characters; using {:?}",
+        DEFAULT_ALPHABET
+    );
+    Ok(DEFAULT_ALPHABET.into())
+}
@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use alacritty_terminal::config::{failure_default, Percentage};
+
+/// Dimming of the terminal after a period without input or output.
+#[serde(default)]
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct IdleConfig {
+    /// Minutes without activity before the terminal is dimmed, zero to never dim it.
+    #[serde(deserialize_with = "failure_default")]
+    timeout: u64,
+
+    /// Fraction of the brightness taken away while idle.
+    #[serde(deserialize_with = "failure_default")]
+    dim: Percentage,
+
+    /// Time the dimming fades in over, in milliseconds.
+    #[serde(deserialize_with = "failure_default")]
+    fade: u64,
+
+    /// Fragment shader run over every frame while idle, instead of the post-processing shader.
+    #[serde(deserialize_with = "failure_default")]
+    pub shader: Option<PathBuf>,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self { timeout: 0, dim: Percentage::new(0.5), fade: 1000, shader: None }
+    }
+}
+
+impl IdleConfig {
+    pub fn timeout(&self) -> Option<Duration> {
+        match self.timeout {
+            0 => None,
+            minutes => Some(Duration::from_secs(minutes * 60)),
+        }
+    }
+
+    pub fn dim(&self) -> f32 {
+        self.dim.as_f32()
+    }
+
+    pub fn fade(&self) -> Duration {
+        Duration::from_millis(self.fade)
+    }
+}
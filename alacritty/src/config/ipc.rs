@@ -0,0 +1,85 @@
+use serde::Deserialize;
+
+use alacritty_terminal::config::failure_default;
+
+use crate::config::ui_config::DefaultTrueBool;
+
+/// Classes of messages sent with `alacritty msg`.
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Permission {
+    /// Read information about the running instance.
+    Read,
+
+    /// Change the state of the terminal, like its background opacity.
+    Modify,
+
+    /// Override configuration options.
+    Config,
+
+    /// Override options which launch programs or relax security restrictions.
+    Privileged,
+}
+
+/// Socket for messages from `alacritty msg`.
+#[serde(default)]
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct IpcConfig {
+    /// Listen for messages on a socket.
+    #[serde(deserialize_with = "failure_default")]
+    socket: DefaultTrueBool,
+
+    /// Password every message has to be sent with.
+    #[serde(deserialize_with = "failure_default")]
+    pub password: Option<String>,
+
+    /// Classes of messages which are accepted.
+    #[serde(deserialize_with = "failure_default")]
+    pub permissions: Vec<Permission>,
+}
+
+impl Default for IpcConfig {
+    fn default() -> Self {
+        Self {
+            socket: Default::default(),
+            password: None,
+            permissions: vec![Permission::Read, Permission::Modify, Permission::Config],
+        }
+    }
+}
+
+impl IpcConfig {
+    #[inline]
+    pub fn socket(&self) -> bool {
+        self.socket.0
+    }
+
+    /// Check the password and permission of a message.
+    pub fn authorize(&self, password: Option<&str>, permission: Permission) -> Result<(), String> {
+        if self.password.is_some() && self.password.as_deref() != password {
+            return Err(String::from("Wrong password, set it in ALACRITTY_IPC_PASSWORD"));
+        }
+
+        if !self.permissions.contains(&permission) {
+            return Err(format!("{:?} messages are not permitted by ipc.permissions", permission));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn authorize_messages() {
+        let mut config = IpcConfig::default();
+        assert_eq!(config.authorize(None, Permission::Config), Ok(()));
+        assert!(config.authorize(None, Permission::Privileged).is_err());
+
+        config.password = Some(String::from("secret"));
+        assert!(config.authorize(None, Permission::Read).is_err());
+        assert!(config.authorize(Some("guess"), Permission::Read).is_err());
+        assert_eq!(config.authorize(Some("secret"), Permission::Read), Ok(()));
+    }
+}
@@ -0,0 +1,30 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use alacritty_terminal::config::failure_default;
+
+/// Overlay showing recently pressed keys.
+#[serde(default)]
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeyOverlayConfig {
+    /// Show the overlay.
+    #[serde(deserialize_with = "failure_default")]
+    pub enabled: bool,
+
+    /// Time each key is shown for, in milliseconds.
+    #[serde(deserialize_with = "failure_default")]
+    duration: u64,
+}
+
+impl Default for KeyOverlayConfig {
+    fn default() -> Self {
+        Self { enabled: false, duration: 1500 }
+    }
+}
+
+impl KeyOverlayConfig {
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration)
+    }
+}
@@ -9,10 +9,20 @@ use serde_yaml::Value;
 
 use alacritty_terminal::config::{Config as TermConfig, LOG_TARGET_CONFIG};
 
+pub mod animations;
+pub mod background_image;
+pub mod cursor_locator;
 pub mod debug;
 pub mod font;
+pub mod hints;
+pub mod idle;
+pub mod ipc;
+pub mod key_overlay;
 pub mod monitor;
+pub mod renderer;
 pub mod serde_utils;
+pub mod theme;
+pub mod title;
 pub mod ui_config;
 pub mod window;
 
@@ -119,10 +129,15 @@ pub fn load(options: &Options) -> Config {
 }
 
 /// Attempt to reload the configuration file.
-pub fn reload(config_path: &PathBuf, options: &Options) -> Result<Config> {
+///
+/// Without a configuration file, only the CLI options are applied to the default config.
+pub fn reload(config_path: Option<&PathBuf>, options: &Options) -> Result<Config> {
     // Load config, propagating errors.
     let config_options = options.config_options().clone();
-    let mut config = load_from(&config_path, config_options)?;
+    let mut config = match config_path {
+        Some(config_path) => load_from(config_path, config_options)?,
+        None => Config::deserialize(config_options).unwrap_or_default(),
+    };
 
     // Override config with CLI options.
     options.override_config(&mut config);
@@ -7,6 +7,7 @@ use serde::{Deserialize, Deserializer};
 use alacritty_terminal::config::{failure_default, Program, LOG_TARGET_CONFIG};
 
 use crate::config::bindings::ModsWrapper;
+use crate::config::ui_config::DefaultTrueBool;
 
 #[serde(default)]
 #[derive(Default, Clone, Debug, Deserialize, PartialEq, Eq)]
@@ -31,12 +32,28 @@ pub struct Url {
     /// Modifier used to open links.
     #[serde(deserialize_with = "failure_default")]
     modifiers: ModsWrapper,
+
+    /// Modifier used to open OSC 8 hyperlinks.
+    #[serde(deserialize_with = "failure_default")]
+    hyperlink_modifiers: ModsWrapper,
+
+    /// Detect file system paths in addition to URLs.
+    #[serde(deserialize_with = "failure_default")]
+    paths: DefaultTrueBool,
 }
 
 impl Url {
     pub fn mods(&self) -> ModifiersState {
         self.modifiers.into_inner()
     }
+
+    pub fn hyperlink_mods(&self) -> ModifiersState {
+        self.hyperlink_modifiers.into_inner()
+    }
+
+    pub fn paths(&self) -> bool {
+        self.paths.0
+    }
 }
 
 fn deserialize_launcher<'a, D>(deserializer: D) -> std::result::Result<Option<Program>, D::Error>
@@ -77,6 +94,8 @@ impl Default for Url {
             #[cfg(windows)]
             launcher: Some(Program::Just(String::from("explorer"))),
             modifiers: Default::default(),
+            hyperlink_modifiers: ModifiersState::CTRL.into(),
+            paths: Default::default(),
         }
     }
 }
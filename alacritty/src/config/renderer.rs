@@ -0,0 +1,63 @@
+use serde::Deserialize;
+
+use alacritty_terminal::config::failure_default;
+
+/// Glyph atlas limits and text effects of the renderer.
+#[serde(default)]
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RendererConfig {
+    /// Width and height of the atlas textures used by the grid renderer.
+    #[serde(deserialize_with = "failure_default")]
+    pub grid_atlas_size: i32,
+
+    /// Maximum number of grid rendering passes, zero for no limit.
+    #[serde(deserialize_with = "failure_default")]
+    pub max_grid_passes: usize,
+
+    /// Width and height of the atlas textures used for glyphs which don't fit the grid.
+    #[serde(deserialize_with = "failure_default")]
+    pub atlas_size: i32,
+
+    /// Blink text with the SGR 5 and 6 attributes.
+    #[serde(deserialize_with = "failure_default")]
+    pub text_blinking: bool,
+
+    /// Maximum number of blinking cells on screen, text stops blinking above it.
+    #[serde(deserialize_with = "failure_default")]
+    pub max_blinking_cells: usize,
+
+    /// Synchronize buffer swaps with the monitor refresh rate.
+    #[serde(deserialize_with = "failure_default")]
+    pub vsync: bool,
+
+    /// Keep cell sizes, glyph origins and lines on whole pixels.
+    #[serde(deserialize_with = "failure_default")]
+    pub integer_scaling: bool,
+
+    /// Bytes of output between two frames above which frames are drawn without quads and
+    /// zero-width characters, zero to always draw every glyph.
+    #[serde(deserialize_with = "failure_default")]
+    pub fast_output_threshold: u64,
+
+    /// Join Arabic letters using their presentation forms, when built with bidi support.
+    #[serde(deserialize_with = "failure_default")]
+    pub arabic_shaping: bool,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        // A 1024x1024 atlas holds all glyphs of mostly ASCII content at reasonable font sizes,
+        // while taking up only 4MB of video memory.
+        Self {
+            grid_atlas_size: 1024,
+            max_grid_passes: 0,
+            atlas_size: 1024,
+            text_blinking: true,
+            max_blinking_cells: 4096,
+            vsync: true,
+            integer_scaling: false,
+            fast_output_threshold: 0,
+            arabic_shaping: true,
+        }
+    }
+}
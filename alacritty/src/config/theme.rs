@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use alacritty_terminal::config::{failure_default, Colors};
+
+use crate::appearance::Appearance;
+use crate::config::Config;
+
+/// Named color schemes and the ones used while following the desktop's light/dark preference.
+#[serde(default)]
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    /// Colors used when the desktop prefers a light color scheme.
+    #[serde(deserialize_with = "failure_default")]
+    pub light: Option<Colors>,
+
+    /// Colors used when the desktop prefers a dark color scheme.
+    #[serde(deserialize_with = "failure_default")]
+    pub dark: Option<Colors>,
+
+    /// Color schemes which can be selected with key bindings.
+    #[serde(deserialize_with = "failure_default")]
+    pub schemes: BTreeMap<String, Colors>,
+
+    /// Duration of the color cross-fade in milliseconds.
+    #[serde(deserialize_with = "failure_default")]
+    transition_duration: u16,
+
+    /// Apply color changes instantly instead of cross-fading them.
+    #[serde(deserialize_with = "failure_default")]
+    reduce_motion: bool,
+
+    /// Colors from the top-level `colors` section.
+    #[serde(skip)]
+    default_colors: Option<Colors>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            light: None,
+            dark: None,
+            schemes: BTreeMap::new(),
+            transition_duration: 200,
+            reduce_motion: false,
+            default_colors: None,
+        }
+    }
+}
+
+impl Theme {
+    /// Duration of the cross-fade when the colors change.
+    pub fn transition_duration(&self) -> Duration {
+        if self.reduce_motion {
+            return Duration::default();
+        }
+
+        Duration::from_millis(u64::from(self.transition_duration))
+    }
+
+    /// Whether any scheme depends on the desktop appearance.
+    pub fn follows_appearance(&self) -> bool {
+        self.light.is_some() || self.dark.is_some()
+    }
+
+    /// Name of the scheme following `current`, `None` for the top-level `colors`.
+    ///
+    /// Schemes are cycled in alphabetical order, starting and ending with the top-level `colors`.
+    pub fn next_scheme(&self, current: Option<&str>) -> Option<String> {
+        let mut names = self.schemes.keys();
+        match current {
+            Some(current) => names.skip_while(|name| name.as_str() != current).nth(1).cloned(),
+            None => names.next().cloned(),
+        }
+    }
+}
+
+/// Switch the active colors to the selected scheme or the one matching the desktop appearance.
+///
+/// A selected `scheme` takes precedence over the desktop appearance. Without either of them, or
+/// when they have no scheme of their own, the top-level `colors` are used.
+pub fn apply_colors(config: &mut Config, scheme: Option<&str>, appearance: Option<Appearance>) {
+    let theme = &mut config.ui_config.theme;
+    if theme.default_colors.is_none() {
+        theme.default_colors = Some(config.colors.clone());
+    }
+
+    let appearance_colors = match appearance {
+        Some(Appearance::Light) => theme.light.as_ref(),
+        Some(Appearance::Dark) => theme.dark.as_ref(),
+        None => None,
+    };
+
+    let colors = scheme
+        .and_then(|scheme| theme.schemes.get(scheme))
+        .or(appearance_colors)
+        .or_else(|| theme.default_colors.as_ref());
+
+    if let Some(colors) = colors {
+        config.colors = colors.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycle_schemes() {
+        let mut theme = Theme::default();
+        assert_eq!(theme.next_scheme(None), None);
+
+        theme.schemes.insert("solarized".into(), Colors::default());
+        theme.schemes.insert("gruvbox".into(), Colors::default());
+
+        assert_eq!(theme.next_scheme(None), Some("gruvbox".into()));
+        assert_eq!(theme.next_scheme(Some("gruvbox")), Some("solarized".into()));
+        assert_eq!(theme.next_scheme(Some("solarized")), None);
+        assert_eq!(theme.next_scheme(Some("removed")), None);
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use alacritty_terminal::config::failure_default;
+
+/// Limits for window titles set by terminal applications.
+#[serde(default)]
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TitleConfig {
+    /// Maximum number of characters, zero for no limit.
+    #[serde(deserialize_with = "failure_default")]
+    pub max_length: usize,
+
+    /// Maximum number of title updates per second, zero for no limit.
+    #[serde(deserialize_with = "failure_default")]
+    max_update_rate: u32,
+}
+
+impl Default for TitleConfig {
+    fn default() -> Self {
+        Self { max_length: 0, max_update_rate: 20 }
+    }
+}
+
+impl TitleConfig {
+    /// Minimum time between two title updates.
+    pub fn update_interval(&self) -> Option<Duration> {
+        match self.max_update_rate {
+            0 => None,
+            rate => Some(Duration::from_secs(1) / rate),
+        }
+    }
+
+    /// Strip control characters and shorten the title to `max_length` characters.
+    pub fn sanitize(&self, title: &str) -> String {
+        let mut chars = title.chars().filter(|c| !c.is_control());
+        if self.max_length == 0 {
+            return chars.collect();
+        }
+
+        let mut sanitized: String = chars.by_ref().take(self.max_length).collect();
+        if chars.next().is_some() {
+            sanitized.pop();
+            sanitized.push('…');
+        }
+        sanitized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize() {
+        let mut config = TitleConfig::default();
+        assert_eq!(config.sanitize("vim\x07 \x1b[1mfile\u{9b}"), "vim [1mfile");
+
+        config.max_length = 5;
+        assert_eq!(config.sanitize("short"), "short");
+        assert_eq!(config.sanitize("longer"), "long…");
+        assert_eq!(config.sanitize("sh\tort"), "short");
+    }
+
+    #[test]
+    fn update_interval() {
+        let mut config = TitleConfig::default();
+        assert_eq!(config.update_interval(), Some(Duration::from_millis(50)));
+
+        config.max_update_rate = 0;
+        assert_eq!(config.update_interval(), None);
+    }
+}
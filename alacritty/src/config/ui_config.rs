@@ -5,10 +5,20 @@ use serde::{Deserialize, Deserializer};
 
 use alacritty_terminal::config::{failure_default, Percentage, LOG_TARGET_CONFIG};
 
+use crate::config::animations::Animations;
+use crate::config::background_image::BackgroundImage;
 use crate::config::bindings::{self, Binding, KeyBinding, MouseBinding};
+use crate::config::cursor_locator::CursorLocatorConfig;
 use crate::config::debug::Debug;
 use crate::config::font::Font;
+use crate::config::hints::HintsConfig;
+use crate::config::idle::IdleConfig;
+use crate::config::ipc::IpcConfig;
+use crate::config::key_overlay::KeyOverlayConfig;
 use crate::config::mouse::Mouse;
+use crate::config::renderer::RendererConfig;
+use crate::config::theme::Theme;
+use crate::config::title::TitleConfig;
 use crate::config::window::WindowConfig;
 
 #[derive(Debug, PartialEq, Deserialize)]
@@ -36,6 +46,22 @@ pub struct UIConfig {
     #[serde(default, deserialize_with = "failure_default")]
     pub debug: Debug,
 
+    /// Glyph atlas limits.
+    #[serde(default, deserialize_with = "failure_default")]
+    pub renderer: RendererConfig,
+
+    /// Overlay showing recently pressed keys.
+    #[serde(default, deserialize_with = "failure_default")]
+    pub key_overlay: KeyOverlayConfig,
+
+    /// Dimming after a period without input or output.
+    #[serde(default, deserialize_with = "failure_default")]
+    pub idle: IdleConfig,
+
+    /// Limits for titles set by terminal applications.
+    #[serde(default, deserialize_with = "failure_default")]
+    pub title: TitleConfig,
+
     /// Send escape sequences using the alt key.
     #[serde(default, deserialize_with = "failure_default")]
     alt_send_esc: DefaultTrueBool,
@@ -44,10 +70,42 @@ pub struct UIConfig {
     #[serde(default, deserialize_with = "failure_default")]
     live_config_reload: DefaultTrueBool,
 
+    /// Socket for messages from `alacritty msg`.
+    #[serde(default, deserialize_with = "failure_default")]
+    pub ipc: IpcConfig,
+
     /// Background opacity from 0.0 to 1.0.
     #[serde(default, deserialize_with = "failure_default")]
     background_opacity: Percentage,
 
+    /// Image drawn behind the terminal grid.
+    #[serde(default, deserialize_with = "failure_default")]
+    pub background_image: Option<BackgroundImage>,
+
+    /// Resolution frames are rendered at, relative to the window.
+    #[serde(default, deserialize_with = "failure_default")]
+    render_scale: RenderScale,
+
+    /// Fragment shader run over every rendered frame.
+    #[serde(default, deserialize_with = "failure_default")]
+    pub post_processing_shader: Option<PathBuf>,
+
+    /// Light and dark color schemes.
+    #[serde(default, deserialize_with = "failure_default")]
+    pub theme: Theme,
+
+    /// Switch for animated transitions.
+    #[serde(default, deserialize_with = "failure_default")]
+    pub animations: Animations,
+
+    /// Keyboard selection of URLs and paths.
+    #[serde(default, deserialize_with = "failure_default")]
+    pub hints: HintsConfig,
+
+    /// Highlighting of the cursor position.
+    #[serde(default, deserialize_with = "failure_default")]
+    pub cursor_locator: CursorLocatorConfig,
+
     /// Path where config was loaded from.
     #[serde(skip)]
     pub config_paths: Vec<PathBuf>,
@@ -66,9 +124,21 @@ impl Default for UIConfig {
             key_bindings: default_key_bindings(),
             mouse_bindings: default_mouse_bindings(),
             debug: Default::default(),
+            renderer: Default::default(),
+            key_overlay: Default::default(),
+            idle: Default::default(),
+            title: Default::default(),
             alt_send_esc: Default::default(),
             background_opacity: Default::default(),
+            background_image: Default::default(),
+            render_scale: Default::default(),
+            post_processing_shader: Default::default(),
+            theme: Default::default(),
+            animations: Default::default(),
+            hints: Default::default(),
+            cursor_locator: Default::default(),
             live_config_reload: Default::default(),
+            ipc: Default::default(),
             dynamic_title: Default::default(),
             config_paths: Default::default(),
         }
@@ -81,6 +151,26 @@ impl UIConfig {
         self.background_opacity.as_f32()
     }
 
+    #[inline]
+    pub fn render_scale(&self) -> f32 {
+        let scale = self.render_scale.0.max(MIN_RENDER_SCALE).min(MAX_RENDER_SCALE);
+
+        // Fractional scales would resample every glyph when the frame is scaled to the window.
+        if self.renderer.integer_scaling {
+            scale.round().max(1.)
+        } else {
+            scale
+        }
+    }
+
+    /// Spread out additional padding evenly.
+    ///
+    /// Integer scaling always centers the grid, so the cells don't start at a fractional offset.
+    #[inline]
+    pub fn dynamic_padding(&self) -> bool {
+        self.window.dynamic_padding || self.renderer.integer_scaling
+    }
+
     #[inline]
     pub fn dynamic_title(&self) -> bool {
         self.dynamic_title.unwrap_or_else(|| self.window.dynamic_title())
@@ -163,6 +253,19 @@ where
     Ok(bindings)
 }
 
+/// Smallest and largest supported render scale.
+const MIN_RENDER_SCALE: f32 = 0.25;
+const MAX_RENDER_SCALE: f32 = 4.;
+
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq)]
+struct RenderScale(f32);
+
+impl Default for RenderScale {
+    fn default() -> Self {
+        RenderScale(1.)
+    }
+}
+
 #[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
 pub struct DefaultTrueBool(pub bool);
 
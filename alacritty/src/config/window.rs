@@ -5,8 +5,11 @@ use log::error;
 use serde::{Deserialize, Deserializer};
 use serde_yaml::Value;
 
-use alacritty_terminal::config::{failure_default, option_explicit_none, LOG_TARGET_CONFIG};
+use alacritty_terminal::config::{
+    failure_default, option_explicit_none, Percentage, LOG_TARGET_CONFIG,
+};
 use alacritty_terminal::index::{Column, Line};
+use alacritty_terminal::term::color::Rgb;
 
 use crate::config::ui_config::{DefaultTrueBool, Delta};
 
@@ -14,7 +17,7 @@ use crate::config::ui_config::{DefaultTrueBool, Delta};
 pub const DEFAULT_NAME: &str = "Alacritty";
 
 #[serde(default)]
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct WindowConfig {
     /// Initial position.
     #[serde(deserialize_with = "failure_default")]
@@ -24,6 +27,10 @@ pub struct WindowConfig {
     #[serde(deserialize_with = "failure_default")]
     pub decorations: Decorations,
 
+    /// Colors of the client side decorations on Wayland.
+    #[serde(deserialize_with = "failure_default")]
+    pub decorations_theme: DecorationsTheme,
+
     /// Startup mode.
     #[serde(deserialize_with = "failure_default")]
     pub startup_mode: StartupMode,
@@ -52,6 +59,14 @@ pub struct WindowConfig {
     #[serde(deserialize_with = "failure_default")]
     padding: Delta<u8>,
 
+    /// Number of columns left of the grid reserved for marks.
+    #[serde(deserialize_with = "failure_default")]
+    pub gutter: usize,
+
+    /// Number of columns right of the grid reserved for the minimap.
+    #[serde(deserialize_with = "failure_default")]
+    pub minimap: usize,
+
     /// Use dynamic title.
     #[serde(default, deserialize_with = "failure_default")]
     dynamic_title: DefaultTrueBool,
@@ -59,6 +74,18 @@ pub struct WindowConfig {
     /// Initial dimensions.
     #[serde(deserialize_with = "failure_default")]
     dimensions: Dimensions,
+
+    /// Font size adjustment to keep the number of columns while resizing.
+    #[serde(deserialize_with = "failure_default")]
+    fit: Fit,
+
+    /// Confirmation before closing the window while a program is running.
+    #[serde(deserialize_with = "failure_default")]
+    pub confirm_close: ConfirmClose,
+
+    /// Fraction of the brightness taken away while the window is not focused.
+    #[serde(deserialize_with = "failure_default")]
+    unfocused_dim: Percentage,
 }
 
 pub fn default_title() -> String {
@@ -93,6 +120,17 @@ impl WindowConfig {
         }
     }
 
+    /// Number of columns the font size is adjusted to, `None` to keep the font size.
+    #[inline]
+    pub fn fit_columns(&self) -> Option<usize> {
+        Some(self.fit.columns).filter(|&columns| columns != 0)
+    }
+
+    #[inline]
+    pub fn unfocused_dim(&self) -> f32 {
+        self.unfocused_dim.as_f32()
+    }
+
     #[inline]
     pub fn padding(&self, dpr: f64) -> (f32, f32) {
         let padding_x = (f32::from(self.padding.x) * dpr as f32).floor();
@@ -119,9 +157,13 @@ impl Default for WindowConfig {
     fn default() -> WindowConfig {
         WindowConfig {
             dimensions: Default::default(),
+            fit: Default::default(),
             position: Default::default(),
             padding: Default::default(),
+            gutter: Default::default(),
+            minimap: Default::default(),
             decorations: Default::default(),
+            decorations_theme: Default::default(),
             dynamic_padding: Default::default(),
             startup_mode: Default::default(),
             class: Default::default(),
@@ -129,10 +171,21 @@ impl Default for WindowConfig {
             gtk_theme_variant: Default::default(),
             title: default_title(),
             dynamic_title: Default::default(),
+            confirm_close: Default::default(),
+            unfocused_dim: Percentage::new(0.),
         }
     }
 }
 
+/// Font size adjustment on window resize.
+#[serde(default)]
+#[derive(Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Fit {
+    /// Number of columns which fill the window, zero to disable the adjustment.
+    #[serde(deserialize_with = "failure_default")]
+    columns: usize,
+}
+
 #[derive(Debug, Deserialize, Copy, Clone, PartialEq, Eq)]
 pub enum StartupMode {
     Windowed,
@@ -168,6 +221,69 @@ impl Default for Decorations {
     }
 }
 
+/// Client side decorations drawn on Wayland compositors without server side decorations.
+#[serde(default)]
+#[derive(Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DecorationsTheme {
+    /// Title bar color, the search bar background by default.
+    #[serde(deserialize_with = "failure_default")]
+    pub background: Option<Rgb>,
+
+    /// Title and button color, the search bar foreground by default.
+    #[serde(deserialize_with = "failure_default")]
+    pub foreground: Option<Rgb>,
+
+    /// Color of the hovered close button, the normal red by default.
+    #[serde(deserialize_with = "failure_default")]
+    pub close_button: Option<Rgb>,
+
+    /// Color of the hovered maximize button, the normal green by default.
+    #[serde(deserialize_with = "failure_default")]
+    pub maximize_button: Option<Rgb>,
+
+    /// Color of the hovered minimize button, the normal yellow by default.
+    #[serde(deserialize_with = "failure_default")]
+    pub minimize_button: Option<Rgb>,
+
+    /// Draw the window title in the title bar.
+    #[serde(deserialize_with = "failure_default")]
+    show_title: DefaultTrueBool,
+}
+
+impl DecorationsTheme {
+    #[inline]
+    pub fn show_title(&self) -> bool {
+        self.show_title.0
+    }
+}
+
+/// Confirmation before closing the window.
+#[serde(default)]
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmClose {
+    /// Ask for confirmation while a foreground process is running.
+    #[serde(deserialize_with = "failure_default")]
+    pub enabled: bool,
+
+    /// Foreground processes which never need confirmation.
+    #[serde(deserialize_with = "failure_default")]
+    pub ignore: Vec<String>,
+}
+
+impl Default for ConfirmClose {
+    fn default() -> Self {
+        let ignore = ["sh", "bash", "zsh", "fish", "dash", "ksh", "tcsh", "csh", "nu"];
+        Self { enabled: false, ignore: ignore.iter().map(|name| name.to_string()).collect() }
+    }
+}
+
+impl ConfirmClose {
+    /// Whether closing the window while `process` is in the foreground needs confirmation.
+    pub fn required(&self, process: &str) -> bool {
+        self.enabled && !self.ignore.iter().any(|name| name == process)
+    }
+}
+
 /// Window Dimensions.
 ///
 /// Newtype to avoid passing values incorrectly.
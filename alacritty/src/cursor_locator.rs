@@ -0,0 +1,162 @@
+//! Rings around the terminal cursor and an outline of its row and column, for finding the cursor
+//! on large screens.
+
+use std::time::{Duration, Instant};
+
+use alacritty_terminal::index::Point;
+use alacritty_terminal::term::color::Rgb;
+use alacritty_terminal::term::SizeInfo;
+
+use crate::renderer::rects::RenderRect;
+
+/// Number of rings drawn around the cursor.
+const RINGS: usize = 3;
+
+/// Distance of the outermost ring from the cursor when the rings appear, in cell heights.
+const MAX_RADIUS: f32 = 8.;
+
+/// Animation pointing out the cursor.
+#[derive(Debug, Default)]
+pub struct CursorLocator {
+    started: Option<Instant>,
+}
+
+impl CursorLocator {
+    pub fn start(&mut self, now: Instant) {
+        self.started = Some(now);
+    }
+
+    /// Whether the rings are still shown, including the frame which removes them.
+    pub fn is_active(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Rings around `point` at `now`, `None` once `duration` has passed.
+    ///
+    /// Without `animated`, the rings stay in place until they disappear.
+    pub fn overlay(
+        &mut self,
+        point: Point,
+        duration: Duration,
+        animated: bool,
+        now: Instant,
+    ) -> Option<LocatorOverlay> {
+        let elapsed = now.saturating_duration_since(self.started?);
+        if elapsed >= duration {
+            self.started = None;
+            return None;
+        }
+
+        let progress = if animated { elapsed.as_secs_f32() / duration.as_secs_f32() } else { 0. };
+        Some(LocatorOverlay { point, progress })
+    }
+}
+
+/// Rings drawn for a single frame of the animation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LocatorOverlay {
+    point: Point,
+
+    /// Fraction of the animation which has passed.
+    progress: f32,
+}
+
+impl LocatorOverlay {
+    /// Rings shrinking towards the cursor cell, fading out as they reach it.
+    pub fn rects(&self, size_info: &SizeInfo, color: Rgb) -> Vec<RenderRect> {
+        let (x, y, width, height) = cell_pixels(self.point, size_info);
+        let thickness = thickness(size_info);
+        let alpha = 1. - self.progress * self.progress;
+
+        let mut rects = Vec::new();
+        for ring in 1..=RINGS {
+            let distance = MAX_RADIUS * ring as f32 / RINGS as f32 * size_info.cell_height();
+            let radius = (1. - self.progress) * distance;
+            let (ring_width, ring_height) = (width + 2. * radius, height + 2. * radius);
+            let sides = outline(x - radius, y - radius, ring_width, ring_height, thickness);
+            for &(x, y, w, h) in &sides {
+                rects.push(RenderRect::new(x, y, w, h, color, alpha));
+            }
+        }
+
+        rects
+    }
+}
+
+/// Outline around the full row and column of the cursor at `point`.
+pub fn outline_rects(point: Point, size_info: &SizeInfo, color: Rgb) -> Vec<RenderRect> {
+    let (x, y, width, height) = cell_pixels(point, size_info);
+    let thickness = thickness(size_info);
+
+    let row_width = size_info.cols().0 as f32 * size_info.cell_width();
+    let column_height = size_info.screen_lines().0 as f32 * size_info.cell_height();
+
+    let row = outline(size_info.padding_left(), y, row_width, height, thickness);
+    let column = outline(x, size_info.padding_y(), width, column_height, thickness);
+
+    row.iter()
+        .chain(&column)
+        .map(|&(x, y, width, height)| RenderRect::new(x, y, width, height, color, 1.))
+        .collect()
+}
+
+/// Position and size of the cell at `point` in pixels.
+fn cell_pixels(point: Point, size_info: &SizeInfo) -> (f32, f32, f32, f32) {
+    let x = size_info.padding_left() + point.col.0 as f32 * size_info.cell_width();
+    let y = size_info.padding_y() + point.line.0 as f32 * size_info.cell_height();
+    (x, y, size_info.cell_width(), size_info.cell_height())
+}
+
+/// Line width of the rings and outline, growing with the font size.
+fn thickness(size_info: &SizeInfo) -> f32 {
+    (size_info.cell_width() / 4.).round().max(2.)
+}
+
+/// Sides of a rectangle outline, without overlapping corners.
+fn outline(x: f32, y: f32, width: f32, height: f32, thickness: f32) -> [(f32, f32, f32, f32); 4] {
+    let inner_height = height - 2. * thickness;
+    [
+        (x, y, width, thickness),
+        (x + width - thickness, y + thickness, thickness, inner_height),
+        (x, y + height - thickness, width, thickness),
+        (x, y + thickness, thickness, inner_height),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alacritty_terminal::index::{Column, Line};
+
+    #[test]
+    fn locator_disappears() {
+        let mut locator = CursorLocator::default();
+        let point = Point::new(Line(1), Column(2));
+        let duration = Duration::from_millis(100);
+        let start = Instant::now();
+
+        assert_eq!(locator.overlay(point, duration, true, start), None);
+
+        locator.start(start);
+        let overlay = locator.overlay(point, duration, true, start + duration / 2);
+        assert_eq!(overlay, Some(LocatorOverlay { point, progress: 0.5 }));
+        let overlay = locator.overlay(point, duration, false, start + duration / 2);
+        assert_eq!(overlay, Some(LocatorOverlay { point, progress: 0. }));
+        assert!(locator.is_active());
+
+        assert_eq!(locator.overlay(point, duration, true, start + duration), None);
+        assert!(!locator.is_active());
+    }
+
+    #[test]
+    fn cursor_outline() {
+        let size = SizeInfo::new(100., 100., 10., 20., 5., 5., false);
+        let point = Point::new(Line(1), Column(2));
+        let rects = outline_rects(point, &size, Rgb::default());
+
+        // Top of the row and left side of the column.
+        assert_eq!((rects[0].x, rects[0].y, rects[0].width), (5., 25., 90.));
+        assert_eq!((rects[7].x, rects[7].y, rects[7].height), (25., 8., 74.));
+    }
+}
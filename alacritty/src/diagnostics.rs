@@ -0,0 +1,192 @@
+//! Diagnostic bundles attached to bug reports about rendering artifacts.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Maximum number of bytes included from the end of the log file.
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// MS-DOS date of the 1st of January 1980, the earliest date a zip archive can store.
+const DOS_EPOCH: u16 = 0x21;
+
+/// Collection of named files written into an uncompressed zip archive.
+#[derive(Debug, Default)]
+pub struct Bundle {
+    entries: Vec<(String, Vec<u8>)>,
+}
+
+impl Bundle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file to the bundle.
+    pub fn add(&mut self, name: impl Into<String>, data: impl Into<Vec<u8>>) {
+        self.entries.push((name.into(), data.into()));
+    }
+
+    /// Add the end of the current log file, if anything has been logged to it yet.
+    pub fn add_log(&mut self) {
+        let path = match env::var_os(crate::logging::ALACRITTY_LOG_ENV) {
+            Some(path) => PathBuf::from(path),
+            None => return,
+        };
+
+        match read_tail(&path, MAX_LOG_SIZE) {
+            Ok(log) => self.add("alacritty.log", log),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => self.add("alacritty.log", format!("Unable to read {:?}: {}", path, err)),
+        }
+    }
+
+    /// Write the bundle into a zip file at `path`.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.write_zip(&mut file)?;
+        file.flush()
+    }
+
+    /// Write all files as a zip archive without compression.
+    fn write_zip<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut central_directory = Vec::new();
+        let mut offset = 0u32;
+
+        for (name, data) in &self.entries {
+            let crc = crc32(data);
+            let size = data.len() as u32;
+            let name_len = name.len() as u16;
+
+            // Local file header.
+            let mut header = Vec::with_capacity(30 + name.len());
+            put_u32(&mut header, 0x0403_4b50);
+            put_common_header(&mut header, crc, size, name_len);
+            put_u16(&mut header, 0);
+            header.extend_from_slice(name.as_bytes());
+            writer.write_all(&header)?;
+            writer.write_all(data)?;
+
+            // Central directory header.
+            put_u32(&mut central_directory, 0x0201_4b50);
+            put_u16(&mut central_directory, 20);
+            put_common_header(&mut central_directory, crc, size, name_len);
+            // Extra field, comment, disk number and file attributes.
+            central_directory.extend_from_slice(&[0; 12]);
+            put_u32(&mut central_directory, offset);
+            central_directory.extend_from_slice(name.as_bytes());
+
+            offset += (header.len() + data.len()) as u32;
+        }
+
+        // End of central directory record.
+        let count = self.entries.len() as u16;
+        let mut end = Vec::with_capacity(22);
+        put_u32(&mut end, 0x0605_4b50);
+        put_u32(&mut end, 0);
+        put_u16(&mut end, count);
+        put_u16(&mut end, count);
+        put_u32(&mut end, central_directory.len() as u32);
+        put_u32(&mut end, offset);
+        put_u16(&mut end, 0);
+
+        writer.write_all(&central_directory)?;
+        writer.write_all(&end)
+    }
+}
+
+/// Fields shared by the local and central headers, up to the file name length.
+fn put_common_header(buf: &mut Vec<u8>, crc: u32, size: u32, name_len: u16) {
+    // Version needed, flags, compression method and modification time.
+    put_u16(buf, 20);
+    buf.extend_from_slice(&[0; 6]);
+    put_u16(buf, DOS_EPOCH);
+    put_u32(buf, crc);
+    put_u32(buf, size);
+    put_u32(buf, size);
+    put_u16(buf, name_len);
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+/// CRC-32 checksum, as used by zip archives.
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut crc = i as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+        *entry = crc;
+    }
+
+    !data.iter().fold(!0, |crc, &byte| table[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+/// Read at most `max` bytes from the end of a file.
+fn read_tail(path: &Path, max: u64) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let len = fs::metadata(path)?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(max)))?;
+
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn zip_layout() {
+        let mut bundle = Bundle::new();
+        bundle.add("a.txt", "alpha");
+        bundle.add("b/c.txt", vec![1, 2, 3]);
+
+        let mut zip = Vec::new();
+        bundle.write_zip(&mut zip).unwrap();
+
+        let u16_at = |i: usize| u16::from_le_bytes([zip[i], zip[i + 1]]);
+        let u32_at = |i: usize| u32::from_le_bytes([zip[i], zip[i + 1], zip[i + 2], zip[i + 3]]);
+
+        // First local header is followed by its name and data.
+        assert_eq!(u32_at(0), 0x0403_4b50);
+        assert_eq!(u32_at(14), crc32(b"alpha"));
+        assert_eq!(u32_at(18), 5);
+        assert_eq!(&zip[30..35], b"a.txt");
+        assert_eq!(&zip[35..40], b"alpha");
+
+        // Second local header starts right after the first entry.
+        assert_eq!(u32_at(40), 0x0403_4b50);
+        assert_eq!(&zip[70..77], b"b/c.txt");
+        assert_eq!(&zip[77..80], &[1, 2, 3]);
+
+        // End of central directory points back at both entries.
+        let end = zip.len() - 22;
+        assert_eq!(u32_at(end), 0x0605_4b50);
+        assert_eq!(u16_at(end + 10), 2);
+        let directory_offset = u32_at(end + 16) as usize;
+        assert_eq!(directory_offset, 80);
+        assert_eq!(u32_at(end + 12) as usize, end - directory_offset);
+
+        assert_eq!(u32_at(directory_offset), 0x0201_4b50);
+        assert_eq!(u32_at(directory_offset + 42), 0);
+        assert_eq!(&zip[directory_offset + 46..directory_offset + 51], b"a.txt");
+        let second = directory_offset + 51;
+        assert_eq!(u32_at(second), 0x0201_4b50);
+        assert_eq!(u32_at(second + 42), 40);
+    }
+}
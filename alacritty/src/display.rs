@@ -2,11 +2,15 @@
 //! GPU drawing.
 
 use std::cmp::min;
+use std::env;
 use std::f64;
 use std::fmt::{self, Formatter};
+use std::path::PathBuf;
+use std::process;
 #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
 use std::sync::atomic::Ordering;
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use glutin::dpi::{PhysicalPosition, PhysicalSize};
 use glutin::event::ModifiersState;
@@ -14,38 +18,64 @@ use glutin::event_loop::EventLoop;
 #[cfg(not(any(target_os = "macos", windows)))]
 use glutin::platform::unix::EventLoopWindowTargetExtUnix;
 use glutin::window::CursorIcon;
-use log::{debug, info};
-use parking_lot::MutexGuard;
+use log::{debug, error, info};
+use parking_lot::{Mutex, MutexGuard};
 use unicode_width::UnicodeWidthChar;
 #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
 use wayland_client::{Display as WaylandDisplay, EventQueue};
 
 #[cfg(target_os = "macos")]
 use crossfont::set_font_smoothing;
-use crossfont::{self, Rasterize, Rasterizer};
+use crossfont::{self, Size};
 
 use alacritty_terminal::event::{EventListener, OnResize};
-use alacritty_terminal::index::{Column, Direction, Point};
+use alacritty_terminal::graphics::RenderableGraphic;
+use alacritty_terminal::index::{Column, Direction, Line, Point};
 use alacritty_terminal::selection::Selection;
-use alacritty_terminal::term::{RenderableCell, SizeInfo, Term, TermMode};
+use alacritty_terminal::term::cell::Flags;
+use alacritty_terminal::term::color::{List, Rgb};
+use alacritty_terminal::term::marks::RenderableMark;
+use alacritty_terminal::term::{CellHighlight, SizeInfo, Term, TermMode};
 use alacritty_terminal::term::{MIN_COLS, MIN_SCREEN_LINES};
 
+use crate::clipboard_history::ClipboardHistory;
 use crate::config::font::Font;
 use crate::config::window::Dimensions;
 #[cfg(not(windows))]
 use crate::config::window::StartupMode;
 use crate::config::Config;
+use crate::cursor_locator::{self, CursorLocator, LocatorOverlay};
+use crate::diagnostics::Bundle;
+#[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+use crate::event::EventProxy;
 use crate::event::{Mouse, SearchState};
+use crate::frame::{FrameCells, FrameSnapshot, SnapshotRecorder, SnapshotReplay};
+use crate::glyph_inspector::GlyphInspector;
+use crate::hint::Hints;
+use crate::idle::Idle;
+use crate::input::FONT_SIZE_STEP;
+use crate::key_overlay::KeyOverlay;
 use crate::message_bar::{MessageBuffer, MessageType};
 use crate::meter::Meter;
-use crate::renderer::rects::{RenderLines, RenderRect};
-use crate::renderer::{self, GlyphCache, RenderContext, Renderer};
+use crate::metrics::{self, Metrics};
+use crate::region_capture::{CaptureOverlay, CaptureRegion, RegionCapture};
+use crate::renderer::glyph::FontRasterizer;
+use crate::renderer::rects::RenderRect;
+use crate::renderer::{self, GlyphCache, RenderContext, RenderStats, Renderer};
 use crate::url::{Url, Urls};
+#[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+use crate::wayland_scale::FractionalScale;
 use crate::window::{self, Window};
 
 const FORWARD_SEARCH_LABEL: &str = "Search: ";
 const BACKWARD_SEARCH_LABEL: &str = "Backward Search: ";
 
+/// Duration of the transition between cell sizes when the font size is changed.
+const FONT_SIZE_ANIMATION: Duration = Duration::from_millis(100);
+
+/// Maximum number of font sizes tried to fit the configured number of columns.
+const FIT_ATTEMPTS: usize = 3;
+
 #[derive(Debug)]
 pub enum Error {
     /// Error with window management.
@@ -113,7 +143,12 @@ pub struct DisplayUpdate {
 
     dimensions: Option<PhysicalSize<u32>>,
     cursor_dirty: bool,
+    background_image_dirty: bool,
+    dump_atlases: bool,
+    reload_shaders: bool,
+    diagnostic_bundle: bool,
     font: Option<Font>,
+    font_animated: bool,
 }
 
 impl DisplayUpdate {
@@ -125,10 +160,31 @@ impl DisplayUpdate {
         self.font.as_ref()
     }
 
+    /// Whether the cell size should change gradually to the size of the new font.
+    pub fn font_animated(&self) -> bool {
+        self.font_animated
+    }
+
     pub fn cursor_dirty(&self) -> bool {
         self.cursor_dirty
     }
 
+    pub fn background_image_dirty(&self) -> bool {
+        self.background_image_dirty
+    }
+
+    pub fn dump_atlases(&self) -> bool {
+        self.dump_atlases
+    }
+
+    pub fn reload_shaders(&self) -> bool {
+        self.reload_shaders
+    }
+
+    pub fn diagnostic_bundle(&self) -> bool {
+        self.diagnostic_bundle
+    }
+
     pub fn set_dimensions(&mut self, dimensions: PhysicalSize<u32>) {
         self.dimensions = Some(dimensions);
         self.dirty = true;
@@ -139,10 +195,56 @@ impl DisplayUpdate {
         self.dirty = true;
     }
 
+    pub fn set_font_animated(&mut self, font: Font) {
+        self.set_font(font);
+        self.font_animated = true;
+    }
+
     pub fn set_cursor_dirty(&mut self) {
         self.cursor_dirty = true;
         self.dirty = true;
     }
+
+    pub fn set_background_image_dirty(&mut self) {
+        self.background_image_dirty = true;
+        self.dirty = true;
+    }
+
+    pub fn set_dump_atlases(&mut self) {
+        self.dump_atlases = true;
+        self.dirty = true;
+    }
+
+    pub fn set_reload_shaders(&mut self) {
+        self.reload_shaders = true;
+        self.dirty = true;
+    }
+
+    pub fn set_diagnostic_bundle(&mut self) {
+        self.diagnostic_bundle = true;
+        self.dirty = true;
+    }
+}
+
+/// Everything which determines the contents of a frame, besides the configuration.
+///
+/// Comparing it against the last frame allows skipping redraws which would not change anything
+/// on screen.
+#[derive(Debug, PartialEq)]
+struct FrameState {
+    snapshot: FrameSnapshot,
+    graphics: Vec<RenderableGraphic>,
+    marks: Vec<RenderableMark>,
+    capture: Option<CaptureOverlay>,
+    hint_labels: Vec<(Point, String)>,
+    clipboard_chooser: Option<(Vec<String>, usize)>,
+    cursor_locator: Option<LocatorOverlay>,
+    cursor_outline: Option<Point>,
+    scroll_locked: bool,
+    cursor_blink_visible: bool,
+    mouse: (usize, usize, ModifiersState),
+    mouse_mode: bool,
+    degraded: bool,
 }
 
 /// The display wraps a window, font rasterizer, and GPU renderer.
@@ -163,9 +265,66 @@ pub struct Display {
     #[cfg(not(any(target_os = "macos", windows)))]
     pub is_x11: bool,
 
+    /// Recently pressed keys.
+    pub key_overlay: KeyOverlay,
+
+    /// Sheet of all cached glyphs.
+    pub glyph_inspector: GlyphInspector,
+
+    /// Time of the last input or output.
+    pub idle: Idle,
+
+    /// Screen region selected for export.
+    pub region_capture: RegionCapture,
+
+    /// Labels for selecting URLs and paths with the keyboard.
+    pub hints: Hints,
+
+    /// Rings pointing out the cursor position.
+    pub cursor_locator: CursorLocator,
+
+    /// Recently copied selections.
+    pub clipboard_history: ClipboardHistory,
+
+    /// Whether a blinking cursor is currently shown.
+    pub cursor_blink_visible: bool,
+
+    /// Background opacity set with `alacritty msg opacity`, overriding the configuration.
+    pub background_opacity: Option<f32>,
+
+    /// State of the frame currently on screen.
+    last_frame: Option<FrameState>,
+
+    /// Whether the next frame must be drawn, even when its state did not change.
+    damaged: bool,
+
+    /// Bytes received by the terminal when the last frame was drawn.
+    last_received_bytes: u64,
+
+    /// Whether a diagnostic bundle should be written once the next frame is rendered.
+    diagnostic_bundle_pending: bool,
+
+    /// Counters served by the metrics endpoint, if it is enabled.
+    metrics: Option<Arc<Mutex<Metrics>>>,
+
+    /// Destination of the snapshots of all drawn frames.
+    snapshot_recorder: Option<SnapshotRecorder>,
+
+    /// Recorded snapshots drawn instead of the terminal.
+    snapshot_replay: Option<SnapshotReplay>,
+
+    /// Directory receiving every replayed frame as PNG file.
+    replay_output: Option<PathBuf>,
+
+    /// Whether the last replayed frame has been written to `replay_output`.
+    replay_exported: bool,
+
     renderer: Renderer,
     glyph_cache: GlyphCache,
     meter: Meter,
+
+    /// Size of the font the glyph cache was last rasterized with.
+    font_size: Size,
 }
 
 impl Display {
@@ -175,7 +334,7 @@ impl Display {
             event_loop.available_monitors().next().map(|m| m.scale_factor()).unwrap_or(1.);
 
         // Guess the target window dimensions.
-        let metrics = GlyphCache::static_metrics(config.ui_config.font.clone(), estimated_dpr)?;
+        let metrics = GlyphCache::static_metrics(config.ui_config.font.clone(), estimated_dpr);
         let (cell_width, cell_height) = GlyphCache::compute_cell_size(config, &metrics);
 
         // Guess the target window size if the user has specified the number of lines/columns.
@@ -210,10 +369,10 @@ impl Display {
         info!("Device pixel ratio: {}", window.dpr);
 
         // Create renderer.
-        let mut renderer = Renderer::new()?;
+        let mut renderer = Renderer::new(&config.ui_config.renderer)?;
 
         let (glyph_cache, cell_width, cell_height) =
-            Self::new_glyph_cache(window.dpr, &mut renderer, config)?;
+            Self::new_glyph_cache(window.dpr, &mut renderer, config);
 
         if let Some(dimensions) = dimensions {
             if (estimated_dpr - window.dpr).abs() < f64::EPSILON {
@@ -229,26 +388,36 @@ impl Display {
         let viewport_size = window.inner_size();
 
         // Create new size with at least one column and row.
-        let size_info = SizeInfo::new(
+        let mut size_info = SizeInfo::new(
             viewport_size.width as f32,
             viewport_size.height as f32,
             cell_width,
             cell_height,
             padding.0,
             padding.1,
-            config.ui_config.window.dynamic_padding && dimensions.is_none(),
+            config.ui_config.dynamic_padding() && dimensions.is_none(),
         );
+        size_info.reserve_gutter(config.ui_config.window.gutter);
+        size_info.reserve_minimap(config.ui_config.window.minimap);
 
         info!("Cell size: {} x {}", cell_width, cell_height);
         info!("Padding: {} x {}", size_info.padding_x(), size_info.padding_y());
+        log_integer_scaling_padding(config, padding, &size_info);
         info!("Width: {}, Height: {}", size_info.width(), size_info.height());
 
         // Update OpenGL projection.
-        renderer.resize(&size_info);
+        renderer.resize(&size_info, config.ui_config.render_scale());
 
         // Clear screen.
-        let background_color = config.colors.primary.background;
-        renderer.clear(background_color, config.ui_config.background_opacity());
+        renderer.clear(
+            &List::from(&config.colors),
+            config.ui_config.background_opacity(),
+            Duration::default(),
+        );
+
+        renderer.set_background_image(config.ui_config.background_image.as_ref());
+        renderer.set_post_process_shader(config.ui_config.post_processing_shader.as_deref());
+        renderer.set_idle_shader(config.ui_config.idle.shader.as_deref());
 
         // Set subpixel anti-aliasing.
         #[cfg(target_os = "macos")]
@@ -287,14 +456,51 @@ impl Display {
             _ => (),
         }
 
+        let metrics = config.ui_config.debug.metrics_port.map(|port| {
+            let metrics = Arc::new(Mutex::new(Metrics::default()));
+            metrics::spawn_metrics_server(port, metrics.clone());
+            metrics
+        });
+
+        let debug = &config.ui_config.debug;
+        let snapshot_recorder = debug.record_frames.as_ref().and_then(|path| {
+            SnapshotRecorder::new(path)
+                .map_err(|err| error!("Unable to record frames to {:?}: {}", path, err))
+                .ok()
+        });
+        let snapshot_replay = debug.replay_frames.as_ref().and_then(|path| {
+            SnapshotReplay::load(path)
+                .map_err(|err| error!("Unable to replay frames from {:?}: {}", path, err))
+                .ok()
+        });
+
         Ok(Self {
             window,
             renderer,
             glyph_cache,
+            font_size: config.ui_config.font.size,
             meter: Meter::new(),
             size_info,
-            urls: Urls::new(),
+            urls: Urls::default(),
             highlighted_url: None,
+            key_overlay: KeyOverlay::default(),
+            glyph_inspector: GlyphInspector::default(),
+            idle: Idle::default(),
+            region_capture: RegionCapture::default(),
+            hints: Hints::default(),
+            cursor_locator: CursorLocator::default(),
+            clipboard_history: ClipboardHistory::default(),
+            cursor_blink_visible: true,
+            background_opacity: None,
+            last_frame: None,
+            damaged: true,
+            last_received_bytes: 0,
+            diagnostic_bundle_pending: config.ui_config.debug.diagnostic_bundle,
+            metrics,
+            snapshot_recorder,
+            snapshot_replay,
+            replay_output: debug.replay_output.clone(),
+            replay_exported: false,
             #[cfg(not(any(target_os = "macos", windows)))]
             is_x11,
             #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
@@ -308,9 +514,10 @@ impl Display {
         dpr: f64,
         renderer: &mut Renderer,
         config: &Config,
-    ) -> Result<(GlyphCache, f32, f32), Error> {
+    ) -> (GlyphCache, f32, f32) {
         let font = config.ui_config.font.clone();
-        let rasterizer = Rasterizer::new(dpr as f32, config.ui_config.font.use_thin_strokes())?;
+        let dpr = dpr * f64::from(config.ui_config.render_scale());
+        let rasterizer = FontRasterizer::system(dpr, config.ui_config.font.use_thin_strokes());
 
         // Initialize glyph cache.
         let glyph_cache = {
@@ -318,7 +525,7 @@ impl Display {
             let init_start = Instant::now();
 
             let cache = renderer
-                .with_loader(|mut api| GlyphCache::new(rasterizer, config, &font, &mut api))?;
+                .with_loader(|mut api| GlyphCache::new(rasterizer, config, &font, dpr, &mut api));
 
             let stop = init_start.elapsed();
             let stop_f = stop.as_secs() as f64 + f64::from(stop.subsec_nanos()) / 1_000_000_000f64;
@@ -330,24 +537,66 @@ impl Display {
         // Need font metrics to resize the window properly. This suggests to me the
         // font metrics should be computed before creating the window in the first
         // place so that a resize is not needed.
-        let (cw, ch) = GlyphCache::compute_cell_size(config, &glyph_cache.font_metrics());
+        let (cw, ch) = Self::cell_size(config, &glyph_cache.font_metrics());
 
-        Ok((glyph_cache, cw, ch))
+        (glyph_cache, cw, ch)
     }
 
     /// Update font size and cell dimensions.
     ///
     /// This will return a tuple of the cell width and height.
     fn update_glyph_cache(&mut self, config: &Config, font: &Font) -> (f32, f32) {
-        let cache = &mut self.glyph_cache;
-        let dpr = self.window.dpr;
+        let dpr = self.window.dpr * f64::from(config.ui_config.render_scale());
+        self.font_size = font.size;
 
-        self.renderer.with_loader(|mut api| {
-            let _ = cache.update_font_size(config, font, dpr, &mut api);
-        });
+        match self.renderer.rescale(&mut self.glyph_cache, config, font, dpr) {
+            Ok(true) => (),
+            Ok(false) => debug!("Glyphs are already rasterized for DPR {}", dpr),
+            Err(err) => error!("Unable to rasterize font: {}", err),
+        }
 
         // Compute new cell sizes.
-        GlyphCache::compute_cell_size(config, &self.glyph_cache.font_metrics())
+        Self::cell_size(config, &self.glyph_cache.font_metrics())
+    }
+
+    /// Rasterize `font` at the size which fills a window `width` pixels wide with `columns` columns.
+    ///
+    /// Cell widths are not exactly proportional to the font size, so the size is estimated from
+    /// the `cell_dimensions` of the current size and refined until the cells have the right width.
+    fn fit_glyph_cache(
+        &mut self,
+        config: &Config,
+        font: &Font,
+        mut cell_dimensions: (f32, f32),
+        width: f32,
+        columns: usize,
+    ) -> (f32, f32) {
+        let window = &config.ui_config.window;
+        let columns = columns + window.gutter + window.minimap;
+        let padding = window.padding(self.window.dpr).0;
+        let target_width = ((width - 2. * padding) / columns as f32).floor().max(1.);
+
+        let mut font_size = self.font_size.as_f32_pts();
+        for _ in 0..FIT_ATTEMPTS {
+            if (cell_dimensions.0 - target_width).abs() < f32::EPSILON {
+                break;
+            }
+
+            font_size = (font_size * target_width / cell_dimensions.0).max(FONT_SIZE_STEP);
+            let font = font.clone().with_size(Size::new(font_size));
+            cell_dimensions = self.update_glyph_cache(config, &font);
+        }
+
+        cell_dimensions
+    }
+
+    /// Calculate the cell dimensions in window pixels.
+    ///
+    /// Glyphs are rasterized at the render scale, so their metrics need to be scaled back down.
+    fn cell_size(config: &Config, metrics: &crossfont::Metrics) -> (f32, f32) {
+        let scale = config.ui_config.render_scale();
+        let (cell_width, cell_height) = GlyphCache::compute_cell_size(config, metrics);
+        ((cell_width / scale).round().max(1.), (cell_height / scale).round().max(1.))
     }
 
     /// Clear glyph cache.
@@ -358,6 +607,11 @@ impl Display {
         });
     }
 
+    /// Size of the font the glyph cache was last rasterized with.
+    pub fn font_size(&self) -> Size {
+        self.font_size
+    }
+
     /// Process update events.
     pub fn handle_update<T>(
         &mut self,
@@ -370,12 +624,44 @@ impl Display {
     ) where
         T: EventListener,
     {
+        self.damaged = true;
+
         let (mut cell_width, mut cell_height) =
             (self.size_info.cell_width(), self.size_info.cell_height());
 
+        let (mut width, mut height) = (self.size_info.width(), self.size_info.height());
+        if let Some(dimensions) = update_pending.dimensions() {
+            width = dimensions.width as f32;
+            height = dimensions.height as f32;
+        }
+
+        // Keep the configured number of columns while the window is resized.
+        let fit_columns =
+            config.ui_config.window.fit_columns().filter(|_| update_pending.dimensions().is_some());
+        let font = update_pending.font().cloned().or_else(|| {
+            fit_columns.map(|_| config.ui_config.font.clone().with_size(self.font_size))
+        });
+
         // Update font size and cell dimensions.
-        if let Some(font) = update_pending.font() {
-            let cell_dimensions = self.update_glyph_cache(config, font);
+        if let Some(font) = font {
+            let mut cell_dimensions = (cell_width, cell_height);
+            if update_pending.font().is_some() {
+                cell_dimensions = self.update_glyph_cache(config, &font);
+            }
+
+            if let Some(columns) = fit_columns {
+                cell_dimensions =
+                    self.fit_glyph_cache(config, &font, cell_dimensions, width, columns);
+            }
+
+            // Fractional scales would resample every glyph, so integer scaling changes instantly.
+            if update_pending.font_animated()
+                && !config.ui_config.renderer.integer_scaling
+                && config.ui_config.animations.enabled()
+            {
+                let ratio = cell_height / cell_dimensions.1;
+                self.renderer.animate_cell_size(ratio, FONT_SIZE_ANIMATION);
+            }
             cell_width = cell_dimensions.0;
             cell_height = cell_dimensions.1;
 
@@ -384,12 +670,23 @@ impl Display {
             self.clear_glyph_cache(config);
         }
 
-        let (mut width, mut height) = (self.size_info.width(), self.size_info.height());
-        if let Some(dimensions) = update_pending.dimensions() {
-            width = dimensions.width as f32;
-            height = dimensions.height as f32;
+        if update_pending.background_image_dirty() {
+            self.renderer.set_background_image(config.ui_config.background_image.as_ref());
+        }
+
+        self.renderer.set_post_process_shader(config.ui_config.post_processing_shader.as_deref());
+        self.renderer.set_idle_shader(config.ui_config.idle.shader.as_deref());
+
+        if update_pending.reload_shaders() {
+            self.renderer.reload_shaders();
         }
 
+        if update_pending.dump_atlases() {
+            self.renderer.dump_atlases(&env::temp_dir());
+        }
+
+        self.diagnostic_bundle_pending |= update_pending.diagnostic_bundle();
+
         let padding = config.ui_config.window.padding(self.window.dpr);
 
         self.size_info = SizeInfo::new(
@@ -399,8 +696,10 @@ impl Display {
             cell_height,
             padding.0,
             padding.1,
-            config.ui_config.window.dynamic_padding,
+            config.ui_config.dynamic_padding(),
         );
+        self.size_info.reserve_gutter(config.ui_config.window.gutter);
+        self.size_info.reserve_minimap(config.ui_config.window.minimap);
 
         // Update number of column/lines in the viewport.
         let message_bar_lines =
@@ -418,9 +717,10 @@ impl Display {
         let physical =
             PhysicalSize::new(self.size_info.width() as u32, self.size_info.height() as u32);
         self.window.resize(physical);
-        self.renderer.resize(&self.size_info);
+        self.renderer.resize(&self.size_info, config.ui_config.render_scale());
 
         info!("Padding: {} x {}", self.size_info.padding_x(), self.size_info.padding_y());
+        log_integer_scaling_padding(config, padding, &self.size_info);
         info!("Width: {}, Height: {}", self.size_info.width(), self.size_info.height());
     }
 
@@ -431,63 +731,212 @@ impl Display {
     /// This call may block if vsync is enabled.
     pub fn draw<T>(
         &mut self,
-        terminal: MutexGuard<'_, Term<T>>,
+        mut terminal: MutexGuard<'_, Term<T>>,
         message_buffer: &MessageBuffer,
         config: &Config,
         mouse: &Mouse,
         mods: ModifiersState,
         search_state: &SearchState,
     ) {
-        let grid_cells: Vec<RenderableCell> = terminal.renderable_cells(config).collect();
-        let visual_bell_intensity = terminal.visual_bell.intensity();
-        let background_color = terminal.background_color();
         let cursor_point = terminal.grid().cursor.point;
-        let metrics = self.glyph_cache.font_metrics();
-        let glyph_cache = &mut self.glyph_cache;
-        let size_info = self.size_info;
+        let (pending_graphics, removed_graphics) = terminal.take_graphic_updates();
+        let scroll_delta = terminal.take_scroll_delta();
+        let mut metrics = self.glyph_cache.font_metrics();
+        metrics.underline_thickness *= config.ui_config.font.underline_thickness();
+        if config.ui_config.renderer.integer_scaling {
+            metrics.descent = metrics.descent.round();
+            metrics.underline_position = metrics.underline_position.round();
+            metrics.underline_thickness = metrics.underline_thickness.round();
+            metrics.strikeout_position = metrics.strikeout_position.round();
+            metrics.strikeout_thickness = metrics.strikeout_thickness.round();
+        }
+        // Frames are rendered at the render scale and only scaled to the window size at the end.
+        let size_info = self.size_info.scaled(config.ui_config.render_scale());
 
         let selection = !terminal.selection.as_ref().map(Selection::is_empty).unwrap_or(true);
         let mouse_mode = terminal.mode().intersects(TermMode::MOUSE_MODE)
             && !terminal.mode().contains(TermMode::VI);
 
-        let vi_mode_cursor = if terminal.mode().contains(TermMode::VI) {
-            Some(terminal.vi_mode_cursor)
+        let vi_mode_point = if terminal.mode().contains(TermMode::VI) {
+            Some(terminal.vi_mode_cursor.point)
         } else {
             None
         };
 
+        // Cursor position on screen, for pointing it out.
+        let mut cursor = cursor_point;
+        cursor.line += terminal.grid().display_offset();
+        let cursor = Some(cursor).filter(|cursor| cursor.line < terminal.screen_lines());
+
+        // Expensive glyphs are skipped while the output is too fast to read anyway.
+        let received_bytes = terminal.received_bytes();
+        let threshold = config.ui_config.renderer.fast_output_threshold;
+        let degraded =
+            threshold != 0 && received_bytes.saturating_sub(self.last_received_bytes) > threshold;
+        self.last_received_bytes = received_bytes;
+
+        // Without animations, the visual bell and idle dimming switch on and off instantly.
+        let now = Instant::now();
+        let idle_config = &config.ui_config.idle;
+        let mut visual_bell_intensity = terminal.visual_bell.intensity();
+        let mut idle_dim = self.idle.dim(idle_config, now);
+        if !config.ui_config.animations.enabled() {
+            visual_bell_intensity = visual_bell_intensity.ceil();
+            idle_dim = if self.idle.is_idle(idle_config, now) { idle_config.dim() } else { 0. };
+        }
+
+        let window_config = &config.ui_config.window;
+        let unfocused_dim = if terminal.is_focused { 0. } else { window_config.unfocused_dim() };
+
+        let mut snapshot = FrameSnapshot {
+            cells: FrameCells::new(&terminal, config),
+            terminal_cursor: terminal.renderable_terminal_cursor(config),
+            colors: *terminal.colors(),
+            vi_mode_point,
+            visual_bell_intensity,
+            idle_dim,
+            unfocused_dim,
+            background_opacity: self.background_opacity(&terminal, config),
+            message: message_buffer.message().cloned(),
+            search_regex: search_state.regex().cloned(),
+            search_matches: search_state.match_position(),
+            size_info,
+            damage: Vec::new(),
+        };
+
+        #[cfg(feature = "bidi")]
+        snapshot.reorder_bidi(config.ui_config.renderer.arabic_shaping);
+
+        // Recorded frames replace the terminal's content while they are replayed.
+        let mut replayed_frame = None;
+        if let Some(replay) = &mut self.snapshot_replay {
+            let replayed = replay.replayed();
+            if let Some(replayed) = replay.next_frame() {
+                snapshot = replayed;
+                snapshot.damage.clear();
+            }
+
+            // Frames written to disk are drawn even if they look like the previous one.
+            if replay.replayed() > replayed && self.replay_output.is_some() {
+                replayed_frame = Some((replay.replayed(), replay.is_finished()));
+            }
+        }
+
+        let mut frame = FrameState {
+            snapshot,
+            graphics: terminal.renderable_graphics(),
+            marks: terminal.renderable_marks(),
+            capture: self.region_capture.overlay(now),
+            hint_labels: self.hints.labels(),
+            clipboard_chooser: self.clipboard_history.selected().map(|selected| {
+                // Leave a column of margin on both sides of the entries.
+                let columns = size_info.cols().0.saturating_sub(2);
+                (self.clipboard_history.previews(columns), selected)
+            }),
+            cursor_locator: cursor.and_then(|cursor| {
+                let duration = config.ui_config.cursor_locator.duration();
+                let animated = config.ui_config.animations.enabled();
+                self.cursor_locator.overlay(cursor, duration, animated, now)
+            }),
+            cursor_outline: cursor.filter(|_| config.ui_config.cursor_locator.outline),
+            scroll_locked: terminal.scroll_locked(),
+            cursor_blink_visible: self.cursor_blink_visible,
+            mouse: (mouse.x, mouse.y, mods),
+            mouse_mode,
+            degraded,
+        };
+
         // Drop terminal as early as possible to free lock.
         drop(terminal);
 
+        // Skip frames which would look exactly like the one already on screen.
+        if !self.needs_redraw(config, &frame)
+            && pending_graphics.is_empty()
+            && removed_graphics.is_empty()
+            && scroll_delta == 0
+            && !self.region_capture.screenshot_pending()
+            && replayed_frame.is_none()
+        {
+            return;
+        }
+
+        let no_cells = FrameCells::default();
+        let last_cells = self.last_frame.as_ref().map_or(&no_cells, |last| &last.snapshot.cells);
+        frame.snapshot.damage = frame.snapshot.cells.damage(last_cells);
+
+        if let Some(recorder) = &mut self.snapshot_recorder {
+            if let Err(err) = recorder.record(&frame.snapshot) {
+                error!("Unable to record frame snapshot: {}", err);
+                self.snapshot_recorder = None;
+            }
+        }
+
         #[cfg(feature = "dump-raw-render-timings")]
         let start = Instant::now();
 
-        self.renderer.clear(background_color, config.ui_config.background_opacity());
+        self.renderer.scroll(scroll_delta);
+        self.renderer.set_degraded(frame.degraded);
+        self.renderer.set_idle(self.idle.is_idle(&config.ui_config.idle, now));
+        self.renderer.clear(
+            &frame.snapshot.colors,
+            frame.snapshot.background_opacity,
+            config.ui_config.animations.duration(config.ui_config.theme.transition_duration()),
+        );
+
+        let glyph_cache = &mut self.glyph_cache;
+        glyph_cache.start_frame();
+        self.renderer.with_loader(|mut api| glyph_cache.defragment(&mut api));
+        let render_stats = self.renderer.stats();
+        self.renderer.update_graphics(pending_graphics, &removed_graphics);
+
+        self.renderer.set_highlights(
+            CellHighlight::Selection.colors(config),
+            CellHighlight::SearchMatch.colors(config),
+        );
 
-        let mut render_context = self.renderer.begin(&config.ui_config, config.cursor, &size_info);
+        let mut render_context = self.renderer.begin(
+            &config.ui_config,
+            config.cursor,
+            self.cursor_blink_visible,
+            &size_info,
+        );
+
+        // Find URLs and paths, so hovered ones can be underlined while drawing the grid.
+        let path_escape_chars = config.ui_config.hints.escape_chars();
+        let mut urls = Urls::new(config.ui_config.mouse.url.paths(), path_escape_chars);
+        for &cell in frame.snapshot.cells.iter() {
+            urls.update(size_info.cols(), cell);
+        }
 
-        let mut lines = RenderLines::new();
-        let mut urls = Urls::new();
+        let highlighted_url = urls.highlighted(config, mouse, mods, mouse_mode, selection);
+        let vi_mode_url = frame.snapshot.vi_mode_point.and_then(|point| urls.find_at(point));
 
         // Draw grid.
         {
             let _sampler = self.meter.sampler();
 
             // Iterate over all non-empty cells in the grid.
-            for cell in grid_cells {
-                // Update URL underlines.
-                urls.update(size_info.cols(), cell);
+            for &cell in frame.snapshot.cells.iter() {
+                let mut cell = cell;
 
-                // Update underline/strikeout.
-                lines.update(cell);
+                // Underline URLs below the mouse and vi mode cursor.
+                if highlighted_url.iter().chain(&vi_mode_url).any(|url| url.underlines(&cell)) {
+                    cell.flags.remove(Flags::ALL_UNDERLINES);
+                    cell.flags.insert(Flags::DOTTED_UNDERLINE);
+                }
 
                 // Draw the cell.
                 render_context.update_cell(cell, glyph_cache);
             }
+
+            // Keep the terminal cursor visible below the vi mode cursor.
+            if let Some(cell) = frame.snapshot.terminal_cursor {
+                render_context.update_terminal_cursor(cell);
+            }
         }
 
-        if let Some(message) = message_buffer.message() {
-            let search_offset = if search_state.regex().is_some() { 1 } else { 0 };
+        if let Some(message) = &frame.snapshot.message {
+            let search_offset = if frame.snapshot.search_regex.is_some() { 1 } else { 0 };
             let text = message.text(&size_info);
 
             let start_line = size_info.screen_lines() + search_offset;
@@ -516,17 +965,28 @@ impl Display {
             config,
             &size_info,
             &self.meter,
+            &render_stats,
         );
 
         // Handle search and IME positioning.
-        let ime_position = match search_state.regex() {
+        let ime_position = match &frame.snapshot.search_regex {
             Some(regex) => {
                 let search_label = match search_state.direction() {
                     Direction::Right => FORWARD_SEARCH_LABEL,
                     Direction::Left => BACKWARD_SEARCH_LABEL,
                 };
 
-                let search_text = Self::format_search(&size_info, regex, search_label);
+                // Show the match counter only if it leaves room for the search label.
+                let mut matches = match frame.snapshot.search_matches {
+                    Some((0, _)) => String::from(" [no matches]"),
+                    Some((index, count)) => format!(" [{}/{}]", index, count),
+                    None => String::new(),
+                };
+                if matches.len() + search_label.len() >= size_info.cols().0 {
+                    matches.clear();
+                }
+
+                let search_text = Self::format_search(&size_info, regex, search_label, &matches);
 
                 // Render the search bar.
                 Self::draw_search(
@@ -535,10 +995,11 @@ impl Display {
                     config,
                     &size_info,
                     &search_text,
+                    &matches,
                 );
 
                 // Compute IME position.
-                Point::new(size_info.screen_lines() + 1, Column(search_text.chars().count() - 1))
+                Point::new(size_info.screen_lines(), Column(search_text.chars().count() - 1))
             },
             None => cursor_point,
         };
@@ -546,15 +1007,19 @@ impl Display {
         // Update IME position.
         self.window.update_ime_position(ime_position, &self.size_info);
 
-        render_context.draw_text();
+        render_context.draw_graphics_underlay(&frame.graphics);
+        render_context.draw_text(&metrics);
+        render_context.draw_graphics(&frame.graphics);
 
-        let mut rects = lines.rects(&metrics, &size_info);
+        // Tint cells annotated by external tools.
+        let mut rects = renderer::rects::mark_rects(&frame.marks, &size_info);
+
+        // Point out cells with more zero-width characters than they can hold.
+        rects.extend(renderer::rects::truncation_rects(frame.snapshot.cells.iter(), &size_info));
 
         // Update visible URLs.
         self.urls = urls;
-        if let Some(url) = self.urls.highlighted(config, mouse, mods, mouse_mode, selection) {
-            rects.append(&mut url.rects(&metrics, &size_info));
-
+        if let Some(url) = highlighted_url {
             self.window.set_mouse_cursor(CursorIcon::Hand);
 
             self.highlighted_url = Some(url);
@@ -568,31 +1033,136 @@ impl Display {
             }
         }
 
-        // Highlight URLs at the vi mode cursor position.
-        if let Some(vi_mode_cursor) = vi_mode_cursor {
-            if let Some(url) = self.urls.find_at(vi_mode_cursor.point) {
-                rects.append(&mut url.rects(&metrics, &size_info));
-            }
-        }
-
         // Push visual bell after url/underline/strikeout rects.
-        if visual_bell_intensity != 0. {
+        if frame.snapshot.visual_bell_intensity != 0. {
             let visual_bell_rect = RenderRect::new(
                 0.,
                 0.,
                 size_info.width(),
                 size_info.height(),
                 config.bell().color,
-                visual_bell_intensity as f32,
+                frame.snapshot.visual_bell_intensity as f32,
             );
             rects.push(visual_bell_rect);
         }
 
+        // Darken the terminal while it is idle or its window is not focused.
+        let dim = 1. - (1. - frame.snapshot.idle_dim) * (1. - frame.snapshot.unfocused_dim);
+        if dim > 0. {
+            let (width, height) = (size_info.width(), size_info.height());
+            let black = Rgb { r: 0, g: 0, b: 0 };
+            rects.push(RenderRect::new(0., 0., width, height, black, dim));
+        }
+
+        // Outline the capture region while it is selected.
+        if let Some(capture) = &frame.capture {
+            rects.extend(capture.rects(&size_info));
+        }
+
+        // Point out the cursor above everything else.
+        let locator_color =
+            config.ui_config.cursor_locator.color.unwrap_or(config.colors.primary.foreground);
+        if let Some(cursor) = frame.cursor_outline {
+            rects.extend(cursor_locator::outline_rects(cursor, &size_info, locator_color));
+        }
+        if let Some(locator) = &frame.cursor_locator {
+            rects.extend(locator.rects(&size_info, locator_color));
+        }
+
         // Draw rectangles.
         render_context.draw_rects(rects);
 
+        if size_info.gutter() > 0. {
+            let fg = config.colors.primary.foreground;
+            render_context.draw_gutter(&mut self.glyph_cache, &frame.marks, fg);
+        }
+
+        if frame.scroll_locked {
+            let fg = config.colors.primary.background;
+            let bg = config.colors.primary.foreground;
+            render_context.draw_scroll_lock(&mut self.glyph_cache, fg, bg);
+        }
+
+        if !frame.hint_labels.is_empty() {
+            let fg = config.colors.primary.background;
+            let bg = config.colors.primary.foreground;
+            render_context.draw_hint_labels(&mut self.glyph_cache, &frame.hint_labels, fg, bg);
+        }
+
+        let key_overlay = &config.ui_config.key_overlay;
+        if key_overlay.enabled {
+            let keys = self.key_overlay.visible(key_overlay.duration());
+            if !keys.is_empty() {
+                let fg = config.colors.primary.background;
+                let bg = config.colors.primary.foreground;
+                render_context.draw_key_overlay(&mut self.glyph_cache, &keys, fg, bg);
+            }
+        }
+
+        if self.glyph_inspector.is_active() {
+            let glyphs = self.glyph_cache.inspect();
+            let probes = match self.glyph_inspector.query_char() {
+                Some(c) => self.glyph_cache.probe(c),
+                None => Vec::new(),
+            };
+            let lines = self.glyph_inspector.sheet(&glyphs, &probes, size_info.screen_lines().0);
+
+            let fg = config.colors.primary.foreground;
+            let bg = config.colors.primary.background;
+            render_context.draw_glyph_inspector(&mut self.glyph_cache, &lines, fg, bg);
+        }
+
+        if let Some((entries, selected)) = &frame.clipboard_chooser {
+            let fg = config.colors.primary.foreground;
+            let bg = config.colors.primary.background;
+            render_context.draw_clipboard_chooser(
+                &mut self.glyph_cache,
+                entries,
+                *selected,
+                fg,
+                bg,
+            );
+        }
+
+        if let Some(capture) = &frame.capture {
+            let status: String = capture.status().chars().take(size_info.cols().0).collect();
+            let fg = config.colors.primary.background;
+            let bg = config.colors.primary.foreground;
+            let line = size_info.screen_lines() - 1;
+            render_context.render_string(&mut self.glyph_cache, line, &status, fg, Some(bg));
+        }
+
+        if config.ui_config.debug.atlas_overlay {
+            render_context.draw_atlas_overlay();
+        }
+
         drop(render_context);
 
+        self.renderer.present(&size_info, cursor_point);
+
+        // Frame time excludes the swap, since it waits for vsync.
+        if let Some(metrics) = &self.metrics {
+            let (glyphs, _) = self.glyph_cache.usage();
+            let mut metrics = metrics.lock();
+            metrics.record_frame(Instant::now(), now.elapsed(), &render_stats);
+            metrics.update_counters(received_bytes, glyphs, self.glyph_cache.stats);
+        }
+
+        // Screenshots are read back from the frame before it is swapped.
+        if let Some(region) = self.region_capture.take_screenshot() {
+            self.write_region_screenshot(region);
+        }
+
+        if let Some((index, finished)) = replayed_frame {
+            self.write_replayed_frame(index);
+            self.replay_exported = finished;
+        }
+
+        if self.diagnostic_bundle_pending {
+            self.diagnostic_bundle_pending = false;
+            self.write_diagnostic_bundle(config, &frame.snapshot.cells);
+        }
+
         #[cfg(feature = "dump-raw-render-timings")]
         {
             self.renderer.finish();
@@ -619,10 +1189,167 @@ impl Display {
         }
 
         self.window.swap_buffers();
+
+        // Damage is relative to the previous frame, so it can't be compared against the next one.
+        frame.snapshot.damage.clear();
+
+        self.last_frame = Some(frame);
+        self.damaged = false;
+    }
+
+    /// Write a region of the frame into a PNG file in the temporary directory.
+    fn write_region_screenshot(&self, region: CaptureRegion) {
+        let (x, y, width, height) = region.pixels(&self.size_info);
+        let png = match self.renderer.screenshot_region(
+            x as i32,
+            y as i32,
+            width as i32,
+            height as i32,
+        ) {
+            Ok(png) => png,
+            Err(err) => {
+                error!("Unable to capture screen region: {}", err);
+                return;
+            },
+        };
+
+        let timestamp =
+            SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+        let name = format!("alacritty-capture-{}-{}.png", process::id(), timestamp);
+        let path = env::temp_dir().join(name);
+        match std::fs::write(&path, png) {
+            Ok(()) => info!("Wrote screen region to {:?}", path),
+            Err(err) => error!("Unable to write screen region {:?}: {}", path, err),
+        }
+    }
+
+    /// Write a replayed frame into a PNG file in the `--replay-output` directory.
+    fn write_replayed_frame(&self, index: usize) {
+        let directory = match &self.replay_output {
+            Some(directory) => directory,
+            None => return,
+        };
+
+        let path = directory.join(format!("frame-{:05}.png", index));
+        let result = self
+            .renderer
+            .screenshot()
+            .map_err(|err| err.to_string())
+            .and_then(|png| std::fs::write(&path, png).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            error!("Unable to write replayed frame {:?}: {}", path, err);
+        }
+    }
+
+    /// Statistics of the render passes of the last frame.
+    pub fn render_stats(&self) -> RenderStats {
+        self.renderer.stats()
+    }
+
+    /// Whether every replayed frame has been written to the `--replay-output` directory.
+    pub fn replay_exported(&self) -> bool {
+        self.replay_exported
+    }
+
+    /// Write a zip file with everything needed to investigate rendering bugs.
+    fn write_diagnostic_bundle(&self, config: &Config, cells: &FrameCells) {
+        let mut bundle = Bundle::new();
+        bundle.add(
+            "version.txt",
+            format!(
+                "alacritty {} ({})\n{} {}\n",
+                env!("CARGO_PKG_VERSION"),
+                env!("GIT_HASH"),
+                env::consts::OS,
+                env::consts::ARCH,
+            ),
+        );
+        bundle.add("config.txt", format!("{:#?}\n", config));
+        bundle.add("gl.txt", self.renderer.gl_report());
+        bundle.add(
+            "renderer.txt",
+            format!("{:#?}\n\n{:#?}\n", self.size_info, self.renderer.stats()),
+        );
+        bundle.add("screen.txt", cells.text().join("\n") + "\n");
+        bundle.add_log();
+
+        match self.renderer.screenshot() {
+            Ok(png) => bundle.add("frame.png", png),
+            Err(err) => error!("Unable to capture frame for diagnostic bundle: {}", err),
+        }
+
+        if config.ui_config.debug.bundle_atlases {
+            match self.renderer.encode_atlases() {
+                Ok(atlases) => {
+                    for (name, png) in atlases {
+                        bundle.add(format!("atlases/{}", name), png);
+                    }
+                },
+                Err(err) => error!("Unable to encode glyph atlases for diagnostic bundle: {}", err),
+            }
+        }
+
+        let timestamp =
+            SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+        let name = format!("alacritty-diagnostics-{}-{}.zip", process::id(), timestamp);
+        let path = env::temp_dir().join(name);
+        match bundle.save(&path) {
+            Ok(()) => info!("Wrote diagnostic bundle to {:?}", path),
+            Err(err) => error!("Unable to write diagnostic bundle {:?}: {}", path, err),
+        }
+    }
+
+    /// Force a redraw of the next frame, even when the terminal content did not change.
+    pub fn damage(&mut self) {
+        self.damaged = true;
+    }
+
+    /// Background opacity, as requested by the application, IPC or the configuration.
+    fn background_opacity<T>(&self, terminal: &Term<T>, config: &Config) -> f32 {
+        terminal
+            .background_opacity()
+            .or(self.background_opacity)
+            .unwrap_or_else(|| config.ui_config.background_opacity())
+    }
+
+    /// Check if a frame differs from the one on screen.
+    fn needs_redraw(&self, config: &Config, frame: &FrameState) -> bool {
+        // Animations and timed overlays change without any change to the frame state.
+        self.damaged
+            || self.is_animating(config)
+            || self.text_blink_timeout().is_some()
+            || config.ui_config.debug.render_timer
+            || config.ui_config.debug.atlas_overlay
+            || self.glyph_inspector.is_active()
+            || self.last_frame.as_ref() != Some(frame)
+    }
+
+    /// Time until blinking text on screen changes its visibility.
+    pub fn text_blink_timeout(&self) -> Option<Duration> {
+        self.renderer.text_blink_timeout()
+    }
+
+    /// Whether the last frame was part of an animation which requires further redraws.
+    pub fn is_animating(&self, config: &Config) -> bool {
+        self.renderer.is_animating()
+            || !self.key_overlay.is_empty()
+            || (config.ui_config.animations.enabled()
+                && self.idle.is_fading(&config.ui_config.idle, Instant::now()))
+            || self.region_capture.is_active()
+            || self.cursor_locator.is_active()
+            || self.last_frame.as_ref().map_or(false, |frame| frame.degraded)
+            || self.snapshot_replay.as_ref().map_or(false, |replay| !replay.is_finished())
     }
 
     /// Format search regex to account for the cursor and fullwidth characters.
-    fn format_search(size_info: &SizeInfo, search_regex: &str, search_label: &str) -> String {
+    ///
+    /// Space for the `matches` counter is kept free at the end of the search bar.
+    fn format_search(
+        size_info: &SizeInfo,
+        search_regex: &str,
+        search_label: &str,
+        matches: &str,
+    ) -> String {
         // Add spacers for wide chars.
         let mut formatted_regex = String::with_capacity(search_regex.len());
         for c in search_regex.chars() {
@@ -636,7 +1363,7 @@ impl Display {
         formatted_regex.push('_');
 
         // Truncate beginning of the search regex if it exceeds the viewport width.
-        let num_cols = size_info.cols().0;
+        let num_cols = size_info.cols().0.saturating_sub(matches.chars().count());
         let label_len = search_label.chars().count();
         let regex_len = formatted_regex.chars().count();
         let truncate_len = min((regex_len + label_len).saturating_sub(num_cols), regex_len);
@@ -652,18 +1379,19 @@ impl Display {
         bar_text
     }
 
-    /// Draw current search regex.
+    /// Draw current search regex, with the match counter on the right.
     fn draw_search(
         glyph_cache: &mut GlyphCache,
         render_context: &mut RenderContext<'_>,
         config: &Config,
         size_info: &SizeInfo,
         text: &str,
+        matches: &str,
     ) {
-        let num_cols = size_info.cols().0;
+        let num_cols = size_info.cols().0.saturating_sub(matches.chars().count());
 
         // Assure text length is at least num_cols.
-        let text = format!("{:<1$}", text, num_cols);
+        let text = format!("{:<1$}{2}", text, num_cols, matches);
 
         let fg = config.colors.search_bar_foreground();
         let bg = config.colors.search_bar_background();
@@ -677,12 +1405,26 @@ impl Display {
         config: &Config,
         size_info: &SizeInfo,
         meter: &Meter,
+        render_stats: &RenderStats,
     ) {
         if !config.ui_config.debug.render_timer {
             return;
         }
 
-        let timing = format!("{:.3} usec", meter.average());
+        let (glyphs, capacity) = glyph_cache.usage();
+        let fallback = glyph_cache.fallback_usage();
+        let stats = glyph_cache.stats;
+        let timing = format!(
+            "{:.3} usec | glyphs {}/{} ({} fallback) hits {} misses {} evictions {}",
+            meter.average(),
+            glyphs,
+            capacity,
+            fallback,
+            stats.hits,
+            stats.misses,
+            stats.evictions
+        );
+        let timing: String = timing.chars().take(size_info.cols().0).collect();
         let fg = config.colors.primary.background;
         let bg = config.colors.normal().red;
 
@@ -693,6 +1435,37 @@ impl Display {
             fg,
             Some(bg),
         );
+
+        // Show GPU statistics above the timer, if there's enough space.
+        if size_info.screen_lines() < Line(3) {
+            return;
+        }
+
+        let passes: String = render_stats.to_string().chars().take(size_info.cols().0).collect();
+
+        render_context.render_string(
+            glyph_cache,
+            size_info.screen_lines() - 3,
+            &passes[..],
+            fg,
+            Some(bg),
+        );
+    }
+
+    /// Render at the compositor's fractional scale factor on Wayland, if it is supported.
+    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+    pub fn watch_fractional_scale(&mut self, event_proxy: EventProxy) {
+        let (queue, surface) = match (&mut self.wayland_event_queue, &self.window.wayland_surface) {
+            (Some(queue), Some(surface)) => (queue, surface),
+            _ => return,
+        };
+
+        let fractional_scale = FractionalScale::new(queue, surface, event_proxy);
+        if fractional_scale.is_none() {
+            info!("Compositor does not support fractional scaling");
+        }
+
+        self.window.fractional_scale = fractional_scale;
     }
 
     /// Requst a new frame for a window on Wayland.
@@ -726,7 +1499,9 @@ fn window_size(
 ) -> PhysicalSize<u32> {
     let padding = config.ui_config.window.padding(dpr);
 
-    let grid_width = cell_width * dimensions.columns.0.max(MIN_COLS) as f32;
+    let window_config = &config.ui_config.window;
+    let columns = dimensions.columns.0.max(MIN_COLS) + window_config.gutter + window_config.minimap;
+    let grid_width = cell_width * columns as f32;
     let grid_height = cell_height * dimensions.lines.0.max(MIN_SCREEN_LINES) as f32;
 
     let width = (padding.0).mul_add(2., grid_width).floor();
@@ -734,3 +1509,19 @@ fn window_size(
 
     PhysicalSize::new(width as u32, height as u32)
 }
+
+/// Report padding added to keep the grid on whole cells in integer scaling mode.
+fn log_integer_scaling_padding(config: &Config, padding: (f32, f32), size_info: &SizeInfo) {
+    if !config.ui_config.renderer.integer_scaling {
+        return;
+    }
+
+    let (padding_x, padding_y) = (padding.0.floor(), padding.1.floor());
+    if size_info.padding_x() > padding_x || size_info.padding_y() > padding_y {
+        let (x, y) = (size_info.padding_x(), size_info.padding_y());
+        info!(
+            "Padding adjusted from {} x {} to {} x {} to fit whole cells",
+            padding_x, padding_y, x, y
+        );
+    }
+}
@@ -4,30 +4,36 @@ use std::borrow::Cow;
 use std::cmp::{max, min};
 use std::env;
 use std::fmt::Debug;
-#[cfg(unix)]
-use std::fs;
 use std::fs::File;
 use std::io::Write;
 use std::mem;
 use std::path::PathBuf;
 #[cfg(not(any(target_os = "macos", windows)))]
 use std::sync::atomic::Ordering;
+#[cfg(unix)]
+use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use glutin::dpi::PhysicalSize;
-use glutin::event::{ElementState, Event as GlutinEvent, ModifiersState, MouseButton, WindowEvent};
+use glutin::event::{
+    ElementState, Event as GlutinEvent, KeyboardInput, ModifiersState, MouseButton, WindowEvent,
+};
 use glutin::event_loop::{ControlFlow, EventLoop, EventLoopProxy, EventLoopWindowTarget};
 use glutin::platform::desktop::EventLoopExtDesktop;
 #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
 use glutin::platform::unix::EventLoopWindowTargetExtUnix;
-use log::info;
+use log::{info, warn};
 use serde_json as json;
+#[cfg(unix)]
+use serde_yaml::Value;
 
 #[cfg(target_os = "macos")]
 use crossfont::set_font_smoothing;
 use crossfont::{self, Size};
 
+#[cfg(unix)]
+use alacritty_terminal::ansi::{self, Handler};
 use alacritty_terminal::config::LOG_TARGET_CONFIG;
 use alacritty_terminal::event::{Event as TerminalEvent, EventListener, Notify, OnResize};
 use alacritty_terminal::grid::{Dimensions, Scroll};
@@ -35,20 +41,35 @@ use alacritty_terminal::index::{Boundary, Column, Direction, Line, Point, Side};
 use alacritty_terminal::selection::{Selection, SelectionType};
 use alacritty_terminal::sync::FairMutex;
 use alacritty_terminal::term::cell::Cell;
+use alacritty_terminal::term::search::Match;
 use alacritty_terminal::term::{ClipboardType, SizeInfo, Term, TermMode};
-#[cfg(not(windows))]
-use alacritty_terminal::tty;
 
+use crate::appearance::Appearance;
 use crate::cli::Options as CLIOptions;
 use crate::clipboard::Clipboard;
+use crate::clipboard_history::ClipboardHistory;
 use crate::config;
+#[cfg(unix)]
+use crate::config::serde_utils;
+use crate::config::theme;
 use crate::config::Config;
+use crate::cursor_locator::CursorLocator;
 use crate::daemon::start_daemon;
 use crate::display::{Display, DisplayUpdate};
+use crate::glyph_inspector::GlyphInspector;
+use crate::hint::{HintAction, Hints};
 use crate::input::{self, ActionContext as _, FONT_SIZE_STEP};
-use crate::message_bar::{Message, MessageBuffer};
+#[cfg(unix)]
+use crate::ipc::{SocketMessage, SocketReply, SocketRequest};
+use crate::key_overlay::KeyOverlay;
+use crate::message_bar::{Message, MessageBuffer, MessageType};
+use crate::process;
+use crate::region_capture::{self, CaptureFormat, RegionCapture};
+#[cfg(unix)]
+use crate::renderer::RenderStats;
 use crate::scheduler::{Scheduler, TimerId};
-use crate::url::{Url, Urls};
+use crate::touch::TouchGestures;
+use crate::url::{resolve_path, Url, UrlKind, Urls};
 use crate::window::Window;
 
 /// Duration after the last user input until an unlimited search is performed.
@@ -57,15 +78,31 @@ pub const TYPING_SEARCH_DELAY: Duration = Duration::from_millis(500);
 /// Maximum number of lines for the blocking search while still typing the search regex.
 const MAX_SEARCH_WHILE_TYPING: Option<usize> = Some(1000);
 
+/// Message bar target of the window close confirmation.
+const CLOSE_CONFIRMATION_TARGET: &str = "close_confirmation";
+
+/// Background opacities switched between by `CycleBackgroundOpacity`, from opaque to transparent.
+const BACKGROUND_OPACITY_STEPS: [f32; 4] = [1., 0.9, 0.75, 0.5];
+
 /// Events dispatched through the UI event loop.
 #[derive(Debug, Clone)]
 pub enum Event {
     TerminalEvent(TerminalEvent),
     DPRChanged(f64, (u32, u32)),
+    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+    FractionalScaleChanged(f64),
     Scroll(Scroll),
     ConfigReload(PathBuf),
     Message(Message),
     SearchNext,
+    SearchCount,
+    BlinkCursor,
+    AppearanceChanged(Appearance),
+    ReducedMotionChanged(bool),
+    TouchLongPress,
+    TouchMomentum,
+    #[cfg(unix)]
+    SocketMessage(SocketRequest, Sender<SocketReply>),
 }
 
 impl From<Event> for GlutinEvent<'_, Event> {
@@ -93,6 +130,12 @@ pub struct SearchState {
 
     /// Search origin in viewport coordinates relative to original display offset.
     origin: Point,
+
+    /// Match the search has jumped to.
+    focused_match: Option<Match>,
+
+    /// Index of the focused match and the total number of matches in the buffer.
+    match_position: Option<(usize, usize)>,
 }
 
 impl SearchState {
@@ -109,6 +152,11 @@ impl SearchState {
     pub fn direction(&self) -> Direction {
         self.direction
     }
+
+    /// One-based index of the focused match and the total number of matches, once counted.
+    pub fn match_position(&self) -> Option<(usize, usize)> {
+        self.match_position
+    }
 }
 
 impl Default for SearchState {
@@ -118,6 +166,8 @@ impl Default for SearchState {
             display_offset_delta: 0,
             origin: Point::default(),
             regex: None,
+            focused_match: None,
+            match_position: None,
         }
     }
 }
@@ -139,13 +189,27 @@ pub struct ActionContext<'a, N, T> {
     pub urls: &'a Urls,
     pub scheduler: &'a mut Scheduler,
     pub search_state: &'a mut SearchState,
-    cli_options: &'a CLIOptions,
+    pub key_overlay: &'a mut KeyOverlay,
+    pub glyph_inspector: &'a mut GlyphInspector,
+    pub region_capture: &'a mut RegionCapture,
+    pub hints: &'a mut Hints,
+    pub cursor_locator: &'a mut CursorLocator,
+    pub clipboard_history: &'a mut ClipboardHistory,
+    pub cursor_blink_visible: &'a mut bool,
+    pub background_opacity: &'a mut Option<f32>,
+    #[cfg(unix)]
+    pub render_stats: RenderStats,
+    last_title_update: &'a mut Option<Instant>,
+    cli_options: &'a mut CLIOptions,
     font_size: &'a mut Size,
+    appearance: &'a mut Option<Appearance>,
+    color_scheme: &'a mut Option<String>,
 }
 
 impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionContext<'a, N, T> {
     fn write_to_pty<B: Into<Cow<'static, [u8]>>>(&mut self, val: B) {
         self.notifier.notify(val);
+        self.reset_cursor_blink();
     }
 
     fn size_info(&self) -> SizeInfo {
@@ -180,6 +244,8 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
     fn copy_selection(&mut self, ty: ClipboardType) {
         if let Some(selected) = self.terminal.selection_to_string() {
             if !selected.is_empty() {
+                let history_size = self.config.selection.history_size();
+                self.clipboard_history.push(selected.clone(), history_size);
                 self.clipboard.store(ty, selected);
             }
         }
@@ -302,24 +368,11 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         let mut env_args = env::args();
         let alacritty = env_args.next().unwrap();
 
+        // Use working directory of controlling process, or fallback to initial shell.
         #[cfg(unix)]
-        let mut args = {
-            // Use working directory of controlling process, or fallback to initial shell.
-            let mut pid = unsafe { libc::tcgetpgrp(tty::master_fd()) };
-            if pid < 0 {
-                pid = tty::child_pid();
-            }
-
-            #[cfg(not(target_os = "freebsd"))]
-            let link_path = format!("/proc/{}/cwd", pid);
-            #[cfg(target_os = "freebsd")]
-            let link_path = format!("/compat/linux/proc/{}/cwd", pid);
-
-            // Add the current working directory as parameter.
-            fs::read_link(link_path)
-                .map(|path| vec!["--working-directory".into(), path])
-                .unwrap_or_default()
-        };
+        let mut args = process::foreground_cwd()
+            .map(|path| vec!["--working-directory".into(), path])
+            .unwrap_or_default();
 
         #[cfg(not(unix))]
         let mut args: Vec<PathBuf> = Vec::new();
@@ -348,27 +401,228 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
 
         if let Some(ref launcher) = self.config.ui_config.mouse.url.launcher {
             let mut args = launcher.args().to_vec();
-            let start = self.terminal.visible_to_buffer(url.start());
-            let end = self.terminal.visible_to_buffer(url.end());
-            args.push(self.terminal.bounds_to_string(start, end));
+            let text = self.url_text(&url);
+
+            match url.kind() {
+                UrlKind::Url | UrlKind::Hyperlink(_) => args.push(text),
+                UrlKind::Path => {
+                    #[cfg(not(any(target_os = "macos", windows)))]
+                    let home = env::var_os("HOME").map(PathBuf::from);
+                    #[cfg(any(target_os = "macos", windows))]
+                    let home = dirs::home_dir();
+                    let cwd = process::foreground_cwd();
+                    let path = resolve_path(&text, home.as_deref(), cwd.as_deref());
+
+                    // Don't launch anything for text which just looks like a path.
+                    if !path.exists() {
+                        return;
+                    }
+
+                    args.push(path.to_string_lossy().into_owned());
+                },
+            }
 
             start_daemon(launcher.program(), &args);
         }
     }
 
+    /// Copy the text of a URL to the clipboard.
+    fn copy_url(&mut self, url: Url) {
+        let text = self.url_text(&url);
+        self.clipboard.store(ClipboardType::Clipboard, text);
+    }
+
     fn change_font_size(&mut self, delta: f32) {
         *self.font_size = max(*self.font_size + delta, Size::new(FONT_SIZE_STEP));
         let font = self.config.ui_config.font.clone().with_size(*self.font_size);
-        self.display_update_pending.set_font(font);
+        self.display_update_pending.set_font_animated(font);
         self.terminal.dirty = true;
     }
 
     fn reset_font_size(&mut self) {
         *self.font_size = self.config.ui_config.font.size;
-        self.display_update_pending.set_font(self.config.ui_config.font.clone());
+        self.display_update_pending.set_font_animated(self.config.ui_config.font.clone());
+        self.terminal.dirty = true;
+    }
+
+    fn toggle_atlas_overlay(&mut self) {
+        let debug = &mut self.config.ui_config.debug;
+        debug.atlas_overlay = !debug.atlas_overlay;
+        self.terminal.dirty = true;
+    }
+
+    fn dump_atlases(&mut self) {
+        self.display_update_pending.set_dump_atlases();
+    }
+
+    fn diagnostic_bundle(&mut self) {
+        self.display_update_pending.set_diagnostic_bundle();
+    }
+
+    fn toggle_renderer_hud(&mut self) {
+        let debug = &mut self.config.ui_config.debug;
+        debug.render_timer = !debug.render_timer;
+        self.terminal.dirty = true;
+    }
+
+    fn reload_shaders(&mut self) {
+        self.display_update_pending.set_reload_shaders();
+        self.terminal.dirty = true;
+    }
+
+    /// Switch to the next opacity step below the current opacity, starting over once the most
+    /// transparent step is reached.
+    fn cycle_background_opacity(&mut self) {
+        let configured = self.config.ui_config.background_opacity();
+        let opacity = self.background_opacity.unwrap_or(configured);
+        let next = BACKGROUND_OPACITY_STEPS.iter().find(|&&step| step < opacity - f32::EPSILON);
+        *self.background_opacity = Some(*next.unwrap_or(&BACKGROUND_OPACITY_STEPS[0]));
+        self.terminal.dirty = true;
+    }
+
+    /// Switch to the next color scheme of `theme.schemes`, returning to the top-level colors
+    /// after the last one.
+    fn toggle_color_scheme(&mut self) {
+        let next = self.config.ui_config.theme.next_scheme(self.color_scheme.as_deref());
+        self.select_color_scheme(next);
+    }
+
+    fn set_color_scheme(&mut self, name: &str) {
+        if !self.config.ui_config.theme.schemes.contains_key(name) {
+            warn!("Unknown color scheme `{}`", name);
+            return;
+        }
+
+        self.select_color_scheme(Some(name.to_owned()));
+    }
+
+    #[inline]
+    fn toggle_glyph_inspector(&mut self) {
+        self.glyph_inspector.toggle();
+        self.terminal.dirty = true;
+    }
+
+    fn glyph_inspector(&self) -> &GlyphInspector {
+        self.glyph_inspector
+    }
+
+    /// Glyph inspector state, redrawing it after any change.
+    fn glyph_inspector_mut(&mut self) -> &mut GlyphInspector {
+        self.terminal.dirty = true;
+        self.glyph_inspector
+    }
+
+    #[inline]
+    fn toggle_key_overlay(&mut self) {
+        let key_overlay = &mut self.config.ui_config.key_overlay;
+        key_overlay.enabled = !key_overlay.enabled;
+        self.key_overlay.clear();
+        self.terminal.dirty = true;
+    }
+
+    fn toggle_region_capture(&mut self) {
+        if self.region_capture.is_active() {
+            self.region_capture.cancel();
+        } else {
+            self.region_capture.start(self.terminal.grid().cursor.point);
+        }
+        self.terminal.dirty = true;
+    }
+
+    fn region_capture(&self) -> &RegionCapture {
+        self.region_capture
+    }
+
+    /// Capture region state, redrawing it after any change.
+    fn region_capture_mut(&mut self) -> &mut RegionCapture {
+        self.terminal.dirty = true;
+        self.region_capture
+    }
+
+    /// Copy the region to the clipboard, or read it back from the next frame for PNG export.
+    fn export_region(&mut self) {
+        let region = self.region_capture.region();
+        match self.region_capture.format() {
+            CaptureFormat::Png => self.region_capture.request_screenshot(),
+            format => {
+                let ansi = format == CaptureFormat::Ansi;
+                let text = region_capture::region_text(self.terminal, region, ansi);
+                self.clipboard.store(ClipboardType::Clipboard, text);
+                self.region_capture.cancel();
+            },
+        }
+        self.terminal.dirty = true;
+    }
+
+    fn start_hints(&mut self, action: HintAction) {
+        let alphabet = self.config.ui_config.hints.alphabet();
+        self.hints.start(action, self.urls, alphabet);
+        self.terminal.dirty = true;
+    }
+
+    fn hints(&self) -> &Hints {
+        self.hints
+    }
+
+    /// Hint state, redrawing the labels after any change.
+    fn hints_mut(&mut self) -> &mut Hints {
+        self.terminal.dirty = true;
+        self.hints
+    }
+
+    fn locate_cursor(&mut self) {
+        self.cursor_locator.start(Instant::now());
+        self.terminal.dirty = true;
+    }
+
+    fn open_clipboard_history(&mut self) {
+        self.clipboard_history.open();
         self.terminal.dirty = true;
     }
 
+    fn clipboard_history(&self) -> &ClipboardHistory {
+        self.clipboard_history
+    }
+
+    /// Clipboard history state, redrawing the chooser after any change.
+    fn clipboard_history_mut(&mut self) -> &mut ClipboardHistory {
+        self.terminal.dirty = true;
+        self.clipboard_history
+    }
+
+    fn show_key(&mut self, input: KeyboardInput) {
+        if self.config.ui_config.key_overlay.enabled {
+            self.key_overlay.push(input, *self.modifiers);
+            self.terminal.dirty = true;
+        }
+    }
+
+    fn request_close(&mut self) {
+        // Closing again while the confirmation is visible confirms it.
+        let confirming = self
+            .message_buffer
+            .message()
+            .and_then(|message| message.target())
+            .map_or(false, |target| target == CLOSE_CONFIRMATION_TARGET);
+
+        let confirm_close = &self.config.ui_config.window.confirm_close;
+        let process = match process::foreground_name() {
+            Some(process) if !confirming && confirm_close.required(&process) => process,
+            _ => {
+                self.terminal.exit();
+                return;
+            },
+        };
+
+        let text = format!("{} is still running, close the window again to quit", process);
+        let mut message = Message::new(text, MessageType::Warning);
+        message.set_target(CLOSE_CONFIRMATION_TARGET.to_owned());
+
+        self.message_buffer.remove_target(CLOSE_CONFIRMATION_TARGET);
+        self.message_buffer.push(message);
+        self.display_update_pending.dirty = true;
+    }
+
     #[inline]
     fn pop_message(&mut self) {
         if !self.message_buffer.is_empty() {
@@ -514,6 +768,52 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
 }
 
 impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
+    /// Target of a URL, which is the text on screen unless it is a hyperlink.
+    fn url_text(&self, url: &Url) -> String {
+        if let UrlKind::Hyperlink(id) = url.kind() {
+            return self.terminal.hyperlink(id).map(|link| link.uri.clone()).unwrap_or_default();
+        }
+
+        let start = self.terminal.visible_to_buffer(url.start());
+        let end = self.terminal.visible_to_buffer(url.end());
+        self.terminal.bounds_to_string(start, end)
+    }
+
+    /// Replace the active colors with a scheme of `theme.schemes`.
+    ///
+    /// Without a scheme, the colors follow the desktop appearance again.
+    fn select_color_scheme(&mut self, scheme: Option<String>) {
+        match &scheme {
+            Some(scheme) => info!("Switching to color scheme {}", scheme),
+            None => info!("Switching to the default colors"),
+        }
+
+        *self.color_scheme = scheme;
+        theme::apply_colors(self.config, self.color_scheme.as_deref(), *self.appearance);
+        self.terminal.update_config(self.config);
+
+        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+        if self.event_loop.is_wayland() {
+            self.window.set_wayland_theme(self.config);
+        }
+
+        self.terminal.dirty = true;
+    }
+
+    /// Show the blinking cursor and restart its blink interval, so it stays visible while typing.
+    fn reset_cursor_blink(&mut self) {
+        if self.scheduler.unschedule(TimerId::BlinkCursor).is_some() {
+            let interval = self.config.cursor.blink_interval();
+            let event = Event::BlinkCursor.into();
+            self.scheduler.schedule(event, interval, true, TimerId::BlinkCursor);
+        }
+
+        if !*self.cursor_blink_visible {
+            *self.cursor_blink_visible = true;
+            self.terminal.dirty = true;
+        }
+    }
+
     fn update_search(&mut self) {
         let regex = match self.search_state.regex.as_mut() {
             Some(regex) => regex,
@@ -529,6 +829,8 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
             // Stop search if there's nothing to search for.
             self.search_reset_state();
             self.terminal.cancel_search();
+            self.search_state.focused_match = None;
+            self.search_state.match_position = None;
 
             // Restart search without vi mode to clear the search origin.
             if !self.terminal.mode().contains(TermMode::VI) {
@@ -595,10 +897,27 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
 
                 // Since we found a result, we require no delayed re-search.
                 self.scheduler.unschedule(TimerId::DelayedSearch);
+
+                // Count matches of the whole buffer only once typing has stopped.
+                self.search_state.focused_match = Some(regex_match);
+                self.search_state.match_position = None;
+                self.scheduler.unschedule(TimerId::SearchCount);
+                self.scheduler.schedule(
+                    Event::SearchCount.into(),
+                    TYPING_SEARCH_DELAY,
+                    false,
+                    TimerId::SearchCount,
+                );
             },
             // Reset viewport only when we know there is no match, to prevent unnecessary jumping.
-            None if limit.is_none() => self.search_reset_state(),
+            None if limit.is_none() => {
+                self.search_reset_state();
+                self.search_state.focused_match = None;
+                self.search_state.match_position = Some((0, 0));
+            },
             None => {
+                self.search_state.match_position = None;
+
                 // Schedule delayed search if we ran into our search limit.
                 if !self.scheduler.scheduled(TimerId::DelayedSearch) {
                     self.scheduler.schedule(
@@ -626,9 +945,21 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
 
         self.display_update_pending.dirty = true;
         self.search_state.regex = None;
+        self.search_state.focused_match = None;
+        self.search_state.match_position = None;
+        self.scheduler.unschedule(TimerId::SearchCount);
         self.terminal.dirty = true;
     }
 
+    /// Count the matches of the active search for the search bar.
+    fn count_matches(&mut self) {
+        if let Some(focused_match) = &self.search_state.focused_match {
+            self.search_state.match_position =
+                self.terminal.search_match_position(focused_match).or(Some((0, 0)));
+            self.terminal.dirty = true;
+        }
+    }
+
     /// Get the absolute position of the search origin.
     ///
     /// This takes the relative motion of the viewport since the start of the search into account.
@@ -670,6 +1001,12 @@ pub struct Mouse {
     pub lines_scrolled: f32,
     pub block_url_launcher: bool,
     pub inside_text_area: bool,
+
+    /// Minimap line under the mouse while the minimap is dragged.
+    pub minimap_line: Option<isize>,
+
+    /// Touchscreen gestures in progress.
+    pub touch: TouchGestures,
 }
 
 impl Default for Mouse {
@@ -690,6 +1027,8 @@ impl Default for Mouse {
             lines_scrolled: 0.,
             block_url_launcher: false,
             inside_text_area: false,
+            minimap_line: None,
+            touch: TouchGestures::default(),
         }
     }
 }
@@ -712,6 +1051,9 @@ pub struct Processor<N> {
     event_queue: Vec<GlutinEvent<'static, Event>>,
     search_state: SearchState,
     cli_options: CLIOptions,
+    appearance: Option<Appearance>,
+    color_scheme: Option<String>,
+    last_title_update: Option<Instant>,
 }
 
 impl<N: Notify + OnResize> Processor<N> {
@@ -724,6 +1066,7 @@ impl<N: Notify + OnResize> Processor<N> {
         config: Config,
         display: Display,
         cli_options: CLIOptions,
+        appearance: Option<Appearance>,
     ) -> Processor<N> {
         #[cfg(not(any(target_os = "macos", windows)))]
         let clipboard = Clipboard::new(display.window.wayland_display());
@@ -744,6 +1087,9 @@ impl<N: Notify + OnResize> Processor<N> {
             clipboard,
             search_state: SearchState::new(),
             cli_options,
+            appearance,
+            color_scheme: None,
+            last_title_update: None,
         }
     }
 
@@ -820,14 +1166,29 @@ impl<N: Notify + OnResize> Processor<N> {
                 // Since we remap that event to remove the lifetime, this is safe.
                 event => unsafe {
                     *control_flow = ControlFlow::Poll;
+
+                    // Redraw immediately when activity ends the idle dimming.
+                    if Self::is_activity(&event)
+                        && self.display.idle.activity(&self.config.ui_config.idle, Instant::now())
+                    {
+                        let wakeup: Event = TerminalEvent::Wakeup.into();
+                        self.event_queue.push(wakeup.into());
+                    }
+
                     self.event_queue.push(mem::transmute(event));
                     return;
                 },
             }
 
+            if self.event_queue.iter().any(Self::damages_display) {
+                self.display.damage();
+            }
+
             let mut terminal = terminal.lock();
 
             let mut display_update_pending = DisplayUpdate::default();
+            #[cfg(unix)]
+            let render_stats = self.display.render_stats();
             let old_is_searching = self.search_state.regex.is_some();
 
             let context = ActionContext {
@@ -847,7 +1208,20 @@ impl<N: Notify + OnResize> Processor<N> {
                 urls: &self.display.urls,
                 scheduler: &mut scheduler,
                 search_state: &mut self.search_state,
-                cli_options: &self.cli_options,
+                key_overlay: &mut self.display.key_overlay,
+                glyph_inspector: &mut self.display.glyph_inspector,
+                region_capture: &mut self.display.region_capture,
+                hints: &mut self.display.hints,
+                cursor_locator: &mut self.display.cursor_locator,
+                clipboard_history: &mut self.display.clipboard_history,
+                cursor_blink_visible: &mut self.display.cursor_blink_visible,
+                background_opacity: &mut self.display.background_opacity,
+                #[cfg(unix)]
+                render_stats,
+                last_title_update: &mut self.last_title_update,
+                cli_options: &mut self.cli_options,
+                appearance: &mut self.appearance,
+                color_scheme: &mut self.color_scheme,
                 event_loop,
             };
             let mut processor = input::Processor::new(context, &self.display.highlighted_url);
@@ -861,13 +1235,15 @@ impl<N: Notify + OnResize> Processor<N> {
                 self.submit_display_update(&mut terminal, old_is_searching, display_update_pending);
             }
 
+            self.update_cursor_blinking(&mut terminal, &mut scheduler);
+
             // Skip rendering on Wayland until we get frame event from compositor.
             #[cfg(not(any(target_os = "macos", windows)))]
             if !self.display.is_x11 && !self.display.window.should_draw.load(Ordering::Relaxed) {
                 return;
             }
 
-            if terminal.dirty {
+            if terminal.dirty && !Self::sync_update_pending(&terminal, &mut scheduler) {
                 terminal.dirty = false;
 
                 // Request immediate re-draw if visual bell animation is not finished yet.
@@ -887,6 +1263,48 @@ impl<N: Notify + OnResize> Processor<N> {
                     self.modifiers,
                     &self.search_state,
                 );
+
+                // Quit once all recorded frames have been written to disk.
+                if self.display.replay_exported() {
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+
+                // Request immediate re-draw while the renderer is animating.
+                if self.display.is_animating(&self.config) {
+                    let event: Event = TerminalEvent::Wakeup.into();
+                    self.event_queue.push(event.into());
+
+                    *control_flow = ControlFlow::Poll;
+                }
+
+                // Redraw once blinking text changes its visibility.
+                match self.display.text_blink_timeout() {
+                    Some(timeout) if !scheduler.scheduled(TimerId::BlinkText) => {
+                        let event: Event = TerminalEvent::Wakeup.into();
+                        scheduler.schedule(event.into(), timeout, false, TimerId::BlinkText);
+                    },
+                    Some(_) => (),
+                    None => {
+                        scheduler.unschedule(TimerId::BlinkText);
+                    },
+                }
+            }
+
+            // Redraw once the terminal becomes idle.
+            scheduler.unschedule(TimerId::Idle);
+            let idle_config = &self.config.ui_config.idle;
+            if let Some(timeout) = self.display.idle.timeout(idle_config, Instant::now()) {
+                let event: Event = TerminalEvent::Wakeup.into();
+                scheduler.schedule(event.into(), timeout, false, TimerId::Idle);
+            }
+
+            // Wake up for timers scheduled while processing the events.
+            if *control_flow != ControlFlow::Poll {
+                *control_flow = match scheduler.next_deadline() {
+                    Some(instant) => ControlFlow::WaitUntil(instant),
+                    None => ControlFlow::Wait,
+                };
             }
         });
 
@@ -908,17 +1326,19 @@ impl<N: Notify + OnResize> Processor<N> {
         match event {
             GlutinEvent::UserEvent(event) => match event {
                 Event::DPRChanged(scale_factor, (width, height)) => {
-                    let display_update_pending = &mut processor.ctx.display_update_pending;
-
-                    // Push current font to update its DPR.
-                    let font = processor.ctx.config.ui_config.font.clone();
-                    display_update_pending.set_font(font.with_size(*processor.ctx.font_size));
+                    // Winit only knows about integer scale factors on Wayland.
+                    let window = &processor.ctx.window;
+                    let scale_factor = window.fractional_scale().unwrap_or(scale_factor);
+                    let size = window.scaled_size(PhysicalSize::new(width, height));
 
-                    // Resize to event's dimensions, since no resize event is emitted on Wayland.
-                    display_update_pending.set_dimensions(PhysicalSize::new(width, height));
+                    Self::update_dpr(scale_factor, size, processor);
+                },
+                #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+                Event::FractionalScaleChanged(scale_factor) => {
+                    processor.ctx.window.set_fractional_scale(scale_factor);
+                    let size = processor.ctx.window.inner_size();
 
-                    processor.ctx.window.dpr = scale_factor;
-                    processor.ctx.terminal.dirty = true;
+                    Self::update_dpr(scale_factor, size, processor);
                 },
                 Event::Message(message) => {
                     processor.ctx.message_buffer.push(message);
@@ -926,19 +1346,38 @@ impl<N: Notify + OnResize> Processor<N> {
                     processor.ctx.terminal.dirty = true;
                 },
                 Event::SearchNext => processor.ctx.goto_match(None),
-                Event::ConfigReload(path) => Self::reload_config(&path, processor),
+                Event::SearchCount => processor.ctx.count_matches(),
+                Event::BlinkCursor => {
+                    *processor.ctx.cursor_blink_visible ^= true;
+                    processor.ctx.terminal.dirty = true;
+                },
+                Event::AppearanceChanged(appearance) => {
+                    Self::update_appearance(appearance, processor)
+                },
+                Event::ReducedMotionChanged(reduced_motion) => {
+                    Self::update_reduced_motion(reduced_motion, processor)
+                },
+                Event::ConfigReload(path) => Self::reload_config(Some(&path), processor),
+                #[cfg(unix)]
+                Event::SocketMessage(request, reply) => {
+                    // The client might not wait for the reply anymore.
+                    let _ = reply.send(Self::handle_socket_message(request, processor));
+                },
                 Event::Scroll(scroll) => processor.ctx.scroll(scroll),
+                Event::TouchLongPress => processor.touch_long_press(),
+                Event::TouchMomentum => processor.touch_momentum(),
                 Event::TerminalEvent(event) => match event {
                     TerminalEvent::Title(title) => {
-                        let ui_config = &processor.ctx.config.ui_config;
-                        if ui_config.dynamic_title() {
-                            processor.ctx.window.set_title(&title);
+                        if processor.ctx.config.ui_config.dynamic_title() {
+                            Self::update_title(title, processor);
                         }
                     },
                     TerminalEvent::ResetTitle => {
                         let ui_config = &processor.ctx.config.ui_config;
                         if ui_config.dynamic_title() {
+                            processor.ctx.scheduler.unschedule(TimerId::Title);
                             processor.ctx.window.set_title(&ui_config.window.title);
+                            *processor.ctx.last_title_update = Some(Instant::now());
                         }
                     },
                     TerminalEvent::Wakeup => processor.ctx.terminal.dirty = true,
@@ -963,7 +1402,7 @@ impl<N: Notify + OnResize> Processor<N> {
             GlutinEvent::RedrawRequested(_) => processor.ctx.terminal.dirty = true,
             GlutinEvent::WindowEvent { event, window_id, .. } => {
                 match event {
-                    WindowEvent::CloseRequested => processor.ctx.terminal.exit(),
+                    WindowEvent::CloseRequested => processor.ctx.request_close(),
                     WindowEvent::Resized(size) => {
                         // Minimizing the window sends a Resize event with zero width and
                         // height. But there's no need to ever actually resize to this.
@@ -974,6 +1413,7 @@ impl<N: Notify + OnResize> Processor<N> {
                             return;
                         }
 
+                        let size = processor.ctx.window.scaled_size(size);
                         processor.ctx.display_update_pending.set_dimensions(size);
                         processor.ctx.terminal.dirty = true;
                     },
@@ -989,8 +1429,13 @@ impl<N: Notify + OnResize> Processor<N> {
                     WindowEvent::ModifiersChanged(modifiers) => {
                         processor.modifiers_input(modifiers)
                     },
+                    WindowEvent::Touch(touch) => {
+                        let position = processor.ctx.window.scaled_position(touch.location);
+                        processor.touch(touch.id, touch.phase, position);
+                    },
                     WindowEvent::CursorMoved { position, .. } => {
                         processor.ctx.window.set_mouse_visible(true);
+                        let position = processor.ctx.window.scaled_position(position);
                         processor.mouse_moved(position);
                     },
                     WindowEvent::MouseWheel { delta, phase, .. } => {
@@ -1004,6 +1449,24 @@ impl<N: Notify + OnResize> Processor<N> {
 
                             if is_focused {
                                 processor.ctx.window.set_urgent(false);
+
+                                // There is no appearance change event on macOS, so check on focus.
+                                #[cfg(target_os = "macos")]
+                                if processor.ctx.config.ui_config.theme.follows_appearance() {
+                                    if let Some(appearance) = crate::appearance::current() {
+                                        Self::update_appearance(appearance, processor);
+                                    }
+                                }
+
+                                // The motion preference is only watched through the desktop portal.
+                                #[cfg(any(target_os = "macos", windows))]
+                                if processor.ctx.config.ui_config.animations.follow_system() {
+                                    if let Some(reduced_motion) =
+                                        crate::appearance::reduced_motion()
+                                    {
+                                        Self::update_reduced_motion(reduced_motion, processor);
+                                    }
+                                }
                             } else {
                                 processor.ctx.window.set_mouse_visible(true);
                             }
@@ -1015,6 +1478,9 @@ impl<N: Notify + OnResize> Processor<N> {
                         let path: String = path.to_string_lossy().into();
                         processor.ctx.write_to_pty((path + " ").into_bytes());
                     },
+                    WindowEvent::ThemeChanged(theme) => {
+                        Self::update_appearance(theme.into(), processor);
+                    },
                     WindowEvent::CursorLeft { .. } => {
                         processor.ctx.mouse.inside_text_area = false;
 
@@ -1029,9 +1495,7 @@ impl<N: Notify + OnResize> Processor<N> {
                     | WindowEvent::AxisMotion { .. }
                     | WindowEvent::HoveredFileCancelled
                     | WindowEvent::Destroyed
-                    | WindowEvent::ThemeChanged(_)
                     | WindowEvent::HoveredFile(_)
-                    | WindowEvent::Touch(_)
                     | WindowEvent::Moved(_) => (),
                 }
             },
@@ -1057,7 +1521,6 @@ impl<N: Notify + OnResize> Processor<N> {
                     | WindowEvent::HoveredFileCancelled
                     | WindowEvent::Destroyed
                     | WindowEvent::HoveredFile(_)
-                    | WindowEvent::Touch(_)
                     | WindowEvent::Moved(_)
             ),
             GlutinEvent::Suspended { .. }
@@ -1068,8 +1531,37 @@ impl<N: Notify + OnResize> Processor<N> {
         }
     }
 
+    /// Check if an event is user input or terminal output, which ends the idle dimming.
+    fn is_activity(event: &GlutinEvent<'_, Event>) -> bool {
+        match event {
+            GlutinEvent::WindowEvent { event, .. } => matches!(
+                event,
+                WindowEvent::KeyboardInput { .. }
+                    | WindowEvent::ReceivedCharacter(_)
+                    | WindowEvent::MouseInput { .. }
+                    | WindowEvent::MouseWheel { .. }
+                    | WindowEvent::CursorMoved { .. }
+                    | WindowEvent::Touch(_)
+            ),
+            GlutinEvent::UserEvent(Event::TerminalEvent(TerminalEvent::Wakeup)) => true,
+            _ => false,
+        }
+    }
+
+    /// Check if an event invalidates the frame on screen, regardless of the terminal content.
+    fn damages_display(event: &GlutinEvent<'_, Event>) -> bool {
+        match event {
+            GlutinEvent::RedrawRequested(_)
+            | GlutinEvent::UserEvent(Event::ConfigReload(_))
+            | GlutinEvent::UserEvent(Event::AppearanceChanged(_)) => true,
+            #[cfg(unix)]
+            GlutinEvent::UserEvent(Event::SocketMessage(..)) => true,
+            _ => false,
+        }
+    }
+
     fn reload_config<T>(
-        path: &PathBuf,
+        path: Option<&PathBuf>,
         processor: &mut input::Processor<'_, T, ActionContext<'_, N, T>>,
     ) where
         T: EventListener,
@@ -1079,13 +1571,24 @@ impl<N: Notify + OnResize> Processor<N> {
             processor.ctx.display_update_pending.dirty = true;
         }
 
-        let config = match config::reload(&path, &processor.ctx.cli_options) {
+        let mut config = match config::reload(path, &processor.ctx.cli_options) {
             Ok(config) => config,
             Err(_) => return,
         };
 
+        // Keep the selected color scheme or the one following the desktop appearance.
+        let color_scheme = processor.ctx.color_scheme.as_deref();
+        theme::apply_colors(&mut config, color_scheme, *processor.ctx.appearance);
+
+        // Keep following the desktop motion preference.
+        let reduced_motion = processor.ctx.config.ui_config.animations.system_reduced_motion();
+        config.ui_config.animations.set_system_reduced_motion(reduced_motion);
+
         processor.ctx.terminal.update_config(&config);
 
+        // Restart blinking with the new interval.
+        processor.ctx.scheduler.unschedule(TimerId::BlinkCursor);
+
         // Reload cursor if we've changed its thickness.
         if (processor.ctx.config.cursor.thickness() - config.cursor.thickness()).abs()
             > std::f64::EPSILON
@@ -1093,7 +1596,12 @@ impl<N: Notify + OnResize> Processor<N> {
             processor.ctx.display_update_pending.set_cursor_dirty();
         }
 
-        if processor.ctx.config.ui_config.font != config.ui_config.font {
+        // Glyphs need to be rasterized again when the render scale changes.
+        let render_scale_changed =
+            (processor.ctx.config.ui_config.render_scale() - config.ui_config.render_scale()).abs()
+                > f32::EPSILON;
+
+        if processor.ctx.config.ui_config.font != config.ui_config.font || render_scale_changed {
             // Do not update font size if it has been changed at runtime.
             if *processor.ctx.font_size == processor.ctx.config.ui_config.font.size {
                 *processor.ctx.font_size = config.ui_config.font.size;
@@ -1103,10 +1611,21 @@ impl<N: Notify + OnResize> Processor<N> {
             processor.ctx.display_update_pending.set_font(font);
         }
 
-        // Update display if padding options were changed.
-        let window_config = &processor.ctx.config.ui_config.window;
-        if window_config.padding(1.) != config.ui_config.window.padding(1.)
-            || window_config.dynamic_padding != config.ui_config.window.dynamic_padding
+        // Reload the background image, since the file itself might have changed.
+        if processor.ctx.config.ui_config.background_image.is_some()
+            || config.ui_config.background_image.is_some()
+        {
+            processor.ctx.display_update_pending.set_background_image_dirty();
+        }
+
+        // Update display if padding options or the post-processing shader were changed.
+        let ui_config = &processor.ctx.config.ui_config;
+        if ui_config.window.padding(1.) != config.ui_config.window.padding(1.)
+            || ui_config.dynamic_padding() != config.ui_config.dynamic_padding()
+            || ui_config.window.gutter != config.ui_config.window.gutter
+            || ui_config.window.minimap != config.ui_config.window.minimap
+            || ui_config.post_processing_shader != config.ui_config.post_processing_shader
+            || ui_config.idle.shader != config.ui_config.idle.shader
         {
             processor.ctx.display_update_pending.dirty = true;
         }
@@ -1120,7 +1639,7 @@ impl<N: Notify + OnResize> Processor<N> {
 
         #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
         if processor.ctx.event_loop.is_wayland() {
-            processor.ctx.window.set_wayland_theme(&config.colors);
+            processor.ctx.window.set_wayland_theme(config);
         }
 
         // Set subpixel anti-aliasing.
@@ -1132,6 +1651,195 @@ impl<N: Notify + OnResize> Processor<N> {
         processor.ctx.terminal.dirty = true;
     }
 
+    /// Switch color schemes after the desktop appearance has changed.
+    /// Apply configuration options sent to the socket.
+    ///
+    /// Like options passed with `--option`, they are kept when the configuration file is reloaded.
+    #[cfg(unix)]
+    fn override_config<T>(
+        options: Value,
+        processor: &mut input::Processor<'_, T, ActionContext<'_, N, T>>,
+    ) where
+        T: EventListener,
+    {
+        let cli_options = &mut processor.ctx.cli_options;
+        let config_options = mem::replace(&mut cli_options.config_options, Value::Null);
+        cli_options.config_options = serde_utils::merge(config_options, options);
+
+        let path = processor.ctx.config.ui_config.config_paths.first().cloned();
+        Self::reload_config(path.as_ref(), processor);
+    }
+
+    /// Handle a message from `alacritty msg`, if its password and permission class are accepted.
+    #[cfg(unix)]
+    fn handle_socket_message<T>(
+        request: SocketRequest,
+        processor: &mut input::Processor<'_, T, ActionContext<'_, N, T>>,
+    ) -> SocketReply
+    where
+        T: EventListener,
+    {
+        let ipc_config = &processor.ctx.config.ui_config.ipc;
+        ipc_config.authorize(request.password.as_deref(), request.message.permission())?;
+
+        match request.message {
+            SocketMessage::Config(options) => Self::override_config(options, processor),
+            SocketMessage::Opacity(opacity) => {
+                *processor.ctx.background_opacity = opacity;
+                processor.ctx.terminal.dirty = true;
+            },
+            SocketMessage::Stats => return Ok(processor.ctx.render_stats.to_string()),
+            SocketMessage::Mark(params) => {
+                let params: Vec<&[u8]> = params.iter().map(|param| param.as_bytes()).collect();
+                let (lines_above, mark) = ansi::parse_mark(&params)
+                    .ok_or_else(|| String::from("Invalid mark parameters"))?;
+                processor.ctx.terminal.set_mark(lines_above, mark);
+                processor.ctx.terminal.dirty = true;
+            },
+            SocketMessage::ClearMarks => {
+                processor.ctx.terminal.clear_marks();
+                processor.ctx.terminal.dirty = true;
+            },
+        }
+
+        Ok(String::new())
+    }
+
+    /// Set a title requested by the terminal, delaying updates which follow each other too
+    /// quickly.
+    ///
+    /// Only the latest of the delayed titles is shown once the update interval has passed.
+    fn update_title<T>(
+        title: String,
+        processor: &mut input::Processor<'_, T, ActionContext<'_, N, T>>,
+    ) where
+        T: EventListener,
+    {
+        let now = Instant::now();
+        let title_config = processor.ctx.config.ui_config.title;
+        match (*processor.ctx.last_title_update, title_config.update_interval()) {
+            (Some(last), Some(interval)) if last + interval > now => {
+                let delay = last + interval - now;
+                let event: Event = TerminalEvent::Title(title).into();
+                let scheduler = &mut processor.ctx.scheduler;
+                match scheduler.get_mut(TimerId::Title) {
+                    Some(timer) => timer.event = event.into(),
+                    None => scheduler.schedule(event.into(), delay, false, TimerId::Title),
+                }
+            },
+            _ => {
+                processor.ctx.window.set_title(&title_config.sanitize(&title));
+                *processor.ctx.last_title_update = Some(now);
+            },
+        }
+    }
+
+    /// Rasterize the font and resize the window for a new scale factor.
+    fn update_dpr<T>(
+        scale_factor: f64,
+        size: PhysicalSize<u32>,
+        processor: &mut input::Processor<'_, T, ActionContext<'_, N, T>>,
+    ) where
+        T: EventListener,
+    {
+        let display_update_pending = &mut processor.ctx.display_update_pending;
+
+        // Push current font to update its DPR.
+        let font = processor.ctx.config.ui_config.font.clone();
+        display_update_pending.set_font(font.with_size(*processor.ctx.font_size));
+
+        // Resize to event's dimensions, since no resize event is emitted on Wayland.
+        display_update_pending.set_dimensions(size);
+
+        processor.ctx.window.dpr = scale_factor;
+        processor.ctx.terminal.dirty = true;
+    }
+
+    fn update_appearance<T>(
+        appearance: Appearance,
+        processor: &mut input::Processor<'_, T, ActionContext<'_, N, T>>,
+    ) where
+        T: EventListener,
+    {
+        if *processor.ctx.appearance == Some(appearance) {
+            return;
+        }
+        *processor.ctx.appearance = Some(appearance);
+
+        if !processor.ctx.config.ui_config.theme.follows_appearance() {
+            return;
+        }
+
+        info!("Desktop appearance changed to {:?}", appearance);
+
+        let color_scheme = processor.ctx.color_scheme.as_deref();
+        theme::apply_colors(processor.ctx.config, color_scheme, Some(appearance));
+        processor.ctx.terminal.update_config(processor.ctx.config);
+
+        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+        if processor.ctx.event_loop.is_wayland() {
+            processor.ctx.window.set_wayland_theme(processor.ctx.config);
+        }
+
+        processor.ctx.terminal.dirty = true;
+    }
+
+    fn update_reduced_motion<T>(
+        reduced_motion: bool,
+        processor: &mut input::Processor<'_, T, ActionContext<'_, N, T>>,
+    ) where
+        T: EventListener,
+    {
+        let animations = &mut processor.ctx.config.ui_config.animations;
+        if animations.system_reduced_motion() == reduced_motion {
+            return;
+        }
+
+        info!("Desktop reduced motion preference changed to {}", reduced_motion);
+
+        animations.set_system_reduced_motion(reduced_motion);
+    }
+
+    /// Whether drawing is held back for a synchronized update in progress.
+    ///
+    /// A redraw is scheduled for when the update times out.
+    fn sync_update_pending<T>(terminal: &Term<T>, scheduler: &mut Scheduler) -> bool {
+        let now = Instant::now();
+        match terminal.sync_deadline() {
+            Some(deadline) if deadline > now => {
+                if !scheduler.scheduled(TimerId::SyncUpdate) {
+                    let event: Event = TerminalEvent::Wakeup.into();
+                    scheduler.schedule(event.into(), deadline - now, false, TimerId::SyncUpdate);
+                }
+                true
+            },
+            _ => {
+                scheduler.unschedule(TimerId::SyncUpdate);
+                false
+            },
+        }
+    }
+
+    /// Start or stop the cursor blink timer when the terminal's blinking state changed.
+    fn update_cursor_blinking<T>(&mut self, terminal: &mut Term<T>, scheduler: &mut Scheduler) {
+        let blinking = terminal.cursor_blinking() && terminal.is_focused;
+        if blinking == scheduler.scheduled(TimerId::BlinkCursor) {
+            return;
+        }
+
+        if blinking {
+            let interval = self.config.cursor.blink_interval();
+            scheduler.schedule(Event::BlinkCursor.into(), interval, true, TimerId::BlinkCursor);
+        } else {
+            scheduler.unschedule(TimerId::BlinkCursor);
+
+            if !self.display.cursor_blink_visible {
+                self.display.cursor_blink_visible = true;
+                terminal.dirty = true;
+            }
+        }
+    }
+
     /// Submit the pending changes to the `Display`.
     fn submit_display_update<T>(
         &mut self,
@@ -1159,6 +1867,9 @@ impl<N: Notify + OnResize> Processor<N> {
             display_update_pending,
         );
 
+        // Fitting the window to a number of columns changes the font size.
+        self.font_size = self.display.font_size();
+
         // Scroll to make sure search origin is visible and content moves as little as possible.
         if !old_is_searching && self.search_state.regex.is_some() {
             let display_offset = terminal.grid().display_offset();
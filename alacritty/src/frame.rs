@@ -0,0 +1,432 @@
+//! Grid content of a frame, collected once for everything presenting it.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+#[cfg(feature = "bidi")]
+use std::mem;
+use std::path::Path;
+use std::slice;
+
+use serde::{Deserialize, Serialize};
+
+use alacritty_terminal::config::Config;
+use alacritty_terminal::index::{Column, Line, Point};
+use alacritty_terminal::term::cell::Flags;
+use alacritty_terminal::term::color::List;
+use alacritty_terminal::term::{RenderableCell, RenderableCellContent, SizeInfo, Term};
+
+#[cfg(feature = "bidi")]
+use crate::bidi;
+use crate::message_bar::Message;
+
+/// Everything drawn for the terminal in a frame, independent of the terminal itself.
+///
+/// Snapshots can be recorded and drawn again later, to reproduce rendering issues without the
+/// program which produced the output.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FrameSnapshot {
+    pub cells: FrameCells,
+    pub terminal_cursor: Option<RenderableCell>,
+    pub colors: List,
+    pub vi_mode_point: Option<Point>,
+    pub visual_bell_intensity: f64,
+    pub idle_dim: f32,
+
+    /// Fraction of the brightness the terminal loses while the window is not focused.
+    #[serde(default)]
+    pub unfocused_dim: f32,
+
+    pub background_opacity: f32,
+    pub message: Option<Message>,
+    pub search_regex: Option<String>,
+    pub search_matches: Option<(usize, usize)>,
+    pub size_info: SizeInfo,
+
+    /// Lines which changed since the previous frame.
+    #[serde(default)]
+    pub damage: Vec<Line>,
+}
+
+impl FrameSnapshot {
+    /// Move right-to-left text from logical into visual order.
+    #[cfg(feature = "bidi")]
+    pub fn reorder_bidi(&mut self, arabic_shaping: bool) {
+        let columns = self.cells.columns;
+        let mut rest = &mut self.cells.cells[..];
+        while let Some(line) = rest.first().map(|cell| cell.line) {
+            let len = rest.iter().position(|cell| cell.line != line).unwrap_or(rest.len());
+            let (cells, tail) = mem::take(&mut rest).split_at_mut(len);
+            rest = tail;
+
+            let map = match bidi::reorder_line(cells, columns, arabic_shaping) {
+                Some(map) => map,
+                None => continue,
+            };
+
+            // The vi mode's hollow terminal cursor follows the character below it.
+            if let Some(cursor) = self.terminal_cursor.as_mut() {
+                if cursor.line == line && cursor.column.0 < columns {
+                    cursor.column = map[cursor.column.0];
+                }
+            }
+        }
+    }
+}
+
+/// Writer appending snapshots to a file, one JSON document per line.
+pub struct SnapshotRecorder {
+    writer: BufWriter<File>,
+}
+
+impl SnapshotRecorder {
+    pub fn new(path: &Path) -> io::Result<Self> {
+        Ok(Self { writer: BufWriter::new(File::create(path)?) })
+    }
+
+    pub fn record(&mut self, snapshot: &FrameSnapshot) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, snapshot)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
+
+/// Snapshots read back from a recording, in the order they were drawn.
+pub struct SnapshotReplay {
+    snapshots: VecDeque<FrameSnapshot>,
+    last: Option<FrameSnapshot>,
+
+    /// Number of snapshots taken from the recording so far.
+    replayed: usize,
+}
+
+impl SnapshotReplay {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut snapshots = VecDeque::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                snapshots.push_back(serde_json::from_str(&line)?);
+            }
+        }
+
+        Ok(Self { snapshots, last: None, replayed: 0 })
+    }
+
+    /// Snapshot for the next frame.
+    ///
+    /// Once all snapshots have been replayed, the last one stays on screen.
+    pub fn next_frame(&mut self) -> Option<FrameSnapshot> {
+        if let Some(snapshot) = self.snapshots.pop_front() {
+            self.last = Some(snapshot);
+            self.replayed += 1;
+        }
+
+        self.last.clone()
+    }
+
+    /// Number of snapshots replayed so far.
+    pub fn replayed(&self) -> usize {
+        self.replayed
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+}
+
+/// Visible cells of the terminal, in the order they are displayed.
+///
+/// Cells without any content are omitted, the cursor is a separate cell following the cell it
+/// is drawn on.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct FrameCells {
+    cells: Vec<RenderableCell>,
+    screen_lines: usize,
+    columns: usize,
+}
+
+impl FrameCells {
+    pub fn new<T, C>(terminal: &Term<T>, config: &Config<C>) -> Self {
+        Self::from_cells(
+            terminal.renderable_cells(config).collect(),
+            terminal.screen_lines().0,
+            terminal.cols().0,
+        )
+    }
+
+    /// Frame of a screen with `screen_lines` and `columns` containing `cells`.
+    pub fn from_cells(cells: Vec<RenderableCell>, screen_lines: usize, columns: usize) -> Self {
+        Self { cells, screen_lines, columns }
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, RenderableCell> {
+        self.cells.iter()
+    }
+
+    /// Cells grouped by line, skipping lines without any cells.
+    pub fn lines(&self) -> FrameLines<'_> {
+        FrameLines { cells: &self.cells }
+    }
+
+    /// Lines whose cells differ from those in `previous`.
+    pub fn damage(&self, previous: &FrameCells) -> Vec<Line> {
+        if self.screen_lines != previous.screen_lines || self.columns != previous.columns {
+            return (0..self.screen_lines).map(Line).collect();
+        }
+
+        self.line_cells()
+            .iter()
+            .zip(previous.line_cells())
+            .enumerate()
+            .filter(|(_, (cells, previous_cells))| *cells != previous_cells)
+            .map(|(line, _)| Line(line))
+            .collect()
+    }
+
+    /// Cells of every line on the screen, including empty ones.
+    fn line_cells(&self) -> Vec<&[RenderableCell]> {
+        let mut cells = vec![&[][..]; self.screen_lines];
+        for line in self.lines() {
+            if let Some(line_cells) = cells.get_mut(line.line.0) {
+                *line_cells = line.cells;
+            }
+        }
+        cells
+    }
+
+    /// Text of every line on the screen, without trailing whitespace.
+    pub fn text(&self) -> Vec<String> {
+        let mut text = vec![String::new(); self.screen_lines];
+        for line in self.lines() {
+            if let Some(line_text) = text.get_mut(line.line.0) {
+                *line_text = line.text(self.columns);
+            }
+        }
+        text
+    }
+}
+
+/// Cells of a single line.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FrameLine<'a> {
+    pub line: Line,
+    pub cells: &'a [RenderableCell],
+}
+
+impl<'a> FrameLine<'a> {
+    /// Characters of the line up to `columns`, with blank cells as spaces.
+    ///
+    /// Trailing whitespace is removed.
+    pub fn text(&self, columns: usize) -> String {
+        let mut text = String::new();
+        let mut column = Column(0);
+        for cell in self.cells {
+            let chars = match cell.inner {
+                RenderableCellContent::Chars(chars) => chars,
+                RenderableCellContent::Cursor(_) => continue,
+            };
+
+            if cell.column.0 >= columns
+                || cell.flags.intersects(Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER)
+            {
+                continue;
+            }
+
+            while column < cell.column {
+                text.push(' ');
+                column += 1;
+            }
+
+            text.push(chars[0]);
+            text.extend(chars[1..].iter().filter(|&&c| c != ' '));
+            column = cell.column + if cell.flags.contains(Flags::WIDE_CHAR) { 2 } else { 1 };
+        }
+
+        text.truncate(text.trim_end().len());
+        text
+    }
+}
+
+/// Iterator over the lines of a frame.
+#[derive(Debug, Clone)]
+pub struct FrameLines<'a> {
+    cells: &'a [RenderableCell],
+}
+
+impl<'a> Iterator for FrameLines<'a> {
+    type Item = FrameLine<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.cells.first()?.line;
+        let len = self.cells.iter().position(|cell| cell.line != line).unwrap_or(self.cells.len());
+
+        let (cells, rest) = self.cells.split_at(len);
+        self.cells = rest;
+
+        Some(FrameLine { line, cells })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alacritty_terminal::ansi::{CursorStyle, Processor};
+    use alacritty_terminal::config::{Colors, MockConfig};
+    use alacritty_terminal::event::{Event, EventListener};
+    use alacritty_terminal::term::cell::MAX_ZEROWIDTH_CHARS;
+    use alacritty_terminal::term::{CellHighlight, CursorKey};
+
+    fn cell(line: usize, column: usize, c: char) -> RenderableCell {
+        let mut chars = [' '; MAX_ZEROWIDTH_CHARS + 1];
+        chars[0] = c;
+
+        RenderableCell {
+            inner: RenderableCellContent::Chars(chars),
+            line: Line(line),
+            column: Column(column),
+            fg: Default::default(),
+            bg: Default::default(),
+            fg_index: None,
+            bg_index: None,
+            bg_alpha: 0.,
+            flags: Flags::empty(),
+            highlight: CellHighlight::None,
+            hyperlink: 0,
+        }
+    }
+
+    /// Pseudo random numbers, reproducible across test runs.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+            &items[(self.next() % items.len() as u64) as usize]
+        }
+    }
+
+    struct Mock;
+
+    impl EventListener for Mock {
+        fn send_event(&self, _event: Event) {}
+    }
+
+    #[test]
+    fn pathological_output_stays_bounded() {
+        const PARAMS: &[&str] = &["", "0", "1", "7", "300", "99999999999999999999"];
+        const FINALS: &[u8] = b"@ABCDEFGHJKLMPSTXZ`abdefmr";
+        const TEXT: &[&str] = &["a", "字", "\u{301}", "\u{20d7}", "\n", "\r", "\t", "\x08"];
+
+        let size = SizeInfo::new(200., 60., 10., 10., 0., 0., false);
+        let config = MockConfig::default();
+        let mut terminal = Term::new(&config, size, Mock);
+        let mut parser = Processor::new();
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..5000 {
+            let output = if rng.next() % 3 == 0 {
+                let params = [*rng.pick(PARAMS), *rng.pick(PARAMS)].join(";");
+                format!("\x1b[{}{}", params, *rng.pick(FINALS) as char)
+            } else {
+                rng.pick(TEXT).repeat(1 + (rng.next() % 8) as usize)
+            };
+
+            for byte in output.bytes() {
+                parser.advance(&mut terminal, byte, &mut io::sink());
+            }
+
+            // Each cell produces at most one quad per character it holds.
+            let frame = FrameCells::new(&terminal, &config);
+            assert!(frame.iter().count() <= 20 * 6 + 1);
+        }
+
+        // Zero-width characters beyond the capacity of a cell are dropped and marked.
+        let output = format!("\x1bce{}", "\u{301}".repeat(MAX_ZEROWIDTH_CHARS + 3));
+        for byte in output.bytes() {
+            parser.advance(&mut terminal, byte, &mut io::sink());
+        }
+
+        let frame = FrameCells::new(&terminal, &config);
+        let cell = frame.iter().next().unwrap();
+        assert_eq!((cell.line, cell.column), (Line(0), Column(0)));
+        assert!(cell.flags.contains(Flags::ZEROWIDTH_TRUNCATED));
+    }
+
+    #[test]
+    fn group_lines() {
+        let cells = vec![cell(0, 0, 'a'), cell(0, 1, 'b'), cell(2, 0, 'c')];
+        let frame = FrameCells::from_cells(cells, 3, 2);
+
+        let lines: Vec<(Line, usize)> =
+            frame.lines().map(|line| (line.line, line.cells.len())).collect();
+        assert_eq!(lines, vec![(Line(0), 2), (Line(2), 1)]);
+    }
+
+    #[test]
+    fn screen_text() {
+        let mut wide = cell(0, 3, '字');
+        wide.flags = Flags::WIDE_CHAR;
+        let mut spacer = cell(0, 4, ' ');
+        spacer.flags = Flags::WIDE_CHAR_SPACER;
+        let mut accent = cell(0, 5, 'e');
+        if let RenderableCellContent::Chars(chars) = &mut accent.inner {
+            chars[1] = '\u{301}';
+        }
+        let mut cursor = cell(0, 6, ' ');
+        cursor.inner =
+            RenderableCellContent::Cursor(CursorKey { style: CursorStyle::Block, is_wide: false });
+
+        let cells = vec![cell(0, 0, 'a'), wide, spacer, accent, cursor, cell(2, 1, 'b')];
+        let frame = FrameCells::from_cells(cells, 3, 10);
+
+        assert_eq!(frame.text(), vec!["a  字e\u{301}", "", " b"]);
+    }
+
+    #[test]
+    fn damaged_lines() {
+        let previous = FrameCells::from_cells(vec![cell(0, 0, 'a'), cell(1, 0, 'b')], 3, 2);
+        let frame = FrameCells::from_cells(vec![cell(0, 0, 'a'), cell(2, 0, 'c')], 3, 2);
+
+        assert_eq!(frame.damage(&previous), vec![Line(1), Line(2)]);
+        assert_eq!(frame.damage(&frame), Vec::<Line>::new());
+
+        let resized = FrameCells::from_cells(Vec::new(), 2, 2);
+        assert_eq!(resized.damage(&frame), vec![Line(0), Line(1)]);
+    }
+
+    #[test]
+    fn snapshot_roundtrip() {
+        let mut cursor = cell(0, 1, ' ');
+        cursor.inner = RenderableCellContent::Cursor(CursorKey {
+            style: CursorStyle::HollowBlock,
+            is_wide: false,
+        });
+
+        let snapshot = FrameSnapshot {
+            cells: FrameCells::from_cells(vec![cell(0, 0, 'a'), cursor], 1, 2),
+            terminal_cursor: Some(cursor),
+            colors: List::from(&Colors::default()),
+            vi_mode_point: Some(Point::new(Line(0), Column(1))),
+            visual_bell_intensity: 0.,
+            idle_dim: 0.,
+            unfocused_dim: 0.5,
+            background_opacity: 1.,
+            message: None,
+            search_regex: Some(String::from("a")),
+            search_matches: Some((1, 1)),
+            size_info: SizeInfo::new(20., 10., 10., 10., 0., 0., false),
+            damage: vec![Line(0)],
+        };
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(serde_json::from_str::<FrameSnapshot>(&json).unwrap(), snapshot);
+    }
+}
@@ -0,0 +1,267 @@
+//! Interactive view of the glyph cache, for debugging font fallback and atlas packing.
+
+use crate::renderer::glyph::{AtlasGlyph, FaceProbe, GlyphInfo};
+
+/// Number of columns reserved for the glyph at the start of every sheet line.
+pub const GLYPH_COLUMNS: usize = 3;
+
+/// Line of the inspector sheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetLine {
+    /// Glyph drawn in front of the text.
+    pub glyph: Option<crossfont::GlyphKey>,
+    pub text: String,
+}
+
+impl SheetLine {
+    fn text(text: String) -> Self {
+        Self { glyph: None, text }
+    }
+}
+
+/// Glyph inspector overlay state.
+#[derive(Debug, Default)]
+pub struct GlyphInspector {
+    active: bool,
+
+    /// Index of the first cached glyph shown.
+    scroll: usize,
+
+    /// Character which is rasterized in all configured fonts.
+    query: String,
+}
+
+impl GlyphInspector {
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+    }
+
+    /// Move the sheet by a number of glyphs.
+    pub fn pan(&mut self, glyphs: isize) {
+        self.scroll = (self.scroll as isize).saturating_add(glyphs).max(0) as usize;
+    }
+
+    /// Character requested for inspection.
+    ///
+    /// Accepts either the character itself or its codepoint, like `U+1F600` or `0x1f600`.
+    pub fn query_char(&self) -> Option<char> {
+        let query = self.query.trim();
+
+        let mut chars = query.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Some(c);
+        }
+
+        let hex = ["U+", "u+", "0x", "0X"].iter().find_map(|prefix| {
+            if query.starts_with(prefix) {
+                Some(&query[prefix.len()..])
+            } else {
+                None
+            }
+        })?;
+        u32::from_str_radix(hex, 16).ok().and_then(std::char::from_u32)
+    }
+
+    /// Lines of the sheet for a screen with `lines` lines.
+    pub fn sheet(
+        &mut self,
+        glyphs: &[GlyphInfo],
+        probes: &[FaceProbe],
+        lines: usize,
+    ) -> Vec<SheetLine> {
+        self.scroll = self.scroll.min(glyphs.len().saturating_sub(1));
+
+        let mut sheet = vec![
+            SheetLine::text(format!("Glyph inspector: {} cached glyphs", glyphs.len())),
+            SheetLine::text(format!("Inspect: {}_", self.query)),
+        ];
+
+        if !self.query.is_empty() && self.query_char().is_none() {
+            sheet.push(SheetLine::text(String::from("Enter a character or a U+ codepoint")));
+        }
+
+        for probe in probes {
+            let used = if probe.used { " (used)" } else { "" };
+            sheet.push(match &probe.glyph {
+                Some(glyph) => SheetLine {
+                    glyph: Some(probe.key),
+                    text: format!(
+                        "{}: {}x{} left {} top {}{}",
+                        probe.font, glyph.width, glyph.height, glyph.left, glyph.top, used
+                    ),
+                },
+                None => SheetLine::text(format!("{}: missing", probe.font)),
+            });
+        }
+
+        sheet.push(SheetLine::text(String::new()));
+
+        let remaining = lines.saturating_sub(sheet.len());
+        sheet.extend(
+            glyphs
+                .iter()
+                .skip(self.scroll)
+                .take(remaining)
+                .map(|info| SheetLine { glyph: Some(info.key.key), text: glyph_description(info) }),
+        );
+
+        sheet.truncate(lines);
+        sheet
+    }
+}
+
+fn glyph_description(info: &GlyphInfo) -> String {
+    let mut text = format!("U+{:04X} {} ", info.key.key.c as u32, info.font);
+
+    let colored = match info.glyph {
+        AtlasGlyph::Grid(glyph) => {
+            text.push_str(&format!(
+                "grid atlas {} column {} line {}",
+                glyph.atlas_index, glyph.column, glyph.line
+            ));
+            glyph.colored
+        },
+        AtlasGlyph::Quad(glyph) => {
+            text.push_str(&format!(
+                "quad atlas {} {}x{} left {} top {}",
+                glyph.atlas_index, glyph.width, glyph.height, glyph.left, glyph.top
+            ));
+            glyph.colored
+        },
+    };
+
+    if info.key.wide {
+        text.push_str(" wide");
+    }
+    if info.key.zero_width {
+        text.push_str(" zero-width");
+    }
+    if colored {
+        text.push_str(" colored");
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crossfont::{FontKey, Size};
+
+    use crate::renderer::glyph::{GlyphKey, GridAtlasGlyph, Presentation};
+
+    fn glyph(c: char) -> GlyphInfo {
+        GlyphInfo {
+            key: GlyphKey {
+                key: crossfont::GlyphKey { font_key: FontKey::next(), size: Size::new(11.), c },
+                wide: false,
+                zero_width: false,
+                presentation: Presentation::Default,
+            },
+            font: String::from("regular"),
+            glyph: AtlasGlyph::Grid(GridAtlasGlyph {
+                atlas_index: 0,
+                line: 1,
+                column: c as u16 - 'a' as u16,
+                colored: false,
+            }),
+        }
+    }
+
+    #[test]
+    fn query_char() {
+        let mut inspector = GlyphInspector::default();
+        assert_eq!(inspector.query_char(), None);
+
+        inspector.push('x');
+        assert_eq!(inspector.query_char(), Some('x'));
+
+        inspector.pop();
+        assert_eq!(inspector.query_char(), None);
+
+        inspector.query = String::from("U+1F600");
+        assert_eq!(inspector.query_char(), Some('😀'));
+
+        inspector.query = String::from("0x41");
+        assert_eq!(inspector.query_char(), Some('A'));
+
+        inspector.query = String::from("U+D800");
+        assert_eq!(inspector.query_char(), None);
+
+        inspector.query = String::from("ab");
+        assert_eq!(inspector.query_char(), None);
+    }
+
+    #[test]
+    fn sheet_scrolling() {
+        let glyphs: Vec<GlyphInfo> = "abcdef".chars().map(glyph).collect();
+        let mut inspector = GlyphInspector::default();
+
+        let sheet = inspector.sheet(&glyphs, &[], 5);
+        assert_eq!(sheet.len(), 5);
+        assert_eq!(sheet[0].text, "Glyph inspector: 6 cached glyphs");
+        assert_eq!(sheet[1].text, "Inspect: _");
+        assert_eq!(sheet[3].text, "U+0061 regular grid atlas 0 column 0 line 1");
+        assert_eq!(sheet[4].glyph.map(|key| key.c), Some('b'));
+
+        inspector.pan(4);
+        let sheet = inspector.sheet(&glyphs, &[], 5);
+        assert_eq!(sheet[3].glyph.map(|key| key.c), Some('e'));
+
+        // Panning past the last glyph keeps it visible.
+        inspector.pan(100);
+        let sheet = inspector.sheet(&glyphs, &[], 5);
+        assert_eq!(sheet.len(), 4);
+        assert_eq!(sheet[3].glyph.map(|key| key.c), Some('f'));
+
+        inspector.pan(-1);
+        let sheet = inspector.sheet(&glyphs, &[], 5);
+        assert_eq!(sheet[3].glyph.map(|key| key.c), Some('e'));
+
+        inspector.pan(-100);
+        let sheet = inspector.sheet(&glyphs, &[], 5);
+        assert_eq!(sheet[3].glyph.map(|key| key.c), Some('a'));
+    }
+
+    #[test]
+    fn sheet_probes() {
+        let key = crossfont::GlyphKey { font_key: FontKey::next(), size: Size::new(11.), c: 'x' };
+        let rasterized = crossfont::RasterizedGlyph {
+            c: 'x',
+            width: 7,
+            height: 9,
+            top: 9,
+            left: 1,
+            ..Default::default()
+        };
+        let probes = vec![
+            FaceProbe { font: String::from("regular"), key, glyph: None, used: false },
+            FaceProbe {
+                font: String::from("fallback 1"),
+                key,
+                glyph: Some(rasterized),
+                used: true,
+            },
+        ];
+
+        let mut inspector = GlyphInspector::default();
+        inspector.push('x');
+        let sheet = inspector.sheet(&[], &probes, 10);
+        assert_eq!(sheet[2], SheetLine::text(String::from("regular: missing")));
+        assert_eq!(sheet[3].text, "fallback 1: 7x9 left 1 top 9 (used)");
+        assert_eq!(sheet[3].glyph, Some(key));
+    }
+}
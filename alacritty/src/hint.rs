@@ -0,0 +1,141 @@
+//! Keyboard selection of the URLs and paths on screen, using short labels.
+
+use alacritty_terminal::index::Point;
+
+use crate::url::{Url, Urls};
+
+/// Action performed on the target of a hint.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HintAction {
+    /// Pass the target to the URL launcher.
+    Open,
+
+    /// Copy the target to the clipboard.
+    Copy,
+}
+
+/// Hint selection state.
+#[derive(Debug, Default)]
+pub struct Hints {
+    /// Action for the selected target, or `None` when no hints are shown.
+    action: Option<HintAction>,
+
+    /// Targets on screen with their labels.
+    targets: Vec<(String, Url)>,
+
+    /// Label characters typed so far.
+    keys: String,
+}
+
+impl Hints {
+    /// Label all `urls` with characters of the `alphabet`.
+    ///
+    /// Nothing is shown if there are no targets on screen.
+    pub fn start(&mut self, action: HintAction, urls: &Urls, alphabet: &str) {
+        let labels = labels(alphabet, urls.urls().len());
+        self.targets = labels.into_iter().zip(urls.urls().iter().cloned()).collect();
+        self.keys.clear();
+        self.action = if self.targets.is_empty() { None } else { Some(action) };
+    }
+
+    pub fn cancel(&mut self) {
+        self.action = None;
+        self.targets.clear();
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.action.is_some()
+    }
+
+    /// Add a typed label character.
+    ///
+    /// Once a label is complete, hints are hidden again and its target is returned. Characters
+    /// which don't continue any label are ignored.
+    pub fn input(&mut self, c: char) -> Option<(HintAction, Url)> {
+        let action = self.action?;
+
+        self.keys.push(c);
+        if !self.targets.iter().any(|(label, _)| label.starts_with(&self.keys)) {
+            self.keys.pop();
+            return None;
+        }
+
+        let index = self.targets.iter().position(|(label, _)| *label == self.keys)?;
+        let (_, url) = self.targets.swap_remove(index);
+        self.cancel();
+
+        Some((action, url))
+    }
+
+    /// Remove the last typed label character.
+    pub fn pop(&mut self) {
+        self.keys.pop();
+    }
+
+    /// Remaining characters of the labels matching the typed ones, at the start of their target.
+    pub fn labels(&self) -> Vec<(Point, String)> {
+        self.targets
+            .iter()
+            .filter(|(label, _)| label.starts_with(&self.keys))
+            .map(|(label, url)| (url.start(), label[self.keys.len()..].to_owned()))
+            .collect()
+    }
+}
+
+/// Labels for `count` targets, none of them being the prefix of another.
+///
+/// Single characters are used as long as there are enough two character labels left for all
+/// other targets. Targets beyond what two characters can label are left out.
+fn labels(alphabet: &str, count: usize) -> Vec<String> {
+    let mut chars: Vec<char> = Vec::new();
+    for c in alphabet.chars() {
+        if !chars.contains(&c) {
+            chars.push(c);
+        }
+    }
+
+    let len = chars.len();
+    if len < 2 {
+        return Vec::new();
+    }
+
+    let count = count.min(len * len);
+    let singles = if count <= len { count } else { (len * len - count) / (len - 1) };
+
+    let mut labels: Vec<String> = chars[..singles].iter().map(|c| c.to_string()).collect();
+    for prefix in &chars[singles..] {
+        for c in &chars {
+            if labels.len() == count {
+                return labels;
+            }
+
+            labels.push([*prefix, *c].iter().collect());
+        }
+    }
+
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_prefixes() {
+        assert_eq!(labels("abc", 2), vec!["a", "b"]);
+        assert_eq!(labels("abc", 3), vec!["a", "b", "c"]);
+        assert_eq!(labels("abc", 4), vec!["a", "b", "ca", "cb"]);
+        assert_eq!(labels("aabc", 5), vec!["a", "b", "ca", "cb", "cc"]);
+        assert_eq!(labels("abc", 6), vec!["a", "ba", "bb", "bc", "ca", "cb"]);
+        assert_eq!(labels("ab", 10), vec!["aa", "ab", "ba", "bb"]);
+        assert!(labels("a", 1).is_empty());
+
+        // No label is a prefix of another one.
+        let labels = labels("jfkdls;ahgurieowpq", 100);
+        assert_eq!(labels.len(), 100);
+        for label in &labels {
+            let prefixed = labels.iter().filter(|other| other.starts_with(label.as_str()));
+            assert_eq!(prefixed.count(), 1);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Tracking of terminal activity, for dimming the terminal once it has been idle.
+
+use std::time::{Duration, Instant};
+
+use crate::config::idle::IdleConfig;
+
+/// Time of the last input or output.
+#[derive(Debug)]
+pub struct Idle {
+    last_activity: Instant,
+}
+
+impl Default for Idle {
+    fn default() -> Self {
+        Self { last_activity: Instant::now() }
+    }
+}
+
+impl Idle {
+    /// Register input or output at `now`.
+    ///
+    /// Returns `true` if the terminal was idle before.
+    pub fn activity(&mut self, config: &IdleConfig, now: Instant) -> bool {
+        let was_idle = self.is_idle(config, now);
+        self.last_activity = now;
+        was_idle
+    }
+
+    pub fn is_idle(&self, config: &IdleConfig, now: Instant) -> bool {
+        self.idle_for(config, now).is_some()
+    }
+
+    /// Time until the terminal becomes idle, `None` if it already is or dimming is disabled.
+    pub fn timeout(&self, config: &IdleConfig, now: Instant) -> Option<Duration> {
+        let deadline = self.last_activity + config.timeout()?;
+        if deadline > now {
+            Some(deadline - now)
+        } else {
+            None
+        }
+    }
+
+    /// Fraction of the brightness taken away at `now`, fading in once the terminal became idle.
+    pub fn dim(&self, config: &IdleConfig, now: Instant) -> f32 {
+        let idle_for = match self.idle_for(config, now) {
+            Some(idle_for) => idle_for,
+            None => return 0.,
+        };
+
+        let fade = config.fade();
+        if idle_for >= fade {
+            config.dim()
+        } else {
+            config.dim() * idle_for.as_secs_f32() / fade.as_secs_f32()
+        }
+    }
+
+    /// Whether the dimming is still fading in at `now`.
+    pub fn is_fading(&self, config: &IdleConfig, now: Instant) -> bool {
+        self.idle_for(config, now).map_or(false, |idle_for| idle_for < config.fade())
+    }
+
+    /// Time since the terminal became idle.
+    fn idle_for(&self, config: &IdleConfig, now: Instant) -> Option<Duration> {
+        let start = self.last_activity + config.timeout()?;
+        if now >= start {
+            Some(now - start)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(timeout: u64, fade: u64) -> IdleConfig {
+        let yaml = format!("{{ timeout: {}, dim: 0.5, fade: {} }}", timeout, fade);
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    #[test]
+    fn disabled() {
+        let config = config(0, 1000);
+        let idle = Idle::default();
+        let later = idle.last_activity + Duration::from_secs(3600);
+
+        assert_eq!(idle.timeout(&config, later), None);
+        assert!(!idle.is_idle(&config, later));
+        assert!(idle.dim(&config, later) < f32::EPSILON);
+    }
+
+    #[test]
+    fn fade_in() {
+        let config = config(1, 1000);
+        let mut idle = Idle::default();
+        let start = idle.last_activity;
+
+        assert_eq!(idle.timeout(&config, start), Some(Duration::from_secs(60)));
+        assert!(!idle.is_idle(&config, start + Duration::from_secs(59)));
+
+        let halfway = start + Duration::from_millis(60_500);
+        assert!(idle.is_fading(&config, halfway));
+        assert!((idle.dim(&config, halfway) - 0.25).abs() < 0.001);
+
+        let faded = start + Duration::from_secs(62);
+        assert!(!idle.is_fading(&config, faded));
+        assert!((idle.dim(&config, faded) - 0.5).abs() < f32::EPSILON);
+        assert_eq!(idle.timeout(&config, faded), None);
+
+        // Activity wakes the terminal up immediately.
+        assert!(idle.activity(&config, faded));
+        assert!(idle.dim(&config, faded) < f32::EPSILON);
+        assert_eq!(idle.timeout(&config, faded), Some(Duration::from_secs(60)));
+        assert!(!idle.activity(&config, faded));
+    }
+}
@@ -23,6 +23,7 @@ use glutin::platform::macos::EventLoopWindowTargetExtMacOS;
 use glutin::window::CursorIcon;
 
 use alacritty_terminal::ansi::{ClearMode, Handler};
+use alacritty_terminal::config::ScrollToBottom;
 use alacritty_terminal::event::EventListener;
 use alacritty_terminal::grid::{Dimensions, Scroll};
 use alacritty_terminal::index::{Column, Direction, Line, Point, Side};
@@ -32,11 +33,17 @@ use alacritty_terminal::term::{ClipboardType, SizeInfo, Term};
 use alacritty_terminal::vi_mode::ViMotion;
 
 use crate::clipboard::Clipboard;
+use crate::clipboard_history::{ClipboardHistory, NUMBERED_ENTRIES};
 use crate::config::{Action, Binding, Config, Key, ViAction};
 use crate::daemon::start_daemon;
 use crate::event::{ClickState, Event, Mouse, TYPING_SEARCH_DELAY};
+use crate::glyph_inspector::GlyphInspector;
+use crate::hint::{HintAction, Hints};
 use crate::message_bar::{self, Message};
+use crate::region_capture::RegionCapture;
+use crate::renderer;
 use crate::scheduler::{Scheduler, TimerId};
+use crate::touch::{Gesture, LONG_PRESS_DURATION, MOMENTUM_INTERVAL};
 use crate::url::{Url, Urls};
 use crate::window::Window;
 
@@ -85,12 +92,38 @@ pub trait ActionContext<T: EventListener> {
     fn spawn_new_instance(&mut self);
     fn change_font_size(&mut self, delta: f32);
     fn reset_font_size(&mut self);
+    fn request_close(&mut self);
     fn pop_message(&mut self);
     fn message(&self) -> Option<&Message>;
+    fn toggle_atlas_overlay(&mut self);
+    fn dump_atlases(&mut self);
+    fn diagnostic_bundle(&mut self);
+    fn toggle_renderer_hud(&mut self);
+    fn reload_shaders(&mut self);
+    fn cycle_background_opacity(&mut self);
+    fn toggle_color_scheme(&mut self);
+    fn set_color_scheme(&mut self, name: &str);
+    fn toggle_glyph_inspector(&mut self);
+    fn glyph_inspector(&self) -> &GlyphInspector;
+    fn glyph_inspector_mut(&mut self) -> &mut GlyphInspector;
+    fn toggle_key_overlay(&mut self);
+    fn toggle_region_capture(&mut self);
+    fn region_capture(&self) -> &RegionCapture;
+    fn region_capture_mut(&mut self) -> &mut RegionCapture;
+    fn export_region(&mut self);
+    fn start_hints(&mut self, action: HintAction);
+    fn hints(&self) -> &Hints;
+    fn hints_mut(&mut self) -> &mut Hints;
+    fn locate_cursor(&mut self);
+    fn open_clipboard_history(&mut self);
+    fn clipboard_history(&self) -> &ClipboardHistory;
+    fn clipboard_history_mut(&mut self) -> &mut ClipboardHistory;
+    fn show_key(&mut self, input: KeyboardInput);
     fn config(&self) -> &Config;
     fn event_loop(&self) -> &EventLoopWindowTarget<Event>;
     fn urls(&self) -> &Urls;
     fn launch_url(&self, url: Url);
+    fn copy_url(&mut self, url: Url);
     fn mouse_mode(&self) -> bool;
     fn clipboard_mut(&mut self) -> &mut Clipboard;
     fn scheduler_mut(&mut self) -> &mut Scheduler;
@@ -143,7 +176,9 @@ impl<T: EventListener> Execute<T> for Action {
                 }
 
                 ctx.clear_selection();
-                ctx.scroll(Scroll::Bottom);
+                if ctx.config().scrolling.scroll_to_bottom != ScrollToBottom::Never {
+                    ctx.scroll(Scroll::Bottom);
+                }
                 ctx.write_to_pty(s.clone().into_bytes())
             },
             Action::Copy => ctx.copy_selection(ClipboardType::Clipboard),
@@ -235,7 +270,7 @@ impl<T: EventListener> Execute<T> for Action {
             #[cfg(not(target_os = "macos"))]
             Action::Hide => ctx.window().set_visible(false),
             Action::Minimize => ctx.window().set_minimized(true),
-            Action::Quit => ctx.terminal_mut().exit(),
+            Action::Quit => ctx.request_close(),
             Action::IncreaseFontSize => ctx.change_font_size(FONT_SIZE_STEP),
             Action::DecreaseFontSize => ctx.change_font_size(FONT_SIZE_STEP * -1.),
             Action::ResetFontSize => ctx.reset_font_size(),
@@ -313,6 +348,21 @@ impl<T: EventListener> Execute<T> for Action {
             Action::ClearHistory => ctx.terminal_mut().clear_screen(ClearMode::Saved),
             Action::ClearLogNotice => ctx.pop_message(),
             Action::SpawnNewInstance => ctx.spawn_new_instance(),
+            Action::ToggleAtlasOverlay => ctx.toggle_atlas_overlay(),
+            Action::DumpAtlases => ctx.dump_atlases(),
+            Action::DiagnosticBundle => ctx.diagnostic_bundle(),
+            Action::ToggleRendererHud => ctx.toggle_renderer_hud(),
+            Action::ReloadShaders => ctx.reload_shaders(),
+            Action::CycleBackgroundOpacity => ctx.cycle_background_opacity(),
+            Action::ToggleColorScheme => ctx.toggle_color_scheme(),
+            Action::SetColorScheme(ref name) => ctx.set_color_scheme(name),
+            Action::ToggleGlyphInspector => ctx.toggle_glyph_inspector(),
+            Action::ToggleKeyOverlay => ctx.toggle_key_overlay(),
+            Action::ToggleRegionCapture => ctx.toggle_region_capture(),
+            Action::OpenHint => ctx.start_hints(HintAction::Open),
+            Action::CopyHint => ctx.start_hints(HintAction::Copy),
+            Action::LocateCursor => ctx.locate_cursor(),
+            Action::PasteFromHistory => ctx.open_clipboard_history(),
             Action::ReceiveChar | Action::None => (),
         }
     }
@@ -321,7 +371,7 @@ impl<T: EventListener> Execute<T> for Action {
 fn paste<T: EventListener, A: ActionContext<T>>(ctx: &mut A, contents: &str) {
     if ctx.terminal().mode().contains(TermMode::BRACKETED_PASTE) {
         ctx.write_to_pty(&b"\x1b[200~"[..]);
-        ctx.write_to_pty(contents.replace("\x1b", "").into_bytes());
+        ctx.write_to_pty(strip_controls(contents).into_bytes());
         ctx.write_to_pty(&b"\x1b[201~"[..]);
     } else {
         // In non-bracketed (ie: normal) mode, terminal applications cannot distinguish
@@ -334,6 +384,31 @@ fn paste<T: EventListener, A: ActionContext<T>>(ctx: &mut A, contents: &str) {
     }
 }
 
+/// Remove control characters other than whitespace from bracketed paste contents.
+///
+/// Escapes could end the paste early, while other control characters would be interpreted as
+/// keystrokes by the application.
+fn strip_controls(contents: &str) -> String {
+    contents.chars().filter(|&c| !c.is_control() || matches!(c, '\t' | '\n' | '\r')).collect()
+}
+
+/// Number shown on a number key of the main keyboard or the numpad.
+fn number_key(key: VirtualKeyCode) -> Option<usize> {
+    let number = match key {
+        VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => 1,
+        VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => 2,
+        VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => 3,
+        VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => 4,
+        VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => 5,
+        VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => 6,
+        VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => 7,
+        VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => 8,
+        VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => 9,
+        _ => return None,
+    };
+    Some(number)
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum MouseState {
     Url(Url),
@@ -367,16 +442,37 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
 
         let lmb_pressed = self.ctx.mouse().left_button_state == ElementState::Pressed;
         let rmb_pressed = self.ctx.mouse().right_button_state == ElementState::Pressed;
-        if !self.ctx.selection_is_empty() && (lmb_pressed || rmb_pressed) && !search_active {
+        let minimap_line = self.ctx.mouse().minimap_line;
+        if !self.ctx.selection_is_empty()
+            && (lmb_pressed || rmb_pressed)
+            && !search_active
+            && minimap_line.is_none()
+        {
             self.update_selection_scrolling(y);
         }
 
         let x = min(max(x, 0), size_info.width() as i32 - 1) as usize;
         let y = min(max(y, 0), size_info.height() as i32 - 1) as usize;
 
+        // Pixel reporting applications want to know about every movement, not just cell changes.
+        let mode = *self.ctx.terminal().mode();
+        let pixel_motion = (x != self.ctx.mouse().x || y != self.ctx.mouse().y)
+            && mode.contains(TermMode::SGR_PIXEL_MOUSE)
+            && mode.intersects(TermMode::MOUSE_MOTION | TermMode::MOUSE_DRAG);
+
         self.ctx.mouse_mut().x = x;
         self.ctx.mouse_mut().y = y;
 
+        // Move the viewport along with the mouse while dragging the minimap.
+        if let Some(last_line) = minimap_line {
+            let line = renderer::minimap_line(&size_info, y);
+            self.ctx.mouse_mut().minimap_line = Some(line);
+            if line != last_line {
+                self.ctx.scroll(Scroll::Delta(last_line - line));
+            }
+            return;
+        }
+
         let inside_text_area = size_info.contains_point(x, y);
         let point = size_info.pixels_to_coords(x, y);
         let cell_side = self.get_mouse_side();
@@ -391,6 +487,7 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
 
         // If the mouse hasn't changed cells, do nothing.
         if !cell_changed
+            && !pixel_motion
             && self.ctx.mouse().cell_side == cell_side
             && self.ctx.mouse().inside_text_area == inside_text_area
         {
@@ -405,15 +502,22 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
         // Don't launch URLs if mouse has moved.
         self.ctx.mouse_mut().block_url_launcher = true;
 
+        if self.ctx.region_capture().is_active() {
+            if lmb_pressed {
+                self.ctx.region_capture_mut().drag_to(point);
+            }
+            return;
+        }
+
         if (lmb_pressed || rmb_pressed)
             && (self.ctx.modifiers().shift() || !self.ctx.mouse_mode())
             && !search_active
         {
             self.ctx.update_selection(point, cell_side);
         } else if inside_text_area
-            && cell_changed
+            && (cell_changed || pixel_motion)
             && point.line < self.ctx.terminal().screen_lines()
-            && self.ctx.terminal().mode().intersects(TermMode::MOUSE_MOTION | TermMode::MOUSE_DRAG)
+            && mode.intersects(TermMode::MOUSE_MOTION | TermMode::MOUSE_DRAG)
         {
             if lmb_pressed {
                 self.mouse_report(32, ElementState::Pressed);
@@ -421,7 +525,7 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
                 self.mouse_report(33, ElementState::Pressed);
             } else if self.ctx.mouse().right_button_state == ElementState::Pressed {
                 self.mouse_report(34, ElementState::Pressed);
-            } else if self.ctx.terminal().mode().contains(TermMode::MOUSE_MOTION) {
+            } else if mode.contains(TermMode::MOUSE_MOTION) {
                 self.mouse_report(35, ElementState::Pressed);
             }
         }
@@ -432,11 +536,11 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
         let x = self.ctx.mouse().x;
 
         let cell_x =
-            x.saturating_sub(size_info.padding_x() as usize) % size_info.cell_width() as usize;
+            x.saturating_sub(size_info.padding_left() as usize) % size_info.cell_width() as usize;
         let half_cell_width = (size_info.cell_width() / 2.0) as usize;
 
-        let additional_padding =
-            (size_info.width() - size_info.padding_x() * 2.) % size_info.cell_width();
+        let grid_width = size_info.width() - size_info.padding_x() * 2. - size_info.gutter();
+        let additional_padding = grid_width % size_info.cell_width();
         let end_of_grid = size_info.width() - size_info.padding_x() - additional_padding;
 
         if cell_x > half_cell_width
@@ -484,13 +588,20 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
     }
 
     fn sgr_mouse_report(&mut self, button: u8, state: ElementState) {
-        let (line, column) = (self.ctx.mouse().line, self.ctx.mouse().column);
+        // Pixel coordinates are relative to the grid, so the padding is never reported.
+        let (x, y) = if self.ctx.terminal().mode().contains(TermMode::SGR_PIXEL_MOUSE) {
+            let mouse = self.ctx.mouse();
+            self.ctx.size_info().pixels_to_grid_pixels(mouse.x, mouse.y)
+        } else {
+            (self.ctx.mouse().column.0, self.ctx.mouse().line.0)
+        };
+
         let c = match state {
             ElementState::Pressed => 'M',
             ElementState::Released => 'm',
         };
 
-        let msg = format!("\x1b[<{};{};{}{}", button, column + 1, line + 1, c);
+        let msg = format!("\x1b[<{};{};{}{}", button, x + 1, y + 1, c);
         self.ctx.write_to_pty(msg.into_bytes());
     }
 
@@ -509,7 +620,7 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
         }
 
         // Report mouse events.
-        if self.ctx.terminal().mode().contains(TermMode::SGR_MOUSE) {
+        if self.ctx.terminal().mode().intersects(TermMode::SGR_MOUSE | TermMode::SGR_PIXEL_MOUSE) {
             self.sgr_mouse_report(button + mods, state);
         } else if let ElementState::Released = state {
             self.normal_mouse_report(3 + mods);
@@ -581,6 +692,9 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
 
                 self.expand_selection(point, selection_type);
             },
+            ClickState::DoubleClick if self.ctx.modifiers().ctrl() => {
+                self.expand_selection(point, SelectionType::Path)
+            },
             ClickState::DoubleClick => self.expand_selection(point, SelectionType::Semantic),
             ClickState::TripleClick => self.expand_selection(point, SelectionType::Lines),
             ClickState::None => (),
@@ -625,7 +739,12 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
             },
             ClickState::DoubleClick => {
                 self.ctx.mouse_mut().block_url_launcher = true;
-                self.ctx.start_selection(SelectionType::Semantic, point, side);
+
+                if self.ctx.modifiers().ctrl() {
+                    self.ctx.start_selection(SelectionType::Path, point, side);
+                } else {
+                    self.ctx.start_selection(SelectionType::Semantic, point, side);
+                }
             },
             ClickState::TripleClick => {
                 self.ctx.mouse_mut().block_url_launcher = true;
@@ -680,10 +799,78 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
         }
     }
 
+    /// Handle touchscreen input.
+    pub fn touch(&mut self, id: u64, phase: TouchPhase, position: PhysicalPosition<f64>) {
+        let dpr = self.ctx.window().dpr;
+        let touch = &mut self.ctx.mouse_mut().touch;
+        let gesture = match touch.input(id, phase, position, Instant::now(), dpr) {
+            Some(gesture) => gesture,
+            None => return,
+        };
+
+        match gesture {
+            Gesture::Press => {
+                self.ctx.mouse_mut().scroll_px = 0.;
+
+                let scheduler = self.ctx.scheduler_mut();
+                scheduler.unschedule(TimerId::TouchMomentum);
+                scheduler.unschedule(TimerId::TouchLongPress);
+                let event = Event::TouchLongPress.into();
+                scheduler.schedule(event, LONG_PRESS_DURATION, false, TimerId::TouchLongPress);
+            },
+            Gesture::Scroll(delta) => self.scroll_terminal(delta),
+            Gesture::Fling => {
+                let event = Event::TouchMomentum.into();
+                let scheduler = self.ctx.scheduler_mut();
+                scheduler.schedule(event, MOMENTUM_INTERVAL, true, TimerId::TouchMomentum);
+            },
+            Gesture::Zoom(steps) => self.ctx.change_font_size(steps as f32 * FONT_SIZE_STEP),
+            Gesture::Select(position) => {
+                let point = self.touch_point(position);
+                self.ctx.update_selection(point, Side::Right);
+            },
+            Gesture::SelectionEnd => self.copy_selection(),
+        }
+    }
+
+    /// Start selecting the word under a finger resting on the screen.
+    pub fn touch_long_press(&mut self) {
+        if let Some(position) = self.ctx.mouse_mut().touch.long_press(Instant::now()) {
+            let point = self.touch_point(position);
+            self.ctx.start_selection(SelectionType::Semantic, point, Side::Left);
+        }
+    }
+
+    /// Keep scrolling after a fling, until the momentum is gone.
+    pub fn touch_momentum(&mut self) {
+        let dpr = self.ctx.window().dpr;
+        match self.ctx.mouse_mut().touch.momentum(Instant::now(), dpr) {
+            Some(delta) => self.scroll_terminal(delta),
+            None => {
+                self.ctx.scheduler_mut().unschedule(TimerId::TouchMomentum);
+            },
+        }
+    }
+
+    /// Grid point under a touch position.
+    fn touch_point(&self, position: PhysicalPosition<f64>) -> Point {
+        let size_info = self.ctx.size_info();
+        let x = position.x.max(0.) as usize;
+        let y = position.y.max(0.) as usize;
+        size_info.pixels_to_coords(x, y)
+    }
+
     fn scroll_terminal(&mut self, new_scroll_px: f64) {
         let height = f64::from(self.ctx.size_info().cell_height());
 
-        if self.ctx.mouse_mode() {
+        if self.ctx.glyph_inspector().is_active() {
+            self.ctx.mouse_mut().scroll_px += new_scroll_px;
+
+            let lines = (self.ctx.mouse().scroll_px / height) as isize;
+            if lines != 0 {
+                self.ctx.glyph_inspector_mut().pan(-lines);
+            }
+        } else if self.ctx.mouse_mode() {
             self.ctx.mouse_mut().scroll_px += new_scroll_px;
 
             let code = if new_scroll_px > 0. { 64 } else { 65 };
@@ -764,6 +951,34 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
             _ => (),
         }
 
+        // Clicking the minimap centers the viewport on the line below the mouse.
+        if button == MouseButton::Left {
+            let minimap_line = match state {
+                ElementState::Pressed => self.minimap_line(),
+                ElementState::Released => self.ctx.mouse_mut().minimap_line.take(),
+            };
+
+            if let (Some(line), ElementState::Pressed) = (minimap_line, state) {
+                self.ctx.mouse_mut().minimap_line = Some(line);
+                let lines = self.ctx.size_info().screen_lines().0 as isize;
+                self.ctx.scroll(Scroll::Delta(lines / 2 - line));
+            }
+
+            if minimap_line.is_some() {
+                return;
+            }
+        }
+
+        // Clicking starts a new capture region, which is extended by dragging the mouse.
+        if self.ctx.region_capture().is_active() {
+            if button == MouseButton::Left && state == ElementState::Pressed {
+                let mouse = self.ctx.mouse();
+                let point = self.ctx.size_info().pixels_to_coords(mouse.x, mouse.y);
+                self.ctx.region_capture_mut().press(point);
+            }
+            return;
+        }
+
         // Skip normal mouse events if the message bar has been clicked.
         if self.message_bar_mouse_state() == Some(MouseState::MessageBarButton)
             && state == ElementState::Pressed
@@ -804,7 +1019,23 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
 
     /// Process key input.
     pub fn key_input(&mut self, input: KeyboardInput) {
+        if input.state == ElementState::Pressed {
+            self.ctx.show_key(input);
+        }
+
         match input.state {
+            ElementState::Pressed if self.ctx.hints().is_active() => {
+                self.hints_key_input(input);
+            },
+            ElementState::Pressed if self.ctx.region_capture().is_active() => {
+                self.region_capture_key_input(input);
+            },
+            ElementState::Pressed if self.ctx.glyph_inspector().is_active() => {
+                self.glyph_inspector_key_input(input);
+            },
+            ElementState::Pressed if self.ctx.clipboard_history().is_active() => {
+                self.clipboard_chooser_key_input(input);
+            },
             ElementState::Pressed if self.ctx.search_active() => {
                 match (input.virtual_keycode, *self.ctx.modifiers()) {
                     (Some(VirtualKeyCode::Back), _) => {
@@ -863,6 +1094,94 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
         }
     }
 
+    /// Pan and edit the glyph inspector, instead of sending keys to the terminal.
+    fn glyph_inspector_key_input(&mut self, input: KeyboardInput) {
+        let lines = self.ctx.size_info().screen_lines().0 as isize;
+        let inspector = self.ctx.glyph_inspector_mut();
+        match input.virtual_keycode {
+            Some(VirtualKeyCode::Escape) => inspector.toggle(),
+            Some(VirtualKeyCode::Back) => inspector.pop(),
+            Some(VirtualKeyCode::Up) => inspector.pan(-1),
+            Some(VirtualKeyCode::Down) => inspector.pan(1),
+            Some(VirtualKeyCode::PageUp) => inspector.pan(-lines),
+            Some(VirtualKeyCode::PageDown) => inspector.pan(lines),
+            Some(VirtualKeyCode::Home) => inspector.pan(isize::min_value()),
+            _ => {
+                *self.ctx.received_count() = 0;
+                self.process_key_bindings(input);
+                return;
+            },
+        }
+
+        *self.ctx.suppress_chars() = true;
+    }
+
+    /// Cancel hints or remove typed label characters, instead of sending keys to the terminal.
+    ///
+    /// The labels themselves are typed as characters.
+    fn hints_key_input(&mut self, input: KeyboardInput) {
+        match input.virtual_keycode {
+            Some(VirtualKeyCode::Escape) => self.ctx.hints_mut().cancel(),
+            Some(VirtualKeyCode::Back) => self.ctx.hints_mut().pop(),
+            _ => return,
+        }
+
+        *self.ctx.suppress_chars() = true;
+    }
+
+    /// Select and export the capture region, instead of sending keys to the terminal.
+    fn region_capture_key_input(&mut self, input: KeyboardInput) {
+        let size_info = self.ctx.size_info();
+        let capture = self.ctx.region_capture_mut();
+        match input.virtual_keycode {
+            Some(VirtualKeyCode::Escape) => capture.cancel(),
+            Some(VirtualKeyCode::Up) => capture.move_cursor(-1, 0, &size_info),
+            Some(VirtualKeyCode::Down) => capture.move_cursor(1, 0, &size_info),
+            Some(VirtualKeyCode::Left) => capture.move_cursor(0, -1, &size_info),
+            Some(VirtualKeyCode::Right) => capture.move_cursor(0, 1, &size_info),
+            Some(VirtualKeyCode::Space) => capture.toggle_anchor(),
+            Some(VirtualKeyCode::Tab) => capture.cycle_format(),
+            Some(VirtualKeyCode::Return) => self.ctx.export_region(),
+            _ => {
+                *self.ctx.received_count() = 0;
+                self.process_key_bindings(input);
+                return;
+            },
+        }
+
+        *self.ctx.suppress_chars() = true;
+    }
+
+    /// Pick an entry of the clipboard history chooser, instead of sending keys to the terminal.
+    fn clipboard_chooser_key_input(&mut self, input: KeyboardInput) {
+        let history = self.ctx.clipboard_history_mut();
+        let chosen = match input.virtual_keycode {
+            Some(VirtualKeyCode::Escape) => {
+                history.close();
+                None
+            },
+            Some(VirtualKeyCode::Up) => {
+                history.select(-1);
+                None
+            },
+            Some(VirtualKeyCode::Down) => {
+                history.select(1);
+                None
+            },
+            Some(VirtualKeyCode::Return) => history.choose(),
+            Some(key) if number_key(key).map_or(false, |number| number <= NUMBERED_ENTRIES) => {
+                number_key(key).and_then(|number| history.pick(number - 1))
+            },
+            _ => None,
+        };
+
+        if let Some(text) = chosen {
+            paste(&mut self.ctx, &text);
+        }
+
+        *self.ctx.suppress_chars() = true;
+    }
+
     /// Modifier state change.
     pub fn modifiers_input(&mut self, modifiers: ModifiersState) {
         *self.ctx.modifiers() = modifiers;
@@ -876,6 +1195,34 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
     /// Process a received character.
     pub fn received_char(&mut self, c: char) {
         let suppress_chars = *self.ctx.suppress_chars();
+        if self.ctx.hints().is_active() {
+            if !suppress_chars && !c.is_control() {
+                match self.ctx.hints_mut().input(c) {
+                    Some((HintAction::Open, url)) => self.ctx.launch_url(url),
+                    Some((HintAction::Copy, url)) => self.ctx.copy_url(url),
+                    None => (),
+                }
+            }
+
+            *self.ctx.suppress_chars() = false;
+            return;
+        }
+
+        if self.ctx.region_capture().is_active() || self.ctx.clipboard_history().is_active() {
+            *self.ctx.suppress_chars() = false;
+            return;
+        }
+
+        if self.ctx.glyph_inspector().is_active() {
+            if !suppress_chars && !c.is_control() {
+                self.ctx.glyph_inspector_mut().push(c);
+            }
+
+            *self.ctx.suppress_chars() = false;
+
+            return;
+        }
+
         let search_active = self.ctx.search_active();
         if suppress_chars || self.ctx.terminal().mode().contains(TermMode::VI) || search_active {
             if search_active {
@@ -897,7 +1244,9 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
             self.ctx.window_mut().set_mouse_visible(false);
         }
 
-        self.ctx.scroll(Scroll::Bottom);
+        if self.ctx.config().scrolling.scroll_to_bottom != ScrollToBottom::Never {
+            self.ctx.scroll(Scroll::Bottom);
+        }
         self.ctx.clear_selection();
 
         let utf8_len = c.len_utf8();
@@ -982,6 +1331,18 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
     }
 
     /// Check mouse state in relation to the message bar.
+    /// Line of the minimap below the mouse, relative to the top of the screen.
+    fn minimap_line(&self) -> Option<isize> {
+        let size = self.ctx.size_info();
+        let x = self.ctx.mouse().x as f32;
+        let left = size.padding_left() + size.cols().0 as f32 * size.cell_width();
+        if x < left || x >= left + size.minimap() {
+            return None;
+        }
+
+        Some(renderer::minimap_line(&size, self.ctx.mouse().y))
+    }
+
     fn message_bar_mouse_state(&self) -> Option<MouseState> {
         // Since search is above the message bar, the button is offset by search's height.
         let search_height = if self.ctx.search_active() { 1 } else { 0 };
@@ -1008,7 +1369,9 @@ impl<'a, T: EventListener, A: ActionContext<T>> Processor<'a, T, A> {
         if self.ctx.config().selection.save_to_clipboard {
             self.ctx.copy_selection(ClipboardType::Clipboard);
         }
-        self.ctx.copy_selection(ClipboardType::Selection);
+        if self.ctx.config().selection.copy_on_select() {
+            self.ctx.copy_selection(ClipboardType::Selection);
+        }
     }
 
     /// Trigger redraw when URL highlight changed.
@@ -1128,6 +1491,10 @@ mod tests {
         pub received_count: usize,
         pub suppress_chars: bool,
         pub modifiers: ModifiersState,
+        pub glyph_inspector: GlyphInspector,
+        pub region_capture: RegionCapture,
+        pub hints: Hints,
+        pub clipboard_history: ClipboardHistory,
         config: &'a Config,
     }
 
@@ -1150,6 +1517,72 @@ mod tests {
 
         fn reset_font_size(&mut self) {}
 
+        fn toggle_atlas_overlay(&mut self) {}
+
+        fn dump_atlases(&mut self) {}
+
+        fn diagnostic_bundle(&mut self) {}
+
+        fn toggle_renderer_hud(&mut self) {}
+
+        fn reload_shaders(&mut self) {}
+
+        fn cycle_background_opacity(&mut self) {}
+
+        fn toggle_color_scheme(&mut self) {}
+
+        fn set_color_scheme(&mut self, _name: &str) {}
+
+        fn toggle_glyph_inspector(&mut self) {}
+
+        fn glyph_inspector(&self) -> &GlyphInspector {
+            &self.glyph_inspector
+        }
+
+        fn glyph_inspector_mut(&mut self) -> &mut GlyphInspector {
+            &mut self.glyph_inspector
+        }
+
+        fn toggle_key_overlay(&mut self) {}
+
+        fn toggle_region_capture(&mut self) {}
+
+        fn region_capture(&self) -> &RegionCapture {
+            &self.region_capture
+        }
+
+        fn region_capture_mut(&mut self) -> &mut RegionCapture {
+            &mut self.region_capture
+        }
+
+        fn export_region(&mut self) {}
+
+        fn start_hints(&mut self, _action: HintAction) {}
+
+        fn hints(&self) -> &Hints {
+            &self.hints
+        }
+
+        fn hints_mut(&mut self) -> &mut Hints {
+            &mut self.hints
+        }
+
+        fn locate_cursor(&mut self) {}
+
+        fn open_clipboard_history(&mut self) {
+            self.clipboard_history.open();
+        }
+
+        fn clipboard_history(&self) -> &ClipboardHistory {
+            &self.clipboard_history
+        }
+
+        fn clipboard_history_mut(&mut self) -> &mut ClipboardHistory {
+            &mut self.clipboard_history
+        }
+
+        fn show_key(&mut self, _input: KeyboardInput) {}
+
         fn start_search(&mut self, _direction: Direction) {}
 
         fn confirm_search(&mut self) {}
@@ -1237,6 +1670,10 @@ mod tests {
             unimplemented!();
         }
 
+        fn request_close(&mut self) {
+            self.terminal.exit();
+        }
+
         fn pop_message(&mut self) {
             self.message_buffer.pop();
         }
@@ -1265,6 +1702,10 @@ mod tests {
             unimplemented!();
         }
 
+        fn copy_url(&mut self, _: Url) {
+            unimplemented!();
+        }
+
         fn scheduler_mut(&mut self) -> &mut Scheduler {
             unimplemented!();
         }
@@ -1323,6 +1764,10 @@ mod tests {
                     suppress_chars: false,
                     modifiers: Default::default(),
                     message_buffer: &mut message_buffer,
+                    glyph_inspector: Default::default(),
+                    region_capture: Default::default(),
+                    hints: Default::default(),
+                    clipboard_history: Default::default(),
                     config: &cfg,
                 };
 
@@ -1524,4 +1969,9 @@ mod tests {
         mode: TermMode::NONE,
         mods: ModifiersState::ALT | ModifiersState::LOGO,
     }
+
+    #[test]
+    fn bracketed_paste_strips_controls() {
+        assert_eq!(strip_controls("a\x1b[201~b\x03\tc\r\nd"), "a[201~b\tc\r\nd");
+    }
 }
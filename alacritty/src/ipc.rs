@@ -0,0 +1,271 @@
+//! Unix socket for controlling a running instance with `alacritty msg`.
+
+use std::fs::{self, Permissions};
+use std::io::{self, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Duration;
+use std::{env, process};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+use alacritty_terminal::thread;
+
+use crate::config::ipc::Permission;
+use crate::event::{Event, EventProxy};
+
+/// Environment variable with the socket of the instance a shell is running in.
+pub const ALACRITTY_SOCKET_ENV: &str = "ALACRITTY_SOCKET";
+
+/// Environment variable with the password sent along with messages.
+///
+/// Unlike the socket path, this is never exported to child processes.
+pub const ALACRITTY_IPC_PASSWORD_ENV: &str = "ALACRITTY_IPC_PASSWORD";
+
+/// Time a client has to send its message, before the connection is dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time the event loop has to handle a message, before the client is told it failed.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Maximum size of a message in bytes.
+const MAX_MESSAGE_SIZE: u64 = 1024 * 1024;
+
+/// Options which launch programs or relax security restrictions.
+///
+/// Overriding them requires the `Privileged` permission.
+const PRIVILEGED_OPTIONS: [&[&str]; 8] = [
+    &["shell"],
+    &["mouse", "url", "launcher"],
+    &["bell", "command"],
+    &["key_bindings"],
+    &["mouse_bindings"],
+    &["import"],
+    &["ipc"],
+    &["escape_sequences"],
+];
+
+/// Message sent to a running instance.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SocketMessage {
+    /// Override configuration options, on top of the configuration file.
+    Config(Value),
+
+    /// Change the background opacity, `None` restores the configured opacity.
+    Opacity(Option<f32>),
+
+    /// Statistics of the render passes of the last frame.
+    Stats,
+
+    /// Mark cells, with the `key=value` parameters of `OSC 1339 ; mark`.
+    Mark(Vec<String>),
+
+    /// Remove all marks of the active screen buffer.
+    ClearMarks,
+}
+
+impl SocketMessage {
+    /// Permission required to handle the message.
+    pub fn permission(&self) -> Permission {
+        match self {
+            SocketMessage::Config(options) if is_privileged(options) => Permission::Privileged,
+            SocketMessage::Config(_) => Permission::Config,
+            SocketMessage::Opacity(_) => Permission::Modify,
+            SocketMessage::Stats => Permission::Read,
+            SocketMessage::Mark(_) | SocketMessage::ClearMarks => Permission::Modify,
+        }
+    }
+}
+
+/// Message with the password it was sent with.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SocketRequest {
+    pub password: Option<String>,
+    pub message: SocketMessage,
+}
+
+/// Answer to a message, with text printed by `alacritty msg` or the reason it was refused.
+pub type SocketReply = Result<String, String>;
+
+/// Listen for messages on a new socket.
+///
+/// The socket path is exported to child processes through `ALACRITTY_SOCKET`. Only processes of
+/// the same user can connect to it.
+pub fn spawn_ipc_socket(event_proxy: EventProxy) -> Option<PathBuf> {
+    let directory = env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from).unwrap_or_else(env::temp_dir);
+    let path = directory.join(format!("Alacritty-{}.sock", process::id()));
+
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            warn!("Unable to create socket {:?}: {}", path, err);
+            return None;
+        },
+    };
+
+    if let Err(err) = fs::set_permissions(&path, Permissions::from_mode(0o600)) {
+        warn!("Unable to restrict access to socket {:?}: {}", path, err);
+        let _ = fs::remove_file(&path);
+        return None;
+    }
+
+    env::set_var(ALACRITTY_SOCKET_ENV, &path);
+
+    thread::spawn_named("socket listener", move || {
+        for stream in listener.incoming().filter_map(Result::ok) {
+            // Clients are served on their own thread, so a stalled client can't block others.
+            let event_proxy = event_proxy.clone();
+            thread::spawn_named("socket client", move || {
+                if let Err(err) = serve_client(stream, &event_proxy) {
+                    warn!("Invalid socket message: {}", err);
+                }
+            });
+        }
+    });
+
+    Some(path)
+}
+
+/// Send a message to the instance listening on `socket`, or the one in `ALACRITTY_SOCKET`.
+///
+/// Returns the text the instance answered with.
+pub fn send_message(socket: Option<PathBuf>, message: SocketMessage) -> io::Result<String> {
+    let path = match socket.or_else(|| env::var_os(ALACRITTY_SOCKET_ENV).map(PathBuf::from)) {
+        Some(path) => path,
+        None => {
+            let err =
+                format!("{} is not set, use --socket to select an instance", ALACRITTY_SOCKET_ENV);
+            return Err(io::Error::new(io::ErrorKind::NotFound, err));
+        },
+    };
+
+    let password = env::var(ALACRITTY_IPC_PASSWORD_ENV).ok();
+    let json = serde_json::to_vec(&SocketRequest { password, message })?;
+    let mut stream = UnixStream::connect(&path)?;
+    stream.write_all(&json)?;
+    stream.shutdown(Shutdown::Write)?;
+
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply)?;
+    serde_json::from_slice::<SocketReply>(&reply)?
+        .map_err(|err| io::Error::new(io::ErrorKind::PermissionDenied, err))
+}
+
+/// Pass the message of a client to the event loop and send back its reply.
+fn serve_client(mut stream: UnixStream, event_proxy: &EventProxy) -> io::Result<()> {
+    if !is_same_user(&stream)? {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "client of another user"));
+    }
+
+    let request = read_request(&stream)?;
+
+    let (sender, receiver) = mpsc::channel();
+    event_proxy.send_event(Event::SocketMessage(request, sender));
+    let reply = receiver
+        .recv_timeout(REPLY_TIMEOUT)
+        .unwrap_or_else(|_| Err(String::from("No reply from Alacritty")));
+
+    stream.write_all(&serde_json::to_vec(&reply)?)
+}
+
+/// Read a single request, which ends when the client closes its side of the stream.
+fn read_request(stream: &UnixStream) -> io::Result<SocketRequest> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let mut json = Vec::new();
+    stream.take(MAX_MESSAGE_SIZE).read_to_end(&mut json)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// Whether configuration overrides touch any privileged option.
+fn is_privileged(options: &Value) -> bool {
+    PRIVILEGED_OPTIONS.iter().any(|path| {
+        let mut value = Some(options);
+        for key in path.iter() {
+            value = value.and_then(|value| value.get(*key));
+        }
+        value.is_some()
+    })
+}
+
+/// Whether the process on the other end of `stream` runs as the current user.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn is_same_user(stream: &UnixStream) -> io::Result<bool> {
+    let mut credentials = libc::ucred { pid: 0, uid: 0, gid: 0 };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut credentials as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(credentials.uid == unsafe { libc::geteuid() })
+}
+
+/// Whether the process on the other end of `stream` runs as the current user.
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd"
+))]
+fn is_same_user(stream: &UnixStream) -> io::Result<bool> {
+    let (mut uid, mut gid) = (0, 0);
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(uid == unsafe { libc::geteuid() })
+}
+
+/// Only the permissions of the socket file restrict access on other platforms.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd"
+)))]
+fn is_same_user(_stream: &UnixStream) -> io::Result<bool> {
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_permissions() {
+        let options = serde_yaml::from_str("font: { size: 14 }").unwrap();
+        assert_eq!(SocketMessage::Config(options).permission(), Permission::Config);
+
+        let options = serde_yaml::from_str("mouse: { url: { launcher: sh } }").unwrap();
+        assert_eq!(SocketMessage::Config(options).permission(), Permission::Privileged);
+
+        let options = serde_yaml::from_str("{ mouse: { hide_when_typing: true }, shell: sh }");
+        assert_eq!(SocketMessage::Config(options.unwrap()).permission(), Permission::Privileged);
+
+        assert_eq!(SocketMessage::Opacity(None).permission(), Permission::Modify);
+        assert_eq!(SocketMessage::Stats.permission(), Permission::Read);
+        assert_eq!(SocketMessage::ClearMarks.permission(), Permission::Modify);
+    }
+}
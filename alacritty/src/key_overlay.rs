@@ -0,0 +1,149 @@
+//! Display of recently pressed keys, for screencasts and bug reproduction videos.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use glutin::event::{KeyboardInput, ModifiersState, VirtualKeyCode};
+
+/// Maximum number of key combinations which are shown at once.
+const MAX_KEYS: usize = 8;
+
+/// Time it takes for a key to fade out at the end of its display duration.
+const FADE_DURATION: Duration = Duration::from_millis(300);
+
+/// Key combination in the overlay.
+#[derive(Debug)]
+struct Key {
+    name: String,
+
+    /// Number of consecutive presses.
+    count: usize,
+
+    /// Time of the last press.
+    time: Instant,
+}
+
+impl Key {
+    fn label(&self) -> String {
+        if self.count > 1 {
+            format!("{} ×{}", self.name, self.count)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+/// Recently pressed key combinations.
+#[derive(Debug, Default)]
+pub struct KeyOverlay {
+    keys: VecDeque<Key>,
+}
+
+impl KeyOverlay {
+    /// Add a key press.
+    pub fn push(&mut self, input: KeyboardInput, mods: ModifiersState) {
+        let name = match input.virtual_keycode.and_then(|key| key_name(key, mods)) {
+            Some(name) => name,
+            None => return,
+        };
+
+        let time = Instant::now();
+        match self.keys.back_mut() {
+            Some(key) if key.name == name => {
+                key.count += 1;
+                key.time = time;
+            },
+            _ => self.keys.push_back(Key { name, count: 1, time }),
+        }
+
+        if self.keys.len() > MAX_KEYS {
+            self.keys.pop_front();
+        }
+    }
+
+    /// Labels of all keys which are still visible, oldest first, along with their opacity.
+    pub fn visible(&mut self, duration: Duration) -> Vec<(String, f32)> {
+        let now = Instant::now();
+        self.keys.retain(|key| now.duration_since(key.time) < duration);
+
+        self.keys
+            .iter()
+            .map(|key| {
+                let remaining = duration - now.duration_since(key.time);
+                let opacity = (remaining.as_secs_f32() / FADE_DURATION.as_secs_f32()).min(1.);
+                (key.label(), opacity)
+            })
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+}
+
+/// Name of a key combination, or `None` for keys which are only modifiers.
+fn key_name(key: VirtualKeyCode, mods: ModifiersState) -> Option<String> {
+    let name = match key {
+        VirtualKeyCode::LControl
+        | VirtualKeyCode::RControl
+        | VirtualKeyCode::LShift
+        | VirtualKeyCode::RShift
+        | VirtualKeyCode::LAlt
+        | VirtualKeyCode::RAlt
+        | VirtualKeyCode::LWin
+        | VirtualKeyCode::RWin => return None,
+        key => format!("{:?}", key),
+    };
+
+    // Number keys are called `Key1` to `Key0`.
+    let mut name =
+        if name.len() == 4 && name.starts_with("Key") { name[3..].to_owned() } else { name };
+
+    // Letters typed without other modifiers are shown as they appear in the terminal.
+    let text = mods.is_empty() || mods == ModifiersState::SHIFT;
+    if text && name.len() == 1 {
+        if !mods.shift() {
+            name.make_ascii_lowercase();
+        }
+        return Some(name);
+    }
+
+    let mut combination = String::new();
+    if mods.ctrl() {
+        combination.push_str("Ctrl+");
+    }
+    if mods.alt() {
+        combination.push_str("Alt+");
+    }
+    if mods.shift() {
+        combination.push_str("Shift+");
+    }
+    if mods.logo() {
+        combination.push_str("Super+");
+    }
+    combination.push_str(&name);
+
+    Some(combination)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names() {
+        let shift = ModifiersState::SHIFT;
+        let ctrl_shift = ModifiersState::CTRL | ModifiersState::SHIFT;
+
+        assert_eq!(key_name(VirtualKeyCode::A, ModifiersState::empty()), Some("a".into()));
+        assert_eq!(key_name(VirtualKeyCode::A, shift), Some("A".into()));
+        assert_eq!(key_name(VirtualKeyCode::Key1, ModifiersState::empty()), Some("1".into()));
+        assert_eq!(key_name(VirtualKeyCode::C, ctrl_shift), Some("Ctrl+Shift+C".into()));
+        assert_eq!(key_name(VirtualKeyCode::Return, shift), Some("Shift+Return".into()));
+        assert_eq!(key_name(VirtualKeyCode::LControl, ModifiersState::CTRL), None);
+    }
+}
@@ -20,7 +20,7 @@ use crate::event::Event;
 use crate::message_bar::{Message, MessageType};
 
 /// Name for the environment variable containing the log file's path.
-const ALACRITTY_LOG_ENV: &str = "ALACRITTY_LOG";
+pub const ALACRITTY_LOG_ENV: &str = "ALACRITTY_LOG";
 /// List of targets which will be logged by Alacritty.
 const ALLOWED_TARGETS: [&str; 4] =
     ["alacritty_terminal", "alacritty_config", "alacritty", "crossfont"];
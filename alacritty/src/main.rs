@@ -30,26 +30,44 @@ use alacritty_terminal::sync::FairMutex;
 use alacritty_terminal::term::Term;
 use alacritty_terminal::tty;
 
+mod appearance;
+#[cfg(feature = "bidi")]
+mod bidi;
 mod cli;
 mod clipboard;
+mod clipboard_history;
 mod config;
-mod cursor;
+mod cursor_locator;
 mod daemon;
+mod diagnostics;
 mod display;
 mod event;
+mod frame;
+mod glyph_inspector;
+mod hint;
+mod idle;
 mod input;
+#[cfg(unix)]
+mod ipc;
+mod key_overlay;
 #[cfg(target_os = "macos")]
 mod locale;
 mod logging;
 mod message_bar;
 mod meter;
+mod metrics;
 #[cfg(windows)]
 mod panic;
+mod process;
+mod region_capture;
 mod renderer;
 mod scheduler;
+mod touch;
 mod url;
 mod window;
 
+#[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+mod wayland_scale;
 #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
 mod wayland_theme;
 
@@ -60,6 +78,7 @@ mod gl {
 
 use crate::cli::Options;
 use crate::config::monitor;
+use crate::config::theme;
 use crate::config::Config;
 use crate::display::Display;
 use crate::event::{Event, EventProxy, Processor};
@@ -83,6 +102,20 @@ fn main() {
     // Load command line options.
     let options = Options::new();
 
+    // Send a message to a running instance instead of starting a new one.
+    #[cfg(unix)]
+    if let Some(message) = &options.message {
+        match ipc::send_message(message.socket.clone(), message.message.clone()) {
+            Ok(reply) if reply.is_empty() => (),
+            Ok(reply) => println!("{}", reply),
+            Err(err) => {
+                eprintln!("Unable to send message: {}", err);
+                std::process::exit(1);
+            },
+        }
+        return;
+    }
+
     // Setup glutin event loop.
     let window_event_loop = GlutinEventLoop::<Event>::with_user_event();
 
@@ -126,7 +159,7 @@ fn main() {
 /// config change monitor, and runs the main display loop.
 fn run(
     window_event_loop: GlutinEventLoop<Event>,
-    config: Config,
+    mut config: Config,
     options: Options,
 ) -> Result<(), Box<dyn Error>> {
     info!("Welcome to Alacritty");
@@ -141,10 +174,42 @@ fn run(
 
     let event_proxy = EventProxy::new(window_event_loop.create_proxy());
 
+    // Listen for messages from `alacritty msg`, before the shell inherits the environment.
+    #[cfg(unix)]
+    let socket_path = if config.ui_config.ipc.socket() {
+        ipc::spawn_ipc_socket(event_proxy.clone())
+    } else {
+        None
+    };
+
+    // Pick the color scheme matching the desktop appearance.
+    let follows_appearance = config.ui_config.theme.follows_appearance();
+    let follows_motion = config.ui_config.animations.follow_system();
+    if follows_appearance || follows_motion {
+        appearance::watch(event_proxy.clone());
+    }
+
+    let appearance = if follows_appearance { appearance::current() } else { None };
+    if let Some(appearance) = appearance {
+        info!("Desktop appearance: {:?}", appearance);
+        theme::apply_colors(&mut config, None, Some(appearance));
+    }
+
+    // Disable animations if the desktop prefers reduced motion.
+    if follows_motion && appearance::reduced_motion() == Some(true) {
+        info!("Desktop prefers reduced motion");
+        config.ui_config.animations.set_system_reduced_motion(true);
+    }
+
     // Create a display.
     //
     // The display manages a window and can draw the terminal.
-    let display = Display::new(&config, &window_event_loop)?;
+    #[allow(unused_mut)]
+    let mut display = Display::new(&config, &window_event_loop)?;
+
+    // Render at the exact scale factor of Wayland compositors, instead of winit's integer one.
+    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+    display.watch_fractional_scale(event_proxy.clone());
 
     info!(
         "PTY dimensions: {:?} x {:?}",
@@ -190,7 +255,10 @@ fn run(
     // The monitor watches the config file for changes and reloads it. Pending
     // config changes are processed in the main loop.
     if config.ui_config.live_config_reload() {
-        monitor::watch(config.ui_config.config_paths.clone(), event_proxy);
+        let mut paths = config.ui_config.config_paths.clone();
+        paths.extend(config.ui_config.background_image.as_ref().map(|image| image.path.clone()));
+        paths.extend(config.ui_config.post_processing_shader.clone());
+        monitor::watch(paths, event_proxy);
     }
 
     // Setup storage for message UI.
@@ -203,6 +271,7 @@ fn run(
         config,
         display,
         options,
+        appearance,
     );
 
     // Kick off the I/O thread.
@@ -230,6 +299,12 @@ fn run(
     loop_tx.send(Msg::Shutdown).expect("Error sending shutdown to PTY event loop");
     io_thread.join().expect("join io thread");
 
+    // Clean up the socket.
+    #[cfg(unix)]
+    if let Some(socket_path) = socket_path {
+        let _ = fs::remove_file(socket_path);
+    }
+
     // FIXME patch notify library to have a shutdown method.
     // config_reloader.join().ok();
 
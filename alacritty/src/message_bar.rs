@@ -1,6 +1,7 @@
 use std::collections::VecDeque;
 
 use alacritty_terminal::term::SizeInfo;
+use serde::{Deserialize, Serialize};
 use unicode_width::UnicodeWidthChar;
 
 pub const CLOSE_BUTTON_TEXT: &str = "[X]";
@@ -9,7 +10,7 @@ const MIN_FREE_LINES: usize = 3;
 const TRUNCATED_MESSAGE: &str = "[MESSAGE TRUNCATED]";
 
 /// Message for display in the MessageBuffer.
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
 pub struct Message {
     text: String,
     ty: MessageType,
@@ -17,7 +18,7 @@ pub struct Message {
 }
 
 /// Purpose of the message.
-#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone, Copy)]
 pub enum MessageType {
     /// A message represents an error.
     Error,
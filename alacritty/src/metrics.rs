@@ -0,0 +1,264 @@
+//! Rendering and throughput metrics served in the Prometheus text format.
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use parking_lot::Mutex;
+
+use alacritty_terminal::thread;
+
+use crate::renderer::glyph::GlyphCacheStats;
+use crate::renderer::RenderStats;
+
+/// Frames which take longer than this to render miss a refresh at 60Hz.
+const FRAME_BUDGET: Duration = Duration::from_micros(16_667);
+
+/// Period over which the frame rate is averaged.
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+/// Time a client has to send its request and receive the response, before it is dropped.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Counters collected by the display, for monitoring long-running sessions.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    frames: u64,
+    dropped_frames: u64,
+    frame_seconds: f64,
+
+    /// Time at which the frames of the last `FPS_WINDOW` were rendered.
+    recent_frames: VecDeque<Instant>,
+
+    draw_calls: u64,
+    uploaded_bytes: u64,
+    pty_bytes: u64,
+    glyphs: usize,
+    glyph_cache: GlyphCacheStats,
+}
+
+impl Metrics {
+    /// Account for a frame which took `duration` to render, up to `now`.
+    pub fn record_frame(&mut self, now: Instant, duration: Duration, stats: &RenderStats) {
+        self.frames += 1;
+        self.frame_seconds += duration.as_secs_f64();
+        if duration > FRAME_BUDGET {
+            self.dropped_frames += 1;
+        }
+
+        for pass in &[stats.grid, stats.quad, stats.graphics, stats.rects] {
+            self.draw_calls += pass.draw_calls as u64;
+            self.uploaded_bytes += pass.uploaded_bytes as u64;
+        }
+
+        self.recent_frames.push_back(now);
+        self.expire_frames(now);
+    }
+
+    /// Update the counters owned by the terminal and glyph cache.
+    pub fn update_counters(&mut self, pty_bytes: u64, glyphs: usize, glyph_cache: GlyphCacheStats) {
+        self.pty_bytes = pty_bytes;
+        self.glyphs = glyphs;
+        self.glyph_cache = glyph_cache;
+    }
+
+    /// Frames per second, averaged over the last second.
+    pub fn fps(&mut self, now: Instant) -> f64 {
+        self.expire_frames(now);
+        self.recent_frames.len() as f64 / FPS_WINDOW.as_secs_f64()
+    }
+
+    /// All metrics in the Prometheus text exposition format.
+    pub fn encode(&mut self, now: Instant) -> String {
+        let mut text = String::new();
+
+        let fps = self.fps(now);
+        write_metric(&mut text, "frames_total", "counter", "Frames rendered.", self.frames);
+        write_metric(
+            &mut text,
+            "dropped_frames_total",
+            "counter",
+            "Frames which took longer than 16.7ms to render.",
+            self.dropped_frames,
+        );
+        write_metric(
+            &mut text,
+            "frame_time_seconds_total",
+            "counter",
+            "Time spent rendering frames.",
+            self.frame_seconds,
+        );
+        write_metric(&mut text, "fps", "gauge", "Frames rendered in the last second.", fps);
+        write_metric(
+            &mut text,
+            "draw_calls_total",
+            "counter",
+            "OpenGL draw calls.",
+            self.draw_calls,
+        );
+        write_metric(
+            &mut text,
+            "uploaded_bytes_total",
+            "counter",
+            "Bytes uploaded to the GPU.",
+            self.uploaded_bytes,
+        );
+        write_metric(
+            &mut text,
+            "pty_read_bytes_total",
+            "counter",
+            "Bytes read from the PTY.",
+            self.pty_bytes,
+        );
+        write_metric(&mut text, "cached_glyphs", "gauge", "Glyphs in the cache.", self.glyphs);
+        write_metric(
+            &mut text,
+            "glyph_cache_misses_total",
+            "counter",
+            "Glyphs which had to be rasterized.",
+            self.glyph_cache.misses,
+        );
+        write_metric(
+            &mut text,
+            "glyph_cache_evictions_total",
+            "counter",
+            "Glyphs evicted from the cache.",
+            self.glyph_cache.evictions,
+        );
+
+        if let Some(resident) = resident_memory() {
+            write_metric(
+                &mut text,
+                "resident_memory_bytes",
+                "gauge",
+                "Resident memory size.",
+                resident,
+            );
+        }
+
+        text
+    }
+
+    fn expire_frames(&mut self, now: Instant) {
+        while self.recent_frames.front().map_or(false, |&time| now - time >= FPS_WINDOW) {
+            self.recent_frames.pop_front();
+        }
+    }
+}
+
+/// Write a single sample with its help text and type.
+fn write_metric<T: std::fmt::Display>(
+    text: &mut String,
+    name: &str,
+    ty: &str,
+    help: &str,
+    value: T,
+) {
+    let _ = writeln!(text, "# HELP alacritty_{} {}", name, help);
+    let _ = writeln!(text, "# TYPE alacritty_{} {}", name, ty);
+    let _ = writeln!(text, "alacritty_{} {}", name, value);
+}
+
+/// Resident set size of the process in bytes.
+#[cfg(target_os = "linux")]
+fn resident_memory() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * page_size as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_memory() -> Option<u64> {
+    None
+}
+
+/// Serve the metrics over HTTP on a local port.
+pub fn spawn_metrics_server(port: u16, metrics: Arc<Mutex<Metrics>>) {
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            warn!("Unable to serve metrics on port {}: {}", port, err);
+            return;
+        },
+    };
+
+    info!("Serving metrics on http://127.0.0.1:{}/metrics", port);
+
+    thread::spawn_named("metrics server", move || {
+        for stream in listener.incoming().filter_map(Result::ok) {
+            if let Err(err) = respond(stream, &metrics) {
+                warn!("Unable to send metrics: {}", err);
+            }
+        }
+    });
+}
+
+/// Answer any request with the current metrics.
+fn respond(mut stream: TcpStream, metrics: &Mutex<Metrics>) -> io::Result<()> {
+    // Idle clients would otherwise stall the server for everyone else.
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+    // The request itself is irrelevant, but has to be read before the connection is closed.
+    let mut request = [0; 1024];
+    let _ = stream.read(&mut request)?;
+
+    let body = metrics.lock().encode(Instant::now());
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::renderer::PassStats;
+
+    #[test]
+    fn frame_counters() {
+        let mut metrics = Metrics::default();
+        let start = Instant::now();
+
+        let pass = PassStats { draw_calls: 2, uploaded_bytes: 100, gpu_time: None };
+        let stats = RenderStats { grid: pass, rects: pass, ..Default::default() };
+        metrics.record_frame(start, Duration::from_millis(5), &stats);
+        metrics.record_frame(start + Duration::from_millis(500), Duration::from_millis(20), &stats);
+        metrics.update_counters(4096, 10, GlyphCacheStats { hits: 5, misses: 3, evictions: 1 });
+
+        let text = metrics.encode(start + Duration::from_millis(600));
+        assert!(text.contains("# TYPE alacritty_frames_total counter\nalacritty_frames_total 2\n"));
+        assert!(text.contains("\nalacritty_dropped_frames_total 1\n"));
+        assert!(text.contains("\nalacritty_frame_time_seconds_total 0.025\n"));
+        assert!(text.contains("\nalacritty_fps 2\n"));
+        assert!(text.contains("\nalacritty_draw_calls_total 8\n"));
+        assert!(text.contains("\nalacritty_uploaded_bytes_total 400\n"));
+        assert!(text.contains("\nalacritty_pty_read_bytes_total 4096\n"));
+        assert!(text.contains("\nalacritty_cached_glyphs 10\n"));
+        assert!(text.contains("\nalacritty_glyph_cache_misses_total 3\n"));
+    }
+
+    #[test]
+    fn fps_window() {
+        let mut metrics = Metrics::default();
+        let start = Instant::now();
+
+        for i in 0..10 {
+            let time = start + Duration::from_millis(i * 200);
+            metrics.record_frame(time, Duration::from_millis(1), &RenderStats::default());
+        }
+
+        // Only frames of the last second are counted.
+        assert!((metrics.fps(start + Duration::from_millis(1800)) - 5.).abs() < f64::EPSILON);
+        assert!(metrics.fps(start + Duration::from_secs(10)).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,51 @@
+//! Inspection of the processes running inside the terminal.
+
+#[cfg(unix)]
+use std::fs;
+use std::path::PathBuf;
+
+#[cfg(unix)]
+use alacritty_terminal::tty;
+
+/// Process group in the foreground of the terminal, falling back to the shell.
+#[cfg(unix)]
+fn foreground_pid() -> libc::pid_t {
+    let pid = unsafe { libc::tcgetpgrp(tty::master_fd()) };
+    if pid < 0 {
+        tty::child_pid()
+    } else {
+        pid
+    }
+}
+
+/// Path of an entry in the procfs directory of a process.
+#[cfg(unix)]
+fn proc_path(pid: libc::pid_t, entry: &str) -> String {
+    #[cfg(not(target_os = "freebsd"))]
+    return format!("/proc/{}/{}", pid, entry);
+    #[cfg(target_os = "freebsd")]
+    return format!("/compat/linux/proc/{}/{}", pid, entry);
+}
+
+/// Working directory of the foreground process.
+#[cfg(unix)]
+pub fn foreground_cwd() -> Option<PathBuf> {
+    fs::read_link(proc_path(foreground_pid(), "cwd")).ok()
+}
+
+#[cfg(not(unix))]
+pub fn foreground_cwd() -> Option<PathBuf> {
+    None
+}
+
+/// Executable name of the foreground process.
+#[cfg(unix)]
+pub fn foreground_name() -> Option<String> {
+    let name = fs::read_to_string(proc_path(foreground_pid(), "comm")).ok()?;
+    Some(name.trim_end().to_owned())
+}
+
+#[cfg(not(unix))]
+pub fn foreground_name() -> Option<String> {
+    None
+}
@@ -0,0 +1,404 @@
+//! Keyboard and mouse driven selection of a screen region, exported as text, ANSI text or PNG.
+
+use std::cmp::{max, min};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use alacritty_terminal::ansi::{Color, NamedColor};
+use alacritty_terminal::index::{Column, Line, Point};
+use alacritty_terminal::term::cell::{Cell, Flags};
+use alacritty_terminal::term::color::Rgb;
+use alacritty_terminal::term::{SizeInfo, Term};
+
+use crate::renderer::rects::RenderRect;
+
+/// Length of the dashes and gaps of the region outline in pixels.
+const DASH_LENGTH: u32 = 4;
+
+/// Time until the region outline moves by one pixel.
+const DASH_STEP: Duration = Duration::from_millis(50);
+
+/// Format a captured region is exported as.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CaptureFormat {
+    /// Plain text, copied to the clipboard.
+    Text,
+
+    /// Text with SGR escapes for colors and attributes, copied to the clipboard.
+    Ansi,
+
+    /// Cropped screenshot of the rendered region, written to a PNG file.
+    Png,
+}
+
+impl Default for CaptureFormat {
+    fn default() -> Self {
+        CaptureFormat::Text
+    }
+}
+
+impl CaptureFormat {
+    fn next(self) -> Self {
+        match self {
+            CaptureFormat::Text => CaptureFormat::Ansi,
+            CaptureFormat::Ansi => CaptureFormat::Png,
+            CaptureFormat::Png => CaptureFormat::Text,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            CaptureFormat::Text => "text",
+            CaptureFormat::Ansi => "ANSI text",
+            CaptureFormat::Png => "PNG",
+        }
+    }
+}
+
+/// Rectangle of cells, with both corners included.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CaptureRegion {
+    pub start: Point,
+    pub end: Point,
+}
+
+impl CaptureRegion {
+    fn new(a: Point, b: Point) -> Self {
+        Self {
+            start: Point::new(min(a.line, b.line), min(a.col, b.col)),
+            end: Point::new(max(a.line, b.line), max(a.col, b.col)),
+        }
+    }
+
+    /// Region in window pixels, as origin and size.
+    pub fn pixels(&self, size_info: &SizeInfo) -> (f32, f32, f32, f32) {
+        let x = size_info.padding_left() + self.start.col.0 as f32 * size_info.cell_width();
+        let y = size_info.padding_y() + self.start.line.0 as f32 * size_info.cell_height();
+        let width = (self.end.col.0 + 1 - self.start.col.0) as f32 * size_info.cell_width();
+        let height = (self.end.line.0 + 1 - self.start.line.0) as f32 * size_info.cell_height();
+        (x, y, width, height)
+    }
+}
+
+/// Region outline drawn while it is selected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CaptureOverlay {
+    pub region: CaptureRegion,
+    pub format: CaptureFormat,
+
+    /// Offset of the outline dashes in pixels.
+    phase: u32,
+}
+
+impl CaptureOverlay {
+    /// Dashed outline around the region, moving clockwise with the phase.
+    pub fn rects(&self, size_info: &SizeInfo) -> Vec<RenderRect> {
+        let (x, y, width, height) = self.region.pixels(size_info);
+        let black = Rgb { r: 0, g: 0, b: 0 };
+        let white = Rgb { r: 0xff, g: 0xff, b: 0xff };
+        let phase = self.phase as f32;
+
+        // White outline with black dashes on top of it.
+        let mut rects = vec![
+            RenderRect::new(x, y, width, 1., white, 1.),
+            RenderRect::new(x + width - 1., y, 1., height, white, 1.),
+            RenderRect::new(x, y + height - 1., width, 1., white, 1.),
+            RenderRect::new(x, y, 1., height, white, 1.),
+        ];
+
+        for (start, length) in dashes(width, phase) {
+            rects.push(RenderRect::new(x + start, y, length, 1., black, 1.));
+            let bottom_x = x + width - start - length;
+            rects.push(RenderRect::new(bottom_x, y + height - 1., length, 1., black, 1.));
+        }
+
+        for (start, length) in dashes(height, phase) {
+            rects.push(RenderRect::new(x + width - 1., y + start, 1., length, black, 1.));
+            rects.push(RenderRect::new(x, y + height - start - length, 1., length, black, 1.));
+        }
+
+        rects
+    }
+
+    /// Help text shown while selecting.
+    pub fn status(&self) -> String {
+        format!(
+            "Capture {}: arrows/mouse select, space anchors, tab changes format, enter exports",
+            self.format.name()
+        )
+    }
+}
+
+/// Region selection state.
+#[derive(Debug, Default)]
+pub struct RegionCapture {
+    /// Time the selection was started, or `None` when it is inactive.
+    started: Option<Instant>,
+
+    /// Corner fixed while the other corner is moved.
+    anchor: Option<Point>,
+
+    /// Corner moved with the arrow keys or mouse.
+    cursor: Point,
+
+    format: CaptureFormat,
+
+    /// Region which should be read back from the next frame.
+    pending_screenshot: Option<CaptureRegion>,
+}
+
+impl RegionCapture {
+    /// Start selecting a region at `cursor`.
+    pub fn start(&mut self, cursor: Point) {
+        self.started = Some(Instant::now());
+        self.anchor = None;
+        self.cursor = cursor;
+    }
+
+    pub fn cancel(&mut self) {
+        self.started = None;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Move the cursor by a number of cells, keeping it inside the grid.
+    pub fn move_cursor(&mut self, lines: isize, cols: isize, size_info: &SizeInfo) {
+        let max_line = size_info.screen_lines().0.saturating_sub(1) as isize;
+        let max_col = size_info.cols().0.saturating_sub(1) as isize;
+        let line = (self.cursor.line.0 as isize + lines).max(0).min(max_line);
+        let col = (self.cursor.col.0 as isize + cols).max(0).min(max_col);
+        self.cursor = Point::new(Line(line as usize), Column(col as usize));
+    }
+
+    /// Move the cursor to a cell, anchoring the region if it was not anchored yet.
+    pub fn drag_to(&mut self, point: Point) {
+        if self.anchor.is_none() {
+            self.anchor = Some(self.cursor);
+        }
+        self.cursor = point;
+    }
+
+    /// Start a new region at `point`.
+    pub fn press(&mut self, point: Point) {
+        self.anchor = Some(point);
+        self.cursor = point;
+    }
+
+    /// Fix the current corner, or release the anchor if it is already fixed.
+    pub fn toggle_anchor(&mut self) {
+        self.anchor = match self.anchor {
+            Some(_) => None,
+            None => Some(self.cursor),
+        };
+    }
+
+    pub fn cycle_format(&mut self) {
+        self.format = self.format.next();
+    }
+
+    pub fn format(&self) -> CaptureFormat {
+        self.format
+    }
+
+    /// Selected region, a single cell until the anchor is set.
+    pub fn region(&self) -> CaptureRegion {
+        CaptureRegion::new(self.anchor.unwrap_or(self.cursor), self.cursor)
+    }
+
+    /// Outline of the selected region at time `now`.
+    pub fn overlay(&self, now: Instant) -> Option<CaptureOverlay> {
+        let started = self.started?;
+        let steps = now.saturating_duration_since(started).as_millis() / DASH_STEP.as_millis();
+        let phase = (steps % u128::from(2 * DASH_LENGTH)) as u32;
+        Some(CaptureOverlay { region: self.region(), format: self.format, phase })
+    }
+
+    /// Read back the region from the next frame, once the outline is gone.
+    pub fn request_screenshot(&mut self) {
+        self.pending_screenshot = Some(self.region());
+        self.cancel();
+    }
+
+    pub fn take_screenshot(&mut self) -> Option<CaptureRegion> {
+        self.pending_screenshot.take()
+    }
+
+    pub fn screenshot_pending(&self) -> bool {
+        self.pending_screenshot.is_some()
+    }
+}
+
+/// Contents of a region, with SGR escapes for colors and attributes when `ansi` is set.
+pub fn region_text<T>(terminal: &Term<T>, region: CaptureRegion, ansi: bool) -> String {
+    let rows: Vec<Vec<Cell>> = (region.start.line.0..=region.end.line.0)
+        .map(|line| {
+            let line = terminal.visible_to_buffer(Point::new(Line(line), Column(0))).line;
+            let row = &terminal.grid()[line];
+            (region.start.col.0..=region.end.col.0).map(|col| row[Column(col)]).collect()
+        })
+        .collect();
+
+    encode_rows(&rows, ansi)
+}
+
+/// Join rows of cells into lines, without trailing whitespace.
+fn encode_rows(rows: &[Vec<Cell>], ansi: bool) -> String {
+    let mut text = String::new();
+
+    for row in rows {
+        let len = row.iter().rposition(|cell| cell.c != ' ' || has_background(cell));
+        let mut last_sgr = String::new();
+
+        for cell in &row[..len.map_or(0, |len| len + 1)] {
+            if cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
+                continue;
+            }
+
+            if ansi {
+                let sgr = sgr(cell);
+                if sgr != last_sgr {
+                    let _ = write!(text, "\x1b[0{}m", sgr);
+                    last_sgr = sgr;
+                }
+            }
+
+            text.push(cell.c);
+            for c in cell.chars()[1..].iter().take_while(|c| **c != ' ') {
+                text.push(*c);
+            }
+        }
+
+        if ansi && !last_sgr.is_empty() {
+            text.push_str("\x1b[0m");
+        }
+        text.push('\n');
+    }
+
+    text
+}
+
+/// Whether the cell has a background color which should be kept in ANSI output.
+fn has_background(cell: &Cell) -> bool {
+    cell.bg != Color::Named(NamedColor::Background)
+}
+
+/// SGR parameters of a cell, each prefixed with `;`.
+fn sgr(cell: &Cell) -> String {
+    let mut sgr = String::new();
+
+    let flags = [
+        (Flags::BOLD, 1),
+        (Flags::DIM, 2),
+        (Flags::ITALIC, 3),
+        (Flags::ALL_UNDERLINES, 4),
+        (Flags::BLINK, 5),
+        (Flags::INVERSE, 7),
+        (Flags::HIDDEN, 8),
+        (Flags::STRIKEOUT, 9),
+    ];
+    for (flag, param) in flags.iter() {
+        if cell.flags.intersects(*flag) {
+            let _ = write!(sgr, ";{}", param);
+        }
+    }
+
+    write_color(&mut sgr, cell.fg, 30);
+    write_color(&mut sgr, cell.bg, 40);
+
+    sgr
+}
+
+/// Write the SGR parameters of a foreground (`base` 30) or background (`base` 40) color.
+fn write_color(sgr: &mut String, color: Color, base: u8) {
+    let _ = match color {
+        Color::Named(color) if (color as usize) < 8 => write!(sgr, ";{}", base + color as u8),
+        Color::Named(color) if (color as usize) < 16 => {
+            write!(sgr, ";{}", base + 60 + color as u8 - 8)
+        },
+        Color::Named(_) => Ok(()),
+        Color::Indexed(index) => write!(sgr, ";{};5;{}", base + 8, index),
+        Color::Spec(rgb) => write!(sgr, ";{};2;{};{};{}", base + 8, rgb.r, rgb.g, rgb.b),
+    };
+}
+
+/// Dashes along an edge of `length` pixels, as offset and length, moved forward by `phase`.
+fn dashes(length: f32, phase: f32) -> Vec<(f32, f32)> {
+    let period = 2. * DASH_LENGTH as f32;
+    let mut dashes = Vec::new();
+
+    let mut start = phase % period - period;
+    while start < length {
+        let from = start.max(0.);
+        let to = (start + DASH_LENGTH as f32).min(length);
+        if to > from {
+            dashes.push((from, to - from));
+        }
+        start += period;
+    }
+
+    dashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(text: &str) -> Vec<Cell> {
+        text.chars().map(|c| Cell { c, ..Cell::default() }).collect()
+    }
+
+    #[test]
+    fn region_corners() {
+        let size_info = SizeInfo::new(100., 100., 10., 10., 0., 0., false);
+        let mut capture = RegionCapture::default();
+        capture.start(Point::new(Line(5), Column(5)));
+        assert!(capture.is_active());
+        assert_eq!(capture.region(), CaptureRegion::new(capture.cursor, capture.cursor));
+
+        capture.toggle_anchor();
+        capture.move_cursor(-2, -3, &size_info);
+        let expected = CaptureRegion {
+            start: Point::new(Line(3), Column(2)),
+            end: Point::new(Line(5), Column(5)),
+        };
+        assert_eq!(capture.region(), expected);
+
+        // The cursor stays inside the grid.
+        capture.move_cursor(100, 100, &size_info);
+        assert_eq!(capture.region().end, Point::new(Line(9), Column(9)));
+
+        capture.request_screenshot();
+        assert!(!capture.is_active());
+        assert!(capture.take_screenshot().is_some());
+        assert!(!capture.screenshot_pending());
+    }
+
+    #[test]
+    fn plain_text() {
+        let rows = vec![row("ab  "), row("    "), row(" c d")];
+        assert_eq!(encode_rows(&rows, false), "ab\n\n c d\n");
+    }
+
+    #[test]
+    fn ansi_text() {
+        let mut rows = vec![row("abc ")];
+        rows[0][1].flags = Flags::BOLD;
+        rows[0][1].fg = Color::Named(NamedColor::Red);
+        rows[0][2].fg = Color::Named(NamedColor::BrightBlue);
+        rows[0][3].bg = Color::Spec(Rgb { r: 1, g: 2, b: 3 });
+
+        assert_eq!(
+            encode_rows(&rows, true),
+            "a\x1b[0;1;31mb\x1b[0;94mc\x1b[0;48;2;1;2;3m \x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn dash_offsets() {
+        assert_eq!(dashes(10., 0.), vec![(0., 4.), (8., 2.)]);
+        assert_eq!(dashes(10., 2.), vec![(2., 4.)]);
+        assert_eq!(dashes(10., 6.), vec![(0., 2.), (6., 4.)]);
+    }
+}
@@ -1,22 +1,15 @@
 use log::*;
+use std::collections::HashSet;
 use std::ptr;
 
 use crate::gl;
-use crate::gl::types::*;
 use crossfont::BitmapBuffer;
 
+use super::gl_api::Gl;
 use super::glyph::{GridAtlasGlyph, QuadAtlasGlyph, RasterizedGlyph};
 use super::math::*;
 use super::texture::*;
 
-/// Rationale for 1024x1024 texture:
-/// - for most common case (mostly ASCII-only contents and reasonable font size) this is more than
-///   enough
-/// - it's just 4Mb, so not a huge waste of RAM
-/// Note: for less common case (larger/hidpi font, non-ASCII content) it might be advisable to make
-/// it possible to increase atlas size (TODO)
-static GRID_ATLAS_SIZE: i32 = 1024;
-
 /// Additinal entry padding in percent
 static GRID_ATLAS_PAD_PCT: Vec2<i32> = Vec2 { x: 10, y: 10 };
 
@@ -30,6 +23,21 @@ pub enum AtlasInsertError {
     GlyphTooLarge,
 }
 
+/// Location of a glyph in the grid atlases.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AtlasEntry {
+    pub atlas_index: usize,
+    pub line: u16,
+    pub column: u16,
+}
+
+/// Glyph moved to another grid atlas entry by defragmentation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GlyphMove {
+    pub from: AtlasEntry,
+    pub to: AtlasEntry,
+}
+
 /// Grid atlas entry dimensions.
 pub struct CellDims {
     /// Offset to glyph baseline (i.e. padding).
@@ -45,9 +53,12 @@ pub struct CellDims {
 /// Rasterized glyphs sizes and offsets are "consumed" by placing it accordingly into the atlas
 /// cell.
 #[derive(Debug)]
-pub struct GridAtlas {
-    /// OpenGL texture name/id.
-    pub tex: GLuint,
+pub struct GridAtlas<T = Texture> {
+    /// Texture storing the glyphs.
+    pub tex: T,
+
+    /// Width and height of the texture.
+    size: i32,
 
     /// This atlas index/id.
     index: usize,
@@ -67,6 +78,9 @@ pub struct GridAtlas {
     /// Next free entry coordinates
     free_line: i32,
     free_column: i32,
+
+    /// Entries released by evicted glyphs, as line and column.
+    free_slots: Vec<(i32, i32)>,
 }
 
 impl GridAtlas {
@@ -74,7 +88,21 @@ impl GridAtlas {
     /// cell_size is the entire precomputed cell size for each element (atlas will also apply
     /// additional padding, see GRID_ATLAS_PAD_PCT) cell_offset is the position of glyph origin
     /// relative to cell left-bottom corner.
-    pub fn new(index: usize, cell_size: Vec2<i32>, cell_offset: Vec2<i32>) -> Self {
+    pub fn new(index: usize, size: i32, cell_size: Vec2<i32>, cell_offset: Vec2<i32>) -> Self {
+        let tex = Texture { id: unsafe { create_texture(&Gl, size, size, PixelFormat::RGBA8) } };
+        Self::with_texture(index, size, cell_size, cell_offset, tex)
+    }
+}
+
+impl<T: TextureUpload> GridAtlas<T> {
+    /// Create new grid atlas uploading its glyphs to `tex`.
+    pub fn with_texture(
+        index: usize,
+        size: i32,
+        cell_size: Vec2<i32>,
+        cell_offset: Vec2<i32>,
+        tex: T,
+    ) -> Self {
         let atlas_cell_size = cell_size + cell_offset;
 
         // Apply additinal padding
@@ -85,27 +113,106 @@ impl GridAtlas {
         let half_padding = padding / 2;
         let cell_offset = cell_offset + half_padding;
         let atlas_cell_size = atlas_cell_size + padding;
-        let grid_size = (Vec2::from(GRID_ATLAS_SIZE) / atlas_cell_size).min(Vec2::from(256));
+        let grid_size = (Vec2::from(size) / atlas_cell_size).min(Vec2::from(256));
 
-        let ret = Self {
+        let mut ret = Self {
             index,
-            tex: unsafe { create_texture(GRID_ATLAS_SIZE, GRID_ATLAS_SIZE, PixelFormat::RGBA8) },
+            tex,
+            size,
             cell_size: atlas_cell_size,
             cell_offset,
             half_padding,
             grid_size,
             free_line: 0,
-            free_column: 1, // FIXME do not use sentinel 0,0 value as empty, prefere flags instead
+            free_column: 0,
+            free_slots: Vec::new(),
         };
+
+        // FIXME do not use sentinel 0,0 value as empty, prefere flags instead
+        ret.advance_free_entry();
+
         debug!("new atlas with padding: {:?}, {:?}", padding, ret);
         ret
     }
 
+    /// Move the next free entry to the following column, wrapping at the end of a line.
+    fn advance_free_entry(&mut self) {
+        self.free_column += 1;
+        if self.free_column >= self.grid_size.x {
+            self.free_column = 0;
+            self.free_line += 1;
+        }
+    }
+
     /// Return atlas entry cell dimensions
     pub fn cell_dims(&self) -> CellDims {
         CellDims { offset: self.cell_offset, size: self.cell_size }
     }
 
+    /// Size of the atlas texture in pixels.
+    pub fn size(&self) -> Vec2<i32> {
+        Vec2::from(self.size)
+    }
+
+    /// Whether there are entries of removed glyphs which can be reused.
+    pub fn has_free_slots(&self) -> bool {
+        !self.free_slots.is_empty()
+    }
+
+    /// Number of entries holding a glyph.
+    pub fn glyph_count(&self) -> usize {
+        // The entry at (0, 0) is never used.
+        let filled = (self.free_line * self.grid_size.x + self.free_column - 1).max(0) as usize;
+        filled - self.free_slots.len()
+    }
+
+    /// Number of entries which can still be filled.
+    pub fn free_capacity(&self) -> usize {
+        let total = self.grid_size.x * self.grid_size.y;
+        let filled = self.free_line * self.grid_size.x + self.free_column;
+        self.free_slots.len() + (total - filled).max(0) as usize
+    }
+
+    /// Entries holding a glyph.
+    pub fn occupied(&self) -> Vec<AtlasEntry> {
+        let free: HashSet<&(i32, i32)> = self.free_slots.iter().collect();
+        let end = self.free_line * self.grid_size.x + self.free_column;
+        (1..end.max(1))
+            .map(|position| (position / self.grid_size.x, position % self.grid_size.x))
+            .filter(|entry| !free.contains(entry))
+            .map(|(line, column)| self.entry(line, column))
+            .collect()
+    }
+
+    /// Reserve a free entry for a glyph.
+    fn allocate(&mut self) -> Option<AtlasEntry> {
+        let (line, column) = match self.free_slots.pop() {
+            Some(slot) => slot,
+            None if self.free_line < self.grid_size.y && self.free_column < self.grid_size.x => {
+                let slot = (self.free_line, self.free_column);
+                self.advance_free_entry();
+                slot
+            },
+            None => return None,
+        };
+
+        Some(self.entry(line, column))
+    }
+
+    fn entry(&self, line: i32, column: i32) -> AtlasEntry {
+        AtlasEntry { atlas_index: self.index, line: line as u16, column: column as u16 }
+    }
+
+    /// Texture coordinates of the bottom left corner of an entry.
+    pub fn entry_origin(&self, entry: AtlasEntry) -> Vec2<i32> {
+        Vec2::new(i32::from(entry.column), i32::from(entry.line)) * self.cell_size
+    }
+
+    /// Mark the entry of a glyph as free, so it can be reused by the next insertion.
+    pub fn remove(&mut self, glyph: &GridAtlasGlyph) {
+        self.free_slots.push((i32::from(glyph.line), i32::from(glyph.column)));
+    }
+
     /// Attempt to insert a new rasterized glyph into this atlas
     /// Glyphs which have offsets and sizes that make them not fit into cell dimensions will return
     /// GlyphTooLarge error.
@@ -113,13 +220,13 @@ impl GridAtlas {
         &mut self,
         rasterized: &RasterizedGlyph,
     ) -> Result<GridAtlasGlyph, AtlasInsertError> {
-        if self.free_line >= self.grid_size.y {
+        let has_free_entry =
+            self.free_line < self.grid_size.y && self.free_column < self.grid_size.x;
+        if self.free_slots.is_empty() && !has_free_entry {
             return Err(AtlasInsertError::Full);
         }
 
         let rasterized = &rasterized.rasterized;
-        let line = self.free_line;
-        let column = self.free_column;
 
         // Atlas cell metrics in logical glyph space
         //   .----------------.<-- single glyph cell in atlas texture (self.cell_size)
@@ -159,34 +266,61 @@ impl GridAtlas {
         //   `- atlas cell texture origin (0, 0)
         //
 
-        let off_x = self.cell_offset.x + rasterized.left;
-        let off_y = self.cell_size.y - rasterized.top - self.half_padding.y;
-
-        let tex_x = off_x + column * self.cell_size.x;
-        let tex_y = off_y + line * self.cell_size.y;
+        let mut off_x = self.cell_offset.x + rasterized.left;
+        let mut off_y = self.cell_size.y - rasterized.top - self.half_padding.y;
+        let mut width = rasterized.width;
+        let mut height = rasterized.height;
+        let mut scaled = None;
 
         if off_x < 0
             || off_y < 0
-            || off_x + rasterized.width > self.cell_size.x
-            || off_y + rasterized.height > self.cell_size.y
+            || off_x + width > self.cell_size.x
+            || off_y + height > self.cell_size.y
         {
-            debug!(
-                "glyph '{}' {},{} {}x{} doesn't fit into atlas cell size={:?} offset={:?}",
-                rasterized.c,
-                rasterized.left,
-                rasterized.top,
-                rasterized.width,
-                rasterized.height,
-                self.cell_size,
-                self.cell_offset,
-            );
-
-            return Err(AtlasInsertError::GlyphTooLarge);
+            match &rasterized.buf {
+                // Colored glyphs like emoji are fixed size bitmaps, which are scaled down to fit
+                // and centered in the cell.
+                BitmapBuffer::RGBA(buf) if width > 0 && height > 0 => {
+                    let scale = (self.cell_size.x as f32 / width as f32)
+                        .min(self.cell_size.y as f32 / height as f32)
+                        .min(1.);
+                    let size = Vec2::new(
+                        ((width as f32 * scale) as i32).max(1),
+                        ((height as f32 * scale) as i32).max(1),
+                    );
+                    scaled = Some(resample_rgba(buf, Vec2::new(width, height), size));
+
+                    width = size.x;
+                    height = size.y;
+                    off_x = (self.cell_size.x - width) / 2;
+                    off_y = (self.cell_size.y - height) / 2;
+                },
+                _ => {
+                    debug!(
+                        "glyph '{}' {},{} {}x{} doesn't fit into atlas cell size={:?} offset={:?}",
+                        rasterized.c,
+                        rasterized.left,
+                        rasterized.top,
+                        rasterized.width,
+                        rasterized.height,
+                        self.cell_size,
+                        self.cell_offset,
+                    );
+
+                    return Err(AtlasInsertError::GlyphTooLarge);
+                },
+            }
         }
 
+        let reused_slot = self.free_slots.pop();
+        let (line, column) = reused_slot.unwrap_or((self.free_line, self.free_column));
+
+        let tex_x = off_x + column * self.cell_size.x;
+        let tex_y = off_y + line * self.cell_size.y;
+
         let (colored, format, buf) = match &rasterized.buf {
-            BitmapBuffer::RGB(buf) => (false, gl::RGB, buf),
-            BitmapBuffer::RGBA(buf) => (true, gl::RGBA, buf),
+            BitmapBuffer::RGB(buf) => (false, PixelFormat::RGB8, buf),
+            BitmapBuffer::RGBA(buf) => (true, PixelFormat::RGBA8, scaled.as_ref().unwrap_or(buf)),
         };
 
         // Load data into OpenGL.
@@ -195,22 +329,16 @@ impl GridAtlas {
         // 2. upload once before drawing by column/line subrect
         // This can substantially improve start-up time, and lower perceptible lag when a bunch of
         // new glyphs are displayed.
-        unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, self.tex);
-            gl::TexSubImage2D(
-                gl::TEXTURE_2D,
-                0,
-                tex_x,
-                tex_y,
-                rasterized.width,
-                rasterized.height,
-                format,
-                gl::UNSIGNED_BYTE,
-                buf.as_ptr() as *const _,
-            );
-            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+        // Reused entries still contain the previous glyph, which might not be fully covered.
+        if reused_slot.is_some() {
+            let empty = vec![0u8; (self.cell_size.x * self.cell_size.y * 4) as usize];
+            let origin = Vec2::new(column, line) * self.cell_size;
+            self.tex.upload(origin, self.cell_size, PixelFormat::RGBA8, &empty);
         }
 
+        self.tex.upload(Vec2::new(tex_x, tex_y), Vec2::new(width, height), format, buf);
+
         trace!(
             "'{}' {},{} {}x{} {},{} => l={} c={} {},{}",
             rasterized.c,
@@ -226,10 +354,8 @@ impl GridAtlas {
             tex_y,
         );
 
-        self.free_column += 1;
-        if self.free_column == self.grid_size.x {
-            self.free_column = 0;
-            self.free_line += 1;
+        if reused_slot.is_none() {
+            self.advance_free_entry();
         }
 
         let line = line as u16;
@@ -238,12 +364,65 @@ impl GridAtlas {
     }
 }
 
-impl Drop for GridAtlas {
-    fn drop(&mut self) {
-        unsafe {
-            gl::DeleteTextures(1, &self.tex);
+/// Plan moving every glyph of the `last` grid atlas into free entries of the `earlier` ones.
+///
+/// The destination entries are reserved right away. Returns `None` without reserving anything
+/// when there isn't enough room, since the last atlas can only be released once it is empty.
+pub fn plan_defragment<T: TextureUpload>(
+    last: &GridAtlas<T>,
+    earlier: &mut [&mut GridAtlas<T>],
+) -> Option<Vec<GlyphMove>> {
+    let free: usize = earlier.iter().map(|atlas| atlas.free_capacity()).sum();
+    if last.glyph_count() > free {
+        return None;
+    }
+
+    let mut destinations = earlier.iter_mut();
+    let mut destination = destinations.next();
+    let mut moves = Vec::with_capacity(last.glyph_count());
+    for from in last.occupied() {
+        let to = loop {
+            match destination.as_mut()?.allocate() {
+                Some(to) => break to,
+                None => destination = destinations.next(),
+            }
+        };
+        moves.push(GlyphMove { from, to });
+    }
+
+    Some(moves)
+}
+
+/// Resample an RGBA bitmap of `size` to `new_size` using bilinear filtering.
+fn resample_rgba(buf: &[u8], size: Vec2<i32>, new_size: Vec2<i32>) -> Vec<u8> {
+    let scale_x = size.x as f32 / new_size.x as f32;
+    let scale_y = size.y as f32 / new_size.y as f32;
+
+    // Source coordinates of the center of a destination pixel, along with the neighbouring pixel
+    // and the weight of the neighbour.
+    let source = |position: i32, scale: f32, size: i32| {
+        let source = ((position as f32 + 0.5) * scale - 0.5).max(0.);
+        let first = (source as i32).min(size - 1);
+        let second = (first + 1).min(size - 1);
+        (first, second, source - first as f32)
+    };
+
+    let mut resampled = Vec::with_capacity((new_size.x * new_size.y * 4) as usize);
+    for y in 0..new_size.y {
+        let (y0, y1, fy) = source(y, scale_y, size.y);
+        for x in 0..new_size.x {
+            let (x0, x1, fx) = source(x, scale_x, size.x);
+            for channel in 0..4 {
+                let texel =
+                    |x: i32, y: i32| f32::from(buf[((y * size.x + x) * 4 + channel) as usize]);
+                let top = texel(x0, y0) * (1. - fx) + texel(x1, y0) * fx;
+                let bottom = texel(x0, y1) * (1. - fx) + texel(x1, y1) * fx;
+                resampled.push((top * (1. - fy) + bottom * fy).round() as u8);
+            }
         }
     }
+
+    resampled
 }
 
 /// Manages a single texture atlas.
@@ -265,9 +444,9 @@ impl Drop for GridAtlas {
 /// (0, 0)  x->
 /// ```
 #[derive(Debug)]
-pub struct Atlas {
-    /// Texture id for this atlas.
-    pub id: GLuint,
+pub struct Atlas<T = Texture> {
+    /// Texture storing the glyphs.
+    pub tex: T,
 
     /// This atlas index
     index: usize,
@@ -295,7 +474,7 @@ pub struct Atlas {
 
 impl Atlas {
     pub fn new(index: usize, size: i32) -> Self {
-        let mut id: GLuint = 0;
+        let mut id = 0;
         unsafe {
             gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
             gl::GenTextures(1, &mut id);
@@ -322,8 +501,15 @@ impl Atlas {
             gl::BindTexture(gl::TEXTURE_2D, 0);
         }
 
+        Self::with_texture(index, size, Texture { id })
+    }
+}
+
+impl<T: TextureUpload> Atlas<T> {
+    /// Create new atlas uploading its glyphs to `tex`.
+    pub fn with_texture(index: usize, size: i32, tex: T) -> Self {
         Self {
-            id,
+            tex,
             index,
             width: size,
             height: size,
@@ -333,6 +519,11 @@ impl Atlas {
         }
     }
 
+    /// Size of the atlas texture in pixels.
+    pub fn size(&self) -> Vec2<i32> {
+        Vec2::new(self.width, self.height)
+    }
+
     pub fn clear(&mut self) {
         self.row_extent = 0;
         self.row_baseline = 0;
@@ -362,43 +553,18 @@ impl Atlas {
 
     /// Insert the glyph without checking for room.
     ///
-    /// Internal function for use once atlas has been checked for space. GL
-    /// errors could still occur at this point if we were checking for them;
-    /// hence, the Result.
+    /// Internal function for use once atlas has been checked for space.
     fn insert_inner(&mut self, glyph: &crossfont::RasterizedGlyph) -> QuadAtlasGlyph {
         let offset_y = self.row_baseline;
         let offset_x = self.row_extent;
         let height = glyph.height as i32;
         let width = glyph.width as i32;
-        let colored;
-
-        unsafe {
-            gl::BindTexture(gl::TEXTURE_2D, self.id);
-
-            // Load data into OpenGL.
-            let (format, buf) = match &glyph.buf {
-                BitmapBuffer::RGB(buf) => {
-                    colored = false;
-                    (gl::RGB, buf)
-                },
-                BitmapBuffer::RGBA(buf) => {
-                    colored = true;
-                    (gl::RGBA, buf)
-                },
-            };
 
-            gl::TexSubImage2D(
-                gl::TEXTURE_2D,
-                0,
-                offset_x,
-                offset_y,
-                width,
-                height,
-                format,
-                gl::UNSIGNED_BYTE,
-                buf.as_ptr() as *const _,
-            );
-        }
+        let (colored, format, buf) = match &glyph.buf {
+            BitmapBuffer::RGB(buf) => (false, PixelFormat::RGB8, buf),
+            BitmapBuffer::RGBA(buf) => (true, PixelFormat::RGBA8, buf),
+        };
+        self.tex.upload(Vec2::new(offset_x, offset_y), Vec2::new(width, height), format, buf);
 
         // Update Atlas state.
         self.row_extent = offset_x + width;
@@ -430,7 +596,7 @@ impl Atlas {
     fn room_in_row(&self, raw: &crossfont::RasterizedGlyph) -> bool {
         let next_extent = self.row_extent + raw.width as i32;
         let enough_width = next_extent <= self.width;
-        let enough_height = (raw.height as i32) < (self.height - self.row_baseline);
+        let enough_height = raw.height as i32 <= self.height - self.row_baseline;
 
         enough_width && enough_height
     }
@@ -449,3 +615,306 @@ impl Atlas {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Texture recording the rectangles uploaded to it.
+    #[derive(Debug)]
+    struct RecordingTexture {
+        size: i32,
+        uploads: Vec<(Vec2<i32>, Vec2<i32>)>,
+    }
+
+    impl RecordingTexture {
+        fn new(size: i32) -> Self {
+            Self { size, uploads: Vec::new() }
+        }
+    }
+
+    impl TextureUpload for RecordingTexture {
+        fn upload(&mut self, origin: Vec2<i32>, size: Vec2<i32>, format: PixelFormat, data: &[u8]) {
+            let bytes_per_pixel = match format {
+                PixelFormat::RGBA8 => 4,
+                PixelFormat::RGB8 => 3,
+            };
+            assert_eq!(data.len(), (size.x * size.y * bytes_per_pixel) as usize);
+            assert!(origin.x >= 0 && origin.y >= 0, "upload at {:?}", origin);
+            assert!(
+                origin.x + size.x <= self.size && origin.y + size.y <= self.size,
+                "upload of {:?} at {:?} exceeds texture of {}",
+                size,
+                origin,
+                self.size,
+            );
+
+            self.uploads.push((origin, size));
+        }
+    }
+
+    /// Xorshift generator, so every failing seed can be reproduced.
+    struct Rng(u64);
+
+    impl Rng {
+        fn range(&mut self, min: i32, max: i32) -> i32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            min + (self.0 % (max - min + 1) as u64) as i32
+        }
+    }
+
+    fn glyph(width: i32, height: i32, left: i32, top: i32, colored: bool) -> RasterizedGlyph {
+        let buf = if colored {
+            BitmapBuffer::RGBA(vec![0; (width * height * 4) as usize])
+        } else {
+            BitmapBuffer::RGB(vec![0; (width * height * 3) as usize])
+        };
+
+        RasterizedGlyph {
+            rasterized: crossfont::RasterizedGlyph { c: 'x', width, height, top, left, buf },
+            wide: false,
+            zero_width: false,
+        }
+    }
+
+    /// Glyph with random metrics, including negative bearings.
+    fn random_glyph(rng: &mut Rng, max: i32) -> RasterizedGlyph {
+        let width = rng.range(0, max);
+        let height = rng.range(0, max);
+        glyph(width, height, rng.range(-max, max), rng.range(-max, max), rng.range(0, 7) == 0)
+    }
+
+    fn overlaps(a: (Vec2<i32>, Vec2<i32>), b: (Vec2<i32>, Vec2<i32>)) -> bool {
+        let ((a_origin, a_size), (b_origin, b_size)) = (a, b);
+        a_origin.x < b_origin.x + b_size.x
+            && b_origin.x < a_origin.x + a_size.x
+            && a_origin.y < b_origin.y + b_size.y
+            && b_origin.y < a_origin.y + a_size.y
+    }
+
+    #[test]
+    fn grid_atlas_packing() {
+        for seed in 1..100 {
+            let mut rng = Rng(seed);
+            let size = rng.range(8, 128);
+            let cell_size = Vec2::new(rng.range(1, 16), rng.range(1, 24));
+            let cell_offset = Vec2::new(rng.range(0, 4), rng.range(0, 6));
+            let mut atlas = GridAtlas::with_texture(
+                0,
+                size,
+                cell_size,
+                cell_offset,
+                RecordingTexture::new(size),
+            );
+
+            let dims = atlas.cell_dims();
+            let grid_size = atlas.grid_size;
+            // The entry at (0, 0) is never used.
+            let capacity = (grid_size.x * grid_size.y - 1).max(0) as usize;
+            let mut entries: Vec<GridAtlasGlyph> = Vec::new();
+
+            for _ in 0..500 {
+                if !entries.is_empty() && rng.range(0, 3) == 0 {
+                    let entry =
+                        entries.swap_remove(rng.range(0, entries.len() as i32 - 1) as usize);
+                    atlas.remove(&entry);
+                    continue;
+                }
+
+                let glyph = random_glyph(&mut rng, dims.size.x.max(dims.size.y) + 2);
+                let rasterized = &glyph.rasterized;
+                let off_x = dims.offset.x + rasterized.left;
+                let off_y = dims.size.y - rasterized.top - atlas.half_padding.y;
+                let fits = off_x >= 0
+                    && off_y >= 0
+                    && off_x + rasterized.width <= dims.size.x
+                    && off_y + rasterized.height <= dims.size.y;
+                let scalable = match rasterized.buf {
+                    BitmapBuffer::RGBA(_) => rasterized.width > 0 && rasterized.height > 0,
+                    BitmapBuffer::RGB(_) => false,
+                };
+                let had_free_slots = atlas.has_free_slots();
+                let uploads = atlas.tex.uploads.len();
+
+                match atlas.insert(&glyph) {
+                    Ok(entry) => {
+                        assert!(fits || scalable, "seed {}: {:?} was inserted", seed, rasterized);
+
+                        let (line, column) = (i32::from(entry.line), i32::from(entry.column));
+                        assert!(line < grid_size.y && column < grid_size.x);
+                        assert!((line, column) != (0, 0));
+                        assert!(entries
+                            .iter()
+                            .all(|other| (other.line, other.column) != (entry.line, entry.column)));
+
+                        // Every upload stays within the entry of the glyph.
+                        let cell = (Vec2::new(column, line) * dims.size, dims.size);
+                        for &(origin, size) in &atlas.tex.uploads[uploads..] {
+                            assert!(origin.x >= cell.0.x && origin.y >= cell.0.y);
+                            assert!(origin.x + size.x <= cell.0.x + cell.1.x);
+                            assert!(origin.y + size.y <= cell.0.y + cell.1.y);
+                        }
+
+                        entries.push(entry);
+                    },
+                    Err(AtlasInsertError::GlyphTooLarge) => {
+                        assert!(!fits && !scalable, "seed {}: {:?} was rejected", seed, rasterized);
+                        assert_eq!(atlas.has_free_slots(), had_free_slots);
+                        assert_eq!(atlas.tex.uploads.len(), uploads);
+                    },
+                    Err(AtlasInsertError::Full) => {
+                        assert_eq!(entries.len(), capacity, "seed {}", seed);
+                        assert!(!atlas.has_free_slots());
+                        assert_eq!(atlas.tex.uploads.len(), uploads);
+                    },
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grid_atlas_negative_bearing() {
+        let cell_size = Vec2::new(10, 20);
+        let cell_offset = Vec2::new(2, 4);
+        let mut atlas =
+            GridAtlas::with_texture(0, 64, cell_size, cell_offset, RecordingTexture::new(64));
+        let offset = atlas.cell_dims().offset;
+
+        assert!(atlas.insert(&glyph(4, 4, -offset.x, 4, false)).is_ok());
+        assert_eq!(atlas.tex.uploads.last().unwrap().0.x, atlas.cell_dims().size.x);
+
+        let result = atlas.insert(&glyph(4, 4, -offset.x - 1, 4, false));
+        assert!(matches!(result, Err(AtlasInsertError::GlyphTooLarge)));
+    }
+
+    #[test]
+    fn grid_atlas_single_column() {
+        let mut atlas = GridAtlas::with_texture(
+            0,
+            20,
+            Vec2::new(10, 4),
+            Vec2::new(0, 0),
+            RecordingTexture::new(20),
+        );
+        assert_eq!(atlas.grid_size.x, 1);
+
+        let entry = atlas.insert(&glyph(1, 1, 0, 1, false)).unwrap();
+        assert_eq!((entry.line, entry.column), (1, 0));
+    }
+
+    #[test]
+    fn grid_atlas_defragment() {
+        let new_atlas = |index| {
+            GridAtlas::with_texture(
+                index,
+                20,
+                Vec2::new(4, 4),
+                Vec2::new(0, 0),
+                RecordingTexture::new(20),
+            )
+        };
+        let mut first = new_atlas(0);
+        let mut last = new_atlas(1);
+
+        let mut entries = Vec::new();
+        while let Ok(entry) = first.insert(&glyph(1, 1, 0, 1, false)) {
+            entries.push(entry);
+        }
+        for _ in 0..3 {
+            last.insert(&glyph(1, 1, 0, 1, false)).unwrap();
+        }
+        assert_eq!(first.glyph_count(), 15);
+        assert_eq!(first.free_capacity(), 0);
+        assert_eq!(last.glyph_count(), 3);
+        assert_eq!(last.occupied().len(), 3);
+
+        // Nothing is reserved while the glyphs don't fit.
+        first.remove(&entries[4]);
+        first.remove(&entries[9]);
+        assert!(plan_defragment(&last, &mut [&mut first]).is_none());
+        assert_eq!(first.free_capacity(), 2);
+
+        first.remove(&entries[2]);
+        let moves = plan_defragment(&last, &mut [&mut first]).unwrap();
+        assert_eq!(first.glyph_count(), 15);
+        assert_eq!(first.free_capacity(), 0);
+
+        let from: Vec<_> = moves.iter().map(|glyph_move| glyph_move.from).collect();
+        assert_eq!(from, last.occupied());
+        for (glyph_move, removed) in moves.iter().zip(&[2, 9, 4]) {
+            let removed = entries[*removed];
+            assert_eq!(glyph_move.to.atlas_index, 0);
+            assert_eq!((glyph_move.to.line, glyph_move.to.column), (removed.line, removed.column));
+        }
+    }
+
+    #[test]
+    fn quad_atlas_packing() {
+        for seed in 1..100 {
+            let mut rng = Rng(seed);
+            let size = rng.range(8, 64);
+            let mut atlas = Atlas::with_texture(0, size, RecordingTexture::new(size));
+            let mut placed = Vec::new();
+
+            for _ in 0..200 {
+                let glyph = random_glyph(&mut rng, size / 2 + 2);
+                let (width, height) = (glyph.rasterized.width, glyph.rasterized.height);
+                let uploads = atlas.tex.uploads.len();
+
+                match atlas.insert(&glyph) {
+                    Ok(entry) => {
+                        let rect = *atlas.tex.uploads.last().unwrap();
+                        assert_eq!(rect.1.x, width);
+                        assert_eq!(rect.1.y, height);
+                        assert_eq!((entry.uv_left * size as f32).round() as i32, rect.0.x);
+                        assert_eq!((entry.uv_bot * size as f32).round() as i32, rect.0.y);
+                        assert!(
+                            placed.iter().all(|other| !overlaps(*other, rect)),
+                            "seed {}: {:?} overlaps",
+                            seed,
+                            rect
+                        );
+
+                        placed.push(rect);
+                    },
+                    Err(AtlasInsertError::GlyphTooLarge) => {
+                        assert!(width > size || height > size);
+                        assert_eq!(atlas.tex.uploads.len(), uploads);
+                    },
+                    Err(AtlasInsertError::Full) => {
+                        let remaining = size - atlas.row_baseline;
+                        assert!(height > remaining || atlas.row_extent + width > size);
+                        assert_eq!(atlas.tex.uploads.len(), uploads);
+                    },
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn quad_atlas_fits_full_size() {
+        let mut atlas = Atlas::with_texture(0, 16, RecordingTexture::new(16));
+        assert!(atlas.insert(&glyph(16, 16, 0, 16, false)).is_ok());
+        assert!(matches!(atlas.insert(&glyph(1, 1, 0, 1, false)), Err(AtlasInsertError::Full)));
+    }
+
+    #[test]
+    fn resample_keeps_size() {
+        let buf: Vec<u8> = (0..2 * 2 * 4).collect();
+        assert_eq!(resample_rgba(&buf, Vec2::new(2, 2), Vec2::new(2, 2)), buf);
+    }
+
+    #[test]
+    fn resample_halves_size() {
+        let mut buf = Vec::new();
+        for pixel in &[0, 255, 1, 1, 255, 0, 1, 1] {
+            let rgba = if *pixel == 1 { [10, 20, 30, 40] } else { [*pixel; 4] };
+            buf.extend_from_slice(&rgba);
+        }
+
+        let resampled = resample_rgba(&buf, Vec2::new(4, 2), Vec2::new(2, 1));
+        assert_eq!(resampled, vec![128, 128, 128, 128, 10, 20, 30, 40]);
+    }
+}
@@ -0,0 +1,151 @@
+use std::path::{Path, PathBuf};
+
+use image::png::PngEncoder;
+use image::{ColorType, ImageResult};
+
+use super::math::Vec2;
+use crate::gl;
+use crate::gl::types::*;
+
+/// Size of each atlas in the overlay, in pixels.
+const OVERLAY_ATLAS_SIZE: i32 = 256;
+
+/// Distance between atlases in the overlay and from the edges of the frame, in pixels.
+const OVERLAY_MARGIN: i32 = 4;
+
+/// Glyph atlas texture which can be inspected for debugging.
+#[derive(Debug, Copy, Clone)]
+pub struct AtlasTexture {
+    /// Renderer the atlas belongs to.
+    pub kind: &'static str,
+    pub index: usize,
+    pub tex: GLuint,
+    pub size: Vec2<i32>,
+}
+
+/// Debug views of the glyph atlas textures.
+#[derive(Debug)]
+pub struct AtlasDebug {
+    /// Framebuffer the atlas textures are attached to, so they can be blitted.
+    fbo: GLuint,
+}
+
+impl AtlasDebug {
+    pub fn new() -> Self {
+        let mut fbo: GLuint = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut fbo);
+        }
+
+        Self { fbo }
+    }
+
+    /// Draw all atlases next to each other in the bottom right corner of the current framebuffer.
+    pub fn draw_overlay(&self, atlases: &[AtlasTexture], frame_size: Vec2<i32>) {
+        unsafe {
+            let mut read_fbo: GLint = 0;
+            gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut read_fbo);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.fbo);
+
+            let mut right = frame_size.x - OVERLAY_MARGIN;
+            for atlas in atlases {
+                let left = right - OVERLAY_ATLAS_SIZE;
+
+                gl::FramebufferTexture2D(
+                    gl::READ_FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0,
+                    gl::TEXTURE_2D,
+                    atlas.tex,
+                    0,
+                );
+                gl::BlitFramebuffer(
+                    0,
+                    0,
+                    atlas.size.x,
+                    atlas.size.y,
+                    left,
+                    OVERLAY_MARGIN,
+                    right,
+                    OVERLAY_MARGIN + OVERLAY_ATLAS_SIZE,
+                    gl::COLOR_BUFFER_BIT,
+                    gl::LINEAR,
+                );
+
+                right = left - OVERLAY_MARGIN;
+            }
+
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, read_fbo as GLuint);
+        }
+    }
+
+    /// Write every atlas into a PNG file in `dir`.
+    ///
+    /// Returns the paths of all written files.
+    pub fn dump(&self, atlases: &[AtlasTexture], dir: &Path) -> ImageResult<Vec<PathBuf>> {
+        let mut paths = Vec::with_capacity(atlases.len());
+
+        for atlas in atlases {
+            let path = dir.join(atlas.file_name());
+            image::save_buffer(
+                &path,
+                &atlas.read_pixels(),
+                atlas.size.x as u32,
+                atlas.size.y as u32,
+                ColorType::Rgba8,
+            )?;
+            paths.push(path);
+        }
+
+        Ok(paths)
+    }
+
+    /// Encode every atlas as PNG, together with its file name.
+    pub fn encode(&self, atlases: &[AtlasTexture]) -> ImageResult<Vec<(String, Vec<u8>)>> {
+        atlases
+            .iter()
+            .map(|atlas| {
+                let png = encode_png(&atlas.read_pixels(), atlas.size)?;
+                Ok((atlas.file_name(), png))
+            })
+            .collect()
+    }
+}
+
+impl AtlasTexture {
+    fn file_name(&self) -> String {
+        format!("alacritty-atlas-{}-{}.png", self.kind, self.index)
+    }
+
+    /// Download the RGBA contents of the texture.
+    fn read_pixels(&self) -> Vec<u8> {
+        let mut pixels = vec![0u8; (self.size.x * self.size.y * 4) as usize];
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::BindTexture(gl::TEXTURE_2D, self.tex);
+            gl::GetTexImage(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut _,
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        pixels
+    }
+}
+
+/// Encode RGBA pixels as PNG in memory.
+pub fn encode_png(pixels: &[u8], size: Vec2<i32>) -> ImageResult<Vec<u8>> {
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png).encode(pixels, size.x as u32, size.y as u32, ColorType::Rgba8)?;
+    Ok(png)
+}
+
+impl Drop for AtlasDebug {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use image::ImageError;
+
+use crate::config::background_image::{BackgroundImage as ImageConfig, BackgroundImageMode};
+use crate::gl;
+use crate::gl::types::*;
+
+use super::gl_api::Gl;
+use super::math::Vec2;
+use super::texture::create_image_texture;
+
+/// Background image uploaded to the GPU.
+#[derive(Debug)]
+pub struct BackgroundImage {
+    pub tex: GLuint,
+    pub size: Vec2<i32>,
+    pub opacity: f32,
+    pub mode: BackgroundImageMode,
+}
+
+impl BackgroundImage {
+    /// Decode the configured image and upload it into a new texture.
+    pub fn load(config: &ImageConfig) -> Result<Self, ImageError> {
+        let image = image::open(&config.path)?.into_rgba8();
+        let (width, height) = image.dimensions();
+        let size = Vec2::new(width as i32, height as i32);
+
+        let tex = unsafe { create_image_texture(&Gl, size.x, size.y, &image.into_raw()) };
+
+        Ok(Self { tex, size, opacity: config.opacity(), mode: config.mode })
+    }
+}
+
+impl Drop for BackgroundImage {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.tex);
+        }
+    }
+}
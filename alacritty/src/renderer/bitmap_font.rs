@@ -0,0 +1,247 @@
+//! Bitmap font embedded in the binary, used when no system font can be loaded.
+//!
+//! The glyphs are from the public domain font8x8 by Daniel Hepper, with every row doubled to
+//! fill an 8x16 cell. Only printable ASCII is covered, other characters are drawn as a box.
+
+use crossfont::{
+    BitmapBuffer, Error, FontDesc, FontKey, GlyphKey, Metrics, Rasterize, RasterizedGlyph, Size,
+};
+
+/// Cell size of the font at a scale of one.
+const CELL_WIDTH: usize = 8;
+const CELL_HEIGHT: usize = 16;
+
+/// Rows of the cell below the baseline.
+const DESCENT: usize = 2;
+
+/// Pixel size of the font at a scale of one, in points at a DPR of one.
+const NATIVE_SIZE: f32 = 12.;
+
+/// Glyphs from U+0020 to U+007E, one byte per row with the leftmost pixel in the lowest bit.
+#[rustfmt::skip]
+static GLYPHS: [[u8; 8]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x18, 0x3c, 0x3c, 0x18, 0x18, 0x00, 0x18, 0x00],
+    [0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x36, 0x36, 0x7f, 0x36, 0x7f, 0x36, 0x36, 0x00],
+    [0x0c, 0x3e, 0x03, 0x1e, 0x30, 0x1f, 0x0c, 0x00],
+    [0x00, 0x63, 0x33, 0x18, 0x0c, 0x66, 0x63, 0x00],
+    [0x1c, 0x36, 0x1c, 0x6e, 0x3b, 0x33, 0x6e, 0x00],
+    [0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x18, 0x0c, 0x06, 0x06, 0x06, 0x0c, 0x18, 0x00],
+    [0x06, 0x0c, 0x18, 0x18, 0x18, 0x0c, 0x06, 0x00],
+    [0x00, 0x66, 0x3c, 0xff, 0x3c, 0x66, 0x00, 0x00],
+    [0x00, 0x0c, 0x0c, 0x3f, 0x0c, 0x0c, 0x00, 0x00],
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x06],
+    [0x00, 0x00, 0x00, 0x3f, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x00],
+    [0x60, 0x30, 0x18, 0x0c, 0x06, 0x03, 0x01, 0x00],
+    [0x3e, 0x63, 0x73, 0x7b, 0x6f, 0x67, 0x3e, 0x00],
+    [0x0c, 0x0e, 0x0c, 0x0c, 0x0c, 0x0c, 0x3f, 0x00],
+    [0x1e, 0x33, 0x30, 0x1c, 0x06, 0x33, 0x3f, 0x00],
+    [0x1e, 0x33, 0x30, 0x1c, 0x30, 0x33, 0x1e, 0x00],
+    [0x38, 0x3c, 0x36, 0x33, 0x7f, 0x30, 0x78, 0x00],
+    [0x3f, 0x03, 0x1f, 0x30, 0x30, 0x33, 0x1e, 0x00],
+    [0x1c, 0x06, 0x03, 0x1f, 0x33, 0x33, 0x1e, 0x00],
+    [0x3f, 0x33, 0x30, 0x18, 0x0c, 0x0c, 0x0c, 0x00],
+    [0x1e, 0x33, 0x33, 0x1e, 0x33, 0x33, 0x1e, 0x00],
+    [0x1e, 0x33, 0x33, 0x3e, 0x30, 0x18, 0x0e, 0x00],
+    [0x00, 0x0c, 0x0c, 0x00, 0x00, 0x0c, 0x0c, 0x00],
+    [0x00, 0x0c, 0x0c, 0x00, 0x00, 0x0c, 0x0c, 0x06],
+    [0x18, 0x0c, 0x06, 0x03, 0x06, 0x0c, 0x18, 0x00],
+    [0x00, 0x00, 0x3f, 0x00, 0x00, 0x3f, 0x00, 0x00],
+    [0x06, 0x0c, 0x18, 0x30, 0x18, 0x0c, 0x06, 0x00],
+    [0x1e, 0x33, 0x30, 0x18, 0x0c, 0x00, 0x0c, 0x00],
+    [0x3e, 0x63, 0x7b, 0x7b, 0x7b, 0x03, 0x1e, 0x00],
+    [0x0c, 0x1e, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x00],
+    [0x3f, 0x66, 0x66, 0x3e, 0x66, 0x66, 0x3f, 0x00],
+    [0x3c, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3c, 0x00],
+    [0x1f, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1f, 0x00],
+    [0x7f, 0x46, 0x16, 0x1e, 0x16, 0x46, 0x7f, 0x00],
+    [0x7f, 0x46, 0x16, 0x1e, 0x16, 0x06, 0x0f, 0x00],
+    [0x3c, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7c, 0x00],
+    [0x33, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x33, 0x00],
+    [0x1e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00],
+    [0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1e, 0x00],
+    [0x67, 0x66, 0x36, 0x1e, 0x36, 0x66, 0x67, 0x00],
+    [0x0f, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7f, 0x00],
+    [0x63, 0x77, 0x7f, 0x7f, 0x6b, 0x63, 0x63, 0x00],
+    [0x63, 0x67, 0x6f, 0x7b, 0x73, 0x63, 0x63, 0x00],
+    [0x1c, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1c, 0x00],
+    [0x3f, 0x66, 0x66, 0x3e, 0x06, 0x06, 0x0f, 0x00],
+    [0x1e, 0x33, 0x33, 0x33, 0x3b, 0x1e, 0x38, 0x00],
+    [0x3f, 0x66, 0x66, 0x3e, 0x36, 0x66, 0x67, 0x00],
+    [0x1e, 0x33, 0x07, 0x0e, 0x38, 0x33, 0x1e, 0x00],
+    [0x3f, 0x2d, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00],
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3f, 0x00],
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x1e, 0x0c, 0x00],
+    [0x63, 0x63, 0x63, 0x6b, 0x7f, 0x77, 0x63, 0x00],
+    [0x63, 0x63, 0x36, 0x1c, 0x1c, 0x36, 0x63, 0x00],
+    [0x33, 0x33, 0x33, 0x1e, 0x0c, 0x0c, 0x1e, 0x00],
+    [0x7f, 0x63, 0x31, 0x18, 0x4c, 0x66, 0x7f, 0x00],
+    [0x1e, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1e, 0x00],
+    [0x03, 0x06, 0x0c, 0x18, 0x30, 0x60, 0x40, 0x00],
+    [0x1e, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1e, 0x00],
+    [0x08, 0x1c, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff],
+    [0x0c, 0x0c, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x1e, 0x30, 0x3e, 0x33, 0x6e, 0x00],
+    [0x07, 0x06, 0x06, 0x3e, 0x66, 0x66, 0x3b, 0x00],
+    [0x00, 0x00, 0x1e, 0x33, 0x03, 0x33, 0x1e, 0x00],
+    [0x38, 0x30, 0x30, 0x3e, 0x33, 0x33, 0x6e, 0x00],
+    [0x00, 0x00, 0x1e, 0x33, 0x3f, 0x03, 0x1e, 0x00],
+    [0x1c, 0x36, 0x06, 0x0f, 0x06, 0x06, 0x0f, 0x00],
+    [0x00, 0x00, 0x6e, 0x33, 0x33, 0x3e, 0x30, 0x1f],
+    [0x07, 0x06, 0x36, 0x6e, 0x66, 0x66, 0x67, 0x00],
+    [0x0c, 0x00, 0x0e, 0x0c, 0x0c, 0x0c, 0x1e, 0x00],
+    [0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1e],
+    [0x07, 0x06, 0x66, 0x36, 0x1e, 0x36, 0x67, 0x00],
+    [0x0e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00],
+    [0x00, 0x00, 0x33, 0x7f, 0x7f, 0x6b, 0x63, 0x00],
+    [0x00, 0x00, 0x1f, 0x33, 0x33, 0x33, 0x33, 0x00],
+    [0x00, 0x00, 0x1e, 0x33, 0x33, 0x33, 0x1e, 0x00],
+    [0x00, 0x00, 0x3b, 0x66, 0x66, 0x3e, 0x06, 0x0f],
+    [0x00, 0x00, 0x6e, 0x33, 0x33, 0x3e, 0x30, 0x78],
+    [0x00, 0x00, 0x3b, 0x6e, 0x66, 0x06, 0x0f, 0x00],
+    [0x00, 0x00, 0x3e, 0x03, 0x1e, 0x30, 0x1f, 0x00],
+    [0x08, 0x0c, 0x3e, 0x0c, 0x0c, 0x2c, 0x18, 0x00],
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6e, 0x00],
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x1e, 0x0c, 0x00],
+    [0x00, 0x00, 0x63, 0x6b, 0x7f, 0x7f, 0x36, 0x00],
+    [0x00, 0x00, 0x63, 0x36, 0x1c, 0x36, 0x63, 0x00],
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x3e, 0x30, 0x1f],
+    [0x00, 0x00, 0x3f, 0x19, 0x0c, 0x26, 0x3f, 0x00],
+    [0x38, 0x0c, 0x0c, 0x07, 0x0c, 0x0c, 0x38, 0x00],
+    [0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00],
+    [0x07, 0x0c, 0x0c, 0x38, 0x0c, 0x0c, 0x07, 0x00],
+    [0x6e, 0x3b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+];
+
+/// Rasterizer drawing the embedded bitmap font, scaled by whole pixels.
+pub struct BitmapRasterizer {
+    font_key: FontKey,
+    device_pixel_ratio: f32,
+}
+
+impl BitmapRasterizer {
+    /// Integer scale of the glyphs at a font size.
+    fn scale(&self, size: Size) -> usize {
+        (size.as_f32_pts() * self.device_pixel_ratio / NATIVE_SIZE).round().max(1.) as usize
+    }
+}
+
+impl Rasterize for BitmapRasterizer {
+    fn new(device_pixel_ratio: f32, _: bool) -> Result<Self, Error> {
+        Ok(Self { font_key: FontKey::next(), device_pixel_ratio })
+    }
+
+    fn metrics(&self, _: FontKey, size: Size) -> Result<Metrics, Error> {
+        let scale = self.scale(size);
+        let pixels = scale as f32;
+        Ok(Metrics {
+            average_advance: (CELL_WIDTH * scale) as f64,
+            line_height: (CELL_HEIGHT * scale) as f64,
+            descent: -((DESCENT * scale) as f32),
+            underline_position: -pixels,
+            underline_thickness: pixels,
+            strikeout_position: 5. * pixels,
+            strikeout_thickness: pixels,
+        })
+    }
+
+    /// All styles and families share the single embedded face.
+    fn load_font(&mut self, _: &FontDesc, _: Size) -> Result<FontKey, Error> {
+        Ok(self.font_key)
+    }
+
+    fn get_glyph(&mut self, key: GlyphKey) -> Result<RasterizedGlyph, Error> {
+        let scale = self.scale(key.size);
+        let (width, height) = (CELL_WIDTH * scale, CELL_HEIGHT * scale);
+
+        let mut buf = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            for x in 0..width {
+                let filled = pixel(key.c, x / scale, y / scale);
+                let coverage = if filled { 255 } else { 0 };
+                buf.extend_from_slice(&[coverage; 3]);
+            }
+        }
+
+        Ok(RasterizedGlyph {
+            c: key.c,
+            width: width as i32,
+            height: height as i32,
+            top: ((CELL_HEIGHT - DESCENT) * scale) as i32,
+            left: 0,
+            buf: BitmapBuffer::RGB(buf),
+        })
+    }
+
+    fn update_dpr(&mut self, device_pixel_ratio: f32) {
+        self.device_pixel_ratio = device_pixel_ratio;
+    }
+}
+
+/// Whether a pixel of the unscaled 8x16 glyph is set.
+fn pixel(c: char, x: usize, y: usize) -> bool {
+    match c {
+        ' '..='~' => (GLYPHS[c as usize - 0x20][y / 2] >> x) & 1 == 1,
+        // Outline of a box inside the cell for characters without a glyph.
+        _ => {
+            let (left, right, top, bottom) = (1, CELL_WIDTH - 2, 3, CELL_HEIGHT - DESCENT - 1);
+            (x >= left && x <= right && (y == top || y == bottom))
+                || (y >= top && y <= bottom && (x == left || x == right))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coverage(glyph: &RasterizedGlyph, x: usize, y: usize) -> u8 {
+        match &glyph.buf {
+            BitmapBuffer::RGB(buf) => buf[(y * glyph.width as usize + x) * 3],
+            BitmapBuffer::RGBA(buf) => buf[(y * glyph.width as usize + x) * 4 + 3],
+        }
+    }
+
+    #[test]
+    fn glyphs_scale_with_font_size() {
+        let mut rasterizer = BitmapRasterizer::new(1., false).unwrap();
+        let key = GlyphKey { font_key: rasterizer.font_key, c: 'A', size: Size::new(11.25) };
+
+        let glyph = rasterizer.get_glyph(key).unwrap();
+        assert_eq!((glyph.width, glyph.height, glyph.top), (8, 16, 14));
+
+        rasterizer.update_dpr(2.);
+        let glyph = rasterizer.get_glyph(key).unwrap();
+        assert_eq!((glyph.width, glyph.height, glyph.top), (16, 32, 28));
+
+        let metrics = rasterizer.metrics(key.font_key, key.size).unwrap();
+        assert_eq!((metrics.average_advance, metrics.line_height), (16., 32.));
+    }
+
+    #[test]
+    fn glyph_rows_are_doubled() {
+        let mut rasterizer = BitmapRasterizer::new(1., false).unwrap();
+        let key = GlyphKey { font_key: rasterizer.font_key, c: 'A', size: Size::new(12.) };
+        let glyph = rasterizer.get_glyph(key).unwrap();
+
+        // Apex of the 'A' in the two topmost rows.
+        assert_eq!(coverage(&glyph, 2, 0), 255);
+        assert_eq!(coverage(&glyph, 2, 1), 255);
+        assert_eq!(coverage(&glyph, 0, 0), 0);
+
+        // Empty row below the baseline.
+        assert!((0..8).all(|x| coverage(&glyph, x, 15) == 0));
+    }
+
+    #[test]
+    fn missing_glyphs_are_boxes() {
+        assert!(pixel('ä', 1, 3));
+        assert!(pixel('ä', 6, 13));
+        assert!(!pixel('ä', 3, 8));
+        assert!(!pixel(' ', 3, 8));
+    }
+}
@@ -0,0 +1,478 @@
+//! Procedurally drawn box drawing and block element glyphs.
+//!
+//! Fonts frequently get these characters slightly wrong, which leaves visible seams between cells.
+//! Drawing them ourselves at exactly the cell size makes adjacent glyphs connect perfectly.
+
+use std::cmp::max;
+
+use crossfont::{BitmapBuffer, Metrics, RasterizedGlyph};
+
+use super::math::Vec2;
+
+/// Line weights of the box drawing characters from U+2500 to U+257F.
+///
+/// Every entry packs the weight of the up, right, down and left arm into two bits each, from the
+/// most significant to the least significant bits. See [`Weight`] for the encoding.
+#[rustfmt::skip]
+static BOX_DRAWING_ARMS: [u8; 128] = [
+    0x11, 0x22, 0x44, 0x88, 0x11, 0x22, 0x44, 0x88,
+    0x11, 0x22, 0x44, 0x88, 0x14, 0x24, 0x18, 0x28,
+    0x05, 0x06, 0x09, 0x0a, 0x50, 0x60, 0x90, 0xa0,
+    0x41, 0x42, 0x81, 0x82, 0x54, 0x64, 0x94, 0x58,
+    0x98, 0xa4, 0x68, 0xa8, 0x45, 0x46, 0x85, 0x49,
+    0x89, 0x86, 0x4a, 0x8a, 0x15, 0x16, 0x25, 0x26,
+    0x19, 0x1a, 0x29, 0x2a, 0x51, 0x52, 0x61, 0x62,
+    0x91, 0x92, 0xa1, 0xa2, 0x55, 0x56, 0x65, 0x66,
+    0x95, 0x59, 0x99, 0x96, 0xa5, 0x5a, 0x69, 0xa6,
+    0x6a, 0x9a, 0xa9, 0xaa, 0x11, 0x22, 0x44, 0x88,
+    0x33, 0xcc, 0x34, 0x1c, 0x3c, 0x07, 0x0d, 0x0f,
+    0x70, 0xd0, 0xf0, 0x43, 0xc1, 0xc3, 0x74, 0xdc,
+    0xfc, 0x47, 0xcd, 0xcf, 0x37, 0x1d, 0x3f, 0x73,
+    0xd1, 0xf3, 0x77, 0xdd, 0xff, 0x14, 0x05, 0x41,
+    0x50, 0x00, 0x00, 0x00, 0x01, 0x40, 0x10, 0x04,
+    0x02, 0x80, 0x20, 0x08, 0x21, 0x48, 0x12, 0x84,
+];
+
+/// Check if a character can be drawn without the help of a font.
+pub fn is_builtin_glyph(c: char) -> bool {
+    ('\u{2500}'..='\u{259f}').contains(&c)
+}
+
+/// Draw a box drawing or block element character covering the whole cell.
+///
+/// Returns `None` for all characters outside of U+2500 to U+259F.
+pub fn builtin_glyph(c: char, cell_size: Vec2<i32>, metrics: &Metrics) -> Option<RasterizedGlyph> {
+    if !is_builtin_glyph(c) {
+        return None;
+    }
+
+    let width = max(cell_size.x, 1) as usize;
+    let height = max(cell_size.y, 1) as usize;
+    let mut canvas = Canvas::new(width, height);
+
+    // Use the underline thickness, so lines blend in with the rest of the font.
+    let light = max(metrics.underline_thickness.round() as usize, 1);
+
+    match c {
+        // Dashed lines.
+        '\u{2504}'..='\u{250b}' | '\u{254c}'..='\u{254f}' => {
+            let dashes = match c {
+                '\u{2504}'..='\u{2507}' => 3,
+                '\u{2508}'..='\u{250b}' => 4,
+                _ => 2,
+            };
+            let arms = BOX_DRAWING_ARMS[c as usize - 0x2500];
+            canvas.draw_dashes(Weight::from_bits(arms), arms & 0x0f != 0, dashes, light);
+        },
+        // Rounded corners.
+        '\u{256d}' => canvas.draw_arc(1., 1., light),
+        '\u{256e}' => canvas.draw_arc(-1., 1., light),
+        '\u{256f}' => canvas.draw_arc(-1., -1., light),
+        '\u{2570}' => canvas.draw_arc(1., -1., light),
+        // Diagonals.
+        '\u{2571}' => canvas.draw_diagonal(false, light),
+        '\u{2572}' => canvas.draw_diagonal(true, light),
+        '\u{2573}' => {
+            canvas.draw_diagonal(false, light);
+            canvas.draw_diagonal(true, light);
+        },
+        // Regular lines.
+        '\u{2500}'..='\u{257f}' => {
+            let arms = BOX_DRAWING_ARMS[c as usize - 0x2500];
+            canvas.draw_box(
+                [
+                    Weight::from_bits(arms >> 6),
+                    Weight::from_bits(arms >> 4),
+                    Weight::from_bits(arms >> 2),
+                    Weight::from_bits(arms),
+                ],
+                light,
+            );
+        },
+        // Block elements.
+        _ => canvas.draw_block(c),
+    }
+
+    Some(canvas.into_glyph(c))
+}
+
+/// Weight of a single box drawing line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Weight {
+    None,
+    Light,
+    Heavy,
+    Double,
+}
+
+impl Weight {
+    /// Decode the two least significant bits.
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0 => Weight::None,
+            1 => Weight::Light,
+            2 => Weight::Heavy,
+            _ => Weight::Double,
+        }
+    }
+
+    /// Total thickness of the line in pixels.
+    fn thickness(self, light: usize) -> usize {
+        match self {
+            Weight::None => 0,
+            Weight::Light => light,
+            Weight::Heavy => light * 2,
+            Weight::Double => light * 3,
+        }
+    }
+}
+
+/// Centered span of `thickness` pixels inside `size` pixels.
+fn centered(size: usize, thickness: usize) -> (usize, usize) {
+    let start = size.saturating_sub(thickness) / 2;
+    (start, start + thickness)
+}
+
+/// Single channel coverage buffer.
+struct Canvas {
+    width: usize,
+    height: usize,
+    buf: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self { width, height, buf: vec![0; width * height] }
+    }
+
+    /// Fill the rectangle between `x` and `y` ranges, clamped to the canvas.
+    fn fill_rect(&mut self, x: (usize, usize), y: (usize, usize), coverage: u8) {
+        for row in y.0..y.1.min(self.height) {
+            let offset = row * self.width;
+            for pixel in &mut self.buf[offset + x.0.min(self.width)..offset + x.1.min(self.width)] {
+                *pixel = max(*pixel, coverage);
+            }
+        }
+    }
+
+    /// Fill a rectangle with `across` and `along` swapped for horizontal lines.
+    fn fill_oriented(&mut self, across: (usize, usize), along: (usize, usize), horizontal: bool) {
+        if horizontal {
+            self.fill_rect(along, across, 255);
+        } else {
+            self.fill_rect(across, along, 255);
+        }
+    }
+
+    /// Draw all four arms of a box drawing character, ordered as up, right, down and left.
+    fn draw_box(&mut self, arms: [Weight; 4], light: usize) {
+        let [up, right, down, left] = arms;
+
+        self.draw_arm(up, true, down, [left, right], false, light);
+        self.draw_arm(down, false, up, [left, right], false, light);
+        self.draw_arm(left, true, right, [up, down], true, light);
+        self.draw_arm(right, false, left, [up, down], true, light);
+    }
+
+    /// Draw one arm from the cell border towards the center.
+    ///
+    /// The `sides` are the perpendicular arms, ordered from lower to higher coordinates. Both the
+    /// extent of the arm and the gaps in double lines depend on them, so that joints look right.
+    fn draw_arm(
+        &mut self,
+        weight: Weight,
+        from_start: bool,
+        opposite: Weight,
+        sides: [Weight; 2],
+        horizontal: bool,
+        light: usize,
+    ) {
+        if weight == Weight::None {
+            return;
+        }
+
+        let (across_size, along_size) =
+            if horizontal { (self.height, self.width) } else { (self.width, self.height) };
+
+        // Thickest perpendicular line which is not doubled.
+        let perpendicular = sides
+            .iter()
+            .filter(|side| **side != Weight::Double)
+            .map(|side| side.thickness(light))
+            .max()
+            .unwrap_or(0);
+
+        // Gap between the two strokes of a double line.
+        let gap = centered(along_size, light);
+
+        // Convert the span around the center into the arm's range along its axis.
+        let along = |(end_start, end_stop): (usize, usize)| {
+            if from_start {
+                (0, end_stop)
+            } else {
+                (end_start, along_size)
+            }
+        };
+
+        if weight != Weight::Double {
+            let thickness = weight.thickness(light);
+
+            let center = match sides {
+                [Weight::Double, _] | [_, Weight::Double] => {
+                    (gap.0.saturating_sub(light), gap.1 + light)
+                },
+                _ if perpendicular > 0 => centered(along_size, perpendicular),
+                _ => centered(along_size, thickness),
+            };
+
+            self.fill_oriented(centered(across_size, thickness), along(center), horizontal);
+            return;
+        }
+
+        let across_gap = centered(across_size, light);
+        let strokes = [
+            (across_gap.0.saturating_sub(light), across_gap.0),
+            (across_gap.1, across_gap.1 + light),
+        ];
+
+        for (side, stroke) in strokes.iter().enumerate() {
+            let center = if sides[side] == Weight::Double {
+                // Stop at the inner stroke of the perpendicular double line.
+                (gap.1, gap.0)
+            } else if opposite != Weight::None {
+                (gap.0, gap.1)
+            } else if sides[1 - side] == Weight::Double {
+                // Wrap around the outside of a double line corner.
+                (gap.0.saturating_sub(light), gap.1 + light)
+            } else if perpendicular > 0 {
+                centered(along_size, perpendicular)
+            } else {
+                gap
+            };
+
+            self.fill_oriented(*stroke, along(center), horizontal);
+        }
+    }
+
+    /// Draw a dashed line through the entire cell.
+    fn draw_dashes(&mut self, weight: Weight, horizontal: bool, dashes: usize, light: usize) {
+        let (across_size, along_size) =
+            if horizontal { (self.height, self.width) } else { (self.width, self.height) };
+
+        let across = centered(across_size, weight.thickness(light));
+        let gap = max(along_size / (dashes * 4), 1);
+
+        for dash in 0..dashes {
+            let start = dash * along_size / dashes + gap / 2;
+            let end = ((dash + 1) * along_size / dashes).saturating_sub(gap - gap / 2);
+            self.fill_oriented(across, (start, max(start, end)), horizontal);
+        }
+    }
+
+    /// Draw a rounded corner connecting the center of two cell borders.
+    ///
+    /// The direction signs point from the corner towards the borders the arc connects to.
+    fn draw_arc(&mut self, dir_x: f32, dir_y: f32, light: usize) {
+        let vertical = centered(self.width, light);
+        let horizontal = centered(self.height, light);
+
+        // Center of the lines the arc needs to connect.
+        let line_x = (vertical.0 + vertical.1) as f32 / 2.;
+        let line_y = (horizontal.0 + horizontal.1) as f32 / 2.;
+
+        let space_x = if dir_x > 0. { self.width as f32 - line_x } else { line_x };
+        let space_y = if dir_y > 0. { self.height as f32 - line_y } else { line_y };
+        let radius = space_x.min(space_y);
+
+        let center_x = line_x + dir_x * radius;
+        let center_y = line_y + dir_y * radius;
+
+        // Straight extensions from the end of the arc to the cell borders.
+        let (x_start, x_end) = if dir_x > 0. {
+            (center_x as usize, self.width)
+        } else {
+            (0, center_x.ceil() as usize)
+        };
+        let (y_start, y_end) = if dir_y > 0. {
+            (center_y as usize, self.height)
+        } else {
+            (0, center_y.ceil() as usize)
+        };
+        self.fill_rect((x_start, x_end), horizontal, 255);
+        self.fill_rect(vertical, (y_start, y_end), 255);
+
+        // Anti-aliased quarter circle.
+        let half_thickness = light as f32 / 2.;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let dx = x as f32 + 0.5 - center_x;
+                let dy = y as f32 + 0.5 - center_y;
+                if dx * dir_x > 0. || dy * dir_y > 0. {
+                    continue;
+                }
+
+                let distance = ((dx * dx + dy * dy).sqrt() - radius).abs();
+                let coverage = (half_thickness + 0.5 - distance).max(0.).min(1.);
+                let pixel = &mut self.buf[y * self.width + x];
+                *pixel = max(*pixel, (coverage * 255.) as u8);
+            }
+        }
+    }
+
+    /// Draw an anti-aliased diagonal line from corner to corner.
+    fn draw_diagonal(&mut self, from_top_left: bool, light: usize) {
+        let width = self.width as f32;
+        let height = self.height as f32;
+        let length = (width * width + height * height).sqrt();
+        let half_thickness = light as f32 / 2.;
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let px = x as f32 + 0.5;
+                let py = y as f32 + 0.5;
+                let distance = if from_top_left {
+                    (height * px - width * py).abs() / length
+                } else {
+                    (height * px + width * py - width * height).abs() / length
+                };
+
+                let coverage = (half_thickness + 0.5 - distance).max(0.).min(1.);
+                let pixel = &mut self.buf[y * self.width + x];
+                *pixel = max(*pixel, (coverage * 255.) as u8);
+            }
+        }
+    }
+
+    /// Draw characters from the block elements range.
+    fn draw_block(&mut self, c: char) {
+        let (width, height) = (self.width, self.height);
+
+        // Size of `eighths` eighths of `size`, rounded consistently for complementary blocks.
+        let eighths = |size: usize, eighths: usize| (eighths * size + 4) / 8;
+
+        match c {
+            '\u{2580}' => self.fill_rect((0, width), (0, height / 2), 255),
+            '\u{2581}'..='\u{2588}' => {
+                let top = height - eighths(height, c as usize - 0x2580);
+                self.fill_rect((0, width), (top, height), 255);
+            },
+            '\u{2589}'..='\u{258f}' => {
+                let right = eighths(width, 0x2590 - c as usize);
+                self.fill_rect((0, right), (0, height), 255);
+            },
+            '\u{2590}' => self.fill_rect((width / 2, width), (0, height), 255),
+            '\u{2591}' => self.fill_rect((0, width), (0, height), 64),
+            '\u{2592}' => self.fill_rect((0, width), (0, height), 128),
+            '\u{2593}' => self.fill_rect((0, width), (0, height), 192),
+            '\u{2594}' => self.fill_rect((0, width), (0, eighths(height, 1)), 255),
+            '\u{2595}' => self.fill_rect((width - eighths(width, 1), width), (0, height), 255),
+            _ => {
+                // Quadrants as upper left, upper right, lower left and lower right.
+                let quadrants: [bool; 4] = match c {
+                    '\u{2596}' => [false, false, true, false],
+                    '\u{2597}' => [false, false, false, true],
+                    '\u{2598}' => [true, false, false, false],
+                    '\u{2599}' => [true, false, true, true],
+                    '\u{259a}' => [true, false, false, true],
+                    '\u{259b}' => [true, true, true, false],
+                    '\u{259c}' => [true, true, false, true],
+                    '\u{259d}' => [false, true, false, false],
+                    '\u{259e}' => [false, true, true, false],
+                    _ => [false, true, true, true],
+                };
+
+                let (mid_x, mid_y) = (width / 2, height / 2);
+                let areas = [
+                    ((0, mid_x), (0, mid_y)),
+                    ((mid_x, width), (0, mid_y)),
+                    ((0, mid_x), (mid_y, height)),
+                    ((mid_x, width), (mid_y, height)),
+                ];
+
+                for (area, _) in areas.iter().zip(quadrants.iter()).filter(|(_, filled)| **filled) {
+                    self.fill_rect(area.0, area.1, 255);
+                }
+            },
+        }
+    }
+
+    fn into_glyph(self, c: char) -> RasterizedGlyph {
+        let buf = self.buf.iter().flat_map(|coverage| vec![*coverage; 3]).collect();
+
+        RasterizedGlyph {
+            c,
+            top: self.height as i32,
+            left: 0,
+            width: self.width as i32,
+            height: self.height as i32,
+            buf: BitmapBuffer::RGB(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics() -> Metrics {
+        Metrics {
+            average_advance: 8.,
+            line_height: 16.,
+            descent: -4.,
+            underline_position: -2.,
+            underline_thickness: 1.,
+            strikeout_position: 5.,
+            strikeout_thickness: 1.,
+        }
+    }
+
+    fn coverage(glyph: &RasterizedGlyph, x: usize, y: usize) -> u8 {
+        match &glyph.buf {
+            BitmapBuffer::RGB(buf) => buf[(y * glyph.width as usize + x) * 3],
+            BitmapBuffer::RGBA(buf) => buf[(y * glyph.width as usize + x) * 4 + 3],
+        }
+    }
+
+    #[test]
+    fn builtin_glyphs_fill_cell() {
+        let cell_size = Vec2::new(8, 16);
+        for c in (0x2500..=0x259f).filter_map(std::char::from_u32) {
+            let glyph = builtin_glyph(c, cell_size, &metrics()).unwrap();
+            assert_eq!((glyph.width, glyph.height, glyph.top, glyph.left), (8, 16, 16, 0));
+        }
+
+        assert!(builtin_glyph('a', cell_size, &metrics()).is_none());
+    }
+
+    #[test]
+    fn light_horizontal_spans_cell() {
+        let glyph = builtin_glyph('─', Vec2::new(8, 16), &metrics()).unwrap();
+        for x in 0..8 {
+            assert_eq!(coverage(&glyph, x, 7), 255);
+            assert_eq!(coverage(&glyph, x, 6), 0);
+        }
+    }
+
+    #[test]
+    fn double_corner_leaves_gap() {
+        let glyph = builtin_glyph('╔', Vec2::new(9, 15), &metrics()).unwrap();
+
+        // Outer stroke reaches the right border, inner stroke too, with a gap between them.
+        assert_eq!(coverage(&glyph, 8, 6), 255);
+        assert_eq!(coverage(&glyph, 8, 7), 0);
+        assert_eq!(coverage(&glyph, 8, 8), 255);
+
+        // Nothing is drawn above or to the left of the corner.
+        assert_eq!(coverage(&glyph, 4, 2), 0);
+        assert_eq!(coverage(&glyph, 1, 7), 0);
+    }
+
+    #[test]
+    fn half_blocks_are_complementary() {
+        let upper = builtin_glyph('▀', Vec2::new(7, 15), &metrics()).unwrap();
+        let lower = builtin_glyph('▄', Vec2::new(7, 15), &metrics()).unwrap();
+        for y in 0..15 {
+            assert_ne!(coverage(&upper, 0, y), coverage(&lower, 0, y));
+        }
+    }
+}
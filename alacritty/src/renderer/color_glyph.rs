@@ -0,0 +1,564 @@
+//! Layered color glyphs of fonts with `COLR` and `CPAL` tables.
+//!
+//! The system rasterizer only draws the base outline of such glyphs, which is often empty. Their
+//! layers are rasterized here instead and composited with the colors of the first palette.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+#[cfg(not(any(target_os = "macos", windows)))]
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+
+use crossfont::{BitmapBuffer, FontKey, RasterizedGlyph, Size, Slant, Weight};
+use log::*;
+use ttf_parser::{GlyphId, OutlineBuilder};
+
+use alacritty_terminal::term::color::Rgb;
+
+use crate::config::font::FontDescription;
+
+/// Palette index of layers drawn in the text color.
+const FOREGROUND_PALETTE_INDEX: u16 = 0xFFFF;
+
+/// Number of line segments a curve is split into for rasterization.
+const CURVE_SEGMENTS: usize = 8;
+
+/// Layer of a color glyph, drawn with a single palette color.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Layer {
+    glyph_id: u16,
+    palette_index: u16,
+}
+
+/// Glyph which is composed of colored layers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct BaseGlyph {
+    glyph_id: u16,
+    first_layer: u16,
+    num_layers: u16,
+}
+
+/// Font file with color layer tables.
+#[derive(Debug)]
+pub struct ColorFont {
+    data: Vec<u8>,
+
+    /// Index of the face inside a font collection.
+    index: u32,
+
+    /// Layered glyphs, sorted by glyph ID.
+    base_glyphs: Vec<BaseGlyph>,
+
+    layers: Vec<Layer>,
+
+    /// Colors of the first palette, as non-premultiplied RGBA.
+    palette: Vec<[u8; 4]>,
+}
+
+impl ColorFont {
+    /// Parse the color tables of a face, `None` if the face has no color layers.
+    pub fn new(data: Vec<u8>, index: u32) -> Option<Self> {
+        let colr = table(&data, index, b"COLR")?;
+        let cpal = table(&data, index, b"CPAL")?;
+        let (base_glyphs, layers) = parse_colr(colr)?;
+        let palette = parse_cpal(cpal)?;
+
+        // Make sure outlines can be read before committing to the color layers.
+        ttf_parser::Font::from_data(&data, index)?;
+
+        Some(Self { data, index, base_glyphs, layers, palette })
+    }
+
+    /// Rasterize the layers of the glyph for `c` at a size of `px_size` pixels per em.
+    ///
+    /// Returns `None` if the glyph has no color layers.
+    pub fn rasterize(&self, c: char, px_size: f32, foreground: Rgb) -> Option<RasterizedGlyph> {
+        let font = ttf_parser::Font::from_data(&self.data, self.index)?;
+        let glyph_id = font.glyph_index(c)?.0;
+        let base = self
+            .base_glyphs
+            .binary_search_by_key(&glyph_id, |base| base.glyph_id)
+            .ok()
+            .map(|index| self.base_glyphs[index])?;
+
+        let first = usize::from(base.first_layer);
+        let layers = self.layers.get(first..first + usize::from(base.num_layers))?;
+
+        let scale = px_size / f32::from(font.units_per_em()?);
+        let outlines: Vec<(Outline, [u8; 4])> = layers
+            .iter()
+            .map(|layer| {
+                let mut outline = Outline::new(scale);
+                font.outline_glyph(GlyphId(layer.glyph_id), &mut outline);
+                (outline, self.color(layer.palette_index, foreground))
+            })
+            .collect();
+
+        // Pixel bounds of all layers, with Y pointing up.
+        let points = outlines.iter().flat_map(|(outline, _)| outline.points());
+        let (mut x_min, mut y_min) = (f32::MAX, f32::MAX);
+        let (mut x_max, mut y_max) = (f32::MIN, f32::MIN);
+        for (x, y) in points {
+            x_min = x_min.min(x);
+            y_min = y_min.min(y);
+            x_max = x_max.max(x);
+            y_max = y_max.max(y);
+        }
+
+        let mut glyph = RasterizedGlyph { c, ..Default::default() };
+        if x_min > x_max || y_min > y_max {
+            glyph.buf = BitmapBuffer::RGBA(Vec::new());
+            return Some(glyph);
+        }
+
+        let left = x_min.floor();
+        let top = y_max.ceil();
+        let width = (x_max.ceil() - left).max(1.) as usize;
+        let height = (top - y_min.floor()).max(1.) as usize;
+
+        let mut buf = vec![0u8; width * height * 4];
+        for (outline, color) in &outlines {
+            let coverage = outline.coverage(left, top, width, height);
+            composite(&mut buf, &coverage, *color);
+        }
+
+        glyph.left = left as i32;
+        glyph.top = top as i32;
+        glyph.width = width as i32;
+        glyph.height = height as i32;
+        glyph.buf = BitmapBuffer::RGBA(buf);
+        Some(glyph)
+    }
+
+    /// Color of a palette entry, the text color for the foreground index.
+    fn color(&self, palette_index: u16, foreground: Rgb) -> [u8; 4] {
+        if palette_index == FOREGROUND_PALETTE_INDEX {
+            return [foreground.r, foreground.g, foreground.b, 0xFF];
+        }
+
+        self.palette.get(usize::from(palette_index)).copied().unwrap_or([0, 0, 0, 0])
+    }
+}
+
+/// Color fonts among the loaded faces.
+#[derive(Debug, Default)]
+pub struct ColorFonts {
+    /// Faces with color layers.
+    faces: Vec<(FontKey, Rc<ColorFont>)>,
+
+    /// Parsed font files by font pattern, `None` for faces without color layers.
+    ///
+    /// This is kept across font reloads, so changing the font size doesn't read the files again.
+    files: HashMap<String, Option<Rc<ColorFont>>>,
+
+    /// Color of layers drawn in the text color.
+    pub foreground: Rgb,
+
+    /// Pixels per point.
+    scale_factor: f32,
+}
+
+impl ColorFonts {
+    /// Replace the faces searched for color glyphs.
+    pub fn load<'a, I>(&mut self, faces: I, dpr: f64)
+    where
+        I: IntoIterator<Item = (FontKey, &'a FontDescription, Slant, Weight)>,
+    {
+        self.scale_factor = dpr as f32 * 96. / 72.;
+        self.faces.clear();
+
+        for (key, desc, slant, weight) in faces {
+            if self.faces.iter().any(|(face, _)| *face == key) {
+                continue;
+            }
+
+            let pattern = font_pattern(desc, slant, weight);
+            let font = self.files.entry(pattern.clone()).or_insert_with(|| {
+                let (path, index) = find_font(&pattern)?;
+                let data = fs::read(&path).ok()?;
+                let font = ColorFont::new(data, index)?;
+                info!("Using color layers of {:?}", path);
+                Some(Rc::new(font))
+            });
+
+            if let Some(font) = font {
+                self.faces.push((key, font.clone()));
+            }
+        }
+    }
+
+    /// Rasterize the color layers of a glyph, `None` if its face has no color layers for it.
+    pub fn rasterize(&self, key: crossfont::GlyphKey) -> Option<RasterizedGlyph> {
+        let (_, font) = self.faces.iter().find(|(face, _)| *face == key.font_key)?;
+        font.rasterize(key.c, self.px_size(key.size), self.foreground)
+    }
+
+    fn px_size(&self, size: Size) -> f32 {
+        size.as_f32_pts() * self.scale_factor
+    }
+}
+
+/// Fontconfig pattern matching a font description.
+fn font_pattern(desc: &FontDescription, slant: Slant, weight: Weight) -> String {
+    let mut pattern = String::new();
+    for c in desc.family.chars() {
+        if matches!(c, '\\' | '-' | ':' | ',') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+
+    match &desc.style {
+        Some(style) => pattern.push_str(&format!(":style={}", style)),
+        None => {
+            if weight == Weight::Bold {
+                pattern.push_str(":weight=bold");
+            }
+            if slant != Slant::Normal {
+                pattern.push_str(":slant=italic");
+            }
+        },
+    }
+
+    pattern
+}
+
+/// Find the file and collection index of the face fontconfig picks for `pattern`.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn find_font(pattern: &str) -> Option<(PathBuf, u32)> {
+    let output = Command::new("fc-match")
+        .args(&["--format=%{file}\n%{index}", pattern])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    let output = String::from_utf8(output.stdout).ok()?;
+    let mut lines = output.lines();
+    let path = PathBuf::from(lines.next()?);
+    let index = lines.next().and_then(|index| index.parse().ok()).unwrap_or(0);
+    Some((path, index))
+}
+
+/// The system rasterizers of macOS and Windows draw color layers themselves.
+#[cfg(any(target_os = "macos", windows))]
+fn find_font(_pattern: &str) -> Option<(PathBuf, u32)> {
+    None
+}
+
+/// Draw `color` over a premultiplied RGBA buffer with the alpha of `coverage`.
+fn composite(buf: &mut [u8], coverage: &[f32], color: [u8; 4]) {
+    let alpha = f32::from(color[3]) / 255.;
+    for (pixel, coverage) in buf.chunks_exact_mut(4).zip(coverage) {
+        let src_alpha = coverage * alpha;
+        if src_alpha <= 0. {
+            continue;
+        }
+
+        for channel in 0..3 {
+            let src = f32::from(color[channel]) * src_alpha;
+            let dst = f32::from(pixel[channel]) * (1. - src_alpha);
+            pixel[channel] = (src + dst).round().min(255.) as u8;
+        }
+        let dst_alpha = f32::from(pixel[3]) * (1. - src_alpha);
+        pixel[3] = (src_alpha * 255. + dst_alpha).round().min(255.) as u8;
+    }
+}
+
+/// Data of the table `tag` of the face at `index`, also inside font collections.
+fn table<'a>(data: &'a [u8], index: u32, tag: &[u8; 4]) -> Option<&'a [u8]> {
+    let offset = if data.get(..4)? == b"ttcf" {
+        let offset = 12 + 4 * index as usize;
+        read_u32(data, offset)? as usize
+    } else {
+        0
+    };
+
+    let num_tables = usize::from(read_u16(data, offset + 4)?);
+    (0..num_tables).find_map(|i| {
+        let record = offset + 12 + i * 16;
+        if data.get(record..record + 4)? != tag {
+            return None;
+        }
+
+        let start = read_u32(data, record + 8)? as usize;
+        let length = read_u32(data, record + 12)? as usize;
+        data.get(start..start + length)
+    })
+}
+
+/// Parse the base glyphs and layers of a version 0 `COLR` table.
+fn parse_colr(data: &[u8]) -> Option<(Vec<BaseGlyph>, Vec<Layer>)> {
+    let num_base_glyphs = usize::from(read_u16(data, 2)?);
+    let base_glyphs_offset = read_u32(data, 4)? as usize;
+    let layers_offset = read_u32(data, 8)? as usize;
+    let num_layers = usize::from(read_u16(data, 12)?);
+
+    let base_glyphs = (0..num_base_glyphs)
+        .map(|i| {
+            let record = base_glyphs_offset + i * 6;
+            Some(BaseGlyph {
+                glyph_id: read_u16(data, record)?,
+                first_layer: read_u16(data, record + 2)?,
+                num_layers: read_u16(data, record + 4)?,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let layers = (0..num_layers)
+        .map(|i| {
+            let record = layers_offset + i * 4;
+            Some(Layer {
+                glyph_id: read_u16(data, record)?,
+                palette_index: read_u16(data, record + 2)?,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    if base_glyphs.is_empty() {
+        return None;
+    }
+
+    Some((base_glyphs, layers))
+}
+
+/// Parse the first palette of a `CPAL` table.
+fn parse_cpal(data: &[u8]) -> Option<Vec<[u8; 4]>> {
+    let num_entries = usize::from(read_u16(data, 2)?);
+    let colors_offset = read_u32(data, 8)? as usize;
+    let first_color = usize::from(read_u16(data, 12)?);
+
+    (0..num_entries)
+        .map(|i| {
+            let record = colors_offset + (first_color + i) * 4;
+            let bgra = data.get(record..record + 4)?;
+            Some([bgra[2], bgra[1], bgra[0], bgra[3]])
+        })
+        .collect()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Glyph outline flattened into line segments, in pixels with Y pointing up.
+struct Outline {
+    lines: Vec<((f32, f32), (f32, f32))>,
+    start: (f32, f32),
+    current: (f32, f32),
+
+    /// Pixels per font unit.
+    scale: f32,
+}
+
+impl Outline {
+    fn new(scale: f32) -> Self {
+        Self { lines: Vec::new(), start: (0., 0.), current: (0., 0.), scale }
+    }
+
+    fn points(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
+        self.lines.iter().flat_map(|&(from, to)| vec![from, to])
+    }
+
+    fn push_line(&mut self, to: (f32, f32)) {
+        if to != self.current {
+            self.lines.push((self.current, to));
+        }
+        self.current = to;
+    }
+
+    /// Coverage of each pixel of a `width` by `height` bitmap, whose top left corner is at
+    /// `left`, `top`.
+    ///
+    /// Each line adds the signed area it covers to the right of itself, so the running sum over
+    /// a row is the coverage of the nonzero filled outline.
+    fn coverage(&self, left: f32, top: f32, width: usize, height: usize) -> Vec<f32> {
+        // Lines at the right edge spill into the next cells, which is accounted for by the sum.
+        let mut area = vec![0f32; width * height + 2];
+        for &((x0, y0), (x1, y1)) in &self.lines {
+            let from = (x0 - left, top - y0);
+            let to = (x1 - left, top - y1);
+            accumulate_line(&mut area, width, height, from, to);
+        }
+
+        let mut sum = 0.;
+        area.truncate(width * height);
+        for cell in &mut area {
+            sum += *cell;
+            *cell = sum.abs().min(1.);
+        }
+
+        area
+    }
+}
+
+impl OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = (x * self.scale, y * self.scale);
+        self.current = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.push_line((x * self.scale, y * self.scale));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x0, y0) = self.current;
+        let (x1, y1) = (x1 * self.scale, y1 * self.scale);
+        let (x, y) = (x * self.scale, y * self.scale);
+        for i in 1..=CURVE_SEGMENTS {
+            let t = i as f32 / CURVE_SEGMENTS as f32;
+            let mt = 1. - t;
+            let px = mt * mt * x0 + 2. * mt * t * x1 + t * t * x;
+            let py = mt * mt * y0 + 2. * mt * t * y1 + t * t * y;
+            self.push_line((px, py));
+        }
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x0, y0) = self.current;
+        let (x1, y1) = (x1 * self.scale, y1 * self.scale);
+        let (x2, y2) = (x2 * self.scale, y2 * self.scale);
+        let (x, y) = (x * self.scale, y * self.scale);
+        for i in 1..=CURVE_SEGMENTS {
+            let t = i as f32 / CURVE_SEGMENTS as f32;
+            let mt = 1. - t;
+            let (a, b, c, d) = (mt * mt * mt, 3. * mt * mt * t, 3. * mt * t * t, t * t * t);
+            let px = a * x0 + b * x1 + c * x2 + d * x;
+            let py = a * y0 + b * y1 + c * y2 + d * y;
+            self.push_line((px, py));
+        }
+    }
+
+    fn close(&mut self) {
+        let start = self.start;
+        self.push_line(start);
+    }
+}
+
+/// Add the signed area right of a line to the cells of a `width` by `height` bitmap.
+fn accumulate_line(
+    area: &mut [f32],
+    width: usize,
+    height: usize,
+    from: (f32, f32),
+    to: (f32, f32),
+) {
+    if (from.1 - to.1).abs() <= f32::EPSILON {
+        return;
+    }
+
+    // Walk the line downwards, remembering its original direction in the sign.
+    let (direction, from, to) = if from.1 < to.1 { (1., from, to) } else { (-1., to, from) };
+    let dxdy = (to.0 - from.0) / (to.1 - from.1);
+
+    let mut x = from.0;
+    if from.1 < 0. {
+        x -= from.1 * dxdy;
+    }
+
+    let first_row = from.1.max(0.) as usize;
+    let last_row = (to.1.ceil() as usize).min(height);
+    for row in first_row..last_row {
+        let row_start = row * width;
+        let dy = ((row + 1) as f32).min(to.1) - (row as f32).max(from.1);
+        let x_next = x + dxdy * dy;
+        let d = dy * direction;
+
+        let (x0, x1) = if x < x_next { (x, x_next) } else { (x_next, x) };
+        let x0_floor = x0.floor().max(0.);
+        let x0i = x0_floor as usize;
+        let x1_ceil = x1.ceil();
+        let x1i = (x1_ceil as usize).max(x0i);
+
+        if x1i <= x0i + 1 {
+            // The line stays within a single column.
+            let mid = 0.5 * (x + x_next) - x0_floor;
+            area[row_start + x0i] += d - d * mid;
+            area[row_start + x0i + 1] += d * mid;
+        } else {
+            let s = (x1 - x0).recip();
+            let x0f = x0 - x0_floor;
+            let a0 = 0.5 * s * (1. - x0f) * (1. - x0f);
+            let x1f = x1 - x1_ceil + 1.;
+            let am = 0.5 * s * x1f * x1f;
+
+            area[row_start + x0i] += d * a0;
+            if x1i == x0i + 2 {
+                area[row_start + x0i + 1] += d * (1. - a0 - am);
+            } else {
+                let a1 = s * (1.5 - x0f);
+                area[row_start + x0i + 1] += d * (a1 - a0);
+                for column in x0i + 2..x1i - 1 {
+                    area[row_start + column] += d * s;
+                }
+                let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+                area[row_start + x1i - 1] += d * (1. - a2 - am);
+            }
+            area[row_start + x1i] += d * am;
+        }
+
+        x = x_next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_coverage() {
+        let mut outline = Outline::new(1.);
+        outline.move_to(1., 1.);
+        outline.line_to(3., 1.);
+        outline.line_to(3., 3.);
+        outline.line_to(1., 3.);
+        outline.close();
+
+        let coverage = outline.coverage(0., 4., 4, 4);
+        let expected = [
+            0., 0., 0., 0., //
+            0., 1., 1., 0., //
+            0., 1., 1., 0., //
+            0., 0., 0., 0., //
+        ];
+        for (coverage, expected) in coverage.iter().zip(&expected) {
+            assert!((coverage - expected).abs() < 1e-4, "{:?}", coverage);
+        }
+    }
+
+    #[test]
+    fn composite_layers() {
+        let mut buf = vec![0u8; 8];
+        composite(&mut buf, &[1., 0.5], [255, 0, 0, 255]);
+        composite(&mut buf, &[0.5, 0.], [0, 0, 255, 255]);
+
+        assert_eq!(buf, vec![128, 0, 128, 255, 128, 0, 0, 128]);
+    }
+
+    #[test]
+    fn parse_tables() {
+        #[rustfmt::skip]
+        let colr = [
+            0, 0, 0, 1, 0, 0, 0, 14, 0, 0, 0, 20, 0, 2,
+            0, 5, 0, 0, 0, 2,
+            0, 6, 0, 1, 0, 7, 0xFF, 0xFF,
+        ];
+        let (base_glyphs, layers) = parse_colr(&colr).unwrap();
+        assert_eq!(base_glyphs, vec![BaseGlyph { glyph_id: 5, first_layer: 0, num_layers: 2 }]);
+        assert_eq!(layers[1], Layer { glyph_id: 7, palette_index: FOREGROUND_PALETTE_INDEX });
+
+        #[rustfmt::skip]
+        let cpal = [
+            0, 0, 0, 2, 0, 1, 0, 2, 0, 0, 0, 14, 0, 0,
+            0x10, 0x20, 0x30, 0xFF, 0, 0, 0xFF, 0x80,
+        ];
+        assert_eq!(parse_cpal(&cpal).unwrap(), vec![[0x30, 0x20, 0x10, 0xFF], [0xFF, 0, 0, 0x80]]);
+    }
+}
@@ -0,0 +1,101 @@
+use std::ptr;
+
+use super::gl_api::Gl;
+use super::math::Vec2;
+use super::texture::{create_texture, upload_texture, PixelFormat};
+use crate::gl;
+use crate::gl::types::*;
+
+/// Offscreen render target with a single color texture.
+#[derive(Debug)]
+pub struct Framebuffer {
+    fbo: GLuint,
+    pub tex: GLuint,
+    pub size: Vec2<i32>,
+}
+
+impl Framebuffer {
+    pub fn new(size: Vec2<i32>) -> Self {
+        let mut fbo: GLuint = 0;
+
+        unsafe {
+            let tex = create_texture(&Gl, size.x, size.y, PixelFormat::RGBA8);
+
+            // Scaled frames are filtered when they are presented.
+            gl::BindTexture(gl::TEXTURE_2D, tex);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                tex,
+                0,
+            );
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+            Self { fbo, tex, size }
+        }
+    }
+
+    /// Reallocate the color texture if the size has changed.
+    pub fn resize(&mut self, size: Vec2<i32>) {
+        if size.x == self.size.x && size.y == self.size.y {
+            return;
+        }
+
+        self.size = size;
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.tex);
+            upload_texture(&Gl, size.x, size.y, PixelFormat::RGBA8, ptr::null());
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    /// Redirect all further drawing into this framebuffer.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+        }
+    }
+
+    /// Copy the contents into the window, scaling them to `zoom` times `window_size`.
+    ///
+    /// Zoomed frames stay anchored to the top left corner of the window.
+    pub fn blit(&self, window_size: Vec2<i32>, zoom: f32) {
+        let width = (window_size.x as f32 * zoom).round() as i32;
+        let height = (window_size.y as f32 * zoom).round() as i32;
+
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.fbo);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+            gl::BlitFramebuffer(
+                0,
+                0,
+                self.size.x,
+                self.size.y,
+                0,
+                window_size.y - height,
+                width,
+                window_size.y,
+                gl::COLOR_BUFFER_BIT,
+                gl::LINEAR,
+            );
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteTextures(1, &self.tex);
+        }
+    }
+}
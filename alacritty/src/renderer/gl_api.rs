@@ -0,0 +1,272 @@
+use crate::gl;
+use crate::gl::types::*;
+
+use super::math::Vec2;
+use super::texture::PixelFormat;
+
+/// Thin wrapper around the OpenGL calls used for managing textures.
+///
+/// Renderer components issue their calls through this trait, so the order and size of their
+/// uploads can be tested with a recorder instead of a real context.
+///
+/// All functions operate on the `TEXTURE_2D` target of the active texture unit.
+pub trait GlApi {
+    unsafe fn gen_texture(&self) -> GLuint;
+
+    unsafe fn delete_texture(&self, id: GLuint);
+
+    /// Bind texture `id`, or unbind the current texture when it is zero.
+    unsafe fn bind_texture(&self, id: GLuint);
+
+    unsafe fn pixel_store(&self, name: GLenum, param: GLint);
+
+    unsafe fn tex_parameter(&self, name: GLenum, param: GLint);
+
+    /// Allocate storage for the bound texture, filled from `data` unless it is null.
+    unsafe fn tex_image_2d(
+        &self,
+        width: i32,
+        height: i32,
+        format: PixelFormat,
+        data: *const libc::c_void,
+    );
+
+    /// Replace a rectangle of the bound texture.
+    unsafe fn tex_sub_image_2d(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        format: PixelFormat,
+        data: *const libc::c_void,
+    );
+
+    /// Copy rectangles of texture `src` into the bound texture, as pairs of source and
+    /// destination origin.
+    unsafe fn copy_texture_regions(
+        &self,
+        src: GLuint,
+        size: Vec2<i32>,
+        regions: &[(Vec2<i32>, Vec2<i32>)],
+    );
+}
+
+struct TextureFormat {
+    internal: i32,
+    format: u32,
+    texel_type: u32,
+}
+
+fn get_gl_format(format: PixelFormat) -> TextureFormat {
+    match format {
+        PixelFormat::RGBA8 => TextureFormat {
+            internal: gl::RGBA as i32,
+            format: gl::RGBA,
+            texel_type: gl::UNSIGNED_BYTE,
+        },
+        PixelFormat::RGB8 => TextureFormat {
+            internal: gl::RGB as i32,
+            format: gl::RGB,
+            texel_type: gl::UNSIGNED_BYTE,
+        },
+    }
+}
+
+/// Calls into the current OpenGL context.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Gl;
+
+impl GlApi for Gl {
+    unsafe fn gen_texture(&self) -> GLuint {
+        let mut id = 0;
+        gl::GenTextures(1, &mut id);
+        id
+    }
+
+    unsafe fn delete_texture(&self, id: GLuint) {
+        gl::DeleteTextures(1, &id);
+    }
+
+    unsafe fn bind_texture(&self, id: GLuint) {
+        gl::BindTexture(gl::TEXTURE_2D, id);
+    }
+
+    unsafe fn pixel_store(&self, name: GLenum, param: GLint) {
+        gl::PixelStorei(name, param);
+    }
+
+    unsafe fn tex_parameter(&self, name: GLenum, param: GLint) {
+        gl::TexParameteri(gl::TEXTURE_2D, name, param);
+    }
+
+    unsafe fn tex_image_2d(
+        &self,
+        width: i32,
+        height: i32,
+        format: PixelFormat,
+        data: *const libc::c_void,
+    ) {
+        let format = get_gl_format(format);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            format.internal,
+            width,
+            height,
+            0,
+            format.format,
+            format.texel_type,
+            data,
+        );
+    }
+
+    unsafe fn tex_sub_image_2d(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        format: PixelFormat,
+        data: *const libc::c_void,
+    ) {
+        let format = get_gl_format(format);
+        gl::TexSubImage2D(
+            gl::TEXTURE_2D,
+            0,
+            x,
+            y,
+            width,
+            height,
+            format.format,
+            format.texel_type,
+            data,
+        );
+    }
+
+    unsafe fn copy_texture_regions(
+        &self,
+        src: GLuint,
+        size: Vec2<i32>,
+        regions: &[(Vec2<i32>, Vec2<i32>)],
+    ) {
+        let mut read_fbo: GLint = 0;
+        gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut read_fbo);
+
+        let mut fbo: GLuint = 0;
+        gl::GenFramebuffers(1, &mut fbo);
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, fbo);
+        gl::FramebufferTexture2D(
+            gl::READ_FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            src,
+            0,
+        );
+
+        for (from, to) in regions {
+            gl::CopyTexSubImage2D(gl::TEXTURE_2D, 0, to.x, to.y, from.x, from.y, size.x, size.y);
+        }
+
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, read_fbo as GLuint);
+        gl::DeleteFramebuffers(1, &fbo);
+    }
+}
+
+/// Call recorded by [`RecordingGl`].
+#[cfg(test)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GlCall {
+    GenTexture(GLuint),
+    DeleteTexture(GLuint),
+    BindTexture(GLuint),
+    PixelStore(GLenum, GLint),
+    TexParameter(GLenum, GLint),
+    TexImage2D { width: i32, height: i32, format: PixelFormat, filled: bool },
+    TexSubImage2D { x: i32, y: i32, width: i32, height: i32, format: PixelFormat },
+    CopyTexture { src: GLuint, from: (i32, i32), to: (i32, i32), size: (i32, i32) },
+}
+
+/// Fake context recording every call, for testing without OpenGL.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct RecordingGl {
+    calls: std::cell::RefCell<Vec<GlCall>>,
+    next_texture: std::cell::Cell<GLuint>,
+}
+
+#[cfg(test)]
+impl RecordingGl {
+    /// Remove all calls recorded so far.
+    pub fn take_calls(&self) -> Vec<GlCall> {
+        std::mem::take(&mut *self.calls.borrow_mut())
+    }
+
+    fn record(&self, call: GlCall) {
+        self.calls.borrow_mut().push(call);
+    }
+}
+
+#[cfg(test)]
+impl GlApi for RecordingGl {
+    unsafe fn gen_texture(&self) -> GLuint {
+        let id = self.next_texture.get() + 1;
+        self.next_texture.set(id);
+        self.record(GlCall::GenTexture(id));
+        id
+    }
+
+    unsafe fn delete_texture(&self, id: GLuint) {
+        self.record(GlCall::DeleteTexture(id));
+    }
+
+    unsafe fn bind_texture(&self, id: GLuint) {
+        self.record(GlCall::BindTexture(id));
+    }
+
+    unsafe fn pixel_store(&self, name: GLenum, param: GLint) {
+        self.record(GlCall::PixelStore(name, param));
+    }
+
+    unsafe fn tex_parameter(&self, name: GLenum, param: GLint) {
+        self.record(GlCall::TexParameter(name, param));
+    }
+
+    unsafe fn tex_image_2d(
+        &self,
+        width: i32,
+        height: i32,
+        format: PixelFormat,
+        data: *const libc::c_void,
+    ) {
+        self.record(GlCall::TexImage2D { width, height, format, filled: !data.is_null() });
+    }
+
+    unsafe fn tex_sub_image_2d(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        format: PixelFormat,
+        _data: *const libc::c_void,
+    ) {
+        self.record(GlCall::TexSubImage2D { x, y, width, height, format });
+    }
+
+    unsafe fn copy_texture_regions(
+        &self,
+        src: GLuint,
+        size: Vec2<i32>,
+        regions: &[(Vec2<i32>, Vec2<i32>)],
+    ) {
+        for (from, to) in regions {
+            self.record(GlCall::CopyTexture {
+                src,
+                from: (from.x, from.y),
+                to: (to.x, to.y),
+                size: (size.x, size.y),
+            });
+        }
+    }
+}
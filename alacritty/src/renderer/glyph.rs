@@ -1,11 +1,17 @@
+use super::atlas::{AtlasEntry, GlyphMove};
+use super::bitmap_font::BitmapRasterizer;
+use super::builtin_font;
+use super::color_glyph::ColorFonts;
 use super::math::*;
-use crate::config::font::{Font, FontDescription};
+use super::scale;
+use super::synthesis::{self, Synthesis, SyntheticFont};
+use crate::config::font::{FallbackSizing, Font, FontDescription};
 use crate::config::ui_config::Delta;
 use crate::config::Config;
-use crate::cursor;
-use alacritty_terminal::ansi::CursorStyle;
-use alacritty_terminal::term::CursorKey;
-use crossfont::{FontDesc, FontKey, Rasterize, Rasterizer, Size, Slant, Style, Weight};
+use alacritty_terminal::term::cell::{EMOJI_PRESENTATION_SELECTOR, TEXT_PRESENTATION_SELECTOR};
+use crossfont::{
+    BitmapBuffer, FontDesc, FontKey, Rasterize, Rasterizer, Size, Slant, Style, Weight,
+};
 use fnv::FnvHasher;
 use log::*;
 use std::collections::HashMap;
@@ -16,6 +22,43 @@ pub struct GlyphKey {
     pub key: crossfont::GlyphKey,
     pub wide: bool,
     pub zero_width: bool,
+    pub presentation: Presentation,
+}
+
+/// Glyph style requested by a variation selector.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Presentation {
+    /// Whichever glyph the font provides.
+    Default,
+
+    /// Monochrome glyph drawn in the text color.
+    Text,
+
+    /// Colored emoji glyph.
+    Emoji,
+}
+
+impl Presentation {
+    /// Presentation selected by the zero-width characters following a character.
+    pub fn from_zerowidth(chars: &[char]) -> Self {
+        chars
+            .iter()
+            .find_map(|&c| match c {
+                TEXT_PRESENTATION_SELECTOR => Some(Presentation::Text),
+                EMOJI_PRESENTATION_SELECTOR => Some(Presentation::Emoji),
+                _ => None,
+            })
+            .unwrap_or(Presentation::Default)
+    }
+
+    fn accepts(self, glyph: &crossfont::RasterizedGlyph) -> bool {
+        let colored = matches!(glyph.buffer, BitmapBuffer::RGBA(_));
+        match self {
+            Presentation::Default => true,
+            Presentation::Text => !colored,
+            Presentation::Emoji => colored,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -30,10 +73,18 @@ pub trait LoadGlyph {
     /// Load the rasterized glyph into GPU memory.
     fn load_glyph(&mut self, rasterized: &RasterizedGlyph) -> AtlasGlyph;
 
+    /// Release the atlas space of a glyph which was evicted from the cache.
+    fn unload_glyph(&mut self, glyph: &AtlasGlyph);
+
     /// Clear any state accumulated from previous loaded glyphs.
     ///
     /// This can, for instance, be used to reset the texture Atlas.
     fn clear(&mut self, cell_size: Vec2<i32>, cell_offset: Vec2<i32>);
+
+    /// Move glyphs out of the last grid atlas when the others have room for them, releasing it.
+    ///
+    /// Returns the moved glyphs, whose cached locations are no longer valid.
+    fn defragment(&mut self) -> Vec<GlyphMove>;
 }
 
 #[derive(Copy, Debug, Clone)]
@@ -44,6 +95,12 @@ pub struct GridAtlasGlyph {
     pub colored: bool,
 }
 
+impl GridAtlasGlyph {
+    pub fn entry(&self) -> AtlasEntry {
+        AtlasEntry { atlas_index: self.atlas_index, line: self.line, column: self.column }
+    }
+}
+
 #[derive(Copy, Debug, Clone)]
 pub struct QuadAtlasGlyph {
     pub atlas_index: usize,
@@ -64,19 +121,185 @@ pub enum AtlasGlyph {
     Quad(QuadAtlasGlyph),
 }
 
+/// Glyph in the cache, along with the frame it was last drawn in.
+#[derive(Copy, Debug, Clone)]
+struct CachedGlyph {
+    glyph: AtlasGlyph,
+    last_used: u64,
+
+    /// Font the glyph was rasterized from.
+    font_key: FontKey,
+}
+
+/// Font which is searched for glyphs missing from the configured faces.
+#[derive(Copy, Debug, Clone)]
+struct FallbackFont {
+    key: FontKey,
+
+    /// Whether the metrics of the font differ from the regular font, so its glyphs have to be
+    /// centered in the cell instead of being placed on the regular baseline.
+    centered: bool,
+
+    /// Whether centered glyphs which are larger than the cell are scaled down to fit it.
+    scaled: bool,
+
+    /// Cell size of the font.
+    cell_size: Vec2<i32>,
+
+    /// Position of the font in the configured fallback list.
+    index: usize,
+}
+
+/// Keys of the faces used for each style.
+#[derive(Debug, Clone)]
+struct FontKeys {
+    regular: FontKey,
+    bold: FontKey,
+    italic: FontKey,
+    bold_italic: FontKey,
+
+    /// Styles which are synthesized from other faces.
+    synthetic: Vec<SyntheticFont>,
+}
+
+/// Cached glyph, as listed by the glyph inspector.
+#[derive(Debug, Clone)]
+pub struct GlyphInfo {
+    pub key: GlyphKey,
+
+    /// Name of the face the glyph was rasterized from.
+    pub font: String,
+
+    pub glyph: AtlasGlyph,
+}
+
+/// Rasterization of a character in one of the configured faces.
+#[derive(Debug, Clone)]
+pub struct FaceProbe {
+    /// Name of the face.
+    pub font: String,
+
+    pub key: crossfont::GlyphKey,
+
+    /// Rasterized glyph, `None` if the face has no glyph for the character.
+    pub glyph: Option<crossfont::RasterizedGlyph>,
+
+    /// Whether regular text uses this face for the character.
+    pub used: bool,
+}
+
+/// Font and device pixel ratio the cached glyphs were rasterized with.
+#[derive(Debug, Clone, PartialEq)]
+struct Rasterization {
+    font: Font,
+    dpr: f64,
+}
+
+impl Rasterization {
+    /// Whether glyphs rasterized for `font` at `dpr` would look the same.
+    fn matches(&self, font: &Font, dpr: f64) -> bool {
+        self.font == *font && (self.dpr - dpr).abs() < f64::EPSILON
+    }
+}
+
+/// Glyph cache usage counters.
+#[derive(Copy, Debug, Clone, Default)]
+pub struct GlyphCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+/// Rasterizer for the system fonts, or for the embedded bitmap font when none can be loaded.
+pub enum FontRasterizer {
+    System(Rasterizer),
+    Bitmap(BitmapRasterizer),
+}
+
+impl FontRasterizer {
+    /// Rasterizer for the system fonts, falling back to the bitmap font if it can't be created.
+    pub fn system(dpr: f64, use_thin_strokes: bool) -> Self {
+        match Rasterizer::new(dpr as f32, use_thin_strokes) {
+            Ok(rasterizer) => FontRasterizer::System(rasterizer),
+            Err(err) => {
+                error!("Unable to load system fonts, using the builtin bitmap font: {}", err);
+                Self::bitmap(dpr)
+            },
+        }
+    }
+
+    /// Rasterizer for the embedded bitmap font.
+    pub fn bitmap(dpr: f64) -> Self {
+        // The bitmap font is embedded, so creating its rasterizer never fails.
+        FontRasterizer::Bitmap(BitmapRasterizer::new(dpr as f32, false).unwrap())
+    }
+}
+
+impl Rasterize for FontRasterizer {
+    fn new(device_pixel_ratio: f32, use_thin_strokes: bool) -> Result<Self, crossfont::Error> {
+        Ok(Self::system(f64::from(device_pixel_ratio), use_thin_strokes))
+    }
+
+    fn metrics(&self, key: FontKey, size: Size) -> Result<crossfont::Metrics, crossfont::Error> {
+        match self {
+            FontRasterizer::System(rasterizer) => rasterizer.metrics(key, size),
+            FontRasterizer::Bitmap(rasterizer) => rasterizer.metrics(key, size),
+        }
+    }
+
+    fn load_font(&mut self, desc: &FontDesc, size: Size) -> Result<FontKey, crossfont::Error> {
+        match self {
+            FontRasterizer::System(rasterizer) => rasterizer.load_font(desc, size),
+            FontRasterizer::Bitmap(rasterizer) => rasterizer.load_font(desc, size),
+        }
+    }
+
+    fn get_glyph(
+        &mut self,
+        key: crossfont::GlyphKey,
+    ) -> Result<crossfont::RasterizedGlyph, crossfont::Error> {
+        match self {
+            FontRasterizer::System(rasterizer) => rasterizer.get_glyph(key),
+            FontRasterizer::Bitmap(rasterizer) => rasterizer.get_glyph(key),
+        }
+    }
+
+    fn update_dpr(&mut self, device_pixel_ratio: f32) {
+        match self {
+            FontRasterizer::System(rasterizer) => rasterizer.update_dpr(device_pixel_ratio),
+            FontRasterizer::Bitmap(rasterizer) => rasterizer.update_dpr(device_pixel_ratio),
+        }
+    }
+}
+
+/// Fraction of the cache capacity which is evicted at once when the cache is full.
+///
+/// Evicting in batches spreads the cost of finding the least recently used glyphs over many
+/// insertions.
+const EVICTION_DIVISOR: usize = 8;
+
 /// Naïve glyph cache.
 ///
 /// Currently only keyed by `char`, and thus not possible to hold different
 /// representations of the same code point.
 pub struct GlyphCache {
     /// Cache of buffered glyphs.
-    pub cache: HashMap<GlyphKey, AtlasGlyph, BuildHasherDefault<FnvHasher>>,
+    cache: HashMap<GlyphKey, CachedGlyph, BuildHasherDefault<FnvHasher>>,
+
+    /// Maximum number of glyphs in the cache, zero for no limit.
+    capacity: usize,
 
-    /// Cache of buffered cursor glyphs.
-    pub cursor_cache: HashMap<CursorKey, AtlasGlyph, BuildHasherDefault<FnvHasher>>,
+    /// Number of the frame which is currently being drawn.
+    frame: u64,
+
+    /// Cache usage counters.
+    pub stats: GlyphCacheStats,
 
     /// Rasterizer for loading new glyphs.
-    rasterizer: Rasterizer,
+    rasterizer: FontRasterizer,
+
+    /// Font and scale of the cached glyphs.
+    rasterization: Rasterization,
 
     /// Regular font.
     pub font_key: FontKey,
@@ -90,12 +313,27 @@ pub struct GlyphCache {
     /// Bold italic font.
     pub bold_italic_key: FontKey,
 
+    /// Styles without a face of their own, synthesized from other faces.
+    synthetic: Vec<SyntheticFont>,
+
+    /// Style faces whose metrics differ from the regular font, so their glyphs are centered.
+    centered_styles: Vec<FontKey>,
+
+    /// Fallback fonts, in order of priority.
+    fallback: Vec<FallbackFont>,
+
+    /// Faces whose glyphs are composed of colored layers.
+    color_fonts: ColorFonts,
+
     /// Font size.
     pub font_size: crossfont::Size,
 
     /// Glyph offset.
     glyph_offset: Delta<i8>,
 
+    /// Draw box drawing characters without the font.
+    builtin_box_drawing: bool,
+
     /// Font metrics.
     pub metrics: crossfont::Metrics,
 
@@ -105,50 +343,92 @@ pub struct GlyphCache {
 
 impl GlyphCache {
     pub fn new<L>(
-        mut rasterizer: Rasterizer,
+        mut rasterizer: FontRasterizer,
         config: &Config,
         font: &Font,
+        dpr: f64,
         loader: &mut L,
-    ) -> Result<GlyphCache, crossfont::Error>
+    ) -> GlyphCache
     where
         L: LoadGlyph,
     {
-        let (regular, bold, italic, bold_italic) = Self::compute_font_keys(font, &mut rasterizer)?;
-
-        // Need to load at least one glyph for the face before calling metrics.
-        // The glyph requested here ('m' at the time of writing) has no special
-        // meaning.
-        rasterizer.get_glyph(crossfont::GlyphKey { font_key: regular, c: 'm', size: font.size })?;
-
-        let metrics = rasterizer.metrics(regular, font.size)?;
+        let (keys, metrics) = match Self::load_fonts(font, &mut rasterizer) {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                error!("Unable to load a font, using the builtin bitmap font: {}", err);
+                rasterizer = FontRasterizer::bitmap(dpr);
+                Self::load_fonts(font, &mut rasterizer).expect("bitmap font is always available")
+            },
+        };
+        let regular = keys.regular;
 
         let (cell_width, cell_height) = Self::compute_cell_size(config, &metrics);
         let cell_size = Vec2::new(cell_width as i32, cell_height as i32);
 
+        let centered_styles =
+            Self::check_style_metrics(&keys, &metrics, font.size, &mut rasterizer);
+        let fallback = Self::load_fallback_fonts(font, &metrics, &mut rasterizer);
+
         let mut cache = Self {
             cache: HashMap::default(),
-            cursor_cache: HashMap::default(),
+            capacity: font.glyph_cache_size,
+            frame: 0,
+            stats: GlyphCacheStats::default(),
             rasterizer,
+            rasterization: Rasterization { font: font.clone(), dpr },
             font_size: font.size,
             font_key: regular,
-            bold_key: bold,
-            italic_key: italic,
-            bold_italic_key: bold_italic,
+            bold_key: keys.bold,
+            italic_key: keys.italic,
+            bold_italic_key: keys.bold_italic,
+            synthetic: keys.synthetic,
+            centered_styles,
+            fallback,
+            color_fonts: ColorFonts::default(),
             glyph_offset: font.glyph_offset,
+            builtin_box_drawing: font.builtin_box_drawing(),
             metrics,
             cell_size,
         };
 
-        cache.clear_cache_with_common_glyphs(loader, config);
+        cache.color_fonts.foreground = config.colors.primary.foreground;
+        cache.load_color_fonts(font, dpr);
+        cache.clear_cache_with_common_glyphs(loader);
+
+        cache
+    }
+
+    /// Load the faces of `font` along with the metrics of its regular face.
+    fn load_fonts(
+        font: &Font,
+        rasterizer: &mut FontRasterizer,
+    ) -> Result<(FontKeys, crossfont::Metrics), crossfont::Error> {
+        let keys = Self::compute_font_keys(font, rasterizer)?;
+        let metrics = Self::regular_metrics(keys.regular, font.size, rasterizer)?;
+        Ok((keys, metrics))
+    }
+
+    fn regular_metrics(
+        regular: FontKey,
+        size: Size,
+        rasterizer: &mut FontRasterizer,
+    ) -> Result<crossfont::Metrics, crossfont::Error> {
+        // Need to load at least one glyph for the face before calling metrics.
+        // The glyph requested here ('m' at the time of writing) has no special
+        // meaning.
+        rasterizer.get_glyph(crossfont::GlyphKey { font_key: regular, c: 'm', size })?;
 
-        Ok(cache)
+        rasterizer.metrics(regular, size)
     }
 
     /// Computes font keys for (Regular, Bold, Italic, Bold Italic).
+    ///
+    /// With synthesis enabled, styles without a face of their own get a new key, whose glyphs are
+    /// emulated using the closest loaded face.
     fn compute_font_keys(
         font: &Font,
-        rasterizer: &mut Rasterizer,
-    ) -> Result<(FontKey, FontKey, FontKey, FontKey), crossfont::Error> {
+        rasterizer: &mut FontRasterizer,
+    ) -> Result<FontKeys, crossfont::Error> {
         let size = font.size;
 
         // Load regular font.
@@ -180,11 +460,39 @@ impl GlyphCache {
 
         let bold_italic = load_or_regular(bold_italic_desc);
 
-        Ok((regular, bold, italic, bold_italic))
+        if !font.builtin_synthesis() {
+            return Ok(FontKeys { regular, bold, italic, bold_italic, synthetic: Vec::new() });
+        }
+
+        let mut synthetic = Vec::new();
+        let mut synthesize = |base: FontKey, bold: bool, italic: bool| {
+            let key = FontKey::next();
+            synthetic.push(SyntheticFont { key, base, synthesis: Synthesis { bold, italic } });
+            key
+        };
+
+        let has_bold = bold != regular;
+        let has_italic = italic != regular;
+
+        // Prefer deriving bold italic from a real bold or italic face.
+        let bold_italic = match (bold_italic != regular, has_bold, has_italic) {
+            (true, ..) => bold_italic,
+            (false, _, true) => synthesize(italic, true, false),
+            (false, true, false) => synthesize(bold, false, true),
+            (false, false, false) => synthesize(regular, true, true),
+        };
+        let bold = if has_bold { bold } else { synthesize(regular, true, false) };
+        let italic = if has_italic { italic } else { synthesize(regular, false, true) };
+
+        if !synthetic.is_empty() {
+            info!("Synthesizing {} font styles missing from the font family", synthetic.len());
+        }
+
+        Ok(FontKeys { regular, bold, italic, bold_italic, synthetic })
     }
 
     fn load_regular_font(
-        rasterizer: &mut Rasterizer,
+        rasterizer: &mut FontRasterizer,
         description: &FontDesc,
         size: Size,
     ) -> Result<FontKey, crossfont::Error> {
@@ -200,6 +508,122 @@ impl GlyphCache {
         }
     }
 
+    /// Load all fallback fonts which are available.
+    fn load_fallback_fonts(
+        font: &Font,
+        metrics: &crossfont::Metrics,
+        rasterizer: &mut FontRasterizer,
+    ) -> Vec<FallbackFont> {
+        let size = font.size;
+
+        font.fallback()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, desc)| {
+                let desc = Self::make_desc(desc, Slant::Normal, Weight::Normal);
+                let key = match rasterizer.load_font(&desc, size) {
+                    Ok(key) => key,
+                    Err(err) => {
+                        warn!("Ignoring fallback font: {}", err);
+                        return None;
+                    },
+                };
+
+                // Metrics are only available once a glyph of the face has been loaded.
+                let _ = rasterizer.get_glyph(crossfont::GlyphKey { font_key: key, c: 'm', size });
+                let fallback_metrics = rasterizer.metrics(key, size).ok();
+                let centered =
+                    fallback_metrics.map_or(true, |fallback| metrics_diverge(&fallback, metrics));
+                let scaled = centered && font.fallback_sizing == FallbackSizing::Scale;
+                let cell_size = fallback_metrics.map_or(Vec2::new(0, 0), |fallback| {
+                    Vec2::new(
+                        fallback.average_advance.ceil() as i32,
+                        fallback.line_height.ceil() as i32,
+                    )
+                });
+
+                Some(FallbackFont { key, centered, scaled, cell_size, index })
+            })
+            .collect()
+    }
+
+    /// Find the bold and italic faces which don't fit the cell of the regular font.
+    ///
+    /// Families configured per style can have different metrics, their glyphs are centered in the
+    /// cell like those of fallback fonts.
+    fn check_style_metrics(
+        keys: &FontKeys,
+        metrics: &crossfont::Metrics,
+        size: Size,
+        rasterizer: &mut FontRasterizer,
+    ) -> Vec<FontKey> {
+        let styles =
+            [("bold", keys.bold), ("italic", keys.italic), ("bold italic", keys.bold_italic)];
+
+        let mut centered: Vec<FontKey> = Vec::new();
+        for &(name, key) in &styles {
+            if key == keys.regular
+                || centered.contains(&key)
+                || keys.synthetic.iter().any(|font| font.key == key)
+            {
+                continue;
+            }
+
+            // Metrics are only available once a glyph of the face has been loaded.
+            let _ = rasterizer.get_glyph(crossfont::GlyphKey { font_key: key, c: 'm', size });
+            let style_metrics = match rasterizer.metrics(key, size) {
+                Ok(style_metrics) => style_metrics,
+                Err(_) => continue,
+            };
+
+            if metrics_diverge(&style_metrics, metrics) {
+                warn!(
+                    "The {} font has a cell size of {}x{} instead of {}x{}, its glyphs will be \
+                     centered in the cell",
+                    name,
+                    style_metrics.average_advance,
+                    style_metrics.line_height,
+                    metrics.average_advance,
+                    metrics.line_height,
+                );
+                centered.push(key);
+            }
+        }
+
+        centered
+    }
+
+    /// Find the faces with color layers among the loaded fonts.
+    fn load_color_fonts(&mut self, font: &Font, dpr: f64) {
+        // The builtin bitmap font replaces all configured faces.
+        if let FontRasterizer::Bitmap(_) = self.rasterizer {
+            self.color_fonts.load(std::iter::empty(), dpr);
+            return;
+        }
+
+        let (bold, italic, bold_italic) = (font.bold(), font.italic(), font.bold_italic());
+        let styles = [
+            (self.font_key, font.normal(), Slant::Normal, Weight::Normal),
+            (self.bold_key, &bold, Slant::Normal, Weight::Bold),
+            (self.italic_key, &italic, Slant::Italic, Weight::Normal),
+            (self.bold_italic_key, &bold_italic, Slant::Italic, Weight::Bold),
+        ];
+
+        // Synthetic styles are rasterized from faces which are already in the list.
+        let synthetic = &self.synthetic;
+        let styles = styles
+            .iter()
+            .copied()
+            .filter(|(key, ..)| synthetic.iter().all(|font| font.key != *key));
+
+        let descriptions = font.fallback();
+        let fallback = self.fallback.iter().map(|fallback| {
+            (fallback.key, &descriptions[fallback.index], Slant::Normal, Weight::Normal)
+        });
+
+        self.color_fonts.load(styles.chain(fallback), dpr);
+    }
+
     fn make_desc(desc: &FontDescription, slant: Slant, weight: Weight) -> FontDesc {
         let style = if let Some(ref spec) = desc.style {
             Style::Specific(spec.to_owned())
@@ -209,78 +633,352 @@ impl GlyphCache {
         FontDesc::new(desc.family.clone(), style)
     }
 
+    /// Rasterize a glyph, searching the fallback fonts if the requested font doesn't have it.
+    ///
+    /// Returns the glyph along with the font it was rasterized from.
+    #[allow(clippy::too_many_arguments)]
     fn rasterize_glyph(
-        glyph_key: GlyphKey,
-        rasterizer: &mut Rasterizer,
+        mut glyph_key: GlyphKey,
+        rasterizer: &mut FontRasterizer,
+        synthetic: &[SyntheticFont],
+        centered_styles: &[FontKey],
+        fallback: &[FallbackFont],
+        color_fonts: &ColorFonts,
         glyph_offset: Delta<i8>,
         metrics: &crossfont::Metrics,
-    ) -> RasterizedGlyph {
-        let mut rasterized =
-            rasterizer.get_glyph(glyph_key.key).unwrap_or_else(|_| Default::default());
+        cell_size: Vec2<i32>,
+    ) -> (RasterizedGlyph, FontKey) {
+        let mut font_key = glyph_key.key.font_key;
+
+        // Synthetic styles are rasterized from their base face.
+        let synthetic = synthetic.iter().find(|font| font.key == font_key);
+        if let Some(font) = synthetic {
+            glyph_key.key.font_key = font.base;
+        }
+
+        let mut centered = centered_styles.contains(&glyph_key.key.font_key);
+        let mut scaled = false;
+
+        let mut face = glyph_key.key;
+        let mut rasterized = match rasterizer.get_glyph(face) {
+            Ok(rasterized) => rasterized,
+            Err(crossfont::Error::MissingGlyph(_)) => fallback
+                .iter()
+                .find_map(|font| {
+                    let key = crossfont::GlyphKey { font_key: font.key, ..glyph_key.key };
+                    let rasterized = rasterizer.get_glyph(key).ok()?;
+                    face = key;
+                    font_key = font.key;
+                    centered = font.centered;
+                    scaled = font.scaled;
+                    Some(rasterized)
+                })
+                .unwrap_or_default(),
+            Err(_) => Default::default(),
+        };
+
+        // The system rasterizer only draws the base outline of layered color glyphs.
+        if let Some(colored) = color_fonts.rasterize(face) {
+            rasterized = colored;
+        }
+
+        // Variation selectors choose between colored and monochrome glyphs of the fallback fonts.
+        let presentation = glyph_key.presentation;
+        if !presentation.accepts(&rasterized) {
+            let preferred = fallback.iter().find_map(|font| {
+                let key = crossfont::GlyphKey { font_key: font.key, ..glyph_key.key };
+                let glyph =
+                    color_fonts.rasterize(key).or_else(|| rasterizer.get_glyph(key).ok())?;
+                Some((font, glyph)).filter(|(_, glyph)| presentation.accepts(glyph))
+            });
+
+            if let Some((font, glyph)) = preferred {
+                rasterized = glyph;
+                font_key = font.key;
+                centered = font.centered;
+                scaled = font.scaled;
+            }
+        }
+
+        if let Some(font) = synthetic {
+            synthesis::apply(&mut rasterized, font.synthesis, cell_size.y);
+        }
+
+        if centered {
+            let width = if glyph_key.wide { cell_size.x * 2 } else { cell_size.x };
+            if scaled {
+                scale::fit_glyph(&mut rasterized, Vec2::new(width, cell_size.y));
+            }
+            rasterized.left = (width - rasterized.width) / 2;
+            rasterized.top = (cell_size.y + rasterized.height) / 2;
+        } else {
+            rasterized.top -= metrics.descent as i32;
+        }
 
         rasterized.left += i32::from(glyph_offset.x);
         rasterized.top += i32::from(glyph_offset.y);
-        rasterized.top -= metrics.descent as i32;
 
-        RasterizedGlyph { wide: glyph_key.wide, zero_width: glyph_key.zero_width, rasterized }
+        let glyph =
+            RasterizedGlyph { wide: glyph_key.wide, zero_width: glyph_key.zero_width, rasterized };
+        (glyph, font_key)
+    }
+
+    /// Start drawing a new frame.
+    ///
+    /// Glyphs which have been drawn in the current frame are never evicted, since the frame still
+    /// references their atlas space.
+    pub fn start_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Number of glyphs in the cache and the maximum number of glyphs, zero for no limit.
+    pub fn usage(&self) -> (usize, usize) {
+        (self.cache.len(), self.capacity)
+    }
+
+    /// Number of cached glyphs which were rasterized from a fallback font.
+    pub fn fallback_usage(&self) -> usize {
+        self.cache
+            .values()
+            .filter(|cached| self.fallback.iter().any(|font| font.key == cached.font_key))
+            .count()
+    }
+
+    /// All cached glyphs, ordered by character.
+    pub fn inspect(&self) -> Vec<GlyphInfo> {
+        let mut glyphs: Vec<GlyphInfo> = self
+            .cache
+            .iter()
+            .map(|(key, cached)| GlyphInfo {
+                key: *key,
+                font: self.face_name(cached.font_key),
+                glyph: cached.glyph,
+            })
+            .collect();
+        glyphs.sort_by(|a, b| {
+            (a.key.key.c, &a.font, a.key.wide).cmp(&(b.key.key.c, &b.font, b.key.wide))
+        });
+        glyphs
+    }
+
+    /// Rasterize a character in every configured face, without caching it.
+    pub fn probe(&mut self, c: char) -> Vec<FaceProbe> {
+        // Styles without a face of their own are covered by the regular face.
+        let styles = [self.font_key, self.bold_key, self.italic_key, self.bold_italic_key];
+        let mut faces: Vec<FontKey> = Vec::new();
+        for font_key in styles.iter().copied().chain(self.fallback.iter().map(|font| font.key)) {
+            if !faces.contains(&font_key) {
+                faces.push(font_key);
+            }
+        }
+
+        let mut probes: Vec<FaceProbe> = faces
+            .into_iter()
+            .map(|font_key| {
+                let key = crossfont::GlyphKey { font_key, c, size: self.font_size };
+                let glyph = match self.synthetic.iter().find(|font| font.key == font_key) {
+                    Some(font) => {
+                        let base_key = crossfont::GlyphKey { font_key: font.base, ..key };
+                        self.rasterizer.get_glyph(base_key).ok().map(|mut glyph| {
+                            synthesis::apply(&mut glyph, font.synthesis, self.cell_size.y);
+                            glyph
+                        })
+                    },
+                    None => self.rasterizer.get_glyph(key).ok(),
+                };
+                FaceProbe { font: self.face_name(font_key), key, glyph, used: false }
+            })
+            .collect();
+
+        // Regular text uses the first fallback font which has the glyph.
+        let regular = self.font_key;
+        let fallback = &self.fallback;
+        let used = probes.iter_mut().find(|probe| {
+            let font_key = probe.key.font_key;
+            probe.glyph.is_some()
+                && (font_key == regular || fallback.iter().any(|font| font.key == font_key))
+        });
+        if let Some(probe) = used {
+            probe.used = true;
+        }
+
+        probes
+    }
+
+    /// Name of a loaded face for the glyph inspector.
+    fn face_name(&self, font_key: FontKey) -> String {
+        if self.synthetic.iter().any(|font| font.key == font_key) {
+            return format!("{} (synthetic)", self.style_name(font_key));
+        }
+
+        self.style_name(font_key)
+    }
+
+    fn style_name(&self, font_key: FontKey) -> String {
+        if font_key == self.font_key {
+            String::from("regular")
+        } else if font_key == self.bold_key {
+            String::from("bold")
+        } else if font_key == self.italic_key {
+            String::from("italic")
+        } else if font_key == self.bold_italic_key {
+            String::from("bold italic")
+        } else {
+            match self.fallback.iter().position(|font| font.key == font_key) {
+                Some(index) => format!("fallback {}", index + 1),
+                None => String::from("unknown"),
+            }
+        }
     }
 
     pub fn get<L>(&mut self, glyph_key: GlyphKey, loader: &mut L) -> &AtlasGlyph
     where
         L: LoadGlyph,
     {
-        let glyph_offset = self.glyph_offset;
-        let rasterizer = &mut self.rasterizer;
-        let metrics = &self.metrics;
+        let frame = self.frame;
 
-        self.cache.entry(glyph_key).or_insert_with(|| {
-            let rasterized = Self::rasterize_glyph(glyph_key, rasterizer, glyph_offset, metrics);
-            loader.load_glyph(&rasterized)
-        })
+        if self.cache.contains_key(&glyph_key) {
+            self.stats.hits += 1;
+            let cached = self.cache.get_mut(&glyph_key).unwrap();
+            cached.last_used = frame;
+            return &cached.glyph;
+        }
+
+        self.stats.misses += 1;
+        if self.capacity != 0 && self.cache.len() >= self.capacity {
+            self.evict(loader);
+        }
+
+        // Box drawing glyphs are sized to the cell, so they ignore the glyph offset.
+        let builtin = if self.builtin_box_drawing && !glyph_key.wide && !glyph_key.zero_width {
+            builtin_font::builtin_glyph(glyph_key.key.c, self.cell_size, &self.metrics)
+        } else {
+            None
+        };
+
+        let (rasterized, font_key) = match builtin {
+            Some(rasterized) => (
+                RasterizedGlyph { wide: false, zero_width: false, rasterized },
+                glyph_key.key.font_key,
+            ),
+            None => Self::rasterize_glyph(
+                glyph_key,
+                &mut self.rasterizer,
+                &self.synthetic,
+                &self.centered_styles,
+                &self.fallback,
+                &self.color_fonts,
+                self.glyph_offset,
+                &self.metrics,
+                self.cell_size,
+            ),
+        };
+
+        let glyph = loader.load_glyph(&rasterized);
+        let cached = CachedGlyph { glyph, last_used: frame, font_key };
+        &self.cache.entry(glyph_key).or_insert(cached).glyph
+    }
+
+    /// Remove the least recently used glyphs and release their atlas space.
+    fn evict<L: LoadGlyph>(&mut self, loader: &mut L) {
+        let frame = self.frame;
+        let mut candidates: Vec<(u64, GlyphKey)> = self
+            .cache
+            .iter()
+            .filter(|(_, cached)| cached.last_used != frame)
+            .map(|(key, cached)| (cached.last_used, *key))
+            .collect();
+        candidates.sort_unstable_by_key(|(last_used, _)| *last_used);
+
+        let count = (self.capacity / EVICTION_DIVISOR).max(1).min(candidates.len());
+        for (_, key) in &candidates[..count] {
+            if let Some(cached) = self.cache.remove(key) {
+                loader.unload_glyph(&cached.glyph);
+            }
+        }
+
+        self.stats.evictions += count as u64;
+        debug!("Evicted {} glyphs from the glyph cache", count);
+    }
+
+    /// Repack the grid atlases once evictions freed enough space to release one of them.
+    pub fn defragment<L: LoadGlyph>(&mut self, loader: &mut L) {
+        let moves: HashMap<AtlasEntry, AtlasEntry> = loader
+            .defragment()
+            .into_iter()
+            .map(|glyph_move| (glyph_move.from, glyph_move.to))
+            .collect();
+        if moves.is_empty() {
+            return;
+        }
+
+        for cached in self.cache.values_mut() {
+            if let AtlasGlyph::Grid(glyph) = &mut cached.glyph {
+                if let Some(to) = moves.get(&glyph.entry()) {
+                    glyph.atlas_index = to.atlas_index;
+                    glyph.line = to.line;
+                    glyph.column = to.column;
+                }
+            }
+        }
+
+        debug!("Moved {} glyphs to release a grid atlas", moves.len());
     }
 
     /// Clear currently cached data in both GL and the registry.
     pub fn clear_glyph_cache<L: LoadGlyph>(&mut self, config: &Config, loader: &mut L) {
         let (cell_width, cell_height) = Self::compute_cell_size(config, &self.metrics);
         self.cell_size = Vec2::new(cell_width as i32, cell_height as i32);
+        self.color_fonts.foreground = config.colors.primary.foreground;
         self.cache = HashMap::default();
-        self.cursor_cache = HashMap::default();
-        self.clear_cache_with_common_glyphs(loader, config);
+        self.clear_cache_with_common_glyphs(loader);
     }
 
+    /// Rasterize glyphs for a new font or device pixel ratio.
+    ///
+    /// Returns `false` without touching the cache if the glyphs would not change.
     pub fn update_font_size<L: LoadGlyph>(
         &mut self,
         config: &Config,
         font: &Font,
         dpr: f64,
         loader: &mut L,
-    ) -> Result<(), crossfont::Error> {
+    ) -> Result<bool, crossfont::Error> {
+        if self.rasterization.matches(font, dpr) {
+            return Ok(false);
+        }
+
         // Update dpi scaling.
         self.rasterizer.update_dpr(dpr as f32);
 
         // Recompute font keys.
-        let (regular, bold, italic, bold_italic) =
-            Self::compute_font_keys(font, &mut self.rasterizer)?;
+        let (keys, metrics) = Self::load_fonts(font, &mut self.rasterizer)?;
+        let regular = keys.regular;
 
-        self.rasterizer.get_glyph(crossfont::GlyphKey {
-            font_key: regular,
-            c: 'm',
-            size: font.size,
-        })?;
-        let metrics = self.rasterizer.metrics(regular, font.size)?;
+        let centered_styles =
+            Self::check_style_metrics(&keys, &metrics, font.size, &mut self.rasterizer);
+        let fallback = Self::load_fallback_fonts(font, &metrics, &mut self.rasterizer);
 
         info!("Font size changed to {:?} with DPR of {}", font.size, dpr);
 
         self.font_size = font.size;
+        self.builtin_box_drawing = font.builtin_box_drawing();
+        self.capacity = font.glyph_cache_size;
         self.font_key = regular;
-        self.bold_key = bold;
-        self.italic_key = italic;
-        self.bold_italic_key = bold_italic;
+        self.bold_key = keys.bold;
+        self.italic_key = keys.italic;
+        self.bold_italic_key = keys.bold_italic;
+        self.synthetic = keys.synthetic;
+        self.centered_styles = centered_styles;
+        self.fallback = fallback;
         self.metrics = metrics;
+        self.load_color_fonts(font, dpr);
+
+        self.rasterization = Rasterization { font: font.clone(), dpr };
 
         self.clear_glyph_cache(config, loader);
 
-        Ok(())
+        Ok(true)
     }
 
     pub fn font_metrics(&self) -> crossfont::Metrics {
@@ -288,16 +986,20 @@ impl GlyphCache {
     }
 
     /// Prefetch glyphs that are almost guaranteed to be loaded anyways.
-    fn clear_cache_with_common_glyphs<L: LoadGlyph>(&mut self, loader: &mut L, config: &Config) {
+    fn clear_cache_with_common_glyphs<L: LoadGlyph>(&mut self, loader: &mut L) {
         let glyph_offset = self.glyph_offset;
         let metrics = &self.metrics;
         let font_size = self.font_size;
         let rasterizer = &mut self.rasterizer;
+        let synthetic = &self.synthetic;
+        let centered_styles = &self.centered_styles;
+        let fallback = &self.fallback;
+        let color_fonts = &self.color_fonts;
 
         let cell_size = self.cell_size;
         let mut atlas_cell_size = self.cell_size;
         let mut atlas_cell_offset = Vec2 { x: 0, y: 0 };
-        type Glyphs = Vec<(GlyphKey, RasterizedGlyph)>;
+        type Glyphs = Vec<(GlyphKey, RasterizedGlyph, FontKey)>;
         let glyphs: Glyphs = [self.font_key, self.bold_key, self.italic_key, self.bold_italic_key]
             .iter()
             .flat_map(|font| {
@@ -306,14 +1008,24 @@ impl GlyphCache {
                         let glyph_key = GlyphKey {
                             wide: false,
                             zero_width: false,
+                            presentation: Presentation::Default,
                             key: crossfont::GlyphKey {
                                 font_key: *font,
                                 c: c as char,
                                 size: font_size,
                             },
                         };
-                        let glyph =
-                            Self::rasterize_glyph(glyph_key, rasterizer, glyph_offset, metrics);
+                        let (glyph, source) = Self::rasterize_glyph(
+                            glyph_key,
+                            rasterizer,
+                            synthetic,
+                            centered_styles,
+                            fallback,
+                            color_fonts,
+                            glyph_offset,
+                            metrics,
+                            cell_size,
+                        );
 
                         atlas_cell_size.x = std::cmp::max(
                             atlas_cell_size.x,
@@ -341,55 +1053,52 @@ impl GlyphCache {
                             atlas_cell_offset,
                         );
 
-                        (glyph_key, glyph)
+                        (glyph_key, glyph, source)
                     })
                     .collect::<Glyphs>()
             })
             .collect();
 
+        // Make room for centered fallback glyphs, which overlap the cell on all sides.
+        for font in fallback.iter().filter(|font| font.centered && !font.scaled) {
+            let size = font.cell_size;
+            let left = (cell_size.x - size.x) / 2 + i32::from(glyph_offset.x);
+            let top = (cell_size.y + size.y) / 2 + i32::from(glyph_offset.y);
+
+            atlas_cell_size.x = std::cmp::max(atlas_cell_size.x, left + size.x);
+            atlas_cell_size.y = std::cmp::max(atlas_cell_size.y, top);
+            atlas_cell_offset.x = std::cmp::max(atlas_cell_offset.x, -left);
+            atlas_cell_offset.y = std::cmp::max(atlas_cell_offset.y, size.y - top);
+        }
+
         info!("Max glyph size: {:?}", cell_size);
 
         loader.clear(atlas_cell_size, atlas_cell_offset);
 
-        // Multipass grid render workaround for large font sizes
-        // Generate cursor glyphs first to ensure that they end up strictly
-        // in the first atlas/pass
-        for style in [
-            CursorStyle::Block,
-            CursorStyle::Beam,
-            CursorStyle::Underline,
-            CursorStyle::HollowBlock,
-        ]
-        .iter()
-        {
-            let cursor_key = CursorKey { style: *style, is_wide: false };
-            let cursor_glyph = RasterizedGlyph {
-                wide: false,
-                zero_width: false,
-                rasterized: cursor::get_cursor_glyph(
-                    cursor_key.style,
-                    *metrics,
-                    config.ui_config.font.offset.x,
-                    config.ui_config.font.offset.y,
-                    cursor_key.is_wide,
-                    config.cursor.thickness(),
-                ),
-            };
-            self.cursor_cache.entry(cursor_key).or_insert_with(|| loader.load_glyph(&cursor_glyph));
-        }
-
-        for glyph in glyphs {
-            self.cache.entry(glyph.0).or_insert_with(|| loader.load_glyph(&glyph.1));
+        let frame = self.frame;
+        for (glyph_key, glyph, font_key) in glyphs {
+            self.cache.entry(glyph_key).or_insert_with(|| CachedGlyph {
+                glyph: loader.load_glyph(&glyph),
+                last_used: frame,
+                font_key,
+            });
         }
     }
 
     /// Calculate font metrics without access to a glyph cache.
-    pub fn static_metrics(font: Font, dpr: f64) -> Result<crossfont::Metrics, crossfont::Error> {
-        let mut rasterizer = crossfont::Rasterizer::new(dpr as f32, font.use_thin_strokes())?;
+    ///
+    /// Uses the metrics of the builtin bitmap font if the regular font can't be loaded.
+    pub fn static_metrics(font: Font, dpr: f64) -> crossfont::Metrics {
         let regular_desc = GlyphCache::make_desc(&font.normal(), Slant::Normal, Weight::Normal);
-        let regular = Self::load_regular_font(&mut rasterizer, &regular_desc, font.size)?;
-        rasterizer.get_glyph(crossfont::GlyphKey { font_key: regular, c: 'm', size: font.size })?;
-        rasterizer.metrics(regular, font.size)
+        let mut rasterizer = FontRasterizer::system(dpr, font.use_thin_strokes());
+        let metrics = Self::load_regular_font(&mut rasterizer, &regular_desc, font.size)
+            .and_then(|regular| Self::regular_metrics(regular, font.size, &mut rasterizer));
+
+        metrics.unwrap_or_else(|_| {
+            let mut rasterizer = FontRasterizer::bitmap(dpr);
+            let regular = rasterizer.load_font(&regular_desc, font.size).unwrap();
+            Self::regular_metrics(regular, font.size, &mut rasterizer).unwrap()
+        })
     }
 
     /// Calculate the cell dimensions based on font metrics.
@@ -397,11 +1106,35 @@ impl GlyphCache {
     /// This will return a tuple of the cell width and height.
     #[inline]
     pub fn compute_cell_size(config: &Config, metrics: &crossfont::Metrics) -> (f32, f32) {
-        let offset_x = f64::from(config.ui_config.font.offset.x);
-        let offset_y = f64::from(config.ui_config.font.offset.y);
+        let font = &config.ui_config.font;
+        let offset_x = f64::from(font.offset.x);
+        let offset_y = f64::from(font.offset.y);
+        let advance = metrics.average_advance * f64::from(font.letter_spacing());
+        let line_height = metrics.line_height * f64::from(font.line_height());
         (
-            (metrics.average_advance + offset_x).floor().max(1.) as f32,
-            (metrics.line_height + offset_y).floor().max(1.) as f32,
+            (advance + offset_x).floor().max(1.) as f32,
+            (line_height + offset_y).floor().max(1.) as f32,
         )
     }
 }
+
+/// Whether the cell of a font differs from the cell of the regular font by a pixel or more.
+fn metrics_diverge(metrics: &crossfont::Metrics, regular: &crossfont::Metrics) -> bool {
+    (metrics.average_advance - regular.average_advance).abs() >= 1.
+        || (metrics.line_height - regular.line_height).abs() >= 1.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rasterization_changes() {
+        let font = Font::default();
+        let rasterization = Rasterization { font: font.clone(), dpr: 2. };
+
+        assert!(rasterization.matches(&font, 2.));
+        assert!(!rasterization.matches(&font, 1.5));
+        assert!(!rasterization.matches(&font.with_size(Size::new(20.)), 2.));
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use alacritty_terminal::graphics::{GraphicData, GraphicId, RenderableGraphic};
+use alacritty_terminal::term::color;
+use alacritty_terminal::term::SizeInfo;
+
+use super::framebuffer::Framebuffer;
+use super::gl_api::Gl;
+use super::math::Vec2;
+use super::quad::{enable_vertex_attribs, Rgb, Vertex};
+use super::shade::GlyphRectShaderProgram;
+use super::stats::PassStats;
+use super::texture::create_image_texture;
+use crate::gl;
+use crate::gl::types::*;
+
+/// Images from the terminal, drawn as textured quads.
+///
+/// Images with a negative z-index are drawn into a separate layer, which the grid pass composites
+/// between the cell backgrounds and the text. All others are drawn on top of the text.
+#[derive(Debug)]
+pub struct GraphicsRenderer {
+    /// Uploaded texture of every image which is still placed on the grid.
+    textures: HashMap<GraphicId, GLuint>,
+
+    /// Layer of images below the text.
+    underlay: Option<Framebuffer>,
+
+    program: GlyphRectShaderProgram,
+    vao: GLuint,
+    vbo: GLuint,
+}
+
+impl GraphicsRenderer {
+    pub fn new() -> Self {
+        let mut vao: GLuint = 0;
+        let mut vbo: GLuint = 0;
+
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            enable_vertex_attribs();
+
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+
+        Self {
+            textures: HashMap::new(),
+            underlay: None,
+            program: GlyphRectShaderProgram::new().unwrap(),
+            vao,
+            vbo,
+        }
+    }
+
+    /// Upload new images and delete the textures of removed ones.
+    pub fn update(&mut self, pending: Vec<GraphicData>, removed: &[GraphicId]) -> PassStats {
+        for id in removed {
+            if let Some(tex) = self.textures.remove(id) {
+                unsafe {
+                    gl::DeleteTextures(1, &tex);
+                }
+            }
+        }
+
+        let mut stats = PassStats::default();
+        for GraphicData { id, image } in pending {
+            // Quads expect colored textures with premultiplied alpha.
+            let mut pixels = image.pixels;
+            for pixel in pixels.chunks_exact_mut(4) {
+                let alpha = u16::from(pixel[3]);
+                for channel in &mut pixel[..3] {
+                    *channel = (u16::from(*channel) * alpha / 255) as u8;
+                }
+            }
+
+            let tex = unsafe {
+                let tex =
+                    create_image_texture(&Gl, image.width as i32, image.height as i32, &pixels);
+
+                // Linear filtering would blend in the opposite edge of repeating textures.
+                gl::BindTexture(gl::TEXTURE_2D, tex);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                gl::BindTexture(gl::TEXTURE_2D, 0);
+
+                tex
+            };
+
+            stats.uploaded_bytes += pixels.len();
+            if let Some(old) = self.textures.insert(id, tex) {
+                unsafe {
+                    gl::DeleteTextures(1, &old);
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// Draw the visible images above the text at their position on the grid.
+    pub fn draw(&mut self, size_info: &SizeInfo, graphics: &[RenderableGraphic]) -> PassStats {
+        let above = graphics.iter().filter(|graphic| graphic.geometry.z_index >= 0);
+        unsafe {
+            gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::SRC_ALPHA, gl::ONE);
+        }
+        self.draw_quads(size_info, above)
+    }
+
+    /// Draw the visible images below the text into the underlay.
+    ///
+    /// Returns the premultiplied underlay texture, if any image is below the text.
+    pub fn draw_underlay(
+        &mut self,
+        size_info: &SizeInfo,
+        graphics: &[RenderableGraphic],
+    ) -> (PassStats, Option<GLuint>) {
+        let mut below = graphics.iter().filter(|graphic| graphic.geometry.z_index < 0).peekable();
+        if below.peek().is_none() {
+            self.underlay = None;
+            return (PassStats::default(), None);
+        }
+
+        // Creating the framebuffer resets the binding, so the current target is saved first.
+        let mut target: GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut target);
+        }
+
+        let size = Vec2::new(size_info.width() as i32, size_info.height() as i32);
+        let underlay = self.underlay.get_or_insert_with(|| Framebuffer::new(size));
+        underlay.resize(size);
+        let tex = underlay.tex;
+
+        let stats = unsafe {
+            underlay.bind();
+            gl::ClearColor(0., 0., 0., 0.);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+
+            // Accumulate premultiplied colors, so the grid can composite them with a single fetch.
+            gl::BlendFuncSeparate(
+                gl::SRC_ALPHA,
+                gl::ONE_MINUS_SRC_ALPHA,
+                gl::ONE,
+                gl::ONE_MINUS_SRC_ALPHA,
+            );
+            let stats = self.draw_quads(size_info, below);
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target as GLuint);
+
+            stats
+        };
+
+        (stats, Some(tex))
+    }
+
+    /// Draw images as quads with the current blend function.
+    ///
+    /// Images are scaled with the cell size, so they keep covering the same cells after the font
+    /// size has changed. Everything outside of the grid is clipped by the viewport.
+    fn draw_quads<'a, I>(&mut self, size_info: &SizeInfo, graphics: I) -> PassStats
+    where
+        I: Iterator<Item = &'a RenderableGraphic>,
+    {
+        let mut graphics = graphics.peekable();
+        let mut stats = PassStats::default();
+        if graphics.peek().is_none() {
+            return stats;
+        }
+
+        unsafe {
+            let pad_x = size_info.padding_left() as i32;
+            let pad_y = size_info.padding_y() as i32;
+            let width = size_info.width() as i32 - pad_x - size_info.padding_x() as i32;
+            let height = size_info.height() as i32 - 2 * pad_y;
+            gl::Viewport(pad_x, pad_y, width, height);
+
+            gl::UseProgram(self.program.get_id());
+            gl::Uniform1i(self.program.u_atlas, 0);
+            gl::Uniform2f(self.program.u_scale, 2.0 / width as f32, -2.0 / height as f32);
+
+            gl::Enable(gl::BLEND);
+
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+
+        let fg = Rgb::from(color::Rgb::default());
+        for graphic in graphics {
+            let tex = match self.textures.get(&graphic.id) {
+                Some(tex) => *tex,
+                None => continue,
+            };
+
+            let geometry = &graphic.geometry;
+            let x = (graphic.column.0 as f32 + geometry.offset.0) * size_info.cell_width();
+            let y = (graphic.line as f32 + geometry.offset.1) * size_info.cell_height();
+            let right = x + geometry.columns * size_info.cell_width();
+            let bottom = y + geometry.lines * size_info.cell_height();
+
+            let [left_u, top_v, width_u, height_v] = geometry.source;
+            let (right_u, bottom_v) = (left_u + width_u, top_v + height_v);
+
+            let vertex = |x, y, u, v| Vertex { x, y, u, v, fg, flags: 1 };
+            let vertices = [
+                vertex(x, y, left_u, top_v),
+                vertex(x, bottom, left_u, bottom_v),
+                vertex(right, y, right_u, top_v),
+                vertex(right, bottom, right_u, bottom_v),
+            ];
+
+            let uploaded_bytes = std::mem::size_of_val(&vertices);
+            unsafe {
+                gl::BindTexture(gl::TEXTURE_2D, tex);
+                gl::BufferData(
+                    gl::ARRAY_BUFFER,
+                    uploaded_bytes as isize,
+                    vertices.as_ptr() as *const _,
+                    gl::STREAM_DRAW,
+                );
+                gl::DrawArrays(gl::TRIANGLE_STRIP, 0, vertices.len() as i32);
+            }
+
+            stats.add(PassStats { draw_calls: 1, uploaded_bytes, ..PassStats::default() });
+        }
+
+        stats
+    }
+}
+
+impl Drop for GraphicsRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            for tex in self.textures.values() {
+                gl::DeleteTextures(1, tex);
+            }
+
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
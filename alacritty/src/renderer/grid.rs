@@ -1,21 +1,47 @@
-use super::atlas::{AtlasInsertError, GridAtlas};
+use super::atlas::{self, AtlasInsertError, GlyphMove, GridAtlas};
+use super::atlas_debug::AtlasTexture;
+use super::background_image::BackgroundImage;
+use super::gl_api::{Gl, GlApi};
 use super::glyph::{GridAtlasGlyph, RasterizedGlyph};
 use super::math::*;
+use super::rects::line_offset;
+use super::screen_texture::{ring_rows, ScreenTexture};
 use super::shade::GridShaderProgram;
+use super::stats::PassStats;
 use super::texture::{create_texture, upload_texture, PixelFormat};
+use crate::config::background_image::BackgroundImageMode;
 use crate::gl;
 use crate::gl::types::*;
 use crate::renderer::Error;
-use alacritty_terminal::term::{color::Rgb, RenderableCell, SizeInfo};
+use alacritty_terminal::ansi::CursorStyle;
+use alacritty_terminal::term::cell::Flags;
+use alacritty_terminal::term::color::{self, CellRgb, Rgb};
+use alacritty_terminal::term::{
+    CellHighlight, CursorKey, HighlightColors, RenderableCell, SizeInfo,
+};
+use crossfont::Metrics;
 use log::*;
+use std::mem;
 use std::ptr;
 
+/// Divisor of the free entries in the other atlases which the glyphs of the last grid atlas may
+/// take up when it is released.
+const DEFRAGMENT_FILL_DIVISOR: usize = 2;
+
+/// Cursor drawn by the main pass of the screen shader.
 #[derive(Debug)]
 pub struct CursorRef {
-    atlas_index: usize,
     cell: [f32; 2],
-    glyph: [f32; 2],
+    style: CursorStyle,
+    wide: bool,
+
+    /// Line width of the beam, underline and hollow box in pixels.
+    thickness: f32,
+
     color: [f32; 3],
+
+    /// Whether the cursor is drawn, false while it is blinked off.
+    visible: bool,
 }
 
 #[derive(Debug)]
@@ -28,23 +54,34 @@ pub struct GridGlyphRenderer {
     cell_size: Vec2<i32>,
     cell_offset: Vec2<i32>,
 
-    /// Foreground colors array for each cell.
-    screen_colors_fg: Vec<[u8; 3]>,
+    /// Foreground color and highlight class of each cell.
+    screen_colors_fg: ScreenTexture<[u8; 4]>,
+
+    /// Background color of each cell.
+    screen_colors_bg: ScreenTexture<[u8; 4]>,
 
-    /// Background colors array for each cell.
-    screen_colors_bg: Vec<[u8; 4]>,
+    /// Palette indices of foreground and background colors for each cell.
+    screen_color_indices: ScreenTexture<[u8; 4]>,
 
     /// Background alpha for empty cells.
     bg_alpha: u8,
 
-    /// Texture that stores glyphs data references for each cell of the screen.
-    screen_glyphs_ref_tex: GLuint,
+    /// Background color for cells using the default background.
+    bg_color: Rgb,
 
-    /// Texture that stores foreground color for each cell.
-    screen_colors_fg_tex: GLuint,
+    /// Palette being faded from in the first row and palette being faded to in the second.
+    palette: Vec<[u8; 3]>,
+    palette_tex: GLuint,
+    palette_dirty: bool,
 
-    /// Texture that stores background color for each cell.
-    screen_colors_bg_tex: GLuint,
+    /// Progress of the palette cross-fade from 0.0 to 1.0.
+    palette_blend: f32,
+
+    /// Image drawn instead of the default background color.
+    background_image: Option<BackgroundImage>,
+
+    /// Premultiplied layer composited between the cell backgrounds and the text.
+    underlay: Option<GLuint>,
 
     /// Shader program that paints the entire screen.
     program: GridShaderProgram,
@@ -56,16 +93,40 @@ pub struct GridGlyphRenderer {
     /// Current cursor data, if any.
     cursor: Option<CursorRef>,
 
+    /// Terminal cursor drawn below the vi mode cursor.
+    terminal_cursor: Option<CursorRef>,
+
+    /// Colors of selected cells and search matches.
+    highlights: [HighlightColors; 2],
+
+    /// Lines the content moved up since the last frame.
+    scroll: isize,
+
+    /// Row of the screen textures storing the first line of the screen.
+    ring_offset: usize,
+
+    /// Lines directly above the screen which are still stored in the screen textures.
+    history_lines: usize,
+
+    /// Time into the current blink cycle and the duration of a blink phase in seconds, zero when
+    /// text doesn't blink.
+    blink_time: f32,
+    blink_interval: f32,
+
     /// Rendering passes. Potentially need multiple because not all glyphs may fit into a single
     /// atlas texture.
     grid_passes: Vec<GridPass>,
+
+    /// Size of the atlas texture of each pass.
+    atlas_size: i32,
+
+    /// Maximum number of passes, zero for no limit.
+    max_passes: usize,
 }
 
 impl GridGlyphRenderer {
-    pub fn new() -> Result<Self, Error> {
-        let screen_glyphs_ref_tex = unsafe { create_texture(256, 256, PixelFormat::RGB8) };
-        let screen_colors_fg_tex = unsafe { create_texture(256, 256, PixelFormat::RGBA8) };
-        let screen_colors_bg_tex = unsafe { create_texture(256, 256, PixelFormat::RGB8) };
+    pub fn new(atlas_size: i32, max_passes: usize) -> Result<Self, Error> {
+        let palette_tex = unsafe { create_texture(&Gl, color::COUNT as i32, 2, PixelFormat::RGB8) };
 
         let mut vao: GLuint = 0;
         let mut vbo: GLuint = 0;
@@ -97,20 +158,34 @@ impl GridGlyphRenderer {
             cell_size: Vec2 { x: 0, y: 0 },
             cell_offset: Vec2 { x: 0, y: 0 },
 
-            screen_colors_fg: Vec::new(),
-            screen_colors_bg: Vec::new(),
+            screen_colors_fg: ScreenTexture::new(PixelFormat::RGBA8),
+            screen_colors_bg: ScreenTexture::new(PixelFormat::RGBA8),
+            screen_color_indices: ScreenTexture::new(PixelFormat::RGBA8),
             bg_alpha: 255,
-
-            screen_glyphs_ref_tex,
-            screen_colors_fg_tex,
-            screen_colors_bg_tex,
+            bg_color: Rgb::default(),
+
+            palette: vec![[0u8; 3]; color::COUNT * 2],
+            palette_tex,
+            palette_dirty: true,
+            palette_blend: 1.,
+            background_image: None,
+            underlay: None,
             program: GridShaderProgram::new()?,
             vao,
             vbo,
 
             cursor: None,
+            terminal_cursor: None,
+            highlights: [HighlightColors::default(); 2],
+            scroll: 0,
+            ring_offset: 0,
+            history_lines: 0,
+            blink_time: 0.,
+            blink_interval: 0.,
 
             grid_passes: Vec::new(),
+            atlas_size,
+            max_passes,
         })
     }
 
@@ -118,10 +193,11 @@ impl GridGlyphRenderer {
     pub fn resize(&mut self, size_info: &SizeInfo) {
         self.columns = size_info.cols().0;
         self.lines = size_info.visible_lines().0;
-        let cells = self.columns * self.lines;
+        self.history_lines = 0;
 
-        self.screen_colors_bg.resize(cells, [0u8; 4]);
-        self.screen_colors_fg.resize(cells, [0u8; 3]);
+        self.screen_colors_bg.resize(self.columns, self.lines, [0u8; 4]);
+        self.screen_colors_fg.resize(self.columns, self.lines, [0u8; 4]);
+        self.screen_color_indices.resize(self.columns, self.lines, [0u8; 4]);
 
         for pass in &mut self.grid_passes {
             pass.resize(self.columns, self.lines);
@@ -129,16 +205,47 @@ impl GridGlyphRenderer {
     }
 
     /// Clear internal buffers to prepare for the next frame.
-    pub fn clear(&mut self, color: Rgb, background_opacity: f32) {
+    pub fn clear(&mut self, color: Rgb, background_opacity: f32, palette_blend: f32) {
         for pass in &mut self.grid_passes {
             pass.clear();
         }
 
         self.cursor = None;
+        self.terminal_cursor = None;
         let bg_alpha = (background_opacity * 255.0) as u8;
         self.bg_alpha = bg_alpha;
-        self.screen_colors_bg.iter_mut().for_each(|x| *x = [color.r, color.g, color.b, bg_alpha]);
-        self.screen_colors_fg.iter_mut().for_each(|x| *x = [0u8; 3]);
+        self.bg_color = color;
+        self.screen_colors_bg.fill([color.r, color.g, color.b, bg_alpha]);
+        self.screen_colors_fg.fill([0u8; 4]);
+        self.screen_color_indices.fill([0u8; 4]);
+        self.palette_blend = palette_blend;
+    }
+
+    /// Set the palettes which indexed cell colors are blended between.
+    pub fn set_palette(&mut self, from: &color::List, to: &color::List) {
+        let (from_row, to_row) = self.palette.split_at_mut(color::COUNT);
+        for index in 0..color::COUNT {
+            from_row[index] = [from[index].r, from[index].g, from[index].b];
+            to_row[index] = [to[index].r, to[index].g, to[index].b];
+        }
+        self.palette_dirty = true;
+    }
+
+    /// Replace the image drawn behind cells using the default background.
+    pub fn set_background_image(&mut self, image: Option<BackgroundImage>) {
+        self.background_image = image;
+    }
+
+    /// Move the content of the last frame up by `lines`, so it can be scrolled on the GPU.
+    ///
+    /// This only moves the row the screen starts at in the screen textures.
+    pub fn scroll(&mut self, lines: isize) {
+        self.scroll += lines;
+    }
+
+    /// Set the layer drawn below the text of the next frame.
+    pub fn set_underlay(&mut self, underlay: Option<GLuint>) {
+        self.underlay = underlay;
     }
 
     /// Completely obliterate atlas data in case e.g. font changed.
@@ -149,23 +256,77 @@ impl GridGlyphRenderer {
         self.grid_passes.clear();
     }
 
+    /// Atlas textures of all rendering passes.
+    pub fn atlas_textures(&self) -> impl Iterator<Item = AtlasTexture> + '_ {
+        self.grid_passes.iter().enumerate().map(|(index, pass)| AtlasTexture {
+            kind: "grid",
+            index,
+            tex: pass.atlas.tex.id,
+            size: pass.atlas.size(),
+        })
+    }
+
     /// Update cursor coordinates and appearance.
+    ///
+    /// The `position` is in cells, with fractions while the cursor moves between cells.
+    ///
+    /// Hiding the cursor only changes a uniform, so blinking does not upload any cells.
     pub fn set_cursor(
         &mut self,
-        atlas_index: usize,
-        column: i32,
-        line: i32,
-        glyph_x: f32,
-        glyph_y: f32,
+        position: Vec2<f32>,
+        key: CursorKey,
+        thickness: f32,
         color: Rgb,
+        visible: bool,
     ) {
         self.cursor = Some(CursorRef {
-            atlas_index,
-            cell: [column as f32, line as f32],
-            glyph: [glyph_x, glyph_y],
+            cell: [position.x, position.y],
+            style: key.style,
+            wide: key.is_wide,
+            thickness,
+            color: [color.r as f32 / 255., color.g as f32 / 255., color.b as f32 / 255.],
+            visible,
+        });
+    }
+
+    /// Show the terminal cursor in addition to the vi mode cursor.
+    ///
+    /// The vi mode cursor is set through `set_cursor` and drawn on top of this one.
+    pub fn set_terminal_cursor(
+        &mut self,
+        position: Vec2<f32>,
+        key: CursorKey,
+        thickness: f32,
+        color: Rgb,
+    ) {
+        self.terminal_cursor = Some(CursorRef {
+            cell: [position.x, position.y],
+            style: key.style,
+            wide: key.is_wide,
+            thickness,
             color: [color.r as f32 / 255., color.g as f32 / 255., color.b as f32 / 255.],
+            visible: true,
         });
-        self.grid_passes[atlas_index].dirty = true;
+    }
+
+    /// Set the colors of the highlight classes applied by the shader.
+    pub fn set_highlights(&mut self, selection: HighlightColors, search_match: HighlightColors) {
+        self.highlights = [selection, search_match];
+    }
+
+    /// Colors of a highlight class.
+    pub fn highlight_colors(&self, highlight: CellHighlight) -> HighlightColors {
+        match highlight {
+            CellHighlight::None => HighlightColors::default(),
+            CellHighlight::Selection => self.highlights[0],
+            CellHighlight::SearchMatch => self.highlights[1],
+        }
+    }
+
+    /// Set the blink phase of text with the blink attribute.
+    pub fn set_text_blink(&mut self, time: f32, interval: f32) {
+        self.blink_time = time;
+        self.blink_interval = interval;
     }
 
     /// Try to load a new rasterized glyph into grid atlas.
@@ -175,9 +336,17 @@ impl GridGlyphRenderer {
             return None;
         }
 
+        let mut created = false;
         loop {
-            if !self.grid_passes.is_empty() {
-                match self.grid_passes.last_mut().unwrap().atlas.insert(rasterized) {
+            // Reuse the space of evicted glyphs before filling up the last atlas.
+            let index = self
+                .grid_passes
+                .iter()
+                .position(|pass| pass.atlas.has_free_slots())
+                .or_else(|| self.grid_passes.len().checked_sub(1));
+
+            if let Some(index) = index {
+                match self.grid_passes[index].atlas.insert(rasterized) {
                     Ok(glyph) => {
                         return Some(glyph);
                     },
@@ -188,6 +357,8 @@ impl GridGlyphRenderer {
                         );
                         return None;
                     },
+                    // Not even a single cell fits into an empty atlas.
+                    Err(AtlasInsertError::Full) if created => return None,
                     Err(AtlasInsertError::Full) => {
                         debug!("GridAtlas is full, creating a new one");
                     },
@@ -195,13 +366,85 @@ impl GridGlyphRenderer {
             }
 
             let index = self.grid_passes.len();
+            if self.max_passes != 0 && index >= self.max_passes {
+                trace!(
+                    "All grid atlases are full, will render '{}' using quads",
+                    rasterized.rasterized.c
+                );
+                return None;
+            }
+
             self.grid_passes.push(GridPass::new(
                 index,
+                self.atlas_size,
                 self.columns,
                 self.lines,
                 self.cell_size,
                 self.cell_offset,
             ));
+            created = true;
+        }
+    }
+
+    /// Move the glyphs of the last atlas into entries freed by evictions in the other atlases and
+    /// release it, reducing the number of passes.
+    ///
+    /// Returns the moved glyphs.
+    pub fn defragment(&mut self) -> Vec<GlyphMove> {
+        let (last, earlier) = match self.grid_passes.split_last_mut() {
+            Some((last, earlier)) if !earlier.is_empty() => (last, earlier),
+            _ => return Vec::new(),
+        };
+
+        // Leave room for new glyphs, so a new atlas isn't created again right away.
+        let free: usize = earlier.iter().map(|pass| pass.atlas.free_capacity()).sum();
+        if last.atlas.glyph_count() * DEFRAGMENT_FILL_DIVISOR > free {
+            return Vec::new();
+        }
+
+        let mut atlases: Vec<&mut GridAtlas> =
+            earlier.iter_mut().map(|pass| &mut pass.atlas).collect();
+        let moves = match atlas::plan_defragment(&last.atlas, &mut atlases) {
+            Some(moves) => moves,
+            None => return Vec::new(),
+        };
+
+        let cell_size = last.atlas.cell_dims().size;
+        for (index, pass) in earlier.iter().enumerate() {
+            let regions: Vec<(Vec2<i32>, Vec2<i32>)> = moves
+                .iter()
+                .filter(|glyph_move| glyph_move.to.atlas_index == index)
+                .map(|glyph_move| {
+                    (
+                        last.atlas.entry_origin(glyph_move.from),
+                        pass.atlas.entry_origin(glyph_move.to),
+                    )
+                })
+                .collect();
+            if regions.is_empty() {
+                continue;
+            }
+
+            unsafe {
+                Gl.bind_texture(pass.atlas.tex.id);
+                Gl.copy_texture_regions(last.atlas.tex.id, cell_size, &regions);
+                Gl.bind_texture(0);
+            }
+        }
+
+        debug!("Released grid atlas {} after moving {} glyphs", earlier.len(), moves.len());
+        self.grid_passes.pop();
+
+        // Lines above the screen may still reference the released atlas.
+        self.history_lines = 0;
+
+        moves
+    }
+
+    /// Release the atlas space of an evicted glyph, so it can be reused for new glyphs.
+    pub fn unload_glyph(&mut self, glyph: &GridAtlasGlyph) {
+        if let Some(pass) = self.grid_passes.get_mut(glyph.atlas_index) {
+            pass.atlas.remove(glyph);
         }
     }
 
@@ -215,14 +458,43 @@ impl GridGlyphRenderer {
         // 0 == empty cell or regular background color with alpha set to opacity from config
         // 1 == some other background color that is not the default one
         // Non-default bg colors should likely also be transparent, see https://github.com/alacritty/alacritty/pull/4196
-        let bg_alpha =
-            if cell.bg_alpha == 0.0 { self.bg_alpha } else { (cell.bg_alpha * 255.0) as u8 };
-        self.screen_colors_fg[cell_index] = [cell.fg.r, cell.fg.g, cell.fg.b];
-        self.screen_colors_bg[cell_index] = [cell.bg.r, cell.bg.g, cell.bg.b, bg_alpha];
-
-        // Wide chars need to update adjacent cell background color too.
+        let (bg, bg_index, bg_alpha) = if cell.bg_alpha == 0.0 {
+            // Default background follows the clear color, which might be mid-transition.
+            (self.bg_color, None, self.bg_alpha)
+        } else {
+            (cell.bg, cell.bg_index, (cell.bg_alpha * 255.0) as u8)
+        };
+        let fg_index = encode_palette_index(cell.fg_index);
+        let bg_index = encode_palette_index(bg_index);
+        let highlight = highlight_class(cell.highlight);
+        self.screen_colors_fg.cells[cell_index] = [cell.fg.r, cell.fg.g, cell.fg.b, highlight];
+        self.screen_colors_bg.cells[cell_index] = [bg.r, bg.g, bg.b, bg_alpha];
+        self.screen_color_indices.cells[cell_index] =
+            [fg_index[0], fg_index[1], bg_index[0], bg_index[1]];
+
+        // Wide chars need to update adjacent cell background color too, which depends on the
+        // foreground of highlighted cells.
         if wide && cell.column.0 < self.columns {
-            self.screen_colors_bg[cell_index + 1] = [cell.bg.r, cell.bg.g, cell.bg.b, bg_alpha];
+            self.screen_colors_fg.cells[cell_index + 1] = self.screen_colors_fg.cells[cell_index];
+            self.screen_colors_bg.cells[cell_index + 1] = [bg.r, bg.g, bg.b, bg_alpha];
+            self.screen_color_indices.cells[cell_index + 1] =
+                self.screen_color_indices.cells[cell_index];
+        }
+
+        // Decorations and the background image are always drawn by the main pass, independent of
+        // the glyph's atlas.
+        if let Some(main_pass) = self.grid_passes.first_mut() {
+            main_pass.glyphs.cells[cell_index].flags |= decoration_flags(cell.flags);
+            main_pass.glyphs.cells[cell_index].attrs |= attribute_bits(cell.flags);
+
+            // Cells with their own background color hide the background image.
+            if cell.bg_alpha != 0.0 {
+                main_pass.glyphs.cells[cell_index].flags |= GLYPH_REF_FLAG_CELL_BACKGROUND_BIT;
+                if wide && cell.column.0 < self.columns {
+                    main_pass.glyphs.cells[cell_index + 1].flags |=
+                        GLYPH_REF_FLAG_CELL_BACKGROUND_BIT;
+                }
+            }
         }
     }
 
@@ -230,42 +502,128 @@ impl GridGlyphRenderer {
     pub fn update_cell(&mut self, cell: &RenderableCell, glyph: &GridAtlasGlyph) {
         let cell_index = cell.line.0 * self.columns + cell.column.0;
 
-        // put glyph reference into texture data
-        self.grid_passes[glyph.atlas_index].glyphs[cell_index] = GlyphRef {
+        // put glyph reference into texture data, keeping main pass flags already set for this cell
+        let glyph_ref = &mut self.grid_passes[glyph.atlas_index].glyphs.cells[cell_index];
+        *glyph_ref = GlyphRef {
             atlas_x: glyph.column as u8,
             atlas_y: glyph.line as u8,
-            flags: GLYPH_REF_FLAG_NOT_EMPTY_BIT
+            flags: (glyph_ref.flags & GLYPH_REF_CELL_FLAGS_MASK)
+                | GLYPH_REF_FLAG_NOT_EMPTY_BIT
                 | if glyph.colored { GLYPH_REF_FLAG_COLORED_BIT } else { 0 },
+            attrs: glyph_ref.attrs | attribute_bits(cell.flags),
         };
         self.grid_passes[glyph.atlas_index].dirty = true;
     }
 
-    fn apply_cursor_uniform(&self, pass: usize) {
-        match &self.cursor {
-            Some(cursor) if cursor.atlas_index == pass => unsafe {
-                gl::Uniform4f(
-                    self.program.u_cursor,
-                    cursor.cell[0],
-                    cursor.cell[1],
-                    cursor.glyph[0],
-                    cursor.glyph[1],
-                );
-                gl::Uniform3f(
-                    self.program.u_cursor_color,
-                    cursor.color[0],
-                    cursor.color[1],
-                    cursor.color[2],
-                );
-            },
-            _ => unsafe {
-                gl::Uniform4f(self.program.u_cursor, -1., -1., 0., 0.);
-                gl::Uniform3f(self.program.u_cursor_color, 0., 0., 0.);
+    fn apply_cursor_uniform(&self) {
+        let cursor = self.cursor.as_ref().or_else(|| self.terminal_cursor.as_ref());
+        let thickness = cursor.map_or(0., |cursor| cursor.thickness);
+        unsafe {
+            gl::Uniform1f(self.program.u_cursor_thickness, thickness);
+        }
+
+        cursor_uniform(self.program.u_cursor, self.program.u_cursor_color, self.cursor.as_ref());
+        cursor_uniform(
+            self.program.u_terminal_cursor,
+            self.program.u_terminal_cursor_color,
+            self.terminal_cursor.as_ref(),
+        );
+    }
+
+    /// Set the colors of selected cells and search matches.
+    fn apply_highlight_uniforms(&self) {
+        let mut fg = [0.; 8];
+        let mut bg = [0.; 8];
+        for (i, highlight) in self.highlights.iter().enumerate() {
+            fg[i * 4..(i + 1) * 4].copy_from_slice(&highlight_uniform(highlight.foreground));
+            bg[i * 4..(i + 1) * 4].copy_from_slice(&highlight_uniform(highlight.background));
+        }
+
+        unsafe {
+            gl::Uniform4fv(self.program.u_highlight_fg, 2, fg.as_ptr());
+            gl::Uniform4fv(self.program.u_highlight_bg, 2, bg.as_ptr());
+        }
+    }
+
+    /// Set position of underlines, strikeout and overline within a cell.
+    fn apply_decoration_uniforms(&self, size_info: &SizeInfo, metrics: &Metrics) {
+        let cell_height = size_info.cell_height();
+        let descent = metrics.descent;
+
+        // Make sure lines are always visible.
+        let underline_thickness = metrics.underline_thickness.max(1.);
+        let strikeout_thickness = metrics.strikeout_thickness.max(1.);
+
+        let underline =
+            line_offset(cell_height, descent, metrics.underline_position, underline_thickness);
+        let strikeout =
+            line_offset(cell_height, descent, metrics.strikeout_position, strikeout_thickness);
+
+        // Keep the overline as far from the top as the underline is from the bottom.
+        let underline_gap = cell_height - underline - underline_thickness;
+        let overline = underline_gap.max(0.);
+
+        // Position double underlines so each one has 50% of descent available.
+        let double_top = line_offset(cell_height, descent, 0.25 * descent, underline_thickness);
+        let double_bottom = line_offset(cell_height, descent, 0.75 * descent, underline_thickness);
+
+        // Curls oscillate around the regular underline, without leaving the cell.
+        let curl_center = underline + underline_thickness / 2.;
+        let curl_amplitude = (underline_thickness * 1.5)
+            .min(cell_height - curl_center - underline_thickness / 2.)
+            .max(0.);
+
+        unsafe {
+            gl::Uniform4f(
+                self.program.u_underline,
+                underline,
+                underline_thickness,
+                double_top,
+                double_bottom,
+            );
+            gl::Uniform4f(
+                self.program.u_strikeout,
+                strikeout,
+                strikeout_thickness,
+                overline,
+                underline_thickness,
+            );
+            gl::Uniform2f(self.program.u_undercurl, curl_center, curl_amplitude);
+        }
+    }
+
+    fn apply_background_image_uniform(&self) {
+        let image = match &self.background_image {
+            Some(image) => image,
+            None => {
+                unsafe {
+                    gl::Uniform4f(self.program.u_background_image_params, 0., 0., 0., 0.);
+                }
+                return;
             },
+        };
+
+        let mode = match image.mode {
+            BackgroundImageMode::Stretch => 0.,
+            BackgroundImageMode::Tile => 1.,
+            BackgroundImageMode::Center => 2.,
+        };
+
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE6);
+            gl::BindTexture(gl::TEXTURE_2D, image.tex);
+            gl::Uniform4f(
+                self.program.u_background_image_params,
+                image.size.x as f32,
+                image.size.y as f32,
+                image.opacity,
+                mode,
+            );
         }
     }
 
     /// Render all grid passes
-    pub fn draw(&mut self, size_info: &SizeInfo) {
+    pub fn draw(&mut self, size_info: &SizeInfo, metrics: &Metrics) -> PassStats {
         #[cfg(feature = "live-shader-reload")]
         {
             match self.program.poll() {
@@ -279,6 +637,19 @@ impl GridGlyphRenderer {
             }
         }
 
+        let scroll = mem::take(&mut self.scroll);
+        let rows = ring_rows(self.lines).max(1) as isize;
+        self.ring_offset = (self.ring_offset as isize + scroll).rem_euclid(rows) as usize;
+
+        // Lines scrolled out at the top stay in the texture, unless they were never on screen.
+        let history = rows - self.lines as isize;
+        self.history_lines = if scroll > self.lines as isize {
+            0
+        } else {
+            (self.history_lines as isize + scroll).max(0).min(history) as usize
+        };
+
+        let mut uploaded_bytes = 0;
         unsafe {
             // Main pass blends glyphs on background manually in shader
             // and it needs to write the final color onto framebuffer as-is
@@ -288,33 +659,68 @@ impl GridGlyphRenderer {
             gl::UseProgram(self.program.get_id());
 
             self.program.set_term_uniforms(size_info);
+            gl::Uniform2f(self.program.u_screen_rows, self.ring_offset as f32, self.lines as f32);
+            gl::Uniform3f(
+                self.program.u_minimap,
+                self.columns as f32 * size_info.cell_width(),
+                size_info.minimap(),
+                self.history_lines as f32,
+            );
+            gl::Uniform1i(self.program.u_minimap_pass, 0);
+            self.apply_decoration_uniforms(size_info, metrics);
             gl::Uniform1i(self.program.u_atlas, 0);
             gl::Uniform1i(self.program.u_glyph_ref, 1);
             gl::Uniform1i(self.program.u_color_fg, 2);
             gl::Uniform1i(self.program.u_color_bg, 3);
+            gl::Uniform1i(self.program.u_color_index, 4);
+            gl::Uniform1i(self.program.u_palette, 5);
+            gl::Uniform1f(self.program.u_palette_blend, self.palette_blend);
+            gl::Uniform1i(self.program.u_background_image, 6);
+            self.apply_background_image_uniform();
+            self.apply_cursor_uniform();
+            self.apply_highlight_uniforms();
+            gl::Uniform1f(self.program.u_time, self.blink_time);
+            gl::Uniform1f(self.program.u_blink_interval, self.blink_interval);
+
+            gl::Uniform1i(self.program.u_underlay, 7);
+            gl::Uniform1i(self.program.u_has_underlay, self.underlay.is_some() as i32);
+            if let Some(underlay) = self.underlay {
+                gl::ActiveTexture(gl::TEXTURE7);
+                gl::BindTexture(gl::TEXTURE_2D, underlay);
+            }
 
             gl::ActiveTexture(gl::TEXTURE2);
-            gl::BindTexture(gl::TEXTURE_2D, self.screen_colors_fg_tex);
-            upload_texture(
-                self.columns as i32,
-                self.lines as i32,
-                PixelFormat::RGB8,
-                self.screen_colors_fg.as_ptr() as *const _,
-            );
+            uploaded_bytes +=
+                self.screen_colors_fg.upload(self.columns, self.lines, self.ring_offset);
 
             gl::ActiveTexture(gl::TEXTURE3);
-            gl::BindTexture(gl::TEXTURE_2D, self.screen_colors_bg_tex);
-            upload_texture(
-                self.columns as i32,
-                self.lines as i32,
-                PixelFormat::RGBA8,
-                self.screen_colors_bg.as_ptr() as *const _,
-            );
+            uploaded_bytes +=
+                self.screen_colors_bg.upload(self.columns, self.lines, self.ring_offset);
+
+            gl::ActiveTexture(gl::TEXTURE4);
+            uploaded_bytes +=
+                self.screen_color_indices.upload(self.columns, self.lines, self.ring_offset);
+
+            gl::ActiveTexture(gl::TEXTURE5);
+            gl::BindTexture(gl::TEXTURE_2D, self.palette_tex);
+            if self.palette_dirty {
+                upload_texture(
+                    &Gl,
+                    color::COUNT as i32,
+                    2,
+                    PixelFormat::RGB8,
+                    self.palette.as_ptr() as *const _,
+                );
+                self.palette_dirty = false;
+                uploaded_bytes += color::COUNT * 2 * 3;
+            }
 
             gl::BindVertexArray(self.vao);
         }
 
-        for (pass_num, pass) in (&self.grid_passes).iter().enumerate() {
+        let mut stats = PassStats { uploaded_bytes, ..PassStats::default() };
+
+        for (pass_num, pass) in self.grid_passes.iter_mut().enumerate() {
             let main_pass = pass_num == 0;
             if !main_pass && !pass.dirty {
                 continue;
@@ -330,23 +736,19 @@ impl GridGlyphRenderer {
                     atlas_dims.size.y as f32,
                 );
                 gl::Uniform1i(self.program.u_main_pass, main_pass as i32);
-                self.apply_cursor_uniform(pass_num);
 
                 gl::ActiveTexture(gl::TEXTURE1);
-                gl::BindTexture(gl::TEXTURE_2D, self.screen_glyphs_ref_tex);
-                upload_texture(
-                    self.columns as i32,
-                    self.lines as i32,
-                    PixelFormat::RGB8,
-                    pass.glyphs.as_ptr() as *const _,
-                );
+                stats.uploaded_bytes +=
+                    pass.glyphs.upload(self.columns, self.lines, self.ring_offset);
 
                 gl::ActiveTexture(gl::TEXTURE0);
-                gl::BindTexture(gl::TEXTURE_2D, pass.atlas.tex);
+                gl::BindTexture(gl::TEXTURE_2D, pass.atlas.tex.id);
 
                 gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
             }
 
+            stats.draw_calls += 1;
+
             if main_pass {
                 unsafe {
                     // All further passes need to blend with framebuffer color
@@ -355,13 +757,153 @@ impl GridGlyphRenderer {
                 }
             }
         }
+
+        // The minimap is drawn from the screen textures and the glyphs of the main pass.
+        if let Some(main_pass) = self.grid_passes.first().filter(|_| size_info.minimap() > 0.) {
+            unsafe {
+                gl::Disable(gl::BLEND);
+                gl::Uniform1i(self.program.u_minimap_pass, 1);
+
+                gl::ActiveTexture(gl::TEXTURE1);
+                main_pass.glyphs.bind();
+
+                gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+                gl::Enable(gl::BLEND);
+            }
+
+            stats.draw_calls += 1;
+        }
+
+        stats
+    }
+}
+
+/// Encode an optional palette index as two bytes, with zero marking direct RGB colors.
+fn encode_palette_index(index: Option<usize>) -> [u8; 2] {
+    match index {
+        Some(index) => [(index & 0xff) as u8, (index >> 8) as u8 + 1],
+        None => [0, 0],
+    }
+}
+
+/// Underline, strikeout and overline bits for a cell.
+fn decoration_flags(flags: Flags) -> u8 {
+    let underline = match flags & Flags::ALL_UNDERLINES {
+        Flags::UNDERLINE => GLYPH_REF_UNDERLINE,
+        Flags::DOUBLE_UNDERLINE => GLYPH_REF_DOUBLE_UNDERLINE,
+        Flags::UNDERCURL => GLYPH_REF_UNDERCURL,
+        Flags::DOTTED_UNDERLINE => GLYPH_REF_DOTTED_UNDERLINE,
+        Flags::DASHED_UNDERLINE => GLYPH_REF_DASHED_UNDERLINE,
+        _ => 0,
+    };
+
+    let mut decoration = underline;
+    if flags.contains(Flags::STRIKEOUT) {
+        decoration |= GLYPH_REF_FLAG_STRIKEOUT_BIT;
     }
+    if flags.contains(Flags::OVERLINE) {
+        decoration |= GLYPH_REF_FLAG_OVERLINE_BIT;
+    }
+
+    decoration
+}
+
+/// Attribute bits of a cell which are evaluated by the shader.
+fn attribute_bits(flags: Flags) -> u8 {
+    let mut attrs = 0;
+    if flags.contains(Flags::BLINK) {
+        attrs |= GLYPH_REF_ATTR_BLINK_BIT;
+    }
+    if flags.contains(Flags::HIDDEN) {
+        attrs |= GLYPH_REF_ATTR_HIDDEN_BIT;
+    }
+    attrs
+}
+
+/// Highlight class stored in the alpha channel of the foreground color.
+fn highlight_class(highlight: CellHighlight) -> u8 {
+    match highlight {
+        CellHighlight::None => HIGHLIGHT_NONE,
+        CellHighlight::Selection => HIGHLIGHT_SELECTION,
+        CellHighlight::SearchMatch => HIGHLIGHT_SEARCH_MATCH,
+    }
+}
+
+/// Highlight color as a `u_highlight_fg`/`u_highlight_bg` uniform, with the color's source in
+/// the last component.
+fn highlight_uniform(color: CellRgb) -> [f32; 4] {
+    let (rgb, source) = match color {
+        CellRgb::CellForeground => (Rgb::default(), HIGHLIGHT_CELL_FOREGROUND),
+        CellRgb::CellBackground => (Rgb::default(), HIGHLIGHT_CELL_BACKGROUND),
+        CellRgb::Rgb(rgb) => (rgb, HIGHLIGHT_RGB),
+    };
+
+    [rgb.r as f32 / 255., rgb.g as f32 / 255., rgb.b as f32 / 255., source]
 }
 
 const GLYPH_REF_FLAG_NOT_EMPTY_BIT: u8 = 0b0000_0001;
 const GLYPH_REF_FLAG_COLORED_BIT: u8 = 0b0000_0010;
+const GLYPH_REF_FLAG_STRIKEOUT_BIT: u8 = 0b0010_0000;
+const GLYPH_REF_FLAG_CELL_BACKGROUND_BIT: u8 = 0b0100_0000;
+const GLYPH_REF_FLAG_OVERLINE_BIT: u8 = 0b1000_0000;
+
+// Underline style, stored in bits 2 to 4
+const GLYPH_REF_UNDERLINE: u8 = 0b0000_0100;
+const GLYPH_REF_DOUBLE_UNDERLINE: u8 = 0b0000_1000;
+const GLYPH_REF_UNDERCURL: u8 = 0b0000_1100;
+const GLYPH_REF_DOTTED_UNDERLINE: u8 = 0b0001_0000;
+const GLYPH_REF_DASHED_UNDERLINE: u8 = 0b0001_0100;
+
+// Bits of the glyph ref attribute byte
+const GLYPH_REF_ATTR_BLINK_BIT: u8 = 0b0000_0001;
+const GLYPH_REF_ATTR_HIDDEN_BIT: u8 = 0b0000_0010;
+
+/// Set the position, style and color uniforms of a cursor.
+fn cursor_uniform(u_cursor: GLint, u_color: GLint, cursor: Option<&CursorRef>) {
+    let visible_cursor = cursor.filter(|cursor| cursor.visible);
+    let style = match visible_cursor.map(|cursor| cursor.style) {
+        Some(CursorStyle::Block) => CURSOR_BLOCK,
+        Some(CursorStyle::Beam) => CURSOR_BEAM,
+        Some(CursorStyle::Underline) => CURSOR_UNDERLINE,
+        Some(CursorStyle::HollowBlock) => CURSOR_HOLLOW_BLOCK,
+        Some(CursorStyle::Hidden) | None => CURSOR_HIDDEN,
+    };
+
+    match visible_cursor {
+        Some(cursor) if style != CURSOR_HIDDEN => unsafe {
+            let [column, line] = cursor.cell;
+            let columns = if cursor.wide { 2. } else { 1. };
+            gl::Uniform4f(u_cursor, column, line, columns, style);
+            gl::Uniform3f(u_color, cursor.color[0], cursor.color[1], cursor.color[2]);
+        },
+        _ => unsafe {
+            gl::Uniform4f(u_cursor, -1., -1., 0., CURSOR_HIDDEN);
+            gl::Uniform3f(u_color, 0., 0., 0.);
+        },
+    }
+}
+
+// Highlight classes in the alpha channel of the foreground color
+const HIGHLIGHT_NONE: u8 = 0;
+const HIGHLIGHT_SELECTION: u8 = 1;
+const HIGHLIGHT_SEARCH_MATCH: u8 = 2;
 
-#[derive(Debug, Clone)]
+// Sources of the `u_highlight_fg` and `u_highlight_bg` colors
+const HIGHLIGHT_CELL_FOREGROUND: f32 = 0.;
+const HIGHLIGHT_CELL_BACKGROUND: f32 = 1.;
+const HIGHLIGHT_RGB: f32 = 2.;
+
+// Cursor styles of the `u_cursor` uniform
+const CURSOR_HIDDEN: f32 = 0.;
+const CURSOR_BLOCK: f32 = 1.;
+const CURSOR_BEAM: f32 = 2.;
+const CURSOR_UNDERLINE: f32 = 3.;
+const CURSOR_HOLLOW_BLOCK: f32 = 4.;
+
+// Flags set from cell attributes rather than from the glyph
+const GLYPH_REF_CELL_FLAGS_MASK: u8 = 0b1111_1100;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 struct GlyphRef {
     // Coordinates into grid atlas
     atlas_x: u8,
@@ -369,9 +911,12 @@ struct GlyphRef {
 
     // Features of this glyph
     flags: u8,
+
+    // Attributes of the cell which are animated by the shader, like blinking
+    attrs: u8,
 }
 
-const EMPTY_GLYPH_REF: GlyphRef = GlyphRef { atlas_x: 0, atlas_y: 0, flags: 0 };
+const EMPTY_GLYPH_REF: GlyphRef = GlyphRef { atlas_x: 0, atlas_y: 0, flags: 0, attrs: 0 };
 
 #[derive(Debug)]
 struct GridPass {
@@ -379,7 +924,7 @@ struct GridPass {
     atlas: GridAtlas,
 
     /// Screen worth of glyphs
-    glyphs: Vec<GlyphRef>,
+    glyphs: ScreenTexture<GlyphRef>,
 
     /// Whether this pass contains any data to render
     dirty: bool,
@@ -388,28 +933,30 @@ struct GridPass {
 impl GridPass {
     fn new(
         index: usize,
+        atlas_size: i32,
         columns: usize,
         lines: usize,
         cell_size: Vec2<i32>,
         cell_offset: Vec2<i32>,
     ) -> Self {
-        let cells = columns * lines;
+        let mut glyphs = ScreenTexture::new(PixelFormat::RGBA8);
+        glyphs.resize(columns, lines, EMPTY_GLYPH_REF);
+
         Self {
-            atlas: GridAtlas::new(index, cell_size, cell_offset),
-            glyphs: vec![EMPTY_GLYPH_REF; cells],
+            atlas: GridAtlas::new(index, atlas_size, cell_size, cell_offset),
+            glyphs,
             dirty: false,
         }
     }
 
     fn resize(&mut self, columns: usize, lines: usize) {
-        let cells = columns * lines;
-        self.glyphs.resize(cells, EMPTY_GLYPH_REF);
+        self.glyphs.resize(columns, lines, EMPTY_GLYPH_REF);
     }
 
     fn clear(&mut self) {
         // TODO Can avoid doing this memset if it's not dirty, but have to track whether it's been
         // cleared then
-        self.glyphs.iter_mut().for_each(|x| *x = EMPTY_GLYPH_REF);
+        self.glyphs.fill(EMPTY_GLYPH_REF);
         self.dirty = false;
     }
 }
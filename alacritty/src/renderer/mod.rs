@@ -1,32 +1,71 @@
 mod atlas;
+mod atlas_debug;
+mod background_image;
+mod bitmap_font;
+mod builtin_font;
+mod color_glyph;
+mod framebuffer;
+mod gl_api;
+mod graphics;
 mod grid;
 mod math;
+mod postprocess;
 mod quad;
+mod scale;
+mod screen_texture;
 mod shade;
 mod solidrect;
+mod stats;
+mod synthesis;
 mod texture;
 
-#[cfg(feature = "live-shader-reload")]
 mod filewatch;
 
 pub mod glyph;
 pub mod rects;
 
+use std::ffi::CStr;
+use std::mem;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::config::background_image::BackgroundImage as BackgroundImageConfig;
+use crate::config::font::Font;
+use crate::config::renderer::RendererConfig;
 use crate::config::ui_config::UIConfig;
-use crate::cursor;
+use crate::config::Config;
 use crate::gl;
-use alacritty_terminal::config::Cursor;
-use alacritty_terminal::index::{Column, Line};
+use crate::gl::types::*;
+use crate::glyph_inspector::{SheetLine, GLYPH_COLUMNS};
+use alacritty_terminal::ansi::NamedColor;
+use alacritty_terminal::config::{Colors, Cursor, LOG_TARGET_CONFIG};
+use alacritty_terminal::graphics::{GraphicData, GraphicId, RenderableGraphic};
+use alacritty_terminal::index::{Column, Line, Point};
 use alacritty_terminal::term::cell::{self, Flags};
-use alacritty_terminal::term::{self, color::Rgb, RenderableCell, RenderableCellContent, SizeInfo};
+use alacritty_terminal::term::color::{self, Rgb};
+use alacritty_terminal::term::marks::RenderableMark;
+use alacritty_terminal::term::{
+    self, CellHighlight, HighlightColors, RenderableCell, RenderableCellContent, SizeInfo,
+};
+use atlas::GlyphMove;
+use atlas_debug::{AtlasDebug, AtlasTexture};
+use background_image::BackgroundImage;
+use crossfont::Metrics;
+use framebuffer::Framebuffer;
 pub use glyph::GlyphCache;
-use glyph::{AtlasGlyph, GlyphKey, LoadGlyph, RasterizedGlyph};
+use glyph::{AtlasGlyph, GlyphKey, LoadGlyph, Presentation, RasterizedGlyph};
+use graphics::GraphicsRenderer;
 use grid::GridGlyphRenderer;
+use image::ImageResult;
 use math::*;
+use postprocess::PostProcess;
 use quad::{GlyphQuad, QuadGlyphRenderer};
-use rects::RenderRect;
+use rects::{RenderRect, MARK_TINT_ALPHA};
 use shade::ShaderCreationError;
 use solidrect::SolidRectRenderer;
+use stats::{GpuTimer, Pass};
+pub use stats::{PassStats, RenderStats};
+use unicode_width::UnicodeWidthChar;
 
 #[derive(Debug)]
 pub enum Error {
@@ -69,21 +108,94 @@ pub struct Renderer {
     // - characters too big for cell-based renderer
     quad_glyphs: QuadGlyphRenderer,
 
+    // Images placed on the grid by the terminal
+    graphics: GraphicsRenderer,
+
     // Solid-color rects
     solid_rects: SolidRectRenderer,
+
+    // Color palette, possibly in the middle of a cross-fade
+    palette: PaletteFade,
+
+    // Cursor position, possibly in the middle of moving to another cell
+    cursor_motion: CursorMotion,
+
+    // Visibility of text with the blink attribute
+    text_blink: TextBlink,
+
+    // User shader applied to the finished frame
+    post_process: Option<PostProcess>,
+
+    // User shader replacing the post-processing shader while the terminal is idle
+    idle_shader: Option<PostProcess>,
+    idle: bool,
+
+    // Offscreen target for frames which are scaled or post-processed before presenting them
+    scene: Option<Framebuffer>,
+
+    // Scaling of the presented frame while the cell size changes
+    zoom: CellZoom,
+
+    // Opacity of the default background in the current frame
+    background_opacity: f32,
+
+    // Skip quads and zero-width characters while output floods the terminal
+    degraded: bool,
+
+    // Debug views of the glyph atlases
+    atlas_debug: AtlasDebug,
+
+    // GPU time measurements of the render passes
+    timer: GpuTimer,
+
+    // Work submitted for the current and the previous frame
+    stats: RenderStats,
+    last_stats: RenderStats,
+
+    // Ratio between the rendering resolution and the window resolution
+    render_scale: f32,
+
+    // Window size and the scaled size frames are rendered at, in pixels
+    window_size: Vec2<i32>,
+    render_size: Vec2<i32>,
 }
 
 impl Renderer {
-    pub fn new() -> Result<Self, Error> {
+    pub fn new(config: &RendererConfig) -> Result<Self, Error> {
+        let mut max_texture_size: GLint = 0;
         unsafe {
             // Depth is irrelevant
             gl::DepthMask(gl::FALSE);
+
+            gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_texture_size);
         }
 
+        let grid_atlas_size =
+            atlas_size_limit("grid_atlas_size", config.grid_atlas_size, max_texture_size);
+        let atlas_size = atlas_size_limit("atlas_size", config.atlas_size, max_texture_size);
+
         Ok(Self {
-            grids: GridGlyphRenderer::new()?,
-            quad_glyphs: QuadGlyphRenderer::new(),
+            grids: GridGlyphRenderer::new(grid_atlas_size, config.max_grid_passes)?,
+            quad_glyphs: QuadGlyphRenderer::new(atlas_size),
+            graphics: GraphicsRenderer::new(),
             solid_rects: SolidRectRenderer::new()?,
+            palette: PaletteFade::new(color::List::from(&Colors::default())),
+            cursor_motion: CursorMotion::new(),
+            text_blink: TextBlink::new(),
+            post_process: None,
+            idle_shader: None,
+            idle: false,
+            scene: None,
+            zoom: CellZoom::new(),
+            background_opacity: 1.,
+            degraded: false,
+            atlas_debug: AtlasDebug::new(),
+            timer: GpuTimer::new(),
+            stats: RenderStats::default(),
+            last_stats: RenderStats::default(),
+            render_scale: 1.,
+            window_size: Vec2::new(0, 0),
+            render_size: Vec2::new(0, 0),
         })
     }
 
@@ -91,9 +203,14 @@ impl Renderer {
         &'a mut self,
         config: &'a UIConfig,
         cursor_config: Cursor,
+        cursor_visible: bool,
         size_info: &'a SizeInfo,
     ) -> RenderContext<'a> {
-        RenderContext { this: self, size_info, config, cursor_config }
+        self.text_blink.begin_frame(&config.renderer, cursor_config.blink_interval());
+        let (time, interval) = self.text_blink.shader_time();
+        self.grids.set_text_blink(time, interval);
+
+        RenderContext { this: self, size_info, config, cursor_config, cursor_visible }
     }
 
     pub fn with_loader<F, T>(&mut self, func: F) -> T
@@ -103,22 +220,79 @@ impl Renderer {
         func(LoaderApi { renderer: self })
     }
 
-    pub fn resize(&mut self, size_info: &term::SizeInfo) {
+    /// Rasterize all glyphs for a new font or device pixel ratio.
+    ///
+    /// The glyph atlases are rebuilt with the cell size of the new glyphs in the same step, so
+    /// glyphs are never placed with the cell size of the previous font. Nothing is rasterized if
+    /// the font and ratio did not change, like when the window moves between monitors with the
+    /// same scale factor.
+    ///
+    /// Returns `true` if the glyphs were rasterized again.
+    pub fn rescale(
+        &mut self,
+        glyph_cache: &mut GlyphCache,
+        config: &Config,
+        font: &Font,
+        dpr: f64,
+    ) -> Result<bool, crossfont::Error> {
+        self.with_loader(|mut api| glyph_cache.update_font_size(config, font, dpr, &mut api))
+    }
+
+    /// Resize for a new window size.
+    ///
+    /// The `size_info` is in window pixels, frames are rendered at `render_scale` times its size.
+    pub fn resize(&mut self, size_info: &term::SizeInfo, render_scale: f32) {
+        self.render_scale = render_scale;
+        self.window_size = Vec2::new(size_info.width() as i32, size_info.height() as i32);
+
+        let size_info = size_info.scaled(render_scale);
+        self.render_size = Vec2::new(size_info.width() as i32, size_info.height() as i32);
+        Self::set_viewport(&size_info);
+
+        self.grids.resize(&size_info);
+
+        if let Some(scene) = &mut self.scene {
+            scene.resize(self.render_size);
+        }
+    }
+
+    fn set_viewport(size_info: &term::SizeInfo) {
         unsafe {
             gl::Viewport(
-                size_info.padding_x() as i32,
+                size_info.padding_left() as i32,
                 size_info.padding_y() as i32,
-                size_info.width() as i32 - 2 * size_info.padding_x() as i32,
+                (size_info.width() - size_info.padding_left() - size_info.padding_x()) as i32,
                 size_info.height() as i32 - 2 * size_info.padding_y() as i32,
             );
         }
-
-        self.grids.resize(size_info);
     }
 
-    pub fn clear(&mut self, color: Rgb, background_opacity: f32) {
+    /// Clear all buffers for a new frame.
+    ///
+    /// Changes to the `palette` are faded in over `transition` instead of being applied
+    /// immediately.
+    pub fn clear(&mut self, palette: &color::List, background_opacity: f32, transition: Duration) {
+        if self.palette.set_target(palette, transition) {
+            self.grids.set_palette(&self.palette.from, &self.palette.to);
+        }
+
+        self.background_opacity = background_opacity;
         self.quad_glyphs.clear();
-        self.grids.clear(color, background_opacity);
+        self.grids.clear(self.palette.background(), background_opacity, self.palette.progress());
+
+        self.last_stats = mem::take(&mut self.stats);
+        self.timer.start_frame(&mut self.last_stats);
+
+        for shader in self.post_process.iter_mut().chain(self.idle_shader.iter_mut()) {
+            shader.poll();
+        }
+
+        // Render into the offscreen framebuffer when the frame can't go to the window directly.
+        if self.offscreen() {
+            let size = self.render_size;
+            let scene = self.scene.get_or_insert_with(|| Framebuffer::new(size));
+            scene.bind();
+        }
 
         unsafe {
             gl::ClearColor(0.0, 0.0, 0.0, 0.0);
@@ -126,6 +300,254 @@ impl Renderer {
         }
     }
 
+    /// Tell the renderer the grid content moved up by `lines` since the last frame.
+    ///
+    /// Rows which are still visible are then reused on the GPU instead of being uploaded again.
+    pub fn scroll(&mut self, lines: isize) {
+        self.grids.scroll(lines);
+    }
+
+    /// Set the colors of selected cells and search matches.
+    pub fn set_highlights(&mut self, selection: HighlightColors, search_match: HighlightColors) {
+        self.grids.set_highlights(selection, search_match);
+    }
+
+    /// Replace the image drawn behind the grid.
+    pub fn set_background_image(&mut self, config: Option<&BackgroundImageConfig>) {
+        let image = config.and_then(|config| match BackgroundImage::load(config) {
+            Ok(image) => Some(image),
+            Err(err) => {
+                error!(
+                    target: LOG_TARGET_CONFIG,
+                    "Unable to load background image {:?}: {}", config.path, err
+                );
+                None
+            },
+        });
+
+        self.grids.set_background_image(image);
+    }
+
+    /// Replace the user shader which is run over every finished frame.
+    pub fn set_post_process_shader(&mut self, path: Option<&Path>) {
+        Self::replace_shader(&mut self.post_process, path);
+    }
+
+    /// Replace the user shader which is run over every frame while the terminal is idle.
+    pub fn set_idle_shader(&mut self, path: Option<&Path>) {
+        Self::replace_shader(&mut self.idle_shader, path);
+    }
+
+    /// Compile the user shaders again, without waiting for changes to their files.
+    pub fn reload_shaders(&mut self) {
+        for shader in self.post_process.iter_mut().chain(self.idle_shader.iter_mut()) {
+            shader.reload();
+        }
+    }
+
+    fn replace_shader(shader: &mut Option<PostProcess>, path: Option<&Path>) {
+        match (path, &shader) {
+            (Some(path), Some(post_process)) if post_process.path() == path => (),
+            (Some(path), _) => *shader = Some(PostProcess::new(path)),
+            (None, _) => *shader = None,
+        }
+    }
+
+    /// Switch to the idle shader, if there is one, until the terminal is active again.
+    pub fn set_idle(&mut self, idle: bool) {
+        self.idle = idle;
+    }
+
+    /// User shader the next frame is presented through.
+    fn active_post_process(&self) -> Option<&PostProcess> {
+        let idle_shader = self.idle_shader.as_ref().filter(|shader| self.idle && shader.active());
+        idle_shader.or_else(|| self.post_process.as_ref().filter(|shader| shader.active()))
+    }
+
+    /// Whether frames are rendered into the offscreen framebuffer.
+    fn offscreen(&self) -> bool {
+        self.active_post_process().is_some()
+            || (self.render_scale - 1.).abs() > f32::EPSILON
+            || !self.zoom.completed()
+    }
+
+    /// Scale frames from the previous cell size to the current one over `duration`.
+    ///
+    /// The `ratio` is the previous cell size divided by the current one.
+    pub fn animate_cell_size(&mut self, ratio: f32, duration: Duration) {
+        self.zoom.start(ratio, duration);
+    }
+
+    /// Draw the following frames without quads and zero-width characters.
+    ///
+    /// This trades text rendering quality for frame time while the terminal is flooded.
+    pub fn set_degraded(&mut self, degraded: bool) {
+        self.degraded = degraded;
+    }
+
+    /// Present an offscreen frame in the window, running the user shader over it.
+    ///
+    /// The `size_info` is the scaled size the frame was rendered with. This is a no-op if the frame
+    /// was drawn into the window directly.
+    pub fn present(&mut self, size_info: &SizeInfo, cursor_point: Point) {
+        let scene = match &self.scene {
+            Some(scene) if self.offscreen() => scene,
+            _ => return,
+        };
+
+        let zoom = self.zoom.zoom();
+        match self.active_post_process() {
+            Some(post_process) => {
+                // Center of the cursor cell in window pixels, with the origin in the bottom left.
+                let x = size_info.padding_left()
+                    + (cursor_point.col.0 as f32 + 0.5) * size_info.cell_width();
+                let y = size_info.height()
+                    - size_info.padding_y()
+                    - (cursor_point.line.0 as f32 + 0.5) * size_info.cell_height();
+                let cursor = Vec2::new(x / self.render_scale, y / self.render_scale);
+
+                post_process.draw(scene.tex, self.window_size, cursor);
+            },
+            None => {
+                // Frames which are scaled down don't cover the entire window.
+                if zoom < 1. {
+                    let color = self.palette.background();
+                    unsafe {
+                        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                        gl::ClearColor(
+                            f32::from(color.r) / 255.,
+                            f32::from(color.g) / 255.,
+                            f32::from(color.b) / 255.,
+                            self.background_opacity,
+                        );
+                        gl::Clear(gl::COLOR_BUFFER_BIT);
+                    }
+                }
+
+                scene.blit(self.window_size, zoom);
+            },
+        }
+
+        self.zoom.presented(zoom);
+
+        Self::set_viewport(size_info);
+    }
+
+    /// Upload images added to the terminal and release the ones it no longer shows.
+    pub fn update_graphics(&mut self, pending: Vec<GraphicData>, removed: &[GraphicId]) {
+        if pending.is_empty() && removed.is_empty() {
+            return;
+        }
+
+        let stats = self.graphics.update(pending, removed);
+        self.stats.pass_mut(Pass::Graphics).add(stats);
+    }
+
+    /// Textures of all glyph atlases.
+    fn atlas_textures(&self) -> Vec<AtlasTexture> {
+        self.grids.atlas_textures().chain(self.quad_glyphs.atlas_textures()).collect()
+    }
+
+    /// Write all glyph atlases into PNG files in `dir`.
+    pub fn dump_atlases(&self, dir: &Path) {
+        match self.atlas_debug.dump(&self.atlas_textures(), dir) {
+            Ok(paths) => info!("Dumped glyph atlases to {:?}", paths),
+            Err(err) => error!("Unable to dump glyph atlases: {}", err),
+        }
+    }
+
+    /// Encode all glyph atlases as PNG files, for diagnostic bundles.
+    pub fn encode_atlases(&self) -> ImageResult<Vec<(String, Vec<u8>)>> {
+        self.atlas_debug.encode(&self.atlas_textures())
+    }
+
+    /// Encode the frame presented last as PNG.
+    ///
+    /// This must be called before the buffers are swapped.
+    pub fn screenshot(&self) -> ImageResult<Vec<u8>> {
+        self.screenshot_region(0, 0, self.window_size.x, self.window_size.y)
+    }
+
+    /// Encode a rectangle of the frame presented last as PNG.
+    ///
+    /// The origin is the top left corner of the rectangle in window pixels. This must be called
+    /// before the buffers are swapped.
+    pub fn screenshot_region(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    ) -> ImageResult<Vec<u8>> {
+        let size = Vec2::new(width, height);
+        let stride = size.x as usize * 4;
+        let mut pixels = vec![0u8; stride * size.y as usize];
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                x,
+                self.window_size.y - y - height,
+                size.x,
+                size.y,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut _,
+            );
+        }
+
+        // OpenGL rows start at the bottom of the window.
+        let flipped: Vec<u8> = pixels.chunks_exact(stride).rev().flatten().copied().collect();
+        atlas_debug::encode_png(&flipped, size)
+    }
+
+    /// Description of the OpenGL implementation, for bug reports.
+    pub fn gl_report(&self) -> String {
+        let string = |name| unsafe {
+            let ptr = gl::GetString(name);
+            if ptr.is_null() {
+                String::from("unknown")
+            } else {
+                CStr::from_ptr(ptr as *const _).to_string_lossy().into_owned()
+            }
+        };
+
+        let mut max_texture_size = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_texture_size);
+        }
+
+        format!(
+            "Vendor: {}\nRenderer: {}\nVersion: {}\nShading language: {}\nMax texture size: {}\n",
+            string(gl::VENDOR),
+            string(gl::RENDERER),
+            string(gl::VERSION),
+            string(gl::SHADING_LANGUAGE_VERSION),
+            max_texture_size,
+        )
+    }
+
+    /// Statistics of the previously rendered frame.
+    pub fn stats(&self) -> RenderStats {
+        self.last_stats
+    }
+
+    /// Time until blinking text on screen changes its visibility.
+    pub fn text_blink_timeout(&self) -> Option<Duration> {
+        self.text_blink.timeout()
+    }
+
+    /// Whether a color transition, cursor movement or post-processing shader needs more frames.
+    pub fn is_animating(&self) -> bool {
+        let idle_shader = self.idle && self.idle_shader.as_ref().map_or(false, PostProcess::active);
+        let timed_shader = self.active_post_process().map_or(false, PostProcess::uses_time);
+        !self.palette.completed()
+            || !self.cursor_motion.completed()
+            || !self.zoom.completed()
+            || idle_shader
+            || timed_shader
+    }
+
     #[cfg(not(any(target_os = "macos", windows)))]
     pub fn finish(&self) {
         unsafe {
@@ -134,6 +556,281 @@ impl Renderer {
     }
 }
 
+/// Opacity of the background of key overlay and scroll lock labels.
+const LABEL_ALPHA: f32 = 0.8;
+
+/// Opacity of the glyph inspector sheet, hiding almost all of the grid below it.
+const INSPECTOR_ALPHA: f32 = 0.95;
+
+/// Text of the label shown while new output is hidden below the viewport.
+const SCROLL_LOCK_LABEL: &str = "Scroll locked";
+
+/// Title line of the clipboard history chooser.
+const CLIPBOARD_CHOOSER_TITLE: &str = "Paste from history";
+
+/// Smallest supported atlas texture size.
+const MIN_ATLAS_SIZE: i32 = 256;
+
+/// Line shown at the vertical pixel position `y` of the minimap, relative to the top of the screen.
+///
+/// Lines in the history above the screen are negative.
+pub fn minimap_line(size_info: &SizeInfo, y: usize) -> isize {
+    let lines = size_info.visible_lines().0;
+    let rows = screen_texture::ring_rows(lines);
+    let row_height = lines as f32 * size_info.cell_height() / rows as f32;
+    let row = ((y as f32 - size_info.padding_y()) / row_height).floor() as isize;
+    row - (rows - lines) as isize
+}
+
+/// Clamp the configured size of an atlas texture to what the GPU supports.
+fn atlas_size_limit(name: &str, size: i32, max_texture_size: GLint) -> i32 {
+    let limited = size.max(MIN_ATLAS_SIZE).min(max_texture_size.max(MIN_ATLAS_SIZE));
+    if limited != size {
+        error!(
+            target: LOG_TARGET_CONFIG,
+            "Problem with config: renderer.{} must be between {} and {}; using {}",
+            name,
+            MIN_ATLAS_SIZE,
+            max_texture_size,
+            limited
+        );
+    }
+
+    limited
+}
+
+/// Cross-fade between two color palettes.
+///
+/// Only the default background is blended on the CPU, all other colors are interpolated by the
+/// grid shader.
+#[derive(Debug)]
+struct PaletteFade {
+    from: color::List,
+    to: color::List,
+    start: Instant,
+    duration: Duration,
+}
+
+impl PaletteFade {
+    fn new(palette: color::List) -> Self {
+        Self { from: palette, to: palette, start: Instant::now(), duration: Duration::default() }
+    }
+
+    /// Start fading towards `palette`, beginning with the currently visible colors.
+    ///
+    /// Returns `true` if the target palette has changed.
+    fn set_target(&mut self, palette: &color::List, duration: Duration) -> bool {
+        if *palette == self.to {
+            return false;
+        }
+
+        self.from = self.palette();
+        self.to = *palette;
+        self.start = Instant::now();
+        self.duration = duration;
+
+        true
+    }
+
+    /// Palette at the current stage of the transition.
+    fn palette(&self) -> color::List {
+        let progress = self.progress();
+        let mut palette = self.to;
+        for index in 0..color::COUNT {
+            palette[index] = self.from[index] * (1. - progress) + self.to[index] * progress;
+        }
+        palette
+    }
+
+    /// Default background color at the current stage of the transition.
+    fn background(&self) -> Rgb {
+        let progress = self.progress();
+        self.from[NamedColor::Background] * (1. - progress)
+            + self.to[NamedColor::Background] * progress
+    }
+
+    fn completed(&self) -> bool {
+        self.progress() >= 1.
+    }
+
+    fn progress(&self) -> f32 {
+        if self.duration == Duration::default() {
+            return 1.;
+        }
+
+        (self.start.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.)
+    }
+}
+
+/// Eased movement of the cursor from one cell to another.
+#[derive(Debug)]
+struct CursorMotion {
+    from: Vec2<f32>,
+    to: Option<(Line, Column)>,
+    start: Instant,
+    duration: Duration,
+}
+
+impl CursorMotion {
+    fn new() -> Self {
+        let from = Vec2::new(0., 0.);
+        Self { from, to: None, start: Instant::now(), duration: Duration::default() }
+    }
+
+    /// Start moving towards the cell at `line` and `column`, beginning at the current position.
+    ///
+    /// Returns the position of the cursor in the current frame, in cells.
+    fn move_to(&mut self, line: Line, column: Column, duration: Duration) -> Vec2<f32> {
+        match self.to {
+            Some(to) if to == (line, column) => (),
+            // The first position is not animated.
+            None => {
+                self.from = Vec2::new(column.0 as f32, line.0 as f32);
+                self.to = Some((line, column));
+            },
+            Some(_) => {
+                self.from = self.position();
+                self.to = Some((line, column));
+                self.start = Instant::now();
+                self.duration = duration;
+            },
+        }
+
+        self.position()
+    }
+
+    /// Cursor position at the current stage of the movement, in cells.
+    fn position(&self) -> Vec2<f32> {
+        let to = match self.to {
+            Some((line, column)) => Vec2::new(column.0 as f32, line.0 as f32),
+            None => self.from,
+        };
+
+        // Ease out, so the cursor slows down towards its target.
+        let eased = 1. - (1. - self.progress()).powi(3);
+        self.from + (to - self.from) * Vec2::from(eased)
+    }
+
+    fn completed(&self) -> bool {
+        self.progress() >= 1.
+    }
+
+    fn progress(&self) -> f32 {
+        if self.duration == Duration::default() {
+            return 1.;
+        }
+
+        (self.start.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.)
+    }
+}
+
+/// Eased scaling of frames from the previous cell size towards the current one.
+#[derive(Debug)]
+struct CellZoom {
+    from: f32,
+    start: Instant,
+    duration: Duration,
+
+    /// Scale of the frame presented last, the animation ends once it is unscaled.
+    presented: f32,
+}
+
+impl CellZoom {
+    fn new() -> Self {
+        Self { from: 1., start: Instant::now(), duration: Duration::default(), presented: 1. }
+    }
+
+    /// Start scaling from `ratio` times the current cell size, continuing any running animation.
+    fn start(&mut self, ratio: f32, duration: Duration) {
+        self.from = self.zoom() * ratio;
+        self.start = Instant::now();
+        self.duration = duration;
+        self.presented = self.from;
+    }
+
+    /// Scale of the frame at the current stage of the animation.
+    fn zoom(&self) -> f32 {
+        // Ease out, so the change slows down towards the final size.
+        let eased = 1. - (1. - self.progress()).powi(3);
+        self.from + (1. - self.from) * eased
+    }
+
+    fn presented(&mut self, zoom: f32) {
+        self.presented = zoom;
+    }
+
+    fn completed(&self) -> bool {
+        self.progress() >= 1. && (self.presented - 1.).abs() < f32::EPSILON
+    }
+
+    fn progress(&self) -> f32 {
+        if self.duration == Duration::default() {
+            return 1.;
+        }
+
+        (self.start.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.)
+    }
+}
+
+/// Alternating visible and hidden phases of text with the blink attribute.
+#[derive(Debug)]
+struct TextBlink {
+    /// Start of the first visible phase.
+    epoch: Instant,
+
+    /// Duration of each phase, `None` while text does not blink.
+    interval: Option<Duration>,
+
+    /// Whether blinking text is hidden in the current frame.
+    hidden: bool,
+
+    /// Number of blinking cells in the current frame.
+    cells: usize,
+}
+
+impl TextBlink {
+    fn new() -> Self {
+        Self { epoch: Instant::now(), interval: None, hidden: false, cells: 0 }
+    }
+
+    /// Decide whether text blinks in the next frame.
+    ///
+    /// Blinking stops while the previous frame had more than `max_blinking_cells`, so large
+    /// blinking areas do not cause a redraw of the whole screen every interval.
+    fn begin_frame(&mut self, config: &RendererConfig, interval: Duration) {
+        let cells = mem::replace(&mut self.cells, 0);
+        self.interval = if config.text_blinking && cells <= config.max_blinking_cells {
+            Some(interval)
+        } else {
+            None
+        };
+        self.hidden = self.interval.map_or(false, |interval| self.cycle_time(interval) >= interval);
+    }
+
+    /// Time into the current pair of visible and hidden phases.
+    fn cycle_time(&self, interval: Duration) -> Duration {
+        let cycle = 2 * interval.as_millis().max(1);
+        Duration::from_millis((self.epoch.elapsed().as_millis() % cycle) as u64)
+    }
+
+    /// Time into the current cycle and the phase duration in seconds, for the grid shader.
+    ///
+    /// The time wraps around every cycle, to avoid precision issues with `mediump` floats.
+    fn shader_time(&self) -> (f32, f32) {
+        match self.interval {
+            Some(interval) => (self.cycle_time(interval).as_secs_f32(), interval.as_secs_f32()),
+            None => (0., 0.),
+        }
+    }
+
+    /// Time until the next phase starts, if there is any blinking text.
+    fn timeout(&self) -> Option<Duration> {
+        let interval = self.interval.filter(|_| self.cells > 0)?;
+        let time = self.cycle_time(interval);
+        Some(if time < interval { interval - time } else { 2 * interval - time })
+    }
+}
+
 impl LoadGlyph for Renderer {
     fn load_glyph(&mut self, rasterized: &RasterizedGlyph) -> AtlasGlyph {
         match self.grids.load_glyph(rasterized) {
@@ -142,10 +839,21 @@ impl LoadGlyph for Renderer {
         }
     }
 
+    fn unload_glyph(&mut self, glyph: &AtlasGlyph) {
+        // Quad atlases are packed tightly, so their space is only reclaimed once they are cleared.
+        if let AtlasGlyph::Grid(glyph) = glyph {
+            self.grids.unload_glyph(glyph);
+        }
+    }
+
     fn clear(&mut self, cell_size: Vec2<i32>, cell_offset: Vec2<i32>) {
         self.grids.clear_atlas(cell_size, cell_offset);
         self.quad_glyphs.clear_atlas();
     }
+
+    fn defragment(&mut self) -> Vec<GlyphMove> {
+        self.grids.defragment()
+    }
 }
 
 #[derive(Debug)]
@@ -154,6 +862,9 @@ pub struct RenderContext<'a> {
     size_info: &'a term::SizeInfo,
     config: &'a UIConfig,
     cursor_config: Cursor,
+
+    /// Whether the cursor is in the visible phase of its blinking.
+    cursor_visible: bool,
 }
 
 impl<'a> RenderContext<'a> {
@@ -184,6 +895,10 @@ impl<'a> RenderContext<'a> {
                 bg_alpha,
                 fg,
                 bg: bg.unwrap_or(Rgb { r: 0, g: 0, b: 0 }),
+                fg_index: None,
+                bg_index: None,
+                highlight: CellHighlight::None,
+                hyperlink: 0,
             })
             .collect::<Vec<_>>();
 
@@ -192,6 +907,24 @@ impl<'a> RenderContext<'a> {
         }
     }
 
+    /// Draw the terminal cursor below the vi mode cursor.
+    ///
+    /// Unlike the vi mode cursor, it is neither animated nor blinking.
+    pub fn update_terminal_cursor(&mut self, cell: RenderableCell) {
+        let cursor_key = match cell.inner {
+            RenderableCellContent::Cursor(cursor_key) => cursor_key,
+            RenderableCellContent::Chars(_) => return,
+        };
+
+        let mut thickness = self.cursor_config.thickness() as f32 * self.size_info.cell_width();
+        if self.config.renderer.integer_scaling {
+            thickness = thickness.round().max(1.);
+        }
+
+        let position = Vec2::new(cell.column.0 as f32, cell.line.0 as f32);
+        self.this.grids.set_terminal_cursor(position, cursor_key, thickness, cell.fg);
+    }
+
     pub fn update_cell(&mut self, cell: RenderableCell, glyph_cache: &mut GlyphCache) {
         let wide = match cell.flags & Flags::WIDE_CHAR {
             Flags::WIDE_CHAR => true,
@@ -200,51 +933,28 @@ impl<'a> RenderContext<'a> {
 
         match cell.inner {
             RenderableCellContent::Cursor(cursor_key) => {
-                // Raw cell pixel buffers like cursors don't need to go through font lookup.
-                let metrics = glyph_cache.metrics;
-                let glyph = glyph_cache.cursor_cache.entry(cursor_key).or_insert_with(|| {
-                    self.load_glyph(&RasterizedGlyph {
-                        wide,
-                        zero_width: false,
-                        rasterized: cursor::get_cursor_glyph(
-                            cursor_key.style,
-                            metrics,
-                            self.config.font.offset.x,
-                            self.config.font.offset.y,
-                            cursor_key.is_wide,
-                            self.cursor_config.thickness(),
-                        ),
-                    })
-                });
-
-                match glyph {
-                    AtlasGlyph::Grid(glyph_grid) => {
-                        self.this.grids.set_cursor(
-                            glyph_grid.atlas_index,
-                            cell.column.0 as i32,
-                            cell.line.0 as i32,
-                            glyph_grid.column as f32,
-                            glyph_grid.line as f32,
-                            cell.fg,
-                        );
-                    },
+                // Animated cursors pass through fractional positions.
+                let duration = if self.config.renderer.integer_scaling {
+                    Duration::default()
+                } else {
+                    self.config.animations.duration(self.cursor_config.animation_duration())
+                };
+                let position = self.this.cursor_motion.move_to(cell.line, cell.column, duration);
 
-                    AtlasGlyph::Quad(quad) => {
-                        let glyph_quad = GlyphQuad {
-                            glyph: quad,
-                            pos: Vec2::<i16> {
-                                x: cell.column.0 as i16 * self.size_info.cell_width() as i16,
-                                y: cell.line.0 as i16 * self.size_info.cell_height() as i16,
-                            },
-                            fg: cell.fg,
-                        };
-
-                        self.this.quad_glyphs.add_to_render(self.size_info, &glyph_quad);
-                    },
+                let mut thickness =
+                    self.cursor_config.thickness() as f32 * self.size_info.cell_width();
+                if self.config.renderer.integer_scaling {
+                    thickness = thickness.round().max(1.);
                 }
+                let visible = self.cursor_visible;
+                self.this.grids.set_cursor(position, cursor_key, thickness, cell.fg, visible);
             },
 
             RenderableCellContent::Chars(chars) => {
+                if cell.flags.contains(Flags::BLINK) {
+                    self.this.text_blink.cells += 1;
+                }
+
                 // Get font key for cell.
                 let font_key = match cell.flags & Flags::BOLD_ITALIC {
                     Flags::BOLD_ITALIC => glyph_cache.bold_italic_key,
@@ -271,6 +981,7 @@ impl<'a> RenderContext<'a> {
                     GlyphKey {
                         wide,
                         zero_width: false,
+                        presentation: Presentation::from_zerowidth(&chars[1..]),
                         key: crossfont::GlyphKey {
                             font_key,
                             size: glyph_cache.font_size,
@@ -283,16 +994,25 @@ impl<'a> RenderContext<'a> {
                 );
 
                 // Render zero-width characters.
-                for c in (&chars[1..]).iter().filter(|c| **c != ' ') {
+                if self.this.degraded {
+                    return;
+                }
+
+                // Glyphs are not shaped, so emoji sequences are drawn as their first emoji.
+                let zero_width = chars[1..].iter().take_while(|&&c| c != cell::ZERO_WIDTH_JOINER);
+                let drawn = |c: &&char| {
+                    **c != ' '
+                        && **c != cell::TEXT_PRESENTATION_SELECTOR
+                        && **c != cell::EMOJI_PRESENTATION_SELECTOR
+                        && !cell::is_emoji_modifier(**c)
+                };
+                for &c in zero_width.filter(drawn) {
                     self.push_char(
                         GlyphKey {
                             wide,
                             zero_width: true,
-                            key: crossfont::GlyphKey {
-                                font_key,
-                                size: glyph_cache.font_size,
-                                c: *c,
-                            },
+                            presentation: Presentation::Default,
+                            key: crossfont::GlyphKey { font_key, size: glyph_cache.font_size, c },
                         },
                         &cell,
                         glyph_cache,
@@ -311,27 +1031,36 @@ impl<'a> RenderContext<'a> {
         zero_width: bool,
     ) {
         let glyph = glyph_cache.get(glyph_key, self);
+        let blink_hidden = cell.flags.contains(Flags::BLINK) && self.this.text_blink.hidden;
 
         match glyph {
             AtlasGlyph::Grid(grid_glyph) => {
                 self.this.grids.update_cell(cell, grid_glyph);
             },
+            // Blinking grid glyphs are hidden by the shader, quads are just not drawn.
+            AtlasGlyph::Quad(_) if blink_hidden => (),
+            AtlasGlyph::Quad(_) if self.this.degraded => (),
             AtlasGlyph::Quad(quad_glyph) => {
+                // Quads have no access to the highlight classes of the grid shader.
+                let mut cell = *cell;
+                let highlight = self.this.grids.highlight_colors(cell.highlight);
+                cell.resolve_highlight(highlight, &self.this.palette.to);
+
                 let glyph_quad = GlyphQuad {
                     glyph: quad_glyph,
-                    pos: Vec2::<i16> {
+                    pos: Vec2::<f32> {
                         x: (if zero_width {
                             // The metrics of zero-width characters are based on rendering
                             // the character after the current cell, with the anchor at the
                             // right side of the preceding character. Since we render the
                             // zero-width characters inside the preceding character, the
                             // anchor has been moved to the right by one cell.
-                            1
+                            1.
                         } else {
-                            0
-                        } + cell.column.0 as i16)
-                            * self.size_info.cell_width() as i16,
-                        y: cell.line.0 as i16 * self.size_info.cell_height() as i16,
+                            0.
+                        } + cell.column.0 as f32)
+                            * self.size_info.cell_width(),
+                        y: cell.line.0 as f32 * self.size_info.cell_height(),
                     },
                     fg: cell.fg,
                 };
@@ -356,13 +1085,362 @@ impl<'a> RenderContext<'a> {
 
     /// Draw all rectangles simultaneously to prevent excessive program swaps.
     pub fn draw_rects(&mut self, rects: Vec<RenderRect>) {
-        self.this.solid_rects.draw(self.size_info, rects);
+        self.begin_pass(Pass::Rects);
+        let stats = self.this.solid_rects.draw(self.size_info, rects);
+        self.end_pass(Pass::Rects, stats);
+    }
+
+    /// Draw the labels of the key overlay stacked upwards from the bottom right corner of the grid.
+    ///
+    /// The labels are drawn on top of everything else, with the newest one at the bottom. Each
+    /// label is faded out by its opacity.
+    pub fn draw_key_overlay(
+        &mut self,
+        glyph_cache: &mut GlyphCache,
+        labels: &[(String, f32)],
+        fg: Rgb,
+        bg: Rgb,
+    ) {
+        let cell_width = self.size_info.cell_width();
+        let cell_height = self.size_info.cell_height();
+        let padding = (cell_height / 4.).round();
+        let right = self.size_info.cols().0 as f32 * cell_width - padding;
+        let mut bottom = self.size_info.screen_lines().0 as f32 * cell_height - padding;
+
+        // The quads of the text have already been drawn, so they can be replaced by the labels.
+        self.this.quad_glyphs.clear();
+
+        let mut rects = Vec::new();
+        for (label, opacity) in labels.iter().rev() {
+            let columns: usize = label.chars().map(|c| c.width().unwrap_or(1)).sum();
+            let width = columns as f32 * cell_width + 2. * padding;
+            let height = cell_height + 2. * padding;
+            let (x, y) = (right - width, bottom - height);
+            if x < 0. || y < 0. {
+                break;
+            }
+
+            rects.push(RenderRect::new(
+                x + self.size_info.padding_left(),
+                y + self.size_info.padding_y(),
+                width,
+                height,
+                bg,
+                LABEL_ALPHA * opacity,
+            ));
+
+            // Quads have no alpha, so the text is faded towards the background instead.
+            let fg = fg * *opacity + bg * (1. - opacity);
+            self.push_label(glyph_cache, label, Vec2::new(x + padding, y + padding), fg);
+
+            bottom = y - padding;
+        }
+
+        self.draw_rects(rects);
+
+        self.begin_pass(Pass::Quad);
+        let stats = self.this.quad_glyphs.draw(self.size_info);
+        self.end_pass(Pass::Quad, stats);
+    }
+
+    /// Draw hint labels on top of the first cells of their targets.
+    pub fn draw_hint_labels(
+        &mut self,
+        glyph_cache: &mut GlyphCache,
+        labels: &[(Point, String)],
+        fg: Rgb,
+        bg: Rgb,
+    ) {
+        let cell_width = self.size_info.cell_width();
+        let cell_height = self.size_info.cell_height();
+
+        // The quads of the text have already been drawn, so they can be replaced by the labels.
+        self.this.quad_glyphs.clear();
+
+        let mut rects = Vec::new();
+        for (point, label) in labels {
+            let columns: usize = label.chars().map(|c| c.width().unwrap_or(1)).sum();
+            let x = point.col.0 as f32 * cell_width;
+            let y = point.line.0 as f32 * cell_height;
+
+            rects.push(RenderRect::new(
+                x + self.size_info.padding_left(),
+                y + self.size_info.padding_y(),
+                columns as f32 * cell_width,
+                cell_height,
+                bg,
+                1.,
+            ));
+            self.push_label(glyph_cache, label, Vec2::new(x, y), fg);
+        }
+
+        self.draw_rects(rects);
+
+        self.begin_pass(Pass::Quad);
+        let stats = self.this.quad_glyphs.draw(self.size_info);
+        self.end_pass(Pass::Quad, stats);
+    }
+
+    /// Draw a label centered at the bottom of the grid, telling that new output is hidden below
+    /// the viewport.
+    pub fn draw_scroll_lock(&mut self, glyph_cache: &mut GlyphCache, fg: Rgb, bg: Rgb) {
+        let cell_width = self.size_info.cell_width();
+        let cell_height = self.size_info.cell_height();
+        let padding = (cell_height / 4.).round();
+        let grid_width = self.size_info.cols().0 as f32 * cell_width;
+        let grid_height = self.size_info.screen_lines().0 as f32 * cell_height;
+
+        let width = SCROLL_LOCK_LABEL.len() as f32 * cell_width + 2. * padding;
+        let height = cell_height + 2. * padding;
+        let (x, y) = (((grid_width - width) / 2.).round(), grid_height - height - padding);
+        if x < 0. || y < 0. {
+            return;
+        }
+
+        // The quads of the text have already been drawn, so they can be replaced by the label.
+        self.this.quad_glyphs.clear();
+
+        let rect = RenderRect::new(
+            x + self.size_info.padding_left(),
+            y + self.size_info.padding_y(),
+            width,
+            height,
+            bg,
+            LABEL_ALPHA,
+        );
+        self.push_label(glyph_cache, SCROLL_LOCK_LABEL, Vec2::new(x + padding, y + padding), fg);
+        self.draw_rects(vec![rect]);
+
+        self.begin_pass(Pass::Quad);
+        let stats = self.this.quad_glyphs.draw(self.size_info);
+        self.end_pass(Pass::Quad, stats);
+    }
+
+    /// Queue the glyphs of a single line of text starting at `pos`, in pixels relative to the grid.
+    fn push_label(
+        &mut self,
+        glyph_cache: &mut GlyphCache,
+        label: &str,
+        mut pos: Vec2<f32>,
+        fg: Rgb,
+    ) {
+        let cell_width = self.size_info.cell_width();
+        for c in label.chars() {
+            let key = crossfont::GlyphKey {
+                font_key: glyph_cache.font_key,
+                size: glyph_cache.font_size,
+                c,
+            };
+            self.push_glyph(glyph_cache, key, pos, fg);
+
+            pos.x += c.width().unwrap_or(1) as f32 * cell_width;
+        }
+    }
+
+    /// Add a glyph at any position to the quads.
+    fn push_glyph(
+        &mut self,
+        glyph_cache: &mut GlyphCache,
+        key: crossfont::GlyphKey,
+        pos: Vec2<f32>,
+        fg: Rgb,
+    ) {
+        // Wide glyphs are always loaded into the quad atlases, which allows drawing them at any
+        // position.
+        let glyph_key =
+            GlyphKey { wide: true, zero_width: false, presentation: Presentation::Default, key };
+
+        if let AtlasGlyph::Quad(glyph) = *glyph_cache.get(glyph_key, self) {
+            let glyph_quad = GlyphQuad { glyph: &glyph, pos, fg };
+            self.this.quad_glyphs.add_to_render(self.size_info, &glyph_quad);
+        }
+    }
+
+    /// Draw the glyph inspector sheet over the grid.
+    pub fn draw_glyph_inspector(
+        &mut self,
+        glyph_cache: &mut GlyphCache,
+        lines: &[SheetLine],
+        fg: Rgb,
+        bg: Rgb,
+    ) {
+        let cell_width = self.size_info.cell_width();
+        let cell_height = self.size_info.cell_height();
+
+        // The quads of the text have already been drawn, so they can be replaced by the sheet.
+        self.this.quad_glyphs.clear();
+
+        let sheet = RenderRect::new(
+            self.size_info.padding_left(),
+            self.size_info.padding_y(),
+            self.size_info.cols().0 as f32 * cell_width,
+            self.size_info.screen_lines().0 as f32 * cell_height,
+            bg,
+            INSPECTOR_ALPHA,
+        );
+
+        for (i, line) in lines.iter().enumerate() {
+            let y = i as f32 * cell_height;
+            if let Some(key) = line.glyph {
+                self.push_glyph(glyph_cache, key, Vec2::new(0., y), fg);
+            }
+
+            let x = GLYPH_COLUMNS as f32 * cell_width;
+            self.push_label(glyph_cache, &line.text, Vec2::new(x, y), fg);
+        }
+
+        self.draw_rects(vec![sheet]);
+
+        self.begin_pass(Pass::Quad);
+        let stats = self.this.quad_glyphs.draw(self.size_info);
+        self.end_pass(Pass::Quad, stats);
+    }
+
+    /// Draw the clipboard history chooser at the top of the grid, highlighting the `selected`
+    /// entry.
+    pub fn draw_clipboard_chooser(
+        &mut self,
+        glyph_cache: &mut GlyphCache,
+        entries: &[String],
+        selected: usize,
+        fg: Rgb,
+        bg: Rgb,
+    ) {
+        let cell_width = self.size_info.cell_width();
+        let cell_height = self.size_info.cell_height();
+        let width = self.size_info.cols().0 as f32 * cell_width;
+
+        // Keep the selected entry visible below the title line.
+        let visible = self.size_info.screen_lines().0.saturating_sub(1).max(1);
+        let first = (selected + 1).saturating_sub(visible);
+        let shown = entries.len().min(first + visible) - first;
+
+        // The quads of the text have already been drawn, so they can be replaced by the chooser.
+        self.this.quad_glyphs.clear();
+
+        let (x, y) = (self.size_info.padding_left(), self.size_info.padding_y());
+        let height = (shown + 1) as f32 * cell_height;
+        let mut rects = vec![RenderRect::new(x, y, width, height, bg, INSPECTOR_ALPHA)];
+        self.push_label(glyph_cache, CLIPBOARD_CHOOSER_TITLE, Vec2::new(cell_width, 0.), fg);
+
+        for (i, entry) in entries.iter().enumerate().skip(first).take(shown) {
+            let line_y = (i - first + 1) as f32 * cell_height;
+            let pos = Vec2::new(cell_width, line_y);
+            if i == selected {
+                rects.push(RenderRect::new(x, y + line_y, width, cell_height, fg, 1.));
+                self.push_label(glyph_cache, entry, pos, bg);
+            } else {
+                self.push_label(glyph_cache, entry, pos, fg);
+            }
+        }
+
+        self.draw_rects(rects);
+
+        self.begin_pass(Pass::Quad);
+        let stats = self.this.quad_glyphs.draw(self.size_info);
+        self.end_pass(Pass::Quad, stats);
+    }
+
+    /// Draw the icons of marks into the gutter, right-aligned next to their line.
+    ///
+    /// The gutter cells of lines with a tinted mark are tinted as well.
+    pub fn draw_gutter(&mut self, glyph_cache: &mut GlyphCache, marks: &[RenderableMark], fg: Rgb) {
+        let cell_width = self.size_info.cell_width();
+        let cell_height = self.size_info.cell_height();
+        let gutter = self.size_info.gutter();
+
+        // Quads are positioned relative to the left edge of the gutter.
+        let size_info = self.size_info.including_gutter();
+
+        // The quads of the text have already been drawn, so they can be replaced by the icons.
+        self.this.quad_glyphs.clear();
+
+        let mut rects = Vec::new();
+        for RenderableMark { line, mark } in marks {
+            let y = line.0 as f32 * cell_height;
+
+            if let Some(tint) = mark.tint {
+                let x = self.size_info.padding_x();
+                let y = y + self.size_info.padding_y();
+                rects.push(RenderRect::new(x, y, gutter, cell_height, tint, MARK_TINT_ALPHA));
+            }
+
+            let icon = match mark.icon {
+                Some(icon) => icon,
+                None => continue,
+            };
+
+            let width = icon.width().unwrap_or(1) as f32 * cell_width;
+            let glyph_key = GlyphKey {
+                wide: true,
+                zero_width: false,
+                presentation: Presentation::Default,
+                key: crossfont::GlyphKey {
+                    font_key: glyph_cache.font_key,
+                    size: glyph_cache.font_size,
+                    c: icon,
+                },
+            };
+
+            if let AtlasGlyph::Quad(glyph) = *glyph_cache.get(glyph_key, self) {
+                let pos = Vec2::new((gutter - width).max(0.), y);
+                let glyph_quad = GlyphQuad { glyph: &glyph, pos, fg };
+                self.this.quad_glyphs.add_to_render(&size_info, &glyph_quad);
+            }
+        }
+
+        self.draw_rects(rects);
+
+        self.begin_pass(Pass::Quad);
+        let stats = self.this.quad_glyphs.draw(&size_info);
+        self.end_pass(Pass::Quad, stats);
+    }
+
+    /// Draw the images visible in the viewport below the text.
+    ///
+    /// This must be called before the text is drawn, which composites them onto the cell
+    /// backgrounds.
+    pub fn draw_graphics_underlay(&mut self, graphics: &[RenderableGraphic]) {
+        self.begin_pass(Pass::Graphics);
+        let (stats, underlay) = self.this.graphics.draw_underlay(self.size_info, graphics);
+        self.this.grids.set_underlay(underlay);
+        self.end_pass(Pass::Graphics, stats);
     }
 
-    /// Perform drawing of all text in the correct order.
-    pub fn draw_text(&mut self) {
-        self.this.grids.draw(self.size_info);
-        self.this.quad_glyphs.draw(self.size_info);
+    /// Draw the images visible in the viewport on top of the text.
+    pub fn draw_graphics(&mut self, graphics: &[RenderableGraphic]) {
+        self.begin_pass(Pass::Graphics);
+        let stats = self.this.graphics.draw(self.size_info, graphics);
+        self.end_pass(Pass::Graphics, stats);
+    }
+
+    /// Draw the contents of all glyph atlases in the bottom right corner.
+    pub fn draw_atlas_overlay(&mut self) {
+        let frame_size = Vec2::new(self.size_info.width() as i32, self.size_info.height() as i32);
+        self.this.atlas_debug.draw_overlay(&self.this.atlas_textures(), frame_size);
+    }
+
+    /// Perform drawing of all text and its decorations in the correct order.
+    pub fn draw_text(&mut self, metrics: &Metrics) {
+        self.begin_pass(Pass::Grid);
+        let stats = self.this.grids.draw(self.size_info, metrics);
+        self.end_pass(Pass::Grid, stats);
+
+        self.begin_pass(Pass::Quad);
+        let stats = self.this.quad_glyphs.draw(self.size_info);
+        self.end_pass(Pass::Quad, stats);
+    }
+
+    /// Start measuring the GPU time of a pass, if the render timer is visible.
+    fn begin_pass(&mut self, pass: Pass) {
+        if self.config.debug.render_timer {
+            self.this.timer.begin(pass);
+        }
+    }
+
+    fn end_pass(&mut self, pass: Pass, stats: PassStats) {
+        self.this.timer.end();
+        self.this.stats.pass_mut(pass).add(stats);
     }
 }
 
@@ -371,9 +1449,17 @@ impl<'a> LoadGlyph for RenderContext<'a> {
         self.this.load_glyph(rasterized)
     }
 
+    fn unload_glyph(&mut self, glyph: &AtlasGlyph) {
+        self.this.unload_glyph(glyph);
+    }
+
     fn clear(&mut self, cell_size: Vec2<i32>, cell_offset: Vec2<i32>) {
         LoadGlyph::clear(self.this, cell_size, cell_offset);
     }
+
+    fn defragment(&mut self) -> Vec<GlyphMove> {
+        self.this.defragment()
+    }
 }
 
 #[derive(Debug)]
@@ -386,7 +1472,15 @@ impl<'a> LoadGlyph for LoaderApi<'a> {
         self.renderer.load_glyph(rasterized)
     }
 
+    fn unload_glyph(&mut self, glyph: &AtlasGlyph) {
+        self.renderer.unload_glyph(glyph);
+    }
+
     fn clear(&mut self, cell_size: Vec2<i32>, cell_offset: Vec2<i32>) {
         LoadGlyph::clear(self.renderer, cell_size, cell_offset);
     }
+
+    fn defragment(&mut self) -> Vec<GlyphMove> {
+        self.renderer.defragment()
+    }
 }
@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::time::Instant;
+
+use log::{error, info};
+
+use alacritty_terminal::config::LOG_TARGET_CONFIG;
+
+use super::filewatch;
+use super::math::Vec2;
+use super::shade::PostProcessShaderProgram;
+use crate::gl;
+use crate::gl::types::*;
+
+/// Runs a user shader over the rendered frame.
+#[derive(Debug)]
+pub struct PostProcess {
+    path: PathBuf,
+    file: filewatch::File,
+
+    /// Last successfully compiled shader, kept while the file contains errors.
+    program: Option<PostProcessShaderProgram>,
+
+    vao: GLuint,
+    vbo: GLuint,
+
+    start: Instant,
+}
+
+impl PostProcess {
+    pub fn new(path: &Path) -> Self {
+        let mut vao: GLuint = 0;
+        let mut vbo: GLuint = 0;
+
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            // Upload just a single full-screen quad.
+            let vertices: [f32; 8] = [-1., 1., -1., -1., 1., 1., 1., -1.];
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&vertices) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, ptr::null());
+            gl::EnableVertexAttribArray(0);
+
+            gl::BindVertexArray(0);
+        }
+
+        Self {
+            path: path.to_path_buf(),
+            file: filewatch::File::new(path),
+            program: None,
+            vao,
+            vbo,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Recompile the shader on the next poll, even if its file did not change.
+    pub fn reload(&mut self) {
+        self.file = filewatch::File::new(&self.path);
+    }
+
+    /// Recompile the shader if its file has changed.
+    pub fn poll(&mut self) {
+        let source = match self.file.read_update() {
+            Some(source) => source,
+            None => return,
+        };
+
+        match PostProcessShaderProgram::new(&self.path, &source) {
+            Ok(program) => {
+                info!("Loaded post-processing shader {:?}", self.path);
+                self.program = Some(program);
+            },
+            Err(err) => error!(target: LOG_TARGET_CONFIG, "{}", err),
+        }
+    }
+
+    /// Whether there is a valid shader to run.
+    pub fn active(&self) -> bool {
+        self.program.is_some()
+    }
+
+    /// Whether the shader reads `u_time`, so it changes without any change to the frame.
+    pub fn uses_time(&self) -> bool {
+        self.program.as_ref().map_or(false, |program| program.u_time != -1)
+    }
+
+    /// Draw the `frame` texture into the window through the user shader.
+    ///
+    /// The `cursor` is the center of the cursor cell in window pixels, with the origin in the
+    /// bottom left corner like `gl_FragCoord`.
+    pub fn draw(&self, frame: GLuint, window_size: Vec2<i32>, cursor: Vec2<f32>) {
+        let program = match &self.program {
+            Some(program) => program,
+            None => return,
+        };
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, window_size.x, window_size.y);
+            gl::Disable(gl::BLEND);
+
+            gl::UseProgram(program.get_id());
+            gl::Uniform1i(program.u_screen, 0);
+            gl::Uniform2f(program.u_resolution, window_size.x as f32, window_size.y as f32);
+            gl::Uniform1f(program.u_time, self.start.elapsed().as_secs_f32());
+            gl::Uniform2f(program.u_cursor, cursor.x, cursor.y);
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, frame);
+
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+            gl::BindVertexArray(0);
+        }
+    }
+}
+
+impl Drop for PostProcess {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
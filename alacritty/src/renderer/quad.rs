@@ -1,7 +1,9 @@
 use super::atlas::{Atlas, AtlasInsertError};
+use super::atlas_debug::AtlasTexture;
 use super::glyph::{QuadAtlasGlyph, RasterizedGlyph};
 use super::math::*;
 use super::shade::GlyphRectShaderProgram;
+use super::stats::PassStats;
 use crate::gl;
 use crate::gl::types::*;
 use crate::renderer::Error;
@@ -18,7 +20,8 @@ enum RectAddError {
 
 pub struct GlyphQuad<'a> {
     pub glyph: &'a QuadAtlasGlyph,
-    pub pos: Vec2<i16>,
+    /// Position of the cell in pixels, relative to the top left corner of the grid.
+    pub pos: Vec2<f32>,
     pub fg: alacritty_terminal::term::color::Rgb,
 }
 
@@ -26,6 +29,9 @@ pub struct GlyphQuad<'a> {
 pub struct QuadGlyphRenderer {
     atlas_groups: Vec<AtlasGroup>,
 
+    // Width and height of the atlas texture of each group.
+    atlas_size: i32,
+
     // GL objects for shared use. There's no point in having these per atlas/batch, as their
     // content is completely transient currently.
     program: GlyphRectShaderProgram,
@@ -35,7 +41,7 @@ pub struct QuadGlyphRenderer {
 }
 
 impl QuadGlyphRenderer {
-    pub fn new() -> Self {
+    pub fn new(atlas_size: i32) -> Self {
         let mut vao: GLuint = 0;
         let mut vbo: GLuint = 0;
         let mut ebo: GLuint = 0;
@@ -66,49 +72,7 @@ impl QuadGlyphRenderer {
             gl::BindVertexArray(vao);
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
 
-            // Position.
-            gl::VertexAttribPointer(
-                0,
-                2,
-                gl::SHORT,
-                gl::FALSE,
-                (size_of::<Vertex>()) as _,
-                ptr::null(),
-            );
-            gl::EnableVertexAttribArray(0);
-
-            // uv.
-            gl::VertexAttribPointer(
-                1,
-                2,
-                gl::FLOAT,
-                gl::FALSE,
-                (size_of::<Vertex>()) as _,
-                offset_of!(Vertex, u) as *const _,
-            );
-            gl::EnableVertexAttribArray(1);
-
-            // Foreground color.
-            gl::VertexAttribPointer(
-                2,
-                3,
-                gl::UNSIGNED_BYTE,
-                gl::TRUE,
-                (size_of::<Vertex>()) as _,
-                offset_of!(Vertex, fg) as *const _,
-            );
-            gl::EnableVertexAttribArray(2);
-
-            // Flags.
-            gl::VertexAttribPointer(
-                3,
-                1,
-                gl::UNSIGNED_BYTE,
-                gl::FALSE,
-                (size_of::<Vertex>()) as _,
-                offset_of!(Vertex, flags) as *const _,
-            );
-            gl::EnableVertexAttribArray(3);
+            enable_vertex_attribs();
 
             // Pre-upload indices.
             gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
@@ -124,6 +88,7 @@ impl QuadGlyphRenderer {
             vbo,
             ebo,
             atlas_groups: Vec::new(),
+            atlas_size,
             program: GlyphRectShaderProgram::new().unwrap(),
         }
     }
@@ -140,6 +105,16 @@ impl QuadGlyphRenderer {
         }
     }
 
+    /// Atlas textures of all atlas groups.
+    pub fn atlas_textures(&self) -> impl Iterator<Item = AtlasTexture> + '_ {
+        self.atlas_groups.iter().enumerate().map(|(index, group)| AtlasTexture {
+            kind: "quad",
+            index,
+            tex: group.atlas.tex.id,
+            size: group.atlas.size(),
+        })
+    }
+
     pub fn insert_into_atlas(&mut self, rasterized: &RasterizedGlyph) -> QuadAtlasGlyph {
         loop {
             for group in &mut self.atlas_groups {
@@ -166,7 +141,7 @@ impl QuadGlyphRenderer {
                 }
             }
 
-            self.atlas_groups.push(AtlasGroup::new(self.atlas_groups.len()));
+            self.atlas_groups.push(AtlasGroup::new(self.atlas_groups.len(), self.atlas_size));
         }
     }
 
@@ -174,7 +149,7 @@ impl QuadGlyphRenderer {
         self.atlas_groups[glyph.glyph.atlas_index].add(size_info, glyph);
     }
 
-    pub fn draw(&mut self, size_info: &SizeInfo) {
+    pub fn draw(&mut self, size_info: &SizeInfo) -> PassStats {
         #[cfg(feature = "live-shader-reload")]
         {
             match self.program.poll() {
@@ -191,15 +166,17 @@ impl QuadGlyphRenderer {
         // Swap to rectangle rendering program.
         unsafe {
             // Add padding to viewport.
-            let pad_x = size_info.padding_x() as i32;
+            let pad_x = size_info.padding_left() as i32;
             let pad_y = size_info.padding_y() as i32;
-            let width = size_info.width() as i32 - 2 * pad_x;
+            let width = size_info.width() as i32 - pad_x - size_info.padding_x() as i32;
             let height = size_info.height() as i32 - 2 * pad_y;
             gl::Viewport(pad_x, pad_y, width, height);
 
             // Swap program.
             gl::UseProgram(self.program.get_id());
 
+            // Map pixel positions to normalized device coordinates using the exact viewport size, so
+            // fractional cell sizes line up with the grid renderer.
             gl::Uniform1i(self.program.u_atlas, 0);
             gl::Uniform2f(self.program.u_scale, 2.0 / width as f32, -2.0 / height as f32);
 
@@ -214,9 +191,12 @@ impl QuadGlyphRenderer {
             gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
         }
 
+        let mut stats = PassStats::default();
         for group in &mut self.atlas_groups {
-            group.draw();
+            stats.add(group.draw());
         }
+
+        stats
     }
 }
 
@@ -227,8 +207,8 @@ struct AtlasGroup {
 }
 
 impl AtlasGroup {
-    fn new(index: usize) -> Self {
-        Self { atlas: Atlas::new(index, 1024), batches: Vec::new() }
+    fn new(index: usize, atlas_size: i32) -> Self {
+        Self { atlas: Atlas::new(index, atlas_size), batches: Vec::new() }
     }
 
     fn clear_atlas(&mut self) {
@@ -256,42 +236,85 @@ impl AtlasGroup {
         }
     }
 
-    fn draw(&mut self) {
+    fn draw(&mut self) -> PassStats {
         unsafe {
             // Binding to active slot 0
-            gl::BindTexture(gl::TEXTURE_2D, self.atlas.id);
+            gl::BindTexture(gl::TEXTURE_2D, self.atlas.tex.id);
         }
 
+        let mut stats = PassStats::default();
         for batch in &mut self.batches {
-            batch.draw();
+            stats.add(batch.draw());
         }
+
+        stats
     }
 }
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
-struct Rgb {
+pub struct Rgb {
     r: u8,
     g: u8,
     b: u8,
 }
 
 impl Rgb {
-    fn from(color: alacritty_terminal::term::color::Rgb) -> Rgb {
+    pub fn from(color: alacritty_terminal::term::color::Rgb) -> Rgb {
         Rgb { r: color.r, g: color.g, b: color.b }
     }
 }
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
-struct Vertex {
-    x: i16,
-    y: i16,
+pub struct Vertex {
+    pub x: f32,
+    pub y: f32,
     // TODO these can also be u/i16
-    u: f32,
-    v: f32,
-    fg: Rgb,
-    flags: u8,
+    pub u: f32,
+    pub v: f32,
+    pub fg: Rgb,
+    pub flags: u8,
+}
+
+/// Set up the attributes of `Vertex` for the currently bound VAO and VBO.
+pub unsafe fn enable_vertex_attribs() {
+    // Position.
+    gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, (size_of::<Vertex>()) as _, ptr::null());
+    gl::EnableVertexAttribArray(0);
+
+    // uv.
+    gl::VertexAttribPointer(
+        1,
+        2,
+        gl::FLOAT,
+        gl::FALSE,
+        (size_of::<Vertex>()) as _,
+        offset_of!(Vertex, u) as *const _,
+    );
+    gl::EnableVertexAttribArray(1);
+
+    // Foreground color.
+    gl::VertexAttribPointer(
+        2,
+        3,
+        gl::UNSIGNED_BYTE,
+        gl::TRUE,
+        (size_of::<Vertex>()) as _,
+        offset_of!(Vertex, fg) as *const _,
+    );
+    gl::EnableVertexAttribArray(2);
+
+    // Flags.
+    gl::VertexAttribPointer(
+        3,
+        1,
+        gl::UNSIGNED_BYTE,
+        gl::FALSE,
+        (size_of::<Vertex>()) as _,
+        offset_of!(Vertex, flags) as *const _,
+    );
+    gl::EnableVertexAttribArray(3);
 }
 
 #[derive(Debug)]
@@ -317,14 +340,16 @@ impl Batch {
         let g = glyph.glyph;
 
         // Calculate rectangle position.
-        let x = glyph.pos.x + g.left;
-        let y = glyph.pos.y + (size_info.cell_height() as i16 - g.top);
+        let x = glyph.pos.x + f32::from(g.left);
+        let y = glyph.pos.y + size_info.cell_height() - f32::from(g.top);
+        let width = f32::from(g.width);
+        let height = f32::from(g.height);
         let fg = Rgb::from(glyph.fg);
         let flags = if g.colored { 1 } else { 0 };
 
         self.vertices.push(Vertex {
             x,
-            y: y + g.height,
+            y: y + height,
             u: g.uv_left,
             v: g.uv_bot + g.uv_height,
             fg,
@@ -332,15 +357,15 @@ impl Batch {
         });
         self.vertices.push(Vertex { x, y, u: g.uv_left, v: g.uv_bot, fg, flags });
         self.vertices.push(Vertex {
-            x: x + g.width,
-            y: y + g.height,
+            x: x + width,
+            y: y + height,
             u: g.uv_left + g.uv_width,
             v: g.uv_bot + g.uv_height,
             fg,
             flags,
         });
         self.vertices.push(Vertex {
-            x: x + g.width,
+            x: x + width,
             y,
             u: g.uv_left + g.uv_width,
             v: g.uv_bot,
@@ -351,15 +376,16 @@ impl Batch {
         Ok(())
     }
 
-    fn draw(&mut self) {
+    fn draw(&mut self) -> PassStats {
         if self.vertices.is_empty() {
-            return;
+            return PassStats::default();
         }
 
+        let uploaded_bytes = self.vertices.len() * std::mem::size_of::<Vertex>();
         unsafe {
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (self.vertices.len() * std::mem::size_of::<Vertex>()) as isize,
+                uploaded_bytes as isize,
                 self.vertices.as_ptr() as *const _,
                 gl::STREAM_DRAW,
             );
@@ -371,5 +397,7 @@ impl Batch {
                 ptr::null(),
             );
         }
+
+        PassStats { draw_calls: 1, uploaded_bytes, ..PassStats::default() }
     }
 }
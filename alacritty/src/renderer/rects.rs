@@ -1,11 +1,12 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
 
 use crossfont::Metrics;
 
 use alacritty_terminal::index::{Column, Point};
 use alacritty_terminal::term::cell::Flags;
 use alacritty_terminal::term::color::Rgb;
-use alacritty_terminal::term::{RenderableCell, SizeInfo};
+use alacritty_terminal::term::marks::RenderableMark;
+use alacritty_terminal::term::{RenderableCell, RenderableCellContent, SizeInfo};
 
 #[derive(Debug, Copy, Clone)]
 pub struct RenderRect {
@@ -21,6 +22,92 @@ impl RenderRect {
     pub fn new(x: f32, y: f32, width: f32, height: f32, color: Rgb, alpha: f32) -> Self {
         RenderRect { x, y, width, height, color, alpha }
     }
+
+    /// Extend this rect by `next` if both are on the same line and touch horizontally.
+    fn merge(&mut self, next: &RenderRect) -> bool {
+        let mergeable = self.y == next.y
+            && self.height == next.height
+            && self.color == next.color
+            && self.alpha == next.alpha
+            && (self.x + self.width - next.x).abs() < MERGE_EPSILON;
+
+        if mergeable {
+            self.width += next.width;
+        }
+
+        mergeable
+    }
+}
+
+/// Maximum gap between two rects which are still considered adjacent.
+const MERGE_EPSILON: f32 = 0.01;
+
+/// Reduce the number of rects which have to be drawn.
+///
+/// Opaque rects are sorted by position and horizontally adjacent rects with the same color are
+/// merged into one. Translucent rects are blended in their original order after all opaque ones.
+pub fn batch(rects: Vec<RenderRect>) -> Vec<RenderRect> {
+    let (mut opaque, translucent): (Vec<_>, Vec<_>) =
+        rects.into_iter().filter(|rect| rect.alpha > 0.).partition(|rect| rect.alpha >= 1.);
+
+    opaque.sort_by(|a, b| {
+        let y = a.y.partial_cmp(&b.y).unwrap_or(Ordering::Equal);
+        y.then_with(|| a.x.partial_cmp(&b.x).unwrap_or(Ordering::Equal))
+    });
+
+    let mut batched: Vec<RenderRect> = Vec::with_capacity(opaque.len() + translucent.len());
+    for rect in opaque {
+        match batched.last_mut() {
+            Some(last) if last.merge(&rect) => (),
+            _ => batched.push(rect),
+        }
+    }
+
+    batched.extend(translucent);
+
+    batched
+}
+
+/// Opacity of the tint blended over marked cells.
+pub const MARK_TINT_ALPHA: f32 = 0.3;
+
+/// Tint the cells of all marks with a color.
+pub fn mark_rects(marks: &[RenderableMark], size: &SizeInfo) -> Vec<RenderRect> {
+    marks
+        .iter()
+        .filter_map(|RenderableMark { line, mark }| {
+            let columns = mark.columns.end.0.saturating_sub(mark.columns.start.0);
+            let x = size.padding_left() + mark.columns.start.0 as f32 * size.cell_width();
+            let y = size.padding_y() + line.0 as f32 * size.cell_height();
+            let width = columns as f32 * size.cell_width();
+            let tint = mark.tint?;
+            Some(RenderRect::new(x, y, width, size.cell_height(), tint, MARK_TINT_ALPHA))
+        })
+        .collect()
+}
+
+/// Cell width per pixel of the truncation marker size.
+const TRUNCATION_MARKER_DIVISOR: f32 = 4.;
+
+/// Mark cells which dropped some of their zero-width characters with a square in the top right
+/// corner.
+pub fn truncation_rects<'a, I>(cells: I, size: &SizeInfo) -> Vec<RenderRect>
+where
+    I: IntoIterator<Item = &'a RenderableCell>,
+{
+    let side = (size.cell_width() / TRUNCATION_MARKER_DIVISOR).floor().max(1.);
+    cells
+        .into_iter()
+        .filter(|cell| {
+            matches!(cell.inner, RenderableCellContent::Chars(_))
+                && cell.flags.contains(Flags::ZEROWIDTH_TRUNCATED)
+        })
+        .map(|cell| {
+            let x = size.padding_left() + (cell.column.0 + 1) as f32 * size.cell_width() - side;
+            let y = size.padding_y() + cell.line.0 as f32 * size.cell_height();
+            RenderRect::new(x, y, side, side, cell.fg, 1.)
+        })
+        .collect()
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -106,17 +193,11 @@ impl RenderLine {
         // Make sure lines are always visible.
         thickness = thickness.max(1.);
 
-        let line_bottom = (start.line.0 as f32 + 1.) * size.cell_height();
-        let baseline = line_bottom + descent;
-
-        let mut y = (baseline - position - thickness / 2.).ceil();
-        let max_y = line_bottom - thickness;
-        if y > max_y {
-            y = max_y;
-        }
+        let line_top = start.line.0 as f32 * size.cell_height();
+        let y = line_top + line_offset(size.cell_height(), descent, position, thickness);
 
         RenderRect::new(
-            start_x + size.padding_x(),
+            start_x + size.padding_left(),
             y + size.padding_y(),
             width,
             thickness,
@@ -126,61 +207,60 @@ impl RenderLine {
     }
 }
 
-/// Lines for underline and strikeout.
-#[derive(Default)]
-pub struct RenderLines {
-    inner: HashMap<Flags, Vec<RenderLine>>,
+/// Distance from the top of a cell to the top of a line.
+///
+/// The `position` of the line is relative to the baseline and the line is kept inside the cell.
+pub fn line_offset(cell_height: f32, descent: f32, position: f32, thickness: f32) -> f32 {
+    let baseline = cell_height + descent;
+    (baseline - position - thickness / 2.).ceil().min(cell_height - thickness)
 }
 
-impl RenderLines {
-    #[inline]
-    pub fn new() -> Self {
-        Self::default()
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgb = Rgb { r: 0xff, g: 0, b: 0 };
+    const BLUE: Rgb = Rgb { r: 0, g: 0, b: 0xff };
 
-    #[inline]
-    pub fn rects(&self, metrics: &Metrics, size: &SizeInfo) -> Vec<RenderRect> {
-        self.inner
-            .iter()
-            .flat_map(|(flag, lines)| {
-                lines.iter().flat_map(move |line| line.rects(*flag, metrics, size))
-            })
-            .collect()
+    #[test]
+    fn batch_merges_adjacent_rects() {
+        let rects = vec![
+            RenderRect::new(20., 10., 10., 1., RED, 1.),
+            RenderRect::new(0., 10., 10., 1., RED, 1.),
+            RenderRect::new(10., 10., 10., 1., RED, 1.),
+        ];
+
+        let batched = batch(rects);
+
+        assert_eq!(batched.len(), 1);
+        assert_eq!(batched[0].x, 0.);
+        assert_eq!(batched[0].width, 30.);
     }
 
-    /// Update the stored lines with the next cell info.
-    #[inline]
-    pub fn update(&mut self, cell: RenderableCell) {
-        self.update_flag(cell, Flags::UNDERLINE);
-        self.update_flag(cell, Flags::DOUBLE_UNDERLINE);
-        self.update_flag(cell, Flags::STRIKEOUT);
+    #[test]
+    fn batch_keeps_distinct_rects() {
+        let rects = vec![
+            RenderRect::new(0., 10., 10., 1., RED, 1.),
+            RenderRect::new(10., 10., 10., 1., BLUE, 1.),
+            RenderRect::new(25., 10., 10., 1., BLUE, 1.),
+            RenderRect::new(0., 20., 10., 1., RED, 1.),
+        ];
+
+        assert_eq!(batch(rects).len(), 4);
     }
 
-    /// Update the lines for a specific flag.
-    fn update_flag(&mut self, cell: RenderableCell, flag: Flags) {
-        if !cell.flags.contains(flag) {
-            return;
-        }
+    #[test]
+    fn batch_draws_translucent_rects_last() {
+        let rects = vec![
+            RenderRect::new(0., 0., 100., 100., BLUE, 0.5),
+            RenderRect::new(0., 10., 10., 1., RED, 1.),
+            RenderRect::new(0., 0., 100., 100., RED, 0.),
+        ];
 
-        // Check if there's an active line.
-        if let Some(line) = self.inner.get_mut(&flag).and_then(|lines| lines.last_mut()) {
-            if cell.fg == line.color
-                && cell.column == line.end.col + 1
-                && cell.line == line.end.line
-            {
-                // Update the length of the line.
-                line.end = cell.into();
-                return;
-            }
-        }
+        let batched = batch(rects);
 
-        // Start new line if there currently is none.
-        let line = RenderLine { start: cell.into(), end: cell.into(), color: cell.fg };
-        match self.inner.get_mut(&flag) {
-            Some(lines) => lines.push(line),
-            None => {
-                self.inner.insert(flag, vec![line]);
-            },
-        }
+        assert_eq!(batched.len(), 2);
+        assert_eq!(batched[0].color, RED);
+        assert_eq!(batched[1].color, BLUE);
     }
 }
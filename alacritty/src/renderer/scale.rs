@@ -0,0 +1,96 @@
+//! Downscaling of rasterized glyphs which are too large for the cell.
+
+use crossfont::{BitmapBuffer, RasterizedGlyph};
+
+use super::math::Vec2;
+
+/// Shrink a glyph to fit into `size`, keeping its aspect ratio.
+///
+/// Each pixel of the scaled glyph is the average of the pixels it covers. The glyph position is
+/// not changed.
+pub fn fit_glyph(glyph: &mut RasterizedGlyph, size: Vec2<i32>) {
+    if (glyph.width <= size.x && glyph.height <= size.y) || size.x <= 0 || size.y <= 0 {
+        return;
+    }
+
+    let factor = (size.x as f32 / glyph.width as f32).min(size.y as f32 / glyph.height as f32);
+    let width = ((glyph.width as f32 * factor).round() as usize).max(1);
+    let height = ((glyph.height as f32 * factor).round() as usize).max(1);
+
+    glyph.buf = match &glyph.buf {
+        BitmapBuffer::RGB(buf) => BitmapBuffer::RGB(downscale(glyph, buf, 3, width, height)),
+        BitmapBuffer::RGBA(buf) => BitmapBuffer::RGBA(downscale(glyph, buf, 4, width, height)),
+    };
+    glyph.width = width as i32;
+    glyph.height = height as i32;
+}
+
+/// Box filter the glyph bitmap `buf` down to `width` by `height` pixels.
+fn downscale(
+    glyph: &RasterizedGlyph,
+    buf: &[u8],
+    channels: usize,
+    width: usize,
+    height: usize,
+) -> Vec<u8> {
+    let src_width = glyph.width as usize;
+    let src_height = glyph.height as usize;
+
+    let mut sums = vec![0u32; width * height * channels];
+    let mut counts = vec![0u32; width * height];
+    for y in 0..src_height {
+        let dst_y = y * height / src_height;
+        for x in 0..src_width {
+            let dst = dst_y * width + x * width / src_width;
+            let src = (y * src_width + x) * channels;
+            counts[dst] += 1;
+            for channel in 0..channels {
+                sums[dst * channels + channel] += u32::from(buf[src + channel]);
+            }
+        }
+    }
+
+    sums.iter().enumerate().map(|(i, sum)| (sum / counts[i / channels].max(1)) as u8).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(width: i32, height: i32, buf: BitmapBuffer) -> RasterizedGlyph {
+        RasterizedGlyph { c: 'x', width, height, top: height, left: 0, buf }
+    }
+
+    #[test]
+    fn small_glyph_unchanged() {
+        let mut small = glyph(2, 2, BitmapBuffer::RGB(vec![255; 12]));
+        fit_glyph(&mut small, Vec2::new(4, 4));
+        assert_eq!((small.width, small.height), (2, 2));
+    }
+
+    #[test]
+    fn keeps_aspect_ratio() {
+        let mut tall = glyph(4, 8, BitmapBuffer::RGBA(vec![255; 4 * 8 * 4]));
+        fit_glyph(&mut tall, Vec2::new(4, 4));
+
+        assert_eq!((tall.width, tall.height), (2, 4));
+        match tall.buf {
+            BitmapBuffer::RGBA(buf) => assert_eq!(buf, vec![255; 2 * 4 * 4]),
+            BitmapBuffer::RGB(_) => panic!("format changed"),
+        }
+    }
+
+    #[test]
+    fn averages_covered_pixels() {
+        // Checkerboard of a single channel, which averages to half coverage.
+        let buf = (0..16).flat_map(|i| vec![if (i + i / 4) % 2 == 0 { 254 } else { 0 }; 3]);
+        let mut checkers = glyph(4, 4, BitmapBuffer::RGB(buf.collect()));
+        fit_glyph(&mut checkers, Vec2::new(2, 2));
+
+        assert_eq!((checkers.width, checkers.height), (2, 2));
+        match checkers.buf {
+            BitmapBuffer::RGB(buf) => assert_eq!(buf, vec![127; 12]),
+            BitmapBuffer::RGBA(_) => panic!("format changed"),
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::mem;
+use std::ops::Range;
+use std::ptr;
+
+use super::gl_api::{Gl, GlApi};
+use super::texture::{create_texture, upload_texture, upload_texture_rows, PixelFormat};
+use crate::gl::types::*;
+
+/// Screens worth of history kept in the texture below the visible lines.
+const HISTORY_SCREENS: usize = 1;
+
+/// Number of rows in the texture for a screen with `lines`.
+pub fn ring_rows(lines: usize) -> usize {
+    lines * (HISTORY_SCREENS + 1)
+}
+
+/// Per-cell data of the screen mirrored into a texture with one texel per cell.
+///
+/// A copy of the data on the GPU is kept, so only the rows which changed since the last upload
+/// are sent again. Scrolling inside a scroll region for example only uploads the region's lines.
+///
+/// The texture is a ring buffer of rows, which is taller than the screen. When the whole screen
+/// scrolled, only the row the screen starts at is moved, so the rows still visible are not touched
+/// and only the newly exposed rows have to be uploaded. Rows which recently scrolled out of view
+/// stay in the texture, which makes scrolling back to them free as well.
+#[derive(Debug)]
+pub struct ScreenTexture<T, G: GlApi = Gl> {
+    /// Data of every cell on the screen, line by line.
+    pub cells: Vec<T>,
+
+    /// Data last uploaded to every row of the texture.
+    uploaded: Vec<T>,
+
+    /// Rows of the texture which contain uploaded data.
+    valid_rows: Vec<bool>,
+
+    /// Size of the texture in cells.
+    columns: usize,
+    rows: usize,
+
+    tex: GLuint,
+    format: PixelFormat,
+
+    gl: G,
+}
+
+impl<T: Copy + PartialEq> ScreenTexture<T> {
+    pub fn new(format: PixelFormat) -> Self {
+        Self::with_gl(format, Gl)
+    }
+}
+
+impl<T: Copy + PartialEq, G: GlApi> ScreenTexture<T, G> {
+    /// Create the texture using `gl` for all OpenGL calls.
+    pub fn with_gl(format: PixelFormat, gl: G) -> Self {
+        Self {
+            cells: Vec::new(),
+            uploaded: Vec::new(),
+            valid_rows: Vec::new(),
+            columns: 0,
+            rows: 0,
+            tex: unsafe { create_texture(&gl, 1, 1, format) },
+            format,
+            gl,
+        }
+    }
+
+    /// Resize the cell data, the texture itself is resized on the next upload.
+    pub fn resize(&mut self, columns: usize, lines: usize, empty: T) {
+        self.cells.resize(columns * lines, empty);
+    }
+
+    /// Set every cell to `value`.
+    pub fn fill(&mut self, value: T) {
+        self.cells.iter_mut().for_each(|cell| *cell = value);
+    }
+
+    /// Bind the texture to the active texture unit.
+    pub unsafe fn bind(&self) {
+        self.gl.bind_texture(self.tex);
+    }
+
+    /// Bind the texture to the active texture unit and upload all damaged rows.
+    ///
+    /// The first line of the screen is stored in row `offset` of the texture, which must be less
+    /// than `ring_rows(lines)`.
+    ///
+    /// Returns the number of bytes uploaded.
+    pub unsafe fn upload(&mut self, columns: usize, lines: usize, offset: usize) -> usize {
+        self.bind();
+
+        let rows = ring_rows(lines);
+        if (self.columns, self.rows) != (columns, rows) {
+            upload_texture(&self.gl, columns as i32, rows as i32, self.format, ptr::null());
+            self.columns = columns;
+            self.rows = rows;
+            self.valid_rows = vec![false; rows];
+
+            // Content of invalid rows is never compared, so any value works as placeholder.
+            self.uploaded.clear();
+            self.uploaded.extend(self.cells.iter().cycle().take(columns * rows));
+        }
+
+        let damaged =
+            match damaged_lines(&self.cells, &self.uploaded, &self.valid_rows, columns, offset) {
+                Some(damaged) => damaged,
+                None => return 0,
+            };
+
+        // The damaged lines wrap around the end of the texture at most once.
+        let row = (offset + damaged.start) % rows;
+        let split = damaged.start + (rows - row).min(damaged.len());
+        self.upload_lines(damaged.start..split, row) + self.upload_lines(split..damaged.end, 0)
+    }
+
+    /// Upload the screen `lines` to consecutive rows of the texture, starting at `row`.
+    unsafe fn upload_lines(&mut self, lines: Range<usize>, row: usize) -> usize {
+        if lines.is_empty() {
+            return 0;
+        }
+
+        let columns = self.columns;
+        let cells = lines.start * columns..lines.end * columns;
+        upload_texture_rows(
+            &self.gl,
+            columns as i32,
+            row as i32,
+            lines.len() as i32,
+            self.format,
+            self.cells[cells.start..].as_ptr() as *const _,
+        );
+
+        let rows = row..row + lines.len();
+        self.uploaded[rows.start * columns..rows.end * columns]
+            .copy_from_slice(&self.cells[cells.clone()]);
+        self.valid_rows[rows].iter_mut().for_each(|valid| *valid = true);
+
+        cells.len() * mem::size_of::<T>()
+    }
+}
+
+impl<T, G: GlApi> Drop for ScreenTexture<T, G> {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_texture(self.tex);
+        }
+    }
+}
+
+/// Smallest range of screen lines containing every line which differs from the row of the texture
+/// it is stored in, when the first line is stored in row `offset`.
+///
+/// Rows which were never uploaded are always damaged.
+fn damaged_lines<T: PartialEq>(
+    cells: &[T],
+    uploaded: &[T],
+    valid_rows: &[bool],
+    columns: usize,
+    offset: usize,
+) -> Option<Range<usize>> {
+    if columns == 0 || valid_rows.is_empty() {
+        return None;
+    }
+
+    let lines = cells.len() / columns;
+    let line_differs = |line: &usize| {
+        let row = (offset + line) % valid_rows.len();
+        !valid_rows[row]
+            || cells[line * columns..(line + 1) * columns]
+                != uploaded[row * columns..(row + 1) * columns]
+    };
+
+    let first = (0..lines).find(line_differs)?;
+    let last = (first..lines).rev().find(line_differs)?;
+
+    Some(first..last + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::renderer::gl_api::{GlCall, RecordingGl};
+
+    /// Calls uploading `lines` rows of a texture with two columns, starting at `row`.
+    fn row_upload(row: i32, lines: i32) -> GlCall {
+        GlCall::TexSubImage2D { x: 0, y: row, width: 2, height: lines, format: PixelFormat::RGBA8 }
+    }
+
+    #[test]
+    fn upload_sequence() {
+        let mut texture = ScreenTexture::with_gl(PixelFormat::RGBA8, RecordingGl::default());
+        texture.resize(2, 3, [0u8; 4]);
+        texture.gl.take_calls();
+
+        // The first upload allocates the ring buffer and uploads every line.
+        let bytes = unsafe { texture.upload(2, 3, 0) };
+        assert_eq!(bytes, 2 * 3 * 4);
+        assert_eq!(
+            texture.gl.take_calls(),
+            vec![
+                GlCall::BindTexture(1),
+                GlCall::TexImage2D {
+                    width: 2,
+                    height: ring_rows(3) as i32,
+                    format: PixelFormat::RGBA8,
+                    filled: false,
+                },
+                row_upload(0, 3),
+            ]
+        );
+
+        // Unchanged content is only bound.
+        assert_eq!(unsafe { texture.upload(2, 3, 0) }, 0);
+        assert_eq!(texture.gl.take_calls(), vec![GlCall::BindTexture(1)]);
+
+        // Scrolling up only uploads the exposed line below the old screen.
+        texture.cells.rotate_left(2);
+        texture.cells[4..].copy_from_slice(&[[1; 4]; 2]);
+        assert_eq!(unsafe { texture.upload(2, 3, 1) }, 2 * 4);
+        assert_eq!(texture.gl.take_calls(), vec![GlCall::BindTexture(1), row_upload(3, 1)]);
+    }
+
+    #[test]
+    fn upload_wrapping_rows() {
+        let mut texture = ScreenTexture::with_gl(PixelFormat::RGBA8, RecordingGl::default());
+        texture.resize(2, 3, [0u8; 4]);
+        unsafe { texture.upload(2, 3, 0) };
+        texture.gl.take_calls();
+        texture.fill([1; 4]);
+
+        // Lines past the end of the texture are stored at its start.
+        let offset = ring_rows(3) - 1;
+        assert_eq!(unsafe { texture.upload(2, 3, offset) }, 2 * 3 * 4);
+        assert_eq!(
+            texture.gl.take_calls(),
+            vec![GlCall::BindTexture(1), row_upload(offset as i32, 1), row_upload(0, 2),]
+        );
+    }
+
+    #[test]
+    fn damaged_region() {
+        let uploaded = [0, 0, 1, 1, 2, 2, 3, 3];
+        let valid_rows = [true; 4];
+
+        assert_eq!(damaged_lines(&uploaded, &uploaded, &valid_rows, 2, 0), None);
+
+        // Scrolling the two middle lines up.
+        let cells = [0, 0, 2, 2, 0, 0, 3, 3];
+        assert_eq!(damaged_lines(&cells, &uploaded, &valid_rows, 2, 0), Some(1..3));
+
+        let cells = [0, 0, 1, 1, 2, 2, 3, 4];
+        assert_eq!(damaged_lines(&cells, &uploaded, &valid_rows, 2, 0), Some(3..4));
+    }
+
+    #[test]
+    fn damaged_after_scroll() {
+        // Screen of four lines, starting at row 1 of the texture.
+        let uploaded = [9, 9, 0, 0, 1, 1, 2, 2, 3, 3, 9, 9];
+        let valid_rows = [false, true, true, true, true, false];
+
+        // Only the exposed line is damaged.
+        let cells = [1, 1, 2, 2, 3, 3, 4, 4];
+        assert_eq!(damaged_lines(&cells, &uploaded, &valid_rows, 2, 2), Some(3..4));
+
+        let cells = [5, 5, 0, 0, 1, 1, 2, 2];
+        assert_eq!(damaged_lines(&cells, &uploaded, &valid_rows, 2, 0), Some(0..1));
+    }
+
+    #[test]
+    fn history_kept_in_texture() {
+        // Line 0 scrolled out of view, but is still stored in row 0.
+        let uploaded = [0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 9, 9];
+        let valid_rows = [true, true, true, true, true, false];
+
+        // Scrolling back to it doesn't damage anything.
+        let cells = [0, 0, 1, 1, 2, 2, 3, 3];
+        assert_eq!(damaged_lines(&cells, &uploaded, &valid_rows, 2, 0), None);
+
+        // Lines wrap around the end of the texture.
+        let cells = [3, 3, 4, 4, 5, 5, 0, 0];
+        assert_eq!(damaged_lines(&cells, &uploaded, &valid_rows, 2, 3), Some(2..3));
+    }
+}
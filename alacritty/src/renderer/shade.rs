@@ -5,7 +5,7 @@ use std::fmt;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[cfg(feature = "live-shader-reload")]
 use super::filewatch;
@@ -344,6 +344,9 @@ static SCREEN_SHADER_F: &str =
 declare_program! { GridShaderProgram,
     SCREEN_SHADER_V_PATH, SCREEN_SHADER_V, SCREEN_SHADER_F_PATH, SCREEN_SHADER_F {
         u_screen_dim,
+        u_screen_rows,
+        u_minimap,
+        u_minimap_pass,
         u_cell_dim,
         u_atlas,
         u_color_bg,
@@ -351,8 +354,25 @@ declare_program! { GridShaderProgram,
         u_glyph_ref,
         u_cursor,
         u_cursor_color,
+        u_cursor_thickness,
+        u_terminal_cursor,
+        u_terminal_cursor_color,
         u_atlas_dim,
-        u_main_pass
+        u_main_pass,
+        u_color_index,
+        u_palette,
+        u_palette_blend,
+        u_underline,
+        u_strikeout,
+        u_undercurl,
+        u_background_image,
+        u_background_image_params,
+        u_underlay,
+        u_has_underlay,
+        u_time,
+        u_blink_interval,
+        u_highlight_fg,
+        u_highlight_bg
     }
 }
 
@@ -361,7 +381,7 @@ impl GridShaderProgram {
         unsafe {
             gl::Uniform4f(
                 self.u_screen_dim,
-                size_info.padding_x(),
+                size_info.padding_left(),
                 size_info.padding_y(),
                 size_info.width(),
                 size_info.height(),
@@ -399,3 +419,69 @@ static RECT_SHADER_F: &str = include_str!("../../res/rect.f.glsl");
 declare_program! { RectShaderProgram, RECT_SHADER_V_PATH, RECT_SHADER_V, RECT_SHADER_F_PATH, RECT_SHADER_F {
 u_color }
 }
+
+/// The post-processing pass draws the same full-screen quad as the grid.
+static POST_PROCESS_SHADER_V: &str =
+    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/res/screen.v.glsl"));
+
+/// Program running a user-supplied fragment shader over the rendered frame.
+#[derive(Debug)]
+pub struct PostProcessShaderProgram {
+    id: GLuint,
+    pub u_screen: GLint,
+    pub u_resolution: GLint,
+    pub u_time: GLint,
+    pub u_cursor: GLint,
+}
+
+impl PostProcessShaderProgram {
+    pub fn new(path: &Path, fragment_src: &str) -> Result<Self, ShaderCreationError> {
+        let vertex_shader = create_shader_from_source(gl::VERTEX_SHADER, POST_PROCESS_SHADER_V)?;
+        let fragment_shader = match create_shader_from_source(gl::FRAGMENT_SHADER, fragment_src) {
+            Ok(shader) => shader,
+            Err(err) => {
+                unsafe {
+                    gl::DeleteShader(vertex_shader);
+                }
+
+                // Point the compile log at the user's file.
+                return Err(match err {
+                    ShaderCreationError::Compile(_, log) => {
+                        ShaderCreationError::Compile(path.to_path_buf(), log)
+                    },
+                    err => err,
+                });
+            },
+        };
+
+        let program = create_program(vertex_shader, fragment_shader);
+
+        unsafe {
+            gl::DeleteShader(fragment_shader);
+            gl::DeleteShader(vertex_shader);
+        }
+
+        let id = program?;
+        let uniform = |name: &str| unsafe { gl::GetUniformLocation(id, name.as_ptr() as *const _) };
+
+        Ok(Self {
+            id,
+            u_screen: uniform("u_screen\0"),
+            u_resolution: uniform("u_resolution\0"),
+            u_time: uniform("u_time\0"),
+            u_cursor: uniform("u_cursor\0"),
+        })
+    }
+
+    pub fn get_id(&self) -> GLuint {
+        self.id
+    }
+}
+
+impl Drop for PostProcessShaderProgram {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.id);
+        }
+    }
+}
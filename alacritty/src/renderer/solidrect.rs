@@ -1,5 +1,6 @@
-use super::rects::RenderRect;
+use super::rects::{self, RenderRect};
 use super::shade::RectShaderProgram;
+use super::stats::PassStats;
 use crate::gl;
 use crate::gl::types::*;
 use crate::renderer::Error;
@@ -11,10 +12,6 @@ use log::*;
 use std::mem::size_of;
 use std::ptr;
 
-enum InsertError {
-    Full,
-}
-
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 struct Rgba {
@@ -41,7 +38,7 @@ pub struct SolidRectRenderer {
     vbo: GLuint,
     ebo: GLuint,
 
-    indices: Vec<u16>,
+    indices: Vec<u32>,
     vertices: Vec<Vertex>,
 }
 
@@ -93,9 +90,9 @@ impl SolidRectRenderer {
         })
     }
 
-    pub fn draw(&mut self, size_info: &SizeInfo, rects: Vec<RenderRect>) {
+    pub fn draw(&mut self, size_info: &SizeInfo, rects: Vec<RenderRect>) -> PassStats {
         if rects.is_empty() {
-            return;
+            return PassStats::default();
         }
 
         #[cfg(feature = "live-shader-reload")]
@@ -129,30 +126,16 @@ impl SolidRectRenderer {
         let center_x = size_info.width() / 2.;
         let center_y = size_info.height() / 2.;
 
-        for rect in &rects {
-            if let Err(InsertError::Full) = self.append_rect(center_x, center_y, rect) {
-                self.draw_accumulated();
-            }
+        // Merge rects, so everything can be uploaded and drawn at once.
+        for rect in &rects::batch(rects) {
+            self.append_rect(center_x, center_y, rect);
         }
 
-        self.draw_accumulated();
+        self.draw_accumulated()
     }
 
-    fn append_rect(
-        &mut self,
-        center_x: f32,
-        center_y: f32,
-        rect: &RenderRect,
-    ) -> Result<(), InsertError> {
-        let index = self.vertices.len();
-        if index >= 65536 - 4 {
-            return Err(InsertError::Full);
-        }
-        let index = index as u16;
-
-        if rect.alpha <= 0. {
-            return Ok(());
-        }
+    fn append_rect(&mut self, center_x: f32, center_y: f32, rect: &RenderRect) {
+        let index = self.vertices.len() as u32;
 
         // Calculate rectangle position.
         let x = (rect.x - center_x) / center_x;
@@ -178,27 +161,28 @@ impl SolidRectRenderer {
         self.indices.push(index + 2);
         self.indices.push(index + 3);
         self.indices.push(index + 1);
-
-        Ok(())
     }
 
-    fn draw_accumulated(&mut self) {
+    fn draw_accumulated(&mut self) -> PassStats {
         if self.indices.is_empty() {
-            return;
+            return PassStats::default();
         }
 
+        let vertex_bytes = self.vertices.len() * std::mem::size_of::<Vertex>();
+        let index_bytes = self.indices.len() * std::mem::size_of::<u32>();
+
         // Upload accumulated buffers
         unsafe {
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (self.vertices.len() * std::mem::size_of::<Vertex>()) as isize,
+                vertex_bytes as isize,
                 self.vertices.as_ptr() as *const _,
                 gl::STREAM_DRAW,
             );
 
             gl::BufferData(
                 gl::ELEMENT_ARRAY_BUFFER,
-                (self.indices.len() * std::mem::size_of::<u16>()) as isize,
+                index_bytes as isize,
                 self.indices.as_ptr() as *const _,
                 gl::STREAM_DRAW,
             );
@@ -206,12 +190,18 @@ impl SolidRectRenderer {
             gl::DrawElements(
                 gl::TRIANGLES,
                 self.indices.len() as i32,
-                gl::UNSIGNED_SHORT,
+                gl::UNSIGNED_INT,
                 ptr::null(),
             );
         }
 
         self.indices.clear();
         self.vertices.clear();
+
+        PassStats {
+            draw_calls: 1,
+            uploaded_bytes: vertex_bytes + index_bytes,
+            ..PassStats::default()
+        }
     }
 }
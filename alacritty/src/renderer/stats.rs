@@ -0,0 +1,185 @@
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+use crate::gl;
+use crate::gl::types::*;
+
+/// Render passes which are measured separately.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Pass {
+    Grid,
+    Quad,
+    Graphics,
+    Rects,
+}
+
+impl Pass {
+    const COUNT: usize = 4;
+
+    fn index(self) -> usize {
+        match self {
+            Pass::Grid => 0,
+            Pass::Quad => 1,
+            Pass::Graphics => 2,
+            Pass::Rects => 3,
+        }
+    }
+}
+
+/// Work submitted by a single render pass.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PassStats {
+    pub draw_calls: usize,
+    pub uploaded_bytes: usize,
+
+    /// Time the GPU spent on the pass, if it was measured.
+    pub gpu_time: Option<Duration>,
+}
+
+impl PassStats {
+    pub fn add(&mut self, other: PassStats) {
+        self.draw_calls += other.draw_calls;
+        self.uploaded_bytes += other.uploaded_bytes;
+    }
+}
+
+/// GPU time, draw calls and uploaded data of the pass.
+impl Display for PassStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.gpu_time {
+            Some(gpu_time) => write!(f, "{:.3} ms", gpu_time.as_secs_f64() * 1000.)?,
+            None => write!(f, "- ms")?,
+        }
+
+        write!(f, " {} draws {} KiB", self.draw_calls, (self.uploaded_bytes + 1023) / 1024)
+    }
+}
+
+/// Statistics of one rendered frame.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct RenderStats {
+    pub grid: PassStats,
+    pub quad: PassStats,
+    pub graphics: PassStats,
+    pub rects: PassStats,
+}
+
+impl Display for RenderStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "grid {} | quad {} | graphics {} | rects {}",
+            self.grid, self.quad, self.graphics, self.rects
+        )
+    }
+}
+
+impl RenderStats {
+    pub fn pass_mut(&mut self, pass: Pass) -> &mut PassStats {
+        match pass {
+            Pass::Grid => &mut self.grid,
+            Pass::Quad => &mut self.quad,
+            Pass::Graphics => &mut self.graphics,
+            Pass::Rects => &mut self.rects,
+        }
+    }
+}
+
+/// GPU timer queries for all passes.
+///
+/// Results only become available once the GPU has finished the frame, so queries alternate
+/// between two sets and every frame reads the results of the previous one.
+#[derive(Debug)]
+pub struct GpuTimer {
+    queries: [[GLuint; Pass::COUNT]; 2],
+
+    /// Whether a query has been started since the last time its result was read.
+    pending: [[bool; Pass::COUNT]; 2],
+
+    /// Set of queries used by the current frame.
+    current: usize,
+
+    /// Whether a query is currently running.
+    active: bool,
+}
+
+impl GpuTimer {
+    pub fn new() -> Self {
+        let mut queries = [[0; Pass::COUNT]; 2];
+        unsafe {
+            for set in &mut queries {
+                gl::GenQueries(Pass::COUNT as i32, set.as_mut_ptr());
+            }
+        }
+
+        Self { queries, pending: [[false; Pass::COUNT]; 2], current: 0, active: false }
+    }
+
+    /// Switch to the other set of queries and collect its results into `stats`.
+    ///
+    /// The collected GPU times are from the frame before the one the `stats` were counted in,
+    /// which avoids stalling until the GPU has caught up.
+    pub fn start_frame(&mut self, stats: &mut RenderStats) {
+        self.current ^= 1;
+
+        for pass in &[Pass::Grid, Pass::Quad, Pass::Graphics, Pass::Rects] {
+            let index = pass.index();
+            if !self.pending[self.current][index] {
+                continue;
+            }
+            self.pending[self.current][index] = false;
+
+            let query = self.queries[self.current][index];
+            let mut available: GLint = 0;
+            let mut elapsed: GLuint64 = 0;
+            unsafe {
+                gl::GetQueryObjectiv(query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+                if available != 0 {
+                    gl::GetQueryObjectui64v(query, gl::QUERY_RESULT, &mut elapsed);
+                }
+            }
+
+            if available != 0 {
+                stats.pass_mut(*pass).gpu_time = Some(Duration::from_nanos(elapsed));
+            }
+        }
+    }
+
+    /// Start measuring `pass`.
+    pub fn begin(&mut self, pass: Pass) {
+        let index = pass.index();
+
+        // Only one query of each set can be active, so skip passes that are drawn twice.
+        if self.pending[self.current][index] {
+            return;
+        }
+        self.pending[self.current][index] = true;
+        self.active = true;
+
+        unsafe {
+            gl::BeginQuery(gl::TIME_ELAPSED, self.queries[self.current][index]);
+        }
+    }
+
+    /// Stop measuring the pass started last.
+    pub fn end(&mut self) {
+        if !self.active {
+            return;
+        }
+        self.active = false;
+
+        unsafe {
+            gl::EndQuery(gl::TIME_ELAPSED);
+        }
+    }
+}
+
+impl Drop for GpuTimer {
+    fn drop(&mut self) {
+        unsafe {
+            for set in &self.queries {
+                gl::DeleteQueries(Pass::COUNT as i32, set.as_ptr());
+            }
+        }
+    }
+}
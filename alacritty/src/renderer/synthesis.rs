@@ -0,0 +1,174 @@
+//! Synthetic bold and italic styles for fonts without these faces.
+
+use crossfont::{BitmapBuffer, FontKey, RasterizedGlyph};
+
+/// Horizontal shift of synthetic italics per pixel above the baseline, about 11 degrees.
+const ITALIC_SHEAR: f32 = 0.2;
+
+/// Cell height per pixel of synthetic bold stroke width.
+const BOLD_STRENGTH_DIVISOR: i32 = 16;
+
+/// Transformations applied to glyphs of a missing style.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Synthesis {
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// Style which is rendered by transforming the glyphs of another face.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SyntheticFont {
+    /// Key used for glyphs of the style.
+    pub key: FontKey,
+
+    /// Face the glyphs are rasterized from.
+    pub base: FontKey,
+
+    pub synthesis: Synthesis,
+}
+
+/// Transform a glyph rasterized from the base face of a synthetic style.
+///
+/// Colored glyphs like emoji are left unchanged.
+pub fn apply(glyph: &mut RasterizedGlyph, synthesis: Synthesis, cell_height: i32) {
+    if glyph.width <= 0 || glyph.height <= 0 {
+        return;
+    }
+
+    if let BitmapBuffer::RGB(_) = glyph.buf {
+        if synthesis.bold {
+            embolden(glyph, (cell_height / BOLD_STRENGTH_DIVISOR).max(1));
+        }
+
+        if synthesis.italic {
+            shear(glyph);
+        }
+    }
+}
+
+/// Widen strokes by drawing the glyph again, shifted right by up to `strength` pixels.
+fn embolden(glyph: &mut RasterizedGlyph, strength: i32) {
+    let buf = match &glyph.buf {
+        BitmapBuffer::RGB(buf) => buf,
+        BitmapBuffer::RGBA(_) => return,
+    };
+
+    let width = glyph.width as usize;
+    let height = glyph.height as usize;
+    let strength = strength as usize;
+    let new_width = width + strength;
+
+    let mut emboldened = vec![0u8; new_width * height * 3];
+    for y in 0..height {
+        for x in 0..width {
+            let src = (y * width + x) * 3;
+            for offset in 0..=strength {
+                let dst = (y * new_width + x + offset) * 3;
+                for channel in 0..3 {
+                    emboldened[dst + channel] = emboldened[dst + channel].max(buf[src + channel]);
+                }
+            }
+        }
+    }
+
+    glyph.width = new_width as i32;
+    glyph.buf = BitmapBuffer::RGB(emboldened);
+}
+
+/// Slant the glyph around its baseline.
+fn shear(glyph: &mut RasterizedGlyph) {
+    let buf = match &glyph.buf {
+        BitmapBuffer::RGB(buf) => buf,
+        BitmapBuffer::RGBA(_) => return,
+    };
+
+    let width = glyph.width as usize;
+    let height = glyph.height as usize;
+
+    // Shift of the center of each row, rows above the baseline move right.
+    let shift = |y: usize| (glyph.top as f32 - y as f32 - 0.5) * ITALIC_SHEAR;
+    let min_shift = shift(height - 1).floor();
+    let max_shift = shift(0).ceil();
+    let new_width = width + (max_shift - min_shift) as usize + 1;
+
+    let mut sheared = vec![0f32; new_width * height * 3];
+    for y in 0..height {
+        let row_shift = shift(y) - min_shift;
+        let whole = row_shift.floor() as usize;
+        let fraction = row_shift.fract();
+
+        for x in 0..width {
+            let src = (y * width + x) * 3;
+            let dst = (y * new_width + x + whole) * 3;
+            for channel in 0..3 {
+                let value = f32::from(buf[src + channel]);
+                sheared[dst + channel] += value * (1. - fraction);
+                sheared[dst + 3 + channel] += value * fraction;
+            }
+        }
+    }
+
+    glyph.left += min_shift as i32;
+    glyph.width = new_width as i32;
+    glyph.buf =
+        BitmapBuffer::RGB(sheared.iter().map(|value| value.round().min(255.) as u8).collect());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Glyph with a vertical line in its first column.
+    fn line_glyph(width: i32, height: i32, top: i32) -> RasterizedGlyph {
+        let mut buf = vec![0; (width * height * 3) as usize];
+        for y in 0..height as usize {
+            let start = y * width as usize * 3;
+            buf[start..start + 3].copy_from_slice(&[255; 3]);
+        }
+
+        RasterizedGlyph { c: 'l', width, height, top, left: 1, buf: BitmapBuffer::RGB(buf) }
+    }
+
+    fn coverage(glyph: &RasterizedGlyph, x: i32, y: i32) -> u8 {
+        match &glyph.buf {
+            BitmapBuffer::RGB(buf) => buf[((y * glyph.width + x) * 3) as usize],
+            BitmapBuffer::RGBA(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn bold_widens_strokes() {
+        let mut glyph = line_glyph(2, 3, 3);
+        apply(&mut glyph, Synthesis { bold: true, italic: false }, 20);
+
+        assert_eq!(glyph.width, 3);
+        assert_eq!(glyph.left, 1);
+        for y in 0..3 {
+            assert_eq!(coverage(&glyph, 0, y), 255);
+            assert_eq!(coverage(&glyph, 1, y), 255);
+            assert_eq!(coverage(&glyph, 2, y), 0);
+        }
+    }
+
+    #[test]
+    fn italic_slants_around_baseline() {
+        // Half of the line is below the baseline.
+        let mut glyph = line_glyph(1, 20, 10);
+        apply(&mut glyph, Synthesis { bold: false, italic: true }, 20);
+
+        let column = |y: i32| (0..glyph.width).max_by_key(|&x| coverage(&glyph, x, y)).unwrap();
+        assert!(column(0) > column(10));
+        assert!(column(10) > column(19));
+
+        // The baseline stays in place.
+        assert_eq!(glyph.left + column(10), 1);
+    }
+
+    #[test]
+    fn colored_glyphs_unchanged() {
+        let buf = BitmapBuffer::RGBA(vec![255; 16]);
+        let mut glyph = RasterizedGlyph { c: 'x', width: 2, height: 2, top: 2, left: 0, buf };
+        apply(&mut glyph, Synthesis { bold: true, italic: true }, 20);
+        assert_eq!(glyph.width, 2);
+    }
+}
@@ -1,80 +1,120 @@
 use crate::gl;
 use crate::gl::types::*;
 
+use std::fmt;
 use std::ptr;
 
+use super::gl_api::{Gl, GlApi};
+use super::math::Vec2;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum PixelFormat {
     RGBA8,
     RGB8,
 }
 
-pub struct TextureFormat {
-    internal: i32,
-    format: u32,
-    texel_type: u32,
+pub unsafe fn upload_texture(
+    gl: &impl GlApi,
+    width: i32,
+    height: i32,
+    format: PixelFormat,
+    ptr: *const libc::c_void,
+) {
+    gl.tex_image_2d(width, height, format, ptr);
 }
 
-pub fn get_gl_format(format: PixelFormat) -> TextureFormat {
-    match format {
-        PixelFormat::RGBA8 => TextureFormat {
-            internal: gl::RGBA as i32,
-            format: gl::RGBA,
-            texel_type: gl::UNSIGNED_BYTE,
-        },
-        PixelFormat::RGB8 => TextureFormat {
-            internal: gl::RGB as i32,
-            format: gl::RGB,
-            texel_type: gl::UNSIGNED_BYTE,
-        },
-    }
+/// Replace `lines` rows of the bound texture, starting at row `line`.
+pub unsafe fn upload_texture_rows(
+    gl: &impl GlApi,
+    width: i32,
+    line: i32,
+    lines: i32,
+    format: PixelFormat,
+    ptr: *const libc::c_void,
+) {
+    gl.tex_sub_image_2d(0, line, width, lines, format, ptr);
 }
 
-pub unsafe fn upload_texture(
+pub unsafe fn create_texture(
+    gl: &impl GlApi,
     width: i32,
     height: i32,
     format: PixelFormat,
-    ptr: *const libc::c_void,
-) {
-    let format = get_gl_format(format);
-    gl::TexImage2D(
-        gl::TEXTURE_2D,
-        0,
-        format.internal,
-        width,
-        height,
-        0,
-        format.format,
-        format.texel_type,
-        ptr,
-    );
+) -> GLuint {
+    gl.pixel_store(gl::UNPACK_ALIGNMENT, 1);
+
+    let id = gl.gen_texture();
+    gl.bind_texture(id);
+    gl.tex_image_2d(width, height, format, ptr::null());
+
+    gl.tex_parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+    gl.tex_parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+    gl.tex_parameter(gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+    gl.tex_parameter(gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+
+    gl.bind_texture(0);
+    id
+}
+
+/// Destination of the pixel uploads of an atlas.
+///
+/// Atlases only write their texture through this trait, which allows testing their packing without
+/// an OpenGL context.
+pub trait TextureUpload: fmt::Debug {
+    /// Replace the rectangle of `size` pixels at `origin` with `data` in `format`.
+    fn upload(&mut self, origin: Vec2<i32>, size: Vec2<i32>, format: PixelFormat, data: &[u8]);
+}
+
+/// OpenGL texture which is deleted when dropped.
+#[derive(Debug)]
+pub struct Texture {
+    pub id: GLuint,
+}
+
+impl TextureUpload for Texture {
+    fn upload(&mut self, origin: Vec2<i32>, size: Vec2<i32>, format: PixelFormat, data: &[u8]) {
+        unsafe {
+            Gl.bind_texture(self.id);
+            Gl.tex_sub_image_2d(
+                origin.x,
+                origin.y,
+                size.x,
+                size.y,
+                format,
+                data.as_ptr() as *const _,
+            );
+            Gl.bind_texture(0);
+        }
+    }
 }
 
-pub unsafe fn create_texture(width: i32, height: i32, format: PixelFormat) -> GLuint {
-    let mut id: GLuint = 0;
-    let format = get_gl_format(format);
-
-    gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
-
-    gl::GenTextures(1, &mut id);
-    gl::BindTexture(gl::TEXTURE_2D, id);
-    gl::TexImage2D(
-        gl::TEXTURE_2D,
-        0,
-        format.internal,
-        width,
-        height,
-        0,
-        format.format,
-        format.texel_type,
-        ptr::null(),
-    );
-
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-    // gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-
-    gl::BindTexture(gl::TEXTURE_2D, 0);
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            Gl.delete_texture(self.id);
+        }
+    }
+}
+
+/// Create an RGBA texture from decoded image pixels.
+///
+/// Unlike the atlas textures, images are filtered linearly and repeat outside of their bounds.
+pub unsafe fn create_image_texture(
+    gl: &impl GlApi,
+    width: i32,
+    height: i32,
+    pixels: &[u8],
+) -> GLuint {
+    let id = create_texture(gl, width, height, PixelFormat::RGBA8);
+
+    gl.bind_texture(id);
+    upload_texture(gl, width, height, PixelFormat::RGBA8, pixels.as_ptr() as *const _);
+
+    gl.tex_parameter(gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+    gl.tex_parameter(gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+    gl.tex_parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+    gl.tex_parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+
+    gl.bind_texture(0);
     id
 }
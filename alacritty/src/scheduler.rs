@@ -14,6 +14,14 @@ type Event = GlutinEvent<'static, AlacrittyEvent>;
 pub enum TimerId {
     SelectionScrolling,
     DelayedSearch,
+    SearchCount,
+    BlinkCursor,
+    BlinkText,
+    SyncUpdate,
+    Idle,
+    Title,
+    TouchLongPress,
+    TouchMomentum,
 }
 
 /// Event scheduled to be emitted at a specific time.
@@ -58,6 +66,11 @@ impl Scheduler {
             }
         }
 
+        self.next_deadline()
+    }
+
+    /// Deadline of the closest pending timer.
+    pub fn next_deadline(&self) -> Option<Instant> {
         self.timers.get(0).map(|timer| timer.deadline)
     }
 
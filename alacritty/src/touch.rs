@@ -0,0 +1,367 @@
+//! Touchscreen gesture recognition.
+
+use std::f64::consts::LN_2;
+use std::time::{Duration, Instant};
+
+use glutin::dpi::PhysicalPosition;
+use glutin::event::TouchPhase;
+
+/// Time a finger has to rest on the screen before a selection is started.
+pub const LONG_PRESS_DURATION: Duration = Duration::from_millis(500);
+
+/// Interval between scroll updates while the viewport is coasting after a fling.
+pub const MOMENTUM_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Distance in logical pixels a finger can move before a press turns into a drag.
+const TOUCH_SLOP: f64 = 8.;
+
+/// Change of the distance between two fingers in logical pixels for one font size step.
+const PINCH_STEP: f64 = 24.;
+
+/// Minimum speed in logical pixels per second to keep scrolling after the finger is lifted.
+const MIN_FLING_VELOCITY: f64 = 50.;
+
+/// Time in seconds after which the speed of a fling is halved.
+const FLING_HALF_LIFE: f64 = 0.3;
+
+/// Time a finger can rest before being lifted, without stopping the scrolling.
+const MAX_FLING_REST: Duration = Duration::from_millis(100);
+
+/// Weight of the latest movement in the smoothed scrolling speed.
+const VELOCITY_SMOOTHING: f64 = 0.4;
+
+/// Gesture recognized from touch input.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Gesture {
+    /// A finger touched the screen, which might become a long press.
+    Press,
+
+    /// Scroll the viewport by a number of pixels.
+    Scroll(f64),
+
+    /// Finger lifted while scrolling fast enough to keep coasting.
+    Fling,
+
+    /// Change the font size by a number of steps.
+    Zoom(i32),
+
+    /// Extend the selection started by a long press.
+    Select(PhysicalPosition<f64>),
+
+    /// Finger lifted after selecting.
+    SelectionEnd,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum State {
+    Idle,
+
+    /// Single finger which has not moved far enough to start scrolling.
+    Pressed {
+        time: Instant,
+        start: PhysicalPosition<f64>,
+    },
+
+    /// Finger dragged with the last time and smoothed speed of its movement.
+    Scrolling {
+        time: Instant,
+        velocity: f64,
+    },
+
+    /// Viewport coasting after the finger was lifted.
+    Flinging {
+        time: Instant,
+        velocity: f64,
+    },
+
+    /// Selection started by a long press.
+    Selecting,
+
+    /// Two fingers with the distance of the last font size change.
+    Pinching {
+        distance: f64,
+    },
+
+    /// Fingers are ignored until all of them have been lifted.
+    Cancelled,
+}
+
+/// Recognizer turning touch events into gestures.
+#[derive(Debug)]
+pub struct TouchGestures {
+    /// Fingers on the screen with their last position.
+    fingers: Vec<(u64, PhysicalPosition<f64>)>,
+    state: State,
+}
+
+impl Default for TouchGestures {
+    fn default() -> Self {
+        Self { fingers: Vec::new(), state: State::Idle }
+    }
+}
+
+impl TouchGestures {
+    /// Process a touch event.
+    ///
+    /// All distances are scaled by the `dpr`, positions are expected in physical pixels.
+    pub fn input(
+        &mut self,
+        id: u64,
+        phase: TouchPhase,
+        position: PhysicalPosition<f64>,
+        now: Instant,
+        dpr: f64,
+    ) -> Option<Gesture> {
+        match phase {
+            TouchPhase::Started => self.press(id, position, now),
+            TouchPhase::Moved => self.drag(id, position, now, dpr),
+            TouchPhase::Ended | TouchPhase::Cancelled => self.release(id, now, dpr),
+        }
+    }
+
+    /// Start selecting, if a single finger has been resting long enough.
+    pub fn long_press(&mut self, now: Instant) -> Option<PhysicalPosition<f64>> {
+        match self.state {
+            State::Pressed { time, .. }
+                if self.fingers.len() == 1
+                    && now.saturating_duration_since(time) >= LONG_PRESS_DURATION =>
+            {
+                self.state = State::Selecting;
+                Some(self.fingers[0].1)
+            },
+            _ => None,
+        }
+    }
+
+    /// Pixels to scroll while coasting after a fling, `None` once it came to a halt.
+    pub fn momentum(&mut self, now: Instant, dpr: f64) -> Option<f64> {
+        let (time, velocity) = match self.state {
+            State::Flinging { time, velocity } => (time, velocity),
+            _ => return None,
+        };
+
+        let elapsed = now.saturating_duration_since(time).as_secs_f64();
+        let decayed = velocity * 0.5f64.powf(elapsed / FLING_HALF_LIFE);
+        if decayed.abs() < MIN_FLING_VELOCITY * dpr {
+            self.state = State::Idle;
+            return None;
+        }
+
+        self.state = State::Flinging { time: now, velocity: decayed };
+
+        // Distance covered by the exponentially decaying speed.
+        Some((velocity - decayed) * FLING_HALF_LIFE / LN_2)
+    }
+
+    fn press(&mut self, id: u64, position: PhysicalPosition<f64>, now: Instant) -> Option<Gesture> {
+        self.fingers.retain(|&(finger, _)| finger != id);
+        self.fingers.push((id, position));
+
+        match (self.fingers.len(), self.state) {
+            (1, _) => {
+                self.state = State::Pressed { time: now, start: position };
+                Some(Gesture::Press)
+            },
+            (2, State::Pressed { .. }) | (2, State::Scrolling { .. }) => {
+                self.state = State::Pinching { distance: self.finger_distance() };
+                None
+            },
+            (_, State::Selecting) => None,
+            _ => {
+                self.state = State::Cancelled;
+                None
+            },
+        }
+    }
+
+    fn drag(
+        &mut self,
+        id: u64,
+        position: PhysicalPosition<f64>,
+        now: Instant,
+        dpr: f64,
+    ) -> Option<Gesture> {
+        let finger = self.fingers.iter_mut().find(|(finger, _)| *finger == id)?;
+        let delta = position.y - finger.1.y;
+        finger.1 = position;
+
+        match self.state {
+            State::Pressed { start, .. } => {
+                let distance = (position.x - start.x).hypot(position.y - start.y);
+                if distance < TOUCH_SLOP * dpr {
+                    return None;
+                }
+
+                self.state = State::Scrolling { time: now, velocity: 0. };
+                Some(Gesture::Scroll(position.y - start.y))
+            },
+            State::Scrolling { time, velocity } => {
+                let elapsed = now.saturating_duration_since(time).as_secs_f64();
+                let velocity = if elapsed > 0. {
+                    velocity * (1. - VELOCITY_SMOOTHING) + delta / elapsed * VELOCITY_SMOOTHING
+                } else {
+                    velocity
+                };
+
+                self.state = State::Scrolling { time: now, velocity };
+                Some(Gesture::Scroll(delta))
+            },
+            State::Selecting => Some(Gesture::Select(position)),
+            State::Pinching { distance } => {
+                let step = PINCH_STEP * dpr;
+                let steps = ((self.finger_distance() - distance) / step) as i32;
+                if steps == 0 {
+                    return None;
+                }
+
+                self.state = State::Pinching { distance: distance + f64::from(steps) * step };
+                Some(Gesture::Zoom(steps))
+            },
+            State::Idle | State::Flinging { .. } | State::Cancelled => None,
+        }
+    }
+
+    fn release(&mut self, id: u64, now: Instant, dpr: f64) -> Option<Gesture> {
+        self.fingers.retain(|&(finger, _)| finger != id);
+
+        let gesture = match self.state {
+            State::Scrolling { time, velocity }
+                if now.saturating_duration_since(time) <= MAX_FLING_REST
+                    && velocity.abs() >= MIN_FLING_VELOCITY * dpr =>
+            {
+                self.state = State::Flinging { time: now, velocity };
+                return Some(Gesture::Fling);
+            },
+            State::Selecting => Some(Gesture::SelectionEnd),
+            _ => None,
+        };
+
+        // Lifting one finger of a pinch should not scroll with the other one.
+        self.state = if self.fingers.is_empty() { State::Idle } else { State::Cancelled };
+
+        gesture
+    }
+
+    /// Distance between the first two fingers.
+    fn finger_distance(&self) -> f64 {
+        match self.fingers.as_slice() {
+            [(_, first), (_, second), ..] => (first.x - second.x).hypot(first.y - second.y),
+            _ => 0.,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(
+        gestures: &mut TouchGestures,
+        id: u64,
+        phase: TouchPhase,
+        (x, y): (f64, f64),
+        now: Instant,
+    ) -> Option<Gesture> {
+        gestures.input(id, phase, PhysicalPosition::new(x, y), now, 1.)
+    }
+
+    #[test]
+    fn drag_scrolls_after_slop() {
+        let mut gestures = TouchGestures::default();
+        let start = Instant::now();
+
+        assert_eq!(
+            touch(&mut gestures, 0, TouchPhase::Started, (10., 10.), start),
+            Some(Gesture::Press)
+        );
+        assert_eq!(touch(&mut gestures, 0, TouchPhase::Moved, (10., 14.), start), None);
+        assert_eq!(
+            touch(&mut gestures, 0, TouchPhase::Moved, (10., 30.), start),
+            Some(Gesture::Scroll(20.))
+        );
+        assert_eq!(
+            touch(&mut gestures, 0, TouchPhase::Moved, (10., 25.), start),
+            Some(Gesture::Scroll(-5.))
+        );
+
+        // A resting finger is lifted without momentum.
+        let later = start + Duration::from_secs(1);
+        assert_eq!(touch(&mut gestures, 0, TouchPhase::Ended, (10., 25.), later), None);
+        assert_eq!(gestures.momentum(later, 1.), None);
+    }
+
+    #[test]
+    fn fling_decays() {
+        let mut gestures = TouchGestures::default();
+        let mut now = Instant::now();
+
+        touch(&mut gestures, 0, TouchPhase::Started, (0., 0.), now);
+        touch(&mut gestures, 0, TouchPhase::Moved, (0., 20.), now);
+        for y in 1..10 {
+            now += Duration::from_millis(10);
+            touch(&mut gestures, 0, TouchPhase::Moved, (0., 20. + f64::from(y) * 10.), now);
+        }
+        assert_eq!(
+            touch(&mut gestures, 0, TouchPhase::Ended, (0., 110.), now),
+            Some(Gesture::Fling)
+        );
+
+        let mut total = 0.;
+        let mut last = f64::MAX;
+        while let Some(delta) = gestures.momentum(now + MOMENTUM_INTERVAL, 1.) {
+            now += MOMENTUM_INTERVAL;
+            assert!(delta > 0. && delta < last);
+            last = delta;
+            total += delta;
+        }
+
+        assert!(total > 100.);
+
+        // Touching the screen again stops the scrolling.
+        touch(&mut gestures, 0, TouchPhase::Started, (0., 0.), now);
+        assert_eq!(gestures.momentum(now + MOMENTUM_INTERVAL, 1.), None);
+    }
+
+    #[test]
+    fn pinch_zooms_in_steps() {
+        let mut gestures = TouchGestures::default();
+        let now = Instant::now();
+
+        touch(&mut gestures, 0, TouchPhase::Started, (100., 100.), now);
+        assert_eq!(touch(&mut gestures, 1, TouchPhase::Started, (200., 100.), now), None);
+        assert_eq!(touch(&mut gestures, 1, TouchPhase::Moved, (210., 100.), now), None);
+        assert_eq!(
+            touch(&mut gestures, 1, TouchPhase::Moved, (260., 100.), now),
+            Some(Gesture::Zoom(2))
+        );
+        assert_eq!(
+            touch(&mut gestures, 0, TouchPhase::Moved, (140., 100.), now),
+            Some(Gesture::Zoom(-1))
+        );
+
+        // The remaining finger does not scroll.
+        touch(&mut gestures, 1, TouchPhase::Ended, (260., 100.), now);
+        assert_eq!(touch(&mut gestures, 0, TouchPhase::Moved, (140., 300.), now), None);
+    }
+
+    #[test]
+    fn long_press_selects() {
+        let mut gestures = TouchGestures::default();
+        let now = Instant::now();
+
+        touch(&mut gestures, 0, TouchPhase::Started, (10., 10.), now);
+        touch(&mut gestures, 0, TouchPhase::Moved, (12., 10.), now);
+        assert_eq!(gestures.long_press(now), None);
+
+        let later = now + LONG_PRESS_DURATION;
+        assert_eq!(gestures.long_press(later), Some(PhysicalPosition::new(12., 10.)));
+        assert_eq!(
+            touch(&mut gestures, 0, TouchPhase::Moved, (50., 10.), later),
+            Some(Gesture::Select(PhysicalPosition::new(50., 10.)))
+        );
+        assert_eq!(
+            touch(&mut gestures, 0, TouchPhase::Ended, (50., 10.), later),
+            Some(Gesture::SelectionEnd)
+        );
+    }
+}
@@ -1,41 +1,45 @@
-use std::cmp::min;
 use std::mem;
+use std::path::{Path, PathBuf};
 
-use crossfont::Metrics;
 use glutin::event::{ElementState, ModifiersState};
 use urlocator::{UrlLocation, UrlLocator};
 
 use alacritty_terminal::index::{Column, Point};
 use alacritty_terminal::term::cell::Flags;
 use alacritty_terminal::term::color::Rgb;
-use alacritty_terminal::term::{RenderableCell, RenderableCellContent, SizeInfo};
+use alacritty_terminal::term::{RenderableCell, RenderableCellContent};
 
 use crate::config::Config;
 use crate::event::Mouse;
-use crate::renderer::rects::{RenderLine, RenderRect};
+use crate::renderer::rects::RenderLine;
+
+/// Prefixes which start a file system path.
+const PATH_PREFIXES: [&str; 4] = ["/", "~/", "./", "../"];
+
+/// Characters which are not part of a path when they terminate it.
+const TRAILING_PUNCTUATION: &str = ".,:;!?";
+
+/// Characters delimiting paths by default, in addition to whitespace.
+pub const DEFAULT_PATH_ESCAPE_CHARS: &str = "│`|\"'()[]{}<>";
+
+/// Type of target a [`Url`] points to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum UrlKind {
+    Url,
+    Path,
+    /// OSC 8 hyperlink with its ID in the terminal.
+    Hyperlink(u16),
+}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Url {
     lines: Vec<RenderLine>,
     end_offset: u16,
     num_cols: Column,
+    kind: UrlKind,
 }
 
 impl Url {
-    pub fn rects(&self, metrics: &Metrics, size: &SizeInfo) -> Vec<RenderRect> {
-        let end = self.end();
-        self.lines
-            .iter()
-            .filter(|line| line.start <= end)
-            .map(|line| {
-                let mut rect_line = *line;
-                rect_line.end = min(line.end, end);
-                rect_line.rects(Flags::UNDERLINE, metrics, size)
-            })
-            .flatten()
-            .collect()
-    }
-
     pub fn start(&self) -> Point {
         self.lines[0].start
     }
@@ -43,97 +47,220 @@ impl Url {
     pub fn end(&self) -> Point {
         self.lines[self.lines.len() - 1].end.sub(self.num_cols, self.end_offset as usize)
     }
+
+    pub fn kind(&self) -> UrlKind {
+        self.kind
+    }
+
+    /// Check if a point is part of the URL.
+    pub fn contains(&self, point: Point) -> bool {
+        match self.kind {
+            // Hyperlinks can be split into multiple runs, with unrelated text in between.
+            UrlKind::Hyperlink(_) => {
+                self.lines.iter().any(|line| (line.start..=line.end).contains(&point))
+            },
+            _ => (self.start()..=self.end()).contains(&point),
+        }
+    }
+
+    /// Check if a cell is underlined while the URL is highlighted.
+    ///
+    /// Hyperlinks include all cells with the same link, even if they are not next to each other.
+    pub fn underlines(&self, cell: &RenderableCell) -> bool {
+        match self.kind {
+            UrlKind::Hyperlink(id) => cell.hyperlink == id,
+            _ => self.contains(Point::from(*cell)),
+        }
+    }
 }
 
-pub struct Urls {
-    locator: UrlLocator,
-    urls: Vec<Url>,
-    scheme_buffer: Vec<RenderableCell>,
-    last_point: Option<Point>,
-    state: UrlLocation,
+/// Resolve a detected path to a location on the file system.
+///
+/// Paths starting with `~/` are relative to `home`, all other relative paths are resolved
+/// against `cwd`. Trailing `:line:column` suffixes are removed.
+pub fn resolve_path(path: &str, home: Option<&Path>, cwd: Option<&Path>) -> PathBuf {
+    let mut path = path;
+    while let Some(index) = path.rfind(':') {
+        let suffix = &path[index + 1..];
+        if suffix.is_empty() || !suffix.bytes().all(|b| b.is_ascii_digit()) {
+            break;
+        }
+        path = &path[..index];
+    }
+
+    match (home, cwd) {
+        (Some(home), _) if path.starts_with("~/") => home.join(&path[2..]),
+        (_, Some(cwd)) if !path.starts_with('/') && !path.starts_with('~') => cwd.join(path),
+        _ => PathBuf::from(path),
+    }
 }
 
-impl Default for Urls {
-    fn default() -> Self {
-        Self {
-            locator: UrlLocator::new(),
-            scheme_buffer: Vec::new(),
-            urls: Vec::new(),
-            state: UrlLocation::Reset,
-            last_point: None,
+/// Incremental parser for link targets.
+trait Locator {
+    fn advance(&mut self, c: char) -> UrlLocation;
+
+    /// Start over, forgetting all characters seen so far.
+    fn reset(&mut self);
+}
+
+impl Locator for UrlLocator {
+    fn advance(&mut self, c: char) -> UrlLocation {
+        let location = UrlLocator::advance(self, c);
+
+        // Start over once the current URL has been terminated.
+        if let UrlLocation::Reset = location {
+            Locator::reset(self);
         }
+
+        location
+    }
+
+    fn reset(&mut self) {
+        *self = UrlLocator::new();
     }
 }
 
-impl Urls {
-    pub fn new() -> Self {
-        Self::default()
+/// Locator for file system paths, following the states of the `UrlLocator`.
+///
+/// Paths must start at the beginning of a word with one of the [`PATH_PREFIXES`].
+#[derive(Default)]
+struct PathLocator {
+    prefix: String,
+    len: u16,
+    end_offset: u16,
+    in_word: bool,
+    /// Characters delimiting paths, in addition to whitespace.
+    escape_chars: String,
+}
+
+impl PathLocator {
+    fn new(escape_chars: &str) -> Self {
+        Self { escape_chars: escape_chars.to_owned(), ..Self::default() }
     }
 
-    // Update tracked URLs.
-    pub fn update(&mut self, num_cols: Column, cell: RenderableCell) {
-        // Convert cell to character.
-        let c = match cell.inner {
-            RenderableCellContent::Chars(chars) => chars[0],
-            RenderableCellContent::Cursor(_) => return,
-        };
+    fn is_delimiter(&self, c: char) -> bool {
+        c.is_whitespace() || self.escape_chars.contains(c)
+    }
 
-        let point: Point = cell.into();
-        let end = point;
+    fn next_end_offset(&self, c: char) -> u16 {
+        if TRAILING_PUNCTUATION.contains(c) {
+            self.end_offset + 1
+        } else {
+            0
+        }
+    }
+}
 
-        // Reset URL when empty cells have been skipped.
-        if point != Point::default() && Some(point.sub(num_cols, 1)) != self.last_point {
+impl Locator for PathLocator {
+    fn advance(&mut self, c: char) -> UrlLocation {
+        if self.is_delimiter(c) {
             self.reset();
+            return UrlLocation::Reset;
         }
 
-        self.last_point = Some(end);
+        // Extend the path once its prefix has been found.
+        if self.len > 0 {
+            self.len = self.len.saturating_add(1);
+            self.end_offset = self.next_end_offset(c);
+            return UrlLocation::Url(self.len, self.end_offset);
+        }
 
-        // Extend current state if a wide char spacer is encountered.
-        if cell.flags.intersects(Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER) {
-            if let UrlLocation::Url(_, mut end_offset) = self.state {
-                if end_offset != 0 {
-                    end_offset += 1;
-                }
+        // Ignore the rest of a word which didn't start with a path prefix.
+        if self.in_word {
+            return UrlLocation::Reset;
+        }
 
-                self.extend_url(point, end, cell.fg, end_offset);
-            }
+        self.prefix.push(c);
 
-            return;
+        let prefix = self.prefix.as_str();
+        if PATH_PREFIXES.iter().any(|candidate| candidate.starts_with(prefix)) {
+            return UrlLocation::Scheme;
         }
 
-        // Advance parser.
+        // Require at least one character after the prefix.
+        let without_last = &prefix[..prefix.len() - c.len_utf8()];
+        if PATH_PREFIXES.contains(&without_last) {
+            self.len = prefix.chars().count() as u16;
+            self.end_offset = self.next_end_offset(c);
+            return UrlLocation::Url(self.len, self.end_offset);
+        }
+
+        self.prefix.clear();
+        self.in_word = true;
+        UrlLocation::Reset
+    }
+
+    fn reset(&mut self) {
+        let escape_chars = mem::take(&mut self.escape_chars);
+        *self = Self { escape_chars, ..Self::default() };
+    }
+}
+
+/// Tracking state for one type of link target.
+struct Detector<L> {
+    locator: L,
+    kind: UrlKind,
+    state: UrlLocation,
+    scheme_buffer: Vec<RenderableCell>,
+    /// Index of the URL which is currently being extended.
+    current: usize,
+}
+
+impl<L: Locator> Detector<L> {
+    fn new(locator: L, kind: UrlKind) -> Self {
+        Self { locator, kind, state: UrlLocation::Reset, scheme_buffer: Vec::new(), current: 0 }
+    }
+
+    /// Advance the parser with the next cell.
+    fn advance(&mut self, urls: &mut Vec<Url>, num_cols: Column, cell: RenderableCell, c: char) {
+        let point: Point = cell.into();
+
         let last_state = mem::replace(&mut self.state, self.locator.advance(c));
         match (self.state, last_state) {
             (UrlLocation::Url(_length, end_offset), UrlLocation::Scheme) => {
                 // Create empty URL.
-                self.urls.push(Url { lines: Vec::new(), end_offset, num_cols });
+                self.current = urls.len();
+                urls.push(Url { lines: Vec::new(), end_offset, num_cols, kind: self.kind });
 
                 // Push schemes into URL.
                 for scheme_cell in self.scheme_buffer.split_off(0) {
                     let point = scheme_cell.into();
-                    self.extend_url(point, point, scheme_cell.fg, end_offset);
+                    self.extend_url(urls, point, point, scheme_cell.fg, end_offset);
                 }
 
                 // Push the new cell into URL.
-                self.extend_url(point, end, cell.fg, end_offset);
+                self.extend_url(urls, point, point, cell.fg, end_offset);
             },
             (UrlLocation::Url(_length, end_offset), UrlLocation::Url(..)) => {
-                self.extend_url(point, end, cell.fg, end_offset);
+                self.extend_url(urls, point, point, cell.fg, end_offset);
             },
             (UrlLocation::Scheme, _) => self.scheme_buffer.push(cell),
-            (UrlLocation::Reset, _) => self.reset(),
+            (UrlLocation::Reset, _) => self.scheme_buffer.clear(),
             _ => (),
         }
+    }
 
-        // Reset at un-wrapped linebreak.
-        if cell.column + 1 == num_cols && !cell.flags.contains(Flags::WRAPLINE) {
-            self.reset();
+    /// Extend current state if a wide char spacer is encountered.
+    fn advance_spacer(&mut self, urls: &mut Vec<Url>, point: Point, color: Rgb) {
+        if let UrlLocation::Url(_, mut end_offset) = self.state {
+            if end_offset != 0 {
+                end_offset += 1;
+            }
+
+            self.extend_url(urls, point, point, color, end_offset);
         }
     }
 
-    /// Extend the last URL.
-    fn extend_url(&mut self, start: Point, end: Point, color: Rgb, end_offset: u16) {
-        let url = self.urls.last_mut().unwrap();
+    /// Extend the current URL.
+    fn extend_url(
+        &mut self,
+        urls: &mut Vec<Url>,
+        start: Point,
+        end: Point,
+        color: Rgb,
+        end_offset: u16,
+    ) {
+        let url = &mut urls[self.current];
 
         // If color changed, we need to insert a new line.
         if url.lines.last().map(|last| last.color) == Some(color) {
@@ -146,6 +273,119 @@ impl Urls {
         url.end_offset = end_offset;
     }
 
+    fn reset(&mut self) {
+        self.locator.reset();
+        self.state = UrlLocation::Reset;
+        self.scheme_buffer.clear();
+    }
+}
+
+pub struct Urls {
+    urls: Vec<Url>,
+    url_detector: Detector<UrlLocator>,
+    path_detector: Option<Detector<PathLocator>>,
+    /// Index of the hyperlink which is currently being extended.
+    hyperlink: Option<usize>,
+    last_point: Option<Point>,
+}
+
+impl Default for Urls {
+    fn default() -> Self {
+        Self::new(true, DEFAULT_PATH_ESCAPE_CHARS)
+    }
+}
+
+impl Urls {
+    /// Detect URLs, and paths delimited by whitespace or any of the `path_escape_chars`.
+    pub fn new(detect_paths: bool, path_escape_chars: &str) -> Self {
+        let path_detector = if detect_paths {
+            Some(Detector::new(PathLocator::new(path_escape_chars), UrlKind::Path))
+        } else {
+            None
+        };
+
+        Self {
+            urls: Vec::new(),
+            url_detector: Detector::new(UrlLocator::new(), UrlKind::Url),
+            path_detector,
+            hyperlink: None,
+            last_point: None,
+        }
+    }
+
+    // Update tracked URLs.
+    pub fn update(&mut self, num_cols: Column, cell: RenderableCell) {
+        // Convert cell to character.
+        let c = match cell.inner {
+            RenderableCellContent::Chars(chars) => chars[0],
+            RenderableCellContent::Cursor(_) => return,
+        };
+
+        let point: Point = cell.into();
+
+        // Reset URL when empty cells have been skipped.
+        if point != Point::default() && Some(point.sub(num_cols, 1)) != self.last_point {
+            self.reset();
+        }
+
+        self.last_point = Some(point);
+
+        // Hyperlinks take precedence over any links in their text.
+        if cell.hyperlink != 0 {
+            self.reset_detectors();
+            self.extend_hyperlink(num_cols, point, cell);
+        } else {
+            self.hyperlink = None;
+            self.advance_detectors(num_cols, point, cell, c);
+        }
+
+        // Reset at un-wrapped linebreak.
+        if cell.column + 1 == num_cols && !cell.flags.contains(Flags::WRAPLINE) {
+            self.reset();
+        }
+    }
+
+    fn advance_detectors(&mut self, num_cols: Column, point: Point, cell: RenderableCell, c: char) {
+        // Extend current state if a wide char spacer is encountered.
+        if cell.flags.intersects(Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER) {
+            self.url_detector.advance_spacer(&mut self.urls, point, cell.fg);
+            if let Some(path_detector) = &mut self.path_detector {
+                path_detector.advance_spacer(&mut self.urls, point, cell.fg);
+            }
+
+            return;
+        }
+
+        // Advance parsers.
+        self.url_detector.advance(&mut self.urls, num_cols, cell, c);
+        if let Some(path_detector) = &mut self.path_detector {
+            path_detector.advance(&mut self.urls, num_cols, cell, c);
+        }
+    }
+
+    /// Add a cell to the hyperlink with the same ID, or start a new one for unseen links.
+    ///
+    /// All runs of cells sharing a hyperlink ID are joined, so hovering any of them highlights
+    /// the entire link.
+    fn extend_hyperlink(&mut self, num_cols: Column, point: Point, cell: RenderableCell) {
+        let kind = UrlKind::Hyperlink(cell.hyperlink);
+        let continued = self.hyperlink.filter(|&index| self.urls[index].kind == kind);
+        let index = match continued.or_else(|| self.urls.iter().rposition(|url| url.kind == kind)) {
+            Some(index) => index,
+            None => {
+                self.urls.push(Url { lines: Vec::new(), end_offset: 0, num_cols, kind });
+                self.urls.len() - 1
+            },
+        };
+        self.hyperlink = Some(index);
+
+        let url = &mut self.urls[index];
+        match url.lines.last_mut() {
+            Some(last) if continued.is_some() && last.color == cell.fg => last.end = point,
+            _ => url.lines.push(RenderLine { color: cell.fg, start: point, end: point }),
+        }
+    }
+
     /// Find URL below the mouse cursor.
     pub fn highlighted(
         &self,
@@ -155,39 +395,55 @@ impl Urls {
         mouse_mode: bool,
         selection: bool,
     ) -> Option<Url> {
-        // Require additional shift in mouse mode.
-        let mut required_mods = config.ui_config.mouse.url.mods();
-        if mouse_mode {
-            required_mods |= ModifiersState::SHIFT;
-        }
-
         // Make sure all prerequisites for highlighting are met.
         if selection
             || !mouse.inside_text_area
             || config.ui_config.mouse.url.launcher.is_none()
-            || required_mods != mods
             || mouse.left_button_state == ElementState::Pressed
         {
             return None;
         }
 
-        self.find_at(Point::new(mouse.line, mouse.column))
+        let url = self.find_at(Point::new(mouse.line, mouse.column))?;
+
+        // Hyperlinks hide their target, so they use separate modifiers.
+        let mut required_mods = match url.kind {
+            UrlKind::Hyperlink(_) => config.ui_config.mouse.url.hyperlink_mods(),
+            _ => config.ui_config.mouse.url.mods(),
+        };
+
+        // Require additional shift in mouse mode.
+        if mouse_mode {
+            required_mods |= ModifiersState::SHIFT;
+        }
+
+        if required_mods != mods {
+            return None;
+        }
+
+        Some(url)
+    }
+
+    /// All URLs, in the order they appear on screen.
+    pub fn urls(&self) -> &[Url] {
+        &self.urls
     }
 
     /// Find URL at location.
     pub fn find_at(&self, point: Point) -> Option<Url> {
-        for url in &self.urls {
-            if (url.start()..=url.end()).contains(&point) {
-                return Some(url.clone());
-            }
-        }
-        None
+        self.urls.iter().find(|url| url.contains(point)).cloned()
     }
 
     fn reset(&mut self) {
-        self.locator = UrlLocator::new();
-        self.state = UrlLocation::Reset;
-        self.scheme_buffer.clear();
+        self.reset_detectors();
+        self.hyperlink = None;
+    }
+
+    fn reset_detectors(&mut self) {
+        self.url_detector.reset();
+        if let Some(path_detector) = &mut self.path_detector {
+            path_detector.reset();
+        }
     }
 }
 
@@ -197,6 +453,7 @@ mod tests {
 
     use alacritty_terminal::index::{Column, Line};
     use alacritty_terminal::term::cell::MAX_ZEROWIDTH_CHARS;
+    use alacritty_terminal::term::CellHighlight;
 
     fn text_to_cells(text: &str) -> Vec<RenderableCell> {
         text.chars()
@@ -207,8 +464,12 @@ mod tests {
                 column: Column(i),
                 fg: Default::default(),
                 bg: Default::default(),
+                fg_index: None,
+                bg_index: None,
                 bg_alpha: 0.,
                 flags: Flags::empty(),
+                highlight: CellHighlight::None,
+                hyperlink: 0,
             })
             .collect()
     }
@@ -220,7 +481,7 @@ mod tests {
 
         input[10].fg = Rgb { r: 0xff, g: 0x00, b: 0xff };
 
-        let mut urls = Urls::new();
+        let mut urls = Urls::default();
 
         for cell in input {
             urls.update(Column(num_cols), cell);
@@ -236,7 +497,7 @@ mod tests {
         let input = text_to_cells("test git:a git:b git:c ing");
         let num_cols = input.len();
 
-        let mut urls = Urls::new();
+        let mut urls = Urls::default();
 
         for cell in input {
             urls.update(Column(num_cols), cell);
@@ -253,4 +514,94 @@ mod tests {
         assert_eq!(urls.urls[2].start().col, Column(17));
         assert_eq!(urls.urls[2].end().col, Column(21));
     }
+
+    #[test]
+    fn paths() {
+        let input = text_to_cells("see ./src/main.rs:12: and ~/a, not a/b or http://x/y .");
+        let num_cols = input.len();
+
+        let mut urls = Urls::default();
+
+        for cell in input {
+            urls.update(Column(num_cols), cell);
+        }
+
+        let paths: Vec<_> = urls.urls.iter().filter(|url| url.kind() == UrlKind::Path).collect();
+        assert_eq!(paths.len(), 2);
+
+        assert_eq!(paths[0].start().col, Column(4));
+        assert_eq!(paths[0].end().col, Column(19));
+
+        assert_eq!(paths[1].start().col, Column(26));
+        assert_eq!(paths[1].end().col, Column(28));
+    }
+
+    #[test]
+    fn path_escape_chars() {
+        let input = text_to_cells("(./a/b) ./c=d");
+        let num_cols = input.len();
+
+        let mut urls = Urls::new(true, "()=");
+
+        for cell in input {
+            urls.update(Column(num_cols), cell);
+        }
+
+        assert_eq!(urls.urls.len(), 2);
+
+        assert_eq!(urls.urls[0].start().col, Column(1));
+        assert_eq!(urls.urls[0].end().col, Column(5));
+
+        assert_eq!(urls.urls[1].start().col, Column(8));
+        assert_eq!(urls.urls[1].end().col, Column(10));
+    }
+
+    #[test]
+    fn hyperlinks() {
+        let mut input = text_to_cells("ab https://x.org cd ef");
+        let num_cols = input.len();
+
+        for cell in &mut input[..18] {
+            cell.hyperlink = 1;
+        }
+        input[18].hyperlink = 2;
+        input[20].hyperlink = 1;
+
+        let mut urls = Urls::default();
+
+        for &cell in &input {
+            urls.update(Column(num_cols), cell);
+        }
+
+        // Text inside of hyperlinks is not searched for URLs, separate runs of a link are joined.
+        let kinds: Vec<_> = urls.urls.iter().map(|url| url.kind()).collect();
+        assert_eq!(kinds, vec![UrlKind::Hyperlink(1), UrlKind::Hyperlink(2)]);
+
+        assert_eq!(urls.urls[0].start().col, Column(0));
+        assert_eq!(urls.urls[0].end().col, Column(20));
+
+        // Hovering any run of a link finds the entire link.
+        let url = urls.find_at(Point::new(Line(0), Column(20))).unwrap();
+        assert_eq!(url, urls.urls[0]);
+        assert!(!url.contains(Point::new(Line(0), Column(19))));
+        assert!(!url.contains(Point::new(Line(0), Column(18))));
+
+        // All cells of a link are underlined together.
+        assert!(url.underlines(&input[0]));
+        assert!(!url.underlines(&input[18]));
+        assert!(!url.underlines(&input[19]));
+    }
+
+    #[test]
+    fn resolve_paths() {
+        let home = Path::new("/home/user");
+        let cwd = Path::new("/tmp");
+
+        let resolve = |path| resolve_path(path, Some(home), Some(cwd));
+        assert_eq!(resolve("~/a/b"), PathBuf::from("/home/user/a/b"));
+        assert_eq!(resolve("./src/main.rs:12:5"), PathBuf::from("/tmp/./src/main.rs"));
+        assert_eq!(resolve("../c"), PathBuf::from("/tmp/../c"));
+        assert_eq!(resolve("/etc/hosts"), PathBuf::from("/etc/hosts"));
+        assert_eq!(resolve("/a:b"), PathBuf::from("/a:b"));
+    }
 }
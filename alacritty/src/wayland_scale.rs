@@ -0,0 +1,102 @@
+//! Fractional scale factors on Wayland.
+//!
+//! Winit only supports integer scale factors on Wayland, so at a scale of 1.5 the window would be
+//! rendered at 2x and downscaled by the compositor. The `wp_fractional_scale_v1` protocol reports
+//! the exact scale factor instead, while a `wp_viewport` maps the buffer rendered at that scale
+//! onto the surface.
+
+use glutin::dpi::LogicalSize;
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::{Attached, EventQueue, GlobalManager, Main};
+use wayland_protocols::viewporter::client::wp_viewport::WpViewport;
+use wayland_protocols::viewporter::client::wp_viewporter::WpViewporter;
+
+use crate::event::{Event, EventProxy};
+
+use self::protocol::wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1;
+use self::protocol::wp_fractional_scale_v1::{Event as ScaleEvent, WpFractionalScaleV1};
+
+/// Denominator of the preferred scale sent by the compositor.
+const SCALE_DENOMINATOR: f64 = 120.;
+
+#[allow(dead_code, non_camel_case_types, unused_unsafe, unused_variables)]
+#[allow(non_upper_case_globals, non_snake_case, unused_imports)]
+#[allow(missing_docs, clippy::all)]
+mod protocol {
+    pub(crate) use wayland_client::protocol::wl_surface;
+    pub(crate) use wayland_client::sys;
+    pub(crate) use wayland_client::{AnonymousObject, Attached, Main, Proxy, ProxyMap};
+    pub(crate) use wayland_commons::map::{Object, ObjectMetadata};
+    pub(crate) use wayland_commons::smallvec;
+    pub(crate) use wayland_commons::wire::{Argument, ArgumentType, Message, MessageDesc};
+    pub(crate) use wayland_commons::{Interface, MessageGroup};
+
+    include!(concat!(env!("OUT_DIR"), "/fractional_scale_v1.rs"));
+}
+
+/// Surface rendered at the compositor's fractional scale factor.
+pub struct FractionalScale {
+    surface: Attached<WlSurface>,
+    viewport: Main<WpViewport>,
+    _fractional_scale: Main<WpFractionalScaleV1>,
+
+    /// Scale factor preferred by the compositor.
+    scale: Option<f64>,
+}
+
+impl FractionalScale {
+    /// Bind the protocols for the surface, if the compositor supports both of them.
+    ///
+    /// Changes of the preferred scale are sent to the event loop as
+    /// `Event::FractionalScaleChanged`.
+    pub fn new(
+        queue: &mut EventQueue,
+        surface: &Attached<WlSurface>,
+        event_proxy: EventProxy,
+    ) -> Option<Self> {
+        let display = queue.display().attach(queue.token());
+        let globals = GlobalManager::new(&display);
+        queue.sync_roundtrip(&mut (), |_, _, _| {}).ok()?;
+
+        let viewporter = globals.instantiate_exact::<WpViewporter>(1).ok()?;
+        let manager = globals.instantiate_exact::<WpFractionalScaleManagerV1>(1).ok()?;
+
+        let viewport = viewporter.get_viewport(surface);
+        let fractional_scale = manager.get_fractional_scale(surface);
+        fractional_scale.quick_assign(move |_, event, _| {
+            if let ScaleEvent::PreferredScale { scale } = event {
+                let scale = f64::from(scale) / SCALE_DENOMINATOR;
+                event_proxy.send_event(Event::FractionalScaleChanged(scale));
+            }
+        });
+
+        Some(Self {
+            surface: surface.clone(),
+            viewport,
+            _fractional_scale: fractional_scale,
+            scale: None,
+        })
+    }
+
+    /// Scale factor preferred by the compositor, once it has been received.
+    #[inline]
+    pub fn scale(&self) -> Option<f64> {
+        self.scale
+    }
+
+    #[inline]
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = Some(scale);
+    }
+
+    /// Map the whole buffer onto the logical size of the surface.
+    pub fn set_logical_size(&self, size: LogicalSize<i32>) {
+        if size.width <= 0 || size.height <= 0 {
+            return;
+        }
+
+        // Winit's integer buffer scale would be applied on top of the viewport.
+        self.surface.set_buffer_scale(1);
+        self.viewport.set_destination(size.width, size.height);
+    }
+}
@@ -1,10 +1,15 @@
 use glutin::platform::unix::{ARGBColor, Button, ButtonState, Element, Theme as WaylandTheme};
 
-use alacritty_terminal::config::Colors;
 use alacritty_terminal::term::color::Rgb;
 
+use crate::config::Config;
+
 const INACTIVE_OPACITY: u8 = 127;
 
+/// Font of the window title, matching winit's default.
+const TITLE_FONT: &str = "sans-serif";
+const TITLE_FONT_SIZE: f32 = 11.;
+
 #[derive(Debug, Clone)]
 pub struct AlacrittyWaylandTheme {
     pub background: ARGBColor,
@@ -13,15 +18,22 @@ pub struct AlacrittyWaylandTheme {
     pub hovered_close_icon: ARGBColor,
     pub hovered_maximize_icon: ARGBColor,
     pub hovered_minimize_icon: ARGBColor,
+    pub show_title: bool,
 }
 
 impl AlacrittyWaylandTheme {
-    pub fn new(colors: &Colors) -> Self {
-        let hovered_close_icon = colors.normal().red.into_rgba();
-        let hovered_maximize_icon = colors.normal().green.into_rgba();
-        let hovered_minimize_icon = colors.normal().yellow.into_rgba();
-        let foreground = colors.search_bar_foreground().into_rgba();
-        let background = colors.search_bar_background().into_rgba();
+    pub fn new(config: &Config) -> Self {
+        let colors = &config.colors;
+        let theme = &config.ui_config.window.decorations_theme;
+
+        let hovered_close_icon = theme.close_button.unwrap_or(colors.normal().red).into_rgba();
+        let hovered_maximize_icon =
+            theme.maximize_button.unwrap_or(colors.normal().green).into_rgba();
+        let hovered_minimize_icon =
+            theme.minimize_button.unwrap_or(colors.normal().yellow).into_rgba();
+        let foreground = theme.foreground.unwrap_or_else(|| colors.search_bar_foreground());
+        let background = theme.background.unwrap_or_else(|| colors.search_bar_background());
+        let (foreground, background) = (foreground.into_rgba(), background.into_rgba());
 
         let mut dim_foreground = foreground;
         dim_foreground.a = INACTIVE_OPACITY;
@@ -33,6 +45,7 @@ impl AlacrittyWaylandTheme {
             hovered_close_icon,
             hovered_minimize_icon,
             hovered_maximize_icon,
+            show_title: theme.show_title(),
         }
     }
 }
@@ -67,6 +80,14 @@ impl WaylandTheme for AlacrittyWaylandTheme {
             (_, Button::Close) => self.hovered_close_icon,
         }
     }
+
+    fn font(&self) -> Option<(String, f32)> {
+        if self.show_title {
+            Some((String::from(TITLE_FONT), TITLE_FONT_SIZE))
+        } else {
+            None
+        }
+    }
 }
 
 trait IntoARGBColor {
@@ -15,8 +15,7 @@ use {
     wayland_client::{Attached, EventQueue, Proxy},
     glutin::platform::unix::EventLoopWindowTargetExtUnix,
 
-    alacritty_terminal::config::Colors,
-
+    crate::wayland_scale::FractionalScale,
     crate::wayland_theme::AlacrittyWaylandTheme,
 };
 
@@ -142,9 +141,17 @@ pub struct Window {
     #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
     pub wayland_surface: Option<Attached<WlSurface>>,
 
+    /// Viewport for rendering at fractional scale factors on Wayland.
+    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+    pub fractional_scale: Option<FractionalScale>,
+
     /// Cached DPR for quickly scaling pixel sizes.
     pub dpr: f64,
 
+    /// Last position of the IME candidate window.
+    #[cfg(not(windows))]
+    ime_position: Option<PhysicalPosition<f64>>,
+
     windowed_context: WindowedContext<PossiblyCurrent>,
     current_mouse_cursor: CursorIcon,
     mouse_visible: bool,
@@ -170,11 +177,12 @@ impl Window {
         #[cfg(any(not(feature = "wayland"), target_os = "macos", windows))]
         let is_wayland = false;
 
+        // Without vsync, frames are presented as soon as they are done drawing.
+        let vsync = config.ui_config.renderer.vsync && !is_wayland;
+
         let windowed_context =
-            create_gl_window(window_builder.clone(), &event_loop, false, !is_wayland, size)
-                .or_else(|_| {
-                    create_gl_window(window_builder, &event_loop, true, !is_wayland, size)
-                })?;
+            create_gl_window(window_builder.clone(), &event_loop, false, vsync, size)
+                .or_else(|_| create_gl_window(window_builder, &event_loop, true, vsync, size))?;
 
         // Text cursor.
         let current_mouse_cursor = CursorIcon::Text;
@@ -194,7 +202,7 @@ impl Window {
         #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
         let wayland_surface = if is_wayland {
             // Apply client side decorations theme.
-            let theme = AlacrittyWaylandTheme::new(&config.colors);
+            let theme = AlacrittyWaylandTheme::new(config);
             windowed_context.window().set_wayland_theme(theme);
 
             // Attach surface to Alacritty's internal wayland queue to handle frame callbacks.
@@ -215,16 +223,60 @@ impl Window {
             should_draw: Arc::new(AtomicBool::new(true)),
             #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
             wayland_surface,
+            #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+            fractional_scale: None,
+            #[cfg(not(windows))]
+            ime_position: None,
             dpr,
         })
     }
 
     pub fn set_inner_size(&mut self, size: PhysicalSize<u32>) {
-        self.window().set_inner_size(size);
+        let scale = self.buffer_scale();
+        let width = (f64::from(size.width) / scale).round() as u32;
+        let height = (f64::from(size.height) / scale).round() as u32;
+        self.window().set_inner_size(PhysicalSize::new(width, height));
     }
 
     pub fn inner_size(&self) -> PhysicalSize<u32> {
-        self.window().inner_size()
+        self.scaled_size(self.window().inner_size())
+    }
+
+    /// Convert a size in winit's physical pixels to the rendered resolution.
+    pub fn scaled_size(&self, size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+        let scale = self.buffer_scale();
+        let width = (f64::from(size.width) * scale).round() as u32;
+        let height = (f64::from(size.height) * scale).round() as u32;
+        PhysicalSize::new(width, height)
+    }
+
+    /// Convert a position in winit's physical pixels to the rendered resolution.
+    pub fn scaled_position(&self, position: PhysicalPosition<f64>) -> PhysicalPosition<f64> {
+        let scale = self.buffer_scale();
+        PhysicalPosition::new(position.x * scale, position.y * scale)
+    }
+
+    /// Scale factor preferred by the compositor, if it differs from winit's integer scale.
+    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+    pub fn fractional_scale(&self) -> Option<f64> {
+        self.fractional_scale.as_ref().and_then(FractionalScale::scale)
+    }
+
+    #[cfg(any(not(feature = "wayland"), target_os = "macos", windows))]
+    pub fn fractional_scale(&self) -> Option<f64> {
+        None
+    }
+
+    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+    pub fn set_fractional_scale(&mut self, scale: f64) {
+        if let Some(fractional_scale) = self.fractional_scale.as_mut() {
+            fractional_scale.set_scale(scale);
+        }
+    }
+
+    /// Ratio between the rendered resolution and winit's physical pixels.
+    fn buffer_scale(&self) -> f64 {
+        self.fractional_scale().map_or(1., |scale| scale / self.window().scale_factor())
     }
 
     #[inline]
@@ -409,17 +461,24 @@ impl Window {
     }
 
     #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-    pub fn set_wayland_theme(&mut self, colors: &Colors) {
-        self.window().set_wayland_theme(AlacrittyWaylandTheme::new(colors));
+    pub fn set_wayland_theme(&mut self, config: &Config) {
+        self.window().set_wayland_theme(AlacrittyWaylandTheme::new(config));
     }
 
     /// Adjust the IME editor position according to the new location of the cursor.
     #[cfg(not(windows))]
     pub fn update_ime_position(&mut self, point: Point, size: &SizeInfo) {
-        let nspot_x = f64::from(size.padding_x() + point.col.0 as f32 * size.cell_width());
+        let nspot_x = f64::from(size.padding_left() + point.col.0 as f32 * size.cell_width());
         let nspot_y = f64::from(size.padding_y() + (point.line.0 + 1) as f32 * size.cell_height());
 
-        self.window().set_ime_position(PhysicalPosition::new(nspot_x, nspot_y));
+        let scale = self.buffer_scale();
+        let position = PhysicalPosition::new(nspot_x / scale, nspot_y / scale);
+
+        // Avoid round trips to the input method while the cursor is not moving.
+        if self.ime_position != Some(position) {
+            self.window().set_ime_position(position);
+            self.ime_position = Some(position);
+        }
     }
 
     /// No-op, since Windows does not support IME positioning.
@@ -432,6 +491,16 @@ impl Window {
 
     pub fn resize(&self, size: PhysicalSize<u32>) {
         self.windowed_context.resize(size);
+
+        // Scale the buffer down to the surface size expected by the compositor.
+        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+        if let Some(fractional_scale) = &self.fractional_scale {
+            if fractional_scale.scale().is_some() {
+                let window = self.window();
+                let size = window.inner_size().to_logical(window.scale_factor());
+                fractional_scale.set_logical_size(size);
+            }
+        }
     }
 
     fn window(&self) -> &GlutinWindow {
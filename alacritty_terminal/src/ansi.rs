@@ -1,14 +1,64 @@
 //! ANSI Terminal Stream Parsing.
 
 use std::convert::TryFrom;
-use std::{io, iter, str};
+use std::time::{Duration, Instant};
+use std::{io, iter, mem, str};
 
+use bitflags::bitflags;
 use log::{debug, trace};
 use serde::{Deserialize, Serialize};
 use vte::{Params, ParamsIter};
 
+use crate::graphics::iterm::InlineImage;
+use crate::graphics::{kitty, sixel, Image};
 use crate::index::{Column, Line};
 use crate::term::color::Rgb;
+use crate::term::marks::Mark;
+
+/// Largest value of numeric CSI parameters, larger values are clamped to it.
+///
+/// No count or position beyond this is useful on any screen, while passing them on would let a
+/// single `CSI Ps b` keep the terminal busy indefinitely and overflow cursor arithmetic.
+const MAX_CSI_PARAM: i64 = u16::MAX as i64;
+
+/// Maximum time a synchronized update is held back, before it is applied anyway.
+pub const SYNC_UPDATE_TIMEOUT: Duration = Duration::from_millis(150);
+
+/// Maximum number of bytes held back by a synchronized update.
+const MAX_SYNC_BYTES: usize = 0x20_0000;
+
+/// End of a synchronized update, `CSI ? 2026 l`.
+const SYNC_UPDATE_END: &[u8] = b"\x1b[?2026l";
+
+/// Parse the `key=value` parameters of a mark.
+///
+/// The `line` is counted upwards from the cursor, `columns` is an inclusive range of one-based
+/// columns and the entire line is marked without it.
+pub fn parse_mark(params: &[&[u8]]) -> Option<(usize, Mark)> {
+    let mut lines_above = 0;
+    let mut mark = Mark { columns: Column(0)..Column(usize::MAX), tint: None, icon: None };
+
+    for param in params {
+        let param = str::from_utf8(param).ok()?;
+        let mut split = param.splitn(2, '=');
+        let (key, value) = (split.next()?, split.next()?);
+
+        match key {
+            "line" => lines_above = value.parse().ok()?,
+            "columns" => {
+                let mut split = value.splitn(2, '-');
+                let first: usize = split.next()?.parse().ok()?;
+                let last: usize = split.next().map_or(Some(first), |last| last.parse().ok())?;
+                mark.columns = Column(first.max(1) - 1)..Column(last.max(first));
+            },
+            "tint" => mark.tint = Some(xparse_color(value.as_bytes())?),
+            "icon" => mark.icon = Some(value.chars().next()?),
+            _ => return None,
+        }
+    }
+
+    Some((lines_above, mark))
+}
 
 /// Parse colors in XParseColor format.
 fn xparse_color(color: &[u8]) -> Option<Rgb> {
@@ -82,8 +132,24 @@ pub struct Processor {
 }
 
 /// Internal state for VTE processor.
+#[derive(Default)]
 struct ProcessorState {
     preceding_char: Option<char>,
+
+    /// Decoder of the Sixel DCS sequence currently being received.
+    sixel_parser: Option<sixel::Parser>,
+
+    /// Contents of the APC string currently being received.
+    apc: Option<Vec<u8>>,
+
+    /// Whether the last byte was an escape.
+    escape: bool,
+
+    /// Start of the synchronized update in progress.
+    sync_start: Option<Instant>,
+
+    /// Bytes received since the synchronized update started.
+    sync_bytes: Vec<u8>,
 }
 
 /// Helper type that implements `vte::Perform`.
@@ -110,7 +176,7 @@ impl<'a, H: Handler + 'a, W: io::Write> Performer<'a, H, W> {
 
 impl Default for Processor {
     fn default() -> Processor {
-        Processor { state: ProcessorState { preceding_char: None }, parser: vte::Parser::new() }
+        Processor { state: ProcessorState::default(), parser: vte::Parser::new() }
     }
 }
 
@@ -125,9 +191,120 @@ impl Processor {
         H: Handler,
         W: io::Write,
     {
+        // Synchronized updates are held back until they end, so they are applied all at once.
+        if self.state.sync_start.is_some() {
+            self.state.sync_bytes.push(byte);
+            if self.state.sync_bytes.ends_with(SYNC_UPDATE_END)
+                || self.state.sync_bytes.len() >= MAX_SYNC_BYTES
+            {
+                self.stop_sync(handler, writer);
+            }
+            return;
+        }
+
+        self.process(handler, byte, writer);
+    }
+
+    /// Apply all bytes held back by the synchronized update in progress.
+    pub fn stop_sync<H, W>(&mut self, handler: &mut H, writer: &mut W)
+    where
+        H: Handler,
+        W: io::Write,
+    {
+        let bytes = mem::take(&mut self.state.sync_bytes);
+        for byte in bytes {
+            self.process(handler, byte, writer);
+        }
+
+        // Updates started by the applied bytes are not held back again, so the terminal must not
+        // report one in progress either, even when it timed out before its end was received.
+        self.state.sync_start = None;
+        handler.unset_mode(Mode::SyncUpdate);
+    }
+
+    /// Time at which the synchronized update in progress is applied, even if it has not ended.
+    pub fn sync_deadline(&self) -> Option<Instant> {
+        self.state.sync_start.map(|start| start + SYNC_UPDATE_TIMEOUT)
+    }
+
+    #[inline]
+    fn process<H, W>(&mut self, handler: &mut H, byte: u8, writer: &mut W)
+    where
+        H: Handler,
+        W: io::Write,
+    {
+        self.capture_apc(handler, byte, writer);
+
         let mut performer = Performer::new(&mut self.state, handler, writer);
         self.parser.advance(&mut performer, byte);
     }
+
+    /// Collect APC strings, which are ignored by the VTE parser.
+    fn capture_apc<H, W>(&mut self, handler: &mut H, byte: u8, writer: &mut W)
+    where
+        H: Handler,
+        W: io::Write,
+    {
+        let escape = mem::replace(&mut self.state.escape, byte == 0x1b);
+
+        match &mut self.state.apc {
+            Some(_) if escape && byte == b'\\' => {
+                let apc = self.state.apc.take().unwrap();
+                if apc.first() == Some(&b'G')
+                    && handler.capabilities().contains(Capabilities::IMAGES)
+                {
+                    match kitty::Command::parse(&apc[1..]) {
+                        Some(command) => handler.kitty_graphics(writer, command),
+                        None => debug!("Invalid kitty graphics command"),
+                    }
+                }
+            },
+            // CAN and SUB abort the string, like every other escape sequence.
+            Some(_) if escape || byte == 0x18 || byte == 0x1a => self.state.apc = None,
+            Some(apc) if byte != 0x1b && apc.len() < kitty::MAX_PAYLOAD_SIZE => apc.push(byte),
+            _ => (),
+        }
+
+        if escape && byte == b'_' {
+            self.state.apc = Some(Vec::new());
+        }
+    }
+}
+
+bitflags! {
+    /// Escape sequence features which can be disabled by the user.
+    pub struct Capabilities: u8 {
+        /// Window title changes with `OSC 0`, `OSC 2` and `CSI 23 t`.
+        const TITLE          = 0b0000_0001;
+        /// Clipboard writes with `OSC 52`.
+        const CLIPBOARD      = 0b0000_0010;
+        /// Hyperlinks with `OSC 8`.
+        const HYPERLINKS     = 0b0000_0100;
+        /// Sixel, kitty and iTerm2 images.
+        const IMAGES         = 0b0000_1000;
+        /// Desktop notifications with `OSC 9` and `OSC 777`.
+        const NOTIFICATIONS  = 0b0001_0000;
+        /// Background opacity changes with `OSC 777 ; opacity`.
+        const OPACITY        = 0b0010_0000;
+        /// Clipboard reads with `OSC 52 ; c ; ?`.
+        const CLIPBOARD_READ = 0b0100_0000;
+    }
+}
+
+impl Capabilities {
+    /// Feature required to handle an OSC sequence.
+    fn for_osc(params: &[&[u8]]) -> Capabilities {
+        match params {
+            [b"0", ..] | [b"2", ..] => Capabilities::TITLE,
+            [b"8", ..] => Capabilities::HYPERLINKS,
+            [b"777", b"opacity", ..] => Capabilities::OPACITY,
+            [b"9", ..] | [b"777", ..] => Capabilities::NOTIFICATIONS,
+            [b"52", _, b"?", ..] => Capabilities::CLIPBOARD_READ,
+            [b"52", ..] => Capabilities::CLIPBOARD,
+            [b"1337", ..] => Capabilities::IMAGES,
+            _ => Capabilities::empty(),
+        }
+    }
 }
 
 /// Type that handles actions from the parser.
@@ -135,12 +312,20 @@ impl Processor {
 /// XXX Should probably not provide default impls for everything, but it makes
 /// writing specific handler impls for tests far easier.
 pub trait Handler {
+    /// Escape sequence features which are enabled.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::all()
+    }
+
     /// OSC to set window title.
     fn set_title(&mut self, _: Option<String>) {}
 
     /// Set the cursor style.
     fn set_cursor_style(&mut self, _: Option<CursorStyle>) {}
 
+    /// Set whether the cursor blinks, `None` resets it to the configured default.
+    fn set_cursor_blinking(&mut self, _: Option<bool>) {}
+
     /// A character to be displayed.
     fn input(&mut self, _c: char) {}
 
@@ -170,6 +355,9 @@ pub trait Handler {
     /// Report device status.
     fn device_status<W: io::Write>(&mut self, _: &mut W, _: usize) {}
 
+    /// Report whether a private mode is set, in response to DECRQM.
+    fn report_private_mode<W: io::Write>(&mut self, _: &mut W, _: i64) {}
+
     /// Move cursor forward `cols`.
     fn move_forward(&mut self, _: Column) {}
 
@@ -296,8 +484,8 @@ pub trait Handler {
     /// Set an indexed color value.
     fn set_color(&mut self, _: usize, _: Rgb) {}
 
-    /// Write a foreground/background color escape sequence with the current color.
-    fn dynamic_color_sequence<W: io::Write>(&mut self, _: &mut W, _: u8, _: usize, _: &str) {}
+    /// Write a color escape sequence with the current color, after the OSC parameters `prefix`.
+    fn dynamic_color_sequence<W: io::Write>(&mut self, _: &mut W, _: &str, _: usize, _: &str) {}
 
     /// Reset an indexed color to original value.
     fn reset_color(&mut self, _: usize) {}
@@ -308,6 +496,12 @@ pub trait Handler {
     /// Load data from clipboard.
     fn clipboard_load(&mut self, _: u8, _: &str) {}
 
+    /// Set the background opacity, `None` resets it to the configured default.
+    fn set_background_opacity(&mut self, _: Option<f32>) {}
+
+    /// Start (`Some`) or end (`None`) a hyperlink for the following text.
+    fn set_hyperlink(&mut self, _: Option<Hyperlink>) {}
+
     /// Run the decaln routine.
     fn decaln(&mut self) {}
 
@@ -322,6 +516,34 @@ pub trait Handler {
 
     /// Report text area size in characters.
     fn text_area_size_chars<W: io::Write>(&mut self, _: &mut W) {}
+
+    /// Report cell size in pixels.
+    fn cell_size_pixels<W: io::Write>(&mut self, _: &mut W) {}
+
+    /// Insert an image at the cursor position.
+    fn insert_graphic(&mut self, _: Image) {}
+
+    /// Handle a kitty graphics protocol command.
+    fn kitty_graphics<W: io::Write>(&mut self, _: &mut W, _: kitty::Command) {}
+
+    /// Display an iTerm2 inline image at the cursor position.
+    fn insert_inline_image(&mut self, _: InlineImage) {}
+
+    /// Annotate cells on the line `lines_above` lines above the cursor.
+    fn set_mark(&mut self, _lines_above: usize, _: Mark) {}
+
+    /// Remove all annotations.
+    fn clear_marks(&mut self) {}
+}
+
+/// Target of an OSC 8 hyperlink.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hyperlink {
+    /// Identifier joining separate runs of text into a single link.
+    pub id: Option<String>,
+
+    /// URI opened when the link is clicked.
+    pub uri: String,
 }
 
 /// Describes shape of cursor.
@@ -402,12 +624,18 @@ pub enum Mode {
     SgrMouse = 1006,
     /// ?1007
     AlternateScroll = 1007,
+    /// ?1016
+    SgrPixelMouse = 1016,
     /// ?1042
     UrgencyHints = 1042,
     /// ?1049
     SwapScreenAndSetRestoreCursor = 1049,
     /// ?2004
     BracketedPaste = 2004,
+    /// ?2026
+    SyncUpdate = 2026,
+    /// ?2027
+    GraphemeClustering = 2027,
 }
 
 impl Mode {
@@ -436,9 +664,12 @@ impl Mode {
                 1005 => Mode::Utf8Mouse,
                 1006 => Mode::SgrMouse,
                 1007 => Mode::AlternateScroll,
+                1016 => Mode::SgrPixelMouse,
                 1042 => Mode::UrgencyHints,
                 1049 => Mode::SwapScreenAndSetRestoreCursor,
                 2004 => Mode::BracketedPaste,
+                2026 => Mode::SyncUpdate,
+                2027 => Mode::GraphemeClustering,
                 _ => {
                     trace!("[unimplemented] primitive mode: {}", num);
                     return None;
@@ -629,6 +860,12 @@ pub enum Attr {
     Underline,
     /// Underlined twice.
     DoubleUnderline,
+    /// Curly underline.
+    Undercurl,
+    /// Dotted underline.
+    DottedUnderline,
+    /// Dashed underline.
+    DashedUnderline,
     /// Blink cursor slowly.
     BlinkSlow,
     /// Blink cursor fast.
@@ -639,6 +876,8 @@ pub enum Attr {
     Hidden,
     /// Strikeout text.
     Strike,
+    /// Line above text.
+    Overline,
     /// Cancel bold.
     CancelBold,
     /// Cancel bold and dim.
@@ -655,6 +894,8 @@ pub enum Attr {
     CancelHidden,
     /// Cancel strikeout.
     CancelStrike,
+    /// Cancel overline.
+    CancelOverline,
     /// Set indexed foreground color.
     Foreground(Color),
     /// Set indexed background color.
@@ -762,21 +1003,37 @@ where
     }
 
     #[inline]
-    fn hook(&mut self, params: &Params, intermediates: &[u8], ignore: bool, _c: char) {
-        debug!(
-            "[unhandled hook] params={:?}, ints: {:?}, ignore: {:?}",
-            params, intermediates, ignore
-        );
+    fn hook(&mut self, params: &Params, intermediates: &[u8], ignore: bool, c: char) {
+        match (c, intermediates) {
+            ('q', []) if !ignore && self.handler.capabilities().contains(Capabilities::IMAGES) => {
+                // The second parameter selects whether unset pixels stay transparent.
+                let transparent_background = params.iter().nth(1) == Some(&[1][..]);
+                self.state.sixel_parser = Some(sixel::Parser::new(transparent_background));
+            },
+            _ => debug!(
+                "[unhandled hook] params={:?}, ints: {:?}, ignore: {:?}, char: {:?}",
+                params, intermediates, ignore, c
+            ),
+        }
     }
 
     #[inline]
     fn put(&mut self, byte: u8) {
-        debug!("[unhandled put] byte={:?}", byte);
+        match &mut self.state.sixel_parser {
+            Some(parser) => parser.put(byte),
+            None => debug!("[unhandled put] byte={:?}", byte),
+        }
     }
 
     #[inline]
     fn unhook(&mut self) {
-        debug!("[unhandled unhook]");
+        match self.state.sixel_parser.take() {
+            Some(parser) => match parser.finish() {
+                Some(image) => self.handler.insert_graphic(image),
+                None => debug!("Ignoring empty sixel image"),
+            },
+            None => debug!("[unhandled unhook]"),
+        }
     }
 
     // TODO replace OSC parsing with parser combinators.
@@ -801,6 +1058,12 @@ where
             return;
         }
 
+        // Ignore features which have been disabled by the user.
+        if !self.handler.capabilities().contains(Capabilities::for_osc(params)) {
+            debug!("Ignoring disabled OSC {}", String::from_utf8_lossy(params[0]));
+            return;
+        }
+
         match params[0] {
             // Set window title.
             b"0" | b"2" => {
@@ -818,19 +1081,58 @@ where
                 unhandled(params);
             },
 
-            // Set color index.
+            // Get/set color index.
             b"4" => {
-                if params.len() > 1 && params.len() % 2 != 0 {
-                    for chunk in params[1..].chunks(2) {
-                        let index = parse_number(chunk[0]);
-                        let color = xparse_color(chunk[1]);
-                        if let (Some(i), Some(c)) = (index, color) {
-                            self.handler.set_color(i as usize, c);
-                            return;
-                        }
+                if params.len() <= 1 || params.len() % 2 == 0 {
+                    return unhandled(params);
+                }
+
+                for chunk in params[1..].chunks(2) {
+                    let index = match parse_number(chunk[0]) {
+                        Some(index) => index,
+                        None => {
+                            unhandled(params);
+                            continue;
+                        },
+                    };
+
+                    if let Some(color) = xparse_color(chunk[1]) {
+                        self.handler.set_color(index as usize, color);
+                    } else if chunk[1] == b"?" {
+                        let prefix = format!("4;{}", index);
+                        self.handler.dynamic_color_sequence(
+                            writer,
+                            &prefix,
+                            index as usize,
+                            terminator,
+                        );
+                    } else {
+                        unhandled(params);
                     }
                 }
-                unhandled(params);
+            },
+
+            // Start or end a hyperlink.
+            b"8" if params.len() > 2 => {
+                let id = str::from_utf8(params[1])
+                    .unwrap_or_default()
+                    .split(':')
+                    .find(|param| param.starts_with("id="))
+                    .map(|param| param[3..].to_owned())
+                    .filter(|id| !id.is_empty());
+
+                // The URI itself may contain semicolons.
+                let uri = params[2..]
+                    .iter()
+                    .map(|param| String::from_utf8_lossy(param))
+                    .collect::<Vec<_>>()
+                    .join(";");
+
+                if uri.is_empty() {
+                    self.handler.set_hyperlink(None);
+                } else {
+                    self.handler.set_hyperlink(Some(Hyperlink { id, uri }));
+                }
             },
 
             // Get/set Foreground, Background, Cursor colors.
@@ -853,7 +1155,7 @@ where
                             } else if param == b"?" {
                                 self.handler.dynamic_color_sequence(
                                     writer,
-                                    dynamic_code,
+                                    &dynamic_code.to_string(),
                                     index,
                                     terminator,
                                 );
@@ -927,6 +1229,33 @@ where
             // Reset text cursor color.
             b"112" => self.handler.reset_color(NamedColor::Cursor as usize),
 
+            // iTerm2 inline images, the arguments of the file are separated by `;` as well.
+            b"1337" if params.len() > 1 => match InlineImage::parse(&params[1..].join(&b';')) {
+                Some(image) => self.handler.insert_inline_image(image),
+                None => unhandled(params),
+            },
+
+            // Set background opacity, an empty value resets it.
+            b"777" if params.get(1) == Some(&&b"opacity"[..]) => match params.get(2).copied() {
+                None | Some(b"") => self.handler.set_background_opacity(None),
+                Some(value) => match str::from_utf8(value).ok().and_then(|v| v.parse().ok()) {
+                    Some(opacity) if (0. ..=1.).contains(&opacity) => {
+                        self.handler.set_background_opacity(Some(opacity))
+                    },
+                    _ => unhandled(params),
+                },
+            },
+
+            // Annotate cells.
+            b"1339" => match params.get(1).copied() {
+                Some(b"clear") => self.handler.clear_marks(),
+                Some(b"mark") => match parse_mark(&params[2..]) {
+                    Some((lines_above, mark)) => self.handler.set_mark(lines_above, mark),
+                    None => unhandled(params),
+                },
+                _ => unhandled(params),
+            },
+
             _ => unhandled(params),
         }
     }
@@ -949,6 +1278,13 @@ where
             }};
         }
 
+        // DECRQM for private modes is the only sequence with two intermediates.
+        if action == 'p' && intermediates == b"?$" && !has_ignored_intermediates {
+            let mode = params.iter().next().map_or(0, |param| param[0]);
+            self.handler.report_private_mode(&mut self.writer, mode);
+            return;
+        }
+
         if has_ignored_intermediates || intermediates.len() > 1 {
             unhandled!();
             return;
@@ -959,7 +1295,11 @@ where
         let writer = &mut self.writer;
 
         let mut next_param_or = |default: i64| {
-            params_iter.next().map(|param| param[0]).filter(|&param| param != 0).unwrap_or(default)
+            params_iter
+                .next()
+                .map(|param| param[0].min(MAX_CSI_PARAM))
+                .filter(|&param| param != 0)
+                .unwrap_or(default)
         };
 
         match (action, intermediates.get(0)) {
@@ -1006,6 +1346,10 @@ where
             ('h', intermediate) => {
                 for param in params_iter.map(|param| param[0]) {
                     match Mode::from_primitive(intermediate, param) {
+                        Some(Mode::SyncUpdate) => {
+                            handler.set_mode(Mode::SyncUpdate);
+                            self.state.sync_start.get_or_insert_with(Instant::now);
+                        },
                         Some(mode) => handler.set_mode(mode),
                         None => unhandled!(),
                     }
@@ -1065,7 +1409,8 @@ where
             ('P', None) => handler.delete_chars(Column(next_param_or(1) as usize)),
             ('q', Some(b' ')) => {
                 // DECSCUSR (CSI Ps SP q) -- Set Cursor Style.
-                let style = match next_param_or(0) {
+                let shape = next_param_or(0);
+                let style = match shape {
                     0 => None,
                     1 | 2 => Some(CursorStyle::Block),
                     3 | 4 => Some(CursorStyle::Underline),
@@ -1076,12 +1421,18 @@ where
                     },
                 };
 
+                // Odd styles blink, even styles are steady.
+                let blinking = style.map(|_| shape % 2 == 1);
+
                 handler.set_cursor_style(style);
+                handler.set_cursor_blinking(blinking);
             },
             ('r', None) => {
                 let top = next_param_or(1) as usize;
-                let bottom =
-                    params_iter.next().map(|param| param[0] as usize).filter(|&param| param != 0);
+                let bottom = params_iter
+                    .next()
+                    .map(|param| param[0].min(MAX_CSI_PARAM) as usize)
+                    .filter(|&param| param != 0);
 
                 handler.set_scrolling_region(top, bottom);
             },
@@ -1090,9 +1441,10 @@ where
             ('T', None) => handler.scroll_down(Line(next_param_or(1) as usize)),
             ('t', None) => match next_param_or(1) as usize {
                 14 => handler.text_area_size_pixels(writer),
+                16 => handler.cell_size_pixels(writer),
                 18 => handler.text_area_size_chars(writer),
                 22 => handler.push_title(),
-                23 => handler.pop_title(),
+                23 if handler.capabilities().contains(Capabilities::TITLE) => handler.pop_title(),
                 _ => unhandled!(),
             },
             ('u', None) => handler.restore_cursor_position(),
@@ -1166,13 +1518,16 @@ fn attrs_from_sgr_parameters(params: &mut ParamsIter<'_>) -> Vec<Option<Attr>> {
             [3] => Some(Attr::Italic),
             [4, 0] => Some(Attr::CancelUnderline),
             [4, 2] => Some(Attr::DoubleUnderline),
+            [4, 3] => Some(Attr::Undercurl),
+            [4, 4] => Some(Attr::DottedUnderline),
+            [4, 5] => Some(Attr::DashedUnderline),
             [4, ..] => Some(Attr::Underline),
             [5] => Some(Attr::BlinkSlow),
             [6] => Some(Attr::BlinkFast),
             [7] => Some(Attr::Reverse),
             [8] => Some(Attr::Hidden),
             [9] => Some(Attr::Strike),
-            [21] => Some(Attr::CancelBold),
+            [21] => Some(Attr::DoubleUnderline),
             [22] => Some(Attr::CancelBoldDim),
             [23] => Some(Attr::CancelItalic),
             [24] => Some(Attr::CancelUnderline),
@@ -1220,6 +1575,8 @@ fn attrs_from_sgr_parameters(params: &mut ParamsIter<'_>) -> Vec<Option<Attr>> {
                 parse_sgr_color(&mut iter).map(Attr::Background)
             },
             [49] => Some(Attr::Background(Color::Named(NamedColor::Background))),
+            [53] => Some(Attr::Overline),
+            [55] => Some(Attr::CancelOverline),
             [90] => Some(Attr::Foreground(Color::Named(NamedColor::BrightBlack))),
             [91] => Some(Attr::Foreground(Color::Named(NamedColor::BrightRed))),
             [92] => Some(Attr::Foreground(Color::Named(NamedColor::BrightGreen))),
@@ -1334,8 +1691,12 @@ pub mod C0 {
 #[cfg(test)]
 mod tests {
     use super::{
-        parse_number, xparse_color, Attr, CharsetIndex, Color, Handler, Processor, StandardCharset,
+        parse_mark, parse_number, xparse_color, Attr, Capabilities, CharsetIndex, Color, Handler,
+        Hyperlink, Processor, StandardCharset,
     };
+    use crate::graphics::iterm::{Dimension, InlineImage};
+    use crate::graphics::{kitty, Image};
+    use crate::index::Column;
     use crate::term::color::Rgb;
     use std::io;
 
@@ -1344,9 +1705,30 @@ mod tests {
         charset: StandardCharset,
         attr: Option<Attr>,
         identity_reported: bool,
+        graphic: Option<Image>,
+        kitty_command: Option<kitty::Command>,
+        inline_image: Option<InlineImage>,
+        cursor_blinking: Option<bool>,
+        title: Option<String>,
+        background_opacity: Option<f32>,
+        hyperlink: Option<Hyperlink>,
+        capabilities: Capabilities,
+        goto: Option<(Line, Column)>,
+        input_count: usize,
+        clipboard_stored: Option<Vec<u8>>,
+        clipboard_loaded: bool,
+        reported_mode: Option<i64>,
     }
 
     impl Handler for MockHandler {
+        fn capabilities(&self) -> Capabilities {
+            self.capabilities
+        }
+
+        fn set_title(&mut self, title: Option<String>) {
+            self.title = title;
+        }
+
         fn terminal_attribute(&mut self, attr: Attr) {
             self.attr = Some(attr);
         }
@@ -1367,6 +1749,50 @@ mod tests {
         fn reset_state(&mut self) {
             *self = Self::default();
         }
+
+        fn insert_graphic(&mut self, image: Image) {
+            self.graphic = Some(image);
+        }
+
+        fn kitty_graphics<W: io::Write>(&mut self, _: &mut W, command: kitty::Command) {
+            self.kitty_command = Some(command);
+        }
+
+        fn insert_inline_image(&mut self, image: InlineImage) {
+            self.inline_image = Some(image);
+        }
+
+        fn set_cursor_blinking(&mut self, blinking: Option<bool>) {
+            self.cursor_blinking = blinking;
+        }
+
+        fn set_background_opacity(&mut self, opacity: Option<f32>) {
+            self.background_opacity = opacity;
+        }
+
+        fn set_hyperlink(&mut self, hyperlink: Option<Hyperlink>) {
+            self.hyperlink = hyperlink;
+        }
+
+        fn goto(&mut self, line: Line, col: Column) {
+            self.goto = Some((line, col));
+        }
+
+        fn input(&mut self, _c: char) {
+            self.input_count += 1;
+        }
+
+        fn clipboard_store(&mut self, _: u8, base64: &[u8]) {
+            self.clipboard_stored = Some(base64.to_vec());
+        }
+
+        fn clipboard_load(&mut self, _: u8, _: &str) {
+            self.clipboard_loaded = true;
+        }
+
+        fn report_private_mode<W: io::Write>(&mut self, _: &mut W, mode: i64) {
+            self.reported_mode = Some(mode);
+        }
     }
 
     impl Default for MockHandler {
@@ -1376,6 +1802,19 @@ mod tests {
                 charset: StandardCharset::Ascii,
                 attr: None,
                 identity_reported: false,
+                graphic: None,
+                kitty_command: None,
+                inline_image: None,
+                cursor_blinking: None,
+                title: None,
+                background_opacity: None,
+                hyperlink: None,
+                capabilities: Capabilities::all(),
+                goto: None,
+                input_count: 0,
+                clipboard_stored: None,
+                clipboard_loaded: false,
+                reported_mode: None,
             }
         }
     }
@@ -1394,6 +1833,263 @@ mod tests {
         assert_eq!(handler.attr, Some(Attr::Bold));
     }
 
+    #[test]
+    fn hold_back_sync_updates() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in b"\x1b[?2026hab" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.input_count, 0);
+        assert!(parser.sync_deadline().is_some());
+
+        for byte in b"c\x1b[?2026l" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.input_count, 3);
+        assert_eq!(parser.sync_deadline(), None);
+
+        // Updates which never end are applied once they time out.
+        for byte in b"\x1b[?2026hd" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        parser.stop_sync(&mut handler, &mut io::sink());
+        assert_eq!(handler.input_count, 4);
+        assert_eq!(parser.sync_deadline(), None);
+    }
+
+    #[test]
+    fn parse_decrqm() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in b"\x1b[?2026$p" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.reported_mode, Some(2026));
+    }
+
+    #[test]
+    fn parse_huge_csi_params() {
+        static BYTES: &[u8] = b"\x1b[99999999999999;0Ha\x1b[99999999999999999999b";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.goto, Some((Line(MAX_CSI_PARAM as usize - 1), Column(0))));
+        assert_eq!(handler.input_count, 1 + MAX_CSI_PARAM as usize);
+    }
+
+    #[test]
+    fn parse_sixel() {
+        static BYTES: &[u8] = b"\x1bP0;1q\"1;1;2;6#1;2;100;0;0~\x1b\\";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        let image = handler.graphic.unwrap();
+        assert_eq!((image.width, image.height), (2, 6));
+        assert_eq!(&image.pixels[..8], &[255, 0, 0, 255, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn parse_kitty_graphics() {
+        static BYTES: &[u8] = b"\x1b_Ga=T,i=3;AAAA\x1b\\\x1b_Gi=4\x1b[m";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        // The second command is aborted by the escape sequence following it.
+        let command = handler.kitty_command.unwrap();
+        assert_eq!(command.action, kitty::Action::TransmitAndDisplay);
+        assert_eq!(command.image_id, 3);
+        assert_eq!(command.payload, b"AAAA");
+    }
+
+    #[test]
+    fn parse_inline_image() {
+        static BYTES: &[u8] = b"\x1b]1337;File=width=2;inline=1:ZGF0YQ==\x07";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        let image = handler.inline_image.unwrap();
+        assert_eq!(image.width, Dimension::Cells(2.));
+        assert!(image.inline);
+        assert_eq!(image.data, b"data");
+    }
+
+    #[test]
+    fn parse_disabled_capabilities() {
+        static BYTES: &[u8] = b"\x1b]2;title\x07\
+            \x1bP0;1q\"1;1;2;6#1;2;100;0;0~\x1b\\\
+            \x1b_Ga=T,i=3;AAAA\x1b\\";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+        handler.capabilities = Capabilities::all() - Capabilities::TITLE - Capabilities::IMAGES;
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.title, None);
+        assert!(handler.graphic.is_none());
+        assert!(handler.kitty_command.is_none());
+
+        handler.capabilities = Capabilities::all();
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.title.as_deref(), Some("title"));
+        assert!(handler.graphic.is_some());
+        assert!(handler.kitty_command.is_some());
+    }
+
+    #[test]
+    fn parse_clipboard_permissions() {
+        static BYTES: &[u8] = b"\x1b]52;c;?\x07\x1b]52;c;YQ==\x07";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+        handler.capabilities = Capabilities::all() - Capabilities::CLIPBOARD_READ;
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert!(!handler.clipboard_loaded);
+        assert_eq!(handler.clipboard_stored.as_deref(), Some(&b"YQ=="[..]));
+
+        handler.capabilities = Capabilities::all() - Capabilities::CLIPBOARD;
+        handler.clipboard_stored = None;
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert!(handler.clipboard_loaded);
+        assert_eq!(handler.clipboard_stored, None);
+    }
+
+    #[test]
+    fn parse_cursor_blinking() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in b"\x1b[3 q" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.cursor_blinking, Some(true));
+
+        for byte in b"\x1b[6 q" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.cursor_blinking, Some(false));
+
+        for byte in b"\x1b[ q" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.cursor_blinking, None);
+    }
+
+    #[test]
+    fn parse_background_opacity() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in b"\x1b]777;opacity;0.5\x07" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.background_opacity, Some(0.5));
+
+        // Values outside of the valid range are ignored.
+        for byte in b"\x1b]777;opacity;2\x07" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.background_opacity, Some(0.5));
+
+        for byte in b"\x1b]777;opacity\x07" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.background_opacity, None);
+
+        handler.capabilities = Capabilities::all() - Capabilities::OPACITY;
+        for byte in b"\x1b]777;opacity;0.5\x07" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.background_opacity, None);
+    }
+
+    #[test]
+    fn parse_hyperlink() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in b"\x1b]8;id=1:x=y;https://example.org/a;b\x1b\\" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        let hyperlink = Hyperlink { id: Some("1".into()), uri: "https://example.org/a;b".into() };
+        assert_eq!(handler.hyperlink, Some(hyperlink));
+
+        for byte in b"\x1b]8;;\x1b\\" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.hyperlink, None);
+
+        handler.capabilities = Capabilities::all() - Capabilities::HYPERLINKS;
+        for byte in b"\x1b]8;;https://example.org\x07" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert_eq!(handler.hyperlink, None);
+    }
+
+    #[test]
+    fn parse_undercurl_attribute() {
+        static BYTES: &[u8] = &[0x1b, b'[', b'4', b':', b'3', b'm'];
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.attr, Some(Attr::Undercurl));
+    }
+
+    #[test]
+    fn parse_overline_attribute() {
+        static BYTES: &[u8] = &[0x1b, b'[', b'5', b'3', b'm'];
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.attr, Some(Attr::Overline));
+    }
+
     #[test]
     fn parse_terminal_identity_csi() {
         let bytes: &[u8] = &[0x1b, b'[', b'1', b'c'];
@@ -1532,6 +2228,22 @@ mod tests {
         assert_eq!(handler.index, CharsetIndex::G1);
     }
 
+    #[test]
+    fn parse_mark_params() {
+        let params: &[&[u8]] = &[b"line=2", b"columns=3-5", b"tint=#ff0000", b"icon=\xe2\x9c\x97"];
+        let (lines_above, mark) = parse_mark(params).unwrap();
+        assert_eq!(lines_above, 2);
+        assert_eq!(mark.columns, Column(2)..Column(5));
+        assert_eq!(mark.tint, Some(Rgb { r: 0xff, g: 0, b: 0 }));
+        assert_eq!(mark.icon, Some('\u{2717}'));
+
+        let params: &[&[u8]] = &[b"columns=4"];
+        assert_eq!(parse_mark(params).unwrap().1.columns, Column(3)..Column(4));
+
+        let params: &[&[u8]] = &[b"color=red"];
+        assert_eq!(parse_mark(params), None);
+    }
+
     #[test]
     fn parse_valid_rgb_colors() {
         assert_eq!(xparse_color(b"rgb:f/e/d"), Some(Rgb { r: 0xff, g: 0xee, b: 0xdd }));
@@ -0,0 +1,39 @@
+use serde::Deserialize;
+
+use crate::ansi::Capabilities;
+use crate::config::{failure_default, DefaultTrueBool};
+
+/// Escape sequence features applications are allowed to use.
+#[serde(default)]
+#[derive(Deserialize, Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub struct EscapeSequences {
+    #[serde(deserialize_with = "failure_default")]
+    title: DefaultTrueBool,
+    #[serde(deserialize_with = "failure_default")]
+    clipboard: DefaultTrueBool,
+    #[serde(deserialize_with = "failure_default")]
+    clipboard_read: bool,
+    #[serde(deserialize_with = "failure_default")]
+    hyperlinks: DefaultTrueBool,
+    #[serde(deserialize_with = "failure_default")]
+    images: DefaultTrueBool,
+    #[serde(deserialize_with = "failure_default")]
+    notifications: DefaultTrueBool,
+    #[serde(deserialize_with = "failure_default")]
+    opacity: DefaultTrueBool,
+}
+
+impl EscapeSequences {
+    /// Features which are enabled in the escape sequence parser.
+    pub fn capabilities(self) -> Capabilities {
+        let mut capabilities = Capabilities::empty();
+        capabilities.set(Capabilities::TITLE, self.title.0);
+        capabilities.set(Capabilities::CLIPBOARD, self.clipboard.0);
+        capabilities.set(Capabilities::CLIPBOARD_READ, self.clipboard_read);
+        capabilities.set(Capabilities::HYPERLINKS, self.hyperlinks.0);
+        capabilities.set(Capabilities::IMAGES, self.images.0);
+        capabilities.set(Capabilities::NOTIFICATIONS, self.notifications.0);
+        capabilities.set(Capabilities::OPACITY, self.opacity.0);
+        capabilities
+    }
+}
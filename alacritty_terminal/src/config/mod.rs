@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use log::error;
 use serde::{Deserialize, Deserializer};
@@ -8,17 +9,21 @@ use serde_yaml::Value;
 
 mod bell;
 mod colors;
+mod escape_sequences;
 mod scrolling;
 
 use crate::ansi::CursorStyle;
+use crate::term::color::DEFAULT_DIM_LUMINANCE;
 
 pub use crate::config::bell::{BellAnimation, BellConfig};
 pub use crate::config::colors::Colors;
-pub use crate::config::scrolling::Scrolling;
+pub use crate::config::escape_sequences::EscapeSequences;
+pub use crate::config::scrolling::{ScrollToBottom, Scrolling};
 
 pub const LOG_TARGET_CONFIG: &str = "alacritty_config";
 const MAX_SCROLLBACK_LINES: u32 = 100_000;
 const DEFAULT_CURSOR_THICKNESS: f32 = 0.15;
+const DEFAULT_CURSOR_BLINK_INTERVAL: u16 = 750;
 
 pub type MockConfig = Config<HashMap<String, serde_yaml::Value>>;
 
@@ -33,6 +38,10 @@ pub struct Config<T> {
     #[serde(default, deserialize_with = "failure_default")]
     draw_bold_text_with_bright_colors: bool,
 
+    /// Relative luminance of dim text, compared to its regular color.
+    #[serde(default, deserialize_with = "failure_default")]
+    dim_text_luminance: Option<Percentage>,
+
     #[serde(default, deserialize_with = "failure_default")]
     pub colors: Colors,
 
@@ -55,6 +64,10 @@ pub struct Config<T> {
     #[serde(default, deserialize_with = "failure_default")]
     pub cursor: Cursor,
 
+    /// Escape sequence features applications are allowed to use.
+    #[serde(default, deserialize_with = "failure_default")]
+    pub escape_sequences: EscapeSequences,
+
     /// Use WinPTY backend even if ConPTY is available.
     #[cfg(windows)]
     #[serde(default, deserialize_with = "failure_default")]
@@ -87,6 +100,12 @@ impl<T> Config<T> {
         self.draw_bold_text_with_bright_colors
     }
 
+    /// Factor for the luminance of dim colors which are not configured explicitly.
+    #[inline]
+    pub fn dim_text_luminance(&self) -> f32 {
+        self.dim_text_luminance.map_or(DEFAULT_DIM_LUMINANCE, Percentage::as_f32)
+    }
+
     #[inline]
     pub fn bell(&self) -> &BellConfig {
         self.visual_bell.as_ref().unwrap_or(&self.bell)
@@ -99,13 +118,33 @@ pub struct Selection {
     #[serde(deserialize_with = "failure_default")]
     semantic_escape_chars: EscapeChars,
     #[serde(deserialize_with = "failure_default")]
+    path_escape_chars: PathEscapeChars,
+    #[serde(deserialize_with = "failure_default")]
     pub save_to_clipboard: bool,
+    #[serde(deserialize_with = "failure_default")]
+    copy_on_select: DefaultTrueBool,
+    #[serde(deserialize_with = "failure_default")]
+    history_size: HistorySize,
 }
 
 impl Selection {
     pub fn semantic_escape_chars(&self) -> &str {
         &self.semantic_escape_chars.0
     }
+
+    pub fn path_escape_chars(&self) -> &str {
+        &self.path_escape_chars.0
+    }
+
+    /// Whether selected text is copied to the primary clipboard.
+    pub fn copy_on_select(&self) -> bool {
+        self.copy_on_select.0
+    }
+
+    /// Number of copied selections which are kept for pasting them again.
+    pub fn history_size(&self) -> usize {
+        self.history_size.0
+    }
 }
 
 #[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -117,6 +156,24 @@ impl Default for EscapeChars {
     }
 }
 
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+struct PathEscapeChars(String);
+
+impl Default for PathEscapeChars {
+    fn default() -> Self {
+        PathEscapeChars(String::from("│`|\"' ()[]{}<>\t"))
+    }
+}
+
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+struct HistorySize(usize);
+
+impl Default for HistorySize {
+    fn default() -> Self {
+        HistorySize(10)
+    }
+}
+
 #[serde(default)]
 #[derive(Deserialize, Copy, Clone, Debug, PartialEq)]
 pub struct Cursor {
@@ -128,6 +185,12 @@ pub struct Cursor {
     thickness: Percentage,
     #[serde(deserialize_with = "failure_default")]
     unfocused_hollow: DefaultTrueBool,
+    #[serde(deserialize_with = "failure_default")]
+    animation_duration: u16,
+    #[serde(deserialize_with = "failure_default")]
+    pub blinking: CursorBlinking,
+    #[serde(deserialize_with = "failure_default")]
+    blink_interval: u16,
 }
 
 impl Cursor {
@@ -140,6 +203,18 @@ impl Cursor {
     pub fn thickness(self) -> f64 {
         self.thickness.0 as f64
     }
+
+    /// Duration of the cursor's movement between cells, zero when it jumps instantly.
+    #[inline]
+    pub fn animation_duration(self) -> Duration {
+        Duration::from_millis(u64::from(self.animation_duration))
+    }
+
+    /// Time the blinking cursor stays visible or hidden.
+    #[inline]
+    pub fn blink_interval(self) -> Duration {
+        Duration::from_millis(u64::from(self.blink_interval.max(1)))
+    }
 }
 
 impl Default for Cursor {
@@ -149,6 +224,43 @@ impl Default for Cursor {
             vi_mode_style: Default::default(),
             thickness: Percentage::new(DEFAULT_CURSOR_THICKNESS),
             unfocused_hollow: Default::default(),
+            animation_duration: 0,
+            blinking: Default::default(),
+            blink_interval: DEFAULT_CURSOR_BLINK_INTERVAL,
+        }
+    }
+}
+
+/// Whether the cursor blinks.
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CursorBlinking {
+    /// Never blink, even if requested by the application.
+    Never,
+
+    /// Steady by default, applications may enable blinking.
+    Off,
+
+    /// Blinking by default, applications may disable it.
+    On,
+
+    /// Always blink, even if the application requested a steady cursor.
+    Always,
+}
+
+impl Default for CursorBlinking {
+    fn default() -> Self {
+        CursorBlinking::Off
+    }
+}
+
+impl CursorBlinking {
+    /// Whether the cursor blinks, given the application's `requested` blinking state.
+    pub fn blinking(self, requested: Option<bool>) -> bool {
+        match self {
+            CursorBlinking::Never => false,
+            CursorBlinking::Off => requested.unwrap_or(false),
+            CursorBlinking::On => requested.unwrap_or(true),
+            CursorBlinking::Always => true,
         }
     }
 }
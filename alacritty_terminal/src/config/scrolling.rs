@@ -11,6 +11,8 @@ pub struct Scrolling {
     history: ScrollingHistory,
     #[serde(deserialize_with = "failure_default")]
     multiplier: ScrollingMultiplier,
+    #[serde(deserialize_with = "failure_default")]
+    pub scroll_to_bottom: ScrollToBottom,
 
     // TODO: REMOVED
     #[serde(deserialize_with = "failure_default")]
@@ -40,6 +42,25 @@ impl Scrolling {
     }
 }
 
+/// Events which move the viewport back to the bottom while it shows the scrollback history.
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScrollToBottom {
+    /// Jump to the bottom when a key is sent to the terminal.
+    Keypress,
+
+    /// Jump to the bottom on keypresses and whenever new output is printed.
+    Output,
+
+    /// Stay in the history until scrolled down explicitly.
+    Never,
+}
+
+impl Default for ScrollToBottom {
+    fn default() -> Self {
+        ScrollToBottom::Keypress
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
 struct ScrollingMultiplier(u8);
 
@@ -7,6 +7,7 @@ use std::io::{self, ErrorKind, Read, Write};
 use std::marker::Send;
 use std::sync::Arc;
 use std::thread::JoinHandle;
+use std::time::Instant;
 
 use log::error;
 #[cfg(not(windows))]
@@ -246,6 +247,7 @@ where
                     for byte in &buf[..got] {
                         state.parser.advance(&mut **terminal, *byte, &mut self.pty.writer());
                     }
+                    terminal.add_received_bytes(got);
 
                     // Exit if we've processed enough bytes.
                     if processed > MAX_READ {
@@ -325,13 +327,24 @@ where
             };
 
             'event_loop: loop {
-                if let Err(err) = self.poll.poll(&mut events, None) {
+                // Wake up to apply synchronized updates which did not end in time.
+                let sync_deadline = state.parser.sync_deadline();
+                let timeout = sync_deadline
+                    .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+
+                if let Err(err) = self.poll.poll(&mut events, timeout) {
                     match err.kind() {
                         ErrorKind::Interrupted => continue,
                         _ => panic!("EventLoop polling error: {:?}", err),
                     }
                 }
 
+                if sync_deadline.map_or(false, |deadline| deadline <= Instant::now()) {
+                    let mut terminal = self.terminal.lock();
+                    state.parser.stop_sync(&mut *terminal, &mut self.pty.writer());
+                    self.event_proxy.send_event(Event::Wakeup);
+                }
+
                 for event in events.iter() {
                     match event.token() {
                         token if token == channel_token => {
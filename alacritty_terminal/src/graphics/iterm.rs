@@ -0,0 +1,225 @@
+//! iTerm2 inline images.
+//!
+//! Images are sent as `OSC 1337 ; File=<arguments> : <base64 data> ST`, where the arguments are a
+//! `;` separated list of `key=value` pairs. The data is any image file supported by the decoder.
+
+use std::io::Cursor;
+use std::str;
+
+use image::io::Reader;
+
+use crate::graphics::{Image, MAX_GRAPHIC_DIMENSIONS};
+
+/// Requested width or height of an image.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Dimension {
+    /// Size of the image itself.
+    Auto,
+
+    /// Number of cells.
+    Cells(f32),
+
+    /// Number of pixels.
+    Pixels(f32),
+
+    /// Percentage of the screen.
+    Percent(f32),
+}
+
+impl Dimension {
+    fn parse(value: &str) -> Option<Self> {
+        // Sizes like `inf`, `nan` or negative numbers are rejected.
+        let size =
+            |value: &str| value.parse().ok().filter(|size: &f32| size.is_finite() && *size > 0.);
+
+        if value == "auto" {
+            Some(Dimension::Auto)
+        } else if value.ends_with("px") {
+            size(&value[..value.len() - 2]).map(Dimension::Pixels)
+        } else if value.ends_with('%') {
+            size(&value[..value.len() - 1]).map(Dimension::Percent)
+        } else {
+            size(value).map(Dimension::Cells)
+        }
+    }
+
+    /// Size in cells, if it was given explicitly.
+    fn cells(self, cell_size: f32, screen_cells: f32) -> Option<f32> {
+        match self {
+            Dimension::Auto => None,
+            Dimension::Cells(cells) => Some(cells),
+            Dimension::Pixels(pixels) => Some(pixels / cell_size),
+            Dimension::Percent(percent) => Some(screen_cells * percent / 100.),
+        }
+    }
+}
+
+/// Image file sent with `OSC 1337 ; File=`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InlineImage {
+    pub width: Dimension,
+    pub height: Dimension,
+    pub preserve_aspect_ratio: bool,
+
+    /// Whether the file is displayed, instead of being downloaded.
+    pub inline: bool,
+
+    /// Contents of the file.
+    pub data: Vec<u8>,
+}
+
+impl InlineImage {
+    /// Parse the parameters of the OSC following `1337;`.
+    pub fn parse(params: &[u8]) -> Option<Self> {
+        if params.len() < 5 || &params[..5] != b"File=" {
+            return None;
+        }
+
+        let separator = params.iter().position(|&byte| byte == b':')?;
+        let arguments = str::from_utf8(&params[5..separator]).ok()?;
+        let data = base64::decode(&params[separator + 1..]).ok()?;
+
+        let mut image = InlineImage {
+            width: Dimension::Auto,
+            height: Dimension::Auto,
+            preserve_aspect_ratio: true,
+            inline: false,
+            data,
+        };
+
+        for argument in arguments.split(';') {
+            let mut split = argument.splitn(2, '=');
+            let (key, value) = match (split.next(), split.next()) {
+                (Some(key), Some(value)) => (key, value),
+                _ => continue,
+            };
+
+            match key {
+                "width" => image.width = Dimension::parse(value)?,
+                "height" => image.height = Dimension::parse(value)?,
+                "preserveAspectRatio" => image.preserve_aspect_ratio = value != "0",
+                "inline" => image.inline = value == "1",
+                // The name and size are only used for downloads.
+                _ => (),
+            }
+        }
+
+        Some(image)
+    }
+
+    /// Decode the image file.
+    pub fn decode(&self) -> Result<Image, String> {
+        let reader = || Reader::new(Cursor::new(&self.data)).with_guessed_format();
+
+        let (width, height) = reader()
+            .map_err(|err| err.to_string())?
+            .into_dimensions()
+            .map_err(|err| err.to_string())?;
+        if width as usize > MAX_GRAPHIC_DIMENSIONS || height as usize > MAX_GRAPHIC_DIMENSIONS {
+            return Err(format!("image of {}x{} pixels is too large", width, height));
+        }
+
+        let image = reader()
+            .map_err(|err| err.to_string())?
+            .decode()
+            .map_err(|err| err.to_string())?
+            .to_rgba8();
+        let (width, height) = image.dimensions();
+
+        Ok(Image { width: width as usize, height: height as usize, pixels: image.into_raw() })
+    }
+
+    /// Number of columns and lines covered by the decoded `image`.
+    ///
+    /// Widths in percent are relative to `max_columns`. Images are scaled down to fit into
+    /// `max_columns` and `screen_lines`, keeping their aspect ratio.
+    pub fn cells(
+        &self,
+        image: &Image,
+        cell_size: (usize, usize),
+        screen_lines: usize,
+        max_columns: usize,
+    ) -> (f32, f32) {
+        let (cell_width, cell_height) = (cell_size.0.max(1) as f32, cell_size.1.max(1) as f32);
+        let native_columns = image.width as f32 / cell_width;
+        let native_lines = image.height as f32 / cell_height;
+
+        let columns = self.width.cells(cell_width, max_columns as f32);
+        let lines = self.height.cells(cell_height, screen_lines as f32);
+
+        let (columns, lines) = match (columns, lines) {
+            (None, None) => (native_columns, native_lines),
+            (Some(columns), None) => (columns, columns * native_lines / native_columns),
+            (None, Some(lines)) => (lines * native_columns / native_lines, lines),
+            (Some(columns), Some(lines)) if self.preserve_aspect_ratio => {
+                let scale = (columns / native_columns).min(lines / native_lines);
+                (native_columns * scale, native_lines * scale)
+            },
+            (Some(columns), Some(lines)) => (columns, lines),
+        };
+
+        let max_columns = max_columns as f32;
+        let (columns, lines) = if columns > max_columns {
+            (max_columns, lines * max_columns / columns)
+        } else {
+            (columns, lines)
+        };
+
+        let max_lines = screen_lines.max(1) as f32;
+        if lines > max_lines {
+            (columns * max_lines / lines, max_lines)
+        } else {
+            (columns, lines)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inline_image(arguments: &str) -> InlineImage {
+        let params = format!("File={}:{}", arguments, base64::encode(b"data"));
+        InlineImage::parse(params.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn parse_arguments() {
+        let image = inline_image("name=aW1n;size=4;width=10;height=50%;inline=1");
+        assert_eq!(image.width, Dimension::Cells(10.));
+        assert_eq!(image.height, Dimension::Percent(50.));
+        assert!(image.preserve_aspect_ratio);
+        assert!(image.inline);
+        assert_eq!(image.data, b"data");
+
+        let image = inline_image("width=20px;preserveAspectRatio=0");
+        assert_eq!(image.width, Dimension::Pixels(20.));
+        assert!(!image.preserve_aspect_ratio);
+        assert!(!image.inline);
+
+        assert_eq!(InlineImage::parse(b"SetMark"), None);
+        assert_eq!(InlineImage::parse(b"File=width=x:"), None);
+        assert_eq!(InlineImage::parse(b"File=height=inf:"), None);
+        assert_eq!(InlineImage::parse(b"File=height=NaNpx:"), None);
+        assert_eq!(InlineImage::parse(b"File=width=-5%:"), None);
+        assert_eq!(InlineImage::parse(b"File=width=0:"), None);
+    }
+
+    #[test]
+    fn scaled_cells() {
+        let image = Image { width: 40, height: 20, pixels: Vec::new() };
+
+        let cells = |arguments| inline_image(arguments).cells(&image, (10, 10), 10, 8);
+        assert_eq!(cells("inline=1"), (4., 2.));
+        assert_eq!(cells("width=2"), (2., 1.));
+        assert_eq!(cells("height=40px"), (8., 4.));
+        assert_eq!(cells("width=2;height=2"), (2., 1.));
+        assert_eq!(cells("width=2;height=2;preserveAspectRatio=0"), (2., 2.));
+
+        // Images are scaled down to fit.
+        assert_eq!(cells("width=100%;height=10"), (8., 4.));
+        assert_eq!(cells("width=16"), (8., 4.));
+        assert_eq!(cells("height=1000000"), (8., 4.));
+        assert_eq!(cells("width=2;height=20;preserveAspectRatio=0"), (1., 10.));
+    }
+}
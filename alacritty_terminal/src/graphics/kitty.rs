@@ -0,0 +1,757 @@
+//! Kitty graphics protocol.
+//!
+//! Commands are sent as `ESC _ G <control data> ; <payload> ESC \`, where the control data is a
+//! comma separated list of `key=value` pairs and the payload is base64 encoded image data. Large
+//! payloads are split over multiple commands, which only carry the `m` key after the first one.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::io::Cursor;
+use std::str;
+
+use log::debug;
+use miniz_oxide::inflate::core::{decompress, inflate_flags, DecompressorOxide};
+use miniz_oxide::inflate::TINFLStatus;
+
+use crate::graphics::{
+    Geometry, GraphicId, Graphics, Image, PlacementId, MAX_GRAPHICS_BYTES, MAX_GRAPHIC_DIMENSIONS,
+};
+use crate::index::Point;
+
+/// Maximum size of an APC string and of the payload of an image after all chunks are received.
+///
+/// This fits the base64 encoding of the largest uncompressed RGBA image.
+pub const MAX_PAYLOAD_SIZE: usize = 96 * 1024 * 1024;
+
+/// What a command does.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    Transmit,
+    TransmitAndDisplay,
+    Put,
+    Delete,
+    Query,
+}
+
+/// Encoding of the transmitted image data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Rgb,
+    Rgba,
+    Png,
+}
+
+/// Graphics command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub action: Action,
+    pub format: Format,
+
+    /// Transmission medium, only direct transmission `d` is supported.
+    pub medium: u8,
+
+    /// Compression of the payload, only zlib `z` is supported.
+    pub compression: Option<u8>,
+
+    /// Size of raw pixel data.
+    pub width: u32,
+    pub height: u32,
+
+    pub image_id: u32,
+    pub image_number: u32,
+    pub placement_id: u32,
+
+    /// Whether more chunks of the payload follow.
+    pub more: bool,
+
+    /// Level of suppressed responses, `1` for success and `2` for errors too.
+    pub quiet: u8,
+
+    /// Part of the image which is displayed, as `x`, `y`, width and height in pixels.
+    pub source: (u32, u32, u32, u32),
+
+    /// Pixel offset of the image within the cursor cell.
+    pub offset: (u32, u32),
+
+    /// Number of cells the image is scaled to.
+    pub columns: u32,
+    pub rows: u32,
+
+    pub z_index: i32,
+
+    /// Whether the cursor stays in place after displaying an image.
+    pub keep_cursor: bool,
+
+    /// Which placements are deleted.
+    pub delete: u8,
+
+    /// Base64 encoded payload.
+    pub payload: Vec<u8>,
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        Self {
+            action: Action::Transmit,
+            format: Format::Rgba,
+            medium: b'd',
+            compression: None,
+            width: 0,
+            height: 0,
+            image_id: 0,
+            image_number: 0,
+            placement_id: 0,
+            more: false,
+            quiet: 0,
+            source: (0, 0, 0, 0),
+            offset: (0, 0),
+            columns: 0,
+            rows: 0,
+            z_index: 0,
+            keep_cursor: false,
+            delete: b'a',
+            payload: Vec::new(),
+        }
+    }
+}
+
+impl Command {
+    /// Parse the contents of an APC string following its `G`.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let (control, payload) = match data.iter().position(|&byte| byte == b';') {
+            Some(index) => (&data[..index], &data[index + 1..]),
+            None => (data, &[][..]),
+        };
+
+        let mut command = Command { payload: payload.to_vec(), ..Command::default() };
+
+        for pair in control.split(|&byte| byte == b',').filter(|pair| !pair.is_empty()) {
+            if pair.len() < 3 || pair[1] != b'=' {
+                debug!("Invalid kitty graphics key {:?}", String::from_utf8_lossy(pair));
+                return None;
+            }
+
+            let (key, value) = (pair[0], &pair[2..]);
+            let number = || str::from_utf8(value).ok()?.parse::<u32>().ok();
+
+            match key {
+                b'a' => {
+                    command.action = match value {
+                        b"t" => Action::Transmit,
+                        b"T" => Action::TransmitAndDisplay,
+                        b"p" => Action::Put,
+                        b"d" => Action::Delete,
+                        b"q" => Action::Query,
+                        _ => return None,
+                    }
+                },
+                b'f' => {
+                    command.format = match number()? {
+                        24 => Format::Rgb,
+                        32 => Format::Rgba,
+                        100 => Format::Png,
+                        _ => return None,
+                    }
+                },
+                b't' => command.medium = value[0],
+                b'o' => command.compression = Some(value[0]),
+                b's' => command.width = number()?,
+                b'v' => command.height = number()?,
+                b'i' => command.image_id = number()?,
+                b'I' => command.image_number = number()?,
+                b'p' => command.placement_id = number()?,
+                b'm' => command.more = number()? == 1,
+                b'q' => command.quiet = number()?.min(2) as u8,
+                b'x' => command.source.0 = number()?,
+                b'y' => command.source.1 = number()?,
+                b'w' => command.source.2 = number()?,
+                b'h' => command.source.3 = number()?,
+                b'X' => command.offset.0 = number()?,
+                b'Y' => command.offset.1 = number()?,
+                b'c' => command.columns = number()?,
+                b'r' => command.rows = number()?,
+                b'z' => command.z_index = str::from_utf8(value).ok()?.parse().ok()?,
+                b'C' => command.keep_cursor = number()? == 1,
+                b'd' => command.delete = value[0],
+                _ => debug!("Ignoring kitty graphics key {:?}", key as char),
+            }
+        }
+
+        Some(command)
+    }
+}
+
+/// Reason a command failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The referenced image does not exist.
+    NotFound,
+
+    /// The command is invalid or unsupported.
+    InvalidArgument(&'static str),
+
+    /// The payload is smaller than the declared image size.
+    NoData,
+
+    /// The image is larger than allowed.
+    TooLarge,
+
+    /// The payload is not a valid PNG image.
+    BadPng,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound => f.write_str("ENOENT:Image not found"),
+            Error::InvalidArgument(reason) => write!(f, "EINVAL:{}", reason),
+            Error::NoData => f.write_str("ENODATA:Insufficient image data"),
+            Error::TooLarge => f.write_str("EFBIG:Image is too large"),
+            Error::BadPng => f.write_str("EBADPNG:Unable to decode PNG data"),
+        }
+    }
+}
+
+/// Reply to a command, if the client asked for one.
+///
+/// Only commands with an image id or number are answered.
+pub fn response(command: &Command, result: &Result<(), Error>) -> Option<String> {
+    if command.image_id == 0 && command.image_number == 0 {
+        return None;
+    }
+
+    let message = match result {
+        Ok(()) if command.quiet == 0 => String::from("OK"),
+        Err(err) if command.quiet < 2 => err.to_string(),
+        _ => return None,
+    };
+
+    let mut keys = format!("i={}", command.image_id);
+    if command.image_number != 0 {
+        keys.push_str(&format!(",I={}", command.image_number));
+    }
+    if command.placement_id != 0 {
+        keys.push_str(&format!(",p={}", command.placement_id));
+    }
+
+    Some(format!("\x1b_G{};{}\x1b\\", keys, message))
+}
+
+/// Decode the image transmitted by a command.
+pub fn decode(command: &Command) -> Result<Image, Error> {
+    if command.medium != b'd' {
+        return Err(Error::InvalidArgument("Only direct transmission is supported"));
+    }
+
+    let mut data = base64::decode(&command.payload)
+        .map_err(|_| Error::InvalidArgument("Invalid base64 payload"))?;
+
+    let bytes_per_pixel = match command.format {
+        Format::Png => {
+            if command.compression.is_some() {
+                data = decompress_payload(command, &data, MAX_PAYLOAD_SIZE)?;
+            }
+            return decode_png(&data);
+        },
+        Format::Rgb => 3,
+        Format::Rgba => 4,
+    };
+
+    let (width, height) = (command.width as usize, command.height as usize);
+    if width == 0 || height == 0 {
+        return Err(Error::InvalidArgument("Missing image size"));
+    } else if width > MAX_GRAPHIC_DIMENSIONS || height > MAX_GRAPHIC_DIMENSIONS {
+        return Err(Error::TooLarge);
+    }
+
+    let size = width * height * bytes_per_pixel;
+    if command.compression.is_some() {
+        data = decompress_payload(command, &data, size)?;
+    }
+
+    if data.len() < size {
+        return Err(Error::NoData);
+    }
+
+    data.truncate(size);
+    let pixels = if bytes_per_pixel == 3 { rgb_to_rgba(&data) } else { data };
+
+    Ok(Image { width, height, pixels })
+}
+
+/// Decompress the payload of a command, which must not inflate to more than `limit` bytes.
+fn decompress_payload(command: &Command, data: &[u8], limit: usize) -> Result<Vec<u8>, Error> {
+    match command.compression {
+        Some(b'z') => inflate_zlib(data, limit),
+        _ => Err(Error::InvalidArgument("Unsupported compression")),
+    }
+}
+
+/// Inflate zlib data, failing as soon as the output grows beyond `limit` bytes.
+///
+/// This keeps small payloads from expanding into gigabytes of memory.
+fn inflate_zlib(data: &[u8], limit: usize) -> Result<Vec<u8>, Error> {
+    let flags = inflate_flags::TINFL_FLAG_PARSE_ZLIB_HEADER
+        | inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF;
+
+    let mut decompressor = Box::<DecompressorOxide>::default();
+    let mut output = vec![0; (data.len() * 2).min(limit).max(1)];
+    let (mut in_pos, mut out_pos) = (0, 0);
+
+    loop {
+        // The decompressor is handed the entire output, since matches refer back into it.
+        let mut cursor = Cursor::new(output.as_mut_slice());
+        cursor.set_position(out_pos as u64);
+        let (status, consumed, written) =
+            decompress(&mut decompressor, &data[in_pos..], &mut cursor, flags);
+        in_pos += consumed;
+        out_pos += written;
+
+        match status {
+            TINFLStatus::Done => {
+                output.truncate(out_pos);
+                return Ok(output);
+            },
+            TINFLStatus::HasMoreOutput if output.len() < limit => {
+                let len = (output.len() * 2).min(limit);
+                output.resize(len, 0);
+            },
+            TINFLStatus::HasMoreOutput => {
+                return Err(Error::InvalidArgument("Decompressed data exceeds the image size"));
+            },
+            _ => return Err(Error::InvalidArgument("Invalid zlib data")),
+        }
+    }
+}
+
+fn decode_png(data: &[u8]) -> Result<Image, Error> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+    let (info, mut reader) = decoder.read_info().map_err(|_| Error::BadPng)?;
+    let (width, height) = (info.width as usize, info.height as usize);
+    if width > MAX_GRAPHIC_DIMENSIONS || height > MAX_GRAPHIC_DIMENSIONS {
+        return Err(Error::TooLarge);
+    }
+
+    let mut buf = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buf).map_err(|_| Error::BadPng)?;
+
+    let pixels = match info.color_type {
+        png::ColorType::RGBA => buf,
+        png::ColorType::RGB => rgb_to_rgba(&buf),
+        png::ColorType::GrayscaleAlpha => {
+            let mut pixels = Vec::with_capacity(width * height * 4);
+            for pixel in buf.chunks_exact(2) {
+                pixels.extend_from_slice(&[pixel[0], pixel[0], pixel[0], pixel[1]]);
+            }
+            pixels
+        },
+        png::ColorType::Grayscale => {
+            let mut pixels = Vec::with_capacity(width * height * 4);
+            for &value in &buf {
+                pixels.extend_from_slice(&[value, value, value, 255]);
+            }
+            pixels
+        },
+        png::ColorType::Indexed => return Err(Error::BadPng),
+    };
+
+    Ok(Image { width, height, pixels })
+}
+
+fn rgb_to_rgba(data: &[u8]) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(data.len() / 3 * 4);
+    for pixel in data.chunks_exact(3) {
+        pixels.extend_from_slice(&[pixel[0], pixel[1], pixel[2], 255]);
+    }
+    pixels
+}
+
+/// Image transmitted by the client.
+#[derive(Debug)]
+struct StoredImage {
+    graphic: GraphicId,
+    number: u32,
+    width: usize,
+    height: usize,
+
+    /// Placements which have been given an id by the client.
+    placements: HashMap<u32, PlacementId>,
+}
+
+/// Images which can be referenced by later commands.
+#[derive(Debug, Default)]
+pub struct KittyImages {
+    images: HashMap<u32, StoredImage>,
+
+    /// Transmission whose payload is still being received in chunks.
+    loading: Option<Command>,
+
+    /// Whether the remaining chunks of a dropped transmission are ignored.
+    discarding: bool,
+
+    /// Last id the terminal has picked for an image.
+    last_assigned_id: u32,
+}
+
+impl KittyImages {
+    /// Collect the chunks of a transmission, returning the command once it is complete.
+    pub fn receive(&mut self, command: Command) -> Option<Command> {
+        // Chunks of an oversized payload must not be mistaken for new commands.
+        if self.discarding {
+            self.discarding = command.more;
+            return None;
+        }
+
+        let mut loading = match self.loading.take() {
+            Some(mut loading) => {
+                loading.payload.extend_from_slice(&command.payload);
+                loading.more = command.more;
+                loading
+            },
+            None => command,
+        };
+
+        if loading.payload.len() > MAX_PAYLOAD_SIZE {
+            debug!("Dropping kitty graphics payload larger than {} bytes", MAX_PAYLOAD_SIZE);
+            self.discarding = loading.more;
+            return None;
+        }
+
+        if loading.more {
+            loading.more = false;
+            self.loading = Some(loading);
+            return None;
+        }
+
+        Some(loading)
+    }
+
+    /// Decode and store a transmitted image, returning its id.
+    pub fn transmit(&mut self, graphics: &mut Graphics, command: &Command) -> Result<u32, Error> {
+        let image = decode(command)?;
+
+        let id = match command.image_id {
+            0 => self.unused_id(),
+            id => id,
+        };
+
+        // Images which are replaced are removed from the grid.
+        if let Some(old) = self.images.remove(&id) {
+            graphics.remove_placements_of(old.graphic);
+            graphics.release(old.graphic);
+        }
+
+        // Make room for the new image, evicting the oldest images which are not placed first.
+        while graphics.used_bytes() + image.pixels.len() > MAX_GRAPHICS_BYTES {
+            let oldest = self
+                .images
+                .iter()
+                .min_by_key(|(_, image)| (graphics.is_placed(image.graphic), image.graphic))
+                .map(|(id, _)| *id);
+
+            let image = match oldest.and_then(|id| self.images.remove(&id)) {
+                Some(image) => image,
+                None => break,
+            };
+            graphics.remove_placements_of(image.graphic);
+            graphics.release(image.graphic);
+        }
+
+        let (width, height) = (image.width, image.height);
+        let graphic = graphics.add_image(image);
+        let number = command.image_number;
+        let stored = StoredImage { graphic, number, width, height, placements: HashMap::new() };
+        self.images.insert(id, stored);
+
+        Ok(id)
+    }
+
+    /// Id of the image referenced by a command.
+    ///
+    /// Image numbers refer to the newest image transmitted with that number.
+    pub fn find(&self, command: &Command) -> Option<u32> {
+        if command.image_id != 0 {
+            return Some(command.image_id).filter(|id| self.images.contains_key(id));
+        }
+
+        self.images
+            .iter()
+            .filter(|(_, image)| command.image_number != 0 && image.number == command.image_number)
+            .max_by_key(|(_, image)| image.graphic)
+            .map(|(id, _)| *id)
+    }
+
+    /// Place an image with its top left corner in the cell at `line` and `column`.
+    ///
+    /// The image is scaled to at most the `screen_size` in columns and lines.
+    ///
+    /// Returns the number of columns and lines covered by the image.
+    pub fn place(
+        &mut self,
+        graphics: &mut Graphics,
+        id: u32,
+        command: &Command,
+        point: Point,
+        cell_size: (usize, usize),
+        screen_size: (usize, usize),
+    ) -> Result<(usize, usize), Error> {
+        let image = self.images.get_mut(&id).ok_or(Error::NotFound)?;
+
+        let (cell_width, cell_height) = (cell_size.0.max(1) as f32, cell_size.1.max(1) as f32);
+        let (image_width, image_height) = (image.width as f32, image.height as f32);
+
+        // Clamp the source rectangle to the image.
+        let (x, y, width, height) = command.source;
+        let x = (x as f32).min(image_width);
+        let y = (y as f32).min(image_height);
+        let width = if width == 0 { image_width - x } else { (width as f32).min(image_width - x) };
+        let height =
+            if height == 0 { image_height - y } else { (height as f32).min(image_height - y) };
+        if width <= 0. || height <= 0. {
+            return Err(Error::InvalidArgument("Empty source rectangle"));
+        }
+
+        // Scale to the requested cells, keeping the aspect ratio if only one side is given.
+        let (columns, lines) = match (command.columns, command.rows) {
+            (0, 0) => (width / cell_width, height / cell_height),
+            (0, rows) => {
+                let lines = rows as f32;
+                (lines * cell_height * width / height / cell_width, lines)
+            },
+            (columns, 0) => {
+                let columns = columns as f32;
+                (columns, columns * cell_width * height / width / cell_height)
+            },
+            (columns, rows) => (columns as f32, rows as f32),
+        };
+
+        // Images never cover more cells than the screen has.
+        let columns = columns.min(screen_size.0.max(1) as f32);
+        let lines = lines.min(screen_size.1.max(1) as f32);
+
+        let offset = (
+            (command.offset.0 as f32 / cell_width).min(1.),
+            (command.offset.1 as f32 / cell_height).min(1.),
+        );
+
+        let geometry = Geometry {
+            offset,
+            columns,
+            lines,
+            source: [x / image_width, y / image_height, width / image_width, height / image_height],
+            z_index: command.z_index,
+        };
+
+        // Placing an image again with the same placement id moves it.
+        if let Some(old) = image.placements.remove(&command.placement_id) {
+            graphics.remove_placement(old);
+        }
+
+        let placement = graphics.place(image.graphic, point.line, point.col, geometry);
+        if command.placement_id != 0 {
+            image.placements.insert(command.placement_id, placement);
+        }
+
+        let covered_columns = (offset.0 + columns).ceil().max(1.) as usize;
+        let covered_lines = (offset.1 + lines).ceil().max(1.) as usize;
+        Ok((covered_columns, covered_lines))
+    }
+
+    /// Delete placements and images selected by the `d` key of a command.
+    ///
+    /// Uppercase selectors also free the data of images which are no longer placed.
+    pub fn delete(&mut self, graphics: &mut Graphics, command: &Command, cursor: Point) {
+        match command.delete.to_ascii_lowercase() {
+            b'a' => graphics.remove_all_placements(),
+            b'i' | b'n' => {
+                let id = if command.delete.to_ascii_lowercase() == b'i' {
+                    Some(command.image_id).filter(|id| self.images.contains_key(id))
+                } else {
+                    self.find(&Command { image_id: 0, ..command.clone() })
+                };
+
+                if let Some(image) = id.and_then(|id| self.images.get_mut(&id)) {
+                    match image.placements.remove(&command.placement_id) {
+                        Some(placement) => graphics.remove_placement(placement),
+                        None if command.placement_id == 0 => {
+                            graphics.remove_placements_of(image.graphic)
+                        },
+                        None => (),
+                    }
+                }
+            },
+            b'c' => graphics.remove_at(cursor.line, cursor.col),
+            selector => debug!("Unsupported kitty graphics deletion {:?}", selector as char),
+        }
+
+        if command.delete.is_ascii_uppercase() {
+            let unplaced: Vec<u32> = self
+                .images
+                .iter()
+                .filter(|(_, image)| !graphics.is_placed(image.graphic))
+                .map(|(id, _)| *id)
+                .collect();
+
+            for id in unplaced {
+                let image = self.images.remove(&id).unwrap();
+                graphics.release(image.graphic);
+            }
+        }
+    }
+
+    /// Forget all images and abort incomplete transmissions.
+    pub fn clear(&mut self) {
+        self.images.clear();
+        self.loading = None;
+        self.discarding = false;
+    }
+
+    /// Pick an id which is not used by any stored image.
+    fn unused_id(&mut self) -> u32 {
+        loop {
+            self.last_assigned_id = self.last_assigned_id.wrapping_add(1).max(1);
+            if !self.images.contains_key(&self.last_assigned_id) {
+                return self.last_assigned_id;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::index::{Column, Line};
+
+    fn command(control: &str, payload: &[u8]) -> Command {
+        let mut data = control.as_bytes().to_vec();
+        data.push(b';');
+        data.extend_from_slice(base64::encode(payload).as_bytes());
+        Command::parse(&data).unwrap()
+    }
+
+    #[test]
+    fn parse_control_data() {
+        let command = Command::parse(b"a=T,f=24,s=2,v=1,i=7,c=3,z=-5,C=1;AAAA").unwrap();
+        assert_eq!(command.action, Action::TransmitAndDisplay);
+        assert_eq!(command.format, Format::Rgb);
+        assert_eq!((command.width, command.height), (2, 1));
+        assert_eq!(command.image_id, 7);
+        assert_eq!(command.columns, 3);
+        assert_eq!(command.z_index, -5);
+        assert!(command.keep_cursor);
+        assert_eq!(command.payload, b"AAAA");
+
+        assert_eq!(Command::parse(b"a=x"), None);
+        assert_eq!(Command::parse(b"i"), None);
+    }
+
+    #[test]
+    fn decode_raw() {
+        let image = decode(&command("f=24,s=2,v=1", &[1, 2, 3, 4, 5, 6])).unwrap();
+        assert_eq!(image.pixels, vec![1, 2, 3, 255, 4, 5, 6, 255]);
+
+        assert_eq!(decode(&command("f=32,s=2,v=1", &[0; 4])), Err(Error::NoData));
+        assert_eq!(
+            decode(&command("s=1,v=1,t=f", &[0; 4])),
+            Err(Error::InvalidArgument("Only direct transmission is supported"))
+        );
+    }
+
+    #[test]
+    fn decode_compressed() {
+        let data = miniz_oxide::deflate::compress_to_vec_zlib(&[7; 8], 6);
+        let image = decode(&command("f=32,s=2,v=1,o=z", &data)).unwrap();
+        assert_eq!(image.pixels, vec![7; 8]);
+
+        // Payloads inflating beyond the declared image size are rejected early.
+        let bomb = miniz_oxide::deflate::compress_to_vec_zlib(&[0; 1024 * 1024], 10);
+        assert!(bomb.len() < 4096);
+        assert_eq!(
+            decode(&command("f=32,s=2,v=2,o=z", &bomb)),
+            Err(Error::InvalidArgument("Decompressed data exceeds the image size"))
+        );
+    }
+
+    #[test]
+    fn chunked_transmission() {
+        let mut images = KittyImages::default();
+        let payload = base64::encode(&[9; 8]);
+        let (first, second) = payload.split_at(4);
+
+        let first = format!("a=t,s=2,v=1,i=3,m=1;{}", first);
+        assert_eq!(images.receive(Command::parse(first.as_bytes()).unwrap()), None);
+
+        let second = format!("m=0;{}", second);
+        let command = images.receive(Command::parse(second.as_bytes()).unwrap()).unwrap();
+        assert_eq!(command.image_id, 3);
+        assert_eq!(decode(&command).unwrap().pixels, vec![9; 8]);
+    }
+
+    #[test]
+    fn discard_oversized_transmission() {
+        let mut images = KittyImages::default();
+
+        let first = Command {
+            image_id: 1,
+            more: true,
+            payload: vec![b'A'; MAX_PAYLOAD_SIZE + 1],
+            ..Command::default()
+        };
+        assert_eq!(images.receive(first), None);
+
+        // Remaining chunks are dropped instead of being handled as new commands.
+        let chunk = Command::parse(b"m=1;AAAA").unwrap();
+        assert_eq!(images.receive(chunk), None);
+        let last = Command::parse(b"m=0;AAAA").unwrap();
+        assert_eq!(images.receive(last), None);
+
+        let next = Command::parse(b"a=q,i=2").unwrap();
+        assert_eq!(images.receive(next).unwrap().image_id, 2);
+    }
+
+    #[test]
+    fn place_and_delete() {
+        let mut images = KittyImages::default();
+        let mut graphics = Graphics::default();
+
+        let transmit = command("a=T,s=20,v=10,i=1,p=2,c=4", &[0; 800]);
+        let id = images.transmit(&mut graphics, &transmit).unwrap();
+        assert_eq!(id, 1);
+
+        let cursor = Point::new(Line(0), Column(0));
+        let covered = images.place(&mut graphics, id, &transmit, cursor, (10, 10), (80, 24));
+        assert_eq!(covered.unwrap(), (4, 2));
+
+        // Placing with the same placement id replaces the placement.
+        images.place(&mut graphics, id, &transmit, cursor, (10, 10), (80, 24)).unwrap();
+        assert_eq!(graphics.renderable(0, Line(5)).len(), 1);
+
+        // Huge placements are limited to the screen.
+        let huge = command("a=p,i=1,r=4000000000", &[]);
+        let covered = images.place(&mut graphics, id, &huge, cursor, (10, 10), (80, 24));
+        assert_eq!(covered.unwrap(), (80, 24));
+
+        let delete = Command { delete: b'I', image_id: 1, ..Command::default() };
+        images.delete(&mut graphics, &delete, cursor);
+        assert!(graphics.renderable(0, Line(5)).is_empty());
+        assert_eq!(images.find(&Command { image_id: 1, ..Command::default() }), None);
+    }
+
+    #[test]
+    fn responses() {
+        let command = Command { image_id: 4, ..Command::default() };
+        assert_eq!(response(&command, &Ok(())), Some(String::from("\x1b_Gi=4;OK\x1b\\")));
+        assert_eq!(
+            response(&command, &Err(Error::NotFound)),
+            Some(String::from("\x1b_Gi=4;ENOENT:Image not found\x1b\\"))
+        );
+
+        let quiet = Command { quiet: 1, ..command };
+        assert_eq!(response(&quiet, &Ok(())), None);
+
+        assert_eq!(response(&Command::default(), &Ok(())), None);
+    }
+}
@@ -0,0 +1,510 @@
+//! Images placed on the terminal grid.
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::ops::Range;
+
+use crate::index::{Column, Line};
+
+pub mod iterm;
+pub mod kitty;
+pub mod sixel;
+
+/// Largest width and height of an image, in pixels.
+pub const MAX_GRAPHIC_DIMENSIONS: usize = 4096;
+
+/// Most bytes of decoded pixels kept alive by all images together.
+pub const MAX_GRAPHICS_BYTES: usize = 320 * 1024 * 1024;
+
+/// Unique identifier of an image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GraphicId(pub u64);
+
+/// Decoded image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+
+    /// RGBA pixels, row by row starting at the top.
+    pub pixels: Vec<u8>,
+}
+
+/// Image which still has to be uploaded by the renderer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphicData {
+    pub id: GraphicId,
+    pub image: Image,
+}
+
+/// Area of the grid covered by an image.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Geometry {
+    /// Offset of the top left corner within its cell, as a fraction of the cell size.
+    pub offset: (f32, f32),
+
+    /// Size of the image in cells.
+    pub columns: f32,
+    pub lines: f32,
+
+    /// Part of the image which is drawn, as left, top, width and height relative to its size.
+    pub source: [f32; 4],
+
+    /// Images with a negative z-index are drawn below the text.
+    pub z_index: i32,
+}
+
+impl Geometry {
+    /// Draw the entire image across `columns` and `lines` cells, on top of the text.
+    pub fn new(columns: f32, lines: f32) -> Self {
+        Self { offset: (0., 0.), columns, lines, source: [0., 0., 1., 1.], z_index: 0 }
+    }
+}
+
+/// Unique identifier of an image placed on the grid.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PlacementId(u64);
+
+/// Image visible in the viewport.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RenderableGraphic {
+    pub id: GraphicId,
+
+    /// Viewport line of the top edge, negative if the image is partially scrolled out.
+    pub line: isize,
+    pub column: Column,
+
+    pub geometry: Geometry,
+}
+
+/// Position of an image on the grid.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Placement {
+    id: PlacementId,
+    graphic: GraphicId,
+
+    /// Line of the top edge, relative to the top of the screen.
+    ///
+    /// Images which have been scrolled into history have a negative line.
+    line: isize,
+    column: Column,
+
+    /// Covered area, with the size in cells at the time the image was placed.
+    geometry: Geometry,
+}
+
+impl Placement {
+    /// Line below the bottom edge of the image.
+    fn end(&self) -> isize {
+        self.line + (self.geometry.offset.1 + self.geometry.lines).ceil() as isize
+    }
+}
+
+/// Images of both screen buffers.
+///
+/// Images stay alive while they are placed on the grid, or until they are released by whoever
+/// added them. Once their pixels exceed `MAX_GRAPHICS_BYTES`, the oldest placements of images
+/// which are not retained are removed.
+#[derive(Debug, Default)]
+pub struct Graphics {
+    next_id: u64,
+
+    /// Images which have not been uploaded yet.
+    pending: Vec<GraphicData>,
+
+    /// Images which are no longer visible anywhere.
+    removed: Vec<GraphicId>,
+
+    /// Images which are kept alive even without placements.
+    retained: HashSet<GraphicId>,
+
+    /// Size of the pixels of every image which is alive.
+    sizes: HashMap<GraphicId, usize>,
+
+    /// Images of the active screen buffer.
+    placements: Vec<Placement>,
+
+    /// Images of the inactive screen buffer.
+    inactive_placements: Vec<Placement>,
+}
+
+impl Graphics {
+    /// Add an image which is kept alive until it is released.
+    pub fn add_image(&mut self, image: Image) -> GraphicId {
+        let id = GraphicId(self.next_id);
+        self.next_id += 1;
+
+        self.retained.insert(id);
+        self.sizes.insert(id, image.pixels.len());
+        self.pending.push(GraphicData { id, image });
+
+        self.evict(MAX_GRAPHICS_BYTES);
+
+        id
+    }
+
+    /// Stop keeping an image alive, it is removed once it is no longer placed anywhere.
+    pub fn release(&mut self, graphic: GraphicId) {
+        if self.retained.remove(&graphic) {
+            self.collect(graphic);
+        }
+    }
+
+    /// Place an image with its top left corner in the cell at `line` and `column`.
+    pub fn place(
+        &mut self,
+        graphic: GraphicId,
+        line: Line,
+        column: Column,
+        geometry: Geometry,
+    ) -> PlacementId {
+        let id = PlacementId(self.next_id);
+        self.next_id += 1;
+
+        let line = line.0 as isize;
+        self.placements.push(Placement { id, graphic, line, column, geometry });
+
+        id
+    }
+
+    /// Place an image at its pixel size, which is only kept alive while it is on the grid.
+    pub fn insert(&mut self, image: Image, line: Line, column: Column, cell_size: (usize, usize)) {
+        let (cell_width, cell_height) = cell_size;
+        let columns = image.width as f32 / cell_width.max(1) as f32;
+        let lines = image.height as f32 / cell_height.max(1) as f32;
+        self.insert_scaled(image, line, column, Geometry::new(columns, lines));
+    }
+
+    /// Place an image scaled to `geometry`, which is only kept alive while it is on the grid.
+    pub fn insert_scaled(&mut self, image: Image, line: Line, column: Column, geometry: Geometry) {
+        let graphic = self.add_image(image);
+        self.place(graphic, line, column, geometry);
+        self.release(graphic);
+    }
+
+    /// Bytes of decoded pixels of all images which are alive.
+    pub fn used_bytes(&self) -> usize {
+        self.sizes.values().sum()
+    }
+
+    /// Take all images inserted and removed since the last call.
+    pub fn take_updates(&mut self) -> (Vec<GraphicData>, Vec<GraphicId>) {
+        (mem::take(&mut self.pending), mem::take(&mut self.removed))
+    }
+
+    /// Move images in `region` up by `lines`.
+    ///
+    /// Images scrolled out of a region at the top of the screen move into the history, which
+    /// holds at most `history_size` lines. In every other region they are removed.
+    pub fn scroll_up(&mut self, region: &Range<Line>, lines: Line, history_size: usize) {
+        let start = region.start.0 as isize;
+        let end = region.end.0 as isize;
+        let lines = lines.0 as isize;
+
+        for placement in &mut self.placements {
+            let inside = if start == 0 {
+                placement.end() <= end
+            } else {
+                placement.line >= start && placement.end() <= end
+            };
+
+            if inside {
+                placement.line -= lines;
+            }
+        }
+
+        if start == 0 {
+            let history_start = -(history_size as isize);
+            self.remove_where(|placement| placement.end() <= history_start);
+        } else {
+            self.remove_where(|placement| placement.line < start && placement.end() <= end);
+        }
+    }
+
+    /// Move images in `region` down by `lines`, removing those scrolled out of it.
+    pub fn scroll_down(&mut self, region: &Range<Line>, lines: Line) {
+        let start = region.start.0 as isize;
+        let end = region.end.0 as isize;
+        let lines = lines.0 as isize;
+
+        let mut scrolled_out = Vec::new();
+        for placement in &mut self.placements {
+            if placement.line >= start && placement.end() <= end {
+                placement.line += lines;
+
+                if placement.end() > end {
+                    scrolled_out.push(placement.id);
+                }
+            }
+        }
+
+        self.remove_where(|placement| scrolled_out.contains(&placement.id));
+    }
+
+    /// Remove all images which overlap `lines`, relative to the top of the screen.
+    pub fn clear_lines(&mut self, lines: Range<isize>) {
+        self.remove_where(|placement| placement.line < lines.end && placement.end() > lines.start);
+    }
+
+    /// Remove a single placement from the active screen buffer.
+    pub fn remove_placement(&mut self, id: PlacementId) {
+        self.remove_where(|placement| placement.id == id);
+    }
+
+    /// Remove all placements of an image from the active screen buffer.
+    pub fn remove_placements_of(&mut self, graphic: GraphicId) {
+        self.remove_where(|placement| placement.graphic == graphic);
+    }
+
+    /// Remove all placements of the active screen buffer covering a cell.
+    pub fn remove_at(&mut self, line: Line, column: Column) {
+        let line = line.0 as isize;
+        self.remove_where(|placement| {
+            let columns = (placement.geometry.offset.0 + placement.geometry.columns).ceil();
+            placement.line <= line
+                && placement.end() > line
+                && placement.column <= column
+                && placement.column.0 + (columns as usize).max(1) > column.0
+        });
+    }
+
+    /// Remove all placements of the active screen buffer.
+    pub fn remove_all_placements(&mut self) {
+        self.remove_where(|_| true);
+    }
+
+    /// Swap the images of the active and the inactive screen buffer.
+    pub fn swap_screen(&mut self) {
+        mem::swap(&mut self.placements, &mut self.inactive_placements);
+    }
+
+    /// Remove all images of both screen buffers.
+    pub fn reset(&mut self) {
+        let placements = self.placements.drain(..).chain(self.inactive_placements.drain(..));
+        let mut graphics: HashSet<GraphicId> =
+            placements.map(|placement| placement.graphic).collect();
+        graphics.extend(self.retained.drain());
+
+        for data in self.pending.drain(..) {
+            graphics.remove(&data.id);
+        }
+
+        self.removed.extend(graphics);
+        self.sizes.clear();
+    }
+
+    /// Images visible in the viewport, sorted by their z-index.
+    pub fn renderable(&self, display_offset: usize, screen_lines: Line) -> Vec<RenderableGraphic> {
+        let screen_lines = screen_lines.0 as isize;
+
+        let mut graphics: Vec<_> = self
+            .placements
+            .iter()
+            .filter(|placement| {
+                let offset = display_offset as isize;
+                placement.line + offset < screen_lines && placement.end() + offset > 0
+            })
+            .map(|placement| RenderableGraphic {
+                id: placement.graphic,
+                line: placement.line + display_offset as isize,
+                column: placement.column,
+                geometry: placement.geometry,
+            })
+            .collect();
+
+        graphics.sort_by_key(|graphic| graphic.geometry.z_index);
+
+        graphics
+    }
+
+    /// Whether an image is placed in any of the screen buffers.
+    pub fn is_placed(&self, graphic: GraphicId) -> bool {
+        self.placements
+            .iter()
+            .chain(self.inactive_placements.iter())
+            .any(|placement| placement.graphic == graphic)
+    }
+
+    /// Remove the oldest placements of images which are not retained, until all images together
+    /// use at most `limit` bytes.
+    fn evict(&mut self, limit: usize) {
+        while self.used_bytes() > limit {
+            let retained = &self.retained;
+            let oldest = self
+                .placements
+                .iter()
+                .chain(self.inactive_placements.iter())
+                .filter(|placement| !retained.contains(&placement.graphic))
+                .min_by_key(|placement| placement.id.0)
+                .map(|placement| placement.graphic);
+
+            let graphic = match oldest {
+                Some(graphic) => graphic,
+                None => break,
+            };
+
+            // Images placed more than once are only freed without any of their placements.
+            self.placements.retain(|placement| placement.graphic != graphic);
+            self.inactive_placements.retain(|placement| placement.graphic != graphic);
+            self.collect(graphic);
+        }
+    }
+
+    /// Remove all placements of the active screen buffer matching `f`.
+    fn remove_where<F: Fn(&Placement) -> bool>(&mut self, f: F) {
+        let mut graphics = Vec::new();
+        self.placements.retain(|placement| {
+            if f(placement) {
+                graphics.push(placement.graphic);
+                false
+            } else {
+                true
+            }
+        });
+
+        for graphic in graphics {
+            self.collect(graphic);
+        }
+    }
+
+    /// Remove an image if it is neither retained nor placed anywhere.
+    fn collect(&mut self, graphic: GraphicId) {
+        if self.is_placed(graphic)
+            || self.retained.contains(&graphic)
+            || self.removed.contains(&graphic)
+        {
+            return;
+        }
+
+        self.sizes.remove(&graphic);
+
+        // Images removed before the renderer saw them never have to be uploaded.
+        let pending_len = self.pending.len();
+        self.pending.retain(|data| data.id != graphic);
+        if self.pending.len() == pending_len {
+            self.removed.push(graphic);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(height: usize) -> Image {
+        Image { width: 10, height, pixels: vec![0; 10 * height * 4] }
+    }
+
+    fn lines(graphics: &Graphics) -> Vec<isize> {
+        graphics.placements.iter().map(|placement| placement.line).collect()
+    }
+
+    #[test]
+    fn retained_image() {
+        let mut graphics = Graphics::default();
+        let graphic = graphics.add_image(image(10));
+        graphics.take_updates();
+
+        let placement = graphics.place(graphic, Line(0), Column(0), Geometry::new(1., 1.));
+        graphics.place(graphic, Line(1), Column(0), Geometry::new(1., 1.));
+        graphics.remove_placement(placement);
+        assert_eq!(lines(&graphics), vec![1]);
+
+        graphics.remove_all_placements();
+        assert!(graphics.take_updates().1.is_empty());
+
+        graphics.release(graphic);
+        assert_eq!(graphics.take_updates().1, vec![graphic]);
+    }
+
+    #[test]
+    fn sorted_by_z_index() {
+        let mut graphics = Graphics::default();
+        let graphic = graphics.add_image(image(10));
+
+        let mut geometry = Geometry::new(1., 1.);
+        geometry.z_index = 1;
+        graphics.place(graphic, Line(0), Column(0), geometry);
+        geometry.z_index = -1;
+        graphics.place(graphic, Line(1), Column(0), geometry);
+
+        let renderable = graphics.renderable(0, Line(5));
+        assert_eq!(renderable.iter().map(|graphic| graphic.line).collect::<Vec<_>>(), vec![1, 0]);
+    }
+
+    #[test]
+    fn scroll_into_history() {
+        let mut graphics = Graphics::default();
+        graphics.insert(image(20), Line(1), Column(0), (10, 10));
+        graphics.take_updates();
+
+        graphics.scroll_up(&(Line(0)..Line(5)), Line(2), 10);
+        assert_eq!(lines(&graphics), vec![-1]);
+
+        let renderable = graphics.renderable(0, Line(5));
+        assert_eq!(renderable.len(), 1);
+        assert_eq!(renderable[0].line, -1);
+        assert_eq!(renderable[0].geometry.lines, 2.);
+
+        graphics.scroll_up(&(Line(0)..Line(5)), Line(1), 0);
+        assert!(graphics.placements.is_empty());
+        assert_eq!(graphics.take_updates().1, vec![GraphicId(0)]);
+    }
+
+    #[test]
+    fn scroll_region() {
+        let mut graphics = Graphics::default();
+        graphics.insert(image(10), Line(0), Column(0), (10, 10));
+        graphics.insert(image(10), Line(3), Column(0), (10, 10));
+
+        graphics.scroll_up(&(Line(2)..Line(5)), Line(1), 10);
+        assert_eq!(lines(&graphics), vec![0, 2]);
+
+        graphics.scroll_up(&(Line(2)..Line(5)), Line(1), 10);
+        assert_eq!(lines(&graphics), vec![0]);
+
+        // Removed before it was uploaded.
+        assert_eq!(graphics.take_updates().0.len(), 1);
+
+        graphics.scroll_down(&(Line(0)..Line(5)), Line(4));
+        assert_eq!(lines(&graphics), vec![4]);
+
+        graphics.scroll_down(&(Line(0)..Line(5)), Line(1));
+        assert!(graphics.placements.is_empty());
+        assert_eq!(graphics.take_updates().1, vec![GraphicId(0)]);
+    }
+
+    #[test]
+    fn evict_oldest_placements() {
+        let mut graphics = Graphics::default();
+        graphics.insert(image(10), Line(0), Column(0), (10, 10));
+        graphics.insert(image(10), Line(1), Column(0), (10, 10));
+        graphics.swap_screen();
+        graphics.insert(image(10), Line(2), Column(0), (10, 10));
+        let retained = graphics.add_image(image(10));
+        graphics.place(retained, Line(3), Column(0), Geometry::new(1., 1.));
+        assert_eq!(graphics.used_bytes(), 4 * 400);
+
+        // Retained images are left to whoever added them.
+        graphics.evict(2 * 400);
+        assert_eq!(graphics.used_bytes(), 2 * 400);
+        assert_eq!(lines(&graphics), vec![2, 3]);
+        assert!(graphics.inactive_placements.is_empty());
+
+        graphics.evict(0);
+        assert_eq!(graphics.used_bytes(), 400);
+        assert_eq!(lines(&graphics), vec![3]);
+    }
+
+    #[test]
+    fn swap_screen() {
+        let mut graphics = Graphics::default();
+        graphics.insert(image(10), Line(0), Column(0), (10, 10));
+
+        graphics.swap_screen();
+        assert!(graphics.renderable(0, Line(5)).is_empty());
+
+        graphics.swap_screen();
+        assert_eq!(graphics.renderable(0, Line(5)).len(), 1);
+    }
+}
@@ -0,0 +1,340 @@
+//! Decoder for DEC Sixel graphics.
+//!
+//! Sixel data is a stream of characters which each describe a column of six vertical pixels,
+//! interleaved with commands to select colors, repeat sixels and move to the next band of six
+//! pixel rows.
+
+use std::mem;
+
+use log::debug;
+
+use crate::graphics::{Image, MAX_GRAPHIC_DIMENSIONS};
+use crate::term::color::Rgb;
+
+/// Number of color registers.
+const PALETTE_SIZE: usize = 256;
+
+/// Maximum number of parameters of a single command.
+const MAX_PARAMS: usize = 5;
+
+/// Default color registers of the VT340, as RGB percentages.
+const VT340_PALETTE: [(u16, u16, u16); 16] = [
+    (0, 0, 0),
+    (20, 20, 80),
+    (80, 13, 13),
+    (20, 80, 20),
+    (80, 20, 80),
+    (20, 80, 80),
+    (80, 80, 20),
+    (53, 53, 53),
+    (26, 26, 26),
+    (33, 33, 60),
+    (60, 26, 26),
+    (33, 60, 33),
+    (60, 33, 60),
+    (33, 60, 60),
+    (60, 60, 33),
+    (80, 80, 80),
+];
+
+/// Command whose parameters are currently being read.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Command {
+    /// Sixel data, without parameters.
+    Data,
+
+    /// Raster attributes `"Pan;Pad;Ph;Pv`.
+    RasterAttributes,
+
+    /// Color introducer `#Pc;Pu;Px;Py;Pz`.
+    Color,
+
+    /// Graphics repeat introducer `!Pn`.
+    Repeat,
+}
+
+/// Incremental Sixel decoder, fed with the bytes of a DCS sequence.
+#[derive(Debug)]
+pub struct Parser {
+    command: Command,
+    params: Vec<u16>,
+
+    palette: Vec<Rgb>,
+    color: Rgb,
+
+    /// Number of times the next sixel is drawn.
+    repeat: usize,
+
+    /// Position of the next sixel, `y` is the top of the current band.
+    x: usize,
+    y: usize,
+
+    /// Size declared by the raster attributes.
+    declared_size: (usize, usize),
+
+    /// RGBA pixels of every row, unset pixels are fully transparent.
+    rows: Vec<Vec<[u8; 4]>>,
+
+    /// Whether pixels which are never set stay transparent.
+    transparent_background: bool,
+}
+
+impl Parser {
+    /// Create a parser, `transparent_background` is set when the second parameter of the DCS
+    /// sequence is `1`.
+    pub fn new(transparent_background: bool) -> Self {
+        let mut palette = vec![Rgb::default(); PALETTE_SIZE];
+        for (color, &(r, g, b)) in palette.iter_mut().zip(VT340_PALETTE.iter()) {
+            *color = rgb_from_percent(r, g, b);
+        }
+
+        Self {
+            command: Command::Data,
+            params: Vec::new(),
+            color: palette[0],
+            palette,
+            repeat: 1,
+            x: 0,
+            y: 0,
+            declared_size: (0, 0),
+            rows: Vec::new(),
+            transparent_background,
+        }
+    }
+
+    /// Process the next byte of the sequence.
+    pub fn put(&mut self, byte: u8) {
+        match byte {
+            b'0'..=b'9' if self.command != Command::Data => {
+                if self.params.is_empty() {
+                    self.params.push(0);
+                }
+
+                let param = self.params.last_mut().unwrap();
+                *param = param.saturating_mul(10).saturating_add(u16::from(byte - b'0'));
+            },
+            b';' if self.command != Command::Data => {
+                if self.params.is_empty() {
+                    self.params.push(0);
+                }
+
+                if self.params.len() < MAX_PARAMS {
+                    self.params.push(0);
+                }
+            },
+            _ => {
+                self.finish_command();
+                self.dispatch(byte);
+            },
+        }
+    }
+
+    /// Finish decoding and return the image, if anything was drawn.
+    pub fn finish(mut self) -> Option<Image> {
+        self.finish_command();
+
+        let width = self.rows.iter().map(Vec::len).max().unwrap_or(0).max(self.declared_size.0);
+        let height = self.rows.len().max(self.declared_size.1);
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let background = if self.transparent_background {
+            [0; 4]
+        } else {
+            let Rgb { r, g, b } = self.palette[0];
+            [r, g, b, 255]
+        };
+
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            let row = self.rows.get(y).map(Vec::as_slice).unwrap_or(&[]);
+            for x in 0..width {
+                match row.get(x) {
+                    Some(pixel) if pixel[3] != 0 => pixels.extend_from_slice(pixel),
+                    _ => pixels.extend_from_slice(&background),
+                }
+            }
+        }
+
+        Some(Image { width, height, pixels })
+    }
+
+    /// Start a new command or draw sixel data.
+    fn dispatch(&mut self, byte: u8) {
+        match byte {
+            b'"' => self.command = Command::RasterAttributes,
+            b'#' => self.command = Command::Color,
+            b'!' => self.command = Command::Repeat,
+            b'$' => self.x = 0,
+            b'-' => {
+                self.x = 0;
+                self.y += 6;
+            },
+            b'?'..=b'~' => {
+                let count = mem::replace(&mut self.repeat, 1);
+                self.draw(byte - b'?', count);
+            },
+            _ => (),
+        }
+    }
+
+    /// Apply the parameters of the current command.
+    fn finish_command(&mut self) {
+        let params = mem::take(&mut self.params);
+        let param = |index: usize| params.get(index).copied().unwrap_or(0) as usize;
+
+        match mem::replace(&mut self.command, Command::Data) {
+            Command::Data => (),
+            Command::RasterAttributes => {
+                self.declared_size =
+                    (param(2).min(MAX_GRAPHIC_DIMENSIONS), param(3).min(MAX_GRAPHIC_DIMENSIONS));
+            },
+            Command::Color => {
+                let register = param(0) % PALETTE_SIZE;
+
+                if params.len() >= 5 {
+                    let (x, y, z) = (params[2], params[3], params[4]);
+                    match params[1] {
+                        1 => self.palette[register] = rgb_from_hls(x, y, z),
+                        2 => self.palette[register] = rgb_from_percent(x, y, z),
+                        coordinates => debug!("Unknown sixel color coordinates {}", coordinates),
+                    }
+                }
+
+                self.color = self.palette[register];
+            },
+            Command::Repeat => self.repeat = param(0).max(1),
+        }
+    }
+
+    /// Draw `count` columns of the six pixels in `bits` with the current color.
+    fn draw(&mut self, bits: u8, count: usize) {
+        let start = self.x.min(MAX_GRAPHIC_DIMENSIONS);
+        let end = self.x.saturating_add(count).min(MAX_GRAPHIC_DIMENSIONS);
+        self.x = self.x.saturating_add(count);
+
+        let Rgb { r, g, b } = self.color;
+        for bit in 0..6 {
+            let y = self.y + bit;
+            if bits & (1 << bit) == 0 || y >= MAX_GRAPHIC_DIMENSIONS {
+                continue;
+            }
+
+            if self.rows.len() <= y {
+                self.rows.resize_with(y + 1, Vec::new);
+            }
+
+            let row = &mut self.rows[y];
+            if row.len() < end {
+                row.resize(end, [0; 4]);
+            }
+
+            for pixel in &mut row[start..end] {
+                *pixel = [r, g, b, 255];
+            }
+        }
+    }
+}
+
+/// Convert RGB percentages to a color.
+fn rgb_from_percent(r: u16, g: u16, b: u16) -> Rgb {
+    let convert = |value: u16| ((u32::from(value.min(100)) * 255 + 50) / 100) as u8;
+    Rgb { r: convert(r), g: convert(g), b: convert(b) }
+}
+
+/// Convert a Sixel HLS color to RGB.
+///
+/// Sixel hues are rotated compared to the usual HSL model, with blue at 0 degrees.
+fn rgb_from_hls(hue: u16, lightness: u16, saturation: u16) -> Rgb {
+    let hue = f32::from((hue % 360 + 240) % 360);
+    let lightness = f32::from(lightness.min(100)) / 100.;
+    let saturation = f32::from(saturation.min(100)) / 100.;
+
+    let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+    let x = chroma * (1. - ((hue / 60.) % 2. - 1.).abs());
+    let (r, g, b) = match hue as u16 / 60 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+
+    let m = lightness - chroma / 2.;
+    let convert = |value: f32| ((value + m) * 255.).round().max(0.).min(255.) as u8;
+    Rgb { r: convert(r), g: convert(g), b: convert(b) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(data: &[u8], transparent_background: bool) -> Option<Image> {
+        let mut parser = Parser::new(transparent_background);
+        for &byte in data {
+            parser.put(byte);
+        }
+        parser.finish()
+    }
+
+    fn pixel(image: &Image, x: usize, y: usize) -> [u8; 4] {
+        let index = (y * image.width + x) * 4;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&image.pixels[index..index + 4]);
+        pixel
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(decode(b"", true), None);
+    }
+
+    #[test]
+    fn single_column() {
+        let image = decode(b"#1;2;100;0;0~", true).unwrap();
+        assert_eq!((image.width, image.height), (1, 6));
+        assert_eq!(pixel(&image, 0, 5), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn repeat_and_bands() {
+        let image = decode(b"#2;2;0;100;0!3@-#2A", true).unwrap();
+        assert_eq!((image.width, image.height), (3, 8));
+
+        assert_eq!(pixel(&image, 2, 0), [0, 255, 0, 255]);
+        assert_eq!(pixel(&image, 0, 1), [0; 4]);
+        assert_eq!(pixel(&image, 0, 7), [0, 255, 0, 255]);
+        assert_eq!(pixel(&image, 1, 7), [0; 4]);
+    }
+
+    #[test]
+    fn carriage_return_overdraws() {
+        let image = decode(b"#1;2;100;0;0~$#2;2;0;0;100@", true).unwrap();
+        assert_eq!(pixel(&image, 0, 0), [0, 0, 255, 255]);
+        assert_eq!(pixel(&image, 0, 1), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn raster_attributes() {
+        let image = decode(b"\"1;1;4;2#0;2;0;0;100@", false).unwrap();
+        assert_eq!((image.width, image.height), (4, 2));
+
+        // Unset pixels are filled with the first color register.
+        assert_eq!(pixel(&image, 3, 1), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn hls_colors() {
+        assert_eq!(rgb_from_hls(0, 50, 100), Rgb { r: 0, g: 0, b: 255 });
+        assert_eq!(rgb_from_hls(120, 50, 100), Rgb { r: 255, g: 0, b: 0 });
+        assert_eq!(rgb_from_hls(240, 50, 100), Rgb { r: 0, g: 255, b: 0 });
+    }
+
+    #[test]
+    fn oversized() {
+        let image = decode(b"!9999~", true).unwrap();
+        assert_eq!(image.width, MAX_GRAPHIC_DIMENSIONS);
+    }
+}
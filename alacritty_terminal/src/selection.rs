@@ -59,16 +59,18 @@ pub enum SelectionType {
     Simple,
     Block,
     Semantic,
+    Path,
     Lines,
 }
 
 /// Describes a region of a 2-dimensional area.
 ///
-/// Used to track a text selection. There are four supported modes, each with its own constructor:
-/// [`simple`], [`block`], [`semantic`], and [`lines`]. The [`simple`] mode precisely tracks which
-/// cells are selected without any expansion. [`block`] will select rectangular regions.
+/// Used to track a text selection. There are five supported modes, each with its own constructor:
+/// [`simple`], [`block`], [`semantic`], `path` and [`lines`]. The [`simple`] mode precisely tracks
+/// which cells are selected without any expansion. [`block`] will select rectangular regions.
 /// [`semantic`] mode expands the initial selection to the nearest semantic escape char in either
-/// direction. [`lines`] will always select entire lines.
+/// direction. `path` mode does the same using the path escape chars, so it selects entire file
+/// paths. [`lines`] will always select entire lines.
 ///
 /// Calls to [`update`] operate different based on the selection kind. The [`simple`] and [`block`]
 /// mode do nothing special, simply track points and sides. [`semantic`] will continue to expand
@@ -189,7 +191,7 @@ impl Selection {
                         && start.side == Side::Left
                         && end.side == Side::Right)
             },
-            SelectionType::Semantic | SelectionType::Lines => false,
+            SelectionType::Semantic | SelectionType::Path | SelectionType::Lines => false,
         }
     }
 
@@ -256,6 +258,7 @@ impl Selection {
             SelectionType::Simple => self.range_simple(start, end, num_cols),
             SelectionType::Block => self.range_block(start, end),
             SelectionType::Semantic => Self::range_semantic(term, start.point, end.point),
+            SelectionType::Path => Self::range_path(term, start.point, end.point),
             SelectionType::Lines => Self::range_lines(term, start.point, end.point),
         }
     }
@@ -315,6 +318,17 @@ impl Selection {
         Some(SelectionRange { start, end, is_block: false })
     }
 
+    fn range_path<T>(
+        term: &Term<T>,
+        start: Point<usize>,
+        end: Point<usize>,
+    ) -> Option<SelectionRange> {
+        let start = term.path_search_left(start);
+        let end = term.path_search_right(end);
+
+        Some(SelectionRange { start, end, is_block: false })
+    }
+
     fn range_lines<T>(
         term: &Term<T>,
         mut start: Point<usize>,
@@ -7,25 +7,49 @@ use crate::grid::{self, GridCell};
 use crate::index::Column;
 
 /// Maximum number of zerowidth characters which will be stored per cell.
+///
+/// Longer grapheme clusters continue in the terminal, see `Cell::cluster`.
 pub const MAX_ZEROWIDTH_CHARS: usize = 5;
 
+/// Joins the emoji before and after it into a single grapheme cluster.
+pub const ZERO_WIDTH_JOINER: char = '\u{200d}';
+
+/// Variation selector requesting the text presentation of the preceding character.
+pub const TEXT_PRESENTATION_SELECTOR: char = '\u{fe0e}';
+
+/// Variation selector requesting the emoji presentation of the preceding character.
+pub const EMOJI_PRESENTATION_SELECTOR: char = '\u{fe0f}';
+
+/// Whether `c` is a skin tone modifier, which changes the emoji before it.
+#[inline]
+pub fn is_emoji_modifier(c: char) -> bool {
+    ('\u{1f3fb}'..='\u{1f3ff}').contains(&c)
+}
+
 bitflags! {
     #[derive(Serialize, Deserialize)]
-    pub struct Flags: u16 {
-        const INVERSE                   = 0b0000_0000_0000_0001;
-        const BOLD                      = 0b0000_0000_0000_0010;
-        const ITALIC                    = 0b0000_0000_0000_0100;
-        const BOLD_ITALIC               = 0b0000_0000_0000_0110;
-        const UNDERLINE                 = 0b0000_0000_0000_1000;
-        const WRAPLINE                  = 0b0000_0000_0001_0000;
-        const WIDE_CHAR                 = 0b0000_0000_0010_0000;
-        const WIDE_CHAR_SPACER          = 0b0000_0000_0100_0000;
-        const DIM                       = 0b0000_0000_1000_0000;
-        const DIM_BOLD                  = 0b0000_0000_1000_0010;
-        const HIDDEN                    = 0b0000_0001_0000_0000;
-        const STRIKEOUT                 = 0b0000_0010_0000_0000;
-        const LEADING_WIDE_CHAR_SPACER  = 0b0000_0100_0000_0000;
-        const DOUBLE_UNDERLINE          = 0b0000_1000_0000_0000;
+    pub struct Flags: u32 {
+        const INVERSE                   = 0b0000_0000_0000_0000_0000_0000_0000_0001;
+        const BOLD                      = 0b0000_0000_0000_0000_0000_0000_0000_0010;
+        const ITALIC                    = 0b0000_0000_0000_0000_0000_0000_0000_0100;
+        const BOLD_ITALIC               = 0b0000_0000_0000_0000_0000_0000_0000_0110;
+        const UNDERLINE                 = 0b0000_0000_0000_0000_0000_0000_0000_1000;
+        const WRAPLINE                  = 0b0000_0000_0000_0000_0000_0000_0001_0000;
+        const WIDE_CHAR                 = 0b0000_0000_0000_0000_0000_0000_0010_0000;
+        const WIDE_CHAR_SPACER          = 0b0000_0000_0000_0000_0000_0000_0100_0000;
+        const DIM                       = 0b0000_0000_0000_0000_0000_0000_1000_0000;
+        const DIM_BOLD                  = 0b0000_0000_0000_0000_0000_0000_1000_0010;
+        const HIDDEN                    = 0b0000_0000_0000_0000_0000_0001_0000_0000;
+        const STRIKEOUT                 = 0b0000_0000_0000_0000_0000_0010_0000_0000;
+        const LEADING_WIDE_CHAR_SPACER  = 0b0000_0000_0000_0000_0000_0100_0000_0000;
+        const DOUBLE_UNDERLINE          = 0b0000_0000_0000_0000_0000_1000_0000_0000;
+        const UNDERCURL                 = 0b0000_0000_0000_0000_0001_0000_0000_0000;
+        const DOTTED_UNDERLINE          = 0b0000_0000_0000_0000_0010_0000_0000_0000;
+        const DASHED_UNDERLINE          = 0b0000_0000_0000_0000_0100_0000_0000_0000;
+        const ALL_UNDERLINES            = 0b0000_0000_0000_0000_0111_1000_0000_1000;
+        const OVERLINE                  = 0b0000_0000_0000_0000_1000_0000_0000_0000;
+        const BLINK                     = 0b0000_0000_0000_0001_0000_0000_0000_0000;
+        const ZEROWIDTH_TRUNCATED       = 0b0000_0000_0000_0010_0000_0000_0000_0000;
     }
 }
 
@@ -41,6 +65,14 @@ pub struct Cell {
     pub flags: Flags,
     #[serde(default = "default_extra")]
     pub extra: [char; MAX_ZEROWIDTH_CHARS],
+
+    /// Hyperlink this cell belongs to, `0` if there is none.
+    #[serde(default)]
+    pub hyperlink: u16,
+
+    /// Zero-width characters which did not fit into `extra`, `0` if there are none.
+    #[serde(default)]
+    pub cluster: u16,
 }
 
 impl Default for Cell {
@@ -56,11 +88,12 @@ impl GridCell for Cell {
             && self.extra[0] == ' '
             && self.bg == Color::Named(NamedColor::Background)
             && self.fg == Color::Named(NamedColor::Foreground)
+            && self.hyperlink == 0
             && !self.flags.intersects(
                 Flags::INVERSE
-                    | Flags::UNDERLINE
-                    | Flags::DOUBLE_UNDERLINE
+                    | Flags::ALL_UNDERLINES
                     | Flags::STRIKEOUT
+                    | Flags::OVERLINE
                     | Flags::WRAPLINE
                     | Flags::WIDE_CHAR_SPACER
                     | Flags::LEADING_WIDE_CHAR_SPACER,
@@ -125,7 +158,15 @@ impl Cell {
     }
 
     pub fn new(c: char, fg: Color, bg: Color) -> Cell {
-        Cell { extra: [' '; MAX_ZEROWIDTH_CHARS], c, bg, fg, flags: Flags::empty() }
+        Cell {
+            extra: [' '; MAX_ZEROWIDTH_CHARS],
+            c,
+            bg,
+            fg,
+            flags: Flags::empty(),
+            hyperlink: 0,
+            cluster: 0,
+        }
     }
 
     #[inline]
@@ -148,20 +189,31 @@ impl Cell {
         }
     }
 
+    /// Whether there is no room left for zero-width characters.
+    #[inline]
+    pub fn extra_full(&self) -> bool {
+        self.extra[MAX_ZEROWIDTH_CHARS - 1] != ' '
+    }
+
+    /// Last zero-width character stored in the cell itself.
+    #[inline]
+    pub fn last_extra(&self) -> Option<char> {
+        self.extra.iter().rev().find(|&&c| c != ' ').copied()
+    }
+
+    /// Add a zero-width character, marking the cell as truncated once there is no room left.
     #[inline]
     pub fn push_extra(&mut self, c: char) {
-        for elem in self.extra.iter_mut() {
-            if elem == &' ' {
-                *elem = c;
-                break;
-            }
+        match self.extra.iter_mut().find(|elem| **elem == ' ') {
+            Some(elem) => *elem = c,
+            None => self.flags.insert(Flags::ZEROWIDTH_TRUNCATED),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Cell, LineLength};
+    use super::{Cell, Flags, LineLength, MAX_ZEROWIDTH_CHARS};
 
     use crate::grid::Row;
     use crate::index::Column;
@@ -179,10 +231,23 @@ mod tests {
     fn line_length_works_with_wrapline() {
         let template = Cell::default();
         let mut row = Row::new(Column(10), template);
-        row[Column(9)].flags.insert(super::Flags::WRAPLINE);
+        row[Column(9)].flags.insert(Flags::WRAPLINE);
 
         assert_eq!(row.line_length(), Column(10));
     }
+
+    #[test]
+    fn push_extra_marks_truncation() {
+        let mut cell = Cell::default();
+        for _ in 0..MAX_ZEROWIDTH_CHARS {
+            cell.push_extra('\u{301}');
+        }
+        assert!(!cell.flags.contains(Flags::ZEROWIDTH_TRUNCATED));
+
+        cell.push_extra('\u{302}');
+        assert!(cell.flags.contains(Flags::ZEROWIDTH_TRUNCATED));
+        assert_eq!(cell.chars()[MAX_ZEROWIDTH_CHARS], '\u{301}');
+    }
 }
 
 #[cfg(all(test, feature = "bench"))]
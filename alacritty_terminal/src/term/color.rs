@@ -4,7 +4,7 @@ use std::str::FromStr;
 
 use log::trace;
 use serde::de::{Error as _, Visitor};
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_yaml::Value;
 
 use crate::ansi;
@@ -12,8 +12,8 @@ use crate::config::Colors;
 
 pub const COUNT: usize = 269;
 
-/// Factor for automatic computation of dim colors used by terminal.
-pub const DIM_FACTOR: f32 = 0.66;
+/// Default relative luminance of dim colors, compared to their regular variant.
+pub const DEFAULT_DIM_LUMINANCE: f32 = 0.4;
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Default, Serialize)]
 pub struct Rgb {
@@ -26,22 +26,21 @@ impl Rgb {
     /// Implementation of W3C's luminance algorithm:
     /// https://www.w3.org/TR/WCAG20/#relativeluminancedef
     fn luminance(self) -> f64 {
-        let channel_luminance = |channel| {
-            let channel = channel as f64 / 255.;
-            if channel <= 0.03928 {
-                channel / 12.92
-            } else {
-                f64::powf((channel + 0.055) / 1.055, 2.4)
-            }
-        };
-
-        let r_luminance = channel_luminance(self.r);
-        let g_luminance = channel_luminance(self.g);
-        let b_luminance = channel_luminance(self.b);
+        let r_luminance = srgb_to_linear(self.r);
+        let g_luminance = srgb_to_linear(self.g);
+        let b_luminance = srgb_to_linear(self.b);
 
         0.2126 * r_luminance + 0.7152 * g_luminance + 0.0722 * b_luminance
     }
 
+    /// Scale the relative luminance of the color by `factor`, keeping its hue and saturation.
+    pub fn dim(self, factor: f32) -> Rgb {
+        let factor = f64::from(factor.max(0.).min(1.));
+        let channel = |channel| (linear_to_srgb(srgb_to_linear(channel) * factor) * 255.).round();
+
+        Rgb { r: channel(self.r) as u8, g: channel(self.g) as u8, b: channel(self.b) as u8 }
+    }
+
     /// Implementation of W3C's contrast algorithm:
     /// https://www.w3.org/TR/WCAG20/#contrast-ratiodef
     pub fn contrast(self, other: Rgb) -> f64 {
@@ -58,7 +57,26 @@ impl Rgb {
     }
 }
 
-// A multiply function for Rgb, as the default dim is just *2/3.
+/// Linear light intensity of an sRGB channel, between 0 and 1.
+fn srgb_to_linear(channel: u8) -> f64 {
+    let channel = f64::from(channel) / 255.;
+    if channel <= 0.03928 {
+        channel / 12.92
+    } else {
+        f64::powf((channel + 0.055) / 1.055, 2.4)
+    }
+}
+
+/// Gamma encoded sRGB channel of a linear light intensity, between 0 and 1.
+fn linear_to_srgb(linear: f64) -> f64 {
+    if linear <= 0.03928 / 12.92 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1. / 2.4) - 0.055
+    }
+}
+
+// A multiply function for Rgb, used for blending colors.
 impl Mul<f32> for Rgb {
     type Output = Rgb;
 
@@ -187,6 +205,15 @@ impl CellRgb {
             Self::Rgb(rgb) => rgb,
         }
     }
+
+    /// Palette entry of the resolved color, if it is not a fixed RGB value.
+    pub fn index(self, foreground: Option<usize>, background: Option<usize>) -> Option<usize> {
+        match self {
+            Self::CellForeground => foreground,
+            Self::CellBackground => background,
+            Self::Rgb(_) => None,
+        }
+    }
 }
 
 impl Default for CellRgb {
@@ -243,19 +270,24 @@ pub struct List([Rgb; COUNT]);
 
 impl<'a> From<&'a Colors> for List {
     fn from(colors: &Colors) -> List {
+        List::new(colors, DEFAULT_DIM_LUMINANCE)
+    }
+}
+
+impl List {
+    /// Palette of the configured colors, deriving missing dim colors with `dim_luminance`.
+    pub fn new(colors: &Colors, dim_luminance: f32) -> List {
         // Type inference fails without this annotation.
         let mut list = List([Rgb::default(); COUNT]);
 
-        list.fill_named(colors);
+        list.fill_named(colors, dim_luminance);
         list.fill_cube(colors);
         list.fill_gray_ramp(colors);
 
         list
     }
-}
 
-impl List {
-    pub fn fill_named(&mut self, colors: &Colors) {
+    pub fn fill_named(&mut self, colors: &Colors, dim_luminance: f32) {
         // Normals.
         self[ansi::NamedColor::Black] = colors.normal().black;
         self[ansi::NamedColor::Red] = colors.normal().red;
@@ -283,8 +315,10 @@ impl List {
         self[ansi::NamedColor::Background] = colors.primary.background;
 
         // Dims.
-        self[ansi::NamedColor::DimForeground] =
-            colors.primary.dim_foreground.unwrap_or(colors.primary.foreground * DIM_FACTOR);
+        self[ansi::NamedColor::DimForeground] = colors
+            .primary
+            .dim_foreground
+            .unwrap_or_else(|| colors.primary.foreground.dim(dim_luminance));
         match colors.dim {
             Some(ref dim) => {
                 trace!("Using config-provided dim colors");
@@ -299,14 +333,14 @@ impl List {
             },
             None => {
                 trace!("Deriving dim colors from normal colors");
-                self[ansi::NamedColor::DimBlack] = colors.normal().black * DIM_FACTOR;
-                self[ansi::NamedColor::DimRed] = colors.normal().red * DIM_FACTOR;
-                self[ansi::NamedColor::DimGreen] = colors.normal().green * DIM_FACTOR;
-                self[ansi::NamedColor::DimYellow] = colors.normal().yellow * DIM_FACTOR;
-                self[ansi::NamedColor::DimBlue] = colors.normal().blue * DIM_FACTOR;
-                self[ansi::NamedColor::DimMagenta] = colors.normal().magenta * DIM_FACTOR;
-                self[ansi::NamedColor::DimCyan] = colors.normal().cyan * DIM_FACTOR;
-                self[ansi::NamedColor::DimWhite] = colors.normal().white * DIM_FACTOR;
+                self[ansi::NamedColor::DimBlack] = colors.normal().black.dim(dim_luminance);
+                self[ansi::NamedColor::DimRed] = colors.normal().red.dim(dim_luminance);
+                self[ansi::NamedColor::DimGreen] = colors.normal().green.dim(dim_luminance);
+                self[ansi::NamedColor::DimYellow] = colors.normal().yellow.dim(dim_luminance);
+                self[ansi::NamedColor::DimBlue] = colors.normal().blue.dim(dim_luminance);
+                self[ansi::NamedColor::DimMagenta] = colors.normal().magenta.dim(dim_luminance);
+                self[ansi::NamedColor::DimCyan] = colors.normal().cyan.dim(dim_luminance);
+                self[ansi::NamedColor::DimWhite] = colors.normal().white.dim(dim_luminance);
             },
         }
     }
@@ -362,6 +396,39 @@ impl List {
     }
 }
 
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        self.0[..] == other.0[..]
+    }
+}
+
+impl Eq for List {}
+
+impl Serialize for List {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.0.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for List {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let colors = Vec::<Rgb>::deserialize(deserializer)?;
+        if colors.len() != COUNT {
+            return Err(D::Error::invalid_length(colors.len(), &"269 colors"));
+        }
+
+        let mut list = List([Rgb::default(); COUNT]);
+        list.0.copy_from_slice(&colors);
+        Ok(list)
+    }
+}
+
 impl fmt::Debug for List {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str("List[..]")
@@ -439,4 +506,21 @@ mod tests {
         let rgb2 = Rgb { r: 0xfe, g: 0xdc, b: 0xba };
         assert!((rgb1.contrast(rgb2) - 9.786_558_997_257_74).abs() < EPSILON);
     }
+
+    #[test]
+    fn dim() {
+        let rgb = Rgb { r: 0xc5, g: 0x67, b: 0x12 };
+        assert_eq!(rgb.dim(1.), rgb);
+        assert_eq!(rgb.dim(0.), Rgb::default());
+
+        // Luminance is scaled, within the precision of 8 bit channels.
+        for &factor in &[0.2, 0.4, 0.66] {
+            let dimmed = rgb.dim(factor);
+            assert!((dimmed.luminance() / rgb.luminance() - f64::from(factor)).abs() < 0.01);
+        }
+
+        // Pure colors keep their hue.
+        let red = Rgb { r: 0xff, g: 0, b: 0 };
+        assert_eq!(red.dim(0.4), Rgb { r: 0xaa, g: 0, b: 0 });
+    }
 }
@@ -0,0 +1,207 @@
+//! Annotations attached to cells by external tools.
+//!
+//! Marks are not part of the terminal content, they only tint cells or add an icon next to a
+//! line. They scroll with the content and disappear when their line is cleared.
+
+use std::mem;
+use std::ops::Range;
+
+use crate::index::{Column, Line};
+use crate::term::color::Rgb;
+
+/// Maximum number of marks of each screen buffer, the oldest ones are dropped first.
+const MAX_MARKS: usize = 4096;
+
+/// Annotation of cells on a single line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mark {
+    /// Annotated columns, clipped to the width of the grid.
+    pub columns: Range<Column>,
+
+    /// Color blended over the cells.
+    pub tint: Option<Rgb>,
+
+    /// Symbol shown next to the line.
+    pub icon: Option<char>,
+}
+
+/// Mark visible in the viewport.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderableMark {
+    pub line: Line,
+    pub mark: Mark,
+}
+
+/// Marks of both screen buffers.
+#[derive(Debug, Default)]
+pub struct Marks {
+    /// Marks of the active screen buffer with their line relative to the top of the screen.
+    ///
+    /// Marks which have been scrolled into history have a negative line.
+    marks: Vec<(isize, Mark)>,
+
+    /// Marks of the inactive screen buffer.
+    inactive_marks: Vec<(isize, Mark)>,
+}
+
+impl Marks {
+    /// Add a mark, replacing any previous mark of the same cells.
+    pub fn insert(&mut self, line: isize, mark: Mark) {
+        self.marks.retain(|(l, m)| *l != line || m.columns != mark.columns);
+
+        if self.marks.len() >= MAX_MARKS {
+            self.marks.remove(0);
+        }
+
+        self.marks.push((line, mark));
+    }
+
+    /// Remove all marks of the active screen buffer.
+    pub fn clear(&mut self) {
+        self.marks.clear();
+    }
+
+    /// Move marks in `region` up by `lines`.
+    ///
+    /// Marks scrolled out of a region at the top of the screen move into the history, which
+    /// holds at most `history_size` lines. In every other region they are removed.
+    pub fn scroll_up(&mut self, region: &Range<Line>, lines: Line, history_size: usize) {
+        let start = region.start.0 as isize;
+        let end = region.end.0 as isize;
+        let first = if start == 0 { -(history_size as isize) } else { start };
+
+        let inside = |line| line < end && (start == 0 || line >= start);
+        self.move_lines(-(lines.0 as isize), inside, |line| line >= first);
+    }
+
+    /// Move marks in `region` down by `lines`, removing those scrolled out of it.
+    pub fn scroll_down(&mut self, region: &Range<Line>, lines: Line) {
+        let start = region.start.0 as isize;
+        let end = region.end.0 as isize;
+
+        self.move_lines(lines.0 as isize, |line| line >= start && line < end, |line| line < end);
+    }
+
+    /// Remove all marks on `lines`, relative to the top of the screen.
+    pub fn clear_lines(&mut self, lines: Range<isize>) {
+        self.marks.retain(|(line, _)| !lines.contains(line));
+    }
+
+    /// Swap the marks of the active and the inactive screen buffer.
+    pub fn swap_screen(&mut self) {
+        mem::swap(&mut self.marks, &mut self.inactive_marks);
+    }
+
+    /// Remove all marks of both screen buffers.
+    pub fn reset(&mut self) {
+        self.marks.clear();
+        self.inactive_marks.clear();
+    }
+
+    /// Marks visible in the viewport, clipped to `columns`.
+    pub fn renderable(
+        &self,
+        display_offset: usize,
+        screen_lines: Line,
+        columns: Column,
+    ) -> Vec<RenderableMark> {
+        self.marks
+            .iter()
+            .filter_map(|(line, mark)| {
+                let line = line + display_offset as isize;
+                if line < 0 || line >= screen_lines.0 as isize {
+                    return None;
+                }
+
+                let start = mark.columns.start.min(columns);
+                let end = mark.columns.end.min(columns);
+                let mark = Mark { columns: start..end, ..mark.clone() };
+                Some(RenderableMark { line: Line(line as usize), mark })
+            })
+            .collect()
+    }
+
+    /// Move marks on lines matching `inside` by `delta`, removing those which end up on a line
+    /// not matching `keep`.
+    fn move_lines<I, K>(&mut self, delta: isize, inside: I, keep: K)
+    where
+        I: Fn(isize) -> bool,
+        K: Fn(isize) -> bool,
+    {
+        let marks = mem::take(&mut self.marks);
+        self.marks = marks
+            .into_iter()
+            .filter_map(|(line, mark)| {
+                if !inside(line) {
+                    return Some((line, mark));
+                }
+
+                let line = line + delta;
+                if keep(line) {
+                    Some((line, mark))
+                } else {
+                    None
+                }
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mark(columns: Range<usize>) -> Mark {
+        Mark { columns: Column(columns.start)..Column(columns.end), tint: None, icon: Some('x') }
+    }
+
+    fn lines(marks: &Marks) -> Vec<isize> {
+        marks.marks.iter().map(|(line, _)| *line).collect()
+    }
+
+    #[test]
+    fn replace_same_cells() {
+        let mut marks = Marks::default();
+        marks.insert(1, mark(0..3));
+        marks.insert(1, mark(0..4));
+        marks.insert(1, Mark { tint: Some(Rgb::default()), ..mark(0..3) });
+
+        assert_eq!(marks.marks.len(), 2);
+        assert_eq!(marks.marks[1].1.tint, Some(Rgb::default()));
+    }
+
+    #[test]
+    fn scroll_into_history() {
+        let mut marks = Marks::default();
+        marks.insert(0, mark(0..1));
+        marks.insert(3, mark(0..1));
+
+        marks.scroll_up(&(Line(0)..Line(5)), Line(2), 1);
+        assert_eq!(lines(&marks), vec![1]);
+
+        let renderable = marks.renderable(1, Line(5), Column(10));
+        assert_eq!(renderable[0].line, Line(2));
+    }
+
+    #[test]
+    fn scroll_region() {
+        let mut marks = Marks::default();
+        marks.insert(0, mark(0..1));
+        marks.insert(3, mark(0..1));
+
+        marks.scroll_up(&(Line(2)..Line(5)), Line(1), 10);
+        assert_eq!(lines(&marks), vec![0, 2]);
+
+        marks.scroll_down(&(Line(2)..Line(5)), Line(3));
+        assert_eq!(lines(&marks), vec![0]);
+    }
+
+    #[test]
+    fn clipped_to_grid() {
+        let mut marks = Marks::default();
+        marks.insert(0, mark(2..usize::MAX));
+
+        let renderable = marks.renderable(0, Line(5), Column(10));
+        assert_eq!(renderable[0].mark.columns, Column(2)..Column(10));
+    }
+}
@@ -1,6 +1,8 @@
 //! Exports the `Term` type which is a high-level API for the Grid.
 
 use std::cmp::{max, min};
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::iter::Peekable;
 use std::ops::{Index, IndexMut, Range, RangeInclusive};
 use std::sync::Arc;
@@ -12,21 +14,27 @@ use serde::{Deserialize, Serialize};
 use unicode_width::UnicodeWidthChar;
 
 use crate::ansi::{
-    self, Attr, CharsetIndex, Color, CursorStyle, Handler, NamedColor, StandardCharset,
+    self, Attr, Capabilities, CharsetIndex, Color, CursorStyle, Handler, Hyperlink, NamedColor,
+    StandardCharset,
 };
-use crate::config::{BellAnimation, BellConfig, Config};
+use crate::config::{BellAnimation, BellConfig, Config, CursorBlinking, ScrollToBottom};
 use crate::event::{Event, EventListener};
+use crate::graphics::iterm::InlineImage;
+use crate::graphics::kitty::{self, KittyImages};
+use crate::graphics::{Geometry, GraphicData, GraphicId, Graphics, Image, RenderableGraphic};
 use crate::grid::{Dimensions, DisplayIter, Grid, IndexRegion, Indexed, Scroll};
 use crate::index::{self, Boundary, Column, Direction, IndexRange, Line, Point, Side};
 use crate::selection::{Selection, SelectionRange};
-use crate::term::cell::{Cell, Flags, LineLength};
-use crate::term::color::{CellRgb, Rgb, DIM_FACTOR};
+use crate::term::cell::{is_emoji_modifier, Cell, Flags, LineLength, ZERO_WIDTH_JOINER};
+use crate::term::color::{CellRgb, Rgb};
+use crate::term::marks::{Mark, Marks, RenderableMark};
 use crate::term::search::{RegexIter, RegexSearch};
 use crate::vi_mode::{ViModeCursor, ViMotion};
 
 pub mod cell;
 pub mod color;
-mod search;
+pub mod marks;
+pub mod search;
 
 /// Max size of the window title stack.
 const TITLE_STACK_MAX_DEPTH: usize = 4096;
@@ -37,6 +45,15 @@ const MIN_CURSOR_CONTRAST: f64 = 1.5;
 /// Maximum number of linewraps followed outside of the viewport during search highlighting.
 const MAX_SEARCH_LINES: usize = 100;
 
+/// Maximum number of zero-width characters stored outside of a single cell.
+const MAX_CLUSTER_CHARS: usize = 32;
+
+/// Maximum number of cells which can store zero-width characters outside of themselves.
+const MAX_CLUSTERS: usize = u16::MAX as usize;
+
+/// Maximum number of hyperlinks cells can refer to at the same time.
+const MAX_HYPERLINKS: usize = u16::MAX as usize;
+
 /// Default tab interval, corresponding to terminfo `it` value.
 const INITIAL_TABSTOPS: usize = 8;
 
@@ -58,13 +75,25 @@ struct RenderableCursor {
     rendered: bool,
 }
 
-/// A key for caching cursor glyphs.
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash, Deserialize)]
+/// Style of the cursor and whether it covers a wide character.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash, Serialize, Deserialize)]
 pub struct CursorKey {
+    #[serde(with = "CursorStyleDef")]
     pub style: CursorStyle,
     pub is_wide: bool,
 }
 
+/// Serialization of every cursor style, including the ones which can't be configured.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "CursorStyle")]
+enum CursorStyleDef {
+    Block,
+    Underline,
+    Beam,
+    HollowBlock,
+    Hidden,
+}
+
 type MatchIter<'a> = Box<dyn Iterator<Item = RangeInclusive<Point<usize>>> + 'a>;
 
 /// Regex search highlight tracking.
@@ -249,13 +278,51 @@ impl<'a, C> RenderableCellsIter<'a, C> {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RenderableCellContent {
     Chars([char; cell::MAX_ZEROWIDTH_CHARS + 1]),
     Cursor(CursorKey),
 }
 
-#[derive(Copy, Clone, Debug)]
+/// Highlighting of a cell, which the renderer applies on top of the cell's colors.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CellHighlight {
+    None,
+    Selection,
+    SearchMatch,
+}
+
+impl CellHighlight {
+    /// Colors replacing those of cells with this highlight.
+    pub fn colors<C>(self, config: &Config<C>) -> HighlightColors {
+        match self {
+            CellHighlight::None => HighlightColors::default(),
+            CellHighlight::Selection => HighlightColors {
+                foreground: config.colors.selection.text(),
+                background: config.colors.selection.background(),
+            },
+            CellHighlight::SearchMatch => HighlightColors {
+                foreground: config.colors.search.matches.foreground,
+                background: config.colors.search.matches.background,
+            },
+        }
+    }
+}
+
+/// Foreground and background of a cell highlight.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HighlightColors {
+    pub foreground: CellRgb,
+    pub background: CellRgb,
+}
+
+impl Default for HighlightColors {
+    fn default() -> Self {
+        Self { foreground: CellRgb::CellForeground, background: CellRgb::CellBackground }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct RenderableCell {
     /// A _Display_ line (not necessarily an _Active_ line).
     pub line: Line,
@@ -263,8 +330,17 @@ pub struct RenderableCell {
     pub inner: RenderableCellContent,
     pub fg: Rgb,
     pub bg: Rgb,
+    /// Palette entry the foreground was resolved from, if it is not a direct RGB color.
+    pub fg_index: Option<usize>,
+    /// Palette entry the background was resolved from, if it is not a direct RGB color.
+    pub bg_index: Option<usize>,
     pub bg_alpha: f32,
     pub flags: Flags,
+    /// Highlight which has not been applied to the colors yet.
+    pub highlight: CellHighlight,
+    /// Hyperlink of the cell, `0` if there is none.
+    #[serde(default)]
+    pub hyperlink: u16,
 }
 
 impl RenderableCell {
@@ -274,39 +350,25 @@ impl RenderableCell {
         // Lookup RGB values.
         let mut fg_rgb = Self::compute_fg_rgb(iter.config, iter.colors, cell.fg, cell.flags);
         let mut bg_rgb = Self::compute_bg_rgb(iter.colors, cell.bg);
+        let mut fg_index = Self::compute_fg_index(iter.config, cell.fg, cell.flags);
+        let mut bg_index = Self::compute_bg_index(cell.bg);
 
-        let mut bg_alpha = if cell.inverse() {
+        let bg_alpha = if cell.inverse() {
             mem::swap(&mut fg_rgb, &mut bg_rgb);
+            mem::swap(&mut fg_index, &mut bg_index);
             1.0
         } else {
             Self::compute_bg_alpha(cell.bg)
         };
 
-        if iter.is_selected(point) {
-            let config_bg = iter.config.colors.selection.background();
-            let selected_fg = iter.config.colors.selection.text().color(fg_rgb, bg_rgb);
-            bg_rgb = config_bg.color(fg_rgb, bg_rgb);
-            fg_rgb = selected_fg;
-
-            if fg_rgb == bg_rgb && !cell.flags.contains(Flags::HIDDEN) {
-                // Reveal inversed text when fg/bg is the same.
-                fg_rgb = iter.colors[NamedColor::Background];
-                bg_rgb = iter.colors[NamedColor::Foreground];
-                bg_alpha = 1.0;
-            } else if config_bg != CellRgb::CellBackground {
-                bg_alpha = 1.0;
-            }
+        // Highlights are applied by the renderer, so they can change without touching the colors.
+        let highlight = if iter.is_selected(point) {
+            CellHighlight::Selection
         } else if iter.search.advance(iter.grid.visible_to_buffer(point)) {
-            // Highlight the cell if it is part of a search match.
-            let config_bg = iter.config.colors.search.matches.background;
-            let matched_fg = iter.config.colors.search.matches.foreground.color(fg_rgb, bg_rgb);
-            bg_rgb = config_bg.color(fg_rgb, bg_rgb);
-            fg_rgb = matched_fg;
-
-            if config_bg != CellRgb::CellBackground {
-                bg_alpha = 1.0;
-            }
-        }
+            CellHighlight::SearchMatch
+        } else {
+            CellHighlight::None
+        };
 
         RenderableCell {
             line: cell.line,
@@ -314,54 +376,116 @@ impl RenderableCell {
             inner: RenderableCellContent::Chars(cell.chars()),
             fg: fg_rgb,
             bg: bg_rgb,
+            fg_index,
+            bg_index,
             bg_alpha,
             flags: cell.flags,
+            highlight,
+            hyperlink: cell.hyperlink,
+        }
+    }
+
+    /// Replace the colors of a highlighted cell with the colors of its highlight.
+    ///
+    /// The renderer does the same for cells which are not resolved on the CPU.
+    pub fn resolve_highlight(&mut self, highlight: HighlightColors, colors: &color::List) {
+        if self.highlight == CellHighlight::None {
+            return;
+        }
+
+        let fg = highlight.foreground.color(self.fg, self.bg);
+        let fg_index = highlight.foreground.index(self.fg_index, self.bg_index);
+        self.bg = highlight.background.color(self.fg, self.bg);
+        self.bg_index = highlight.background.index(self.fg_index, self.bg_index);
+        self.fg = fg;
+        self.fg_index = fg_index;
+
+        if self.highlight == CellHighlight::Selection
+            && self.fg == self.bg
+            && !self.flags.contains(Flags::HIDDEN)
+        {
+            // Reveal inversed text when fg/bg is the same.
+            self.fg = colors[NamedColor::Background];
+            self.bg = colors[NamedColor::Foreground];
+            self.fg_index = Some(NamedColor::Background as usize);
+            self.bg_index = Some(NamedColor::Foreground as usize);
+            self.bg_alpha = 1.0;
+        } else if highlight.background != CellRgb::CellBackground {
+            self.bg_alpha = 1.0;
         }
+
+        self.highlight = CellHighlight::None;
     }
 
     fn is_empty(&self) -> bool {
-        self.bg_alpha == 0.
-            && !self.flags.intersects(Flags::UNDERLINE | Flags::STRIKEOUT | Flags::DOUBLE_UNDERLINE)
+        self.highlight == CellHighlight::None
+            && self.bg_alpha == 0.
+            && !self.flags.intersects(Flags::ALL_UNDERLINES | Flags::STRIKEOUT | Flags::OVERLINE)
             && self.inner == RenderableCellContent::Chars([' '; cell::MAX_ZEROWIDTH_CHARS + 1])
     }
 
     fn compute_fg_rgb<C>(config: &Config<C>, colors: &color::List, fg: Color, flags: Flags) -> Rgb {
+        let rgb = match fg {
+            Color::Spec(rgb) => rgb,
+            Color::Named(ansi) => colors[Self::compute_named_fg_index(config, ansi, flags)],
+            Color::Indexed(idx) => colors[Self::compute_indexed_fg_index(config, idx, flags)],
+        };
+
+        if Self::computes_dim_fg(fg, flags) {
+            rgb.dim(config.dim_text_luminance())
+        } else {
+            rgb
+        }
+    }
+
+    /// Whether a dim foreground has no dim variant in the palette and is computed instead.
+    #[inline]
+    fn computes_dim_fg(fg: Color, flags: Flags) -> bool {
         match fg {
-            Color::Spec(rgb) => match flags & Flags::DIM {
-                Flags::DIM => rgb * DIM_FACTOR,
-                _ => rgb,
-            },
-            Color::Named(ansi) => {
-                match (config.draw_bold_text_with_bright_colors(), flags & Flags::DIM_BOLD) {
-                    // If no bright foreground is set, treat it like the BOLD flag doesn't exist.
-                    (_, Flags::DIM_BOLD)
-                        if ansi == NamedColor::Foreground
-                            && config.colors.primary.bright_foreground.is_none() =>
-                    {
-                        colors[NamedColor::DimForeground]
-                    },
-                    // Draw bold text in bright colors *and* contains bold flag.
-                    (true, Flags::BOLD) => colors[ansi.to_bright()],
-                    // Cell is marked as dim and not bold.
-                    (_, Flags::DIM) | (false, Flags::DIM_BOLD) => colors[ansi.to_dim()],
-                    // None of the above, keep original color..
-                    _ => colors[ansi],
-                }
-            },
-            Color::Indexed(idx) => {
-                let idx = match (
-                    config.draw_bold_text_with_bright_colors(),
-                    flags & Flags::DIM_BOLD,
-                    idx,
-                ) {
-                    (true, Flags::BOLD, 0..=7) => idx as usize + 8,
-                    (false, Flags::DIM, 8..=15) => idx as usize - 8,
-                    (false, Flags::DIM, 0..=7) => idx as usize + 260,
-                    _ => idx as usize,
-                };
+            Color::Spec(_) => flags.contains(Flags::DIM),
+            Color::Indexed(idx) => idx >= 16 && flags.contains(Flags::DIM),
+            Color::Named(_) => false,
+        }
+    }
+
+    /// Compute the palette entry used for the foreground, if there is one.
+    #[inline]
+    fn compute_fg_index<C>(config: &Config<C>, fg: Color, flags: Flags) -> Option<usize> {
+        if Self::computes_dim_fg(fg, flags) {
+            return None;
+        }
+
+        match fg {
+            Color::Spec(_) => None,
+            Color::Named(ansi) => Some(Self::compute_named_fg_index(config, ansi, flags)),
+            Color::Indexed(idx) => Some(Self::compute_indexed_fg_index(config, idx, flags)),
+        }
+    }
 
-                colors[idx]
+    fn compute_named_fg_index<C>(config: &Config<C>, ansi: NamedColor, flags: Flags) -> usize {
+        match (config.draw_bold_text_with_bright_colors(), flags & Flags::DIM_BOLD) {
+            // If no bright foreground is set, treat it like the BOLD flag doesn't exist.
+            (_, Flags::DIM_BOLD)
+                if ansi == NamedColor::Foreground
+                    && config.colors.primary.bright_foreground.is_none() =>
+            {
+                NamedColor::DimForeground as usize
             },
+            // Draw bold text in bright colors *and* contains bold flag.
+            (true, Flags::BOLD) => ansi.to_bright() as usize,
+            // Cell is marked as dim and not bold.
+            (_, Flags::DIM) | (false, Flags::DIM_BOLD) => ansi.to_dim() as usize,
+            // None of the above, keep original color..
+            _ => ansi as usize,
+        }
+    }
+
+    fn compute_indexed_fg_index<C>(config: &Config<C>, idx: u8, flags: Flags) -> usize {
+        match (config.draw_bold_text_with_bright_colors(), flags & Flags::DIM_BOLD, idx) {
+            (true, Flags::BOLD, 0..=7) => idx as usize + 8,
+            (false, Flags::DIM, 8..=15) => idx as usize - 8,
+            (false, Flags::DIM, 0..=7) => idx as usize + 260,
+            _ => idx as usize,
         }
     }
 
@@ -387,6 +511,16 @@ impl RenderableCell {
             Color::Indexed(idx) => colors[idx],
         }
     }
+
+    /// Compute the palette entry used for the background, if there is one.
+    #[inline]
+    fn compute_bg_index(bg: Color) -> Option<usize> {
+        match bg {
+            Color::Spec(_) => None,
+            Color::Named(ansi) => Some(ansi as usize),
+            Color::Indexed(idx) => Some(idx as usize),
+        }
+    }
 }
 
 impl<'a, C> Iterator for RenderableCellsIter<'a, C> {
@@ -408,14 +542,22 @@ impl<'a, C> Iterator for RenderableCellsIter<'a, C> {
                     let mut cell = RenderableCell::new(self, cell);
 
                     if self.cursor.key.style == CursorStyle::Block {
-                        cell.fg = match self.cursor.cursor_color {
+                        // The cursor text color depends on the highlighted colors.
+                        cell.resolve_highlight(cell.highlight.colors(self.config), self.colors);
+
+                        let (fg, fg_index) = match self.cursor.cursor_color {
                             // Apply cursor color, or invert the cursor if it has a fixed background
                             // close to the cell's background.
                             CellRgb::Rgb(col) if col.contrast(cell.bg) < MIN_CURSOR_CONTRAST => {
-                                cell.bg
+                                (cell.bg, cell.bg_index)
                             },
-                            _ => self.cursor.text_color.color(cell.fg, cell.bg),
+                            _ => (
+                                self.cursor.text_color.color(cell.fg, cell.bg),
+                                self.cursor.text_color.index(cell.fg_index, cell.bg_index),
+                            ),
                         };
+                        cell.fg = fg;
+                        cell.fg_index = fg_index;
                     }
 
                     return Some(cell);
@@ -432,6 +574,7 @@ impl<'a, C> Iterator for RenderableCellsIter<'a, C> {
 
                     let mut cell = RenderableCell::new(self, cell);
                     cell.inner = RenderableCellContent::Cursor(self.cursor.key);
+                    cell.resolve_highlight(cell.highlight.colors(self.config), self.colors);
 
                     // Apply cursor color, or invert the cursor if it has a fixed background close
                     // to the cell's background.
@@ -440,6 +583,8 @@ impl<'a, C> Iterator for RenderableCellsIter<'a, C> {
                         CellRgb::Rgb(color) if color.contrast(cell.bg) < MIN_CURSOR_CONTRAST
                     ) {
                         cell.fg = self.cursor.cursor_color.color(cell.fg, cell.bg);
+                        cell.fg_index =
+                            self.cursor.cursor_color.index(cell.fg_index, cell.bg_index);
                     }
 
                     return Some(cell);
@@ -481,6 +626,8 @@ pub mod mode {
             const ALTERNATE_SCROLL    = 0b0000_1000_0000_0000_0000;
             const VI                  = 0b0001_0000_0000_0000_0000;
             const URGENCY_HINTS       = 0b0010_0000_0000_0000_0000;
+            const SGR_PIXEL_MOUSE     = 0b0100_0000_0000_0000_0000;
+            const GRAPHEME_CLUSTERING = 0b1000_0000_0000_0000_0000;
             const ANY                 = std::u32::MAX;
         }
     }
@@ -638,6 +785,12 @@ pub struct SizeInfo {
     /// Horizontal window padding.
     padding_y: f32,
 
+    /// Width of the gutter between the left padding and the grid.
+    gutter: f32,
+
+    /// Width of the minimap right of the grid.
+    minimap: f32,
+
     /// Number of lines in the viewport.
     screen_lines: Line,
 
@@ -677,24 +830,63 @@ impl SizeInfo {
             cell_height,
             padding_x: padding_x.floor(),
             padding_y: padding_y.floor(),
+            gutter: 0.,
+            minimap: 0.,
             screen_lines,
             visible_lines: screen_lines,
             cols,
         }
     }
 
+    /// Scale all pixel dimensions, keeping the number of lines and columns.
+    pub fn scaled(&self, scale: f32) -> SizeInfo {
+        SizeInfo {
+            width: self.width * scale,
+            height: self.height * scale,
+            cell_width: self.cell_width * scale,
+            cell_height: self.cell_height * scale,
+            padding_x: (self.padding_x * scale).floor(),
+            padding_y: (self.padding_y * scale).floor(),
+            gutter: self.gutter * scale,
+            minimap: self.minimap * scale,
+            ..*self
+        }
+    }
+
     #[inline]
     pub fn reserve_lines(&mut self, count: usize) {
         self.screen_lines = Line(max(self.screen_lines.saturating_sub(count), MIN_SCREEN_LINES));
     }
 
+    /// Reserve columns on the left of the grid for the gutter.
+    #[inline]
+    pub fn reserve_gutter(&mut self, columns: usize) {
+        let columns = min(columns, self.cols.saturating_sub(MIN_COLS));
+        self.gutter = columns as f32 * self.cell_width;
+        self.cols -= columns;
+    }
+
+    /// Reserve columns on the right of the grid for the minimap.
+    #[inline]
+    pub fn reserve_minimap(&mut self, columns: usize) {
+        let columns = min(columns, self.cols.saturating_sub(MIN_COLS));
+        self.minimap = columns as f32 * self.cell_width;
+        self.cols -= columns;
+    }
+
+    /// Size with the grid extended over the gutter, for drawing into the gutter.
+    #[inline]
+    pub fn including_gutter(&self) -> SizeInfo {
+        SizeInfo { gutter: 0., ..*self }
+    }
+
     /// Check if coordinates are inside the terminal grid.
     ///
-    /// The padding, message bar or search are not counted as part of the grid.
+    /// The padding, gutter, message bar or search are not counted as part of the grid.
     #[inline]
     pub fn contains_point(&self, x: usize, y: usize) -> bool {
-        x <= (self.padding_x + self.cols.0 as f32 * self.cell_width) as usize
-            && x > self.padding_x as usize
+        x <= (self.padding_left() + self.cols.0 as f32 * self.cell_width) as usize
+            && x > self.padding_left() as usize
             && y <= (self.padding_y + self.screen_lines.0 as f32 * self.cell_height) as usize
             && y > self.padding_y as usize
     }
@@ -704,8 +896,9 @@ impl SizeInfo {
     /// If the coordinates are outside of the terminal grid, like positions inside the padding, the
     /// coordinates will be clamped to the closest grid coordinates.
     pub fn pixels_to_coords(&self, x: usize, y: usize) -> Point {
-        let col = Column(x.saturating_sub(self.padding_x as usize) / (self.cell_width as usize));
-        let line = Line(y.saturating_sub(self.padding_y as usize) / (self.cell_height as usize));
+        let (x, y) = self.pixels_to_grid_pixels(x, y);
+        let col = Column((x as f32 / self.cell_width) as usize);
+        let line = Line((y as f32 / self.cell_height) as usize);
 
         Point {
             line: min(line, Line(self.screen_lines.saturating_sub(1))),
@@ -713,6 +906,19 @@ impl SizeInfo {
         }
     }
 
+    /// Convert window space pixels to pixels relative to the top-left corner of the terminal grid.
+    ///
+    /// Coordinates outside of the terminal grid are clamped to its closest edge.
+    pub fn pixels_to_grid_pixels(&self, x: usize, y: usize) -> (usize, usize) {
+        let grid_width = self.cols.0 as f32 * self.cell_width;
+        let grid_height = self.screen_lines.0 as f32 * self.cell_height;
+
+        let x = (x as f32 - self.padding_left()).min(grid_width - 1.).max(0.);
+        let y = (y as f32 - self.padding_y).min(grid_height - 1.).max(0.);
+
+        (x as usize, y as usize)
+    }
+
     #[inline]
     pub fn width(&self) -> f32 {
         self.width
@@ -743,6 +949,24 @@ impl SizeInfo {
         self.padding_y
     }
 
+    /// Width of the gutter left of the grid.
+    #[inline]
+    pub fn gutter(&self) -> f32 {
+        self.gutter
+    }
+
+    /// Width of the minimap right of the grid.
+    #[inline]
+    pub fn minimap(&self) -> f32 {
+        self.minimap
+    }
+
+    /// Distance between the left window edge and the grid, including the gutter.
+    #[inline]
+    pub fn padding_left(&self) -> f32 {
+        self.padding_x + self.gutter
+    }
+
     #[inline]
     pub fn screen_lines(&self) -> Line {
         self.screen_lines
@@ -807,6 +1031,7 @@ pub struct Term<T> {
     scroll_region: Range<Line>,
 
     semantic_escape_chars: String,
+    path_escape_chars: String,
 
     /// Colors used for rendering.
     colors: color::List,
@@ -823,9 +1048,18 @@ pub struct Term<T> {
     /// Default style for resetting the cursor.
     default_cursor_style: CursorStyle,
 
+    /// Cursor color from config, reported while no color has been set with `OSC 12`.
+    default_cursor_color: CellRgb,
+
     /// Style of the vi mode cursor.
     vi_mode_cursor_style: Option<CursorStyle>,
 
+    /// Blinking state requested by the application.
+    cursor_blinking: Option<bool>,
+
+    /// Configured cursor blinking behavior.
+    default_cursor_blinking: CursorBlinking,
+
     /// Proxy for sending events to the event loop.
     event_proxy: T,
 
@@ -836,12 +1070,65 @@ pub struct Term<T> {
     /// term is set.
     title_stack: Vec<Option<String>>,
 
+    /// Targets of the OSC 8 hyperlinks, cells refer to them by their index plus one.
+    ///
+    /// Entries which no cell refers to anymore are freed once the table is full.
+    hyperlinks: Vec<Option<Hyperlink>>,
+
+    /// Index of every hyperlink with an ID in `hyperlinks`.
+    hyperlink_indices: HashMap<Hyperlink, usize>,
+
+    /// Freed entries of `hyperlinks`.
+    free_hyperlinks: Vec<usize>,
+
+    /// Zero-width characters which did not fit into their cell, cells refer to them by their
+    /// index plus one.
+    ///
+    /// Entries which no cell refers to anymore are freed once the table is full.
+    clusters: Vec<Vec<char>>,
+
+    /// Freed entries of `clusters`.
+    free_clusters: Vec<usize>,
+
     /// Current forward and backward buffer search regexes.
     regex_search: Option<RegexSearch>,
 
     /// Information about cell dimensions.
     cell_width: usize,
     cell_height: usize,
+
+    /// Images placed on the grid.
+    graphics: Graphics,
+
+    /// Images transmitted with the kitty graphics protocol.
+    kitty_images: KittyImages,
+
+    /// Annotations of cells set by external tools.
+    marks: Marks,
+
+    /// Lines the entire screen scrolled up since the last call to `take_scroll_delta`.
+    scrolled_lines: isize,
+
+    /// Display offset at the last call to `take_scroll_delta`.
+    last_display_offset: usize,
+
+    /// Jump to the bottom when output is printed while scrolled into the history.
+    scroll_on_output: bool,
+
+    /// Output was printed since the viewport was last at the bottom.
+    unseen_output: bool,
+
+    /// Start of the synchronized update in progress.
+    sync_start: Option<Instant>,
+
+    /// Total number of bytes received from the PTY.
+    received_bytes: u64,
+
+    /// Background opacity requested by the application.
+    background_opacity: Option<f32>,
+
+    /// Escape sequence features enabled by the user.
+    capabilities: Capabilities,
 }
 
 impl<T> Term<T> {
@@ -853,6 +1140,10 @@ impl<T> Term<T> {
         self.grid.scroll_display(scroll);
         self.event_proxy.send_event(Event::MouseCursorDirty);
         self.dirty = true;
+
+        if self.grid.display_offset() == 0 {
+            self.unseen_output = false;
+        }
     }
 
     pub fn new<C>(config: &Config<C>, size: SizeInfo, event_proxy: T) -> Term<T> {
@@ -867,7 +1158,7 @@ impl<T> Term<T> {
 
         let scroll_region = Line(0)..grid.screen_lines();
 
-        let colors = color::List::from(&config.colors);
+        let colors = color::List::new(&config.colors, config.dim_text_luminance());
 
         Term {
             dirty: false,
@@ -883,17 +1174,37 @@ impl<T> Term<T> {
             color_modified: [false; color::COUNT],
             original_colors: colors,
             semantic_escape_chars: config.selection.semantic_escape_chars().to_owned(),
+            path_escape_chars: config.selection.path_escape_chars().to_owned(),
             cursor_style: None,
             default_cursor_style: config.cursor.style,
+            default_cursor_color: config.colors.cursor.cursor(),
             vi_mode_cursor_style: config.cursor.vi_mode_style,
+            cursor_blinking: None,
+            default_cursor_blinking: config.cursor.blinking,
             event_proxy,
             is_focused: true,
             title: None,
             title_stack: Vec::new(),
+            hyperlinks: Vec::new(),
+            hyperlink_indices: HashMap::new(),
+            free_hyperlinks: Vec::new(),
+            clusters: Vec::new(),
+            free_clusters: Vec::new(),
             selection: None,
             regex_search: None,
             cell_width: size.cell_width as usize,
             cell_height: size.cell_height as usize,
+            graphics: Default::default(),
+            kitty_images: Default::default(),
+            marks: Default::default(),
+            scrolled_lines: 0,
+            last_display_offset: 0,
+            scroll_on_output: config.scrolling.scroll_to_bottom == ScrollToBottom::Output,
+            unseen_output: false,
+            sync_start: None,
+            received_bytes: 0,
+            background_opacity: None,
+            capabilities: config.escape_sequences.capabilities(),
         }
     }
 
@@ -902,7 +1213,8 @@ impl<T> Term<T> {
         T: EventListener,
     {
         self.semantic_escape_chars = config.selection.semantic_escape_chars().to_owned();
-        self.original_colors.fill_named(&config.colors);
+        self.path_escape_chars = config.selection.path_escape_chars().to_owned();
+        self.original_colors.fill_named(&config.colors, config.dim_text_luminance());
         self.original_colors.fill_cube(&config.colors);
         self.original_colors.fill_gray_ramp(&config.colors);
         for i in 0..color::COUNT {
@@ -915,7 +1227,11 @@ impl<T> Term<T> {
             self.mode.remove(TermMode::ALTERNATE_SCROLL);
         }
         self.default_cursor_style = config.cursor.style;
+        self.default_cursor_color = config.colors.cursor.cursor();
         self.vi_mode_cursor_style = config.cursor.vi_mode_style;
+        self.default_cursor_blinking = config.cursor.blinking;
+        self.scroll_on_output = config.scrolling.scroll_to_bottom == ScrollToBottom::Output;
+        self.capabilities = config.escape_sequences.capabilities();
 
         let title_event = match &self.title {
             Some(title) => Event::Title(title.clone()),
@@ -1011,6 +1327,7 @@ impl<T> Term<T> {
                 for c in (&cell.chars()[1..]).iter().take_while(|c| **c != ' ') {
                     text.push(*c);
                 }
+                text.extend(self.cluster(cell.cluster));
             }
         }
 
@@ -1062,6 +1379,229 @@ impl<T> Term<T> {
         RenderableCellsIter::new(&self, config, selection)
     }
 
+    /// Display a kitty image at the cursor position.
+    ///
+    /// Unless the client asked to keep the cursor in place, it is moved to the last line of the
+    /// image, right after its last column.
+    fn kitty_place(&mut self, id: u32, command: &kitty::Command) -> Result<(), kitty::Error>
+    where
+        T: EventListener,
+    {
+        let cursor = self.grid.cursor.point;
+        let cell_size = (self.cell_width, self.cell_height);
+        let screen_size = (self.cols().0, self.screen_lines().0);
+        let (columns, lines) = self.kitty_images.place(
+            &mut self.graphics,
+            id,
+            command,
+            cursor,
+            cell_size,
+            screen_size,
+        )?;
+
+        if !command.keep_cursor {
+            self.move_past_graphic(cursor, columns, lines);
+        }
+
+        Ok(())
+    }
+
+    /// Move the cursor from `origin` to the last line of an image, right after its last column.
+    ///
+    /// The cursor moves down by at most the height of the screen.
+    fn move_past_graphic(&mut self, origin: Point, columns: usize, lines: usize)
+    where
+        T: EventListener,
+    {
+        for _ in 1..min(lines, self.screen_lines().0) {
+            self.linefeed();
+        }
+
+        let col = origin.col.0 + columns;
+        let last_col = self.cols().0 - 1;
+        self.grid.cursor.point.col = Column(min(col, last_col));
+        self.grid.cursor.input_needs_wrap = col > last_col;
+    }
+
+    /// Images visible in the viewport.
+    pub fn renderable_graphics(&self) -> Vec<RenderableGraphic> {
+        self.graphics.renderable(self.grid.display_offset(), self.screen_lines())
+    }
+
+    /// Marks visible in the viewport.
+    pub fn renderable_marks(&self) -> Vec<RenderableMark> {
+        self.marks.renderable(self.grid.display_offset(), self.screen_lines(), self.cols())
+    }
+
+    /// Take all images which have to be uploaded and all images which can be deleted.
+    pub fn take_graphic_updates(&mut self) -> (Vec<GraphicData>, Vec<GraphicId>) {
+        self.graphics.take_updates()
+    }
+
+    /// Whether output was printed below the viewport while it shows the scrollback history.
+    pub fn scroll_locked(&self) -> bool {
+        self.unseen_output && self.grid.display_offset() != 0
+    }
+
+    /// Time until which rendering is held back for a synchronized update in progress.
+    pub fn sync_deadline(&self) -> Option<Instant> {
+        self.sync_start.map(|start| start + ansi::SYNC_UPDATE_TIMEOUT)
+    }
+
+    /// Whether the terminal cursor should blink.
+    ///
+    /// The vi mode cursor never blinks.
+    pub fn cursor_blinking(&self) -> bool {
+        !self.mode.contains(TermMode::VI)
+            && self.default_cursor_blinking.blinking(self.cursor_blinking)
+    }
+
+    /// Number of lines the content of the viewport moved up since the last call.
+    ///
+    /// This only accounts for scrolling the entire screen, other changes are not reflected.
+    pub fn take_scroll_delta(&mut self) -> isize {
+        let display_offset = self.grid.display_offset();
+        let display_delta = display_offset as isize - self.last_display_offset as isize;
+        let delta = self.scrolled_lines - display_delta;
+
+        self.scrolled_lines = 0;
+        self.last_display_offset = display_offset;
+
+        delta
+    }
+
+    /// Target of the hyperlink stored in cells as `id`.
+    #[inline]
+    pub fn hyperlink(&self, id: u16) -> Option<&Hyperlink> {
+        self.hyperlinks.get(usize::from(id).checked_sub(1)?)?.as_ref()
+    }
+
+    /// Store a new hyperlink, returning its index.
+    ///
+    /// Returns `None` if every entry is still referred to by a cell.
+    fn insert_hyperlink(&mut self, hyperlink: Hyperlink) -> Option<usize> {
+        if self.free_hyperlinks.is_empty() && self.hyperlinks.len() >= MAX_HYPERLINKS {
+            self.sweep_hyperlinks();
+        }
+
+        let index = match self.free_hyperlinks.pop() {
+            Some(index) => index,
+            None if self.hyperlinks.len() < MAX_HYPERLINKS => {
+                self.hyperlinks.push(None);
+                self.hyperlinks.len() - 1
+            },
+            None => return None,
+        };
+
+        if hyperlink.id.is_some() {
+            self.hyperlink_indices.insert(hyperlink.clone(), index);
+        }
+        self.hyperlinks[index] = Some(hyperlink);
+
+        Some(index)
+    }
+
+    /// Free the hyperlinks which are no longer referred to by any cell or cursor.
+    fn sweep_hyperlinks(&mut self) {
+        let mut used = vec![false; self.hyperlinks.len()];
+        let mut mark = |id: u16| {
+            if let Some(used) = usize::from(id).checked_sub(1).and_then(|index| used.get_mut(index))
+            {
+                *used = true;
+            }
+        };
+
+        for grid in [&self.grid, &self.inactive_grid].iter() {
+            mark(grid.cursor.template.hyperlink);
+            mark(grid.saved_cursor.template.hyperlink);
+            for line in 0..grid.total_lines() {
+                grid[line][..].iter().for_each(|cell| mark(cell.hyperlink));
+            }
+        }
+
+        // Free in reverse, so the lowest IDs are reused first.
+        for (index, used) in used.into_iter().enumerate().rev() {
+            if used {
+                continue;
+            }
+
+            if let Some(hyperlink) = self.hyperlinks[index].take() {
+                self.hyperlink_indices.remove(&hyperlink);
+                self.free_hyperlinks.push(index);
+            }
+        }
+    }
+
+    /// Reserve an empty entry for the zero-width characters of a cell, returning its ID.
+    fn insert_cluster(&mut self) -> Option<u16> {
+        if self.free_clusters.is_empty() && self.clusters.len() >= MAX_CLUSTERS {
+            self.sweep_clusters();
+        }
+
+        let index = match self.free_clusters.pop() {
+            Some(index) => index,
+            None if self.clusters.len() < MAX_CLUSTERS => {
+                self.clusters.push(Vec::new());
+                self.clusters.len() - 1
+            },
+            None => return None,
+        };
+
+        Some(index as u16 + 1)
+    }
+
+    /// Free the clusters which are no longer referred to by any cell.
+    fn sweep_clusters(&mut self) {
+        let mut used = vec![false; self.clusters.len()];
+        let mut mark = |id: u16| {
+            if let Some(used) = usize::from(id).checked_sub(1).and_then(|index| used.get_mut(index))
+            {
+                *used = true;
+            }
+        };
+
+        for grid in [&self.grid, &self.inactive_grid].iter() {
+            for line in 0..grid.total_lines() {
+                grid[line][..].iter().for_each(|cell| mark(cell.cluster));
+            }
+        }
+
+        // Free in reverse, so the lowest IDs are reused first.
+        for (index, used) in used.into_iter().enumerate().rev() {
+            if !used {
+                self.clusters[index] = Vec::new();
+                self.free_clusters.push(index);
+            }
+        }
+    }
+
+    /// Zero-width characters stored outside of the cell as `id`.
+    #[inline]
+    pub fn cluster(&self, id: u16) -> &[char] {
+        usize::from(id)
+            .checked_sub(1)
+            .and_then(|index| self.clusters.get(index))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Background opacity requested by the application, overriding the configured opacity.
+    #[inline]
+    pub fn background_opacity(&self) -> Option<f32> {
+        self.background_opacity
+    }
+
+    /// Total number of bytes received from the PTY.
+    #[inline]
+    pub fn received_bytes(&self) -> u64 {
+        self.received_bytes
+    }
+
+    /// Account for bytes received from the PTY.
+    #[inline]
+    pub fn add_received_bytes(&mut self, count: usize) {
+        self.received_bytes += count as u64;
+    }
+
     /// Resize terminal to new dimensions.
     pub fn resize(&mut self, size: SizeInfo) {
         self.cell_width = size.cell_width as usize;
@@ -1131,6 +1671,8 @@ impl<T> Term<T> {
         }
 
         mem::swap(&mut self.grid, &mut self.inactive_grid);
+        self.graphics.swap_screen();
+        self.marks.swap_screen();
         self.mode ^= TermMode::ALT_SCREEN;
         self.selection = None;
     }
@@ -1160,6 +1702,13 @@ impl<T> Term<T> {
         // Scroll between origin and bottom
         let template = Cell { bg: self.grid.cursor.template.bg, ..Cell::default() };
         self.grid.scroll_down(&region, lines, template);
+
+        self.graphics.scroll_down(&region, lines);
+        self.marks.scroll_down(&region, lines);
+
+        if region == (Line(0)..num_lines) {
+            self.scrolled_lines -= lines.0 as isize;
+        }
     }
 
     /// Scroll screen up
@@ -1184,6 +1733,13 @@ impl<T> Term<T> {
         // Scroll from origin to bottom less number of lines.
         let template = Cell { bg: self.grid.cursor.template.bg, ..Cell::default() };
         self.grid.scroll_up(&region, lines, template);
+
+        self.graphics.scroll_up(&region, lines, self.history_size());
+        self.marks.scroll_up(&region, lines, self.history_size());
+
+        if region == (Line(0)..num_lines) {
+            self.scrolled_lines += lines.0 as isize;
+        }
     }
 
     fn deccolm(&mut self)
@@ -1204,6 +1760,12 @@ impl<T> Term<T> {
         self.colors[NamedColor::Background]
     }
 
+    /// Current color palette, including runtime modifications.
+    #[inline]
+    pub fn colors(&self) -> &color::List {
+        &self.colors
+    }
+
     #[inline]
     pub fn exit(&mut self)
     where
@@ -1363,6 +1925,72 @@ impl<T> Term<T> {
         self.grid.cursor.input_needs_wrap = false;
     }
 
+    /// Cell of the last character written before the cursor.
+    fn previous_cell_point(&self) -> Point {
+        let mut point = self.grid.cursor.point;
+
+        // The cursor stays on the last column until the next character wraps the line.
+        if !self.grid.cursor.input_needs_wrap {
+            point.col = Column(point.col.0.saturating_sub(1));
+        }
+
+        if self.grid[point.line][point.col].flags.contains(Flags::WIDE_CHAR_SPACER) {
+            point.col = Column(point.col.0.saturating_sub(1));
+        }
+
+        point
+    }
+
+    /// Add a zero-width character to the grapheme cluster of the cell at `point`.
+    fn push_zerowidth(&mut self, point: Point, c: char) {
+        let cell = &mut self.grid[point.line][point.col];
+        if !cell.extra_full() {
+            cell.push_extra(c);
+            return;
+        }
+
+        // Only the first emoji of a sequence is drawn, so nothing visible is dropped from them.
+        if !cell.extra.contains(&ZERO_WIDTH_JOINER) {
+            cell.flags.insert(Flags::ZEROWIDTH_TRUNCATED);
+        }
+
+        if cell.cluster == 0 {
+            match self.insert_cluster() {
+                Some(id) => self.grid[point.line][point.col].cluster = id,
+                None => {
+                    self.grid[point.line][point.col].flags.insert(Flags::ZEROWIDTH_TRUNCATED);
+                    return;
+                },
+            }
+        }
+
+        let cell = &mut self.grid[point.line][point.col];
+        let cluster = &mut self.clusters[usize::from(cell.cluster) - 1];
+        if cluster.len() < MAX_CLUSTER_CHARS {
+            cluster.push(c);
+        } else {
+            cell.flags.insert(Flags::ZEROWIDTH_TRUNCATED);
+        }
+    }
+
+    /// Whether `c` continues the grapheme cluster of the cell at `point`.
+    fn joins_previous(&self, point: Point, c: char) -> bool {
+        let cell = &self.grid[point.line][point.col];
+        let last = match self.cluster(cell.cluster).last() {
+            Some(&last) => Some(last),
+            None => cell.last_extra(),
+        };
+
+        if last == Some(ZERO_WIDTH_JOINER) {
+            return true;
+        }
+
+        // Skin tone modifiers attach to the emoji before them.
+        is_emoji_modifier(c)
+            && cell.flags.contains(Flags::WIDE_CHAR)
+            && last.map_or(true, |last| last.width() == Some(2))
+    }
+
     /// Write `c` to the cell at the cursor position.
     #[inline]
     fn write_at_cursor(&mut self, c: char) -> &mut Cell
@@ -1378,12 +2006,59 @@ impl<T> Term<T> {
         cursor_cell
     }
 
-    /// Get rendering information about the active cursor.
-    fn renderable_cursor<C>(&self, config: &Config<C>) -> RenderableCursor {
-        let vi_mode = self.mode.contains(TermMode::VI);
+    /// Terminal cursor covered by the vi mode cursor.
+    ///
+    /// While vi mode is active the terminal cursor is drawn as a hollow block in addition to the vi
+    /// mode cursor, so the position of further input stays visible.
+    pub fn renderable_terminal_cursor<C>(&self, config: &Config<C>) -> Option<RenderableCell> {
+        if !self.mode.contains(TermMode::VI | TermMode::SHOW_CURSOR) {
+            return None;
+        }
 
-        // Cursor position.
-        let mut point = if vi_mode {
+        let mut point = self.grid.cursor.point;
+        point.line += self.grid.display_offset();
+        if point.line >= self.screen_lines() || point == self.vi_mode_cursor.point {
+            return None;
+        }
+
+        // Expand across wide cell when inside wide char or spacer.
+        let buffer_point = self.visible_to_buffer(point);
+        let cell = self.grid[buffer_point.line][buffer_point.col];
+        let is_wide = if cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
+            point.col -= 1;
+            true
+        } else {
+            cell.flags.contains(Flags::WIDE_CHAR)
+        };
+
+        let cursor_color = if self.color_modified[NamedColor::Cursor as usize] {
+            CellRgb::Rgb(self.colors[NamedColor::Cursor])
+        } else {
+            config.colors.cursor.cursor()
+        };
+
+        let buffer_point = self.visible_to_buffer(point);
+        let cell = Indexed {
+            inner: self.grid[buffer_point.line][buffer_point.col],
+            column: point.col,
+            line: point.line,
+        };
+        let mut iter = RenderableCellsIter::new(self, config, None);
+        let mut cell = RenderableCell::new(&mut iter, cell);
+        cell.inner =
+            RenderableCellContent::Cursor(CursorKey { style: CursorStyle::HollowBlock, is_wide });
+        cell.fg = cursor_color.color(cell.fg, cell.bg);
+        cell.fg_index = cursor_color.index(cell.fg_index, cell.bg_index);
+
+        Some(cell)
+    }
+
+    /// Get rendering information about the active cursor.
+    fn renderable_cursor<C>(&self, config: &Config<C>) -> RenderableCursor {
+        let vi_mode = self.mode.contains(TermMode::VI);
+
+        // Cursor position.
+        let mut point = if vi_mode {
             self.vi_mode_cursor.point
         } else {
             let mut point = self.grid.cursor.point;
@@ -1456,6 +2131,11 @@ impl<T> Dimensions for Term<T> {
 }
 
 impl<T: EventListener> Handler for Term<T> {
+    #[inline]
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
     /// A character to be displayed.
     #[inline]
     fn input(&mut self, c: char) {
@@ -1465,17 +2145,30 @@ impl<T: EventListener> Handler for Term<T> {
             None => return,
         };
 
+        if self.grid.display_offset() != 0 {
+            if self.scroll_on_output {
+                self.scroll_display(Scroll::Bottom);
+            } else {
+                self.unseen_output = true;
+            }
+        }
+
         // Handle zero-width characters.
         if width == 0 {
-            let mut col = self.grid.cursor.point.col.0.saturating_sub(1);
-            let line = self.grid.cursor.point.line;
-            if self.grid[line][Column(col)].flags.contains(Flags::WIDE_CHAR_SPACER) {
-                col = col.saturating_sub(1);
-            }
-            self.grid[line][Column(col)].push_extra(c);
+            let point = self.previous_cell_point();
+            self.push_zerowidth(point, c);
             return;
         }
 
+        // Continue emoji sequences in the cell of their first emoji.
+        if self.mode.contains(TermMode::GRAPHEME_CLUSTERING) {
+            let point = self.previous_cell_point();
+            if self.joins_previous(point, c) {
+                self.push_zerowidth(point, c);
+                return;
+            }
+        }
+
         // Move cursor to next line.
         if self.grid.cursor.input_needs_wrap {
             self.wrapline();
@@ -1621,7 +2314,13 @@ impl<T: EventListener> Handler for Term<T> {
         match intermediate {
             None => {
                 trace!("Reporting primary device attributes");
-                let _ = writer.write_all(b"\x1b[?6c");
+                // Sixel support is only advertised while images are enabled.
+                let response: &[u8] = if self.capabilities.contains(Capabilities::IMAGES) {
+                    b"\x1b[?62;4c"
+                } else {
+                    b"\x1b[?62c"
+                };
+                let _ = writer.write_all(response);
             },
             Some('>') => {
                 trace!("Reporting secondary device attributes");
@@ -1648,6 +2347,41 @@ impl<T: EventListener> Handler for Term<T> {
         };
     }
 
+    #[inline]
+    fn report_private_mode<W: io::Write>(&mut self, writer: &mut W, mode: i64) {
+        trace!("Reporting private mode: {}", mode);
+        let flag = |flag| Some(self.mode.contains(flag));
+        let set = match ansi::Mode::from_primitive(Some(&b'?'), mode) {
+            Some(ansi::Mode::SyncUpdate) => Some(self.sync_start.is_some()),
+            Some(ansi::Mode::CursorKeys) => flag(TermMode::APP_CURSOR),
+            Some(ansi::Mode::Origin) => flag(TermMode::ORIGIN),
+            Some(ansi::Mode::LineWrap) => flag(TermMode::LINE_WRAP),
+            Some(ansi::Mode::ShowCursor) => flag(TermMode::SHOW_CURSOR),
+            Some(ansi::Mode::ReportMouseClicks) => flag(TermMode::MOUSE_REPORT_CLICK),
+            Some(ansi::Mode::ReportCellMouseMotion) => flag(TermMode::MOUSE_DRAG),
+            Some(ansi::Mode::ReportAllMouseMotion) => flag(TermMode::MOUSE_MOTION),
+            Some(ansi::Mode::ReportFocusInOut) => flag(TermMode::FOCUS_IN_OUT),
+            Some(ansi::Mode::Utf8Mouse) => flag(TermMode::UTF8_MOUSE),
+            Some(ansi::Mode::SgrMouse) => flag(TermMode::SGR_MOUSE),
+            Some(ansi::Mode::SgrPixelMouse) => flag(TermMode::SGR_PIXEL_MOUSE),
+            Some(ansi::Mode::AlternateScroll) => flag(TermMode::ALTERNATE_SCROLL),
+            Some(ansi::Mode::UrgencyHints) => flag(TermMode::URGENCY_HINTS),
+            Some(ansi::Mode::SwapScreenAndSetRestoreCursor) => flag(TermMode::ALT_SCREEN),
+            Some(ansi::Mode::BracketedPaste) => flag(TermMode::BRACKETED_PASTE),
+            Some(ansi::Mode::GraphemeClustering) => flag(TermMode::GRAPHEME_CLUSTERING),
+            _ => None,
+        };
+
+        // The mode is either set (1), reset (2) or not recognized (0).
+        let state = match set {
+            Some(true) => 1,
+            Some(false) => 2,
+            None => 0,
+        };
+
+        let _ = write!(writer, "\x1b[?{};{}$y", mode, state);
+    }
+
     #[inline]
     fn move_down_and_cr(&mut self, lines: Line) {
         trace!("Moving down and cr: {}", lines);
@@ -1930,20 +2664,29 @@ impl<T: EventListener> Handler for Term<T> {
         self.color_modified[index] = true;
     }
 
-    /// Write a foreground/background color escape sequence with the current color.
+    /// Write a color escape sequence with the current color, after the OSC parameters `prefix`.
     #[inline]
     fn dynamic_color_sequence<W: io::Write>(
         &mut self,
         writer: &mut W,
-        code: u8,
+        prefix: &str,
         index: usize,
         terminator: &str,
     ) {
-        trace!("Writing escape sequence for dynamic color code {}: color[{}]", code, index);
-        let color = self.colors[index];
+        trace!("Writing escape sequence for color OSC {}: color[{}]", prefix, index);
+
+        // Cursor colors taken from the cell are reported for the default cell colors.
+        let color = if index == NamedColor::Cursor as usize && !self.color_modified[index] {
+            let foreground = self.colors[NamedColor::Foreground];
+            let background = self.colors[NamedColor::Background];
+            self.default_cursor_color.color(foreground, background)
+        } else {
+            self.colors[index]
+        };
+
         let response = format!(
             "\x1b]{};rgb:{1:02x}{1:02x}/{2:02x}{2:02x}/{3:02x}{3:02x}{4}",
-            code, color.r, color.g, color.b, terminator
+            prefix, color.r, color.g, color.b, terminator
         );
         let _ = writer.write_all(response.as_bytes());
     }
@@ -2020,6 +2763,9 @@ impl<T: EventListener> Handler for Term<T> {
                     .selection
                     .take()
                     .filter(|s| !s.intersects_range(cursor_buffer_line..num_lines));
+
+                self.graphics.clear_lines(0..cursor.line.0 as isize + 1);
+                self.marks.clear_lines(0..cursor.line.0 as isize + 1);
             },
             ansi::ClearMode::Below => {
                 let cursor = self.grid.cursor.point;
@@ -2033,6 +2779,9 @@ impl<T: EventListener> Handler for Term<T> {
 
                 self.selection =
                     self.selection.take().filter(|s| !s.intersects_range(..=cursor_buffer_line));
+
+                self.graphics.clear_lines(cursor.line.0 as isize..num_lines as isize);
+                self.marks.clear_lines(cursor.line.0 as isize..num_lines as isize);
             },
             ansi::ClearMode::All => {
                 if self.mode.contains(TermMode::ALT_SCREEN) {
@@ -2043,11 +2792,17 @@ impl<T: EventListener> Handler for Term<T> {
                 }
 
                 self.selection = self.selection.take().filter(|s| !s.intersects_range(..num_lines));
+
+                self.graphics.clear_lines(0..num_lines as isize);
+                self.marks.clear_lines(0..num_lines as isize);
             },
             ansi::ClearMode::Saved if self.history_size() > 0 => {
                 self.grid.clear_history();
 
                 self.selection = self.selection.take().filter(|s| !s.intersects_range(num_lines..));
+
+                self.graphics.clear_lines(isize::MIN..0);
+                self.marks.clear_lines(isize::MIN..0);
             },
             // We have no history to clear.
             ansi::ClearMode::Saved => (),
@@ -2078,14 +2833,25 @@ impl<T: EventListener> Handler for Term<T> {
         self.colors = self.original_colors;
         self.color_modified = [false; color::COUNT];
         self.cursor_style = None;
+        self.cursor_blinking = None;
+        self.background_opacity = None;
+        self.sync_start = None;
         self.grid.reset(Cell::default());
         self.inactive_grid.reset(Cell::default());
         self.scroll_region = Line(0)..self.screen_lines();
         self.tabs = TabStops::new(self.cols());
         self.title_stack = Vec::new();
         self.title = None;
+        self.hyperlinks.clear();
+        self.hyperlink_indices.clear();
+        self.free_hyperlinks.clear();
+        self.clusters.clear();
+        self.free_clusters.clear();
         self.selection = None;
         self.regex_search = None;
+        self.kitty_images.clear();
+        self.graphics.reset();
+        self.marks.reset();
     }
 
     #[inline]
@@ -2121,23 +2887,34 @@ impl<T: EventListener> Handler for Term<T> {
             Attr::Italic => cursor.template.flags.insert(Flags::ITALIC),
             Attr::CancelItalic => cursor.template.flags.remove(Flags::ITALIC),
             Attr::Underline => {
-                cursor.template.flags.remove(Flags::DOUBLE_UNDERLINE);
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
                 cursor.template.flags.insert(Flags::UNDERLINE);
             },
             Attr::DoubleUnderline => {
-                cursor.template.flags.remove(Flags::UNDERLINE);
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
                 cursor.template.flags.insert(Flags::DOUBLE_UNDERLINE);
             },
-            Attr::CancelUnderline => {
-                cursor.template.flags.remove(Flags::UNDERLINE | Flags::DOUBLE_UNDERLINE);
+            Attr::Undercurl => {
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
+                cursor.template.flags.insert(Flags::UNDERCURL);
+            },
+            Attr::DottedUnderline => {
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
+                cursor.template.flags.insert(Flags::DOTTED_UNDERLINE);
             },
+            Attr::DashedUnderline => {
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
+                cursor.template.flags.insert(Flags::DASHED_UNDERLINE);
+            },
+            Attr::CancelUnderline => cursor.template.flags.remove(Flags::ALL_UNDERLINES),
             Attr::Hidden => cursor.template.flags.insert(Flags::HIDDEN),
             Attr::CancelHidden => cursor.template.flags.remove(Flags::HIDDEN),
             Attr::Strike => cursor.template.flags.insert(Flags::STRIKEOUT),
             Attr::CancelStrike => cursor.template.flags.remove(Flags::STRIKEOUT),
-            _ => {
-                debug!("Term got unhandled attr: {:?}", attr);
-            },
+            Attr::Overline => cursor.template.flags.insert(Flags::OVERLINE),
+            Attr::CancelOverline => cursor.template.flags.remove(Flags::OVERLINE),
+            Attr::BlinkSlow | Attr::BlinkFast => cursor.template.flags.insert(Flags::BLINK),
+            Attr::CancelBlink => cursor.template.flags.remove(Flags::BLINK),
         }
     }
 
@@ -2173,22 +2950,30 @@ impl<T: EventListener> Handler for Term<T> {
             ansi::Mode::BracketedPaste => self.mode.insert(TermMode::BRACKETED_PASTE),
             // Mouse encodings are mutually exclusive.
             ansi::Mode::SgrMouse => {
-                self.mode.remove(TermMode::UTF8_MOUSE);
+                self.mode.remove(TermMode::UTF8_MOUSE | TermMode::SGR_PIXEL_MOUSE);
                 self.mode.insert(TermMode::SGR_MOUSE);
             },
             ansi::Mode::Utf8Mouse => {
-                self.mode.remove(TermMode::SGR_MOUSE);
+                self.mode.remove(TermMode::SGR_MOUSE | TermMode::SGR_PIXEL_MOUSE);
                 self.mode.insert(TermMode::UTF8_MOUSE);
             },
+            ansi::Mode::SgrPixelMouse => {
+                self.mode.remove(TermMode::SGR_MOUSE | TermMode::UTF8_MOUSE);
+                self.mode.insert(TermMode::SGR_PIXEL_MOUSE);
+            },
             ansi::Mode::AlternateScroll => self.mode.insert(TermMode::ALTERNATE_SCROLL),
             ansi::Mode::LineWrap => self.mode.insert(TermMode::LINE_WRAP),
             ansi::Mode::LineFeedNewLine => self.mode.insert(TermMode::LINE_FEED_NEW_LINE),
             ansi::Mode::Origin => self.mode.insert(TermMode::ORIGIN),
             ansi::Mode::DECCOLM => self.deccolm(),
             ansi::Mode::Insert => self.mode.insert(TermMode::INSERT),
-            ansi::Mode::BlinkingCursor => {
-                trace!("... unimplemented mode");
+            ansi::Mode::BlinkingCursor => self.cursor_blinking = Some(true),
+            ansi::Mode::SyncUpdate => {
+                if self.sync_start.is_none() {
+                    self.sync_start = Some(Instant::now());
+                }
             },
+            ansi::Mode::GraphemeClustering => self.mode.insert(TermMode::GRAPHEME_CLUSTERING),
         }
     }
 
@@ -2220,15 +3005,16 @@ impl<T: EventListener> Handler for Term<T> {
             ansi::Mode::BracketedPaste => self.mode.remove(TermMode::BRACKETED_PASTE),
             ansi::Mode::SgrMouse => self.mode.remove(TermMode::SGR_MOUSE),
             ansi::Mode::Utf8Mouse => self.mode.remove(TermMode::UTF8_MOUSE),
+            ansi::Mode::SgrPixelMouse => self.mode.remove(TermMode::SGR_PIXEL_MOUSE),
             ansi::Mode::AlternateScroll => self.mode.remove(TermMode::ALTERNATE_SCROLL),
             ansi::Mode::LineWrap => self.mode.remove(TermMode::LINE_WRAP),
             ansi::Mode::LineFeedNewLine => self.mode.remove(TermMode::LINE_FEED_NEW_LINE),
             ansi::Mode::Origin => self.mode.remove(TermMode::ORIGIN),
             ansi::Mode::DECCOLM => self.deccolm(),
             ansi::Mode::Insert => self.mode.remove(TermMode::INSERT),
-            ansi::Mode::BlinkingCursor => {
-                trace!("... unimplemented mode");
-            },
+            ansi::Mode::BlinkingCursor => self.cursor_blinking = Some(false),
+            ansi::Mode::SyncUpdate => self.sync_start = None,
+            ansi::Mode::GraphemeClustering => self.mode.remove(TermMode::GRAPHEME_CLUSTERING),
         }
     }
 
@@ -2286,6 +3072,40 @@ impl<T: EventListener> Handler for Term<T> {
         self.cursor_style = style;
     }
 
+    #[inline]
+    fn set_cursor_blinking(&mut self, blinking: Option<bool>) {
+        trace!("Setting cursor blinking {:?}", blinking);
+        self.cursor_blinking = blinking;
+    }
+
+    #[inline]
+    fn set_background_opacity(&mut self, opacity: Option<f32>) {
+        trace!("Setting background opacity to {:?}", opacity);
+        self.background_opacity = opacity;
+    }
+
+    #[inline]
+    fn set_hyperlink(&mut self, hyperlink: Option<Hyperlink>) {
+        trace!("Setting hyperlink to {:?}", hyperlink);
+
+        let hyperlink = match hyperlink {
+            Some(hyperlink) => hyperlink,
+            None => {
+                self.grid.cursor.template.hyperlink = 0;
+                return;
+            },
+        };
+
+        // Links without an ID are never joined, since they are not meant to be.
+        let index = match self.hyperlink_indices.get(&hyperlink) {
+            Some(&index) => Some(index),
+            None => self.insert_hyperlink(hyperlink),
+        };
+
+        // Once all IDs are in use, further links are displayed as plain text.
+        self.grid.cursor.template.hyperlink = index.map_or(0, |index| index as u16 + 1);
+    }
+
     #[inline]
     fn set_title(&mut self, title: Option<String>) {
         trace!("Setting title to '{:?}'", title);
@@ -2336,6 +3156,116 @@ impl<T: EventListener> Handler for Term<T> {
     fn text_area_size_chars<W: io::Write>(&mut self, writer: &mut W) {
         let _ = write!(writer, "\x1b[8;{};{}t", self.screen_lines(), self.cols());
     }
+
+    #[inline]
+    fn cell_size_pixels<W: io::Write>(&mut self, writer: &mut W) {
+        let _ = write!(writer, "\x1b[6;{};{}t", self.cell_height, self.cell_width);
+    }
+
+    #[inline]
+    fn insert_graphic(&mut self, image: Image) {
+        trace!("Inserting {}x{} image", image.width, image.height);
+
+        let cursor = self.grid.cursor.point;
+        let cell_height = self.cell_height.max(1);
+        let lines = (image.height + cell_height - 1) / cell_height;
+        self.graphics.insert(image, cursor.line, cursor.col, (self.cell_width, self.cell_height));
+
+        // Move the cursor below the image, scrolling it up if necessary.
+        for _ in 0..lines {
+            self.linefeed();
+        }
+        self.grid.cursor.point.col = cursor.col;
+        self.grid.cursor.input_needs_wrap = false;
+    }
+
+    #[inline]
+    fn insert_inline_image(&mut self, inline_image: InlineImage) {
+        if !inline_image.inline {
+            debug!("Ignoring iTerm2 file download");
+            return;
+        }
+
+        let image = match inline_image.decode() {
+            Ok(image) => image,
+            Err(err) => {
+                debug!("Unable to decode iTerm2 inline image: {}", err);
+                return;
+            },
+        };
+
+        trace!("Inserting {}x{} inline image", image.width, image.height);
+
+        let cursor = self.grid.cursor.point;
+        let cell_size = (self.cell_width, self.cell_height);
+        let max_columns = self.cols().0 - cursor.col.0;
+        let (columns, lines) =
+            inline_image.cells(&image, cell_size, self.screen_lines().0, max_columns);
+
+        self.graphics.insert_scaled(image, cursor.line, cursor.col, Geometry::new(columns, lines));
+        self.move_past_graphic(cursor, columns.ceil() as usize, lines.ceil() as usize);
+    }
+
+    #[inline]
+    fn set_mark(&mut self, lines_above: usize, mark: Mark) {
+        trace!("Marking columns {:?} {} lines above the cursor", mark.columns, lines_above);
+
+        let line = self.grid.cursor.point.line.0 as isize - lines_above as isize;
+        if line >= -(self.history_size() as isize) {
+            self.marks.insert(line, mark);
+        }
+    }
+
+    #[inline]
+    fn clear_marks(&mut self) {
+        trace!("Clearing marks");
+        self.marks.clear();
+    }
+
+    #[inline]
+    fn kitty_graphics<W: io::Write>(&mut self, writer: &mut W, command: kitty::Command) {
+        let mut command = match self.kitty_images.receive(command) {
+            Some(command) => command,
+            None => return,
+        };
+
+        trace!("Kitty graphics {:?} for image {}", command.action, command.image_id);
+
+        let result = match command.action {
+            kitty::Action::Transmit | kitty::Action::TransmitAndDisplay => {
+                match self.kitty_images.transmit(&mut self.graphics, &command) {
+                    Ok(id) if command.action == kitty::Action::TransmitAndDisplay => {
+                        self.kitty_place(id, &command)
+                    },
+                    Ok(_) => Ok(()),
+                    Err(err) => Err(err),
+                }
+            },
+            kitty::Action::Put => match self.kitty_images.find(&command) {
+                Some(id) => self.kitty_place(id, &command),
+                None => Err(kitty::Error::NotFound),
+            },
+            kitty::Action::Delete => {
+                let cursor = self.grid.cursor.point;
+                self.kitty_images.delete(&mut self.graphics, &command, cursor);
+                Ok(())
+            },
+            kitty::Action::Query => kitty::decode(&command).map(|_| ()),
+        };
+
+        if let Err(err) = &result {
+            debug!("Kitty graphics command failed: {}", err);
+        }
+
+        // Images transmitted with only a number are answered with the id they were assigned.
+        if command.image_id == 0 && command.image_number != 0 {
+            command.image_id = self.kitty_images.find(&command).unwrap_or(0);
+        }
+
+        if let Some(response) = kitty::response(&command, &result) {
+            let _ = writer.write_all(response.as_bytes());
+        }
+    }
 }
 
 /// Terminal version for escape sequence reports.
@@ -2497,6 +3427,336 @@ mod tests {
         fn send_event(&self, _event: Event) {}
     }
 
+    #[test]
+    fn window_size_reports() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+
+        let mut report = Vec::new();
+        term.text_area_size_pixels(&mut report);
+        term.cell_size_pixels(&mut report);
+        term.text_area_size_chars(&mut report);
+
+        assert_eq!(report, b"\x1b[4;51;21t\x1b[6;3;3t\x1b[8;17;7t");
+    }
+
+    #[test]
+    fn primary_device_attributes() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+
+        let mut report = Vec::new();
+        term.identify_terminal(&mut report, None);
+        term.capabilities.remove(Capabilities::IMAGES);
+        term.identify_terminal(&mut report, None);
+
+        assert_eq!(report, b"\x1b[?62;4c\x1b[?62c");
+    }
+
+    #[test]
+    fn dynamic_color_reports() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+
+        let mut parser = ansi::Processor::new();
+        let mut report = Vec::new();
+        for byte in b"\x1b]11;#ffffff\x07\x1b]11;?\x07\x1b]12;?\x1b\\" {
+            parser.advance(&mut term, *byte, &mut report);
+        }
+
+        assert_eq!(term.background_color(), Rgb { r: 0xff, g: 0xff, b: 0xff });
+        assert_eq!(report, &b"\x1b]11;rgb:ffff/ffff/ffff\x07\x1b]12;rgb:c5c5/c8c8/c6c6\x1b\\"[..]);
+
+        term.reset_color(NamedColor::Background as usize);
+        assert_eq!(term.background_color(), term.original_colors[NamedColor::Background]);
+    }
+
+    #[test]
+    fn indexed_color_changes() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        let original = term.colors;
+
+        let mut parser = ansi::Processor::new();
+        let mut report = Vec::new();
+        for byte in b"\x1b]4;1;#ff0000;200;rgb:00/80/ff\x07\x1b]4;200;?\x07" {
+            parser.advance(&mut term, *byte, &mut report);
+        }
+
+        assert_eq!(term.colors[1usize], Rgb { r: 0xff, g: 0, b: 0 });
+        assert_eq!(term.colors[200usize], Rgb { r: 0, g: 0x80, b: 0xff });
+        assert_eq!(report, b"\x1b]4;200;rgb:0000/8080/ffff\x07");
+
+        // Config reloads keep modified colors.
+        term.update_config(&MockConfig::default());
+        assert_eq!(term.colors[1usize], Rgb { r: 0xff, g: 0, b: 0 });
+
+        for byte in b"\x1b]104;1\x07" {
+            parser.advance(&mut term, *byte, &mut report);
+        }
+        assert_eq!(term.colors[1usize], original[1usize]);
+        assert_eq!(term.colors[200usize], Rgb { r: 0, g: 0x80, b: 0xff });
+
+        for byte in b"\x1b]104\x07" {
+            parser.advance(&mut term, *byte, &mut report);
+        }
+        assert_eq!(term.colors[200usize], original[200usize]);
+    }
+
+    #[test]
+    fn graphic_scrolls_with_content() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        term.goto(Line(15), Column(2));
+
+        let image = Image { width: 6, height: 7, pixels: vec![0; 6 * 7 * 4] };
+        term.insert_graphic(image);
+
+        // The cursor is moved below the image, scrolling it up by two lines.
+        assert_eq!(term.grid.cursor.point, Point::new(Line(16), Column(2)));
+
+        let graphics = term.renderable_graphics();
+        assert_eq!(graphics.len(), 1);
+        assert_eq!(graphics[0].line, 13);
+        assert_eq!(graphics[0].column, Column(2));
+        assert_eq!(graphics[0].geometry.lines, 7. / 3.);
+
+        term.clear_screen(ansi::ClearMode::All);
+        assert!(term.renderable_graphics().is_empty());
+    }
+
+    #[test]
+    fn kitty_image_moves_cursor() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        term.goto(Line(2), Column(1));
+
+        let payload = base64::encode(&[255; 6 * 4 * 3]);
+        let control = format!("a=T,f=24,s=6,v=4,i=5;{}", payload);
+        let command = kitty::Command::parse(control.as_bytes()).unwrap();
+
+        let mut response = Vec::new();
+        term.kitty_graphics(&mut response, command);
+        assert_eq!(response, b"\x1b_Gi=5;OK\x1b\\");
+
+        // The cursor is placed on the last line of the image, after its last column.
+        assert_eq!(term.grid.cursor.point, Point::new(Line(3), Column(3)));
+
+        let graphics = term.renderable_graphics();
+        assert_eq!(graphics.len(), 1);
+        assert_eq!((graphics[0].line, graphics[0].column), (2, Column(1)));
+
+        let command = kitty::Command::parse(b"a=d,d=I,i=5").unwrap();
+        term.kitty_graphics(&mut io::sink(), command);
+        assert!(term.renderable_graphics().is_empty());
+    }
+
+    #[test]
+    fn scroll_delta() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+
+        term.scroll_up(Line(3));
+        term.scroll_down(Line(1));
+        assert_eq!(term.take_scroll_delta(), 2);
+        assert_eq!(term.take_scroll_delta(), 0);
+
+        // Scrolling the viewport into the history moves the content down.
+        term.scroll_display(Scroll::Delta(1));
+        assert_eq!(term.take_scroll_delta(), -1);
+
+        // The viewport stays in place while scrolled into the history.
+        term.scroll_up(Line(1));
+        assert_eq!(term.take_scroll_delta(), 0);
+
+        // Scrolling a region does not move the entire screen.
+        term.set_scrolling_region(2, Some(4));
+        term.scroll_up(Line(1));
+        assert_eq!(term.take_scroll_delta(), 0);
+    }
+
+    #[test]
+    fn scroll_lock() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut config = MockConfig::default();
+        let mut term = Term::new(&config, size, Mock);
+        for _ in 0..20 {
+            term.linefeed();
+        }
+
+        term.scroll_display(Scroll::Delta(1));
+        assert!(!term.scroll_locked());
+
+        term.input('a');
+        assert!(term.scroll_locked());
+        assert_eq!(term.grid().display_offset(), 1);
+
+        term.scroll_display(Scroll::Bottom);
+        assert!(!term.scroll_locked());
+
+        // Output moves the viewport to the bottom.
+        config.scrolling.scroll_to_bottom = ScrollToBottom::Output;
+        term.update_config(&config);
+        term.scroll_display(Scroll::Delta(1));
+        term.input('a');
+        assert!(!term.scroll_locked());
+        assert_eq!(term.grid().display_offset(), 0);
+    }
+
+    #[test]
+    fn sync_update() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        assert_eq!(term.sync_deadline(), None);
+
+        term.set_mode(ansi::Mode::SyncUpdate);
+        let deadline = term.sync_deadline().unwrap();
+        assert!(deadline > Instant::now());
+
+        // Repeated starts do not extend the timeout.
+        term.set_mode(ansi::Mode::SyncUpdate);
+        assert_eq!(term.sync_deadline(), Some(deadline));
+
+        term.unset_mode(ansi::Mode::SyncUpdate);
+        assert_eq!(term.sync_deadline(), None);
+
+        let mut reply = Vec::new();
+        term.report_private_mode(&mut reply, 2026);
+        term.set_mode(ansi::Mode::SyncUpdate);
+        term.report_private_mode(&mut reply, 2026);
+        term.report_private_mode(&mut reply, 25);
+        term.report_private_mode(&mut reply, 9999);
+        assert_eq!(reply, b"\x1b[?2026;2$y\x1b[?2026;1$y\x1b[?25;1$y\x1b[?9999;0$y".to_vec());
+    }
+
+    #[test]
+    fn sync_update_timeout() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+
+        let mut parser = ansi::Processor::new();
+        let mut reply = Vec::new();
+        for byte in b"\x1b[?2026habc" {
+            parser.advance(&mut term, *byte, &mut reply);
+        }
+        assert!(term.sync_deadline().is_some());
+
+        // Held back bytes are applied once the update times out, which also ends it.
+        parser.stop_sync(&mut term, &mut reply);
+        assert_eq!(term.sync_deadline(), None);
+        assert_eq!(term.grid[Line(0)][Column(2)].c, 'c');
+
+        for byte in b"\x1b[?2026$p" {
+            parser.advance(&mut term, *byte, &mut reply);
+        }
+        assert_eq!(reply, b"\x1b[?2026;2$y".to_vec());
+    }
+
+    #[test]
+    fn dim_foreground() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let config = MockConfig::default();
+        let mut term = Term::new(&config, size, Mock);
+        let orange = Rgb { r: 0xff, g: 0x80, b: 0 };
+
+        term.terminal_attribute(Attr::Dim);
+        term.terminal_attribute(Attr::Foreground(Color::Named(NamedColor::Red)));
+        term.input('a');
+        term.terminal_attribute(Attr::Foreground(Color::Indexed(208)));
+        term.input('b');
+        term.terminal_attribute(Attr::Foreground(Color::Spec(orange)));
+        term.input('c');
+
+        let cells: Vec<_> =
+            term.renderable_cells(&config).filter(|cell| cell.line == Line(0)).collect();
+
+        // Named colors use the dim colors of the palette.
+        assert_eq!(cells[0].fg, term.colors[NamedColor::DimRed]);
+        assert_eq!(cells[0].fg_index, Some(NamedColor::DimRed as usize));
+
+        // Other colors are dimmed directly.
+        let dim_luminance = config.dim_text_luminance();
+        assert_eq!(cells[1].fg, term.colors[208usize].dim(dim_luminance));
+        assert_eq!(cells[1].fg_index, None);
+        assert_eq!(cells[2].fg, orange.dim(dim_luminance));
+        assert_eq!(cells[2].fg_index, None);
+    }
+
+    #[test]
+    fn selection_highlight() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let config = MockConfig::default();
+        let mut term = Term::new(&config, size, Mock);
+        term.input('a');
+        term.input('b');
+
+        let mut selection =
+            Selection::new(SelectionType::Simple, Point::new(16, Column(0)), Side::Left);
+        selection.update(Point::new(16, Column(1)), Side::Right);
+        term.selection = Some(selection);
+
+        // Colors of selected cells are left to the renderer.
+        let mut cells = term.renderable_cells(&config);
+        let cell = cells.find(|cell| cell.line == Line(0) && cell.column == Column(0)).unwrap();
+        assert_eq!(cell.highlight, CellHighlight::Selection);
+        assert_eq!(cell.fg, term.colors[NamedColor::Foreground]);
+        assert_eq!(cell.bg, term.colors[NamedColor::Background]);
+
+        // Default selection colors invert the cell.
+        let mut resolved = cell;
+        resolved.resolve_highlight(cell.highlight.colors(&config), &term.colors);
+        assert_eq!(resolved.highlight, CellHighlight::None);
+        assert_eq!((resolved.fg, resolved.bg), (cell.bg, cell.fg));
+        assert!(resolved.bg_alpha > 0.);
+    }
+
+    #[test]
+    fn cursor_blinking() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut config = MockConfig::default();
+        let mut term = Term::new(&config, size, Mock);
+        assert!(!term.cursor_blinking());
+
+        term.set_mode(ansi::Mode::BlinkingCursor);
+        assert!(term.cursor_blinking());
+
+        // Blinking is disabled in vi mode.
+        term.toggle_vi_mode();
+        assert!(!term.cursor_blinking());
+        term.toggle_vi_mode();
+
+        // The config can override the application.
+        config.cursor.blinking = CursorBlinking::Never;
+        term.update_config(&config);
+        assert!(!term.cursor_blinking());
+
+        config.cursor.blinking = CursorBlinking::On;
+        term.update_config(&config);
+        term.set_cursor_blinking(None);
+        assert!(term.cursor_blinking());
+    }
+
+    #[test]
+    fn terminal_cursor_in_vi_mode() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let config = MockConfig::default();
+        let mut term = Term::new(&config, size, Mock);
+        term.grid.cursor.point = Point::new(Line(2), Column(4));
+        assert_eq!(term.renderable_terminal_cursor(&config), None);
+
+        // Both cursors are drawn once the vi mode cursor moved away.
+        term.toggle_vi_mode();
+        assert_eq!(term.renderable_terminal_cursor(&config), None);
+        term.vi_mode_cursor.point = Point::new(Line(0), Column(0));
+
+        let cursor = term.renderable_terminal_cursor(&config).unwrap();
+        assert_eq!((cursor.line, cursor.column), (Line(2), Column(4)));
+        match cursor.inner {
+            RenderableCellContent::Cursor(key) => assert_eq!(key.style, CursorStyle::HollowBlock),
+            RenderableCellContent::Chars(_) => panic!("expected cursor"),
+        }
+    }
+
     #[test]
     fn semantic_selection_works() {
         let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
@@ -2545,6 +3805,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn path_selection_works() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        let mut grid: Grid<Cell> = Grid::new(Line(1), Column(12), 0, Cell::default());
+        for (i, c) in "(src/a.rs:1)".chars().enumerate() {
+            grid[Line(0)][Column(i)].c = c;
+        }
+
+        mem::swap(&mut term.grid, &mut grid);
+
+        term.selection = Some(Selection::new(
+            SelectionType::Path,
+            Point { line: 0, col: Column(3) },
+            Side::Left,
+        ));
+        assert_eq!(term.selection_to_string(), Some(String::from("src/a.rs:1")));
+
+        term.selection = Some(Selection::new(
+            SelectionType::Semantic,
+            Point { line: 0, col: Column(3) },
+            Side::Left,
+        ));
+        assert_eq!(term.selection_to_string(), Some(String::from("src/a.rs")));
+    }
+
     #[test]
     fn line_selection_works() {
         let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
@@ -2566,6 +3852,36 @@ mod tests {
         assert_eq!(term.selection_to_string(), Some(String::from("\"aa\"a\n")));
     }
 
+    #[test]
+    fn line_selection_follows_wrapped_lines() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        let mut grid: Grid<Cell> = Grid::new(Line(3), Column(3), 0, Cell::default());
+        for (i, c) in "abc".chars().enumerate() {
+            grid[Line(0)][Column(i)].c = c;
+            grid[Line(2)][Column(i)].c = c;
+        }
+        grid[Line(1)][Column(0)].c = 'd';
+        grid[Line(1)][Column(1)].c = 'e';
+        grid[Line(0)][Column(2)].flags.insert(Flags::WRAPLINE);
+
+        mem::swap(&mut term.grid, &mut grid);
+
+        term.selection = Some(Selection::new(
+            SelectionType::Lines,
+            Point { line: 1, col: Column(0) },
+            Side::Left,
+        ));
+        assert_eq!(term.selection_to_string(), Some(String::from("abcde\n")));
+
+        term.selection = Some(Selection::new(
+            SelectionType::Lines,
+            Point { line: 0, col: Column(1) },
+            Side::Left,
+        ));
+        assert_eq!(term.selection_to_string(), Some(String::from("abc\n")));
+    }
+
     #[test]
     fn selecting_empty_line() {
         let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
@@ -2728,6 +4044,51 @@ mod tests {
         assert_eq!(term.grid.cursor.point, Point::new(Line(4), Column(0)));
     }
 
+    #[test]
+    fn scaled_size_keeps_grid_dimensions() {
+        let mut size = SizeInfo::new(100.0, 51.0, 7.0, 13.0, 5.0, 5.0, false);
+        size.reserve_lines(1);
+
+        let scaled = size.scaled(2.);
+        assert_eq!(scaled.width(), 200.);
+        assert_eq!(scaled.cell_width(), 14.);
+        assert_eq!(scaled.padding_y(), 10.);
+        assert_eq!(scaled.cols(), size.cols());
+        assert_eq!(scaled.screen_lines(), size.screen_lines());
+        assert_eq!(scaled.visible_lines(), size.visible_lines());
+    }
+
+    #[test]
+    fn gutter_offsets_grid() {
+        let mut size = SizeInfo::new(100.0, 51.0, 7.0, 13.0, 5.0, 5.0, false);
+        let cols = size.cols();
+        size.reserve_gutter(2);
+
+        assert_eq!(size.cols(), cols - 2);
+        assert_eq!(size.padding_left(), 19.);
+        assert!(!size.contains_point(15, 10));
+        assert_eq!(size.pixels_to_coords(15, 10), Point::new(Line(0), Column(0)));
+        assert_eq!(size.pixels_to_coords(27, 10), Point::new(Line(0), Column(1)));
+
+        // The grid keeps at least the minimum number of columns.
+        let mut size = SizeInfo::new(100.0, 51.0, 7.0, 13.0, 5.0, 5.0, false);
+        size.reserve_gutter(100);
+        assert_eq!(size.cols(), Column(MIN_COLS));
+    }
+
+    #[test]
+    fn fractional_cells_map_to_pixels() {
+        let size = SizeInfo::new(100.0, 52.0, 7.5, 12.5, 5.0, 1.0, false);
+
+        assert_eq!(size.pixels_to_grid_pixels(4, 0), (0, 0));
+        assert_eq!(size.pixels_to_grid_pixels(20, 26), (15, 25));
+        assert_eq!(size.pixels_to_grid_pixels(99, 51), (89, 49));
+
+        // Integer division by the truncated cell size would place these in the next cell.
+        assert_eq!(size.pixels_to_coords(19, 13), Point::new(Line(0), Column(1)));
+        assert_eq!(size.pixels_to_coords(34, 38), Point::new(Line(2), Column(3)));
+    }
+
     #[test]
     fn window_title() {
         let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
@@ -2776,6 +4137,105 @@ mod tests {
         assert_eq!(term.title, None);
     }
 
+    #[test]
+    fn hyperlinks() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+
+        let link = Hyperlink { id: Some("a".into()), uri: "https://example.org".into() };
+        term.set_hyperlink(Some(link.clone()));
+        term.input('a');
+        term.set_hyperlink(None);
+        term.input('b');
+        term.set_hyperlink(Some(link.clone()));
+        term.input('c');
+
+        let line = &term.grid()[Line(0)];
+        assert_eq!(line[Column(0)].hyperlink, 1);
+        assert_eq!(line[Column(1)].hyperlink, 0);
+        assert_eq!(line[Column(2)].hyperlink, 1);
+        assert_eq!(term.hyperlink(1), Some(&link));
+        assert_eq!(term.hyperlink(0), None);
+
+        // Links without an ID are kept apart.
+        let link = Hyperlink { id: None, uri: "https://example.org".into() };
+        term.set_hyperlink(Some(link.clone()));
+        term.set_hyperlink(Some(link));
+        assert_eq!(term.grid.cursor.template.hyperlink, 3);
+
+        term.reset_state();
+        assert_eq!(term.hyperlink(1), None);
+    }
+
+    #[test]
+    fn hyperlinks_are_reused() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+
+        let link = Hyperlink { id: None, uri: "https://example.org".into() };
+        term.set_hyperlink(Some(link.clone()));
+        term.input('a');
+
+        // Links which are not displayed anymore are freed once the table is full.
+        for _ in 1..MAX_HYPERLINKS {
+            term.set_hyperlink(Some(link.clone()));
+        }
+        assert_eq!(term.grid.cursor.template.hyperlink, MAX_HYPERLINKS as u16);
+
+        term.set_hyperlink(Some(link.clone()));
+        assert_eq!(term.grid.cursor.template.hyperlink, 2);
+        assert_eq!(term.hyperlink(1), Some(&link));
+        assert_eq!(term.hyperlink(3), None);
+    }
+
+    #[test]
+    fn grapheme_clusters() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        let family = "👩\u{200d}👩\u{200d}👧\u{200d}👦\u{1f3fb}";
+
+        // Without the mode, every emoji occupies its own cells.
+        family.chars().take(3).for_each(|c| term.input(c));
+        assert_eq!(term.grid.cursor.point.col, Column(4));
+
+        term.reset_state();
+        term.set_mode(ansi::Mode::GraphemeClustering);
+        family.chars().for_each(|c| term.input(c));
+        term.input('x');
+        assert_eq!(term.grid.cursor.point.col, Column(3));
+
+        // Characters which don't fit into the cell are kept by the terminal.
+        let cell = term.grid[Line(0)][Column(0)];
+        assert!(cell.extra_full());
+        assert_eq!(term.cluster(cell.cluster), &['\u{200d}', '\u{1f3fb}']);
+        assert!(!cell.flags.contains(Flags::ZEROWIDTH_TRUNCATED));
+
+        let text = term.bounds_to_string(Point::new(16, Column(0)), Point::new(16, Column(6)));
+        assert_eq!(text, format!("{}x\n", family));
+    }
+
+    #[test]
+    fn grapheme_clusters_are_reused() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, Mock);
+        term.set_mode(ansi::Mode::GraphemeClustering);
+        let family = "👩\u{200d}👩\u{200d}👧\u{200d}👦\u{1f3fb}";
+
+        // Clusters of cells which are not displayed anymore are freed once the table is full.
+        for _ in 0..=MAX_CLUSTERS {
+            family.chars().for_each(|c| term.input(c));
+        }
+        term.carriage_return();
+        term.linefeed();
+        family.chars().for_each(|c| term.input(c));
+
+        assert!(term.clusters.len() <= MAX_CLUSTERS);
+        let cursor = term.grid.cursor.point;
+        let cell = term.grid[cursor.line][Column(0)];
+        assert_eq!(term.cluster(cell.cluster), &['\u{200d}', '\u{1f3fb}']);
+        assert!(!cell.flags.contains(Flags::ZEROWIDTH_TRUNCATED));
+    }
+
     #[test]
     fn parse_cargo_version() {
         assert!(version_number(env!("CARGO_PKG_VERSION")) >= 10_01);
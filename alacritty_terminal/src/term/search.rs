@@ -1,4 +1,5 @@
 use std::cmp::min;
+use std::iter;
 use std::mem;
 use std::ops::RangeInclusive;
 
@@ -80,6 +81,31 @@ impl<T> Term<T> {
         }
     }
 
+    /// Position of a match among all matches in the buffer, counting from the top.
+    ///
+    /// Returns the one-based index of the match with the same start as `regex_match` and the total
+    /// number of matches, or `None` if there is no such match.
+    pub fn search_match_position(&self, regex_match: &Match) -> Option<(usize, usize)> {
+        let start = Point::new(self.total_lines() - 1, Column(0));
+        let end = Point::new(0, self.cols() - 1);
+        let mut regex_iter = RegexIter::new(start, end, Direction::Right, &self);
+
+        // Matches at the end of the buffer wrap the iterator around to the first one again.
+        let first_match = regex_iter.next()?;
+        let matches = regex_iter.take_while(|next_match| *next_match != first_match);
+
+        let mut position = None;
+        let mut count = 0;
+        for next_match in iter::once(first_match.clone()).chain(matches) {
+            count += 1;
+            if next_match.start() == regex_match.start() {
+                position = Some(count);
+            }
+        }
+
+        position.map(|position| (position, count))
+    }
+
     /// Find the next match to the right of the origin.
     fn next_match_right(
         &self,
@@ -358,7 +384,27 @@ impl<T> Term<T> {
     }
 
     /// Find left end of semantic block.
-    pub fn semantic_search_left(&self, mut point: Point<usize>) -> Point<usize> {
+    pub fn semantic_search_left(&self, point: Point<usize>) -> Point<usize> {
+        self.escape_search_left(point, &self.semantic_escape_chars)
+    }
+
+    /// Find right end of semantic block.
+    pub fn semantic_search_right(&self, point: Point<usize>) -> Point<usize> {
+        self.escape_search_right(point, &self.semantic_escape_chars)
+    }
+
+    /// Find left end of a file path.
+    pub fn path_search_left(&self, point: Point<usize>) -> Point<usize> {
+        self.escape_search_left(point, &self.path_escape_chars)
+    }
+
+    /// Find right end of a file path.
+    pub fn path_search_right(&self, point: Point<usize>) -> Point<usize> {
+        self.escape_search_right(point, &self.path_escape_chars)
+    }
+
+    /// Find the left end of a block delimited by `escape_chars`.
+    fn escape_search_left(&self, mut point: Point<usize>, escape_chars: &str) -> Point<usize> {
         // Limit the starting point to the last line in the history
         point.line = min(point.line, self.total_lines() - 1);
 
@@ -367,7 +413,7 @@ impl<T> Term<T> {
 
         let wide = Flags::WIDE_CHAR | Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER;
         while let Some(cell) = iter.prev() {
-            if !cell.flags.intersects(wide) && self.semantic_escape_chars.contains(cell.c) {
+            if !cell.flags.intersects(wide) && escape_chars.contains(cell.c) {
                 break;
             }
 
@@ -381,8 +427,8 @@ impl<T> Term<T> {
         point
     }
 
-    /// Find right end of semantic block.
-    pub fn semantic_search_right(&self, mut point: Point<usize>) -> Point<usize> {
+    /// Find the right end of a block delimited by `escape_chars`.
+    fn escape_search_right(&self, mut point: Point<usize>, escape_chars: &str) -> Point<usize> {
         // Limit the starting point to the last line in the history
         point.line = min(point.line, self.total_lines() - 1);
 
@@ -391,7 +437,7 @@ impl<T> Term<T> {
 
         let wide = Flags::WIDE_CHAR | Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER;
         while let Some(cell) = iter.next() {
-            if !cell.flags.intersects(wide) && self.semantic_escape_chars.contains(cell.c) {
+            if !cell.flags.intersects(wide) && escape_chars.contains(cell.c) {
                 break;
             }
 
@@ -536,6 +582,27 @@ mod tests {
         assert_eq!(term.regex_search_left(start, end), Some(match_start..=match_end));
     }
 
+    #[test]
+    fn match_position() {
+        #[rustfmt::skip]
+        let mut term = mock_term("\
+            abc\r\n\
+            xbx\r\n\
+            bbb\
+        ");
+
+        term.regex_search = Some(RegexSearch::new("b+").unwrap());
+        let second = Point::new(1, Column(1))..=Point::new(1, Column(1));
+        assert_eq!(term.search_match_position(&second), Some((2, 3)));
+
+        // Match at the end of the buffer.
+        let last = Point::new(0, Column(0))..=Point::new(0, Column(2));
+        assert_eq!(term.search_match_position(&last), Some((3, 3)));
+
+        let missing = Point::new(2, Column(0))..=Point::new(2, Column(0));
+        assert_eq!(term.search_match_position(&missing), None);
+    }
+
     #[test]
     fn nested_regex() {
         #[rustfmt::skip]
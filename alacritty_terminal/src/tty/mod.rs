@@ -5,6 +5,7 @@ use std::{env, io};
 use terminfo::Database;
 
 use crate::config::Config;
+use crate::term::SizeInfo;
 
 #[cfg(not(windows))]
 mod unix;
@@ -60,6 +61,19 @@ pub trait EventedPty: EventedReadWrite {
     fn next_child_event(&mut self) -> Option<ChildEvent>;
 }
 
+/// Columns and lines of the terminal grid, if both fit into a PTY limited to `max` cells.
+///
+/// This is shared by all PTY backends, so the grid size reported to the shell always matches
+/// the `SizeInfo` used by the renderer.
+pub fn pty_dimensions(size: &SizeInfo, max: usize) -> Option<(usize, usize)> {
+    let (cols, lines) = (size.cols().0, size.screen_lines().0);
+    if cols > 0 && cols <= max && lines > 0 && lines <= max {
+        Some((cols, lines))
+    } else {
+        None
+    }
+}
+
 /// Setup environment variables.
 pub fn setup_env<C>(config: &Config<C>) {
     // Default to 'alacritty' terminfo if it is available, otherwise
@@ -81,3 +95,26 @@ pub fn setup_env<C>(config: &Config<C>) {
         env::set_var(key, value);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pty_dimensions_exclude_reserved_space() {
+        let mut size = SizeInfo::new(100.0, 52.0, 5.0, 10.0, 0.0, 1.0, false);
+        assert_eq!(pty_dimensions(&size, 100), Some((20, 5)));
+
+        // Lines of the message bar and columns of the gutter are not part of the PTY.
+        size.reserve_lines(2);
+        size.reserve_gutter(3);
+        assert_eq!(pty_dimensions(&size, 100), Some((17, 3)));
+    }
+
+    #[test]
+    fn pty_dimensions_overflow() {
+        let size = SizeInfo::new(100.0, 52.0, 5.0, 10.0, 0.0, 1.0, false);
+        assert_eq!(pty_dimensions(&size, 20), Some((20, 5)));
+        assert_eq!(pty_dimensions(&size, 19), None);
+    }
+}
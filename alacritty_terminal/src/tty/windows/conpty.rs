@@ -20,6 +20,7 @@ use winapi::um::wincontypes::{COORD, HPCON};
 use crate::config::Config;
 use crate::event::OnResize;
 use crate::term::SizeInfo;
+use crate::tty::pty_dimensions;
 use crate::tty::windows::child::ChildExitWatcher;
 use crate::tty::windows::{cmdline, win32_string, Pty};
 
@@ -234,12 +235,6 @@ impl OnResize for Conpty {
 
 /// Helper to build a COORD from a SizeInfo, returning None in overflow cases.
 fn coord_from_sizeinfo(size: &SizeInfo) -> Option<COORD> {
-    let cols = size.cols().0;
-    let lines = size.screen_lines().0;
-
-    if cols <= i16::MAX as usize && lines <= i16::MAX as usize {
-        Some(COORD { X: cols as i16, Y: lines as i16 })
-    } else {
-        None
-    }
+    let (cols, lines) = pty_dimensions(size, i16::MAX as usize)?;
+    Some(COORD { X: cols as i16, Y: lines as i16 })
 }
@@ -11,6 +11,7 @@ use winpty::{Config as WinptyConfig, ConfigFlags, MouseMode, SpawnConfig, SpawnF
 use crate::config::Config;
 use crate::event::OnResize;
 use crate::term::SizeInfo;
+use crate::tty::pty_dimensions;
 use crate::tty::windows::child::ChildExitWatcher;
 use crate::tty::windows::{cmdline, Pty};
 
@@ -61,8 +62,7 @@ pub fn new<C>(config: &Config<C>, size: &SizeInfo, _window_id: Option<usize>) ->
 
 impl OnResize for Agent {
     fn on_resize(&mut self, size: &SizeInfo) {
-        let (cols, lines) = (size.cols().0, size.screen_lines().0);
-        if cols > 0 && cols <= u16::MAX as usize && lines > 0 && lines <= u16::MAX as usize {
+        if let Some((cols, lines)) = pty_dimensions(size, u16::MAX as usize) {
             self.set_size(cols as u16, lines as u16)
                 .unwrap_or_else(|_| info!("Unable to set WinPTY size, did it die?"));
         }
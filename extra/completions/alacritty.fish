@@ -18,6 +18,9 @@ complete -c alacritty \
 complete -c alacritty \
   -l "persistent-logging" \
   -d "Keep the log file after quitting Alacritty"
+complete -c alacritty \
+  -l "diagnostic-bundle" \
+  -d "Write a diagnostic bundle for bug reports after the first frame"
 complete -c alacritty \
   -f \
   -l "config-file" \
@@ -88,3 +91,9 @@ complete \
   -s "e" \
   -l "command" \
   -d "Execute command (must be last arg)"
+
+complete \
+  -c alacritty \
+  -n "__fish_use_subcommand" \
+  -a "msg" \
+  -d "Send a message to a running Alacritty instance"